@@ -0,0 +1,184 @@
+//! Headless editor for scripted batch edits
+//!
+//! Runs an editor script (see `editor_native::editor::script`) on each
+//! file with the same `Editor` the app uses, then prints a unified diff,
+//! writes the files back, or only reports whether anything would change
+//! (for pre-commit hooks). `.editorconfig` files apply as in the app.
+//!
+//! Exit status: 0 on success, 1 if `--check` found changes, 2 on errors.
+
+use std::path::{Path, PathBuf};
+use std::process::ExitCode;
+
+use anyhow::{bail, Context, Result};
+
+use editor_native::editor::{editorconfig, unified_diff, LanguageId, LargeFileConfig, Script};
+use editor_native::workspace::write_atomic;
+use editor_native::Editor;
+
+const USAGE: &str = "\
+Usage: editor-cli [OPTIONS] FILE...
+
+Runs an editor script on each file and prints a diff of the changes.
+
+Options:
+  -s, --script FILE    Script file to run (repeatable, run in order)
+  -e, --exec COMMAND   Script line to run (repeatable, after --script)
+  -l, --language LANG  Language of the files (default: from the extension)
+  -w, --write          Write the changes back instead of printing a diff
+      --check          Only list files that would change (exit with 1 if any)
+  -h, --help           Print this help";
+
+/// Lines of context in printed diffs
+const DIFF_CONTEXT: usize = 3;
+
+/// What to do with the edited files.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Mode {
+    Diff,
+    Write,
+    Check,
+}
+
+/// Parsed command line.
+#[derive(Debug)]
+struct Options {
+    script: Script,
+    language: Option<LanguageId>,
+    mode: Mode,
+    files: Vec<PathBuf>,
+}
+
+/// Helper: Parses the command line
+///
+/// Returns: None if help was requested
+fn parse_args(mut args: impl Iterator<Item = String>) -> Result<Option<Options>> {
+    let mut script_files = Vec::new();
+    let mut exec_lines = Vec::new();
+    let mut language = None;
+    let mut mode = Mode::Diff;
+    let mut files = Vec::new();
+
+    while let Some(arg) = args.next() {
+        let mut value = |name: &str| args.next().with_context(|| format!("{} needs a value", name));
+        match arg.as_str() {
+            "-h" | "--help" => return Ok(None),
+            "-s" | "--script" => script_files.push(PathBuf::from(value(&arg)?)),
+            "-e" | "--exec" => exec_lines.push(value(&arg)?),
+            "-l" | "--language" => language = Some(LanguageId::parse(&value(&arg)?)),
+            "-w" | "--write" => mode = Mode::Write,
+            "--check" => mode = Mode::Check,
+            "--" => files.extend(args.by_ref().map(PathBuf::from)),
+            option if option.starts_with('-') && option != "-" => bail!("unknown option: {}", option),
+            _ => files.push(PathBuf::from(arg)),
+        }
+    }
+
+    let mut script = Script::default();
+    for path in &script_files {
+        let source = std::fs::read_to_string(path).with_context(|| format!("failed to read {}", path.display()))?;
+        script.extend(Script::parse(&source).with_context(|| path.display().to_string())?);
+    }
+    script.extend(Script::parse(&exec_lines.join("\n")).context("--exec")?);
+
+    if script.is_empty() {
+        bail!("no script given (use --script or --exec)");
+    }
+    if files.is_empty() {
+        bail!("no files given");
+    }
+    Ok(Some(Options { script, language, mode, files }))
+}
+
+/// Helper: Runs the script on one file
+///
+/// Returns: true if the file changed
+fn process_file(path: &Path, options: &Options) -> Result<bool> {
+    let original = std::fs::read(path).with_context(|| format!("failed to read {}", path.display()))?;
+    let props = editorconfig::resolve_for_path(path)?;
+    let mut editor = Editor::open_file_with_charset(path, LargeFileConfig::default(), props.charset)?;
+    editor.apply_editorconfig(&props);
+    if let Some(language) = &options.language {
+        editor.set_language(language.clone())?;
+    }
+
+    let matches = editor.run_script(&options.script)?;
+    if options.mode != Mode::Check {
+        for m in matches {
+            let text = m.text.lines().next().unwrap_or_default();
+            println!(
+                "{}:{}:{}: {}",
+                path.display(),
+                m.range.start.line + 1,
+                m.range.start.character + 1,
+                text
+            );
+        }
+    }
+
+    // Unchanged buffers are not saved (save actions would still apply)
+    if !editor.is_dirty() {
+        return Ok(false);
+    }
+    let saved = editor.prepare_save()?;
+    if saved == original {
+        return Ok(false);
+    }
+
+    match options.mode {
+        Mode::Diff => {
+            // git-style labels, so the diff applies with `patch -p1`
+            let name = path.display().to_string();
+            let (old_name, new_name) = if path.is_absolute() {
+                (name.clone(), name)
+            } else {
+                (format!("a/{}", name), format!("b/{}", name))
+            };
+            let old = String::from_utf8_lossy(&original);
+            let new = String::from_utf8_lossy(&saved);
+            print!("{}", unified_diff(&old, &new, &old_name, &new_name, DIFF_CONTEXT));
+        }
+        Mode::Write => write_atomic(path, &saved)?,
+        Mode::Check => {}
+    }
+    Ok(true)
+}
+
+fn main() -> ExitCode {
+    let options = match parse_args(std::env::args().skip(1)) {
+        Ok(Some(options)) => options,
+        Ok(None) => {
+            println!("{}", USAGE);
+            return ExitCode::SUCCESS;
+        }
+        Err(e) => {
+            eprintln!("editor-cli: {:#}\n\n{}", e, USAGE);
+            return ExitCode::from(2);
+        }
+    };
+
+    let mut changed = false;
+    let mut failed = false;
+    for path in &options.files {
+        match process_file(path, &options) {
+            Ok(file_changed) => {
+                if file_changed && options.mode == Mode::Check {
+                    eprintln!("{} would change", path.display());
+                }
+                changed |= file_changed;
+            }
+            Err(e) => {
+                eprintln!("editor-cli: {}: {:#}", path.display(), e);
+                failed = true;
+            }
+        }
+    }
+
+    if failed {
+        ExitCode::from(2)
+    } else if changed && options.mode == Mode::Check {
+        ExitCode::from(1)
+    } else {
+        ExitCode::SUCCESS
+    }
+}
@@ -9,7 +9,7 @@ use crate::editor::cursor::Position;
 /// - Language-specific rules
 ///
 /// Example:
-/// ```
+/// ```text
 /// fn main() {
 ///     println!("hello");  // Auto-indented to 4 spaces
 /// }  // Auto-dedented
@@ -251,8 +251,8 @@ pub fn normalize_indentation(rope: &mut Rope, config: &IndentConfig) {
         let line = rope.line(line_idx);
         let line_text = line.to_string();
 
-        // Get current indent
-        let current_indent = get_line_indent(&line_text);
+        // Get current indent (spaces and tabs only, blank lines keep their break)
+        let current_indent: String = line_text.chars().take_while(|c| *c == ' ' || *c == '\t').collect();
         if current_indent.is_empty() {
             continue;
         }
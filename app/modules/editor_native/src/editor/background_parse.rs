@@ -0,0 +1,445 @@
+//! Background parsing with cancellation and time budgets
+//!
+//! Small documents parse inline after every edit (incremental parses take
+//! microseconds). Bigger ones get an inline time budget; a parse that runs
+//! over it moves to a worker thread owned by the editor, and readers keep
+//! the last good tree (edited to follow the text) until the new one is
+//! installed by `poll_parse`. A newer edit flips the cancellation flag of
+//! the parse in flight, so the worker never finishes stale work.
+
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::mpsc::{self, Receiver, RecvTimeoutError, Sender};
+use std::sync::Arc;
+use std::thread::JoinHandle;
+use std::time::{Duration, Instant};
+
+use ropey::Rope;
+use tree_sitter::{Language, Parser, Point, Tree};
+
+use crate::editor::telemetry::{self, Operation};
+use crate::editor::{Editor, FileSizeClass, LanguageId, PerformanceMetrics};
+
+/// Parsing budgets.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ParseConfig {
+    /// Documents up to this size always parse inline, without a budget
+    pub inline_bytes: usize,
+
+    /// Time an inline parse of a bigger document may take before it is
+    /// handed to the worker (zero = always in the background)
+    pub inline_budget: Duration,
+
+    /// Longest a background parse may run; the last good tree is kept
+    /// if it gives up
+    pub background_timeout: Duration,
+
+    /// Use the worker at all (false = every parse is inline and unbounded)
+    pub background: bool,
+}
+
+impl Default for ParseConfig {
+    fn default() -> Self {
+        Self {
+            inline_bytes: 256 * 1024,
+            inline_budget: Duration::from_millis(4),
+            background_timeout: Duration::from_secs(5),
+            background: true,
+        }
+    }
+}
+
+/// Parse request sent to the worker.
+struct ParseJob {
+    version: u64,
+    language_id: LanguageId,
+    language: Language,
+    rope: Rope,
+
+    /// Last good tree, edited to match `rope`
+    old_tree: Option<Tree>,
+
+    cancel: Arc<AtomicUsize>,
+    timeout: Duration,
+}
+
+/// Result of a background parse.
+struct ParseOutcome {
+    version: u64,
+    language_id: LanguageId,
+    rope: Rope,
+
+    /// None if the parse was cancelled, timed out or skipped
+    tree: Option<Tree>,
+
+    duration: Duration,
+}
+
+/// Worker thread parsing one editor's documents.
+pub(crate) struct ParseWorker {
+    jobs: Option<Sender<ParseJob>>,
+    results: Receiver<ParseOutcome>,
+    thread: Option<JoinHandle<()>>,
+
+    /// Cancellation flag of the newest job
+    cancel: Arc<AtomicUsize>,
+
+    /// Version and language of the newest job, until its outcome arrives
+    pending: Option<(u64, LanguageId)>,
+}
+
+impl ParseWorker {
+    fn spawn() -> Self {
+        let (job_sender, jobs) = mpsc::channel();
+        let (result_sender, results) = mpsc::channel();
+        let thread = std::thread::Builder::new()
+            .name("editor-parse".to_string())
+            .spawn(move || run_worker(jobs, result_sender))
+            .ok();
+
+        Self {
+            jobs: Some(job_sender),
+            results,
+            thread,
+            cancel: Arc::new(AtomicUsize::new(0)),
+            pending: None,
+        }
+    }
+
+    /// Flips the cancellation flag of the job in flight
+    fn cancel(&mut self) {
+        self.cancel.store(1, Ordering::Relaxed);
+    }
+
+    /// Queues a job (cancelling the previous one)
+    ///
+    /// Returns: false if the worker thread is gone
+    fn submit(&mut self, mut job: ParseJob) -> bool {
+        self.cancel();
+        self.cancel = Arc::new(AtomicUsize::new(0));
+        job.cancel = Arc::clone(&self.cancel);
+
+        let key = (job.version, job.language_id.clone());
+        let sent = self.thread.is_some() && self.jobs.as_ref().is_some_and(|jobs| jobs.send(job).is_ok());
+        self.pending = sent.then_some(key);
+        sent
+    }
+}
+
+impl Drop for ParseWorker {
+    fn drop(&mut self) {
+        self.cancel();
+        self.jobs = None;
+        if let Some(thread) = self.thread.take() {
+            let _ = thread.join();
+        }
+    }
+}
+
+/// Worker loop: parses the newest queued job, skipping older ones
+fn run_worker(jobs: Receiver<ParseJob>, results: Sender<ParseOutcome>) {
+    let mut parser = Parser::new();
+
+    while let Ok(mut job) = jobs.recv() {
+        let mut skipped = Vec::new();
+        while let Ok(newer) = jobs.try_recv() {
+            skipped.push(std::mem::replace(&mut job, newer));
+        }
+        for old in skipped {
+            let outcome = ParseOutcome {
+                version: old.version,
+                language_id: old.language_id,
+                rope: old.rope,
+                tree: None,
+                duration: Duration::ZERO,
+            };
+            if results.send(outcome).is_err() {
+                return;
+            }
+        }
+
+        let start = Instant::now();
+        let tree = parse_job(&mut parser, &job);
+        let outcome = ParseOutcome {
+            version: job.version,
+            language_id: job.language_id,
+            rope: job.rope,
+            tree,
+            duration: start.elapsed(),
+        };
+        if results.send(outcome).is_err() {
+            return;
+        }
+    }
+}
+
+/// Parses a job's rope chunk by chunk, honouring its flag and timeout
+fn parse_job(parser: &mut Parser, job: &ParseJob) -> Option<Tree> {
+    if job.cancel.load(Ordering::Relaxed) != 0 || parser.set_language(job.language).is_err() {
+        return None;
+    }
+
+    // A cancelled or timed-out parse would otherwise resume on the next job
+    parser.reset();
+    parser.set_timeout_micros(job.timeout.as_micros().min(u64::MAX as u128) as u64);
+
+    let rope = &job.rope;
+    let mut input = |byte: usize, _: Point| -> &[u8] {
+        if byte >= rope.len_bytes() {
+            return &[];
+        }
+        let (chunk, chunk_start, _, _) = rope.chunk_at_byte(byte);
+        &chunk.as_bytes()[byte - chunk_start..]
+    };
+
+    // SAFETY: the flag is kept alive by `job` for the whole parse and
+    // removed from the parser before `job` can be dropped
+    unsafe { parser.set_cancellation_flag(Some(&job.cancel)) };
+    let tree = parser.parse_with(&mut input, job.old_tree.as_ref());
+    unsafe { parser.set_cancellation_flag(None) };
+
+    tree
+}
+
+impl Editor {
+    /// Gets the parsing budgets
+    pub fn parse_config(&self) -> &ParseConfig {
+        &self.parse_config
+    }
+
+    /// Sets the parsing budgets (applies from the next edit)
+    pub fn set_parse_config(&mut self, config: ParseConfig) {
+        self.parse_config = config;
+    }
+
+    /// Gets the recorded operation timings (parse durations included)
+    pub fn performance_metrics(&self) -> &PerformanceMetrics {
+        &self.performance
+    }
+
+    /// Checks if a background parse is running for the current text
+    pub fn is_parse_pending(&self) -> bool {
+        self.parse_worker
+            .as_ref()
+            .is_some_and(|worker| worker.pending == Some((self.version, self.language.clone())))
+    }
+
+    /// Installs finished background parses (call periodically, e.g. per frame)
+    ///
+    /// Returns: true if a new tree was installed
+    pub fn poll_parse(&mut self) -> bool {
+        let mut installed = false;
+        while let Some(outcome) = self.parse_worker.as_ref().and_then(|worker| worker.results.try_recv().ok()) {
+            installed |= self.accept_parse(outcome);
+        }
+        installed
+    }
+
+    /// Blocks until the background parse of the current text finishes
+    ///
+    /// Parameters:
+    /// - `timeout`: Longest time to wait
+    ///
+    /// Returns: true if no parse is pending anymore
+    pub fn wait_for_parse(&mut self, timeout: Duration) -> bool {
+        let deadline = Instant::now() + timeout;
+        self.poll_parse();
+
+        while self.is_parse_pending() {
+            let remaining = deadline.saturating_duration_since(Instant::now());
+            let Some(worker) = &self.parse_worker else {
+                break;
+            };
+            match worker.results.recv_timeout(remaining) {
+                Ok(outcome) => {
+                    self.accept_parse(outcome);
+                }
+                Err(RecvTimeoutError::Timeout) => return false,
+                Err(RecvTimeoutError::Disconnected) => break,
+            }
+        }
+        true
+    }
+
+    /// Parses the current text inline or hands it to the worker
+    pub(super) fn parse_syntax(&mut self) {
+        // Any parse in flight is for older text
+        self.poll_parse();
+        if let Some(worker) = &mut self.parse_worker {
+            worker.cancel();
+        }
+
+        let Some(parser) = &mut self.parser else {
+            return;
+        };
+
+        let config = &self.parse_config;
+        let unbounded = !config.background || self.rope.len_bytes() <= config.inline_bytes;
+        if unbounded || !config.inline_budget.is_zero() {
+            let budget = if unbounded { 0 } else { config.inline_budget.as_micros().max(1) as u64 };
+            let content = self.rope.to_string();
+
+            let start = Instant::now();
+            parser.set_timeout_micros(budget);
+            let tree = parser.parse(&content, self.syntax_tree.as_ref());
+            parser.set_timeout_micros(0);
+
+            match tree {
+                Some(tree) => {
+                    self.record_parse(start.elapsed());
+                    self.install_tree(tree, &content);
+                    return;
+                }
+                // Over budget: do not resume this parse later
+                None => parser.reset(),
+            }
+        }
+
+        let Some(language) = self.language.tree_sitter_language() else {
+            return;
+        };
+        let job = ParseJob {
+            version: self.version,
+            language_id: self.language.clone(),
+            language,
+            rope: self.rope.clone(),
+            old_tree: self.syntax_tree.clone(),
+            cancel: Arc::new(AtomicUsize::new(0)),
+            timeout: self.parse_config.background_timeout,
+        };
+        self.parse_worker.get_or_insert_with(ParseWorker::spawn).submit(job);
+    }
+
+    /// Helper: Installs a finished background parse if it is still current
+    fn accept_parse(&mut self, outcome: ParseOutcome) -> bool {
+        let Some(worker) = &mut self.parse_worker else {
+            return false;
+        };
+        if worker.pending.as_ref() == Some(&(outcome.version, outcome.language_id.clone())) {
+            worker.pending = None;
+        }
+
+        let current = outcome.version == self.version
+            && outcome.language_id == self.language
+            && self.size_class == FileSizeClass::Normal;
+        match outcome.tree {
+            Some(tree) if current => {
+                self.record_parse(outcome.duration);
+                let content = outcome.rope.to_string();
+                self.install_tree(tree, &content);
+                true
+            }
+            Some(_) => {
+                self.record_parse(outcome.duration);
+                false
+            }
+            None => {
+                self.performance.record_cancelled_parse();
+                false
+            }
+        }
+    }
+
+    /// Helper: Records a parse duration (per editor and process-wide)
+    fn record_parse(&mut self, duration: Duration) {
+        self.performance.record_parse(duration);
+        telemetry::record(Operation::Parse, duration);
+    }
+
+    /// Helper: Makes a tree for the current text the last good tree
+    fn install_tree(&mut self, tree: Tree, content: &str) {
+        self.injections.update(&tree, &self.language, content);
+        self.completion_index.refresh_syntax(&self.rope, &tree);
+        if let Some(spell) = &mut self.spell {
+            spell.syntax_changed(self.syntax_tree.as_ref(), &tree);
+        }
+        self.syntax_tree = Some(tree);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::editor::Position;
+
+    /// Config that always parses in the background
+    fn background_only() -> ParseConfig {
+        ParseConfig {
+            inline_bytes: 0,
+            inline_budget: Duration::ZERO,
+            ..ParseConfig::default()
+        }
+    }
+
+    fn tree_covers_text(editor: &Editor) -> bool {
+        editor
+            .syntax_tree()
+            .is_some_and(|tree| tree.root_node().end_byte() == editor.rope().len_bytes())
+    }
+
+    #[test]
+    fn test_small_documents_parse_inline() {
+        let mut editor = Editor::with_content("fn main() {}\n", LanguageId::Rust).unwrap();
+        editor.insert_text("// hi\n").unwrap();
+
+        assert!(!editor.is_parse_pending());
+        assert!(tree_covers_text(&editor));
+        assert!(editor.performance_metrics().avg_parse_time().is_some());
+    }
+
+    #[test]
+    fn test_background_parse_keeps_last_good_tree() {
+        let mut editor = Editor::with_content("fn a() {}\n", LanguageId::Rust).unwrap();
+        editor.set_parse_config(background_only());
+
+        editor.move_cursor(Position::new(1, 0));
+        editor.insert_text("fn b() {}\n").unwrap();
+
+        // Readers keep the previous tree until the new one is installed
+        assert!(editor.syntax_tree().is_some());
+        assert!(editor.wait_for_parse(Duration::from_secs(10)));
+        assert!(!editor.is_parse_pending());
+        assert!(tree_covers_text(&editor));
+
+        let root = editor.syntax_tree().unwrap().root_node();
+        assert_eq!(root.named_child_count(), 2);
+        assert!(editor.performance_metrics().avg_parse_time().is_some());
+    }
+
+    #[test]
+    fn test_newer_edits_supersede_background_parses() {
+        let mut editor = Editor::with_content("", LanguageId::Rust).unwrap();
+        editor.set_parse_config(background_only());
+
+        for i in 0..20 {
+            editor.insert_text(&format!("fn f{}() {{}}\n", i)).unwrap();
+        }
+        assert!(editor.wait_for_parse(Duration::from_secs(10)));
+
+        // Only the parse of the final text is installed
+        assert!(tree_covers_text(&editor));
+        assert_eq!(editor.syntax_tree().unwrap().root_node().named_child_count(), 20);
+
+        let metrics = editor.performance_metrics();
+        assert_eq!(metrics.parse_count() + metrics.cancelled_parses(), 21);
+    }
+
+    #[test]
+    fn test_language_change_discards_pending_parse() {
+        let mut editor = Editor::with_content("fn a() {}\n", LanguageId::Rust).unwrap();
+        editor.set_parse_config(background_only());
+        editor.insert_text("x").unwrap();
+
+        editor.set_language(LanguageId::PlainText).unwrap();
+        assert!(!editor.is_parse_pending());
+        assert!(editor.syntax_tree().is_none());
+
+        // The Rust parse finishing later is not installed
+        std::thread::sleep(Duration::from_millis(50));
+        editor.poll_parse();
+        assert!(editor.syntax_tree().is_none());
+
+        editor.set_language(LanguageId::Rust).unwrap();
+        assert!(editor.wait_for_parse(Duration::from_secs(10)));
+        assert!(tree_covers_text(&editor));
+    }
+}
@@ -184,8 +184,7 @@ pub fn paste_text(
         }
         ClipboardMode::Block => {
             // Insert each line at the same column
-            let mut current_line = position.line;
-            for line_content in clipboard.content.lines() {
+            for (current_line, line_content) in (position.line..).zip(clipboard.content.lines()) {
                 if current_line < rope.len_lines() {
                     let line_start = rope.line_to_char(current_line);
                     let line = rope.line(current_line);
@@ -197,7 +196,6 @@ pub fn paste_text(
                     rope.insert(rope.len_chars(), "\n");
                     rope.insert(rope.len_chars(), line_content);
                 }
-                current_line += 1;
             }
 
             Position::new(
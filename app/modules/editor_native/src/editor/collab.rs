@@ -0,0 +1,1451 @@
+//! Collaborative editing (sequence CRDT)
+//!
+//! An RGA-style replicated sequence mirrors the rope one element per
+//! character (the text itself stays in the rope). Every character gets a
+//! unique id (Lamport counter plus replica id), and concurrent inserts at
+//! the same place are ordered by id on every replica. Deleted characters
+//! stay in the sequence as tombstones, which keeps ids usable as anchors.
+//!
+//! Local edits are turned into operations in `apply_raw_edit` (undo and
+//! redo included); remote operations are integrated into the sequence and
+//! applied to the rope as ordinary edits. Operations from each replica are
+//! numbered, so duplicates are dropped and anything arriving before the
+//! operations it depends on waits in a pending queue. Moving the bytes
+//! between replicas is up to the application.
+//!
+//! Integrated operations are logged so replicas that reconnect can catch up
+//! (`collab_ops_since`). The log drops operations every known peer has
+//! acknowledged, and the oldest ones past a size limit; a replica that
+//! needs dropped operations catches up from a `CollabSnapshot` instead.
+//!
+//! All replicas must start from the same content: the initial text gets
+//! ids from the reserved base replica 0.
+
+use std::collections::{BTreeMap, HashMap, VecDeque};
+
+use anyhow::{anyhow, bail, Result};
+use serde::{Deserialize, Serialize};
+
+use crate::editor::{Editor, Position, Selection};
+
+/// Replica identifier (0 is reserved for the initial content)
+pub type ReplicaId = u64;
+
+/// Number of operations integrated from each replica
+pub type VersionVector = BTreeMap<ReplicaId, u64>;
+
+/// Replica that owns the characters of the initial content
+const BASE_REPLICA: ReplicaId = 0;
+
+/// Operations kept in the log when peers do not acknowledge them
+const MAX_LOG_OPS: usize = 10_000;
+
+/// Highest counter accepted from other replicas (the rest of the range is
+/// left for local edits to count on)
+const MAX_COUNTER: u64 = u64::MAX / 2;
+
+/// Elements per chunk of the sequence
+const CHUNK_LEN: usize = 256;
+
+/// Unique id of a character.
+///
+/// Ordered by Lamport counter first, so a character inserted after seeing
+/// another one always sorts above it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+pub struct CharId {
+    pub counter: u64,
+    pub replica: ReplicaId,
+}
+
+/// Characters with consecutive counters from one replica.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CharRange {
+    pub start: CharId,
+    pub len: u64,
+}
+
+impl CharRange {
+    fn ids(&self) -> impl Iterator<Item = CharId> + '_ {
+        (0..self.len).map_while(|i| {
+            Some(CharId {
+                counter: self.start.counter.checked_add(i)?,
+                replica: self.start.replica,
+            })
+        })
+    }
+}
+
+/// Change carried by an operation.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub enum CrdtOpKind {
+    /// Inserts `text` after `origin` (None = document start). Character
+    /// `i` gets the id `(counter + i, replica)`.
+    Insert {
+        origin: Option<CharId>,
+        counter: u64,
+        text: String,
+    },
+
+    /// Deletes characters (already deleted ones are ignored)
+    Delete { targets: Vec<CharRange> },
+}
+
+/// Replicated edit.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CrdtOp {
+    pub replica: ReplicaId,
+
+    /// Position in the replica's operation sequence (starts at 1)
+    pub seq: u64,
+
+    pub kind: CrdtOpKind,
+}
+
+impl CrdtOp {
+    /// Serializes operations for the transport
+    pub fn encode(ops: &[CrdtOp]) -> Vec<u8> {
+        bincode::serialize(ops).expect("operations always serialize")
+    }
+
+    /// Deserializes operations produced by `encode`
+    pub fn decode(bytes: &[u8]) -> Result<Vec<CrdtOp>> {
+        bincode::deserialize(bytes).map_err(|e| anyhow!("invalid collaboration operations: {}", e))
+    }
+
+    /// Helper: Checks that every counter of the operation is at most
+    /// `MAX_COUNTER` (a decoded operation may carry anything)
+    fn counters_in_range(&self) -> bool {
+        let in_range = |start: u64, len: u64| start.checked_add(len).is_some_and(|end| end <= MAX_COUNTER);
+        match &self.kind {
+            CrdtOpKind::Insert { counter, text, .. } => in_range(*counter, text.chars().count() as u64),
+            CrdtOpKind::Delete { targets } => targets.iter().all(|t| in_range(t.start.counter, t.len)),
+        }
+    }
+}
+
+/// Position anchored to a character (survives concurrent edits).
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Anchor {
+    /// Character the position follows (None = document start)
+    pub after: Option<CharId>,
+}
+
+/// Collaboration state of a replica, for replicas too far behind to catch
+/// up with operations.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CollabSnapshot {
+    text: String,
+    clock: u64,
+    versions: VersionVector,
+
+    /// Character ids in sequence order, with their tombstone flag
+    elements: Vec<(CharId, bool)>,
+}
+
+impl CollabSnapshot {
+    /// Serializes the snapshot for the transport
+    pub fn encode(&self) -> Vec<u8> {
+        bincode::serialize(self).expect("snapshots always serialize")
+    }
+
+    /// Deserializes a snapshot produced by `encode`
+    pub fn decode(bytes: &[u8]) -> Result<Self> {
+        bincode::deserialize(bytes).map_err(|e| anyhow!("invalid collaboration snapshot: {}", e))
+    }
+}
+
+/// Cursor and selection of a replica, as anchors.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CursorUpdate {
+    pub replica: ReplicaId,
+
+    /// Newer updates replace older ones
+    pub seq: u64,
+
+    pub cursor: Anchor,
+    pub selection: Option<(Anchor, Anchor)>,
+}
+
+impl CursorUpdate {
+    /// Serializes the update for the transport
+    pub fn encode(&self) -> Vec<u8> {
+        bincode::serialize(self).expect("cursor updates always serialize")
+    }
+
+    /// Deserializes an update produced by `encode`
+    pub fn decode(bytes: &[u8]) -> Result<Self> {
+        bincode::deserialize(bytes).map_err(|e| anyhow!("invalid cursor update: {}", e))
+    }
+}
+
+/// Cursor of another replica, resolved against the current text.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RemoteCursor {
+    pub replica: ReplicaId,
+    pub cursor: Position,
+    pub selection: Option<Selection>,
+}
+
+/// Character of the replicated sequence.
+#[derive(Debug, Clone)]
+struct Element {
+    id: CharId,
+    deleted: bool,
+}
+
+/// Elements of the sequence, in document order.
+#[derive(Debug, Clone)]
+struct Chunk {
+    /// Stable id (index in `Sequence::positions`)
+    id: usize,
+    elements: Vec<Element>,
+    visible: usize,
+}
+
+/// Place in the sequence: chunk position and index in the chunk.
+type Slot = (usize, usize);
+
+/// Replicated sequence, tombstones included.
+///
+/// Elements live in chunks of a few hundred, so only one chunk is ever
+/// scanned: a map finds the chunk of a character id, and a Fenwick tree
+/// over the chunks' visible counts maps visible offsets to chunks (and
+/// back) in O(log n).
+#[derive(Debug, Clone)]
+struct Sequence {
+    chunks: Vec<Chunk>,
+
+    /// Position in `chunks` of each chunk id
+    positions: Vec<usize>,
+
+    /// Chunk id of every character
+    chunk_of: HashMap<CharId, usize>,
+
+    /// Fenwick tree over the visible count of each chunk (1-based)
+    visible_tree: Vec<usize>,
+}
+
+impl Sequence {
+    fn new(elements: Vec<Element>) -> Self {
+        let mut sequence = Self {
+            chunks: vec![Chunk {
+                id: 0,
+                elements: Vec::new(),
+                visible: 0,
+            }],
+            positions: vec![0],
+            chunk_of: HashMap::new(),
+            visible_tree: vec![0, 0],
+        };
+        sequence.insert((0, 0), elements);
+        sequence
+    }
+
+    fn len(&self) -> usize {
+        self.chunk_of.len()
+    }
+
+    fn visible_len(&self) -> usize {
+        self.visible_before_chunk(self.chunks.len())
+    }
+
+    fn contains(&self, id: CharId) -> bool {
+        self.chunk_of.contains_key(&id)
+    }
+
+    fn get(&self, (chunk, index): Slot) -> Option<&Element> {
+        self.chunks[chunk].elements.get(index)
+    }
+
+    fn iter(&self) -> impl Iterator<Item = &Element> {
+        self.chunks.iter().flat_map(|chunk| chunk.elements.iter())
+    }
+
+    /// Slot of a character
+    fn locate(&self, id: CharId) -> Option<Slot> {
+        let chunk = self.positions[*self.chunk_of.get(&id)?];
+        let index = self.chunks[chunk].elements.iter().position(|e| e.id == id)?;
+        Some((chunk, index))
+    }
+
+    /// Slot after `slot`, moving past the end of its chunk
+    fn next(&self, (chunk, index): Slot) -> Slot {
+        self.normalize((chunk, index + 1))
+    }
+
+    /// Helper: Moves a slot at the end of a chunk to the next element
+    fn normalize(&self, (mut chunk, mut index): Slot) -> Slot {
+        while index >= self.chunks[chunk].elements.len() && chunk + 1 < self.chunks.len() {
+            chunk += 1;
+            index = 0;
+        }
+        (chunk, index)
+    }
+
+    /// Number of visible characters before a slot
+    fn visible_before(&self, (chunk, index): Slot) -> usize {
+        let in_chunk = self.chunks[chunk].elements[..index].iter().filter(|e| !e.deleted).count();
+        self.visible_before_chunk(chunk) + in_chunk
+    }
+
+    /// Slot of the visible character at `offset`
+    fn nth_visible(&self, offset: usize) -> Option<Slot> {
+        // Fenwick descent to the chunk holding the offset
+        let count = self.chunks.len();
+        let mut chunk = 0;
+        let mut remaining = offset;
+        let mut step = count.next_power_of_two();
+        while step > 0 {
+            if chunk + step <= count && self.visible_tree[chunk + step] <= remaining {
+                chunk += step;
+                remaining -= self.visible_tree[chunk];
+            }
+            step >>= 1;
+        }
+
+        let elements = &self.chunks.get(chunk)?.elements;
+        let index = elements
+            .iter()
+            .enumerate()
+            .filter(|(_, e)| !e.deleted)
+            .nth(remaining)
+            .map(|(i, _)| i)?;
+        Some((chunk, index))
+    }
+
+    /// Tombstones the character at a slot
+    ///
+    /// Returns: false if it was already deleted
+    fn delete(&mut self, (chunk, index): Slot) -> bool {
+        let element = &mut self.chunks[chunk].elements[index];
+        if element.deleted {
+            return false;
+        }
+        element.deleted = true;
+        self.chunks[chunk].visible -= 1;
+        self.add_visible(chunk, -1);
+        true
+    }
+
+    /// Inserts elements at a slot
+    fn insert(&mut self, (chunk, index): Slot, elements: Vec<Element>) {
+        let chunk_id = self.chunks[chunk].id;
+        for element in &elements {
+            self.chunk_of.insert(element.id, chunk_id);
+        }
+        let visible = elements.iter().filter(|e| !e.deleted).count();
+        let target = &mut self.chunks[chunk];
+        target.visible += visible;
+        target.elements.splice(index..index, elements);
+
+        if target.elements.len() > 2 * CHUNK_LEN {
+            self.split(chunk);
+        } else {
+            self.add_visible(chunk, visible as isize);
+        }
+    }
+
+    /// Helper: Splits an oversized chunk into chunks of `CHUNK_LEN`
+    fn split(&mut self, chunk: usize) {
+        let head = &mut self.chunks[chunk];
+        let tail: Vec<Element> = head.elements.drain(CHUNK_LEN..).collect();
+        head.elements.shrink_to(2 * CHUNK_LEN);
+        head.visible = head.elements.iter().filter(|e| !e.deleted).count();
+
+        let mut pieces = Vec::new();
+        for elements in tail.chunks(CHUNK_LEN) {
+            let id = self.positions.len() + pieces.len();
+            for element in elements {
+                self.chunk_of.insert(element.id, id);
+            }
+            pieces.push(Chunk {
+                id,
+                visible: elements.iter().filter(|e| !e.deleted).count(),
+                elements: elements.to_vec(),
+            });
+        }
+
+        self.positions.resize(self.positions.len() + pieces.len(), 0);
+        self.chunks.splice(chunk + 1..chunk + 1, pieces);
+        for (position, chunk) in self.chunks.iter().enumerate().skip(chunk + 1) {
+            self.positions[chunk.id] = position;
+        }
+        self.rebuild_tree();
+    }
+
+    /// Helper: Number of visible characters in the chunks before `chunk`
+    fn visible_before_chunk(&self, chunk: usize) -> usize {
+        let mut sum = 0;
+        let mut i = chunk;
+        while i > 0 {
+            sum += self.visible_tree[i];
+            i &= i - 1;
+        }
+        sum
+    }
+
+    /// Helper: Adds to the visible count of a chunk in the Fenwick tree
+    fn add_visible(&mut self, chunk: usize, delta: isize) {
+        let mut i = chunk + 1;
+        while i < self.visible_tree.len() {
+            self.visible_tree[i] = self.visible_tree[i].saturating_add_signed(delta);
+            i += i & i.wrapping_neg();
+        }
+    }
+
+    /// Helper: Rebuilds the Fenwick tree from the chunk counts
+    fn rebuild_tree(&mut self) {
+        let count = self.chunks.len();
+        self.visible_tree = std::iter::once(0).chain(self.chunks.iter().map(|c| c.visible)).collect();
+        for i in 1..=count {
+            let parent = i + (i & i.wrapping_neg());
+            if parent <= count {
+                self.visible_tree[parent] += self.visible_tree[i];
+            }
+        }
+    }
+}
+
+/// Rope change produced by integrating a remote operation (char offsets).
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum TextChange {
+    Insert { at: usize, text: String },
+    Delete { start: usize, end: usize },
+}
+
+/// Collaboration state of an editor.
+#[derive(Debug)]
+pub(crate) struct Collaboration {
+    replica: ReplicaId,
+
+    /// Lamport clock (highest counter seen)
+    clock: u64,
+
+    sequence: Sequence,
+
+    /// Operations integrated per replica (this one included)
+    versions: VersionVector,
+
+    /// Integrated operations not trimmed yet, in integration order
+    log: VecDeque<CrdtOp>,
+
+    /// Operations trimmed from the log, per replica
+    trimmed: VersionVector,
+
+    /// Operations each peer reported integrating
+    acknowledged: BTreeMap<ReplicaId, VersionVector>,
+
+    /// Log length past which the oldest operations are trimmed
+    log_limit: usize,
+
+    /// Local operations not yet taken by `take_collab_ops`
+    outgoing: Vec<CrdtOp>,
+
+    /// Remote operations waiting for the operations they depend on, by
+    /// replica and seq
+    pending: BTreeMap<ReplicaId, BTreeMap<u64, CrdtOp>>,
+
+    cursors: BTreeMap<ReplicaId, CursorUpdate>,
+    cursor_seq: u64,
+}
+
+impl Collaboration {
+    fn new(replica: ReplicaId, content: &str) -> Self {
+        let elements: Vec<Element> = (1..=content.chars().count() as u64)
+            .map(|counter| Element {
+                id: CharId {
+                    counter,
+                    replica: BASE_REPLICA,
+                },
+                deleted: false,
+            })
+            .collect();
+
+        Self {
+            replica,
+            clock: elements.len() as u64,
+            sequence: Sequence::new(elements),
+            versions: VersionVector::new(),
+            log: VecDeque::new(),
+            trimmed: VersionVector::new(),
+            acknowledged: BTreeMap::new(),
+            log_limit: MAX_LOG_OPS,
+            outgoing: Vec::new(),
+            pending: BTreeMap::new(),
+            cursors: BTreeMap::new(),
+            cursor_seq: 0,
+        }
+    }
+
+    /// Helper: Restores the state captured by `Editor::collab_snapshot`
+    fn from_snapshot(replica: ReplicaId, snapshot: CollabSnapshot) -> Result<Self> {
+        let count = snapshot.elements.len();
+        let mut collab = Self::new(replica, "");
+        collab.clock = snapshot.elements.iter().map(|(id, _)| id.counter).fold(snapshot.clock, u64::max);
+        if collab.clock > MAX_COUNTER {
+            bail!("snapshot counters are out of range");
+        }
+        collab.sequence = Sequence::new(
+            snapshot
+                .elements
+                .into_iter()
+                .map(|(id, deleted)| Element { id, deleted })
+                .collect(),
+        );
+        if collab.sequence.len() != count {
+            bail!("snapshot repeats character ids");
+        }
+
+        // Nothing before the snapshot is in the log
+        collab.trimmed = snapshot.versions.clone();
+        collab.versions = snapshot.versions;
+        Ok(collab)
+    }
+
+    /// Records a local edit (char offsets in the text before the edit)
+    pub(crate) fn record_local(&mut self, start: usize, end: usize, text: &str) {
+        if end > start {
+            let targets = self.delete_visible(start, end);
+            self.push_local(CrdtOpKind::Delete { targets });
+        }
+
+        if !text.is_empty() {
+            let origin = start.checked_sub(1).map(|i| self.visible_id(i));
+            let counter = self.clock + 1;
+            self.integrate_insert(origin, counter, self.replica, text);
+            self.push_local(CrdtOpKind::Insert {
+                origin,
+                counter,
+                text: text.to_string(),
+            });
+        }
+    }
+
+    /// Helper: Numbers a local operation and queues it for sending
+    fn push_local(&mut self, kind: CrdtOpKind) {
+        let seq = self.versions.get(&self.replica).copied().unwrap_or(0) + 1;
+        self.versions.insert(self.replica, seq);
+
+        let op = CrdtOp {
+            replica: self.replica,
+            seq,
+            kind,
+        };
+        self.push_log(op.clone());
+        self.outgoing.push(op);
+    }
+
+    /// Helper: Tombstones visible characters `start..end`
+    ///
+    /// Returns: Deleted ids, as runs
+    fn delete_visible(&mut self, start: usize, end: usize) -> Vec<CharRange> {
+        let mut targets: Vec<CharRange> = Vec::new();
+        let Some(mut slot) = self.sequence.nth_visible(start) else {
+            return targets;
+        };
+
+        let mut remaining = end - start;
+        while remaining > 0 {
+            let Some(element) = self.sequence.get(slot) else {
+                break;
+            };
+            let id = element.id;
+            if self.sequence.delete(slot) {
+                remaining -= 1;
+                match targets.last_mut() {
+                    Some(run) if run.start.replica == id.replica && run.start.counter + run.len == id.counter => {
+                        run.len += 1;
+                    }
+                    _ => targets.push(CharRange { start: id, len: 1 }),
+                }
+            }
+            slot = self.sequence.next(slot);
+        }
+        targets
+    }
+
+    /// Integrates remote operations (and any pending ones they unblock)
+    ///
+    /// Returns: Rope changes in the order they must be applied
+    fn integrate_remote(&mut self, ops: Vec<CrdtOp>) -> Result<Vec<TextChange>> {
+        if ops.iter().any(|op| op.replica == BASE_REPLICA) {
+            bail!("operation from replica {} cannot be applied here", BASE_REPLICA);
+        }
+        if let Some(op) = ops.iter().find(|op| !op.counters_in_range()) {
+            bail!("operation {} from replica {} has counters out of range", op.seq, op.replica);
+        }
+
+        for op in ops {
+            // Relays that echo to everyone send our own operations back
+            if op.replica == self.replica || op.seq <= self.integrated(op.replica) {
+                continue; // Duplicate
+            }
+            self.pending.entry(op.replica).or_default().insert(op.seq, op);
+        }
+
+        // Only the next operation of each replica can be ready
+        let mut changes = Vec::new();
+        let mut progress = true;
+        while progress {
+            progress = false;
+            let replicas: Vec<ReplicaId> = self.pending.keys().copied().collect();
+            for replica in replicas {
+                while let Some(op) = self.take_ready(replica) {
+                    match &op.kind {
+                        CrdtOpKind::Insert { origin, counter, text } => {
+                            let at = self.integrate_insert(*origin, *counter, op.replica, text);
+                            changes.push(TextChange::Insert {
+                                at,
+                                text: text.clone(),
+                            });
+                        }
+                        CrdtOpKind::Delete { targets } => changes.extend(self.integrate_delete(targets)),
+                    }
+
+                    self.versions.insert(op.replica, op.seq);
+                    self.push_log(op);
+                    progress = true;
+                }
+            }
+        }
+
+        Ok(changes)
+    }
+
+    /// Helper: Number of operations integrated from a replica
+    fn integrated(&self, replica: ReplicaId) -> u64 {
+        self.versions.get(&replica).copied().unwrap_or(0)
+    }
+
+    /// Number of remote operations waiting for missing ones
+    fn pending_len(&self) -> usize {
+        self.pending.values().map(BTreeMap::len).sum()
+    }
+
+    /// Helper: Takes a replica's next operation if its dependencies arrived
+    fn take_ready(&mut self, replica: ReplicaId) -> Option<CrdtOp> {
+        let seq = self.integrated(replica) + 1;
+        let op = self.pending.get(&replica)?.get(&seq)?;
+        let ready = match &op.kind {
+            CrdtOpKind::Insert { origin, .. } => origin.is_none_or(|id| self.sequence.contains(id)),
+            CrdtOpKind::Delete { targets } => targets
+                .iter()
+                .flat_map(CharRange::ids)
+                .all(|id| self.sequence.contains(id)),
+        };
+        if !ready {
+            return None;
+        }
+
+        let queue = self.pending.get_mut(&replica)?;
+        let op = queue.remove(&seq);
+        if queue.is_empty() {
+            self.pending.remove(&replica);
+        }
+        op
+    }
+
+    /// Helper: Logs an integrated operation, trimming the oldest past the limit
+    fn push_log(&mut self, op: CrdtOp) {
+        self.log.push_back(op);
+        while self.log.len() > self.log_limit {
+            if let Some(op) = self.log.pop_front() {
+                self.trimmed.insert(op.replica, op.seq);
+            }
+        }
+    }
+
+    /// Helper: Trims the operations every known peer has integrated
+    fn trim_acknowledged(&mut self) {
+        if self.acknowledged.is_empty() {
+            return;
+        }
+
+        let floors: VersionVector = self
+            .versions
+            .keys()
+            .map(|replica| {
+                let floor = self.acknowledged.values().map(|v| v.get(replica).copied().unwrap_or(0)).min();
+                (*replica, floor.unwrap_or(0))
+            })
+            .collect();
+        let trimmed = &mut self.trimmed;
+        // Each replica's operations are logged in order, so the last one
+        // trimmed has the highest seq
+        self.log.retain(|op| {
+            let keep = op.seq > floors.get(&op.replica).copied().unwrap_or(0);
+            if !keep {
+                trimmed.insert(op.replica, op.seq);
+            }
+            keep
+        });
+    }
+
+    /// Helper: Places inserted characters in the sequence
+    ///
+    /// Right after the origin, characters with a higher id (inserted
+    /// concurrently, or after them) are skipped, so every replica ends up
+    /// with the same order whatever the arrival order was.
+    ///
+    /// Returns: Visible offset of the first inserted character
+    fn integrate_insert(&mut self, origin: Option<CharId>, counter: u64, replica: ReplicaId, text: &str) -> usize {
+        let first = CharId { counter, replica };
+        let mut slot = match origin.and_then(|origin| self.sequence.locate(origin)) {
+            Some(slot) => self.sequence.next(slot),
+            None => self.sequence.normalize((0, 0)),
+        };
+        while self.sequence.get(slot).is_some_and(|e| e.id > first) {
+            slot = self.sequence.next(slot);
+        }
+
+        let at = self.sequence.visible_before(slot);
+        let len = text.chars().count() as u64;
+        let inserted = (0..len)
+            .map_while(|i| counter.checked_add(i))
+            .map(|counter| Element {
+                id: CharId { counter, replica },
+                deleted: false,
+            })
+            .collect();
+        self.sequence.insert(slot, inserted);
+        let last = counter.checked_add(len.saturating_sub(1)).expect("counters are checked on arrival");
+        self.clock = self.clock.max(last);
+        at
+    }
+
+    /// Helper: Tombstones remote deletions
+    ///
+    /// Returns: Deleted visible ranges, last first (each one is applied on
+    /// the text left by the previous)
+    fn integrate_delete(&mut self, targets: &[CharRange]) -> Vec<TextChange> {
+        // Visible offsets in the text before the deletion; runs are
+        // usually adjacent, so each id is first looked for after the last
+        let mut hits: Vec<(usize, Slot)> = Vec::new();
+        for target in targets {
+            let mut previous: Option<(usize, Slot)> = None;
+            for id in target.ids() {
+                let adjacent = previous.and_then(|(offset, slot)| {
+                    let visible = !self.sequence.get(slot)?.deleted;
+                    let next = self.sequence.next(slot);
+                    (self.sequence.get(next)?.id == id).then_some((offset + usize::from(visible), next))
+                });
+                let found = adjacent.or_else(|| {
+                    let slot = self.sequence.locate(id)?;
+                    Some((self.sequence.visible_before(slot), slot))
+                });
+                let Some((offset, slot)) = found else {
+                    continue;
+                };
+                if self.sequence.get(slot).is_some_and(|e| !e.deleted) {
+                    hits.push((offset, slot));
+                }
+                previous = Some((offset, slot));
+            }
+        }
+
+        let mut ranges: Vec<(usize, usize)> = Vec::new();
+        hits.sort_unstable();
+        hits.dedup();
+        for (offset, slot) in hits {
+            self.sequence.delete(slot);
+            match ranges.last_mut() {
+                Some((_, end)) if *end == offset => *end += 1,
+                _ => ranges.push((offset, offset + 1)),
+            }
+        }
+
+        ranges
+            .into_iter()
+            .rev()
+            .map(|(start, end)| TextChange::Delete { start, end })
+            .collect()
+    }
+
+    /// Helper: Gets the id of the visible character at `offset`
+    fn visible_id(&self, offset: usize) -> CharId {
+        let slot = self.sequence.nth_visible(offset).expect("offset within the document");
+        self.sequence.get(slot).expect("slot of a character").id
+    }
+
+    /// Helper: Anchors a char offset to the character before it
+    fn anchor_at(&self, offset: usize) -> Anchor {
+        Anchor {
+            after: offset.checked_sub(1).map(|i| self.visible_id(i)),
+        }
+    }
+
+    /// Helper: Resolves an anchor to a char offset (None if not integrated yet)
+    fn resolve(&self, anchor: &Anchor) -> Option<usize> {
+        match anchor.after {
+            None => Some(0),
+            Some(id) => {
+                let slot = self.sequence.locate(id)?;
+                let visible = !self.sequence.get(slot)?.deleted;
+                Some(self.sequence.visible_before(slot) + usize::from(visible))
+            }
+        }
+    }
+}
+
+impl Editor {
+    /// Starts collaborating on the current content
+    ///
+    /// Every replica must enable collaboration on the same content (for
+    /// example the file as saved on disk) before exchanging operations.
+    ///
+    /// Parameters:
+    /// - `replica`: Unique id of this replica (not 0)
+    pub fn enable_collaboration(&mut self, replica: ReplicaId) -> Result<()> {
+        if replica == BASE_REPLICA {
+            bail!("replica id 0 is reserved for the initial content");
+        }
+
+        self.collab = Some(Collaboration::new(replica, &self.content()));
+        Ok(())
+    }
+
+    /// Stops collaborating (pending operations and remote cursors are dropped)
+    pub fn disable_collaboration(&mut self) {
+        self.collab = None;
+    }
+
+    /// Gets this replica's id (None when not collaborating)
+    pub fn replica_id(&self) -> Option<ReplicaId> {
+        self.collab.as_ref().map(|collab| collab.replica)
+    }
+
+    /// Takes the operations made locally since the last call
+    pub fn take_collab_ops(&mut self) -> Vec<CrdtOp> {
+        self.collab
+            .as_mut()
+            .map(|collab| std::mem::take(&mut collab.outgoing))
+            .unwrap_or_default()
+    }
+
+    /// Integrates operations from other replicas
+    ///
+    /// Operations may arrive in any order and more than once; this replica's
+    /// own operations (echoed back by a relay) are skipped. The resulting
+    /// changes are not undoable; local undo history is moved past them (a
+    /// local step overlapped by a remote change is dropped, with everything
+    /// older).
+    ///
+    /// Returns: Number of operations still waiting for missing ones
+    pub fn apply_collab_ops(&mut self, ops: Vec<CrdtOp>) -> Result<usize> {
+        if self.transaction_depth > 0 {
+            bail!("cannot apply remote operations inside a transaction");
+        }
+        let Some(collab) = &mut self.collab else {
+            bail!("collaboration is not enabled");
+        };
+
+        let changes = collab.integrate_remote(ops)?;
+        let pending = collab.pending_len();
+        if changes.is_empty() {
+            return Ok(pending);
+        }
+
+        // Detached so the rope changes are not recorded as local operations
+        let collab = self.collab.take();
+        for change in changes {
+            let (start_char, end_char, text) = match &change {
+                TextChange::Insert { at, text } => (*at, *at, text.as_str()),
+                TextChange::Delete { start, end } => (*start, *end, ""),
+            };
+            let start = self.rope.char_to_byte(start_char);
+            let end = self.rope.char_to_byte(end_char);
+            self.rebase_history(start, end, text.len());
+
+            let cursor = shift_offset(self.cursor.to_byte_offset(&self.rope), start, end, text.len());
+            let selection = self.selection.map(|s| {
+                (
+                    shift_offset(s.start.to_byte_offset(&self.rope), start, end, text.len()),
+                    shift_offset(s.end.to_byte_offset(&self.rope), start, end, text.len()),
+                )
+            });
+
+            self.apply_raw_edit(start, end, text);
+
+            self.cursor = Position::from_byte_offset(&self.rope, cursor);
+            self.selection = selection.map(|(s, e)| {
+                Selection::new(Position::from_byte_offset(&self.rope, s), Position::from_byte_offset(&self.rope, e))
+            });
+        }
+        self.collab = collab;
+
+        self.redo_stack.clear();
+        self.is_dirty = true;
+        self.reparse();
+        Ok(pending)
+    }
+
+    /// Gets the number of operations integrated from each replica
+    pub fn collab_versions(&self) -> Option<VersionVector> {
+        self.collab.as_ref().map(|collab| collab.versions.clone())
+    }
+
+    /// Gets the operations a replica has not integrated yet (offline merge)
+    ///
+    /// Parameters:
+    /// - `versions`: The other replica's `collab_versions()`
+    ///
+    /// Returns: Err if some of them were trimmed from the log (send it a
+    /// `collab_snapshot()` instead)
+    pub fn collab_ops_since(&self, versions: &VersionVector) -> Result<Vec<CrdtOp>> {
+        let Some(collab) = &self.collab else {
+            return Ok(Vec::new());
+        };
+
+        let behind = collab
+            .trimmed
+            .iter()
+            .find(|(replica, seq)| **seq > versions.get(replica).copied().unwrap_or(0));
+        if let Some((replica, _)) = behind {
+            bail!("operations from replica {} were trimmed; a snapshot is needed", replica);
+        }
+
+        Ok(collab
+            .log
+            .iter()
+            .filter(|op| op.seq > versions.get(&op.replica).copied().unwrap_or(0))
+            .cloned()
+            .collect())
+    }
+
+    /// Records the operations another replica reported integrating
+    ///
+    /// Operations every known peer has integrated are trimmed from the log.
+    /// Report each peer's `collab_versions()` as it arrives (for example
+    /// with every sync) to keep the log short.
+    pub fn acknowledge_collab_versions(&mut self, replica: ReplicaId, versions: &VersionVector) -> Result<()> {
+        let Some(collab) = &mut self.collab else {
+            bail!("collaboration is not enabled");
+        };
+        if replica == collab.replica || replica == BASE_REPLICA {
+            bail!("replica {} cannot acknowledge operations here", replica);
+        }
+
+        let acknowledged = collab.acknowledged.entry(replica).or_default();
+        for (from, seq) in versions {
+            let current = acknowledged.entry(*from).or_default();
+            *current = (*current).max(*seq);
+        }
+        collab.trim_acknowledged();
+        Ok(())
+    }
+
+    /// Forgets a replica that left the session (its cursor and acknowledgements)
+    pub fn remove_collab_peer(&mut self, replica: ReplicaId) {
+        if let Some(collab) = &mut self.collab {
+            collab.cursors.remove(&replica);
+            collab.acknowledged.remove(&replica);
+            collab.trim_acknowledged();
+        }
+    }
+
+    /// Captures the collaboration state for a replica that cannot catch up
+    /// with `collab_ops_since`
+    pub fn collab_snapshot(&self) -> Option<CollabSnapshot> {
+        let collab = self.collab.as_ref()?;
+        Some(CollabSnapshot {
+            text: self.content(),
+            clock: collab.clock,
+            versions: collab.versions.clone(),
+            elements: collab.sequence.iter().map(|e| (e.id, e.deleted)).collect(),
+        })
+    }
+
+    /// Starts collaborating from another replica's snapshot
+    ///
+    /// Replaces the content; the undo history is dropped since it refers
+    /// to the old text.
+    ///
+    /// Parameters:
+    /// - `replica`: Unique id of this replica (not 0)
+    pub fn enable_collaboration_from_snapshot(
+        &mut self,
+        replica: ReplicaId,
+        mut snapshot: CollabSnapshot,
+    ) -> Result<()> {
+        if replica == BASE_REPLICA {
+            bail!("replica id 0 is reserved for the initial content");
+        }
+        if self.transaction_depth > 0 {
+            bail!("cannot load a snapshot inside a transaction");
+        }
+
+        let text = std::mem::take(&mut snapshot.text);
+        let collab = Collaboration::from_snapshot(replica, snapshot)?;
+        if collab.sequence.visible_len() != text.chars().count() {
+            bail!("snapshot sequence does not match its text");
+        }
+
+        self.collab = None;
+        self.set_content(&text)?;
+        self.undo_stack.clear();
+        self.redo_stack.clear();
+        self.collab = Some(collab);
+        Ok(())
+    }
+
+    /// Anchors the local cursor and selection for other replicas
+    pub fn collab_cursor_update(&mut self) -> Option<CursorUpdate> {
+        let char_offset = |position: &Position| self.rope.byte_to_char(position.to_byte_offset(&self.rope));
+        let cursor = char_offset(&self.cursor);
+        let selection = self.selection.map(|s| (char_offset(&s.start), char_offset(&s.end)));
+
+        let collab = self.collab.as_mut()?;
+        collab.cursor_seq += 1;
+        Some(CursorUpdate {
+            replica: collab.replica,
+            seq: collab.cursor_seq,
+            cursor: collab.anchor_at(cursor),
+            selection: selection.map(|(start, end)| (collab.anchor_at(start), collab.anchor_at(end))),
+        })
+    }
+
+    /// Stores another replica's cursor (older updates are ignored)
+    pub fn apply_cursor_update(&mut self, update: CursorUpdate) -> Result<()> {
+        let Some(collab) = &mut self.collab else {
+            bail!("collaboration is not enabled");
+        };
+        if update.replica == collab.replica {
+            bail!("cursor update from this replica");
+        }
+
+        let newer = collab.cursors.get(&update.replica).is_none_or(|current| update.seq > current.seq);
+        if newer {
+            collab.cursors.insert(update.replica, update);
+        }
+        Ok(())
+    }
+
+    /// Forgets a replica's cursor (it left the session)
+    pub fn remove_remote_cursor(&mut self, replica: ReplicaId) {
+        if let Some(collab) = &mut self.collab {
+            collab.cursors.remove(&replica);
+        }
+    }
+
+    /// Gets the other replicas' cursors in the current text
+    ///
+    /// Cursors anchored to characters this replica has not received yet
+    /// are left out until the operations arrive.
+    pub fn remote_cursors(&self) -> Vec<RemoteCursor> {
+        let Some(collab) = &self.collab else {
+            return Vec::new();
+        };
+
+        let position = |anchor: &Anchor| {
+            collab
+                .resolve(anchor)
+                .map(|offset| Position::from_byte_offset(&self.rope, self.rope.char_to_byte(offset)))
+        };
+        collab
+            .cursors
+            .values()
+            .filter_map(|update| {
+                let selection = match &update.selection {
+                    Some((start, end)) => Some(Selection::new(position(start)?, position(end)?)),
+                    None => None,
+                };
+                Some(RemoteCursor {
+                    replica: update.replica,
+                    cursor: position(&update.cursor)?,
+                    selection,
+                })
+            })
+            .collect()
+    }
+
+    /// Helper: Moves undo history past a remote change (byte offsets)
+    ///
+    /// Walks the undo stack newest first, carrying the change back through
+    /// each edit. An edit the change overlaps can no longer be undone, so
+    /// its transaction and all older ones are dropped.
+    fn rebase_history(&mut self, start: usize, end: usize, inserted: usize) {
+        let (mut start, mut end) = (start, end);
+        for index in (0..self.undo_stack.len()).rev() {
+            for edit in self.undo_stack[index].edits.iter_mut().rev() {
+                let edit_end = edit.position + edit.inserted_text.len();
+                if start >= edit_end {
+                    // After the edit: move the change to the text before it
+                    start = start - edit.inserted_text.len() + edit.deleted_text.len();
+                    end = end - edit.inserted_text.len() + edit.deleted_text.len();
+                } else if end <= edit.position {
+                    edit.position = edit.position + inserted - (end - start);
+                } else {
+                    self.undo_stack.drain(..=index);
+                    return;
+                }
+            }
+        }
+    }
+}
+
+/// Helper: Maps a byte offset across a replacement of `start..end`
+///
+/// Offsets inside the replaced range collapse to its start; an insertion
+/// exactly at the offset leaves it in front of the new text.
+fn shift_offset(offset: usize, start: usize, end: usize, inserted: usize) -> usize {
+    if offset <= start {
+        offset
+    } else if offset >= end {
+        offset - (end - start) + inserted
+    } else {
+        start
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::{Duration, Instant};
+
+    use crate::editor::LanguageId;
+
+    fn replica(id: ReplicaId, content: &str) -> Editor {
+        let mut editor = Editor::with_content(content, LanguageId::PlainText).unwrap();
+        editor.enable_collaboration(id).unwrap();
+        editor
+    }
+
+    /// Sends `from`'s new operations to `to` through the byte encoding
+    fn sync(from: &mut Editor, to: &mut Editor) {
+        let bytes = CrdtOp::encode(&from.take_collab_ops());
+        assert_eq!(to.apply_collab_ops(CrdtOp::decode(&bytes).unwrap()).unwrap(), 0);
+    }
+
+    #[test]
+    fn test_local_edits_replicate() {
+        let mut a = replica(1, "hello\n");
+        let mut b = replica(2, "hello\n");
+
+        a.move_cursor(Position::new(0, 5));
+        a.insert_text(" world").unwrap();
+        a.set_selection(Selection::new(Position::new(0, 0), Position::new(0, 1)));
+        a.delete().unwrap();
+        sync(&mut a, &mut b);
+
+        assert_eq!(b.content(), "ello world\n");
+        assert_eq!(b.content(), a.content());
+        assert!(b.is_dirty());
+
+        // Remote changes are not undoable on the receiving side
+        assert!(!b.undo().unwrap());
+    }
+
+    #[test]
+    fn test_concurrent_edits_converge() {
+        let mut a = replica(1, "ac");
+        let mut b = replica(2, "ac");
+        let mut c = replica(3, "ac");
+
+        // Concurrent inserts at the same place and an overlapping delete
+        a.move_cursor(Position::new(0, 1));
+        a.insert_text("X").unwrap();
+        b.move_cursor(Position::new(0, 1));
+        b.insert_text("Y").unwrap();
+        c.set_selection(Selection::new(Position::new(0, 0), Position::new(0, 2)));
+        c.delete().unwrap();
+
+        let ops_a = a.take_collab_ops();
+        let ops_b = b.take_collab_ops();
+        let ops_c = c.take_collab_ops();
+
+        // Each replica receives the others in a different order
+        a.apply_collab_ops(ops_c.clone()).unwrap();
+        a.apply_collab_ops(ops_b.clone()).unwrap();
+        b.apply_collab_ops(ops_a.clone()).unwrap();
+        b.apply_collab_ops(ops_c).unwrap();
+        c.apply_collab_ops(ops_b).unwrap();
+        c.apply_collab_ops(ops_a).unwrap();
+
+        assert_eq!(a.content(), b.content());
+        assert_eq!(b.content(), c.content());
+        assert_eq!(a.content().len(), 2);
+        assert!(a.content() == "XY" || a.content() == "YX");
+
+        // The sequence still mirrors the rope
+        let visible = a.collab.as_ref().unwrap().sequence.visible_len();
+        assert_eq!(visible, a.rope().len_chars());
+    }
+
+    #[test]
+    fn test_offline_merge() {
+        let mut a = replica(1, "fn main() {}\n");
+        let mut b = replica(2, "fn main() {}\n");
+
+        // Both edit while disconnected, several operations each
+        a.move_cursor(Position::new(0, 3));
+        a.insert_text("run ").unwrap();
+        a.insert_text("x").unwrap();
+        a.undo().unwrap();
+        b.move_cursor(Position::new(1, 0));
+        b.insert_text("// end\n").unwrap();
+        b.move_cursor(Position::new(0, 0));
+        b.insert_text("pub ").unwrap();
+
+        // Reconnect: each sends what the other has not seen
+        let for_b = CrdtOp::encode(&a.collab_ops_since(&b.collab_versions().unwrap()).unwrap());
+        let for_a = CrdtOp::encode(&b.collab_ops_since(&a.collab_versions().unwrap()).unwrap());
+        b.apply_collab_ops(CrdtOp::decode(&for_b).unwrap()).unwrap();
+        a.apply_collab_ops(CrdtOp::decode(&for_a).unwrap()).unwrap();
+
+        assert_eq!(a.content(), "pub fn run main() {}\n// end\n");
+        assert_eq!(a.content(), b.content());
+        assert_eq!(a.collab_versions(), b.collab_versions());
+
+        // Nothing left to exchange
+        assert!(a.collab_ops_since(&b.collab_versions().unwrap()).unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_out_of_order_and_duplicate_delivery() {
+        let mut a = replica(1, "");
+        let mut b = replica(2, "");
+
+        a.insert_text("ab").unwrap();
+        a.move_cursor(Position::new(0, 1));
+        a.delete().unwrap();
+        let ops = a.take_collab_ops();
+        assert_eq!(ops.len(), 2);
+
+        // The delete waits for the insert it targets
+        assert_eq!(b.apply_collab_ops(vec![ops[1].clone()]).unwrap(), 1);
+        assert_eq!(b.content(), "");
+        assert_eq!(b.apply_collab_ops(ops.clone()).unwrap(), 0);
+        assert_eq!(b.content(), "a");
+
+        // Replayed operations are ignored
+        b.apply_collab_ops(ops).unwrap();
+        assert_eq!(b.content(), "a");
+    }
+
+    #[test]
+    fn test_remote_edit_moves_cursor_and_history() {
+        let mut a = replica(1, "one two\n");
+        let mut b = replica(2, "one two\n");
+
+        b.move_cursor(Position::new(0, 7));
+        b.insert_text("!").unwrap();
+        b.take_collab_ops();
+
+        a.move_cursor(Position::new(0, 0));
+        a.insert_text(">> ").unwrap();
+        sync(&mut a, &mut b);
+
+        assert_eq!(b.content(), ">> one two!\n");
+        assert_eq!(b.cursor(), Position::new(0, 11));
+
+        // The local insert is still undoable at its new place
+        assert!(b.undo().unwrap());
+        assert_eq!(b.content(), ">> one two\n");
+    }
+
+    #[test]
+    fn test_remote_cursors_follow_edits() {
+        let mut a = replica(1, "abc\ndef\n");
+        let mut b = replica(2, "abc\ndef\n");
+
+        a.move_cursor(Position::new(1, 1));
+        let update = CursorUpdate::decode(&a.collab_cursor_update().unwrap().encode()).unwrap();
+        b.apply_cursor_update(update.clone()).unwrap();
+        assert_eq!(b.remote_cursors()[0].cursor, Position::new(1, 1));
+
+        // Text inserted before the anchor moves the remote cursor
+        b.move_cursor(Position::new(0, 0));
+        b.insert_text("new\n").unwrap();
+        let cursors = b.remote_cursors();
+        assert_eq!(cursors.len(), 1);
+        assert_eq!(cursors[0].replica, 1);
+        assert_eq!(cursors[0].cursor, Position::new(2, 1));
+
+        // Stale updates are ignored
+        let mut stale = update;
+        stale.seq = 0;
+        stale.cursor = Anchor { after: None };
+        b.apply_cursor_update(stale).unwrap();
+        assert_eq!(b.remote_cursors()[0].cursor, Position::new(2, 1));
+
+        b.remove_remote_cursor(1);
+        assert!(b.remote_cursors().is_empty());
+    }
+
+    #[test]
+    fn test_acknowledged_operations_are_trimmed() {
+        let mut a = replica(1, "");
+        let mut b = replica(2, "");
+        a.insert_text("ab").unwrap();
+        sync(&mut a, &mut b);
+        b.insert_text("c").unwrap();
+        sync(&mut b, &mut a);
+        assert_eq!(a.collab.as_ref().unwrap().log.len(), 2);
+
+        a.acknowledge_collab_versions(2, &b.collab_versions().unwrap()).unwrap();
+        assert!(a.collab.as_ref().unwrap().log.is_empty());
+        assert!(a.acknowledge_collab_versions(1, &VersionVector::new()).is_err());
+
+        // b is up to date; a replica that joins now needs a snapshot
+        assert!(a.collab_ops_since(&b.collab_versions().unwrap()).unwrap().is_empty());
+        assert!(a.collab_ops_since(&VersionVector::new()).is_err());
+
+        let snapshot = CollabSnapshot::decode(&a.collab_snapshot().unwrap().encode()).unwrap();
+        let mut c = Editor::with_content("", LanguageId::PlainText).unwrap();
+        c.enable_collaboration_from_snapshot(3, snapshot).unwrap();
+        assert_eq!(c.content(), "cab");
+        assert_eq!(c.collab_versions(), a.collab_versions());
+        a.acknowledge_collab_versions(3, &c.collab_versions().unwrap()).unwrap();
+
+        c.move_cursor(Position::new(0, 1));
+        c.insert_text("!").unwrap();
+        let ops = c.take_collab_ops();
+        a.apply_collab_ops(ops.clone()).unwrap();
+        b.apply_collab_ops(ops).unwrap();
+        assert_eq!(a.content(), "c!ab");
+        assert_eq!(b.content(), a.content());
+
+        // c acknowledged before its operation, which stays logged until c
+        // acknowledges again or leaves
+        a.acknowledge_collab_versions(2, &b.collab_versions().unwrap()).unwrap();
+        assert_eq!(a.collab.as_ref().unwrap().log.len(), 1);
+        a.remove_collab_peer(3);
+        assert!(a.collab.as_ref().unwrap().log.is_empty());
+    }
+
+    #[test]
+    fn test_log_is_capped() {
+        let mut a = replica(1, "");
+        a.collab.as_mut().unwrap().log_limit = 2;
+        for text in ["a", "b", "c"] {
+            a.insert_text(text).unwrap();
+        }
+
+        assert_eq!(a.collab.as_ref().unwrap().log.len(), 2);
+        assert_eq!(a.collab_ops_since(&VersionVector::from([(1, 1)])).unwrap().len(), 2);
+        assert!(a.collab_ops_since(&VersionVector::new()).is_err());
+
+        let mut snapshot = a.collab_snapshot().unwrap();
+        snapshot.text.push('!');
+        let mut b = Editor::with_content("", LanguageId::PlainText).unwrap();
+        assert!(b.enable_collaboration_from_snapshot(2, snapshot).is_err());
+    }
+
+    #[test]
+    fn test_large_document_integration() {
+        let content = format!("{}\n", "x".repeat(79)).repeat(10_000);
+        let mut a = replica(1, &content);
+        let mut b = replica(2, &content);
+
+        let started = Instant::now();
+        for i in 0..2000 {
+            a.move_cursor(Position::new(i * 5, i % 80));
+            a.insert_text("y").unwrap();
+        }
+        a.set_selection(Selection::new(Position::new(100, 0), Position::new(300, 0)));
+        a.delete().unwrap();
+        let local = started.elapsed();
+
+        // Reversed, so everything waits in the pending queue first
+        let mut ops = a.take_collab_ops();
+        ops.reverse();
+        let started = Instant::now();
+        assert_eq!(b.apply_collab_ops(ops).unwrap(), 0);
+        let remote = started.elapsed();
+
+        assert_eq!(b.content(), a.content());
+        // Scanning the sequence per operation took seconds
+        assert!(local < Duration::from_secs(2), "local edits took {:?}", local);
+        assert!(remote < Duration::from_secs(2), "remote operations took {:?}", remote);
+    }
+
+    #[test]
+    fn test_edits_across_chunks_converge() {
+        let content = "abcdefghij".repeat(200);
+        let mut a = replica(1, &content);
+        let mut b = replica(2, &content);
+
+        // Concurrent inserts and deletes spread over many chunks
+        let mut seed = 7usize;
+        for round in 0..50 {
+            for editor in [&mut a, &mut b] {
+                seed = seed.wrapping_mul(1_103_515_245).wrapping_add(12_345) % 2_147_483_648;
+                let len = editor.rope().len_chars();
+                let start = editor.rope().char_to_byte(seed % len);
+                let start = Position::from_byte_offset(editor.rope(), start);
+                if round % 3 == 0 {
+                    editor.move_cursor(start);
+                    editor.set_selection(Selection::new(start, Position::new(start.line, start.column + 1)));
+                    editor.delete().unwrap();
+                } else {
+                    editor.move_cursor(start);
+                    editor.insert_text(&"xyz".repeat(round % 7 + 1)).unwrap();
+                }
+            }
+            if round % 10 == 9 {
+                let (for_b, for_a) = (a.take_collab_ops(), b.take_collab_ops());
+                b.apply_collab_ops(for_b).unwrap();
+                a.apply_collab_ops(for_a).unwrap();
+                assert_eq!(a.content(), b.content());
+            }
+        }
+        assert!(a.collab.as_ref().unwrap().sequence.chunks.len() > 2);
+    }
+
+    #[test]
+    fn test_rejects_counters_out_of_range() {
+        let mut editor = replica(1, "x");
+        let insert = CrdtOp {
+            replica: 2,
+            seq: 1,
+            kind: CrdtOpKind::Insert { origin: None, counter: u64::MAX, text: "ab".to_string() },
+        };
+        assert!(editor.apply_collab_ops(vec![insert]).is_err());
+
+        let start = CharId { counter: u64::MAX - 1, replica: 2 };
+        let delete = CrdtOp {
+            replica: 2,
+            seq: 1,
+            kind: CrdtOpKind::Delete { targets: vec![CharRange { start, len: 5 }] },
+        };
+        assert!(editor.apply_collab_ops(vec![delete]).is_err());
+        assert_eq!(editor.content(), "x");
+        assert_eq!(editor.collab.as_ref().unwrap().pending_len(), 0);
+    }
+
+    #[test]
+    fn test_rejects_invalid_operations() {
+        let mut editor = Editor::with_content("x", LanguageId::PlainText).unwrap();
+        assert!(editor.apply_collab_ops(Vec::new()).is_err());
+        assert!(editor.enable_collaboration(0).is_err());
+        assert!(CrdtOp::decode(&[0xff, 0x01]).is_err());
+
+        editor.enable_collaboration(1).unwrap();
+        let base = CrdtOp {
+            replica: BASE_REPLICA,
+            seq: 1,
+            kind: CrdtOpKind::Delete { targets: Vec::new() },
+        };
+        assert!(editor.apply_collab_ops(vec![base]).is_err());
+    }
+
+    #[test]
+    fn test_own_operations_are_skipped() {
+        let mut a = replica(1, "x");
+        let mut b = replica(2, "x");
+
+        a.insert_text("y").unwrap();
+        let own = a.take_collab_ops();
+        b.move_cursor(Position::new(0, 1));
+        b.insert_text("z").unwrap();
+
+        // A relay echoes a's operation back along with b's
+        let mut batch = own.clone();
+        batch.extend(b.take_collab_ops());
+        assert_eq!(a.apply_collab_ops(batch).unwrap(), 0);
+        assert_eq!(a.content(), "yxz");
+        b.apply_collab_ops(own).unwrap();
+        assert_eq!(a.content(), b.content());
+    }
+}
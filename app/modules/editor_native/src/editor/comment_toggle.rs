@@ -1,12 +1,18 @@
+use anyhow::Result;
 use ropey::Rope;
+use tree_sitter::{Node, Tree};
+
 use crate::editor::cursor::Position;
+use crate::editor::{Editor, LanguageId};
 
 /// Comment toggling utilities.
 ///
 /// Provides functionality for:
 /// - Toggle line comments (e.g., `//` for Rust)
-/// - Toggle block comments (e.g., `/* */` for Rust)
-/// - Smart comment/uncomment based on selection
+/// - Toggle block comments (e.g., `/* */` for Rust), nesting-aware
+/// - Line toggling with block comments in languages without line
+///   comments (CSS, HTML) and inside JSX (`{/* */}`)
+/// - Doc-comment stubs from function signatures (`///`, `/** */`)
 ///
 /// Example:
 /// ```
@@ -16,50 +22,114 @@ use crate::editor::cursor::Position;
 /// ```
 #[derive(Debug, Clone)]
 pub struct CommentConfig {
-    /// Line comment syntax (e.g., "//" for Rust, "#" for Python)
-    pub line_comment: String,
+    /// Line comment syntax (e.g., "//" for Rust, "#" for Python), None if
+    /// the language only has block comments
+    pub line_comment: Option<String>,
 
     /// Block comment start (e.g., "/*" for Rust)
     pub block_comment_start: Option<String>,
 
     /// Block comment end (e.g., "*/" for Rust)
     pub block_comment_end: Option<String>,
+
+    /// Block comments nest (`/* a /* b */ c */` is one comment in Rust)
+    pub nested_block_comments: bool,
 }
 
 impl CommentConfig {
     /// Creates config for Rust-style comments.
     pub fn rust() -> Self {
         Self {
-            line_comment: "//".to_string(),
-            block_comment_start: Some("/*".to_string()),
-            block_comment_end: Some("*/".to_string()),
+            nested_block_comments: true,
+            ..Self::javascript()
         }
     }
 
     /// Creates config for Python-style comments.
     pub fn python() -> Self {
         Self {
-            line_comment: "#".to_string(),
+            line_comment: Some("#".to_string()),
             block_comment_start: Some("\"\"\"".to_string()),
             block_comment_end: Some("\"\"\"".to_string()),
+            nested_block_comments: false,
         }
     }
 
     /// Creates config for JavaScript-style comments.
     pub fn javascript() -> Self {
         Self {
-            line_comment: "//".to_string(),
+            line_comment: Some("//".to_string()),
             block_comment_start: Some("/*".to_string()),
-            block_comment_end: Some("*/".to_string(),),
+            block_comment_end: Some("*/".to_string()),
+            nested_block_comments: false,
+        }
+    }
+
+    /// Creates config for Dart comments (block comments nest).
+    pub fn dart() -> Self {
+        Self::rust()
+    }
+
+    /// Creates config for CSS comments (block comments only).
+    pub fn css() -> Self {
+        Self::block_only("/*", "*/")
+    }
+
+    /// Creates config for HTML comments (block comments only).
+    pub fn html() -> Self {
+        Self::block_only("<!--", "-->")
+    }
+
+    /// Creates config for comments between JSX elements.
+    pub fn jsx() -> Self {
+        Self::block_only("{/*", "*/}")
+    }
+
+    /// Gets the comment syntax of a language.
+    ///
+    /// Markdown has none: HTML comments are valid there, but toggling
+    /// only applies to code (fenced code uses the fence's language).
+    ///
+    /// Returns: Config, or None if the language has no comments
+    pub fn for_language(language: &LanguageId) -> Option<Self> {
+        match language {
+            LanguageId::Rust => Some(Self::rust()),
+            LanguageId::Dart => Some(Self::dart()),
+            LanguageId::Python => Some(Self::python()),
+            LanguageId::JavaScript | LanguageId::TypeScript | LanguageId::Java | LanguageId::Go => {
+                Some(Self::javascript())
+            }
+            LanguageId::Css => Some(Self::css()),
+            LanguageId::Html => Some(Self::html()),
+            LanguageId::Markdown | LanguageId::PlainText => None,
         }
     }
 
     /// Creates config for custom line comment.
     pub fn line_only(line_comment: &str) -> Self {
         Self {
-            line_comment: line_comment.to_string(),
+            line_comment: Some(line_comment.to_string()),
             block_comment_start: None,
             block_comment_end: None,
+            nested_block_comments: false,
+        }
+    }
+
+    /// Creates config for a language with block comments only.
+    pub fn block_only(start: &str, end: &str) -> Self {
+        Self {
+            line_comment: None,
+            block_comment_start: Some(start.to_string()),
+            block_comment_end: Some(end.to_string()),
+            nested_block_comments: false,
+        }
+    }
+
+    /// Helper: Gets the block comment tokens
+    fn block_tokens(&self) -> Option<(&str, &str)> {
+        match (&self.block_comment_start, &self.block_comment_end) {
+            (Some(start), Some(end)) => Some((start.as_str(), end.as_str())),
+            _ => None,
         }
     }
 }
@@ -67,7 +137,10 @@ impl CommentConfig {
 /// Toggles line comments for selected lines.
 ///
 /// If all selected lines are commented, uncomments them.
-/// Otherwise, comments all selected lines.
+/// Otherwise, comments all selected lines, so a mixed selection is
+/// commented as a whole (already commented lines get a second marker
+/// and come back unchanged on the next toggle). Languages without line
+/// comments wrap each line in a block comment instead.
 ///
 /// Parameters:
 /// - `rope`: The rope to modify
@@ -98,7 +171,11 @@ pub fn toggle_line_comments(
 
 /// Comments selected lines.
 ///
-/// Adds line comment at the beginning of each line.
+/// Line comments are inserted at the smallest indentation of the
+/// selection so they line up; blank lines are left alone. Without line
+/// comments, each line's text is wrapped in a block comment (lines that
+/// would end the comment early are skipped when block comments do not
+/// nest).
 ///
 /// Parameters:
 /// - `rope`: The rope to modify
@@ -111,28 +188,43 @@ pub fn comment_lines(
     end_line: usize,
     config: &CommentConfig,
 ) {
-    let comment_prefix = format!("{} ", config.line_comment);
-
-    for line_idx in start_line..=end_line.min(rope.len_lines().saturating_sub(1)) {
-        let line_start_offset = rope.line_to_char(line_idx);
-
-        // Find first non-whitespace position
-        let line = rope.line(line_idx);
-        let line_text = line.to_string();
-        let first_non_ws = line_text
-            .chars()
-            .position(|c| !c.is_whitespace())
+    let lines = non_blank_lines(rope, start_line, end_line);
+
+    if let Some(line_comment) = &config.line_comment {
+        let comment_prefix = format!("{} ", line_comment);
+        let column = lines
+            .iter()
+            .map(|&line_idx| leading_whitespace(rope, line_idx))
+            .min()
             .unwrap_or(0);
 
-        // Insert comment after leading whitespace
-        let insert_offset = line_start_offset + first_non_ws;
-        rope.insert(insert_offset, &comment_prefix);
+        // Insert comment at the common indentation
+        for line_idx in lines {
+            rope.insert(rope.line_to_char(line_idx) + column, &comment_prefix);
+        }
+        return;
+    }
+
+    let Some((block_start, block_end)) = config.block_tokens() else {
+        return;
+    };
+    for line_idx in lines {
+        let (content_start, content_end) = line_content(rope, line_idx);
+        let content = rope.slice(content_start..content_end).to_string();
+        if !config.nested_block_comments
+            && (is_wrapped(&content, block_start, block_end) || content.contains(block_end))
+        {
+            continue;
+        }
+        rope.insert(content_end, &format!(" {}", block_end));
+        rope.insert(content_start, &format!("{} ", block_start));
     }
 }
 
 /// Uncomments selected lines.
 ///
-/// Removes line comment from the beginning of each line.
+/// Removes the comment marker (and one following space) from lines that
+/// start with it; other lines are left unchanged.
 ///
 /// Parameters:
 /// - `rope`: The rope to modify
@@ -145,60 +237,61 @@ pub fn uncomment_lines(
     end_line: usize,
     config: &CommentConfig,
 ) {
-    for line_idx in start_line..=end_line.min(rope.len_lines().saturating_sub(1)) {
-        let line_start_offset = rope.line_to_char(line_idx);
-        let line = rope.line(line_idx);
-        let line_text = line.to_string();
-
-        // Find comment marker
-        if let Some(comment_pos) = line_text.find(&config.line_comment) {
-            let comment_offset = line_start_offset + comment_pos;
-
-            // Remove comment marker
-            let mut remove_len = config.line_comment.len();
-
-            // Also remove following space if present
-            if line_text[comment_pos..].starts_with(&format!("{} ", config.line_comment)) {
-                remove_len += 1;
+    for line_idx in non_blank_lines(rope, start_line, end_line) {
+        let (content_start, content_end) = line_content(rope, line_idx);
+        let content = rope.slice(content_start..content_end).to_string();
+
+        if let Some(line_comment) = &config.line_comment {
+            if let Some(rest) = content.strip_prefix(line_comment.as_str()) {
+                // Also remove following space if present
+                let remove_len = line_comment.chars().count() + usize::from(rest.starts_with(' '));
+                rope.remove(content_start..content_start + remove_len);
             }
+            continue;
+        }
 
-            rope.remove(comment_offset..(comment_offset + remove_len));
+        let Some((block_start, block_end)) = config.block_tokens() else {
+            return;
+        };
+        if is_wrapped(&content, block_start, block_end) {
+            let inner = &content[block_start.len()..content.len() - block_end.len()];
+            let end_len = block_end.chars().count() + usize::from(inner.ends_with(' '));
+            let start_len = block_start.chars().count() + usize::from(inner.starts_with(' '));
+            rope.remove(content_end - end_len..content_end);
+            rope.remove(content_start..content_start + start_len);
         }
     }
 }
 
 /// Checks if lines are commented.
 ///
-/// Returns true if all non-empty lines have line comments.
+/// Returns true if all non-empty lines have line comments (or are
+/// wrapped in a block comment when the language has no line comments).
 pub fn are_lines_commented(
     rope: &Rope,
     start_line: usize,
     end_line: usize,
     config: &CommentConfig,
 ) -> bool {
-    for line_idx in start_line..=end_line.min(rope.len_lines().saturating_sub(1)) {
-        let line = rope.line(line_idx);
-        let line_text = line.to_string();
-        let line_trimmed = line_text.trim();
-
-        // Skip empty lines
-        if line_trimmed.is_empty() {
-            continue;
+    non_blank_lines(rope, start_line, end_line).into_iter().all(|line_idx| {
+        let (content_start, content_end) = line_content(rope, line_idx);
+        let content = rope.slice(content_start..content_end).to_string();
+        match (&config.line_comment, config.block_tokens()) {
+            (Some(line_comment), _) => content.starts_with(line_comment.as_str()),
+            (None, Some((block_start, block_end))) => is_wrapped(&content, block_start, block_end),
+            (None, None) => false,
         }
-
-        // Check if line starts with comment
-        if !line_trimmed.starts_with(&config.line_comment) {
-            return false;
-        }
-    }
-
-    true
+    })
 }
 
 /// Toggles block comment for selection.
 ///
-/// If selection is already surrounded by block comment, removes it.
-/// Otherwise, adds block comment around selection.
+/// Whitespace around the selection is ignored. If the selection is one
+/// block comment, its delimiters are removed (with one space inside
+/// each, if both have one); otherwise the selection is wrapped. Inner
+/// block comments are fine where they nest (Rust, Dart); elsewhere an
+/// inner end token would close the new comment early, so nothing is
+/// changed.
 ///
 /// Parameters:
 /// - `rope`: The rope to modify
@@ -206,36 +299,39 @@ pub fn are_lines_commented(
 /// - `end_pos`: End position of selection
 /// - `config`: Comment configuration
 ///
-/// Returns: true if block comment was added, false if removed
+/// Returns: Some(true) if block comment was added, Some(false) if
+/// removed, None if nothing could be changed
 pub fn toggle_block_comment(
     rope: &mut Rope,
     start_pos: Position,
     end_pos: Position,
     config: &CommentConfig,
-) -> bool {
-    let Some(_block_start) = &config.block_comment_start else {
-        return false; // No block comments for this language
-    };
+) -> Option<bool> {
+    let (_, block_end) = config.block_tokens()?;
 
-    let Some(_block_end) = &config.block_comment_end else {
-        return false;
-    };
+    let mut start_offset = position_to_offset(rope, start_pos);
+    let mut end_offset = position_to_offset(rope, end_pos).max(start_offset);
 
-    let start_offset = position_to_offset(rope, start_pos);
-    let end_offset = position_to_offset(rope, end_pos);
+    // Ignore surrounding whitespace (whole-line selections)
+    while start_offset < end_offset && rope.char(start_offset).is_whitespace() {
+        start_offset += 1;
+    }
+    while end_offset > start_offset && rope.char(end_offset - 1).is_whitespace() {
+        end_offset -= 1;
+    }
 
     // Check if selection is already surrounded by block comment
-    let is_commented = is_block_commented(rope, start_offset, end_offset, config);
-
-    if is_commented {
-        // Remove block comment
+    if is_block_commented(rope, start_offset, end_offset, config) {
         remove_block_comment(rope, start_offset, end_offset, config);
-        false
-    } else {
-        // Add block comment
-        add_block_comment(rope, start_offset, end_offset, config);
-        true
+        return Some(false);
     }
+
+    let text = rope.slice(start_offset..end_offset).to_string();
+    if !config.nested_block_comments && text.contains(block_end) {
+        return None;
+    }
+    add_block_comment(rope, start_offset, end_offset, config);
+    Some(true)
 }
 
 /// Adds block comment around text range.
@@ -245,8 +341,9 @@ fn add_block_comment(
     end_offset: usize,
     config: &CommentConfig,
 ) {
-    let block_start = config.block_comment_start.as_ref().unwrap();
-    let block_end = config.block_comment_end.as_ref().unwrap();
+    let Some((block_start, block_end)) = config.block_tokens() else {
+        return;
+    };
 
     // Insert closing comment first (to not affect start offset)
     rope.insert(end_offset, block_end);
@@ -262,42 +359,97 @@ fn remove_block_comment(
     end_offset: usize,
     config: &CommentConfig,
 ) {
-    let block_start = config.block_comment_start.as_ref().unwrap();
-    let block_end = config.block_comment_end.as_ref().unwrap();
+    let Some((block_start, block_end)) = config.block_tokens() else {
+        return;
+    };
+
+    let text = rope.slice(start_offset..end_offset).to_string();
+    let inner = &text[block_start.len()..text.len() - block_end.len()];
+    let padded = usize::from(inner.len() >= 2 && inner.starts_with(' ') && inner.ends_with(' '));
 
     // Remove ending comment first
-    let end_remove_start = end_offset - block_end.len();
+    let end_remove_start = end_offset - block_end.chars().count() - padded;
     rope.remove(end_remove_start..end_offset);
 
     // Remove starting comment
-    rope.remove(start_offset..(start_offset + block_start.len()));
+    rope.remove(start_offset..(start_offset + block_start.chars().count() + padded));
 }
 
-/// Checks if range is surrounded by block comment.
+/// Checks if range is one block comment.
+///
+/// The comment opened at the start must be the one closed at the end:
+/// `/* a */ b /* c */` starts and ends with delimiters but is not.
 fn is_block_commented(
     rope: &Rope,
     start_offset: usize,
     end_offset: usize,
     config: &CommentConfig,
 ) -> bool {
-    let Some(block_start) = &config.block_comment_start else {
+    let Some((block_start, block_end)) = config.block_tokens() else {
         return false;
     };
 
-    let Some(block_end) = &config.block_comment_end else {
+    let text = rope.slice(start_offset..end_offset).to_string();
+    if text.len() < block_start.len() + block_end.len()
+        || !text.starts_with(block_start)
+        || !text.ends_with(block_end)
+    {
         return false;
-    };
+    }
 
-    // Check if text starts with block_start
-    let start_text = rope.slice(start_offset..(start_offset + block_start.len().min(rope.len_chars() - start_offset)));
-    if start_text.to_string() != *block_start {
-        return false;
+    // Find where the opening comment closes
+    let mut depth = 1;
+    let mut i = block_start.len();
+    while i < text.len() {
+        let rest = &text[i..];
+        if config.nested_block_comments && rest.starts_with(block_start) {
+            depth += 1;
+            i += block_start.len();
+        } else if rest.starts_with(block_end) {
+            depth -= 1;
+            if depth == 0 {
+                return i + block_end.len() == text.len();
+            }
+            i += block_end.len();
+        } else {
+            i += rest.chars().next().map_or(1, char::len_utf8);
+        }
     }
+    false
+}
+
+/// Helper: Checks if a line's text is wrapped in one block comment
+fn is_wrapped(content: &str, block_start: &str, block_end: &str) -> bool {
+    content.len() >= block_start.len() + block_end.len()
+        && content.starts_with(block_start)
+        && content.ends_with(block_end)
+}
 
-    // Check if text ends with block_end
-    let end_start = end_offset.saturating_sub(block_end.len());
-    let end_text = rope.slice(end_start..end_offset);
-    end_text.to_string() == *block_end
+/// Helper: Gets the selected lines that are not blank
+fn non_blank_lines(rope: &Rope, start_line: usize, end_line: usize) -> Vec<usize> {
+    let last_line = end_line.min(rope.len_lines().saturating_sub(1));
+    (start_line..=last_line)
+        .filter(|&line_idx| rope.line(line_idx).chars().any(|c| !c.is_whitespace()))
+        .collect()
+}
+
+/// Helper: Counts the leading whitespace characters of a line
+fn leading_whitespace(rope: &Rope, line_idx: usize) -> usize {
+    rope.line(line_idx).chars().take_while(|c| *c == ' ' || *c == '\t').count()
+}
+
+/// Helper: Gets a line's text without indentation and trailing whitespace
+///
+/// Returns: (start, end) char offsets
+fn line_content(rope: &Rope, line_idx: usize) -> (usize, usize) {
+    let line = rope.line(line_idx);
+    let line_start = rope.line_to_char(line_idx);
+    let start = leading_whitespace(rope, line_idx);
+    let mut end = line.len_chars();
+    while end > start && line.char(end - 1).is_whitespace() {
+        end -= 1;
+    }
+    (line_start + start, line_start + end)
 }
 
 /// Helper: Converts position to offset.
@@ -307,14 +459,401 @@ fn position_to_offset(rope: &Rope, position: Position) -> usize {
     line_offset + position.column.min(line.len_chars())
 }
 
+// ==================================================================
+// Doc-comment stubs
+// ==================================================================
+
+/// Node kinds that declare a function or method
+const FUNCTION_KINDS: &[&str] = &[
+    "function_item",
+    "function_signature_item",
+    "function_declaration",
+    "generator_function_declaration",
+    "function_signature",
+    "method_definition",
+    "method_signature",
+    "abstract_method_signature",
+    "method_declaration",
+    "constructor_declaration",
+    "function_definition",
+];
+
+/// Node kinds of function values (`const f = (a) => ...`)
+const FUNCTION_VALUE_KINDS: &[&str] = &["arrow_function", "function", "function_expression"];
+
+/// Doc comment to insert for a function.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DocCommentStub {
+    /// Line the stub is inserted before
+    pub line: usize,
+
+    /// Lines of the stub, without indentation
+    pub lines: Vec<String>,
+
+    /// Where to put the cursor (row within `lines`, column in chars)
+    pub cursor: (usize, usize),
+}
+
+/// Builds a doc-comment stub for the function declared on a line.
+///
+/// Rust gets `///` with an `# Arguments` list, JavaScript, TypeScript
+/// and Java get `/** */` with `@param`/`@returns` tags, Go a comment
+/// starting with the function name, and Python a docstring in the body.
+///
+/// Parameters:
+/// - `tree`: Syntax tree of the code
+/// - `rope`: Document text
+/// - `language`: Language of the tree
+/// - `line`: Line the declaration starts on
+///
+/// Returns: Stub, or None if no function starts on the line or it is
+/// already documented
+pub fn doc_comment_stub(tree: &Tree, rope: &Rope, language: &LanguageId, line: usize) -> Option<DocCommentStub> {
+    if line >= rope.len_lines() {
+        return None;
+    }
+    let indent = leading_whitespace(rope, line);
+    let byte = rope.char_to_byte(rope.line_to_char(line) + indent);
+    let (declaration, function) = function_at(tree.root_node(), byte)?;
+    let text = |node: Node| rope.byte_slice(node.byte_range()).to_string();
+
+    let params = parameter_names(function, rope, language);
+    let returns = function
+        .child_by_field_name("return_type")
+        .or_else(|| function.child_by_field_name("type"))
+        .map(|node| text(node).trim_start_matches(':').trim().to_string())
+        .filter(|ty| !matches!(ty.as_str(), "void" | "None" | "()"))
+        .is_some();
+
+    if *language == LanguageId::Python {
+        let body = function.child_by_field_name("body")?;
+        let body_line = body.start_position().row;
+        if body_line == function.start_position().row || is_docstring(body.named_child(0)) {
+            return None;
+        }
+        let mut lines = vec!["\"\"\"".to_string()];
+        if !params.is_empty() || returns {
+            lines.push(String::new());
+        }
+        if !params.is_empty() {
+            lines.push("Args:".to_string());
+            lines.extend(params.iter().map(|name| format!("    {}: ", name)));
+        }
+        if returns {
+            lines.extend(["Returns:".to_string(), "    ".to_string()]);
+        }
+        if lines.len() == 1 {
+            lines[0].push_str("\"\"\"");
+        } else {
+            lines.push("\"\"\"".to_string());
+        }
+        return Some(DocCommentStub { line: body_line, lines, cursor: (0, 3) });
+    }
+
+    // Doc comments go above attributes (`#[test]`)
+    let mut first = declaration;
+    while let Some(previous) = first.prev_named_sibling() {
+        if previous.kind() != "attribute_item" || previous.end_position().row + 1 < first.start_position().row {
+            break;
+        }
+        first = previous;
+    }
+    let line = first.start_position().row;
+    if line > 0 {
+        let (start, end) = line_content(rope, line - 1);
+        let previous = rope.slice(start..end).to_string();
+        if ["///", "/**", "*/", "//"].iter().any(|token| previous.starts_with(token)) {
+            return None;
+        }
+    }
+
+    let (lines, cursor) = match language {
+        LanguageId::Rust => {
+            let mut lines = vec!["/// ".to_string()];
+            if !params.is_empty() {
+                lines.extend(["///".to_string(), "/// # Arguments".to_string(), "///".to_string()]);
+                lines.extend(params.iter().map(|name| format!("/// * `{}` - ", name)));
+            }
+            (lines, (0, 4))
+        }
+        LanguageId::Go => {
+            let name = function.child_by_field_name("name").map(text)?;
+            (vec![format!("// {} ", name)], (0, name.chars().count() + 4))
+        }
+        LanguageId::JavaScript | LanguageId::TypeScript | LanguageId::Java => {
+            let return_tag = if *language == LanguageId::Java { "@return" } else { "@returns" };
+            let mut lines = vec!["/**".to_string(), " * ".to_string()];
+            lines.extend(params.iter().map(|name| format!(" * @param {} ", name)));
+            if returns && function.kind() != "constructor_declaration" {
+                lines.push(format!(" * {} ", return_tag));
+            }
+            lines.push(" */".to_string());
+            (lines, (1, 3))
+        }
+        _ => return None,
+    };
+    Some(DocCommentStub { line, lines, cursor })
+}
+
+/// Helper: Finds the function declared at a byte (first non-blank of a line)
+///
+/// Returns: (declaration statement, function node)
+fn function_at(root: Node, byte: usize) -> Option<(Node, Node)> {
+    let mut node = root.descendant_for_byte_range(byte, byte)?;
+    loop {
+        if node.start_byte() != byte {
+            return None;
+        }
+        if FUNCTION_KINDS.contains(&node.kind()) {
+            return Some((node, node));
+        }
+        // export function f() / const f = () => ...
+        let inner = match node.kind() {
+            "export_statement" => node.child_by_field_name("declaration"),
+            "lexical_declaration" | "variable_declaration" => node
+                .named_child(0)
+                .and_then(|declarator| declarator.child_by_field_name("value"))
+                .filter(|value| FUNCTION_VALUE_KINDS.contains(&value.kind())),
+            _ => None,
+        };
+        if let Some(inner) = inner {
+            return match function_at(inner, inner.start_byte()) {
+                Some((_, function)) => Some((node, function)),
+                None => Some((node, inner)),
+            };
+        }
+        node = node.parent()?;
+    }
+}
+
+/// Helper: Gets the parameter names of a function
+fn parameter_names(function: Node, rope: &Rope, language: &LanguageId) -> Vec<String> {
+    let text = |node: Node| rope.byte_slice(node.byte_range()).to_string();
+
+    // Arrow functions with one unparenthesized parameter
+    if let Some(parameter) = function.child_by_field_name("parameter") {
+        return vec![text(parameter)];
+    }
+    let Some(parameters) = function.child_by_field_name("parameters") else {
+        return Vec::new();
+    };
+
+    let mut names = Vec::new();
+    let mut cursor = parameters.walk();
+    for parameter in parameters.named_children(&mut cursor) {
+        if matches!(parameter.kind(), "self_parameter" | "comment") {
+            continue;
+        }
+        let mut name_cursor = parameter.walk();
+        let fields: Vec<Node> = parameter.children_by_field_name("name", &mut name_cursor).collect();
+        if fields.is_empty() {
+            let name = parameter
+                .child_by_field_name("pattern")
+                .or(Some(parameter))
+                .and_then(first_identifier);
+            names.extend(name.map(text));
+        } else {
+            names.extend(fields.into_iter().map(text));
+        }
+    }
+
+    if *language == LanguageId::Python {
+        if let Some(first) = names.first() {
+            if first == "self" || first == "cls" {
+                names.remove(0);
+            }
+        }
+    }
+    names
+}
+
+/// Helper: Finds the first identifier in a subtree
+fn first_identifier(node: Node) -> Option<Node> {
+    if node.kind().ends_with("identifier") {
+        return Some(node);
+    }
+    let mut cursor = node.walk();
+    let children: Vec<Node> = node.named_children(&mut cursor).collect();
+    children.into_iter().find_map(first_identifier)
+}
+
+/// Helper: Checks if a body statement is a docstring
+fn is_docstring(statement: Option<Node>) -> bool {
+    statement.is_some_and(|statement| {
+        statement.kind() == "expression_statement" && statement.named_child(0).is_some_and(|n| n.kind() == "string")
+    })
+}
+
+// ==================================================================
+// Editor integration
+// ==================================================================
+
+/// Helper: Checks if a byte is between JSX elements (not in a `{}` expression)
+fn in_jsx(tree: &Tree, byte: usize) -> bool {
+    let Some(mut node) = tree.root_node().descendant_for_byte_range(byte, byte) else {
+        return false;
+    };
+    loop {
+        if node.kind() == "jsx_expression" && node.start_byte() < byte {
+            return false;
+        }
+        let parent_is_element = node
+            .parent()
+            .is_some_and(|parent| matches!(parent.kind(), "jsx_element" | "jsx_fragment"));
+        if parent_is_element
+            && matches!(
+                node.kind(),
+                "jsx_text" | "jsx_element" | "jsx_self_closing_element" | "jsx_expression"
+            )
+        {
+            return true;
+        }
+        match node.parent() {
+            Some(parent) => node = parent,
+            None => return false,
+        }
+    }
+}
+
+impl Editor {
+    /// Toggles a block comment around a selection
+    ///
+    /// The comment syntax follows the language at the start of the
+    /// selection (see `toggle_line_comments`). An empty selection
+    /// comments the text of its line. Recorded as one undo step.
+    ///
+    /// Parameters:
+    /// - `start`: Selection start
+    /// - `end`: Selection end
+    ///
+    /// Returns: Some(true) if commented, Some(false) if uncommented, None
+    /// if the language has no block comments or the selection holds a
+    /// comment that would end the new one early
+    pub fn toggle_block_comment(&mut self, start: Position, end: Position) -> Result<Option<bool>> {
+        self.ensure_writable()?;
+        let start = Position::clamp(&start, &self.rope);
+        let end = Position::clamp(&end, &self.rope);
+        let (start, end) = if end < start { (end, start) } else { (start, end) };
+        let (start, end) = if start == end {
+            let (content_start, content_end) = line_content(&self.rope, start.line);
+            let line_start = self.rope.line_to_char(start.line);
+            (
+                Position::new(start.line, content_start - line_start),
+                Position::new(start.line, content_end - line_start),
+            )
+        } else {
+            (start, end)
+        };
+
+        let line = self.rope.line(start.line);
+        let byte = self.rope.line_to_byte(start.line) + line.char_to_byte(start.column.min(line.len_chars()));
+        let Some(config) = self.comment_config_at(byte) else {
+            return Ok(None);
+        };
+
+        let mut scratch = self.rope.clone();
+        let Some(commented) = toggle_block_comment(&mut scratch, start, end, &config) else {
+            return Ok(None);
+        };
+        self.replace_lines_from(&scratch, start.line, end.line);
+        Ok(Some(commented))
+    }
+
+    /// Inserts a doc-comment stub for the function declared on a line
+    ///
+    /// The stub lists the parameters (see `doc_comment_stub`) and the
+    /// cursor moves to its summary. Recorded as one undo step.
+    ///
+    /// Parameters:
+    /// - `line`: Line the declaration starts on
+    ///
+    /// Returns: New cursor position, or None if no undocumented function
+    /// starts on the line
+    pub fn insert_doc_comment(&mut self, line: usize) -> Result<Option<Position>> {
+        self.ensure_writable()?;
+        if line >= self.rope.len_lines() {
+            return Ok(None);
+        }
+        let indent = leading_whitespace(&self.rope, line);
+        let byte = self.rope.line_to_byte(line) + self.rope.line(line).char_to_byte(indent);
+        let language = self.language_at_byte(byte);
+        let tree = match self.injections.layer_at(byte) {
+            Some(layer) => Some(&layer.tree),
+            None => self.syntax_tree.as_ref(),
+        };
+        let Some(stub) = tree.and_then(|tree| doc_comment_stub(tree, &self.rope, &language, line)) else {
+            return Ok(None);
+        };
+
+        let indent = self
+            .rope
+            .line(stub.line)
+            .chars()
+            .take_while(|c| *c == ' ' || *c == '\t')
+            .collect::<String>();
+        let text: String = stub.lines.iter().map(|l| format!("{}{}\n", indent, l)).collect();
+        let at = self.rope.line_to_byte(stub.line);
+        self.replace_bytes(at, at, &text);
+
+        let (row, column) = stub.cursor;
+        self.cursor = Position::new(stub.line + row, indent.chars().count() + column);
+        Ok(Some(self.cursor))
+    }
+
+    /// Helper: Gets the comment syntax at a byte
+    ///
+    /// Uses the innermost (injected) language, and JSX comments between
+    /// JSX elements.
+    pub(super) fn comment_config_at(&self, byte: usize) -> Option<CommentConfig> {
+        let language = self.language_at_byte(byte);
+        if language == LanguageId::JavaScript {
+            let tree = match self.injections.layer_at(byte) {
+                Some(layer) => Some(&layer.tree),
+                None => self.syntax_tree.as_ref(),
+            };
+            if tree.is_some_and(|tree| in_jsx(tree, byte)) {
+                return Some(CommentConfig::jsx());
+            }
+        }
+        CommentConfig::for_language(&language)
+    }
+
+    /// Helper: Replaces a range of lines with the same lines of an edited copy
+    ///
+    /// Only the lines may differ in length, not in number.
+    pub(super) fn replace_lines_from(&mut self, edited: &Rope, start_line: usize, end_line: usize) {
+        let line_end_byte = |rope: &Rope| {
+            if end_line + 1 < rope.len_lines() {
+                rope.line_to_byte(end_line + 1)
+            } else {
+                rope.len_bytes()
+            }
+        };
+        let start = self.rope.line_to_byte(start_line);
+        let old_end = line_end_byte(&self.rope);
+        let text = edited.byte_slice(start..line_end_byte(edited)).to_string();
+
+        self.replace_bytes(start, old_end, &text);
+        self.cursor = Position::clamp(&self.cursor, &self.rope);
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
+    use std::time::Duration;
+
+    #[test]
+    fn test_comment_config_for_language() {
+        assert_eq!(CommentConfig::for_language(&LanguageId::Python).unwrap().line_comment.as_deref(), Some("#"));
+        assert_eq!(CommentConfig::for_language(&LanguageId::Go).unwrap().line_comment.as_deref(), Some("//"));
+        assert!(CommentConfig::for_language(&LanguageId::Markdown).is_none());
+    }
 
     #[test]
     fn test_comment_config_rust() {
         let config = CommentConfig::rust();
-        assert_eq!(config.line_comment, "//");
+        assert_eq!(config.line_comment.as_deref(), Some("//"));
         assert_eq!(config.block_comment_start, Some("/*".to_string()));
         assert_eq!(config.block_comment_end, Some("*/".to_string()));
     }
@@ -322,7 +861,7 @@ mod tests {
     #[test]
     fn test_comment_config_python() {
         let config = CommentConfig::python();
-        assert_eq!(config.line_comment, "#");
+        assert_eq!(config.line_comment.as_deref(), Some("#"));
     }
 
     #[test]
@@ -424,7 +963,7 @@ mod tests {
             Position::new(0, 5),
             &config,
         );
-        assert!(added);
+        assert_eq!(added, Some(true));
         assert_eq!(rope.to_string(), "/*hello*/");
     }
 
@@ -439,7 +978,136 @@ mod tests {
             Position::new(0, 9),
             &config,
         );
-        assert!(!added);
+        assert_eq!(added, Some(false));
         assert_eq!(rope.to_string(), "hello");
     }
+
+    #[test]
+    fn test_mixed_selection_aligns_and_skips_blank_lines() {
+        let mut rope = Rope::from_str("    a\n\n  // b\n      c\n");
+        let config = CommentConfig::rust();
+
+        assert!(toggle_line_comments(&mut rope, 0, 3, &config));
+        assert_eq!(rope.to_string(), "  //   a\n\n  // // b\n  //     c\n");
+        assert!(!toggle_line_comments(&mut rope, 0, 3, &config));
+        assert_eq!(rope.to_string(), "    a\n\n  // b\n      c\n");
+
+        // Markers inside code are not comments
+        let mut rope = Rope::from_str("let url = \"http://x\";");
+        uncomment_lines(&mut rope, 0, 0, &config);
+        assert_eq!(rope.to_string(), "let url = \"http://x\";");
+    }
+
+    #[test]
+    fn test_block_only_line_toggle() {
+        let mut rope = Rope::from_str("  a { color: red; }\n  b {} /* note */\n");
+        let config = CommentConfig::css();
+
+        assert!(toggle_line_comments(&mut rope, 0, 1, &config));
+        // The second line would close the comment early
+        assert_eq!(rope.to_string(), "  /* a { color: red; } */\n  b {} /* note */\n");
+
+        let mut rope = Rope::from_str("<p>hi</p>\n");
+        let config = CommentConfig::html();
+        assert!(toggle_line_comments(&mut rope, 0, 0, &config));
+        assert_eq!(rope.to_string(), "<!-- <p>hi</p> -->\n");
+        assert!(!toggle_line_comments(&mut rope, 0, 0, &config));
+        assert_eq!(rope.to_string(), "<p>hi</p>\n");
+    }
+
+    #[test]
+    fn test_nested_block_comments() {
+        let text = "/* a */ b /* c */";
+        let end = Position::new(0, text.len());
+
+        // Rust comments nest, so the selection can be wrapped and unwrapped
+        let mut rope = Rope::from_str(text);
+        let rust = CommentConfig::rust();
+        assert_eq!(toggle_block_comment(&mut rope, Position::new(0, 0), end, &rust), Some(true));
+        assert_eq!(rope.to_string(), "/*/* a */ b /* c */*/");
+        let end = Position::new(0, rope.len_chars());
+        assert_eq!(toggle_block_comment(&mut rope, Position::new(0, 0), end, &rust), Some(false));
+        assert_eq!(rope.to_string(), text);
+
+        // JavaScript comments do not
+        let end = Position::new(0, text.len());
+        let javascript = CommentConfig::javascript();
+        assert_eq!(toggle_block_comment(&mut rope, Position::new(0, 0), end, &javascript), None);
+        assert_eq!(rope.to_string(), text);
+
+        let mut rope = Rope::from_str("  /* padded */\n");
+        let end = Position::new(1, 0);
+        assert_eq!(toggle_block_comment(&mut rope, Position::new(0, 0), end, &javascript), Some(false));
+        assert_eq!(rope.to_string(), "  padded\n");
+    }
+
+    #[test]
+    fn test_doc_comment_stubs() {
+        let stub_for = |source: &str, language: LanguageId, line: usize| {
+            let mut parser = tree_sitter::Parser::new();
+            parser.set_language(language.tree_sitter_language().unwrap()).unwrap();
+            let tree = parser.parse(source, None).unwrap();
+            doc_comment_stub(&tree, &Rope::from_str(source), &language, line)
+        };
+
+        let stub = stub_for("#[inline]\npub fn add(&self, a: i32, mut b: i32) -> i32 { a + b }\n", LanguageId::Rust, 1).unwrap();
+        assert_eq!(stub.line, 0);
+        assert_eq!(stub.lines, ["/// ", "///", "/// # Arguments", "///", "/// * `a` - ", "/// * `b` - "]);
+        assert_eq!(stub.cursor, (0, 4));
+        assert!(stub_for("/// Adds.\nfn add() {}\n", LanguageId::Rust, 1).is_none());
+        assert!(stub_for("let x = 1;\n", LanguageId::Rust, 0).is_none());
+
+        let stub = stub_for("export const f = (a, b = 2) => a + b;\n", LanguageId::JavaScript, 0).unwrap();
+        assert_eq!(stub.lines, ["/**", " * ", " * @param a ", " * @param b ", " */"]);
+
+        let stub = stub_for("function g(x: number): string { return ''; }\n", LanguageId::TypeScript, 0).unwrap();
+        assert_eq!(stub.lines, ["/**", " * ", " * @param x ", " * @returns ", " */"]);
+
+        let stub = stub_for("class A {\n  void run(int n) {}\n}\n", LanguageId::Java, 1).unwrap();
+        assert_eq!(stub.lines, ["/**", " * ", " * @param n ", " */"]);
+
+        let stub = stub_for("def f(self, x, y=1) -> int:\n    return x\n", LanguageId::Python, 0).unwrap();
+        assert_eq!(stub.line, 1);
+        assert_eq!(stub.lines, ["\"\"\"", "", "Args:", "    x: ", "    y: ", "Returns:", "    ", "\"\"\""]);
+        assert!(stub_for("def f():\n    \"\"\"Doc.\"\"\"\n", LanguageId::Python, 0).is_none());
+
+        let stub = stub_for("package main\n\nfunc Run(a, b int) {}\n", LanguageId::Go, 2).unwrap();
+        assert_eq!(stub.lines, ["// Run "]);
+        assert_eq!(stub.cursor, (0, 7));
+    }
+
+    #[test]
+    fn test_editor_comment_toggling() {
+        let jsx = "const a = (\n  <div>\n    <span>hi</span>\n    {items.map(i =>\n      f(i))}\n  </div>\n);\n";
+        let mut editor = Editor::with_content(jsx, LanguageId::JavaScript).unwrap();
+
+        // JSX children get JSX comments, code in braces and the root element JS ones
+        assert_eq!(editor.toggle_line_comments(2, 2).unwrap(), Some(true));
+        assert_eq!(editor.line(2).unwrap(), "    {/* <span>hi</span> */}\n");
+        editor.wait_for_parse(Duration::from_secs(5));
+        assert_eq!(editor.toggle_line_comments(4, 4).unwrap(), Some(true));
+        assert_eq!(editor.line(4).unwrap(), "      // f(i))}\n");
+        editor.wait_for_parse(Duration::from_secs(5));
+        assert_eq!(editor.toggle_line_comments(1, 1).unwrap(), Some(true));
+        assert_eq!(editor.line(1).unwrap(), "  // <div>\n");
+
+        let mut editor = Editor::with_content("body { color: red; }\n", LanguageId::Css).unwrap();
+        assert_eq!(editor.toggle_line_comments(0, 0).unwrap(), Some(true));
+        assert_eq!(editor.line(0).unwrap(), "/* body { color: red; } */\n");
+        assert_eq!(editor.toggle_block_comment(Position::new(0, 3), Position::new(0, 3)).unwrap(), Some(false));
+        assert_eq!(editor.line(0).unwrap(), "body { color: red; }\n");
+
+        let mut editor = Editor::with_content("fn main() {\n    run(1);\n}\n", LanguageId::Rust).unwrap();
+        assert_eq!(
+            editor.toggle_block_comment(Position::new(1, 8), Position::new(1, 9)).unwrap(),
+            Some(true)
+        );
+        assert_eq!(editor.line(1).unwrap(), "    run(/*1*/);\n");
+        editor.undo().unwrap();
+        assert_eq!(editor.line(1).unwrap(), "    run(1);\n");
+
+        assert_eq!(editor.insert_doc_comment(0).unwrap(), Some(Position::new(0, 4)));
+        assert_eq!(editor.line(0).unwrap(), "/// \n");
+        assert_eq!(editor.insert_doc_comment(1).unwrap(), None);
+    }
 }
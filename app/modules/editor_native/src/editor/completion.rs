@@ -0,0 +1,771 @@
+use std::collections::HashMap;
+use std::ops::Range;
+
+use ropey::Rope;
+use serde::Serialize;
+use tree_sitter::{Node, Tree};
+
+use crate::editor::cursor::Position;
+use crate::editor::lsp_types::LspRange;
+
+/// Minimum length of an indexed word.
+const MIN_WORD_LEN: usize = 2;
+
+/// Maximum number of items returned in a completion list.
+const MAX_COMPLETION_ITEMS: usize = 50;
+
+/// Completion item kind (numeric values match LSP `CompletionItemKind`).
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum CompletionItemKind {
+    Text = 1,
+    Method = 2,
+    Function = 3,
+    Field = 5,
+    Variable = 6,
+    Class = 7,
+    Module = 9,
+    Property = 10,
+    Constant = 21,
+}
+
+impl Serialize for CompletionItemKind {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_u8(*self as u8)
+    }
+}
+
+/// LSP-shaped text edit replacing the typed prefix.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CompletionTextEdit {
+    pub range: LspRange,
+    pub new_text: String,
+}
+
+/// LSP-shaped completion item.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CompletionItem {
+    pub label: String,
+    pub kind: CompletionItemKind,
+    /// Where the candidate comes from ("syntax" or "buffer")
+    pub detail: String,
+    pub sort_text: String,
+    pub filter_text: String,
+    pub text_edit: CompletionTextEdit,
+    /// Ranking score (higher is better)
+    #[serde(skip)]
+    pub score: f64,
+}
+
+/// LSP-shaped completion list.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CompletionList {
+    pub is_incomplete: bool,
+    pub items: Vec<CompletionItem>,
+}
+
+/// Word indexed on a line.
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct IndexedWord {
+    text: String,
+    /// Kind derived from the syntax tree (None for plain buffer words)
+    kind: Option<CompletionItemKind>,
+}
+
+/// Occurrences of a distinct word.
+#[derive(Debug, Clone, Default)]
+struct WordEntry {
+    /// Line of every occurrence, sorted (repeated for several per line)
+    lines: Vec<usize>,
+    /// Classified occurrences per syntax kind
+    kinds: HashMap<CompletionItemKind, usize>,
+}
+
+impl WordEntry {
+    fn add(&mut self, line: usize, kind: Option<CompletionItemKind>) {
+        let at = self.lines.partition_point(|&l| l < line);
+        self.lines.insert(at, line);
+        if let Some(kind) = kind {
+            *self.kinds.entry(kind).or_insert(0) += 1;
+        }
+    }
+
+    fn remove(&mut self, line: usize, kind: Option<CompletionItemKind>) {
+        if let Ok(at) = self.lines.binary_search(&line) {
+            self.lines.remove(at);
+        }
+        if let Some(kind) = kind {
+            self.remove_kind(kind);
+        }
+    }
+
+    fn remove_kind(&mut self, kind: CompletionItemKind) {
+        if let Some(count) = self.kinds.get_mut(&kind) {
+            *count -= 1;
+            if *count == 0 {
+                self.kinds.remove(&kind);
+            }
+        }
+    }
+
+    /// Most telling kind: declarations and fields over plain variables,
+    /// then the most frequent
+    fn kind(&self) -> Option<CompletionItemKind> {
+        self.kinds
+            .iter()
+            .max_by_key(|(kind, count)| {
+                (**kind != CompletionItemKind::Variable, **count, std::cmp::Reverse(**kind as u8))
+            })
+            .map(|(kind, _)| *kind)
+    }
+
+    /// Distance from `line` to the closest occurrence, skipping one on
+    /// `line` itself when `skip_one` is set (the word being typed)
+    fn distance(&self, line: usize, skip_one: bool) -> Option<usize> {
+        let at = self.lines.partition_point(|&l| l < line);
+        let skip = usize::from(skip_one && self.lines.get(at) == Some(&line));
+        let below = self.lines.get(at + skip).map(|l| l - line);
+        let above = at.checked_sub(1).map(|i| line - self.lines[i]);
+        below.into_iter().chain(above).min()
+    }
+}
+
+/// Per-buffer completion index.
+///
+/// Keeps the identifiers of every line so edits only re-tokenize the
+/// lines they touch:
+/// - Buffer words are spliced in on every edit
+/// - Syntax kinds (function, type, field...) are refreshed for the touched
+///   lines after the next reparse
+///
+/// Each distinct word keeps its occurrence lines and kinds, so a
+/// completion request never rescans the buffer.
+///
+/// Example:
+/// ```text
+/// let index = CompletionIndex::from_rope(&rope, tree.as_ref());
+/// let list = complete(&rope, &index, cursor, &[]);
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct CompletionIndex {
+    lines: Vec<Vec<IndexedWord>>,
+    words: HashMap<String, WordEntry>,
+    /// Lines whose syntax kinds are stale
+    pending_syntax: Option<Range<usize>>,
+}
+
+impl CompletionIndex {
+    /// Creates an empty index.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Builds an index for a whole document.
+    pub fn from_rope(rope: &Rope, tree: Option<&Tree>) -> Self {
+        let mut index = Self::new();
+        index.rebuild(rope, tree);
+        index
+    }
+
+    /// Re-indexes the whole document.
+    pub fn rebuild(&mut self, rope: &Rope, tree: Option<&Tree>) {
+        self.lines = (0..rope.len_lines())
+            .map(|line| tokenize_line(&rope.line(line).to_string()))
+            .collect();
+        self.words.clear();
+        for (line, words) in self.lines.iter().enumerate() {
+            for word in words {
+                // Lines are visited in order, so pushing keeps them sorted
+                self.words.entry(word.text.clone()).or_default().lines.push(line);
+            }
+        }
+
+        self.pending_syntax = None;
+        if let Some(tree) = tree {
+            self.refresh_syntax_lines(rope, tree, 0..self.lines.len());
+        }
+    }
+
+    /// Updates the index after an edit.
+    ///
+    /// Parameters:
+    /// - `rope`: Document after the edit
+    /// - `start_line`: First line touched by the edit
+    /// - `old_end_line`: Last touched line before the edit (inclusive)
+    /// - `new_end_line`: Last touched line after the edit (inclusive)
+    pub fn apply_edit(
+        &mut self,
+        rope: &Rope,
+        start_line: usize,
+        old_end_line: usize,
+        new_end_line: usize,
+    ) {
+        let old_end = (old_end_line + 1).min(self.lines.len());
+        let start_line = start_line.min(old_end);
+
+        let new_lines: Vec<Vec<IndexedWord>> = (start_line..=new_end_line)
+            .filter(|&line| line < rope.len_lines())
+            .map(|line| tokenize_line(&rope.line(line).to_string()))
+            .collect();
+
+        for (line, words) in (start_line..old_end).zip(&self.lines[start_line..old_end]) {
+            for word in words {
+                if let Some(entry) = self.words.get_mut(&word.text) {
+                    entry.remove(line, word.kind);
+                    if entry.lines.is_empty() {
+                        self.words.remove(&word.text);
+                    }
+                }
+            }
+        }
+
+        // Occurrences after the edit move with their lines
+        let line_delta = new_lines.len() as isize - (old_end - start_line) as isize;
+        if line_delta != 0 {
+            for entry in self.words.values_mut() {
+                let first = entry.lines.partition_point(|&l| l < old_end);
+                for line in &mut entry.lines[first..] {
+                    *line = line.saturating_add_signed(line_delta);
+                }
+            }
+        }
+
+        for (line, words) in (start_line..).zip(&new_lines) {
+            for word in words {
+                self.words.entry(word.text.clone()).or_default().add(line, None);
+            }
+        }
+        self.lines.splice(start_line..old_end, new_lines);
+
+        // Track lines needing a syntax refresh, shifting the pending range
+        let delta = new_end_line as isize - old_end_line as isize;
+        let touched = start_line..new_end_line + 1;
+        self.pending_syntax = Some(match self.pending_syntax.take() {
+            None => touched,
+            Some(pending) => {
+                let shift = |line: usize| {
+                    if line > old_end_line {
+                        (line as isize + delta).max(0) as usize
+                    } else {
+                        line
+                    }
+                };
+                let start = shift(pending.start).min(touched.start);
+                let end = shift(pending.end).max(touched.end);
+                start..end
+            }
+        });
+    }
+
+    /// Refreshes syntax kinds for lines touched since the last refresh.
+    pub fn refresh_syntax(&mut self, rope: &Rope, tree: &Tree) {
+        if let Some(range) = self.pending_syntax.take() {
+            self.refresh_syntax_lines(rope, tree, range);
+        }
+    }
+
+    /// Number of occurrences of a word in the buffer.
+    pub fn frequency(&self, word: &str) -> usize {
+        self.words.get(word).map_or(0, |entry| entry.lines.len())
+    }
+
+    /// Number of distinct indexed words.
+    pub fn word_count(&self) -> usize {
+        self.words.len()
+    }
+
+    /// Syntax kind of a word, if any occurrence was classified.
+    pub fn kind_of(&self, word: &str) -> Option<CompletionItemKind> {
+        self.words.get(word).and_then(WordEntry::kind)
+    }
+
+    /// Re-classifies identifiers on a line range from the syntax tree.
+    fn refresh_syntax_lines(&mut self, rope: &Rope, tree: &Tree, range: Range<usize>) {
+        let end = range.end.min(self.lines.len());
+        if range.start >= end {
+            return;
+        }
+
+        // Reset kinds, then fill them from identifier nodes in range
+        for words in &mut self.lines[range.start..end] {
+            for word in words.iter_mut() {
+                if let Some(kind) = word.kind.take() {
+                    if let Some(entry) = self.words.get_mut(&word.text) {
+                        entry.remove_kind(kind);
+                    }
+                }
+            }
+        }
+
+        let mut classified = Vec::new();
+        collect_identifiers(tree.root_node(), rope, range.start..end, &mut classified);
+
+        for (line, text, kind) in classified {
+            let Some(words) = self.lines.get_mut(line) else {
+                continue;
+            };
+            if let Some(word) = words.iter_mut().find(|w| w.text == text && w.kind.is_none()) {
+                word.kind = Some(kind);
+                if let Some(entry) = self.words.get_mut(&word.text) {
+                    *entry.kinds.entry(kind).or_insert(0) += 1;
+                }
+            }
+        }
+    }
+}
+
+/// Computes local completions at a position.
+///
+/// Candidates come from the buffer's index plus the indexes of other open
+/// buffers. They are ranked by:
+/// - Fuzzy match score against the typed prefix
+/// - Proximity to the cursor (current buffer only)
+/// - Frequency across all buffers
+///
+/// Parameters:
+/// - `rope`: Current document
+/// - `index`: Index of the current document
+/// - `position`: Cursor position
+/// - `others`: Indexes of other open buffers
+///
+/// Returns: LSP-shaped completion list (empty if no prefix is typed)
+pub fn complete(
+    rope: &Rope,
+    index: &CompletionIndex,
+    position: Position,
+    others: &[&CompletionIndex],
+) -> CompletionList {
+    let position = Position::clamp(&position, rope);
+    let line_text: Vec<char> = rope.line(position.line).chars().collect();
+    let prefix_start = word_start(&line_text, position.column);
+    let prefix: String = line_text[prefix_start..position.column].iter().collect();
+
+    if prefix.is_empty() {
+        return CompletionList {
+            is_incomplete: false,
+            items: Vec::new(),
+        };
+    }
+
+    // The word under the cursor (prefix plus trailing word chars) is excluded once
+    let word_end = line_text[position.column..]
+        .iter()
+        .take_while(|&&c| is_word_char(c))
+        .count()
+        + position.column;
+    let current_word: String = line_text[prefix_start..word_end].iter().collect();
+
+    // Fuzzy matching comes first: only matching words are looked up
+    let mut candidates: HashMap<&str, (f64, usize, Option<CompletionItemKind>)> = HashMap::new();
+    for idx in std::iter::once(index).chain(others.iter().copied()) {
+        for (word, entry) in &idx.words {
+            match candidates.get_mut(word.as_str()) {
+                Some((_, frequency, kind)) => {
+                    *frequency += entry.lines.len();
+                    if kind.is_none() {
+                        *kind = entry.kind();
+                    }
+                }
+                None => {
+                    if let Some(fuzzy) = fuzzy_score(&prefix, word) {
+                        candidates.insert(word, (fuzzy, entry.lines.len(), entry.kind()));
+                    }
+                }
+            }
+        }
+    }
+
+    let replace_range = LspRange {
+        start: Position::new(position.line, prefix_start).into(),
+        end: position.into(),
+    };
+
+    let mut items: Vec<CompletionItem> = candidates
+        .into_iter()
+        .filter_map(|(word, (fuzzy, mut frequency, kind))| {
+            let is_current = word == current_word;
+            if is_current {
+                frequency = frequency.saturating_sub(1);
+                if frequency == 0 {
+                    return None;
+                }
+            }
+
+            // Proximity counts in the current buffer only
+            let proximity = index
+                .words
+                .get(word)
+                .and_then(|entry| entry.distance(position.line, is_current))
+                .map(|distance| 1.0 / (1.0 + distance as f64))
+                .unwrap_or(0.0);
+
+            let score = fuzzy + 10.0 * proximity + (frequency as f64).ln_1p();
+
+            Some(CompletionItem {
+                label: word.to_string(),
+                kind: kind.unwrap_or(CompletionItemKind::Text),
+                detail: if kind.is_some() { "syntax" } else { "buffer" }.to_string(),
+                sort_text: String::new(),
+                filter_text: word.to_string(),
+                text_edit: CompletionTextEdit {
+                    range: replace_range,
+                    new_text: word.to_string(),
+                },
+                score,
+            })
+        })
+        .collect();
+
+    items.sort_by(|a, b| {
+        b.score
+            .partial_cmp(&a.score)
+            .unwrap_or(std::cmp::Ordering::Equal)
+            .then_with(|| a.label.cmp(&b.label))
+    });
+
+    let is_incomplete = items.len() > MAX_COMPLETION_ITEMS;
+    items.truncate(MAX_COMPLETION_ITEMS);
+    for (rank, item) in items.iter_mut().enumerate() {
+        item.sort_text = format!("{:04}", rank);
+    }
+
+    CompletionList { is_incomplete, items }
+}
+
+/// Fuzzy-matches a pattern against a candidate.
+///
+/// The pattern must be a (case-insensitive) subsequence of the candidate.
+/// Bonuses are given for:
+/// - Matching at the start of the candidate
+/// - Matching at word boundaries (`camelCase`, `snake_case`)
+/// - Consecutive matches
+/// - Exact case
+///
+/// Returns: Score, or None if the pattern does not match
+pub fn fuzzy_score(pattern: &str, candidate: &str) -> Option<f64> {
+    let pattern: Vec<char> = pattern.chars().collect();
+    let candidate_chars: Vec<char> = candidate.chars().collect();
+
+    if pattern.is_empty() || pattern.len() > candidate_chars.len() {
+        return None;
+    }
+
+    let mut score = 0.0;
+    let mut pattern_idx = 0;
+    let mut previous_match: Option<usize> = None;
+
+    for (i, &c) in candidate_chars.iter().enumerate() {
+        if pattern_idx == pattern.len() {
+            break;
+        }
+
+        let p = pattern[pattern_idx];
+        if !c.to_lowercase().eq(p.to_lowercase()) {
+            continue;
+        }
+
+        score += 1.0;
+        if c == p {
+            score += 0.5;
+        }
+        if i == 0 {
+            score += 3.0;
+        } else if is_boundary(&candidate_chars, i) {
+            score += 2.0;
+        }
+        if previous_match == Some(i.wrapping_sub(1)) {
+            score += 1.5;
+        }
+
+        previous_match = Some(i);
+        pattern_idx += 1;
+    }
+
+    if pattern_idx < pattern.len() {
+        return None;
+    }
+
+    // Prefer shorter candidates among equal matches
+    Some(score - 0.05 * (candidate_chars.len() - pattern.len()) as f64)
+}
+
+/// Helper: Checks if a char index starts a sub-word.
+fn is_boundary(chars: &[char], i: usize) -> bool {
+    let prev = chars[i - 1];
+    let cur = chars[i];
+    (prev == '_' && cur != '_') || (prev.is_lowercase() && cur.is_uppercase())
+}
+
+/// Helper: Checks if a character belongs to an identifier.
+fn is_word_char(c: char) -> bool {
+    c.is_alphanumeric() || c == '_'
+}
+
+/// Helper: Finds the start of the word ending at `column`.
+fn word_start(line: &[char], column: usize) -> usize {
+    let mut start = column.min(line.len());
+    while start > 0 && is_word_char(line[start - 1]) {
+        start -= 1;
+    }
+    start
+}
+
+/// Helper: Splits a line into identifier-like words.
+fn tokenize_line(line: &str) -> Vec<IndexedWord> {
+    let mut words = Vec::new();
+    let mut current = String::new();
+
+    for c in line.chars().chain(std::iter::once(' ')) {
+        if is_word_char(c) {
+            current.push(c);
+            continue;
+        }
+
+        if current.chars().count() >= MIN_WORD_LEN
+            && !current.starts_with(|ch: char| ch.is_ascii_digit())
+        {
+            words.push(IndexedWord {
+                text: std::mem::take(&mut current),
+                kind: None,
+            });
+        } else {
+            current.clear();
+        }
+    }
+
+    words
+}
+
+/// Helper: Collects classified identifier nodes on a line range.
+fn collect_identifiers(
+    node: Node,
+    rope: &Rope,
+    lines: Range<usize>,
+    out: &mut Vec<(usize, String, CompletionItemKind)>,
+) {
+    if node.end_position().row < lines.start || node.start_position().row >= lines.end {
+        return;
+    }
+
+    if node.child_count() == 0 {
+        if let Some(kind) = classify_identifier(node) {
+            if let Some(text) = rope.get_byte_slice(node.byte_range()) {
+                out.push((node.start_position().row, text.to_string(), kind));
+            }
+        }
+        return;
+    }
+
+    let mut cursor = node.walk();
+    for child in node.children(&mut cursor) {
+        collect_identifiers(child, rope, lines.clone(), out);
+    }
+}
+
+/// Helper: Maps an identifier node to a completion kind.
+fn classify_identifier(node: Node) -> Option<CompletionItemKind> {
+    let kind = node.kind();
+    if !kind.contains("identifier") {
+        return None;
+    }
+
+    let parent_kind = node.parent().map(|p| p.kind()).unwrap_or("");
+    let is_declared_name = node
+        .parent()
+        .and_then(|p| p.child_by_field_name("name"))
+        .is_some_and(|name| name.id() == node.id());
+
+    if is_declared_name {
+        if parent_kind.contains("method") {
+            return Some(CompletionItemKind::Method);
+        }
+        if parent_kind.contains("function") {
+            return Some(CompletionItemKind::Function);
+        }
+        if parent_kind.contains("class")
+            || parent_kind.contains("struct")
+            || parent_kind.contains("enum")
+            || parent_kind.contains("interface")
+            || parent_kind.contains("trait")
+        {
+            return Some(CompletionItemKind::Class);
+        }
+        if parent_kind.contains("mod") {
+            return Some(CompletionItemKind::Module);
+        }
+        if parent_kind.contains("const") || parent_kind.contains("static") {
+            return Some(CompletionItemKind::Constant);
+        }
+    }
+
+    Some(match kind {
+        "type_identifier" => CompletionItemKind::Class,
+        "field_identifier" => CompletionItemKind::Field,
+        "property_identifier" | "shorthand_property_identifier" => CompletionItemKind::Property,
+        _ => CompletionItemKind::Variable,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tree_sitter::Parser;
+
+    fn parse_rust(source: &str) -> Tree {
+        let mut parser = Parser::new();
+        parser.set_language(tree_sitter_rust::language()).unwrap();
+        parser.parse(source, None).unwrap()
+    }
+
+    #[test]
+    fn test_tokenize_line() {
+        let words: Vec<String> = tokenize_line("let foo_bar = x + 42 + baz2;\n")
+            .into_iter()
+            .map(|w| w.text)
+            .collect();
+        assert_eq!(words, vec!["let", "foo_bar", "baz2"]);
+    }
+
+    #[test]
+    fn test_fuzzy_score() {
+        assert!(fuzzy_score("fb", "foo_bar").is_some());
+        assert!(fuzzy_score("fb", "bar").is_none());
+
+        // Prefix and boundary matches rank higher
+        let prefix = fuzzy_score("get", "getValue").unwrap();
+        let inner = fuzzy_score("get", "budget").unwrap();
+        assert!(prefix > inner);
+
+        let camel = fuzzy_score("gv", "getValue").unwrap();
+        let plain = fuzzy_score("gv", "gravy").unwrap();
+        assert!(camel > plain);
+    }
+
+    #[test]
+    fn test_complete_prefix() {
+        let rope = Rope::from_str("let counter = 1;\nlet count = 2;\nco");
+        let index = CompletionIndex::from_rope(&rope, None);
+
+        let list = complete(&rope, &index, Position::new(2, 2), &[]);
+        let labels: Vec<&str> = list.items.iter().map(|i| i.label.as_str()).collect();
+
+        assert!(labels.contains(&"counter"));
+        assert!(labels.contains(&"count"));
+        assert!(!labels.contains(&"co")); // The word being typed
+        assert_eq!(list.items[0].text_edit.range.start.character, 0);
+        assert_eq!(list.items[0].sort_text, "0000");
+    }
+
+    #[test]
+    fn test_complete_empty_prefix() {
+        let rope = Rope::from_str("hello world\n");
+        let index = CompletionIndex::from_rope(&rope, None);
+        assert!(complete(&rope, &index, Position::new(1, 0), &[]).items.is_empty());
+    }
+
+    #[test]
+    fn test_complete_proximity_ranking() {
+        let rope = Rope::from_str("value_far\n\n\n\n\n\n\n\n\n\nvalue_near\nval");
+        let index = CompletionIndex::from_rope(&rope, None);
+
+        let list = complete(&rope, &index, Position::new(11, 3), &[]);
+        assert_eq!(list.items[0].label, "value_near");
+    }
+
+    #[test]
+    fn test_complete_other_buffers() {
+        let rope = Rope::from_str("pri");
+        let index = CompletionIndex::from_rope(&rope, None);
+        let other_rope = Rope::from_str("fn print_report() {}");
+        let other = CompletionIndex::from_rope(&other_rope, None);
+
+        let list = complete(&rope, &index, Position::new(0, 3), &[&other]);
+        assert_eq!(list.items[0].label, "print_report");
+    }
+
+    #[test]
+    fn test_syntax_kinds() {
+        let source = "struct Point { x: i32 }\nfn distance(p: Point) -> i32 { p.x }\n";
+        let rope = Rope::from_str(source);
+        let tree = parse_rust(source);
+        let index = CompletionIndex::from_rope(&rope, Some(&tree));
+
+        assert_eq!(index.kind_of("distance"), Some(CompletionItemKind::Function));
+        assert_eq!(index.kind_of("Point"), Some(CompletionItemKind::Class));
+        assert_eq!(index.kind_of("p"), None); // Too short to index
+    }
+
+    #[test]
+    fn test_apply_edit_updates_counts() {
+        let mut rope = Rope::from_str("alpha\nbeta\ngamma");
+        let mut index = CompletionIndex::from_rope(&rope, None);
+        assert_eq!(index.frequency("beta"), 1);
+
+        // Replace line 1 with two lines
+        let start = rope.line_to_char(1);
+        rope.remove(start..start + 4);
+        rope.insert(start, "delta\nalpha");
+        index.apply_edit(&rope, 1, 1, 2);
+
+        assert_eq!(index.frequency("beta"), 0);
+        assert_eq!(index.frequency("delta"), 1);
+        assert_eq!(index.frequency("alpha"), 2);
+        assert_eq!(index.frequency("gamma"), 1);
+
+        // Occurrences after the edit moved down a line
+        assert_eq!(index.words["alpha"].lines, vec![0, 2]);
+        assert_eq!(index.words["gamma"].lines, vec![3]);
+    }
+
+    #[test]
+    fn test_syntax_refresh_keeps_kind_counts() {
+        let source = "fn run() {}
+fn main() { run(); }
+";
+        let mut rope = Rope::from_str(source);
+        let mut index = CompletionIndex::from_rope(&rope, Some(&parse_rust(source)));
+        assert_eq!(index.kind_of("run"), Some(CompletionItemKind::Function));
+
+        // Renaming the declaration leaves only the call
+        rope.remove(3..6);
+        rope.insert(3, "go");
+        index.apply_edit(&rope, 0, 0, 0);
+        index.refresh_syntax(&rope, &parse_rust(&rope.to_string()));
+        assert_eq!(index.kind_of("run"), Some(CompletionItemKind::Variable));
+        assert_eq!(index.kind_of("go"), Some(CompletionItemKind::Function));
+    }
+
+    #[test]
+    fn test_complete_large_buffer() {
+        let source: String = (0..20_000)
+            .map(|i| format!("let value_{} = compute_{}(item_{});\n", i, i % 500, i))
+            .collect();
+        let rope = Rope::from_str(&(source + "val"));
+        let tree = parse_rust(&rope.to_string());
+        let index = CompletionIndex::from_rope(&rope, Some(&tree));
+        let other = index.clone();
+
+        let started = std::time::Instant::now();
+        let list = complete(&rope, &index, Position::new(20_000, 3), &[&other]);
+        assert!(list.is_incomplete);
+        assert_eq!(list.items[0].label, "value_19999");
+        // Rescanning the buffer per candidate took seconds
+        assert!(started.elapsed() < std::time::Duration::from_secs(1), "{:?}", started.elapsed());
+    }
+
+    #[test]
+    fn test_completion_list_serializes_lsp_shape() {
+        let rope = Rope::from_str("foobar\nfo");
+        let index = CompletionIndex::from_rope(&rope, None);
+        let list = complete(&rope, &index, Position::new(1, 2), &[]);
+
+        let json = serde_json::to_value(&list).unwrap();
+        assert_eq!(json["isIncomplete"], false);
+        assert_eq!(json["items"][0]["label"], "foobar");
+        assert_eq!(json["items"][0]["kind"], 1);
+        assert_eq!(json["items"][0]["textEdit"]["newText"], "foobar");
+        assert_eq!(json["items"][0]["textEdit"]["range"]["end"]["character"], 2);
+    }
+}
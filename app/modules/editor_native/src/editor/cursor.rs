@@ -1,10 +1,11 @@
 use ropey::Rope;
+use serde::{Deserialize, Serialize};
 
 /// Cursor position in the editor (0-indexed).
 ///
 /// Represents a position in the editor as (line, column).
 /// Both line and column are 0-indexed.
-#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
 pub struct Position {
     pub line: usize,
     pub column: usize,
@@ -57,17 +58,33 @@ impl Position {
     }
 
     /// Clamps position to valid range in rope.
+    ///
+    /// The column is clamped to the end of the line's content, so it never
+    /// lands after the line break.
     pub fn clamp(&self, rope: &Rope) -> Self {
         let line = self.line.min(rope.len_lines().saturating_sub(1));
-        let line_len = rope.line(line).len_chars();
+        let line_len = line_len_without_newline(rope, line);
         let column = self.column.min(line_len);
 
         Self { line, column }
     }
 }
 
+/// Gets the length of a line in characters, excluding its line break.
+pub fn line_len_without_newline(rope: &Rope, line: usize) -> usize {
+    let slice = rope.line(line);
+    let mut len = slice.len_chars();
+    if len > 0 && slice.char(len - 1) == '\n' {
+        len -= 1;
+    }
+    if len > 0 && slice.char(len - 1) == '\r' {
+        len -= 1;
+    }
+    len
+}
+
 /// Text selection range.
-#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
 pub struct Selection {
     pub start: Position,
     pub end: Position,
@@ -167,10 +184,10 @@ mod tests {
     fn test_position_clamp() {
         let rope = Rope::from_str("Short\nMedium line\nX");
 
-        let pos = Position::new(0, 100).clamp(&rope);
+        let pos = Position::clamp(&Position::new(0, 100), &rope);
         assert!(pos.column <= 5);
 
-        let pos = Position::new(100, 0).clamp(&rope);
+        let pos = Position::clamp(&Position::new(100, 0), &rope);
         assert!(pos.line <= 2);
     }
 
@@ -0,0 +1,386 @@
+//! Line diff (Myers' O(ND) algorithm)
+//!
+//! Used to compare a buffer with its file on disk (crash recovery, dirty
+//! diff). Lines keep their line endings, so a changed line ending or a
+//! missing final newline shows up as a changed line.
+
+use std::ops::Range;
+
+use serde::Serialize;
+
+/// Kind of a diff run.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub enum DiffOp {
+    Equal,
+    Insert,
+    Delete,
+}
+
+/// Run of lines with the same diff kind.
+///
+/// `old` is empty for insertions and `new` is empty for deletions; the
+/// empty range still marks where the change happened.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DiffRange {
+    pub op: DiffOp,
+
+    /// Lines of the old text (0-indexed)
+    pub old: Range<usize>,
+
+    /// Lines of the new text (0-indexed)
+    pub new: Range<usize>,
+}
+
+/// Line of a diff hunk.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DiffLine {
+    pub op: DiffOp,
+
+    /// Line text without its line ending
+    pub text: String,
+
+    /// Line in the old text (None for insertions)
+    pub old_line: Option<usize>,
+
+    /// Line in the new text (None for deletions)
+    pub new_line: Option<usize>,
+}
+
+/// Group of nearby changes with surrounding context lines.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DiffHunk {
+    pub old_start: usize,
+    pub old_count: usize,
+    pub new_start: usize,
+    pub new_count: usize,
+    pub lines: Vec<DiffLine>,
+}
+
+/// Splits text into lines, keeping line endings.
+pub fn split_lines(text: &str) -> Vec<&str> {
+    text.split_inclusive('\n').collect()
+}
+
+/// Computes the shortest line diff between two line lists.
+///
+/// Returns: Runs covering both inputs in order
+pub fn diff_lines(old: &[&str], new: &[&str]) -> Vec<DiffRange> {
+    // Common prefix and suffix are cheap to strip and usually most of a file
+    let prefix = old.iter().zip(new).take_while(|(a, b)| a == b).count();
+    let suffix = old[prefix..]
+        .iter()
+        .rev()
+        .zip(new[prefix..].iter().rev())
+        .take_while(|(a, b)| a == b)
+        .count();
+
+    let mut ops = Vec::with_capacity(old.len().max(new.len()));
+    ops.extend(std::iter::repeat_n(DiffOp::Equal, prefix));
+    ops.extend(myers(&old[prefix..old.len() - suffix], &new[prefix..new.len() - suffix]));
+    ops.extend(std::iter::repeat_n(DiffOp::Equal, suffix));
+
+    let mut ranges: Vec<DiffRange> = Vec::new();
+    let (mut old_line, mut new_line) = (0, 0);
+    for op in ops {
+        let (old_len, new_len) = match op {
+            DiffOp::Equal => (1, 1),
+            DiffOp::Delete => (1, 0),
+            DiffOp::Insert => (0, 1),
+        };
+
+        match ranges.last_mut() {
+            Some(last) if last.op == op => {
+                last.old.end += old_len;
+                last.new.end += new_len;
+            }
+            _ => ranges.push(DiffRange {
+                op,
+                old: old_line..old_line + old_len,
+                new: new_line..new_line + new_len,
+            }),
+        }
+        old_line += old_len;
+        new_line += new_len;
+    }
+
+    ranges
+}
+
+/// Groups the changes between two texts into hunks.
+///
+/// Parameters:
+/// - `old`: Original text (e.g. the file on disk)
+/// - `new`: Changed text (e.g. the buffer)
+/// - `context`: Unchanged lines kept around each change
+///
+/// Returns: Hunks in order (empty if the texts are equal)
+pub fn diff_hunks(old: &str, new: &str, context: usize) -> Vec<DiffHunk> {
+    let old_lines = split_lines(old);
+    let new_lines = split_lines(new);
+
+    // One entry per line: (op, old cursor, new cursor)
+    let mut entries = Vec::new();
+    for range in diff_lines(&old_lines, &new_lines) {
+        let len = range.old.len().max(range.new.len());
+        for i in 0..len {
+            let old_line = range.old.start + if range.op == DiffOp::Insert { 0 } else { i };
+            let new_line = range.new.start + if range.op == DiffOp::Delete { 0 } else { i };
+            entries.push((range.op, old_line, new_line));
+        }
+    }
+    let changes: Vec<usize> = (0..entries.len()).filter(|&i| entries[i].0 != DiffOp::Equal).collect();
+
+    let mut hunks = Vec::new();
+    let mut i = 0;
+    while i < changes.len() {
+        // Extend over changes separated by at most 2 * context equal lines
+        let mut j = i;
+        while j + 1 < changes.len() && changes[j + 1] - changes[j] - 1 <= 2 * context {
+            j += 1;
+        }
+
+        let start = changes[i].saturating_sub(context);
+        let end = (changes[j] + 1 + context).min(entries.len());
+        let (_, old_start, new_start) = entries[start];
+        let mut hunk = DiffHunk {
+            old_start,
+            old_count: 0,
+            new_start,
+            new_count: 0,
+            lines: Vec::new(),
+        };
+
+        for &(op, old_line, new_line) in &entries[start..end] {
+            match op {
+                DiffOp::Equal => push_line(&mut hunk, op, old_lines[old_line], Some(old_line), Some(new_line)),
+                DiffOp::Delete => push_line(&mut hunk, op, old_lines[old_line], Some(old_line), None),
+                DiffOp::Insert => push_line(&mut hunk, op, new_lines[new_line], None, Some(new_line)),
+            }
+        }
+
+        hunks.push(hunk);
+        i = j + 1;
+    }
+
+    hunks
+}
+
+/// Formats the changes between two texts as a unified diff.
+///
+/// Parameters:
+/// - `old`: Original text
+/// - `new`: Changed text
+/// - `old_name`: Label of the original (e.g. the file path)
+/// - `new_name`: Label of the changed text
+/// - `context`: Unchanged lines kept around each change
+///
+/// Returns: Unified diff (empty if the texts are equal)
+pub fn unified_diff(old: &str, new: &str, old_name: &str, new_name: &str, context: usize) -> String {
+    let hunks = diff_hunks(old, new, context);
+    if hunks.is_empty() {
+        return String::new();
+    }
+
+    let old_lines = split_lines(old);
+    let new_lines = split_lines(new);
+    let mut out = format!("--- {}\n+++ {}\n", old_name, new_name);
+
+    for hunk in &hunks {
+        out.push_str(&format!(
+            "@@ -{} +{} @@\n",
+            hunk_range(hunk.old_start, hunk.old_count),
+            hunk_range(hunk.new_start, hunk.new_count)
+        ));
+
+        for line in &hunk.lines {
+            let (prefix, raw) = match line.op {
+                DiffOp::Equal => (' ', old_lines[line.old_line.unwrap_or_default()]),
+                DiffOp::Delete => ('-', old_lines[line.old_line.unwrap_or_default()]),
+                DiffOp::Insert => ('+', new_lines[line.new_line.unwrap_or_default()]),
+            };
+            out.push(prefix);
+            out.push_str(raw);
+            if !raw.ends_with('\n') {
+                out.push_str("\n\\ No newline at end of file\n");
+            }
+        }
+    }
+
+    out
+}
+
+/// Helper: Formats a hunk header range (`start,count`, 1-indexed)
+fn hunk_range(start: usize, count: usize) -> String {
+    match count {
+        // An empty range names the line before it
+        0 => format!("{},0", start),
+        1 => format!("{}", start + 1),
+        _ => format!("{},{}", start + 1, count),
+    }
+}
+
+/// Helper: Appends a line to a hunk and updates its counts
+fn push_line(hunk: &mut DiffHunk, op: DiffOp, raw: &str, old_line: Option<usize>, new_line: Option<usize>) {
+    if old_line.is_some() {
+        hunk.old_count += 1;
+    }
+    if new_line.is_some() {
+        hunk.new_count += 1;
+    }
+    hunk.lines.push(DiffLine {
+        op,
+        text: raw.trim_end_matches(['\n', '\r']).to_string(),
+        old_line,
+        new_line,
+    });
+}
+
+/// Helper: Myers' greedy diff with a trace for backtracking
+///
+/// Returns: One op per line (deletions before insertions in each change)
+fn myers(a: &[&str], b: &[&str]) -> Vec<DiffOp> {
+    let n = a.len() as isize;
+    let m = b.len() as isize;
+    let max = n + m;
+    if max == 0 {
+        return Vec::new();
+    }
+
+    let offset = max as usize;
+    let index = |k: isize| (k + offset as isize) as usize;
+    let mut v = vec![0isize; 2 * offset + 2];
+    let mut trace: Vec<Vec<isize>> = Vec::new();
+
+    'search: for d in 0..=max {
+        trace.push(v.clone());
+        for k in (-d..=d).step_by(2) {
+            let mut x = if k == -d || (k != d && v[index(k - 1)] < v[index(k + 1)]) {
+                v[index(k + 1)]
+            } else {
+                v[index(k - 1)] + 1
+            };
+            let mut y = x - k;
+            while x < n && y < m && a[x as usize] == b[y as usize] {
+                x += 1;
+                y += 1;
+            }
+            v[index(k)] = x;
+            if x >= n && y >= m {
+                break 'search;
+            }
+        }
+    }
+
+    // Walk the trace back from the end, collecting ops in reverse
+    let mut ops = Vec::with_capacity((n + m) as usize);
+    let (mut x, mut y) = (n, m);
+    for (d, v) in trace.iter().enumerate().rev() {
+        let d = d as isize;
+        let k = x - y;
+        let prev_k = if k == -d || (k != d && v[index(k - 1)] < v[index(k + 1)]) {
+            k + 1
+        } else {
+            k - 1
+        };
+        let prev_x = v[index(prev_k)];
+        let prev_y = prev_x - prev_k;
+
+        while x > prev_x && y > prev_y {
+            ops.push(DiffOp::Equal);
+            x -= 1;
+            y -= 1;
+        }
+        if d > 0 {
+            ops.push(if x == prev_x { DiffOp::Insert } else { DiffOp::Delete });
+        }
+        x = prev_x;
+        y = prev_y;
+    }
+
+    ops.reverse();
+    ops
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Applies a diff to the old lines (must reproduce the new lines)
+    fn apply(old: &[&str], new: &[&str], ranges: &[DiffRange]) -> Vec<String> {
+        let mut out = Vec::new();
+        for range in ranges {
+            match range.op {
+                DiffOp::Equal => {
+                    assert_eq!(old[range.old.clone()], new[range.new.clone()]);
+                    out.extend(old[range.old.clone()].iter().map(|s| s.to_string()));
+                }
+                DiffOp::Delete => assert!(range.new.is_empty()),
+                DiffOp::Insert => out.extend(new[range.new.clone()].iter().map(|s| s.to_string())),
+            }
+        }
+        out
+    }
+
+    #[test]
+    fn test_diff_lines_roundtrip() {
+        let cases = [
+            ("", ""),
+            ("a\nb\nc\n", "a\nb\nc\n"),
+            ("", "a\nb\n"),
+            ("a\nb\n", ""),
+            ("a\nb\nc\nd\n", "a\nx\nc\ny\nd\n"),
+            ("a\nb\nc\na\nb\nb\na\n", "c\nb\na\nb\na\nc\n"),
+        ];
+        for (old, new) in cases {
+            let old_lines = split_lines(old);
+            let new_lines = split_lines(new);
+            let ranges = diff_lines(&old_lines, &new_lines);
+            assert_eq!(apply(&old_lines, &new_lines, &ranges), new_lines, "{:?} -> {:?}", old, new);
+        }
+    }
+
+    #[test]
+    fn test_diff_lines_is_minimal() {
+        // Classic Myers example: 5 edits
+        let old = split_lines("a\nb\nc\na\nb\nb\na\n");
+        let new = split_lines("c\nb\na\nb\na\nc\n");
+        let edits: usize = diff_lines(&old, &new)
+            .iter()
+            .filter(|r| r.op != DiffOp::Equal)
+            .map(|r| r.old.len() + r.new.len())
+            .sum();
+        assert_eq!(edits, 5);
+    }
+
+    #[test]
+    fn test_diff_hunks_context() {
+        let old: String = (0..20).map(|i| format!("line {}\n", i)).collect();
+        let new = old.replace("line 2\n", "line two\n").replace("line 15\n", "");
+
+        let hunks = diff_hunks(&old, &new, 2);
+        assert_eq!(hunks.len(), 2);
+        assert_eq!((hunks[0].old_start, hunks[0].old_count, hunks[0].new_count), (0, 5, 5));
+        assert_eq!(hunks[0].lines[2].op, DiffOp::Delete);
+        assert_eq!(hunks[0].lines[3].text, "line two");
+        assert_eq!((hunks[1].old_start, hunks[1].old_count, hunks[1].new_count), (13, 5, 4));
+
+        // Close changes merge into one hunk
+        let new = old.replace("line 2\n", "").replace("line 5\n", "");
+        assert_eq!(diff_hunks(&old, &new, 2).len(), 1);
+        assert!(diff_hunks(&old, &old, 3).is_empty());
+    }
+
+    #[test]
+    fn test_unified_diff() {
+        let diff = unified_diff("a\nb\nc", "a\nB\nc", "disk", "buffer", 1);
+        assert_eq!(
+            diff,
+            "--- disk\n+++ buffer\n@@ -1,3 +1,3 @@\n a\n-b\n+B\n c\n\\ No newline at end of file\n"
+        );
+        assert_eq!(unified_diff("same\n", "same\n", "a", "b", 3), "");
+        assert!(unified_diff("", "new\n", "a", "b", 3).contains("@@ -0,0 +1 @@\n+new\n"));
+    }
+}
@@ -0,0 +1,429 @@
+//! Dirty diff (gutter change markers)
+//!
+//! Compares the buffer with a base text (the saved file, or any text the
+//! host supplies) line by line. The full diff is computed once when the
+//! base is set; after that every edit only rediffs the lines it touched
+//! plus the changes next to them, so markers stay current while typing in
+//! large files.
+
+use std::ops::Range;
+
+use anyhow::{bail, Result};
+use ropey::Rope;
+use serde::Serialize;
+
+use crate::editor::diff::{diff_lines, DiffOp};
+use crate::editor::{Editor, Position};
+
+/// Kind of a changed block.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub enum ChangeKind {
+    /// Lines that are not in the base
+    Added,
+
+    /// Lines that replace base lines
+    Modified,
+
+    /// Base lines that were removed (no buffer lines)
+    Deleted,
+}
+
+/// Block of lines that differs from the base.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct LineChange {
+    pub kind: ChangeKind,
+
+    /// Lines of the base (0-indexed, empty for additions)
+    pub old: Range<usize>,
+
+    /// Lines of the buffer (0-indexed, empty for deletions)
+    pub new: Range<usize>,
+}
+
+/// Gutter marker of one buffer line.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct GutterMarker {
+    pub line: usize,
+    pub kind: ChangeKind,
+}
+
+/// Base text and the changes against it.
+#[derive(Debug, Clone)]
+pub(crate) struct DiffState {
+    base: Rope,
+
+    /// Changes in buffer order (separated by at least one equal line)
+    changes: Vec<LineChange>,
+
+    /// Buffer line count the changes were computed for
+    line_count: usize,
+
+    /// The base is replaced with the content on every `mark_saved`
+    pub(crate) follows_saved: bool,
+}
+
+impl DiffState {
+    fn new(base: Rope, current: &Rope, follows_saved: bool) -> Self {
+        let mut state = Self {
+            base,
+            changes: Vec::new(),
+            line_count: 0,
+            follows_saved,
+        };
+        state.rediff(current);
+        state
+    }
+
+    /// Recomputes every change
+    pub(crate) fn rediff(&mut self, current: &Rope) {
+        self.line_count = line_count(current);
+        self.changes = diff_window(&self.base, 0..line_count(&self.base), current, 0..self.line_count);
+    }
+
+    /// Updates the changes after an edit
+    ///
+    /// Parameters:
+    /// - `current`: Buffer after the edit
+    /// - `start_line`: First edited line (before the edit)
+    /// - `old_end_line`: Last edited line (before the edit)
+    pub(crate) fn edit(&mut self, current: &Rope, start_line: usize, old_end_line: usize) {
+        let new_count = line_count(current);
+        let delta = new_count as isize - self.line_count as isize;
+
+        // One line back: a line break typed after `\r` joins the lines
+        let mut start = start_line.saturating_sub(1).min(self.line_count);
+        let mut end = (old_end_line + 1).clamp(start, self.line_count);
+
+        // Neighbouring changes are rediffed with the edit so they can merge
+        let first = self.changes.partition_point(|c| c.new.end < start);
+        let last = self.changes.partition_point(|c| c.new.start <= end);
+        if first < last {
+            start = start.min(self.changes[first].new.start);
+            end = end.max(self.changes[last - 1].new.end);
+        }
+
+        let old_start = (start as isize + self.offset_before(first)) as usize;
+        let old_end = (end as isize + self.offset_before(last)) as usize;
+        let new_end = (end as isize + delta).max(start as isize) as usize;
+
+        let window = diff_window(&self.base, old_start..old_end, current, start..new_end);
+        self.changes.splice(first..last, window.iter().cloned());
+        for change in &mut self.changes[first + window.len()..] {
+            change.new.start = (change.new.start as isize + delta) as usize;
+            change.new.end = (change.new.end as isize + delta) as usize;
+        }
+        self.line_count = new_count;
+    }
+
+    /// Helper: Base line minus buffer line after the first `index` changes
+    fn offset_before(&self, index: usize) -> isize {
+        match index.checked_sub(1).map(|i| &self.changes[i]) {
+            Some(change) => change.old.end as isize - change.new.end as isize,
+            None => 0,
+        }
+    }
+
+    /// Helper: Gets the buffer lines a change's marker covers
+    ///
+    /// A deletion is marked on the line now following it (the last line
+    /// when it was at the end).
+    fn marker_lines(&self, change: &LineChange) -> Range<usize> {
+        if change.new.is_empty() {
+            let line = change.new.start.min(self.line_count.saturating_sub(1));
+            line..line + 1
+        } else {
+            change.new.clone()
+        }
+    }
+
+    /// Helper: Gets the change marked on a line
+    fn change_at(&self, line: usize) -> Option<&LineChange> {
+        self.changes.iter().find(|c| self.marker_lines(c).contains(&line))
+    }
+}
+
+/// Counts lines the way the diff sees them (no empty line after a final
+/// line break)
+fn line_count(rope: &Rope) -> usize {
+    let lines = rope.len_lines();
+    if rope.line(lines - 1).len_chars() == 0 {
+        lines - 1
+    } else {
+        lines
+    }
+}
+
+/// Diffs a range of base lines against a range of buffer lines
+///
+/// Returns: Changes with absolute line numbers
+fn diff_window(base: &Rope, old: Range<usize>, current: &Rope, new: Range<usize>) -> Vec<LineChange> {
+    let old_lines: Vec<String> = old.clone().map(|i| base.line(i).to_string()).collect();
+    let new_lines: Vec<String> = new.clone().map(|i| current.line(i).to_string()).collect();
+    let old_refs: Vec<&str> = old_lines.iter().map(String::as_str).collect();
+    let new_refs: Vec<&str> = new_lines.iter().map(String::as_str).collect();
+
+    let mut changes: Vec<LineChange> = Vec::new();
+    let mut in_change = false;
+    for range in diff_lines(&old_refs, &new_refs) {
+        if range.op == DiffOp::Equal {
+            in_change = false;
+            continue;
+        }
+
+        let old_range = old.start + range.old.start..old.start + range.old.end;
+        let new_range = new.start + range.new.start..new.start + range.new.end;
+        match changes.last_mut() {
+            Some(change) if in_change => {
+                change.old.end = old_range.end;
+                change.new.end = new_range.end;
+            }
+            _ => changes.push(LineChange {
+                kind: ChangeKind::Added,
+                old: old_range,
+                new: new_range,
+            }),
+        }
+        in_change = true;
+    }
+
+    for change in &mut changes {
+        change.kind = match (change.old.is_empty(), change.new.is_empty()) {
+            (true, _) => ChangeKind::Added,
+            (_, true) => ChangeKind::Deleted,
+            _ => ChangeKind::Modified,
+        };
+    }
+    changes
+}
+
+impl Editor {
+    /// Sets the text the gutter markers compare against
+    pub fn set_diff_base(&mut self, base: &str) {
+        self.diff_state = Some(DiffState::new(Rope::from_str(base), &self.rope, false));
+    }
+
+    /// Compares against the saved content (updated on every `mark_saved`)
+    ///
+    /// The current content is taken as saved; call it right after loading
+    /// or saving the file.
+    pub fn set_diff_base_to_saved(&mut self) {
+        self.diff_state = Some(DiffState::new(self.rope.clone(), &self.rope, true));
+    }
+
+    /// Removes the diff base (no gutter markers)
+    pub fn clear_diff_base(&mut self) {
+        self.diff_state = None;
+    }
+
+    /// Gets the changed blocks against the diff base (empty without a base)
+    pub fn diff_changes(&self) -> &[LineChange] {
+        self.diff_state.as_ref().map_or(&[], |state| &state.changes)
+    }
+
+    /// Gets gutter markers for a range of lines
+    ///
+    /// Parameters:
+    /// - `first_line`: First visible line
+    /// - `last_line`: Last visible line (inclusive)
+    ///
+    /// Returns: One marker per changed line, in line order
+    pub fn gutter_markers(&self, first_line: usize, last_line: usize) -> Vec<GutterMarker> {
+        let Some(state) = &self.diff_state else {
+            return Vec::new();
+        };
+
+        let mut markers: Vec<GutterMarker> = Vec::new();
+        for change in &state.changes {
+            let lines = state.marker_lines(change);
+            for line in lines.start.max(first_line)..lines.end.min(last_line + 1) {
+                // A deletion can share its line with the change after it
+                if markers.last().is_some_and(|m| m.line == line) {
+                    continue;
+                }
+                markers.push(GutterMarker { line, kind: change.kind });
+            }
+        }
+        markers
+    }
+
+    /// Gets the first line of the next change after `line` (wraps around)
+    pub fn next_change(&self, line: usize) -> Option<usize> {
+        let state = self.diff_state.as_ref()?;
+        let starts: Vec<usize> = state.changes.iter().map(|c| state.marker_lines(c).start).collect();
+        starts.iter().copied().find(|&start| start > line).or(starts.first().copied())
+    }
+
+    /// Gets the first line of the previous change before `line` (wraps around)
+    pub fn previous_change(&self, line: usize) -> Option<usize> {
+        let state = self.diff_state.as_ref()?;
+        let starts: Vec<usize> = state.changes.iter().map(|c| state.marker_lines(c).start).collect();
+        starts.iter().copied().rev().find(|&start| start < line).or(starts.last().copied())
+    }
+
+    /// Restores the base text of the change marked on a line (undoable)
+    ///
+    /// Returns: true if a change was reverted, false if the line is unchanged
+    pub fn revert_change(&mut self, line: usize) -> Result<bool> {
+        self.ensure_writable()?;
+        let Some(state) = &self.diff_state else {
+            bail!("no diff base is set");
+        };
+        let Some(change) = state.change_at(line).cloned() else {
+            return Ok(false);
+        };
+
+        let base = &state.base;
+        let text = base.slice(base.line_to_char(change.old.start)..base.line_to_char(change.old.end)).to_string();
+        let start = self.rope.line_to_byte(change.new.start);
+        let end = self.rope.line_to_byte(change.new.end);
+
+        self.replace_bytes(start, end, &text);
+        self.cursor = Position::from_byte_offset(&self.rope, start);
+        self.selection = None;
+        Ok(true)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::editor::{LanguageId, Selection};
+
+    const BASE: &str = "one\ntwo\nthree\nfour\nfive\n";
+
+    fn editor() -> Editor {
+        let mut editor = Editor::with_content(BASE, LanguageId::PlainText).unwrap();
+        editor.set_diff_base(BASE);
+        editor
+    }
+
+    /// The incremental changes must match a full rediff
+    fn assert_consistent(editor: &Editor) {
+        let state = editor.diff_state.as_ref().unwrap();
+        let full = DiffState::new(state.base.clone(), &editor.rope, false);
+        assert_eq!(state.changes, full.changes, "content: {:?}", editor.content());
+    }
+
+    #[test]
+    fn test_markers_track_edits() {
+        let mut editor = editor();
+        assert!(editor.diff_changes().is_empty());
+
+        // Modified line
+        editor.move_cursor(Position::new(1, 3));
+        editor.insert_text("!").unwrap();
+        assert_eq!(
+            editor.gutter_markers(0, 10),
+            vec![GutterMarker { line: 1, kind: ChangeKind::Modified }]
+        );
+
+        // Added lines
+        editor.move_cursor(Position::new(3, 4));
+        editor.insert_text("\nfour.1\nfour.2").unwrap();
+        assert_consistent(&editor);
+        let kinds: Vec<(usize, ChangeKind)> = editor.gutter_markers(0, 10).iter().map(|m| (m.line, m.kind)).collect();
+        assert_eq!(
+            kinds,
+            vec![(1, ChangeKind::Modified), (4, ChangeKind::Added), (5, ChangeKind::Added)]
+        );
+
+        // Undoing everything leaves no changes
+        while editor.undo().unwrap() {}
+        assert!(editor.diff_changes().is_empty());
+
+        // Deleted line (marked on the line after it)
+        editor.set_selection(Selection::new(Position::new(2, 0), Position::new(3, 0)));
+        editor.delete().unwrap();
+        assert_consistent(&editor);
+        assert_eq!(editor.diff_changes()[0].kind, ChangeKind::Deleted);
+        assert_eq!(editor.diff_changes()[0].old, 2..3);
+        assert_eq!(editor.gutter_markers(0, 10), vec![GutterMarker { line: 2, kind: ChangeKind::Deleted }]);
+    }
+
+    #[test]
+    fn test_incremental_matches_full_diff() {
+        let mut editor = editor();
+        let edits: [(usize, usize, usize, usize, &str); 8] = [
+            (0, 0, 0, 0, "zero\n"),
+            (2, 1, 2, 3, "O"),
+            (5, 0, 5, 4, ""),
+            (3, 0, 4, 0, ""),
+            (1, 2, 1, 2, "\n\n"),
+            (0, 0, 2, 0, "head\r"),
+            (0, 5, 0, 5, "\n"),
+            (6, 0, 6, 0, "tail"),
+        ];
+        for (line, column, end_line, end_column, text) in edits {
+            editor
+                .replace_range(Position::new(line, column), Position::new(end_line, end_column), text)
+                .unwrap();
+            assert_consistent(&editor);
+        }
+
+        while editor.undo().unwrap() {
+            assert_consistent(&editor);
+        }
+        assert!(editor.diff_changes().is_empty());
+    }
+
+    #[test]
+    fn test_revert_change() {
+        let mut editor = editor();
+        editor.replace_range(Position::new(1, 0), Position::new(2, 5), "2\n3").unwrap();
+        editor.move_cursor(Position::new(4, 4));
+        editor.insert_text("\nsix").unwrap();
+        assert_eq!(editor.diff_changes().len(), 2);
+
+        assert!(!editor.revert_change(0).unwrap());
+        assert!(editor.revert_change(2).unwrap());
+        assert_eq!(editor.content(), "one\ntwo\nthree\nfour\nfive\nsix\n");
+        assert_eq!(editor.diff_changes().len(), 1);
+
+        // Reverting is undoable
+        editor.undo().unwrap();
+        assert_eq!(editor.content(), "one\n2\n3\nfour\nfive\nsix\n");
+
+        editor.revert_change(5).unwrap();
+        editor.revert_change(1).unwrap();
+        assert_eq!(editor.content(), BASE);
+        assert!(editor.diff_changes().is_empty());
+    }
+
+    #[test]
+    fn test_change_navigation() {
+        let mut editor = editor();
+        assert_eq!(editor.next_change(0), None);
+
+        editor.replace_range(Position::new(1, 0), Position::new(1, 3), "2").unwrap();
+        editor.replace_range(Position::new(4, 0), Position::new(4, 4), "5").unwrap();
+
+        assert_eq!(editor.next_change(0), Some(1));
+        assert_eq!(editor.next_change(1), Some(4));
+        assert_eq!(editor.next_change(4), Some(1));
+        assert_eq!(editor.previous_change(4), Some(1));
+        assert_eq!(editor.previous_change(1), Some(4));
+    }
+
+    #[test]
+    fn test_diff_base_follows_saves() {
+        let mut editor = Editor::with_content("a\nb\n", LanguageId::PlainText).unwrap();
+        editor.set_diff_base_to_saved();
+        editor.insert_text("x").unwrap();
+        assert_eq!(editor.diff_changes().len(), 1);
+
+        editor.mark_saved();
+        assert!(editor.diff_changes().is_empty());
+
+        // A supplied base is kept across saves
+        editor.set_diff_base("a\n");
+        editor.mark_saved();
+        assert_eq!(editor.diff_changes()[0].kind, ChangeKind::Modified);
+
+        editor.clear_diff_base();
+        assert!(editor.gutter_markers(0, 10).is_empty());
+        assert!(editor.revert_change(0).is_err());
+    }
+}
@@ -0,0 +1,1023 @@
+use std::collections::{BTreeMap, HashMap};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use crate::editor::auto_indent::IndentConfig;
+use crate::editor::save_actions::SaveAction;
+use crate::editor::LanguageId;
+
+/// Name of the EditorConfig file looked up in each directory.
+pub const EDITORCONFIG_FILE_NAME: &str = ".editorconfig";
+
+/// Indentation style (`indent_style`).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum IndentStyle {
+    Space,
+    Tab,
+}
+
+/// Indentation size (`indent_size`).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum IndentSize {
+    /// Fixed number of columns
+    Columns(usize),
+    /// Use the value of `tab_width`
+    Tab,
+}
+
+/// Line ending style (`end_of_line`).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LineEnding {
+    Lf,
+    CrLf,
+    Cr,
+}
+
+impl LineEnding {
+    /// Gets the line break sequence.
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            LineEnding::Lf => "\n",
+            LineEnding::CrLf => "\r\n",
+            LineEnding::Cr => "\r",
+        }
+    }
+
+    /// Converts every line break in `text` (`\n`, `\r\n` or `\r`) to this style.
+    pub fn normalize(&self, text: &str) -> String {
+        let mut result = String::with_capacity(text.len());
+        let mut chars = text.chars().peekable();
+
+        while let Some(c) = chars.next() {
+            match c {
+                '\r' => {
+                    if chars.peek() == Some(&'\n') {
+                        chars.next();
+                    }
+                    result.push_str(self.as_str());
+                }
+                '\n' => result.push_str(self.as_str()),
+                c => result.push(c),
+            }
+        }
+
+        result
+    }
+}
+
+/// File encoding (`charset`).
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Charset {
+    Latin1,
+    #[default]
+    Utf8,
+    Utf8Bom,
+    Utf16Be,
+    Utf16Le,
+}
+
+impl Charset {
+    /// Detects the charset from a byte order mark.
+    ///
+    /// Returns: None if the bytes do not start with a BOM
+    pub fn detect(bytes: &[u8]) -> Option<Charset> {
+        match bytes {
+            [0xEF, 0xBB, 0xBF, ..] => Some(Charset::Utf8Bom),
+            [0xFE, 0xFF, ..] => Some(Charset::Utf16Be),
+            [0xFF, 0xFE, ..] => Some(Charset::Utf16Le),
+            _ => None,
+        }
+    }
+
+    /// Decodes file bytes in this charset.
+    ///
+    /// A leading byte order mark is dropped, so it never ends up in the
+    /// buffer as text (`encode` writes it back where the charset has one).
+    pub fn decode(&self, bytes: &[u8]) -> anyhow::Result<String> {
+        match self {
+            Charset::Utf8 | Charset::Utf8Bom => {
+                let bytes = bytes.strip_prefix(&[0xEF, 0xBB, 0xBF]).unwrap_or(bytes);
+                Ok(String::from_utf8(bytes.to_vec())?)
+            }
+            Charset::Latin1 => Ok(bytes.iter().map(|&b| char::from(b)).collect()),
+            Charset::Utf16Be | Charset::Utf16Le => {
+                let bom: [u8; 2] = if *self == Charset::Utf16Be { [0xFE, 0xFF] } else { [0xFF, 0xFE] };
+                let bytes = bytes.strip_prefix(&bom).unwrap_or(bytes);
+                if !bytes.len().is_multiple_of(2) {
+                    anyhow::bail!("odd number of bytes in UTF-16 text");
+                }
+                let units: Vec<u16> = bytes
+                    .chunks_exact(2)
+                    .map(|pair| match self {
+                        Charset::Utf16Be => u16::from_be_bytes([pair[0], pair[1]]),
+                        _ => u16::from_le_bytes([pair[0], pair[1]]),
+                    })
+                    .collect();
+                Ok(String::from_utf16(&units)?)
+            }
+        }
+    }
+
+    /// Encodes text into bytes for this charset.
+    ///
+    /// UTF-16 output starts with a byte order mark. Characters that cannot
+    /// be represented in Latin-1 are replaced with `?`. A leading U+FEFF in
+    /// the text is dropped, so the BOM is never written twice.
+    pub fn encode(&self, text: &str) -> Vec<u8> {
+        let text = text.strip_prefix('\u{FEFF}').unwrap_or(text);
+        match self {
+            Charset::Utf8 => text.as_bytes().to_vec(),
+            Charset::Utf8Bom => {
+                let mut bytes = vec![0xEF, 0xBB, 0xBF];
+                bytes.extend_from_slice(text.as_bytes());
+                bytes
+            }
+            Charset::Latin1 => text
+                .chars()
+                .map(|c| u8::try_from(u32::from(c)).unwrap_or(b'?'))
+                .collect(),
+            Charset::Utf16Be => std::iter::once(0xFEFF)
+                .chain(text.encode_utf16())
+                .flat_map(u16::to_be_bytes)
+                .collect(),
+            Charset::Utf16Le => std::iter::once(0xFEFF)
+                .chain(text.encode_utf16())
+                .flat_map(u16::to_le_bytes)
+                .collect(),
+        }
+    }
+}
+
+/// Save-time behaviour enforced by `Editor::prepare_save`.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct SaveSettings {
+    /// Remove whitespace at the end of every line
+    pub trim_trailing_whitespace: bool,
+    /// Make sure the file ends with a line break
+    pub insert_final_newline: bool,
+    /// Remove blank lines after the last line break
+    pub trim_final_newlines: bool,
+    /// Convert mixed line breaks in the buffer itself
+    pub normalize_line_endings: bool,
+    /// Convert indentation to the indent style
+    pub normalize_indentation: bool,
+    /// Convert all line breaks on save (`None` keeps them as-is)
+    pub end_of_line: Option<LineEnding>,
+    /// Output encoding
+    pub charset: Charset,
+    /// Per-language overrides of the actions above (these win over
+    /// EditorConfig too)
+    pub language_actions: HashMap<LanguageId, BTreeMap<SaveAction, bool>>,
+}
+
+impl SaveSettings {
+    /// Applies EditorConfig properties on top of existing settings.
+    ///
+    /// Unset properties keep the values from `self`.
+    pub fn with_editorconfig(&self, props: &EditorConfigProperties) -> Self {
+        Self {
+            trim_trailing_whitespace: props
+                .trim_trailing_whitespace
+                .unwrap_or(self.trim_trailing_whitespace),
+            insert_final_newline: props.insert_final_newline.unwrap_or(self.insert_final_newline),
+            end_of_line: props.end_of_line.or(self.end_of_line),
+            charset: props.charset.unwrap_or(self.charset),
+            ..self.clone()
+        }
+    }
+
+    /// Gets the save actions to run for a language, in run order.
+    pub fn actions(&self, language: &LanguageId) -> Vec<SaveAction> {
+        let overrides = self.language_actions.get(language);
+        SaveAction::ALL
+            .into_iter()
+            .filter(|action| {
+                overrides
+                    .and_then(|overrides| overrides.get(action).copied())
+                    .unwrap_or_else(|| self.is_enabled(*action))
+            })
+            .collect()
+    }
+
+    /// Checks if an action is enabled for languages without an override.
+    pub fn is_enabled(&self, action: SaveAction) -> bool {
+        match action {
+            SaveAction::TrimTrailingWhitespace => self.trim_trailing_whitespace,
+            SaveAction::NormalizeIndentation => self.normalize_indentation,
+            SaveAction::NormalizeLineEndings => self.normalize_line_endings,
+            SaveAction::TrimFinalNewlines => self.trim_final_newlines,
+            SaveAction::InsertFinalNewline => self.insert_final_newline,
+        }
+    }
+
+    /// Enables or disables an action for languages without an override.
+    pub fn set_enabled(&mut self, action: SaveAction, enabled: bool) {
+        let flag = match action {
+            SaveAction::TrimTrailingWhitespace => &mut self.trim_trailing_whitespace,
+            SaveAction::NormalizeIndentation => &mut self.normalize_indentation,
+            SaveAction::NormalizeLineEndings => &mut self.normalize_line_endings,
+            SaveAction::TrimFinalNewlines => &mut self.trim_final_newlines,
+            SaveAction::InsertFinalNewline => &mut self.insert_final_newline,
+        };
+        *flag = enabled;
+    }
+
+    /// Overrides an action for one language.
+    ///
+    /// Parameters:
+    /// - `language`: Document language
+    /// - `action`: Action to override
+    /// - `enabled`: Whether to run it, or None to remove the override
+    pub fn set_language_action(&mut self, language: LanguageId, action: SaveAction, enabled: Option<bool>) {
+        let overrides = self.language_actions.entry(language.clone()).or_default();
+        match enabled {
+            Some(enabled) => {
+                overrides.insert(action, enabled);
+            }
+            None => {
+                overrides.remove(&action);
+                if overrides.is_empty() {
+                    self.language_actions.remove(&language);
+                }
+            }
+        }
+    }
+}
+
+/// EditorConfig (`.editorconfig`) support.
+///
+/// Resolves the effective settings for a file following the EditorConfig spec:
+/// - Files are looked up from the file's directory towards the filesystem root
+/// - Lookup stops at the first file declaring `root = true`
+/// - Files closer to the target file take precedence
+/// - Within a file, later sections override earlier ones
+///
+/// Example:
+/// ```ini
+/// root = true
+///
+/// [*]
+/// indent_style = space
+/// indent_size = 4
+///
+/// [*.{js,ts}]
+/// indent_size = 2
+/// ```
+///
+/// `None` means the property is not set (or was explicitly `unset`),
+/// in which case the editor keeps its own default.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct EditorConfigProperties {
+    pub indent_style: Option<IndentStyle>,
+    pub indent_size: Option<IndentSize>,
+    pub tab_width: Option<usize>,
+    pub end_of_line: Option<LineEnding>,
+    pub charset: Option<Charset>,
+    pub trim_trailing_whitespace: Option<bool>,
+    pub insert_final_newline: Option<bool>,
+    /// `None` also covers `max_line_length = off`
+    pub max_line_length: Option<usize>,
+}
+
+impl EditorConfigProperties {
+    /// Builds typed properties from raw (lowercased) key/value pairs.
+    ///
+    /// Applies the spec defaults between related properties:
+    /// - `indent_style = tab` without `indent_size` implies `indent_size = tab`
+    /// - `indent_size = <n>` without `tab_width` implies `tab_width = <n>`
+    pub fn from_pairs(pairs: &HashMap<String, String>) -> Self {
+        let get = |key: &str| pairs.get(key).map(|v| v.as_str());
+
+        let indent_style = match get("indent_style") {
+            Some("space") => Some(IndentStyle::Space),
+            Some("tab") => Some(IndentStyle::Tab),
+            _ => None,
+        };
+
+        let mut indent_size = match get("indent_size") {
+            Some("tab") => Some(IndentSize::Tab),
+            Some(value) => parse_positive(value).map(IndentSize::Columns),
+            None => None,
+        };
+
+        let mut tab_width = get("tab_width").and_then(parse_positive);
+
+        if indent_style == Some(IndentStyle::Tab) && indent_size.is_none() {
+            indent_size = Some(IndentSize::Tab);
+        }
+
+        if let (Some(IndentSize::Columns(size)), None) = (indent_size, tab_width) {
+            tab_width = Some(size);
+        }
+
+        let end_of_line = match get("end_of_line") {
+            Some("lf") => Some(LineEnding::Lf),
+            Some("crlf") => Some(LineEnding::CrLf),
+            Some("cr") => Some(LineEnding::Cr),
+            _ => None,
+        };
+
+        let charset = match get("charset") {
+            Some("latin1") => Some(Charset::Latin1),
+            Some("utf-8") => Some(Charset::Utf8),
+            Some("utf-8-bom") => Some(Charset::Utf8Bom),
+            Some("utf-16be") => Some(Charset::Utf16Be),
+            Some("utf-16le") => Some(Charset::Utf16Le),
+            _ => None,
+        };
+
+        Self {
+            indent_style,
+            indent_size,
+            tab_width,
+            end_of_line,
+            charset,
+            trim_trailing_whitespace: get("trim_trailing_whitespace").and_then(parse_bool),
+            insert_final_newline: get("insert_final_newline").and_then(parse_bool),
+            max_line_length: get("max_line_length").and_then(parse_positive),
+        }
+    }
+
+    /// Resolves the indentation width in columns, if known.
+    pub fn resolved_indent_size(&self) -> Option<usize> {
+        match self.indent_size {
+            Some(IndentSize::Columns(size)) => Some(size),
+            Some(IndentSize::Tab) => self.tab_width,
+            None => None,
+        }
+    }
+
+    /// Applies the indentation properties on top of an existing config.
+    ///
+    /// Unset properties keep the values from `base`.
+    pub fn to_indent_config(&self, base: &IndentConfig) -> IndentConfig {
+        let mut config = base.clone();
+
+        match self.indent_style {
+            Some(IndentStyle::Space) => config.use_spaces = true,
+            Some(IndentStyle::Tab) => config.use_spaces = false,
+            None => {}
+        }
+
+        if config.use_spaces {
+            if let Some(size) = self.resolved_indent_size() {
+                config.tab_size = size;
+            }
+        } else if let Some(width) = self.tab_width.or_else(|| self.resolved_indent_size()) {
+            config.tab_size = width;
+        }
+
+        config
+    }
+}
+
+/// A section of an `.editorconfig` file (`[glob]` + properties).
+#[derive(Debug, Clone)]
+pub struct EditorConfigSection {
+    /// Raw section glob as written in the file
+    pub glob: String,
+    /// Properties in declaration order (keys lowercased)
+    pub properties: Vec<(String, String)>,
+}
+
+/// A parsed `.editorconfig` file.
+#[derive(Debug, Clone, Default)]
+pub struct EditorConfigFile {
+    /// `root = true` in the preamble
+    pub root: bool,
+    /// Sections in file order
+    pub sections: Vec<EditorConfigSection>,
+}
+
+impl EditorConfigFile {
+    /// Parses the contents of an `.editorconfig` file.
+    ///
+    /// Invalid lines are ignored, as required by the spec.
+    pub fn parse(source: &str) -> Self {
+        let mut file = EditorConfigFile::default();
+
+        for raw_line in source.lines() {
+            let line = raw_line.trim();
+
+            if line.is_empty() || line.starts_with('#') || line.starts_with(';') {
+                continue;
+            }
+
+            if line.starts_with('[') && line.ends_with(']') {
+                file.sections.push(EditorConfigSection {
+                    glob: line[1..line.len() - 1].to_string(),
+                    properties: Vec::new(),
+                });
+                continue;
+            }
+
+            let Some((key, value)) = line.split_once('=') else {
+                continue;
+            };
+
+            let key = key.trim().to_lowercase();
+            let value = value.trim().to_string();
+            if key.is_empty() {
+                continue;
+            }
+
+            match file.sections.last_mut() {
+                Some(section) => {
+                    // Values are case-insensitive for all known properties
+                    section.properties.push((key, value.to_lowercase()));
+                }
+                None => {
+                    if key == "root" {
+                        file.root = value.eq_ignore_ascii_case("true");
+                    }
+                }
+            }
+        }
+
+        file
+    }
+
+    /// Collects the properties of all sections matching a path.
+    ///
+    /// Parameters:
+    /// - `relative_path`: Path of the file relative to this `.editorconfig`'s
+    ///   directory, using `/` as separator
+    /// - `pairs`: Accumulated properties (later matches override)
+    pub fn apply_matching(&self, relative_path: &str, pairs: &mut HashMap<String, String>) {
+        for section in &self.sections {
+            if !section_matches(&section.glob, relative_path) {
+                continue;
+            }
+
+            for (key, value) in &section.properties {
+                if value == "unset" {
+                    pairs.remove(key);
+                } else {
+                    pairs.insert(key.clone(), value.clone());
+                }
+            }
+        }
+    }
+}
+
+/// Resolves the effective EditorConfig properties for a file on disk.
+///
+/// Parameters:
+/// - `file_path`: Path of the file being edited (does not need to exist)
+///
+/// Returns: Resolved properties (empty if no `.editorconfig` applies)
+pub fn resolve_for_path(file_path: &Path) -> std::io::Result<EditorConfigProperties> {
+    let file_path = absolute_path(file_path)?;
+    let mut files = Vec::new();
+
+    let mut dir = file_path.parent();
+    while let Some(current) = dir {
+        let candidate = current.join(EDITORCONFIG_FILE_NAME);
+        if candidate.is_file() {
+            let parsed = EditorConfigFile::parse(&fs::read_to_string(&candidate)?);
+            let is_root = parsed.root;
+            files.push((current.to_path_buf(), parsed));
+            if is_root {
+                break;
+            }
+        }
+        dir = current.parent();
+    }
+
+    // Closest file last, so it takes precedence
+    files.reverse();
+    Ok(resolve_with_files(&file_path, &files))
+}
+
+/// Resolves properties from already loaded `.editorconfig` files.
+///
+/// Parameters:
+/// - `file_path`: Absolute path of the file being edited
+/// - `files`: `(directory, file)` pairs ordered from outermost to innermost
+///
+/// Returns: Resolved properties
+pub fn resolve_with_files(
+    file_path: &Path,
+    files: &[(PathBuf, EditorConfigFile)],
+) -> EditorConfigProperties {
+    let mut pairs = HashMap::new();
+
+    for (dir, file) in files {
+        let Ok(relative) = file_path.strip_prefix(dir) else {
+            continue;
+        };
+        let relative = relative
+            .components()
+            .map(|c| c.as_os_str().to_string_lossy())
+            .collect::<Vec<_>>()
+            .join("/");
+        file.apply_matching(&relative, &mut pairs);
+    }
+
+    EditorConfigProperties::from_pairs(&pairs)
+}
+
+/// Checks if a section glob matches a path relative to the `.editorconfig`.
+///
+/// Globs without `/` match the file name in any directory; globs containing
+/// `/` are anchored to the `.editorconfig` directory.
+pub fn section_matches(glob: &str, relative_path: &str) -> bool {
+    let pattern = if glob.contains('/') {
+        glob.trim_start_matches('/').to_string()
+    } else {
+        format!("**/{}", glob)
+    };
+
+    let tokens = compile_glob(&pattern.chars().collect::<Vec<_>>());
+    let text: Vec<char> = relative_path.chars().collect();
+    match_tokens(&tokens, &text)
+}
+
+/// Glob token.
+#[derive(Debug, Clone)]
+enum GlobToken {
+    Literal(char),
+    /// `*` - any run of characters except `/`
+    Star,
+    /// `**` - any run of characters including `/`
+    DoubleStar,
+    /// `**/` - zero or more whole directories
+    DirWildcard,
+    /// `?` - any single character except `/`
+    AnyChar,
+    /// `[...]` / `[!...]`
+    Class { negated: bool, ranges: Vec<(char, char)> },
+    /// `{a,b,c}`
+    Alternatives(Vec<Vec<GlobToken>>),
+    /// `{n..m}`
+    NumberRange(i64, i64),
+}
+
+/// Compiles a glob pattern into tokens.
+fn compile_glob(pattern: &[char]) -> Vec<GlobToken> {
+    let mut tokens = Vec::new();
+    let mut i = 0;
+
+    while i < pattern.len() {
+        match pattern[i] {
+            '\\' if i + 1 < pattern.len() => {
+                tokens.push(GlobToken::Literal(pattern[i + 1]));
+                i += 2;
+            }
+            '*' => {
+                if pattern.get(i + 1) == Some(&'*') {
+                    if pattern.get(i + 2) == Some(&'/') {
+                        tokens.push(GlobToken::DirWildcard);
+                        i += 3;
+                    } else {
+                        tokens.push(GlobToken::DoubleStar);
+                        i += 2;
+                    }
+                } else {
+                    tokens.push(GlobToken::Star);
+                    i += 1;
+                }
+            }
+            '?' => {
+                tokens.push(GlobToken::AnyChar);
+                i += 1;
+            }
+            '[' => match parse_class(pattern, i) {
+                Some((token, next)) => {
+                    tokens.push(token);
+                    i = next;
+                }
+                None => {
+                    tokens.push(GlobToken::Literal('['));
+                    i += 1;
+                }
+            },
+            '{' => match find_closing_brace(pattern, i) {
+                Some(close) => {
+                    tokens.push(parse_braces(&pattern[i + 1..close]));
+                    i = close + 1;
+                }
+                None => {
+                    tokens.push(GlobToken::Literal('{'));
+                    i += 1;
+                }
+            },
+            c => {
+                tokens.push(GlobToken::Literal(c));
+                i += 1;
+            }
+        }
+    }
+
+    tokens
+}
+
+/// Parses a `[...]` character class starting at `start`.
+fn parse_class(pattern: &[char], start: usize) -> Option<(GlobToken, usize)> {
+    let mut i = start + 1;
+    let negated = matches!(pattern.get(i), Some('!') | Some('^'));
+    if negated {
+        i += 1;
+    }
+
+    let mut ranges = Vec::new();
+    let mut first = true;
+
+    while i < pattern.len() {
+        let c = pattern[i];
+        if c == ']' && !first {
+            return Some((GlobToken::Class { negated, ranges }, i + 1));
+        }
+        if c == '/' {
+            // Slashes are never matched inside a class
+            return None;
+        }
+        first = false;
+
+        let c = if c == '\\' && i + 1 < pattern.len() {
+            i += 1;
+            pattern[i]
+        } else {
+            c
+        };
+
+        if pattern.get(i + 1) == Some(&'-') && pattern.get(i + 2).is_some_and(|&e| e != ']') {
+            ranges.push((c, pattern[i + 2]));
+            i += 3;
+        } else {
+            ranges.push((c, c));
+            i += 1;
+        }
+    }
+
+    None
+}
+
+/// Finds the matching `}` for a `{` at `start`.
+fn find_closing_brace(pattern: &[char], start: usize) -> Option<usize> {
+    let mut depth = 0;
+    let mut i = start;
+
+    while i < pattern.len() {
+        match pattern[i] {
+            '\\' => i += 1,
+            '{' => depth += 1,
+            '}' => {
+                depth -= 1;
+                if depth == 0 {
+                    return Some(i);
+                }
+            }
+            _ => {}
+        }
+        i += 1;
+    }
+
+    None
+}
+
+/// Parses the inside of `{...}` into alternatives or a number range.
+fn parse_braces(inner: &[char]) -> GlobToken {
+    let text: String = inner.iter().collect();
+
+    if let Some((from, to)) = text.split_once("..") {
+        if let (Ok(from), Ok(to)) = (from.parse::<i64>(), to.parse::<i64>()) {
+            return GlobToken::NumberRange(from.min(to), from.max(to));
+        }
+    }
+
+    // Split on top-level commas
+    let mut alternatives = Vec::new();
+    let mut depth = 0;
+    let mut current_start = 0;
+    let mut i = 0;
+
+    while i < inner.len() {
+        match inner[i] {
+            '\\' => i += 1,
+            '{' => depth += 1,
+            '}' => depth -= 1,
+            ',' if depth == 0 => {
+                alternatives.push(compile_glob(&inner[current_start..i]));
+                current_start = i + 1;
+            }
+            _ => {}
+        }
+        i += 1;
+    }
+
+    if alternatives.is_empty() {
+        // `{single}` is not an alternation - match it literally
+        let mut literal = vec![GlobToken::Literal('{')];
+        literal.extend(compile_glob(inner));
+        literal.push(GlobToken::Literal('}'));
+        return GlobToken::Alternatives(vec![literal]);
+    }
+
+    alternatives.push(compile_glob(&inner[current_start..]));
+    GlobToken::Alternatives(alternatives)
+}
+
+/// Matches tokens against text (backtracking).
+fn match_tokens(tokens: &[GlobToken], text: &[char]) -> bool {
+    let Some((token, rest)) = tokens.split_first() else {
+        return text.is_empty();
+    };
+
+    match token {
+        GlobToken::Literal(c) => text.first() == Some(c) && match_tokens(rest, &text[1..]),
+        GlobToken::AnyChar => {
+            text.first().is_some_and(|&c| c != '/') && match_tokens(rest, &text[1..])
+        }
+        GlobToken::Star => {
+            for i in 0..=text.len() {
+                if match_tokens(rest, &text[i..]) {
+                    return true;
+                }
+                if i < text.len() && text[i] == '/' {
+                    break;
+                }
+            }
+            false
+        }
+        GlobToken::DoubleStar => (0..=text.len()).any(|i| match_tokens(rest, &text[i..])),
+        GlobToken::DirWildcard => {
+            if match_tokens(rest, text) {
+                return true;
+            }
+            text.iter()
+                .enumerate()
+                .filter(|(_, &c)| c == '/')
+                .any(|(i, _)| match_tokens(rest, &text[i + 1..]))
+        }
+        GlobToken::Class { negated, ranges } => {
+            let Some(&c) = text.first() else {
+                return false;
+            };
+            let in_class = ranges.iter().any(|&(lo, hi)| lo <= c && c <= hi);
+            c != '/' && in_class != *negated && match_tokens(rest, &text[1..])
+        }
+        GlobToken::Alternatives(alternatives) => alternatives.iter().any(|alternative| {
+            let mut combined = alternative.clone();
+            combined.extend_from_slice(rest);
+            match_tokens(&combined, text)
+        }),
+        GlobToken::NumberRange(from, to) => {
+            let sign_len = usize::from(text.first() == Some(&'-'));
+            let digits = text[sign_len..]
+                .iter()
+                .take_while(|c| c.is_ascii_digit())
+                .count();
+
+            (1..=digits).any(|len| {
+                let number: String = text[..sign_len + len].iter().collect();
+                number
+                    .parse::<i64>()
+                    .is_ok_and(|n| *from <= n && n <= *to)
+                    && match_tokens(rest, &text[sign_len + len..])
+            })
+        }
+    }
+}
+
+/// Helper: Parses a positive integer property.
+fn parse_positive(value: &str) -> Option<usize> {
+    value.parse::<usize>().ok().filter(|&n| n > 0)
+}
+
+/// Helper: Parses a boolean property.
+fn parse_bool(value: &str) -> Option<bool> {
+    match value {
+        "true" => Some(true),
+        "false" => Some(false),
+        _ => None,
+    }
+}
+
+/// Helper: Makes a path absolute without touching the filesystem.
+fn absolute_path(path: &Path) -> std::io::Result<PathBuf> {
+    if path.is_absolute() {
+        Ok(path.to_path_buf())
+    } else {
+        Ok(std::env::current_dir()?.join(path))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn temp_dir(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!(
+            "editor_native_editorconfig_{}_{}",
+            name,
+            std::process::id()
+        ));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    #[test]
+    fn test_parse_sections_and_root() {
+        let file = EditorConfigFile::parse(
+            "# comment\nroot = true\n\n[*]\nindent_style = space\n; other comment\n[*.py]\nindent_size = 4\n",
+        );
+
+        assert!(file.root);
+        assert_eq!(file.sections.len(), 2);
+        assert_eq!(file.sections[0].glob, "*");
+        assert_eq!(
+            file.sections[0].properties,
+            vec![("indent_style".to_string(), "space".to_string())]
+        );
+        assert_eq!(file.sections[1].glob, "*.py");
+    }
+
+    #[test]
+    fn test_parse_ignores_invalid_lines() {
+        let file = EditorConfigFile::parse("[*]\nthis is not valid\nINDENT_STYLE = TAB\n");
+        assert_eq!(
+            file.sections[0].properties,
+            vec![("indent_style".to_string(), "tab".to_string())]
+        );
+    }
+
+    #[test]
+    fn test_glob_basename_matching() {
+        assert!(section_matches("*", "main.rs"));
+        assert!(section_matches("*.rs", "src/editor/mod.rs"));
+        assert!(!section_matches("*.rs", "src/editor/mod.py"));
+        assert!(section_matches("Makefile", "sub/Makefile"));
+    }
+
+    #[test]
+    fn test_glob_anchored_paths() {
+        assert!(section_matches("src/*.rs", "src/lib.rs"));
+        assert!(!section_matches("src/*.rs", "src/editor/mod.rs"));
+        assert!(section_matches("src/**.rs", "src/editor/mod.rs"));
+        assert!(section_matches("/lib/**/*.js", "lib/a/b/c.js"));
+        assert!(section_matches("/lib/**/*.js", "lib/c.js"));
+        assert!(!section_matches("/lib/**/*.js", "other/lib/c.js"));
+    }
+
+    #[test]
+    fn test_glob_braces_and_classes() {
+        assert!(section_matches("*.{js,ts}", "app.ts"));
+        assert!(section_matches("*.{js,ts}", "app.js"));
+        assert!(!section_matches("*.{js,ts}", "app.rs"));
+        assert!(section_matches("*.{json,{yml,yaml}}", "ci.yaml"));
+        assert!(section_matches("file[0-9].txt", "file7.txt"));
+        assert!(!section_matches("file[!0-9].txt", "file7.txt"));
+        assert!(section_matches("?.md", "a.md"));
+        assert!(!section_matches("?.md", "ab.md"));
+        assert!(section_matches("{single}.txt", "{single}.txt"));
+    }
+
+    #[test]
+    fn test_glob_number_range() {
+        assert!(section_matches("log{1..10}.txt", "log3.txt"));
+        assert!(section_matches("log{1..10}.txt", "log10.txt"));
+        assert!(!section_matches("log{1..10}.txt", "log11.txt"));
+        assert!(section_matches("n{-5..5}", "n-3"));
+    }
+
+    #[test]
+    fn test_properties_defaults() {
+        let mut pairs = HashMap::new();
+        pairs.insert("indent_style".to_string(), "tab".to_string());
+        pairs.insert("tab_width".to_string(), "8".to_string());
+
+        let props = EditorConfigProperties::from_pairs(&pairs);
+        assert_eq!(props.indent_size, Some(IndentSize::Tab));
+        assert_eq!(props.resolved_indent_size(), Some(8));
+
+        let mut pairs = HashMap::new();
+        pairs.insert("indent_size".to_string(), "2".to_string());
+        let props = EditorConfigProperties::from_pairs(&pairs);
+        assert_eq!(props.tab_width, Some(2));
+    }
+
+    #[test]
+    fn test_properties_max_line_length_off() {
+        let mut pairs = HashMap::new();
+        pairs.insert("max_line_length".to_string(), "off".to_string());
+        assert_eq!(EditorConfigProperties::from_pairs(&pairs).max_line_length, None);
+
+        pairs.insert("max_line_length".to_string(), "100".to_string());
+        assert_eq!(EditorConfigProperties::from_pairs(&pairs).max_line_length, Some(100));
+    }
+
+    #[test]
+    fn test_later_sections_override_and_unset() {
+        let file = EditorConfigFile::parse(
+            "[*]\nindent_size = 4\ntrim_trailing_whitespace = true\n[*.md]\nindent_size = 2\ntrim_trailing_whitespace = unset\n",
+        );
+        let files = vec![(PathBuf::from("/project"), file)];
+
+        let props = resolve_with_files(Path::new("/project/docs/README.md"), &files);
+        assert_eq!(props.indent_size, Some(IndentSize::Columns(2)));
+        assert_eq!(props.trim_trailing_whitespace, None);
+
+        let props = resolve_with_files(Path::new("/project/src/main.rs"), &files);
+        assert_eq!(props.indent_size, Some(IndentSize::Columns(4)));
+        assert_eq!(props.trim_trailing_whitespace, Some(true));
+    }
+
+    #[test]
+    fn test_resolve_for_path_precedence_and_root() {
+        let base = temp_dir("precedence");
+        let project = base.join("project");
+        let nested = project.join("nested");
+        fs::create_dir_all(&nested).unwrap();
+
+        // Above the root - must be ignored
+        fs::write(base.join(EDITORCONFIG_FILE_NAME), "[*]\ncharset = latin1\n").unwrap();
+        fs::write(
+            project.join(EDITORCONFIG_FILE_NAME),
+            "root = true\n[*]\nindent_style = space\nindent_size = 4\nend_of_line = lf\n",
+        )
+        .unwrap();
+        fs::write(
+            nested.join(EDITORCONFIG_FILE_NAME),
+            "[*.rs]\nindent_size = 2\nend_of_line = crlf\n",
+        )
+        .unwrap();
+
+        let props = resolve_for_path(&nested.join("main.rs")).unwrap();
+        assert_eq!(props.indent_style, Some(IndentStyle::Space));
+        assert_eq!(props.indent_size, Some(IndentSize::Columns(2)));
+        assert_eq!(props.end_of_line, Some(LineEnding::CrLf));
+        assert_eq!(props.charset, None);
+
+        let props = resolve_for_path(&nested.join("notes.txt")).unwrap();
+        assert_eq!(props.indent_size, Some(IndentSize::Columns(4)));
+        assert_eq!(props.end_of_line, Some(LineEnding::Lf));
+
+        fs::remove_dir_all(&base).unwrap();
+    }
+
+    #[test]
+    fn test_line_ending_normalize() {
+        assert_eq!(LineEnding::CrLf.normalize("a\nb\r\nc\rd"), "a\r\nb\r\nc\r\nd");
+        assert_eq!(LineEnding::Lf.normalize("a\r\nb\rc"), "a\nb\nc");
+    }
+
+    #[test]
+    fn test_charset_encode() {
+        assert_eq!(Charset::Utf8Bom.encode("a"), vec![0xEF, 0xBB, 0xBF, b'a']);
+        assert_eq!(Charset::Latin1.encode("é€"), vec![0xE9, b'?']);
+        assert_eq!(Charset::Utf16Le.encode("a"), vec![0xFF, 0xFE, b'a', 0]);
+        assert_eq!(Charset::Utf16Be.encode("a"), vec![0xFE, 0xFF, 0, b'a']);
+        assert_eq!(Charset::Utf8Bom.encode("\u{FEFF}a"), vec![0xEF, 0xBB, 0xBF, b'a']);
+    }
+
+    #[test]
+    fn test_charset_decode() {
+        assert_eq!(Charset::Utf8.decode(&[0xEF, 0xBB, 0xBF, b'a']).unwrap(), "a");
+        assert_eq!(Charset::Utf8Bom.decode(b"a").unwrap(), "a");
+        assert!(Charset::Utf8.decode(&[0xE9]).is_err());
+        assert_eq!(Charset::Latin1.decode(&[b'a', 0xE9]).unwrap(), "aé");
+        assert_eq!(Charset::Utf16Le.decode(&[0xFF, 0xFE, b'a', 0]).unwrap(), "a");
+        assert_eq!(Charset::Utf16Be.decode(&[0xFE, 0xFF, 0, b'a']).unwrap(), "a");
+        assert!(Charset::Utf16Le.decode(&[0xFF, 0xFE, b'a']).is_err());
+
+        assert_eq!(Charset::detect(&[0xEF, 0xBB, 0xBF]), Some(Charset::Utf8Bom));
+        assert_eq!(Charset::detect(&[0xFF, 0xFE, b'a', 0]), Some(Charset::Utf16Le));
+        assert_eq!(Charset::detect(b"a"), None);
+    }
+
+    #[test]
+    fn test_charset_round_trip() {
+        let text = "héllo\nwörld €\n";
+        for charset in [Charset::Utf8, Charset::Utf8Bom, Charset::Utf16Be, Charset::Utf16Le] {
+            let bytes = charset.encode(text);
+            assert_eq!(charset.decode(&bytes).unwrap(), text, "{:?}", charset);
+            assert_eq!(charset.encode(&charset.decode(&bytes).unwrap()), bytes, "{:?}", charset);
+        }
+        let latin1 = Charset::Latin1.encode("héllo\n");
+        assert_eq!(Charset::Latin1.encode(&Charset::Latin1.decode(&latin1).unwrap()), latin1);
+    }
+
+    #[test]
+    fn test_to_indent_config() {
+        let props = EditorConfigProperties {
+            indent_style: Some(IndentStyle::Tab),
+            indent_size: Some(IndentSize::Tab),
+            tab_width: Some(8),
+            ..Default::default()
+        };
+        let config = props.to_indent_config(&IndentConfig::default());
+        assert!(!config.use_spaces);
+        assert_eq!(config.tab_size, 8);
+        assert_eq!(config.indent_string(), "\t");
+
+        let props = EditorConfigProperties {
+            indent_size: Some(IndentSize::Columns(2)),
+            ..Default::default()
+        };
+        let config = props.to_indent_config(&IndentConfig::default());
+        assert!(config.use_spaces);
+        assert_eq!(config.indent_string(), "  ");
+    }
+}
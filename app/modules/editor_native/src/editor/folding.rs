@@ -0,0 +1,207 @@
+use std::collections::BTreeMap;
+
+use ropey::Rope;
+use serde::Serialize;
+use tree_sitter::{Node, Range, Tree};
+
+/// Node kinds that fold besides `*block*`, `*body*` and `*_list` nodes.
+const FOLDABLE_KINDS: &[&str] = &[
+    "object",
+    "array",
+    "dictionary",
+    "list",
+    "arguments",
+    "parameters",
+    "formal_parameters",
+    "element",
+    "script_element",
+    "style_element",
+    "fenced_code_block",
+    "template_string",
+    "import_statement",
+    "use_declaration",
+];
+
+/// Foldable line range (LSP `FoldingRange` shape).
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct FoldingRange {
+    /// Line that stays visible when folded
+    pub start_line: usize,
+
+    /// Last hidden line
+    pub end_line: usize,
+
+    /// "comment", "imports", or None for code regions
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub kind: Option<&'static str>,
+}
+
+/// Computes folding ranges from one or more syntax layers.
+///
+/// Each layer contributes its multi-line block-like nodes, so code
+/// injected into another language folds by its own grammar. When a
+/// closing line starts with a bracket or end tag it stays visible.
+///
+/// Parameters:
+/// - `rope`: Document
+/// - `layers`: Trees with their included ranges (None for the root tree)
+///
+/// Returns: Ranges sorted by start line (one per start line, the largest)
+pub fn folding_ranges(rope: &Rope, layers: &[(&Tree, Option<&[Range]>)]) -> Vec<FoldingRange> {
+    let mut by_start: BTreeMap<usize, FoldingRange> = BTreeMap::new();
+
+    for (tree, ranges) in layers {
+        let root = tree.root_node();
+        let mut cursor = root.walk();
+        let mut stack: Vec<Node> = root.named_children(&mut cursor).collect();
+
+        while let Some(node) = stack.pop() {
+            let mut child_cursor = node.walk();
+            stack.extend(node.named_children(&mut child_cursor));
+
+            if let Some(ranges) = ranges {
+                let inside = ranges
+                    .iter()
+                    .any(|r| r.start_byte <= node.start_byte() && node.end_byte() <= r.end_byte);
+                if !inside {
+                    continue;
+                }
+            }
+
+            let Some(range) = node_folding_range(rope, node) else {
+                continue;
+            };
+            let keep_existing = by_start
+                .get(&range.start_line)
+                .is_some_and(|existing| existing.end_line >= range.end_line);
+            if !keep_existing {
+                by_start.insert(range.start_line, range);
+            }
+        }
+    }
+
+    by_start.into_values().collect()
+}
+
+/// Helper: Gets the folding range of a node, if it folds.
+fn node_folding_range(rope: &Rope, node: Node) -> Option<FoldingRange> {
+    let kind = node.kind();
+    let mut start_line = node.start_position().row;
+
+    // Indentation-delimited bodies (Python) start below their header
+    if kind == "block" {
+        if let Some(parent) = node.parent() {
+            let parent_kind = parent.kind();
+            if parent.start_position().row < start_line
+                && (parent_kind.ends_with("_definition")
+                    || parent_kind.ends_with("_statement")
+                    || parent_kind.ends_with("_clause"))
+            {
+                start_line = parent.start_position().row;
+            }
+        }
+    }
+    let mut end_line = node.end_position().row;
+
+    // Nodes ending at column 0 stop on the previous line
+    if node.end_position().column == 0 && end_line > start_line {
+        end_line -= 1;
+    }
+    if end_line <= start_line {
+        return None;
+    }
+
+    let is_comment = kind.contains("comment");
+    let is_import = kind.contains("import") || kind == "use_declaration";
+    let foldable = is_comment
+        || kind.contains("block")
+        || kind.contains("body")
+        || kind.ends_with("_list")
+        || FOLDABLE_KINDS.contains(&kind);
+    if !foldable {
+        return None;
+    }
+
+    // Keep closing brackets / end tags visible
+    let last_line = rope.line(end_line).to_string();
+    let closing = last_line.trim_start();
+    if !is_comment && (closing.starts_with(['}', ']', ')']) || closing.starts_with("</")) {
+        end_line -= 1;
+        if end_line <= start_line {
+            return None;
+        }
+    }
+
+    Some(FoldingRange {
+        start_line,
+        end_line,
+        kind: if is_comment {
+            Some("comment")
+        } else if is_import {
+            Some("imports")
+        } else {
+            None
+        },
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tree_sitter::Parser;
+
+    fn parse_rust(source: &str) -> Tree {
+        let mut parser = Parser::new();
+        parser.set_language(tree_sitter_rust::language()).unwrap();
+        parser.parse(source, None).unwrap()
+    }
+
+    #[test]
+    fn test_folding_ranges_rust() {
+        let source = "\
+/* header
+   comment */
+fn main() {
+    if ready {
+        go();
+    }
+}
+";
+        let tree = parse_rust(source);
+        let ranges = folding_ranges(&Rope::from_str(source), &[(&tree, None)]);
+
+        assert_eq!(
+            ranges,
+            vec![
+                FoldingRange { start_line: 0, end_line: 1, kind: Some("comment") },
+                FoldingRange { start_line: 2, end_line: 5, kind: None },
+                FoldingRange { start_line: 3, end_line: 4, kind: None },
+            ]
+        );
+    }
+
+    #[test]
+    fn test_folding_ranges_python_header() {
+        let source = "class A:\n    def run(self):\n        pass\n";
+        let mut parser = Parser::new();
+        parser.set_language(tree_sitter_python::language()).unwrap();
+        let tree = parser.parse(source, None).unwrap();
+
+        let ranges = folding_ranges(&Rope::from_str(source), &[(&tree, None)]);
+        assert_eq!(
+            ranges,
+            vec![
+                FoldingRange { start_line: 0, end_line: 2, kind: None },
+                FoldingRange { start_line: 1, end_line: 2, kind: None },
+            ]
+        );
+    }
+
+    #[test]
+    fn test_folding_single_line_blocks() {
+        let source = "fn main() { go(); }\n";
+        let tree = parse_rust(source);
+        assert!(folding_ranges(&Rope::from_str(source), &[(&tree, None)]).is_empty());
+    }
+}
@@ -0,0 +1,165 @@
+use std::collections::HashMap;
+use std::ops::Range as ByteRange;
+use std::sync::OnceLock;
+
+use ropey::Rope;
+use serde::Serialize;
+use tree_sitter::{Query, QueryCursor, Range, Tree};
+
+use crate::editor::lsp_types::LspRange;
+use crate::editor::LanguageId;
+
+/// Highlighted span of the document.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct HighlightSpan {
+    pub start_byte: usize,
+    pub end_byte: usize,
+    pub range: LspRange,
+
+    /// Highlight capture name (e.g. "keyword", "string", "function")
+    pub capture: String,
+
+    /// Language the span was highlighted with
+    pub language: &'static str,
+
+    /// Injection depth (0 = root document)
+    pub depth: usize,
+}
+
+/// Gets the compiled highlights query for a language.
+///
+/// TypeScript combines its own query with the JavaScript one (the
+/// TypeScript grammar extends JavaScript).
+///
+/// Returns: Query, or None if the language has no grammar
+pub fn highlights_query(language: &LanguageId) -> Option<&'static Query> {
+    static QUERIES: OnceLock<HashMap<LanguageId, Query>> = OnceLock::new();
+
+    let queries = QUERIES.get_or_init(|| {
+        let typescript = format!(
+            "{}\n{}",
+            tree_sitter_typescript::HIGHLIGHT_QUERY,
+            tree_sitter_javascript::HIGHLIGHT_QUERY
+        );
+        let sources: [(LanguageId, &str); 9] = [
+            (LanguageId::Rust, tree_sitter_rust::HIGHLIGHT_QUERY),
+            (LanguageId::JavaScript, tree_sitter_javascript::HIGHLIGHT_QUERY),
+            (LanguageId::TypeScript, &typescript),
+            (LanguageId::Python, tree_sitter_python::HIGHLIGHT_QUERY),
+            (LanguageId::Java, tree_sitter_java::HIGHLIGHT_QUERY),
+            (LanguageId::Go, tree_sitter_go::HIGHLIGHT_QUERY),
+            (LanguageId::Html, tree_sitter_html::HIGHLIGHT_QUERY),
+            (LanguageId::Css, tree_sitter_css::HIGHLIGHTS_QUERY),
+            (LanguageId::Markdown, tree_sitter_md::HIGHLIGHTS_QUERY),
+        ];
+
+        sources
+            .into_iter()
+            .filter_map(|(language, source)| {
+                let ts_language = language.tree_sitter_language()?;
+                let query = Query::new(ts_language, source).unwrap_or_else(|e| {
+                    panic!("invalid bundled highlights query for {:?}: {}", language, e)
+                });
+                Some((language, query))
+            })
+            .collect()
+    });
+
+    queries.get(language)
+}
+
+/// Highlights one syntax layer within a byte range.
+///
+/// Parameters:
+/// - `tree`: Layer syntax tree
+/// - `language`: Layer language
+/// - `rope`: Document
+/// - `source`: Document text
+/// - `byte_range`: Visible byte range
+/// - `ranges`: Included ranges of an injected layer (None for the root)
+/// - `depth`: Injection depth
+///
+/// Returns: Spans in document order
+pub fn highlight_layer(
+    tree: &Tree,
+    language: &LanguageId,
+    rope: &Rope,
+    source: &str,
+    byte_range: ByteRange<usize>,
+    ranges: Option<&[Range]>,
+    depth: usize,
+) -> Vec<HighlightSpan> {
+    let Some(query) = highlights_query(language) else {
+        return Vec::new();
+    };
+
+    let mut cursor = QueryCursor::new();
+    cursor.set_byte_range(byte_range);
+
+    let mut spans = Vec::new();
+    for (m, capture_index) in cursor.captures(query, tree.root_node(), source.as_bytes()) {
+        let capture = m.captures[capture_index];
+        let node = capture.node;
+
+        // Injected trees only cover their included ranges
+        if let Some(ranges) = ranges {
+            let inside = ranges
+                .iter()
+                .any(|r| r.start_byte <= node.start_byte() && node.end_byte() <= r.end_byte);
+            if !inside {
+                continue;
+            }
+        }
+
+        spans.push(HighlightSpan {
+            start_byte: node.start_byte(),
+            end_byte: node.end_byte(),
+            range: LspRange::from_node(rope, node),
+            capture: query.capture_names()[capture.index as usize].clone(),
+            language: language.name(),
+            depth,
+        });
+    }
+
+    spans
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tree_sitter::Parser;
+
+    #[test]
+    fn test_all_highlights_queries_compile() {
+        for language in [
+            LanguageId::Rust,
+            LanguageId::JavaScript,
+            LanguageId::TypeScript,
+            LanguageId::Python,
+            LanguageId::Java,
+            LanguageId::Go,
+            LanguageId::Html,
+            LanguageId::Css,
+            LanguageId::Markdown,
+        ] {
+            assert!(highlights_query(&language).is_some(), "{:?}", language);
+        }
+    }
+
+    #[test]
+    fn test_highlight_layer_rust() {
+        let source = "fn main() { let s = \"hi\"; }\n";
+        let mut parser = Parser::new();
+        parser.set_language(tree_sitter_rust::language()).unwrap();
+        let tree = parser.parse(source, None).unwrap();
+        let rope = Rope::from_str(source);
+
+        let spans = highlight_layer(&tree, &LanguageId::Rust, &rope, source, 0..source.len(), None, 0);
+
+        let keyword = spans.iter().find(|s| s.capture == "keyword").unwrap();
+        assert_eq!(&source[keyword.start_byte..keyword.end_byte], "fn");
+        assert!(spans.iter().any(|s| s.capture == "string" && s.range.start.character == 20));
+        assert!(spans.iter().all(|s| s.language == "rust" && s.depth == 0));
+    }
+}
@@ -0,0 +1,290 @@
+use ropey::Rope;
+use serde::Serialize;
+use tree_sitter::{Point, Tree};
+
+use crate::editor::cursor::Position;
+
+/// Vertical indentation guide in a viewport.
+///
+/// Example (tab size 4, guides drawn as `|`):
+/// ```text
+/// fn main() {
+/// |   if ready {
+/// |   |   start();
+/// |   }
+/// }
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct IndentGuide {
+    /// Visual column the guide is drawn at
+    pub column: usize,
+
+    /// Indentation level (1 = first level of indentation)
+    pub level: usize,
+
+    /// First line of the guide (clipped to the viewport)
+    pub start_line: usize,
+
+    /// Last line of the guide (clipped to the viewport)
+    pub end_line: usize,
+
+    /// Guide of the scope containing the cursor
+    pub active: bool,
+}
+
+/// Computes indentation guides for a viewport.
+///
+/// Levels come from indentation; blank lines inherit the level of the
+/// surrounding block so guides are not interrupted. The active guide is
+/// the body of the innermost multi-line syntax node around the cursor,
+/// or the cursor line's block when no syntax tree is available.
+///
+/// Parameters:
+/// - `rope`: The rope
+/// - `tree`: Current syntax tree (if any)
+/// - `start_line`: First visible line
+/// - `end_line`: Last visible line (inclusive)
+/// - `cursor`: Cursor position
+/// - `tab_size`: Width of one indentation level
+///
+/// Returns: Guides ordered by start line, then level
+pub fn indent_guides(
+    rope: &Rope,
+    tree: Option<&Tree>,
+    start_line: usize,
+    end_line: usize,
+    cursor: Position,
+    tab_size: usize,
+) -> Vec<IndentGuide> {
+    let tab_size = tab_size.max(1);
+    let last_line = rope.len_lines().saturating_sub(1);
+    let end_line = end_line.min(last_line);
+    if start_line > end_line {
+        return Vec::new();
+    }
+
+    // Sweep the viewport, opening/closing one run per level
+    let mut guides = Vec::new();
+    let mut open: Vec<usize> = Vec::new(); // start line of each open level
+
+    for line in start_line..=end_line {
+        let level = line_level(rope, line, tab_size);
+
+        while open.len() > level {
+            let run_start = open.pop().unwrap_or(line);
+            guides.push(new_guide(open.len() + 1, run_start, line - 1, tab_size));
+        }
+        while open.len() < level {
+            open.push(line);
+        }
+    }
+    while let Some(run_start) = open.pop() {
+        guides.push(new_guide(open.len() + 1, run_start, end_line, tab_size));
+    }
+
+    if let Some((level, first, last)) = active_block(rope, tree, cursor, tab_size) {
+        for guide in &mut guides {
+            if guide.level == level && guide.start_line <= last && guide.end_line >= first {
+                guide.active = true;
+            }
+        }
+    }
+
+    guides.sort_by_key(|guide| (guide.start_line, guide.level));
+    guides
+}
+
+/// Gets the visual indentation width of a line (tabs advance to tab stops).
+pub fn line_indent_width(rope: &Rope, line: usize, tab_size: usize) -> usize {
+    let tab_size = tab_size.max(1);
+    let mut width = 0;
+
+    for c in rope.line(line).chars() {
+        match c {
+            ' ' => width += 1,
+            '\t' => width += tab_size - width % tab_size,
+            _ => break,
+        }
+    }
+
+    width
+}
+
+/// Checks if a line contains only whitespace.
+pub fn is_blank_line(rope: &Rope, line: usize) -> bool {
+    rope.line(line).chars().all(char::is_whitespace)
+}
+
+/// Gets the indentation level of a line.
+///
+/// Blank lines between two lines of different depth belong to the deeper
+/// block (one level below the shallower neighbour). Blank lines at the
+/// start or end of the document have level 0.
+pub fn line_level(rope: &Rope, line: usize, tab_size: usize) -> usize {
+    let tab_size = tab_size.max(1);
+    if !is_blank_line(rope, line) {
+        return line_indent_width(rope, line, tab_size) / tab_size;
+    }
+
+    let above = (0..line)
+        .rev()
+        .find(|&l| !is_blank_line(rope, l))
+        .map(|l| line_indent_width(rope, l, tab_size) / tab_size);
+    let below = (line + 1..rope.len_lines())
+        .find(|&l| !is_blank_line(rope, l))
+        .map(|l| line_indent_width(rope, l, tab_size) / tab_size);
+
+    match (above, below) {
+        (Some(above), Some(below)) => above.min(below) + usize::from(above != below),
+        _ => 0, // Leading/trailing blank lines
+    }
+}
+
+/// Helper: Finds the active block as (level, first line, last line).
+fn active_block(
+    rope: &Rope,
+    tree: Option<&Tree>,
+    cursor: Position,
+    tab_size: usize,
+) -> Option<(usize, usize, usize)> {
+    if let Some(tree) = tree {
+        let cursor = Position::clamp(&cursor, rope);
+        let line = rope.line(cursor.line);
+        let point = Point::new(cursor.line, line.char_to_byte(cursor.column.min(line.len_chars())));
+
+        let mut current = tree.root_node().descendant_for_point_range(point, point);
+        while let Some(node) = current {
+            let header = node.start_position().row;
+            if node.end_position().row > header {
+                let level = line_level(rope, header, tab_size) + 1;
+                if let Some(block) = expand_block(rope, header + 1, level, tab_size) {
+                    return Some(block);
+                }
+            }
+            current = node.parent();
+        }
+        return None;
+    }
+
+    // Indentation fallback: a header line activates the block below it
+    let level = line_level(rope, cursor.line, tab_size);
+    let next = cursor.line + 1;
+    if next < rope.len_lines() && line_level(rope, next, tab_size) > level {
+        return expand_block(rope, next, level + 1, tab_size);
+    }
+    expand_block(rope, cursor.line, level, tab_size)
+}
+
+/// Helper: Expands the run of lines at `level` or deeper around a line.
+fn expand_block(rope: &Rope, line: usize, level: usize, tab_size: usize) -> Option<(usize, usize, usize)> {
+    if level == 0 || line >= rope.len_lines() || line_level(rope, line, tab_size) < level {
+        return None;
+    }
+
+    let mut first = line;
+    while first > 0 && line_level(rope, first - 1, tab_size) >= level {
+        first -= 1;
+    }
+    let mut last = line;
+    while last + 1 < rope.len_lines() && line_level(rope, last + 1, tab_size) >= level {
+        last += 1;
+    }
+
+    Some((level, first, last))
+}
+
+/// Helper: Creates an inactive guide.
+fn new_guide(level: usize, start_line: usize, end_line: usize, tab_size: usize) -> IndentGuide {
+    IndentGuide {
+        column: (level - 1) * tab_size,
+        level,
+        start_line,
+        end_line,
+        active: false,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tree_sitter::Parser;
+
+    const SOURCE: &str = "\
+fn main() {
+    if ready {
+        start();
+
+        stop();
+    }
+    done();
+}
+";
+
+    fn parse_rust(source: &str) -> Tree {
+        let mut parser = Parser::new();
+        parser.set_language(tree_sitter_rust::language()).unwrap();
+        parser.parse(source, None).unwrap()
+    }
+
+    fn summary(guides: &[IndentGuide]) -> Vec<(usize, usize, usize, bool)> {
+        guides
+            .iter()
+            .map(|g| (g.level, g.start_line, g.end_line, g.active))
+            .collect()
+    }
+
+    #[test]
+    fn test_line_indent_width() {
+        let rope = Rope::from_str("    a\n\tb\n  \tc\nd");
+        assert_eq!(line_indent_width(&rope, 0, 4), 4);
+        assert_eq!(line_indent_width(&rope, 1, 4), 4);
+        assert_eq!(line_indent_width(&rope, 2, 4), 4);
+        assert_eq!(line_indent_width(&rope, 3, 4), 0);
+    }
+
+    #[test]
+    fn test_blank_line_level() {
+        let rope = Rope::from_str(SOURCE);
+        assert_eq!(line_level(&rope, 3, 4), 2);
+    }
+
+    #[test]
+    fn test_indent_guides_syntax_active() {
+        let rope = Rope::from_str(SOURCE);
+        let tree = parse_rust(SOURCE);
+
+        let guides = indent_guides(&rope, Some(&tree), 0, 7, Position::new(2, 10), 4);
+        assert_eq!(
+            summary(&guides),
+            vec![(1, 1, 6, false), (2, 2, 4, true)]
+        );
+        assert_eq!(guides[1].column, 4);
+
+        // Cursor on the `fn` header activates the function body guide
+        let guides = indent_guides(&rope, Some(&tree), 0, 7, Position::new(0, 3), 4);
+        assert_eq!(summary(&guides), vec![(1, 1, 6, true), (2, 2, 4, false)]);
+    }
+
+    #[test]
+    fn test_indent_guides_clipped_to_viewport() {
+        let rope = Rope::from_str(SOURCE);
+        let tree = parse_rust(SOURCE);
+
+        let guides = indent_guides(&rope, Some(&tree), 3, 5, Position::new(6, 4), 4);
+        assert_eq!(summary(&guides), vec![(1, 3, 5, true), (2, 3, 4, false)]);
+    }
+
+    #[test]
+    fn test_indent_guides_indentation_fallback() {
+        let source = "a:\n  b:\n    c\n  d\n";
+        let rope = Rope::from_str(source);
+
+        let guides = indent_guides(&rope, None, 0, 4, Position::new(2, 4), 2);
+        assert_eq!(summary(&guides), vec![(1, 1, 3, false), (2, 2, 2, true)]);
+
+        // Header line activates its body
+        let guides = indent_guides(&rope, None, 0, 4, Position::new(1, 0), 2);
+        assert_eq!(summary(&guides), vec![(1, 1, 3, false), (2, 2, 2, true)]);
+    }
+}
@@ -0,0 +1,436 @@
+use std::collections::{HashMap, VecDeque};
+use std::sync::OnceLock;
+
+use tree_sitter::{InputEdit, Node, Parser, Point, Query, QueryCursor, Range, Tree};
+
+use crate::editor::LanguageId;
+
+const HTML_INJECTIONS: &str = include_str!("queries/html/injections.scm");
+const MARKDOWN_INJECTIONS: &str = include_str!("queries/markdown/injections.scm");
+const JAVASCRIPT_INJECTIONS: &str = include_str!("queries/javascript/injections.scm");
+const TYPESCRIPT_INJECTIONS: &str = include_str!("queries/typescript/injections.scm");
+const PYTHON_INJECTIONS: &str = include_str!("queries/python/injections.scm");
+
+/// Maximum nesting of injected languages (e.g. Markdown > HTML > JS = 2).
+const MAX_INJECTION_DEPTH: usize = 4;
+
+const CONTENT_CAPTURE: &str = "injection.content";
+const LANGUAGE_CAPTURE: &str = "injection.language";
+const LANGUAGE_PROPERTY: &str = "injection.language";
+const COMBINED_PROPERTY: &str = "injection.combined";
+const INCLUDE_CHILDREN_PROPERTY: &str = "injection.include-children";
+
+/// Embedded document parsed with its own grammar.
+///
+/// The tree covers only `ranges` of the host document (tree-sitter
+/// included ranges), so positions in the tree are document positions.
+pub struct InjectionLayer {
+    /// Injected language
+    pub language: LanguageId,
+
+    /// Document ranges parsed by this layer
+    pub ranges: Vec<Range>,
+
+    /// Syntax tree of the embedded code
+    pub tree: Tree,
+
+    /// Nesting depth (1 = injected into the root document)
+    pub depth: usize,
+}
+
+impl InjectionLayer {
+    /// Checks if a byte offset lies inside the layer.
+    pub fn contains_byte(&self, byte: usize) -> bool {
+        self.ranges
+            .iter()
+            .any(|range| range.start_byte <= byte && byte < range.end_byte)
+    }
+}
+
+/// Injection to parse, found by an injections query.
+struct PendingInjection {
+    language: LanguageId,
+    ranges: Vec<Range>,
+    depth: usize,
+}
+
+/// Injected language layers of a document.
+///
+/// Kept in sync with the root tree:
+/// - `edit` adjusts every layer tree and range with the edit
+/// - `update` re-runs the injection queries after the root reparse and
+///   reparses each layer incrementally, reusing the tree of the layer that
+///   starts at the same (edited) offset
+///
+/// Example (Markdown with a Rust fence):
+/// ```text
+/// # Title          <- root (markdown)
+/// ~~~rust
+/// fn main() {}     <- layer (rust, depth 1)
+/// ~~~
+/// ```
+#[derive(Default)]
+pub struct InjectionSet {
+    layers: Vec<InjectionLayer>,
+    parsers: HashMap<LanguageId, Parser>,
+}
+
+impl InjectionSet {
+    /// Creates an empty set.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Gets all layers (outer layers before the layers injected into them).
+    pub fn layers(&self) -> &[InjectionLayer] {
+        &self.layers
+    }
+
+    /// Drops all layers (e.g. when the document is replaced).
+    pub fn clear(&mut self) {
+        self.layers.clear();
+    }
+
+    /// Applies an edit to every layer tree and range.
+    pub fn edit(&mut self, edit: &InputEdit) {
+        for layer in &mut self.layers {
+            layer.tree.edit(edit);
+            for range in &mut layer.ranges {
+                edit_range(range, edit);
+            }
+        }
+    }
+
+    /// Recomputes injections after the root tree was reparsed.
+    ///
+    /// Parameters:
+    /// - `root`: Root syntax tree
+    /// - `root_language`: Language of the root tree
+    /// - `source`: Document text
+    pub fn update(&mut self, root: &Tree, root_language: &LanguageId, source: &str) {
+        let mut old_layers = std::mem::take(&mut self.layers);
+        let mut pending: VecDeque<PendingInjection> =
+            find_injections(root, root_language, source, 1).into();
+
+        while let Some(injection) = pending.pop_front() {
+            let Some(ts_language) = injection.language.tree_sitter_language() else {
+                continue;
+            };
+            let first_start = injection.ranges.first().map(|range| range.start_byte);
+
+            // Reuse the old tree of the same injection for an incremental parse
+            let old_tree = old_layers
+                .iter()
+                .position(|layer| {
+                    layer.language == injection.language
+                        && layer.depth == injection.depth
+                        && layer.ranges.first().map(|range| range.start_byte) == first_start
+                })
+                .map(|index| old_layers.swap_remove(index).tree);
+
+            let parser = self.parsers.entry(injection.language.clone()).or_insert_with(|| {
+                let mut parser = Parser::new();
+                // Grammar versions are checked by `tree_sitter_language` tests
+                let _ = parser.set_language(ts_language);
+                parser
+            });
+            if parser.set_included_ranges(&injection.ranges).is_err() {
+                continue;
+            }
+            let Some(tree) = parser.parse(source, old_tree.as_ref()) else {
+                continue;
+            };
+
+            if injection.depth < MAX_INJECTION_DEPTH {
+                pending.extend(find_injections(&tree, &injection.language, source, injection.depth + 1));
+            }
+
+            self.layers.push(InjectionLayer {
+                language: injection.language,
+                ranges: injection.ranges,
+                tree,
+                depth: injection.depth,
+            });
+        }
+    }
+
+    /// Gets the innermost layer containing a byte offset.
+    pub fn layer_at(&self, byte: usize) -> Option<&InjectionLayer> {
+        self.layers
+            .iter()
+            .filter(|layer| layer.contains_byte(byte))
+            .max_by_key(|layer| layer.depth)
+    }
+}
+
+/// Gets the compiled injections query for a language.
+///
+/// Returns: Query, or None if the language embeds no other languages
+pub fn injections_query(language: &LanguageId) -> Option<&'static Query> {
+    static QUERIES: OnceLock<HashMap<LanguageId, Query>> = OnceLock::new();
+
+    let queries = QUERIES.get_or_init(|| {
+        let sources = [
+            (LanguageId::Html, HTML_INJECTIONS),
+            (LanguageId::Markdown, MARKDOWN_INJECTIONS),
+            (LanguageId::JavaScript, JAVASCRIPT_INJECTIONS),
+            (LanguageId::TypeScript, TYPESCRIPT_INJECTIONS),
+            (LanguageId::Python, PYTHON_INJECTIONS),
+        ];
+
+        sources
+            .into_iter()
+            .filter_map(|(language, source)| {
+                let ts_language = language.tree_sitter_language()?;
+                let query = Query::new(ts_language, source).unwrap_or_else(|e| {
+                    panic!("invalid bundled injections query for {:?}: {}", language, e)
+                });
+                Some((language, query))
+            })
+            .collect()
+    });
+
+    queries.get(language)
+}
+
+/// Helper: Runs the injections query of a tree.
+fn find_injections(
+    tree: &Tree,
+    language: &LanguageId,
+    source: &str,
+    depth: usize,
+) -> Vec<PendingInjection> {
+    let Some(query) = injections_query(language) else {
+        return Vec::new();
+    };
+
+    let mut injections = Vec::new();
+    let mut combined: Vec<(usize, PendingInjection)> = Vec::new();
+
+    let mut cursor = QueryCursor::new();
+    for m in cursor.matches(query, tree.root_node(), source.as_bytes()) {
+        let capture = |name: &str| {
+            m.captures
+                .iter()
+                .find(|capture| query.capture_names()[capture.index as usize] == name)
+                .map(|capture| capture.node)
+        };
+        let properties = query.property_settings(m.pattern_index);
+        let has_property = |key: &str| properties.iter().any(|p| &*p.key == key);
+
+        let Some(content) = capture(CONTENT_CAPTURE) else {
+            continue;
+        };
+        let language_name = match capture(LANGUAGE_CAPTURE) {
+            Some(node) => node.utf8_text(source.as_bytes()).unwrap_or("").to_string(),
+            None => match properties.iter().find(|p| &*p.key == LANGUAGE_PROPERTY) {
+                Some(property) => property.value.as_deref().unwrap_or("").to_string(),
+                None => continue,
+            },
+        };
+        let Some(injected) = LanguageId::from_injection_name(&language_name) else {
+            continue;
+        };
+
+        let ranges = content_ranges(content, has_property(INCLUDE_CHILDREN_PROPERTY));
+        if ranges.is_empty() {
+            continue;
+        }
+
+        if has_property(COMBINED_PROPERTY) {
+            match combined
+                .iter_mut()
+                .find(|(pattern, pending)| *pattern == m.pattern_index && pending.language == injected)
+            {
+                Some((_, pending)) => pending.ranges.extend(ranges),
+                None => combined.push((
+                    m.pattern_index,
+                    PendingInjection { language: injected, ranges, depth },
+                )),
+            }
+        } else {
+            injections.push(PendingInjection { language: injected, ranges, depth });
+        }
+    }
+
+    for (_, mut pending) in combined {
+        pending.ranges.sort_by_key(|range| range.start_byte);
+        injections.push(pending);
+    }
+
+    injections
+}
+
+/// Helper: Gets the ranges of a content node.
+///
+/// Unless children are included, the ranges skip the node's named
+/// children (e.g. block quote markers inside a code fence).
+fn content_ranges(node: Node, include_children: bool) -> Vec<Range> {
+    if include_children || node.named_child_count() == 0 {
+        return vec![node.range()];
+    }
+
+    let mut ranges = Vec::new();
+    let mut start_byte = node.start_byte();
+    let mut start_point = node.start_position();
+
+    let mut cursor = node.walk();
+    for child in node.named_children(&mut cursor) {
+        if child.start_byte() > start_byte {
+            ranges.push(Range {
+                start_byte,
+                end_byte: child.start_byte(),
+                start_point,
+                end_point: child.start_position(),
+            });
+        }
+        start_byte = child.end_byte();
+        start_point = child.end_position();
+    }
+
+    if node.end_byte() > start_byte {
+        ranges.push(Range {
+            start_byte,
+            end_byte: node.end_byte(),
+            start_point,
+            end_point: node.end_position(),
+        });
+    }
+
+    ranges
+}
+
+/// Helper: Adjusts a range for an edit (same rules as `Tree::edit`).
+fn edit_range(range: &mut Range, edit: &InputEdit) {
+    if range.end_byte < edit.start_byte {
+        return;
+    }
+
+    if range.start_byte >= edit.old_end_byte {
+        range.start_byte = range.start_byte - edit.old_end_byte + edit.new_end_byte;
+        range.start_point = shift_point(range.start_point, edit);
+    } else if range.start_byte > edit.start_byte {
+        range.start_byte = edit.new_end_byte;
+        range.start_point = edit.new_end_position;
+    }
+
+    if range.end_byte >= edit.old_end_byte {
+        range.end_byte = range.end_byte - edit.old_end_byte + edit.new_end_byte;
+        range.end_point = shift_point(range.end_point, edit);
+    } else {
+        range.end_byte = edit.new_end_byte;
+        range.end_point = edit.new_end_position;
+    }
+}
+
+/// Helper: Moves a point located after an edit.
+fn shift_point(point: Point, edit: &InputEdit) -> Point {
+    let row = point.row - edit.old_end_position.row + edit.new_end_position.row;
+    let column = if point.row == edit.old_end_position.row {
+        point.column - edit.old_end_position.column + edit.new_end_position.column
+    } else {
+        point.column
+    };
+    Point::new(row, column)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn layers_for(source: &str, language: LanguageId) -> InjectionSet {
+        let mut parser = Parser::new();
+        parser.set_language(language.tree_sitter_language().unwrap()).unwrap();
+        let tree = parser.parse(source, None).unwrap();
+
+        let mut set = InjectionSet::new();
+        set.update(&tree, &language, source);
+        set
+    }
+
+    #[test]
+    fn test_injections_queries_compile() {
+        for language in [
+            LanguageId::Html,
+            LanguageId::Markdown,
+            LanguageId::JavaScript,
+            LanguageId::TypeScript,
+            LanguageId::Python,
+        ] {
+            assert!(injections_query(&language).is_some(), "{:?}", language);
+        }
+        assert!(injections_query(&LanguageId::Rust).is_none());
+    }
+
+    #[test]
+    fn test_html_script_and_style() {
+        let source = "<script>let a = 1;</script>\n<style>p { color: red; }</style>\n";
+        let set = layers_for(source, LanguageId::Html);
+
+        let languages: Vec<&LanguageId> = set.layers().iter().map(|l| &l.language).collect();
+        assert_eq!(languages, vec![&LanguageId::JavaScript, &LanguageId::Css]);
+
+        let js = &set.layers()[0];
+        assert_eq!(&source[js.ranges[0].start_byte..js.ranges[0].end_byte], "let a = 1;");
+        assert_eq!(js.tree.root_node().kind(), "program");
+        assert!(!js.tree.root_node().has_error());
+    }
+
+    #[test]
+    fn test_markdown_fences_and_nesting() {
+        let source = "# Doc\n\n```python\nx = 1\n```\n\n<div>\n<script>go()</script>\n</div>\n";
+        let set = layers_for(source, LanguageId::Markdown);
+
+        let python = set.layers().iter().find(|l| l.language == LanguageId::Python).unwrap();
+        assert_eq!(python.depth, 1);
+
+        // HTML block injects JavaScript at depth 2
+        let js = set.layers().iter().find(|l| l.language == LanguageId::JavaScript).unwrap();
+        assert_eq!(js.depth, 2);
+
+        let offset = source.find("go()").unwrap();
+        assert_eq!(set.layer_at(offset).unwrap().language, LanguageId::JavaScript);
+        assert!(set.layer_at(0).is_none());
+    }
+
+    #[test]
+    fn test_unknown_fence_language_is_ignored() {
+        let source = "```sql\nSELECT 1;\n```\n";
+        let set = layers_for(source, LanguageId::Markdown);
+        assert!(set.layers().is_empty());
+    }
+
+    #[test]
+    fn test_tagged_template_injection() {
+        let source = "const view = html`<p>${name}</p>`;\n";
+        let set = layers_for(source, LanguageId::JavaScript);
+        assert_eq!(set.layers()[0].language, LanguageId::Html);
+    }
+
+    #[test]
+    fn test_edit_range_shifts_following_text() {
+        let mut range = Range {
+            start_byte: 10,
+            end_byte: 20,
+            start_point: Point::new(1, 2),
+            end_point: Point::new(2, 5),
+        };
+
+        // Insert "abc\n" at byte 4 (row 0)
+        edit_range(
+            &mut range,
+            &InputEdit {
+                start_byte: 4,
+                old_end_byte: 4,
+                new_end_byte: 8,
+                start_position: Point::new(0, 4),
+                old_end_position: Point::new(0, 4),
+                new_end_position: Point::new(1, 0),
+            },
+        );
+
+        assert_eq!((range.start_byte, range.end_byte), (14, 24));
+        assert_eq!(range.start_point, Point::new(2, 2));
+        assert_eq!(range.end_point, Point::new(3, 5));
+    }
+}
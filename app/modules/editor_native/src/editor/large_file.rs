@@ -0,0 +1,605 @@
+//! Large-file mode
+//!
+//! Above configurable size thresholds the editor stops doing whole-document
+//! work on every keystroke:
+//! - **Large** files are still loaded into the rope (in chunks), but are not
+//!   parsed as a whole; highlighting is limited to the viewport and the
+//!   tree-based features (folding, symbols, sticky scroll...) are disabled.
+//! - **Huge** files are not loaded at all: `LargeFileView` memory-maps them
+//!   read-only and indexes line starts lazily, as far as the UI has scrolled.
+//!
+//! `DegradedFeatures` tells the UI which features are off so it can say so.
+
+use std::fs::File;
+use std::ops::Range;
+use std::path::{Path, PathBuf};
+
+use anyhow::{Context, Result};
+use memmap2::Mmap;
+use ropey::Rope;
+use serde::Serialize;
+use tree_sitter::Parser;
+
+use crate::editor::highlight::{highlight_layer, HighlightSpan};
+use crate::editor::LanguageId;
+
+/// Size thresholds of large-file mode.
+#[derive(Debug, Clone, PartialEq)]
+pub struct LargeFileConfig {
+    /// Files of at least this many bytes are parsed only around the viewport
+    pub large_file_bytes: u64,
+
+    /// Files of at least this many bytes are memory-mapped read-only
+    pub huge_file_bytes: u64,
+
+    /// Lines parsed above and below the viewport for highlighting
+    pub viewport_context_lines: usize,
+
+    /// Highlight the viewport of large files (false = no highlighting)
+    pub viewport_highlighting: bool,
+
+    /// Bytes scanned per step of the lazy line index
+    pub index_chunk_bytes: usize,
+}
+
+impl Default for LargeFileConfig {
+    fn default() -> Self {
+        Self {
+            large_file_bytes: 8 * 1024 * 1024,
+            huge_file_bytes: 256 * 1024 * 1024,
+            viewport_context_lines: 200,
+            viewport_highlighting: true,
+            index_chunk_bytes: 4 * 1024 * 1024,
+        }
+    }
+}
+
+impl LargeFileConfig {
+    /// Classifies a file by its size in bytes.
+    pub fn classify(&self, len_bytes: u64) -> FileSizeClass {
+        if len_bytes >= self.huge_file_bytes {
+            FileSizeClass::Huge
+        } else if len_bytes >= self.large_file_bytes {
+            FileSizeClass::Large
+        } else {
+            FileSizeClass::Normal
+        }
+    }
+
+    /// Classifies a file on disk by its size.
+    pub fn classify_path(&self, path: &Path) -> Result<FileSizeClass> {
+        let metadata = std::fs::metadata(path)
+            .with_context(|| format!("failed to stat {}", path.display()))?;
+        Ok(self.classify(metadata.len()))
+    }
+}
+
+/// File size class.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub enum FileSizeClass {
+    /// All features enabled
+    #[default]
+    Normal,
+
+    /// Loaded in the rope, parsed around the viewport only
+    Large,
+
+    /// Memory-mapped, read-only
+    Huge,
+}
+
+/// Extent of syntax highlighting.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub enum HighlightScope {
+    /// Whole-document syntax tree (incremental)
+    #[default]
+    Full,
+
+    /// Visible lines only, parsed on demand
+    Viewport,
+
+    /// No highlighting
+    Off,
+}
+
+/// Feature that can be switched off in large-file mode.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub enum Feature {
+    Editing,
+    SyntaxTree,
+    Injections,
+    Folding,
+    Symbols,
+    StickyScroll,
+    Completion,
+    StructuralSearch,
+}
+
+/// Features degraded for the current document (reported to the UI).
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DegradedFeatures {
+    pub size_class: FileSizeClass,
+    pub read_only: bool,
+    pub highlighting: HighlightScope,
+
+    /// Disabled features
+    pub disabled: Vec<Feature>,
+}
+
+impl DegradedFeatures {
+    /// Gets the features available for a size class.
+    ///
+    /// Parameters:
+    /// - `class`: File size class
+    /// - `config`: Large-file settings
+    /// - `read_only`: Whether the document is read-only regardless of size
+    pub fn for_class(class: FileSizeClass, config: &LargeFileConfig, read_only: bool) -> Self {
+        let read_only = read_only || class == FileSizeClass::Huge;
+
+        let mut disabled = Vec::new();
+        if read_only {
+            disabled.push(Feature::Editing);
+        }
+        if class != FileSizeClass::Normal {
+            disabled.extend([
+                Feature::SyntaxTree,
+                Feature::Injections,
+                Feature::Folding,
+                Feature::Symbols,
+                Feature::StickyScroll,
+                Feature::Completion,
+                Feature::StructuralSearch,
+            ]);
+        }
+
+        let highlighting = match class {
+            FileSizeClass::Normal => HighlightScope::Full,
+            _ if config.viewport_highlighting => HighlightScope::Viewport,
+            _ => HighlightScope::Off,
+        };
+
+        Self {
+            size_class: class,
+            read_only,
+            highlighting,
+            disabled,
+        }
+    }
+
+    /// Checks if a feature is disabled.
+    pub fn is_disabled(&self, feature: Feature) -> bool {
+        self.disabled.contains(&feature)
+    }
+}
+
+/// Highlights an excerpt of a document with a throwaway parse.
+///
+/// The excerpt is parsed on its own, so constructs opened before it (e.g.
+/// a block comment) are not seen; the context lines around the viewport
+/// make this rare in practice.
+///
+/// Parameters:
+/// - `language`: Document language
+/// - `excerpt`: Text of whole lines around the viewport
+/// - `first_line`: Document line of the excerpt start
+/// - `first_byte`: Document byte offset of the excerpt start
+/// - `byte_range`: Visible bytes (relative to the excerpt)
+///
+/// Returns: Spans in document coordinates (empty without a grammar)
+pub fn highlight_excerpt(
+    language: &LanguageId,
+    excerpt: &str,
+    first_line: usize,
+    first_byte: usize,
+    byte_range: Range<usize>,
+) -> Vec<HighlightSpan> {
+    let Some(ts_language) = language.tree_sitter_language() else {
+        return Vec::new();
+    };
+
+    let mut parser = Parser::new();
+    if parser.set_language(ts_language).is_err() {
+        return Vec::new();
+    }
+    let Some(tree) = parser.parse(excerpt, None) else {
+        return Vec::new();
+    };
+
+    let rope = Rope::from_str(excerpt);
+    let mut spans = highlight_layer(&tree, language, &rope, excerpt, byte_range, None, 0);
+    for span in &mut spans {
+        span.start_byte += first_byte;
+        span.end_byte += first_byte;
+        span.range.start.line += first_line;
+        span.range.end.line += first_line;
+    }
+    spans
+}
+
+/// Result of re-reading a followed file.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RefreshStatus {
+    /// File size did not change
+    Unchanged,
+
+    /// Bytes were appended (tail -f)
+    Appended(u64),
+
+    /// File shrank (truncated or rotated); the line index was reset
+    Truncated,
+}
+
+/// Read-only, memory-mapped view of a huge file.
+///
+/// Line starts are indexed lazily: asking for a line scans the file only
+/// up to that line, in `index_chunk_bytes` steps. The UI can also call
+/// `index_step` in the background and use `estimated_line_count` for the
+/// scrollbar until indexing completes.
+///
+/// The map is shared with the file on disk. Truncating the file while it
+/// is mapped is undefined behaviour on some platforms, so followed files
+/// should only grow; call `refresh` after appends.
+pub struct LargeFileView {
+    path: PathBuf,
+    file: File,
+
+    /// None for an empty file (empty maps are not portable)
+    mmap: Option<Mmap>,
+
+    /// Byte offset of each indexed line start (`line_starts[0] == 0`)
+    line_starts: Vec<u64>,
+
+    /// Bytes scanned by the line index
+    indexed_bytes: u64,
+
+    config: LargeFileConfig,
+}
+
+impl LargeFileView {
+    /// Opens a file for read-only viewing.
+    pub fn open(path: &Path, config: LargeFileConfig) -> Result<Self> {
+        let file = File::open(path).with_context(|| format!("failed to open {}", path.display()))?;
+
+        let mut view = Self {
+            path: path.to_path_buf(),
+            file,
+            mmap: None,
+            line_starts: vec![0],
+            indexed_bytes: 0,
+            config,
+        };
+        view.remap()?;
+        Ok(view)
+    }
+
+    /// Gets the file path
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Gets the mapped size in bytes
+    pub fn len_bytes(&self) -> u64 {
+        self.bytes().len() as u64
+    }
+
+    /// Gets the degraded features (always read-only)
+    pub fn degraded_features(&self) -> DegradedFeatures {
+        DegradedFeatures::for_class(FileSizeClass::Huge, &self.config, true)
+    }
+
+    /// Checks if the whole file has been indexed
+    pub fn is_fully_indexed(&self) -> bool {
+        self.indexed_bytes >= self.len_bytes()
+    }
+
+    /// Gets the number of lines indexed so far
+    ///
+    /// Equals the line count once `is_fully_indexed` is true.
+    pub fn indexed_line_count(&self) -> usize {
+        self.line_starts.len()
+    }
+
+    /// Estimates the line count from the lines indexed so far
+    pub fn estimated_line_count(&self) -> usize {
+        let complete_lines = self.line_starts.len() - 1;
+        if self.is_fully_indexed() || complete_lines == 0 {
+            return self.line_starts.len();
+        }
+
+        let per_line = self.indexed_bytes as f64 / complete_lines as f64;
+        ((self.len_bytes() as f64 / per_line).ceil() as usize + 1).max(self.line_starts.len())
+    }
+
+    /// Gets the exact line count (indexes the whole file)
+    pub fn line_count(&mut self) -> usize {
+        while !self.index_step() {}
+        self.line_starts.len()
+    }
+
+    /// Indexes the next chunk of the file.
+    ///
+    /// Returns: true when the whole file is indexed
+    pub fn index_step(&mut self) -> bool {
+        let len = self.len_bytes();
+        if self.indexed_bytes >= len {
+            return true;
+        }
+
+        let start = self.indexed_bytes;
+        let end = (start + self.config.index_chunk_bytes.max(1) as u64).min(len);
+        let Some(mmap) = &self.mmap else {
+            return true;
+        };
+
+        let chunk = &mmap[start as usize..end as usize];
+        self.line_starts.extend(
+            chunk
+                .iter()
+                .enumerate()
+                .filter(|(_, &byte)| byte == b'\n')
+                .map(|(i, _)| start + i as u64 + 1),
+        );
+        self.indexed_bytes = end;
+        end >= len
+    }
+
+    /// Gets a line without its line ending (invalid UTF-8 is replaced).
+    ///
+    /// Returns: Line text, or None past the end of the file
+    pub fn line(&mut self, index: usize) -> Option<String> {
+        let range = self.line_range(index)?;
+        let bytes = &self.bytes()[range.start as usize..range.end as usize];
+        let text = bytes.strip_suffix(b"\n").unwrap_or(bytes);
+        let text = text.strip_suffix(b"\r").unwrap_or(text);
+        Some(String::from_utf8_lossy(text).into_owned())
+    }
+
+    /// Gets up to `count` lines starting at `start`
+    pub fn lines(&mut self, start: usize, count: usize) -> Vec<String> {
+        (start..start.saturating_add(count)).map_while(|index| self.line(index)).collect()
+    }
+
+    /// Highlights a line range by parsing only the lines around it.
+    ///
+    /// Parameters:
+    /// - `language`: Language to highlight with
+    /// - `start_line`: First line
+    /// - `end_line`: Last line (inclusive)
+    ///
+    /// Returns: Spans in file coordinates (byte offsets assume valid UTF-8)
+    pub fn highlights(&mut self, language: &LanguageId, start_line: usize, end_line: usize) -> Vec<HighlightSpan> {
+        if !self.config.viewport_highlighting || end_line < start_line {
+            return Vec::new();
+        }
+
+        let context = self.config.viewport_context_lines;
+        let first_line = start_line.saturating_sub(context);
+        let (Some(first), Some(start), Some(end)) = (
+            self.line_range(first_line),
+            self.line_range(start_line),
+            self.line_range(end_line).or_else(|| {
+                // Clamp the range to the last line
+                let last = self.line_count() - 1;
+                self.line_range(last)
+            }),
+        ) else {
+            return Vec::new();
+        };
+        let excerpt_end = self
+            .line_range(end_line.saturating_add(context))
+            .map_or(self.len_bytes(), |range| range.end)
+            .max(end.end);
+
+        let excerpt = String::from_utf8_lossy(&self.bytes()[first.start as usize..excerpt_end as usize]).into_owned();
+        let visible = (start.start - first.start) as usize..(end.end - first.start) as usize;
+        highlight_excerpt(language, &excerpt, first_line, first.start as usize, visible)
+    }
+
+    /// Picks up changes to the file size (tail -f).
+    ///
+    /// Appended bytes are indexed lazily like the rest of the file.
+    pub fn refresh(&mut self) -> Result<RefreshStatus> {
+        let old_len = self.len_bytes();
+        let new_len = self
+            .file
+            .metadata()
+            .with_context(|| format!("failed to stat {}", self.path.display()))?
+            .len();
+
+        if new_len == old_len {
+            return Ok(RefreshStatus::Unchanged);
+        }
+
+        // Drop the old map before remapping (a shrunk file must not stay mapped)
+        self.mmap = None;
+        if new_len < old_len {
+            self.line_starts = vec![0];
+            self.indexed_bytes = 0;
+            self.remap()?;
+            return Ok(RefreshStatus::Truncated);
+        }
+
+        self.remap()?;
+        Ok(RefreshStatus::Appended(new_len - old_len))
+    }
+
+    /// Helper: Maps the current file contents
+    fn remap(&mut self) -> Result<()> {
+        let len = self.file.metadata()?.len();
+        self.mmap = if len == 0 {
+            None
+        } else {
+            // Safety: the map is read-only; see the type docs about truncation
+            Some(unsafe { Mmap::map(&self.file) }.with_context(|| format!("failed to map {}", self.path.display()))?)
+        };
+        Ok(())
+    }
+
+    /// Helper: Gets the mapped bytes
+    fn bytes(&self) -> &[u8] {
+        self.mmap.as_deref().unwrap_or(&[])
+    }
+
+    /// Helper: Gets the byte range of a line, including its line ending
+    fn line_range(&mut self, index: usize) -> Option<Range<u64>> {
+        // The end of line `index` is the start of line `index + 1`
+        while self.line_starts.len() <= index + 1 && !self.index_step() {}
+
+        let start = *self.line_starts.get(index)?;
+        let end = self.line_starts.get(index + 1).copied().unwrap_or(self.len_bytes());
+        Some(start..end)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Write;
+
+    fn temp_file(name: &str, content: &str) -> PathBuf {
+        let path = std::env::temp_dir().join(format!("large_file_{}_{}", name, std::process::id()));
+        std::fs::write(&path, content).unwrap();
+        path
+    }
+
+    fn small_config() -> LargeFileConfig {
+        LargeFileConfig {
+            large_file_bytes: 64,
+            huge_file_bytes: 1024,
+            viewport_context_lines: 2,
+            viewport_highlighting: true,
+            index_chunk_bytes: 8,
+        }
+    }
+
+    #[test]
+    fn test_classify() {
+        let config = small_config();
+        assert_eq!(config.classify(10), FileSizeClass::Normal);
+        assert_eq!(config.classify(64), FileSizeClass::Large);
+        assert_eq!(config.classify(4096), FileSizeClass::Huge);
+    }
+
+    #[test]
+    fn test_degraded_features_for_class() {
+        let config = small_config();
+
+        let normal = DegradedFeatures::for_class(FileSizeClass::Normal, &config, false);
+        assert!(normal.disabled.is_empty());
+        assert_eq!(normal.highlighting, HighlightScope::Full);
+
+        let large = DegradedFeatures::for_class(FileSizeClass::Large, &config, false);
+        assert!(!large.read_only);
+        assert!(large.is_disabled(Feature::SyntaxTree));
+        assert!(!large.is_disabled(Feature::Editing));
+        assert_eq!(large.highlighting, HighlightScope::Viewport);
+
+        let huge = DegradedFeatures::for_class(FileSizeClass::Huge, &config, false);
+        assert!(huge.read_only);
+        assert!(huge.is_disabled(Feature::Editing));
+
+        let no_highlight = LargeFileConfig { viewport_highlighting: false, ..config };
+        let large = DegradedFeatures::for_class(FileSizeClass::Large, &no_highlight, false);
+        assert_eq!(large.highlighting, HighlightScope::Off);
+    }
+
+    #[test]
+    fn test_highlight_excerpt_offsets() {
+        let excerpt = "let x = 1;\nfn main() {}\n";
+        let spans = highlight_excerpt(&LanguageId::Rust, excerpt, 100, 5000, 11..excerpt.len());
+
+        let keyword = spans.iter().find(|s| s.capture == "keyword").unwrap();
+        assert_eq!(keyword.start_byte, 5011);
+        assert_eq!(keyword.range.start.line, 101);
+        assert!(spans.iter().all(|s| s.range.start.line == 101));
+    }
+
+    #[test]
+    fn test_view_lazy_line_index() {
+        let path = temp_file("lazy", "one\ntwo\r\nthree\nfour\nfive\n");
+        let mut view = LargeFileView::open(&path, small_config()).unwrap();
+
+        assert_eq!(view.indexed_line_count(), 1);
+        assert_eq!(view.line(1).as_deref(), Some("two"));
+        assert!(!view.is_fully_indexed());
+        assert!(view.indexed_line_count() < 6);
+
+        assert_eq!(view.lines(2, 10), vec!["three", "four", "five", ""]);
+        assert_eq!(view.line_count(), 6);
+        assert!(view.is_fully_indexed());
+        assert_eq!(view.line(6), None);
+        assert!(view.degraded_features().read_only);
+
+        std::fs::remove_file(path).ok();
+    }
+
+    #[test]
+    fn test_view_estimated_line_count() {
+        let content = "0123456\n".repeat(100);
+        let path = temp_file("estimate", &content);
+        let mut view = LargeFileView::open(&path, small_config()).unwrap();
+
+        view.index_step();
+        view.index_step();
+        assert_eq!(view.estimated_line_count(), 101);
+        assert_eq!(view.line_count(), 101);
+
+        std::fs::remove_file(path).ok();
+    }
+
+    #[test]
+    fn test_view_refresh_follows_appends() {
+        let path = temp_file("follow", "first\n");
+        let mut view = LargeFileView::open(&path, small_config()).unwrap();
+        assert_eq!(view.line_count(), 2);
+        assert_eq!(view.refresh().unwrap(), RefreshStatus::Unchanged);
+
+        let mut file = std::fs::OpenOptions::new().append(true).open(&path).unwrap();
+        file.write_all(b"second\nthird").unwrap();
+        drop(file);
+
+        assert_eq!(view.refresh().unwrap(), RefreshStatus::Appended(12));
+        assert_eq!(view.line_count(), 3);
+        assert_eq!(view.line(2).as_deref(), Some("third"));
+
+        std::fs::write(&path, "new\n").unwrap();
+        assert_eq!(view.refresh().unwrap(), RefreshStatus::Truncated);
+        assert_eq!(view.lines(0, 5), vec!["new", ""]);
+
+        std::fs::remove_file(path).ok();
+    }
+
+    #[test]
+    fn test_view_empty_file() {
+        let path = temp_file("empty", "");
+        let mut view = LargeFileView::open(&path, small_config()).unwrap();
+
+        assert_eq!(view.line_count(), 1);
+        assert_eq!(view.line(0).as_deref(), Some(""));
+        assert!(view.highlights(&LanguageId::Rust, 0, 0).is_empty());
+
+        std::fs::remove_file(path).ok();
+    }
+
+    #[test]
+    fn test_view_highlights_viewport() {
+        let content = "// header\n".repeat(50) + "fn main() {}\n" + &"// footer\n".repeat(50);
+        let path = temp_file("highlight", &content);
+        let mut view = LargeFileView::open(&path, small_config()).unwrap();
+
+        let spans = view.highlights(&LanguageId::Rust, 50, 50);
+        let keyword = spans.iter().find(|s| s.capture == "keyword").unwrap();
+        assert_eq!(keyword.range.start.line, 50);
+        assert_eq!(&content[keyword.start_byte..keyword.end_byte], "fn");
+        assert!(spans.iter().all(|s| s.range.start.line == 50));
+
+        // Only the viewport (plus context) was indexed
+        assert!(!view.is_fully_indexed());
+
+        std::fs::remove_file(path).ok();
+    }
+}
@@ -0,0 +1,52 @@
+use ropey::Rope;
+use serde::Serialize;
+use tree_sitter::{Node, Point};
+
+use crate::editor::cursor::Position;
+
+/// LSP-shaped position (0-indexed line and character column).
+///
+/// Shared by the local providers (completion, symbols...) so their results
+/// can be handed to the UI exactly like language server responses.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+pub struct LspPosition {
+    pub line: usize,
+    pub character: usize,
+}
+
+impl From<Position> for LspPosition {
+    fn from(position: Position) -> Self {
+        Self {
+            line: position.line,
+            character: position.column,
+        }
+    }
+}
+
+impl LspPosition {
+    /// Converts a tree-sitter point (byte column) to a character position.
+    pub fn from_point(rope: &Rope, point: Point) -> Self {
+        let line = point.row.min(rope.len_lines().saturating_sub(1));
+        let line_slice = rope.line(line);
+        let character = line_slice.byte_to_char(point.column.min(line_slice.len_bytes()));
+
+        Self { line, character }
+    }
+}
+
+/// LSP-shaped range.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+pub struct LspRange {
+    pub start: LspPosition,
+    pub end: LspPosition,
+}
+
+impl LspRange {
+    /// Gets the range covered by a syntax node.
+    pub fn from_node(rope: &Rope, node: Node) -> Self {
+        Self {
+            start: LspPosition::from_point(rope, node.start_position()),
+            end: LspPosition::from_point(rope, node.end_position()),
+        }
+    }
+}
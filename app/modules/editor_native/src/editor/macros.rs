@@ -0,0 +1,613 @@
+//! Keyboard macros
+//!
+//! Macros record the high-level commands run through
+//! `Editor::execute_command` (not raw keystrokes), so a recorded sequence
+//! means the same thing wherever it is replayed. Direct calls to the editing
+//! primitives (`insert_text`, `delete`, `move_cursor`, the selection setters)
+//! are recorded too, with positions relative to the cursor. Each playback is
+//! a single undo step.
+
+use anyhow::{Context, Result};
+use ropey::Rope;
+use serde::{Deserialize, Serialize};
+
+use crate::editor::auto_indent::{dedent_lines, indent_lines};
+use crate::editor::cursor::line_len_without_newline;
+use crate::editor::search::find_next;
+use crate::editor::{Editor, Position, SearchOptions, Selection};
+
+/// Cursor motion.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum Motion {
+    Left,
+    Right,
+    Up,
+    Down,
+    WordLeft,
+    WordRight,
+    LineStart,
+    LineEnd,
+    DocumentStart,
+    DocumentEnd,
+}
+
+/// Line and column distance between two positions.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Delta {
+    pub lines: isize,
+    pub columns: isize,
+}
+
+impl Delta {
+    /// Distance from `from` to `to`
+    pub fn between(from: Position, to: Position) -> Self {
+        Self {
+            lines: to.line as isize - from.line as isize,
+            columns: to.column as isize - from.column as isize,
+        }
+    }
+
+    /// Position at this distance from `from`, clamped to the document
+    pub fn apply(&self, from: Position, rope: &Rope) -> Position {
+        let position = Position::new(
+            from.line.saturating_add_signed(self.lines),
+            from.column.saturating_add_signed(self.columns),
+        );
+        Position::clamp(&position, rope)
+    }
+}
+
+/// High-level editor command (the unit recorded by macros).
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "command", rename_all = "camelCase")]
+pub enum EditorCommand {
+    /// Inserts text, replacing the selection
+    InsertText { text: String },
+
+    /// Deletes the selection or the character after the cursor
+    DeleteForward,
+
+    /// Deletes the selection or the character before the cursor
+    DeleteBackward,
+
+    /// Moves the cursor; `extend` grows the selection instead of clearing it
+    Move {
+        motion: Motion,
+        #[serde(default)]
+        extend: bool,
+    },
+
+    /// Selects the next match after the cursor (wrapping around)
+    #[serde(rename_all = "camelCase")]
+    SearchNext {
+        query: String,
+        #[serde(default)]
+        case_sensitive: bool,
+        #[serde(default)]
+        whole_word: bool,
+    },
+
+    /// Indents the selected lines (or the cursor line)
+    Indent,
+
+    /// Dedents the selected lines (or the cursor line)
+    Dedent,
+
+    /// Toggles line comments on the selected lines (or the cursor line)
+    ToggleComment,
+
+    /// Inserts text at the cursor, keeping the selection (`insert_text`)
+    InsertAtCursor { text: String },
+
+    /// Moves the cursor by a distance, keeping the selection (`move_cursor`)
+    MoveBy { delta: Delta },
+
+    /// Selects between two positions relative to the cursor (`set_selection`)
+    Select { start: Delta, end: Delta },
+
+    /// Clears the selection (`clear_selection`)
+    ClearSelection,
+}
+
+/// Recorded command sequence.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Macro {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub name: Option<String>,
+    pub commands: Vec<EditorCommand>,
+}
+
+impl Macro {
+    /// Creates a macro from commands
+    pub fn new(commands: Vec<EditorCommand>) -> Self {
+        Self { name: None, commands }
+    }
+
+    /// Serializes the macro to JSON
+    pub fn to_json(&self) -> Result<String> {
+        Ok(serde_json::to_string(self)?)
+    }
+
+    /// Parses a macro saved with `to_json`
+    pub fn from_json(json: &str) -> Result<Self> {
+        serde_json::from_str(json).context("invalid macro JSON")
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.commands.is_empty()
+    }
+}
+
+/// Character class used by word motions.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum CharClass {
+    Whitespace,
+    Word,
+    Punctuation,
+}
+
+fn char_class(c: char) -> CharClass {
+    if c.is_whitespace() {
+        CharClass::Whitespace
+    } else if c.is_alphanumeric() || c == '_' {
+        CharClass::Word
+    } else {
+        CharClass::Punctuation
+    }
+}
+
+/// Char offset of the start of the next word after `offset`
+fn word_right(rope: &Rope, offset: usize) -> usize {
+    let len = rope.len_chars();
+    let mut offset = offset;
+    if offset < len {
+        let class = char_class(rope.char(offset));
+        while offset < len && class != CharClass::Whitespace && char_class(rope.char(offset)) == class {
+            offset += 1;
+        }
+    }
+    while offset < len && char_class(rope.char(offset)) == CharClass::Whitespace {
+        offset += 1;
+    }
+    offset
+}
+
+/// Char offset of the start of the word before `offset`
+fn word_left(rope: &Rope, offset: usize) -> usize {
+    let mut offset = offset;
+    while offset > 0 && char_class(rope.char(offset - 1)) == CharClass::Whitespace {
+        offset -= 1;
+    }
+    if offset > 0 {
+        let class = char_class(rope.char(offset - 1));
+        while offset > 0 && char_class(rope.char(offset - 1)) == class {
+            offset -= 1;
+        }
+    }
+    offset
+}
+
+impl Editor {
+    /// Runs a command (recorded if a macro is being recorded)
+    ///
+    /// Returns: false if the command had nothing to act on (a search
+    /// without matches), which stops macro playback
+    pub fn execute_command(&mut self, command: &EditorCommand) -> Result<bool> {
+        // The primitives the command runs must not record themselves
+        let done = self.without_macro_recording(|editor| editor.run_command(command))?;
+        self.record_command(|| command.clone());
+        Ok(done)
+    }
+
+    /// Runs `f` without recording the primitives it calls
+    ///
+    /// For cursor and selection changes that are not the user's doing
+    /// (restoring a view, clamping after a bulk edit).
+    pub fn without_macro_recording<T>(&mut self, f: impl FnOnce(&mut Self) -> T) -> T {
+        let recording = self.macro_recording.take();
+        let result = f(self);
+        self.macro_recording = recording;
+        result
+    }
+
+    /// Helper: Appends a command to the recording, if one is running
+    pub(super) fn record_command(&mut self, command: impl FnOnce() -> EditorCommand) {
+        if let Some(recording) = &mut self.macro_recording {
+            recording.push(command());
+        }
+    }
+
+    /// Starts recording commands (discards an unfinished recording)
+    pub fn start_macro_recording(&mut self) {
+        self.macro_recording = Some(Vec::new());
+    }
+
+    /// Stops recording
+    ///
+    /// Returns: The recorded macro, or None if not recording
+    pub fn stop_macro_recording(&mut self) -> Option<Macro> {
+        self.macro_recording.take().map(Macro::new)
+    }
+
+    pub fn is_recording_macro(&self) -> bool {
+        self.macro_recording.is_some()
+    }
+
+    /// Plays a macro `times` times from the cursor
+    ///
+    /// Playback stops early when a command finds nothing to act on.
+    /// All iterations form one undo step.
+    ///
+    /// Returns: Number of complete iterations
+    pub fn play_macro(&mut self, macro_: &Macro, times: usize) -> Result<usize> {
+        self.with_playback(|editor| {
+            for iteration in 0..times {
+                if !editor.run_macro_once(macro_)? {
+                    return Ok(iteration);
+                }
+            }
+            Ok(times)
+        })
+    }
+
+    /// Plays a macro once at each cursor
+    ///
+    /// Cursors are visited bottom-up so edits never shift the cursors
+    /// still to be visited. All runs form one undo step.
+    ///
+    /// Returns: Number of cursors where the macro ran to completion
+    pub fn play_macro_at_cursors(&mut self, macro_: &Macro, cursors: &[Position]) -> Result<usize> {
+        let mut cursors: Vec<Position> = cursors.iter().map(|p| Position::clamp(p, &self.rope)).collect();
+        cursors.sort();
+        cursors.dedup();
+
+        self.with_playback(|editor| {
+            let mut completed = 0;
+            for cursor in cursors.into_iter().rev() {
+                editor.cursor = cursor;
+                editor.selection = None;
+                if editor.run_macro_once(macro_)? {
+                    completed += 1;
+                }
+            }
+            Ok(completed)
+        })
+    }
+
+    /// Plays a macro once at the start of every line in a range
+    ///
+    /// Parameters:
+    /// - `start_line`: First line
+    /// - `end_line`: Last line (inclusive)
+    ///
+    /// Returns: Number of lines where the macro ran to completion
+    pub fn play_macro_on_lines(&mut self, macro_: &Macro, start_line: usize, end_line: usize) -> Result<usize> {
+        let last_line = self.rope.len_lines().saturating_sub(1);
+        if start_line > last_line {
+            return Ok(0);
+        }
+
+        let cursors: Vec<Position> = (start_line..=end_line.min(last_line))
+            .map(|line| Position::new(line, 0))
+            .collect();
+        self.play_macro_at_cursors(macro_, &cursors)
+    }
+
+    /// Helper: Runs playback as one transaction without recording it
+    fn with_playback(&mut self, f: impl FnOnce(&mut Self) -> Result<usize>) -> Result<usize> {
+        self.ensure_writable()?;
+        self.without_macro_recording(|editor| editor.transaction(f))
+    }
+
+    /// Helper: Runs every command of a macro, stopping at the first miss
+    fn run_macro_once(&mut self, macro_: &Macro) -> Result<bool> {
+        for command in &macro_.commands {
+            if !self.run_command(command)? {
+                return Ok(false);
+            }
+        }
+        Ok(true)
+    }
+
+    /// Helper: Runs a command without recording it
+    fn run_command(&mut self, command: &EditorCommand) -> Result<bool> {
+        match command {
+            EditorCommand::InsertText { text } => {
+                self.transaction(|editor| {
+                    editor.delete_selection()?;
+                    editor.insert_text(text)
+                })?;
+            }
+            EditorCommand::DeleteForward => self.delete()?,
+            EditorCommand::DeleteBackward => {
+                if !self.delete_selection()? {
+                    let offset = self.cursor.to_char_offset(&self.rope);
+                    if offset > 0 {
+                        self.ensure_writable()?;
+                        self.cursor = Position::from_char_offset(&self.rope, offset - 1);
+                        self.delete()?;
+                    }
+                }
+            }
+            EditorCommand::Move { motion, extend } => self.apply_motion(*motion, *extend),
+            EditorCommand::SearchNext { query, case_sensitive, whole_word } => {
+                let options = SearchOptions {
+                    case_sensitive: *case_sensitive,
+                    whole_word: *whole_word,
+                    ..SearchOptions::default()
+                };
+                let from = self.selection.map_or(self.cursor, |s| s.normalize().end);
+                let found = find_next(&self.rope, query, from, &options)
+                    .or_else(|| find_next(&self.rope, query, Position::start(), &options));
+                let Some(found) = found else {
+                    return Ok(false);
+                };
+                self.selection = Some(Selection::new(found.start, found.end));
+                self.cursor = found.end;
+            }
+            EditorCommand::Indent => self.shift_lines(true)?,
+            EditorCommand::Dedent => self.shift_lines(false)?,
+            EditorCommand::InsertAtCursor { text } => self.insert_text(text)?,
+            EditorCommand::MoveBy { delta } => self.move_cursor(delta.apply(self.cursor, &self.rope)),
+            EditorCommand::Select { start, end } => {
+                let (start, end) = (start.apply(self.cursor, &self.rope), end.apply(self.cursor, &self.rope));
+                self.set_selection(Selection::new(start, end));
+            }
+            EditorCommand::ClearSelection => self.clear_selection(),
+            EditorCommand::ToggleComment => {
+                let (start_line, end_line) = self.command_lines();
+                self.toggle_line_comments(start_line, end_line)?;
+            }
+        }
+        Ok(true)
+    }
+
+    /// Helper: Deletes a non-empty selection
+    ///
+    /// Returns: true if something was deleted
+    fn delete_selection(&mut self) -> Result<bool> {
+        match self.selection {
+            Some(selection) if !selection.is_empty() => {
+                self.delete()?;
+                Ok(true)
+            }
+            _ => {
+                self.selection = None;
+                Ok(false)
+            }
+        }
+    }
+
+    /// Helper: Moves the cursor, extending or clearing the selection
+    fn apply_motion(&mut self, motion: Motion, extend: bool) {
+        let rope = &self.rope;
+        let cursor = self.cursor;
+        let last_line = rope.len_lines().saturating_sub(1);
+        let offset = cursor.to_char_offset(rope);
+
+        let target = match motion {
+            Motion::Left => Position::from_char_offset(rope, offset.saturating_sub(1)),
+            Motion::Right => Position::from_char_offset(rope, (offset + 1).min(rope.len_chars())),
+            Motion::Up if cursor.line == 0 => Position::start(),
+            Motion::Up => Position::clamp(&Position::new(cursor.line - 1, cursor.column), rope),
+            Motion::Down if cursor.line == last_line => {
+                Position::new(last_line, line_len_without_newline(rope, last_line))
+            }
+            Motion::Down => Position::clamp(&Position::new(cursor.line + 1, cursor.column), rope),
+            Motion::WordLeft => Position::from_char_offset(rope, word_left(rope, offset)),
+            Motion::WordRight => Position::from_char_offset(rope, word_right(rope, offset)),
+            Motion::LineStart => Position::new(cursor.line, 0),
+            Motion::LineEnd => Position::new(cursor.line, line_len_without_newline(rope, cursor.line)),
+            Motion::DocumentStart => Position::start(),
+            Motion::DocumentEnd => Position::new(last_line, line_len_without_newline(rope, last_line)),
+        };
+
+        self.selection = if extend {
+            let anchor = self.selection.map_or(cursor, |s| s.start);
+            Some(Selection::new(anchor, target))
+        } else {
+            None
+        };
+        self.cursor = target;
+    }
+
+    /// Helper: Lines covered by the selection, or the cursor line
+    fn command_lines(&self) -> (usize, usize) {
+        match self.selection {
+            Some(selection) if !selection.is_empty() => {
+                let selection = selection.normalize();
+                // A selection ending at column 0 does not include that line
+                let end_line = if selection.end.column == 0 && selection.end.line > selection.start.line {
+                    selection.end.line - 1
+                } else {
+                    selection.end.line
+                };
+                (selection.start.line, end_line)
+            }
+            _ => (self.cursor.line, self.cursor.line),
+        }
+    }
+
+    /// Helper: Indents or dedents the command lines as one edit
+    fn shift_lines(&mut self, indent: bool) -> Result<()> {
+        self.ensure_writable()?;
+        let (start_line, end_line) = self.command_lines();
+
+        let mut scratch = self.rope.clone();
+        if indent {
+            indent_lines(&mut scratch, start_line, end_line, &self.indent_config);
+        } else {
+            dedent_lines(&mut scratch, start_line, end_line, &self.indent_config);
+        }
+
+        let line_end_byte = |rope: &Rope| {
+            if end_line + 1 < rope.len_lines() {
+                rope.line_to_byte(end_line + 1)
+            } else {
+                rope.len_bytes()
+            }
+        };
+        let start = self.rope.line_to_byte(start_line);
+        let old_end = line_end_byte(&self.rope);
+        let text = scratch.byte_slice(start..line_end_byte(&scratch)).to_string();
+
+        // Keep the cursor and selection on the same text
+        let shift = |position: Position, rope: &Rope, scratch: &Rope| {
+            if (start_line..=end_line).contains(&position.line) {
+                let delta = scratch.line(position.line).len_chars() as isize
+                    - rope.line(position.line).len_chars() as isize;
+                Position::new(position.line, position.column.saturating_add_signed(delta))
+            } else {
+                position
+            }
+        };
+        let cursor = shift(self.cursor, &self.rope, &scratch);
+        let selection = self
+            .selection
+            .map(|s| Selection::new(shift(s.start, &self.rope, &scratch), shift(s.end, &self.rope, &scratch)));
+
+        self.replace_bytes(start, old_end, &text);
+        self.cursor = Position::clamp(&cursor, &self.rope);
+        self.selection = selection.map(|s| {
+            Selection::new(Position::clamp(&s.start, &self.rope), Position::clamp(&s.end, &self.rope))
+        });
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::editor::LanguageId;
+
+    fn insert(text: &str) -> EditorCommand {
+        EditorCommand::InsertText { text: text.to_string() }
+    }
+
+    fn motion(motion: Motion) -> EditorCommand {
+        EditorCommand::Move { motion, extend: false }
+    }
+
+    #[test]
+    fn test_record_and_replay_times() {
+        let mut editor = Editor::with_content("a\nb\nc\nd\n", LanguageId::PlainText).unwrap();
+        editor.start_macro_recording();
+        assert!(editor.is_recording_macro());
+        editor.execute_command(&motion(Motion::LineEnd)).unwrap();
+        editor.execute_command(&insert(";")).unwrap();
+        editor.execute_command(&motion(Motion::Down)).unwrap();
+        let recorded = editor.stop_macro_recording().unwrap();
+        assert_eq!(recorded.commands.len(), 3);
+        assert!(!editor.is_recording_macro());
+
+        assert_eq!(editor.play_macro(&recorded, 2).unwrap(), 2);
+        assert_eq!(editor.content(), "a;\nb;\nc;\nd\n");
+
+        // The whole playback is one undo step
+        assert!(editor.undo().unwrap());
+        assert_eq!(editor.content(), "a;\nb\nc\nd\n");
+    }
+
+    #[test]
+    fn test_record_primitives_and_replay() {
+        let mut editor = Editor::with_content("one\ntwo\nsix\n", LanguageId::PlainText).unwrap();
+        editor.start_macro_recording();
+        editor.move_cursor(Position::new(0, 3));
+        editor.insert_text(";").unwrap();
+        editor.set_selection(Selection::new(Position::new(0, 0), Position::new(0, 1)));
+        editor.delete().unwrap();
+        editor.move_cursor(Position::new(1, 0));
+        let recorded = editor.stop_macro_recording().unwrap();
+        assert_eq!(editor.content(), "ne;\ntwo\nsix\n");
+
+        // Positions are recorded relative to the cursor
+        assert_eq!(
+            recorded.commands,
+            vec![
+                EditorCommand::MoveBy { delta: Delta { lines: 0, columns: 3 } },
+                EditorCommand::InsertAtCursor { text: ";".to_string() },
+                EditorCommand::Select { start: Delta { lines: 0, columns: -4 }, end: Delta { lines: 0, columns: -3 } },
+                EditorCommand::DeleteForward,
+                EditorCommand::MoveBy { delta: Delta { lines: 1, columns: 0 } },
+            ]
+        );
+        assert_eq!(Macro::from_json(&recorded.to_json().unwrap()).unwrap(), recorded);
+
+        assert_eq!(editor.play_macro(&recorded, 2).unwrap(), 2);
+        assert_eq!(editor.content(), "ne;\nwo;\nix;\n");
+        assert!(editor.undo().unwrap());
+        assert_eq!(editor.content(), "ne;\ntwo\nsix\n");
+    }
+
+    #[test]
+    fn test_replay_on_lines_and_cursors() {
+        let mut editor = Editor::with_content("let a = 1;\nlet b = 2;\nlet c = 3;\n", LanguageId::Rust).unwrap();
+        let macro_ = Macro::new(vec![
+            motion(Motion::WordRight),
+            EditorCommand::Move { motion: Motion::WordRight, extend: true },
+            insert("mut "),
+        ]);
+
+        assert_eq!(editor.play_macro_on_lines(&macro_, 0, 1).unwrap(), 2);
+        assert_eq!(editor.content(), "let mut = 1;\nlet mut = 2;\nlet c = 3;\n");
+
+        editor.undo().unwrap();
+        let cursors = [Position::new(2, 0), Position::new(0, 0)];
+        assert_eq!(editor.play_macro_at_cursors(&macro_, &cursors).unwrap(), 2);
+        assert_eq!(editor.content(), "let mut = 1;\nlet b = 2;\nlet mut = 3;\n");
+    }
+
+    #[test]
+    fn test_search_stops_playback() {
+        let mut editor = Editor::with_content("foo bar foo baz foo", LanguageId::PlainText).unwrap();
+        let macro_ = Macro::new(vec![
+            EditorCommand::SearchNext { query: "foo".to_string(), case_sensitive: true, whole_word: false },
+            insert("X"),
+        ]);
+
+        // Three matches, then the search finds nothing
+        assert_eq!(editor.play_macro(&macro_, 10).unwrap(), 3);
+        assert_eq!(editor.content(), "X bar X baz X");
+    }
+
+    #[test]
+    fn test_indent_comment_and_delete_commands() {
+        let mut editor = Editor::with_content("fn a() {}\nfn b() {}\n", LanguageId::Rust).unwrap();
+        editor.move_cursor(Position::new(0, 3));
+        editor.execute_command(&EditorCommand::Indent).unwrap();
+        assert_eq!(editor.content(), "    fn a() {}\nfn b() {}\n");
+        assert_eq!(editor.cursor(), Position::new(0, 7));
+
+        editor.execute_command(&EditorCommand::Dedent).unwrap();
+        editor.execute_command(&EditorCommand::ToggleComment).unwrap();
+        assert_eq!(editor.content(), "// fn a() {}\nfn b() {}\n");
+
+        editor.execute_command(&motion(Motion::DocumentEnd)).unwrap();
+        editor.execute_command(&EditorCommand::DeleteBackward).unwrap();
+        assert_eq!(editor.content(), "// fn a() {}\nfn b() {}");
+    }
+
+    #[test]
+    fn test_macro_json_roundtrip() {
+        let macro_ = Macro {
+            name: Some("semicolons".to_string()),
+            commands: vec![
+                EditorCommand::Move { motion: Motion::LineEnd, extend: false },
+                insert(";"),
+                EditorCommand::SearchNext { query: "x".to_string(), case_sensitive: false, whole_word: true },
+                EditorCommand::ToggleComment,
+            ],
+        };
+
+        let json = macro_.to_json().unwrap();
+        assert!(json.contains("\"command\":\"insertText\""));
+        assert!(json.contains("\"wholeWord\":true"));
+        assert_eq!(Macro::from_json(&json).unwrap(), macro_);
+
+        let minimal = Macro::from_json(r#"{"commands":[{"command":"move","motion":"wordRight"}]}"#).unwrap();
+        assert_eq!(minimal.commands, vec![motion(Motion::WordRight)]);
+        assert!(Macro::from_json("{\"commands\":[{\"command\":\"fly\"}]}").is_err());
+    }
+}
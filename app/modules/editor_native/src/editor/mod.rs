@@ -1,6 +1,7 @@
-use anyhow::Result;
+use anyhow::{bail, Context, Result};
 use ropey::Rope;
-use tree_sitter::{Parser, Language, Tree};
+use serde::{Deserialize, Serialize};
+use tree_sitter::{InputEdit, Parser, Language, Point, Tree};
 
 // Sub-modules
 pub mod cursor;
@@ -12,6 +13,27 @@ pub mod syntax_query;
 pub mod bracket_matching;
 pub mod auto_indent;
 pub mod comment_toggle;
+pub mod editorconfig;
+pub mod completion;
+pub mod lsp_types;
+pub mod symbols;
+pub mod sticky_scroll;
+pub mod indent_guides;
+pub mod injections;
+pub mod highlight;
+pub mod folding;
+pub mod structural_replace;
+pub mod large_file;
+pub mod diff;
+pub mod snapshot;
+pub mod macros;
+pub mod background_parse;
+pub mod telemetry;
+pub mod collab;
+pub mod dirty_diff;
+pub mod spell_check;
+pub mod script;
+pub mod save_actions;
 
 // Re-export commonly used items
 pub use cursor::{Position, Selection};
@@ -19,10 +41,31 @@ pub use search::{SearchOptions, SearchMatch, search_rope, find_next, replace_all
 pub use multiline_edit::{MultiCursor, ColumnSelection, MultiEdit};
 pub use performance::{PerformanceMetrics, OperationTimer, PerformanceStats};
 pub use clipboard::{Clipboard, ClipboardMode, copy_text, cut_text, paste_text};
-pub use syntax_query::{SyntaxQuery, QueryError};
+pub use syntax_query::{SyntaxQuery, QueryError, QueryMatchResult};
 pub use bracket_matching::{BracketType, BracketMatch, find_matching_bracket, find_all_bracket_pairs, are_brackets_balanced};
 pub use auto_indent::{IndentConfig, calculate_indent_for_newline, indent_lines, dedent_lines, normalize_indentation};
-pub use comment_toggle::{CommentConfig, toggle_line_comments, toggle_block_comment};
+pub use comment_toggle::{CommentConfig, DocCommentStub, toggle_line_comments, toggle_block_comment, doc_comment_stub};
+pub use editorconfig::{EditorConfigProperties, IndentStyle, IndentSize, LineEnding, Charset, SaveSettings};
+pub use completion::{CompletionIndex, CompletionItem, CompletionItemKind, CompletionList, complete};
+pub use lsp_types::{LspPosition, LspRange};
+pub use symbols::{DocumentSymbol, SymbolKind, document_symbols, document_symbols_layered};
+pub use sticky_scroll::{StickyScope, sticky_scopes};
+pub use indent_guides::{IndentGuide, indent_guides};
+pub use injections::{InjectionLayer, InjectionSet};
+pub use highlight::{HighlightSpan, highlight_layer};
+pub use folding::{FoldingRange, folding_ranges};
+pub use structural_replace::{StructuralPattern, StructuralQuery, StructuralMatch, FileReplacement, preview_files, apply_files};
+pub use large_file::{LargeFileConfig, LargeFileView, FileSizeClass, HighlightScope, Feature, DegradedFeatures, RefreshStatus, highlight_excerpt};
+pub use snapshot::{EditorSnapshot, JournalEntry};
+pub use diff::{DiffOp, DiffRange, DiffLine, DiffHunk, diff_lines, diff_hunks, unified_diff};
+pub use macros::{Delta, EditorCommand, Macro, Motion};
+pub use background_parse::ParseConfig;
+pub use collab::{Anchor, CharId, CharRange, CollabSnapshot, CrdtOp, CrdtOpKind, CursorUpdate, RemoteCursor, ReplicaId, VersionVector};
+pub use dirty_diff::{ChangeKind, GutterMarker, LineChange};
+pub use script::{Script, ScriptCommand, ScriptMatch};
+pub use save_actions::SaveAction;
+pub use spell_check::{Dictionary, SharedSpellChecker, SpellChecker, SpellDiagnostic, WordListKind};
+pub use telemetry::{Operation, LatencyHistogram, LatencySummary, MemoryUsage, TelemetrySnapshot};
 
 /// Language identifier
 #[derive(Debug, Clone, PartialEq, Eq, Hash)]
@@ -34,6 +77,9 @@ pub enum LanguageId {
     Java,
     Go,
     Dart,
+    Html,
+    Css,
+    Markdown,
     PlainText,
 }
 
@@ -43,12 +89,15 @@ impl std::str::FromStr for LanguageId {
     fn from_str(s: &str) -> Result<Self, Self::Err> {
         Ok(match s.to_lowercase().as_str() {
             "rust" | "rs" => Self::Rust,
-            "javascript" | "js" => Self::JavaScript,
+            "javascript" | "js" | "jsx" | "mjs" | "cjs" => Self::JavaScript,
             "typescript" | "ts" => Self::TypeScript,
-            "python" | "py" => Self::Python,
+            "python" | "py" | "python3" => Self::Python,
             "java" => Self::Java,
-            "go" => Self::Go,
+            "go" | "golang" => Self::Go,
             "dart" => Self::Dart,
+            "html" | "htm" => Self::Html,
+            "css" => Self::Css,
+            "markdown" | "md" => Self::Markdown,
             _ => Self::PlainText,
         })
     }
@@ -60,6 +109,42 @@ impl LanguageId {
         s.parse().unwrap_or(Self::PlainText)
     }
 
+    /// Detects the language from a file extension
+    pub fn from_path(path: &std::path::Path) -> Self {
+        match path.extension().and_then(|ext| ext.to_str()) {
+            Some("tsx" | "mts" | "cts") => Self::TypeScript,
+            Some("markdown" | "mdx") => Self::Markdown,
+            Some(ext) => Self::parse(ext),
+            None => Self::PlainText,
+        }
+    }
+
+    /// Resolves a language named by an injection (e.g. a Markdown fence
+    /// info string)
+    ///
+    /// Returns: Language, or None if it has no grammar
+    pub fn from_injection_name(name: &str) -> Option<Self> {
+        let language = Self::parse(name.trim());
+        language.tree_sitter_language().map(|_| language)
+    }
+
+    /// Gets the canonical language name
+    pub fn name(&self) -> &'static str {
+        match self {
+            Self::Rust => "rust",
+            Self::JavaScript => "javascript",
+            Self::TypeScript => "typescript",
+            Self::Python => "python",
+            Self::Java => "java",
+            Self::Go => "go",
+            Self::Dart => "dart",
+            Self::Html => "html",
+            Self::Css => "css",
+            Self::Markdown => "markdown",
+            Self::PlainText => "plaintext",
+        }
+    }
+
     pub fn tree_sitter_language(&self) -> Option<Language> {
         match self {
             Self::Rust => Some(tree_sitter_rust::language()),
@@ -68,19 +153,29 @@ impl LanguageId {
             Self::Python => Some(tree_sitter_python::language()),
             Self::Java => Some(tree_sitter_java::language()),
             Self::Go => Some(tree_sitter_go::language()),
+            Self::Html => Some(tree_sitter_html::language()),
+            Self::Css => Some(tree_sitter_css::language()),
+            Self::Markdown => Some(tree_sitter_md::language()),
             _ => None,
         }
     }
 }
 
 /// Edit record for undo/redo operations
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
 pub struct Edit {
     pub position: usize,
     pub deleted_text: String,
     pub inserted_text: String,
 }
 
+/// Group of edits that is undone/redone as a single step
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Transaction {
+    pub edits: Vec<Edit>,
+}
+
 /// Main Editor struct
 ///
 /// This is the core editor implementation using ropey for text storage
@@ -104,17 +199,74 @@ pub struct Editor {
     /// Syntax tree
     syntax_tree: Option<Tree>,
 
+    /// Embedded languages (e.g. `<script>` in HTML, Markdown code fences)
+    injections: InjectionSet,
+
     /// Undo stack
-    undo_stack: Vec<Edit>,
+    undo_stack: Vec<Transaction>,
 
     /// Redo stack
-    redo_stack: Vec<Edit>,
+    redo_stack: Vec<Transaction>,
+
+    /// Transaction being recorded (see `begin_transaction`)
+    pending_transaction: Option<Transaction>,
+
+    /// Nesting depth of `begin_transaction` calls
+    transaction_depth: usize,
 
     /// Maximum undo history
     max_undo_history: usize,
 
     /// Dirty flag (unsaved changes)
     is_dirty: bool,
+
+    /// Indentation settings
+    indent_config: IndentConfig,
+
+    /// Save-time behaviour (trailing whitespace, final newline, encoding)
+    save_settings: SaveSettings,
+
+    /// Preferred maximum line length (ruler), if any
+    max_line_length: Option<usize>,
+
+    /// Identifier index for local completion
+    completion_index: CompletionIndex,
+
+    /// Large-file thresholds
+    large_file_config: LargeFileConfig,
+
+    /// Size class of the content (Large disables whole-document parsing)
+    size_class: FileSizeClass,
+
+    /// Edits are rejected (viewing only)
+    read_only: bool,
+
+    /// Content version, bumped on every change (LSP document version)
+    version: u64,
+
+    /// Raw edits since the last `take_edit_journal` (None = not recording)
+    edit_journal: Option<snapshot::EditJournal>,
+
+    /// Commands recorded since `start_macro_recording` (None = not recording)
+    macro_recording: Option<Vec<EditorCommand>>,
+
+    /// Inline and background parsing budgets
+    parse_config: ParseConfig,
+
+    /// Background parse thread (spawned on the first over-budget parse)
+    parse_worker: Option<background_parse::ParseWorker>,
+
+    /// Operation timings (parse durations)
+    performance: PerformanceMetrics,
+
+    /// Replicated text for collaborative editing (None = single writer)
+    collab: Option<collab::Collaboration>,
+
+    /// Base text and gutter changes against it (None = no markers)
+    diff_state: Option<dirty_diff::DiffState>,
+
+    /// Cached spell check results per line (None = spell checking off)
+    spell: Option<spell_check::SpellState>,
 }
 
 impl Editor {
@@ -127,10 +279,29 @@ impl Editor {
             language: LanguageId::PlainText,
             parser: None,
             syntax_tree: None,
+            injections: InjectionSet::new(),
             undo_stack: Vec::new(),
             redo_stack: Vec::new(),
+            pending_transaction: None,
+            transaction_depth: 0,
             max_undo_history: 1000,
             is_dirty: false,
+            indent_config: IndentConfig::default(),
+            save_settings: SaveSettings::default(),
+            max_line_length: None,
+            completion_index: CompletionIndex::new(),
+            large_file_config: LargeFileConfig::default(),
+            size_class: FileSizeClass::Normal,
+            read_only: false,
+            version: 0,
+            edit_journal: None,
+            macro_recording: None,
+            parse_config: ParseConfig::default(),
+            parse_worker: None,
+            performance: PerformanceMetrics::default(),
+            collab: None,
+            diff_state: None,
+            spell: None,
         }
     }
 
@@ -142,6 +313,75 @@ impl Editor {
         Ok(editor)
     }
 
+    /// Opens a file for editing
+    ///
+    /// The file is read in chunks straight into the rope. Files above
+    /// `config.large_file_bytes` open in large-file mode (viewport-only
+    /// highlighting); huge files must be opened with `LargeFileView`.
+    ///
+    /// Parameters:
+    /// - `path`: File to open (the language is detected from its extension)
+    /// - `config`: Large-file thresholds
+    pub fn open_file(path: &std::path::Path, config: LargeFileConfig) -> Result<Self> {
+        Self::open_file_with_charset(path, config, None)
+    }
+
+    /// Opens a file for editing, decoding it from a charset
+    ///
+    /// The charset is also used when saving. A leading byte order mark is
+    /// not part of the buffer text.
+    ///
+    /// Parameters:
+    /// - `path`: File to open (the language is detected from its extension)
+    /// - `config`: Large-file thresholds
+    /// - `charset`: Charset of the file (None: from the byte order mark,
+    ///   UTF-8 without one), e.g. from `.editorconfig`
+    pub fn open_file_with_charset(
+        path: &std::path::Path,
+        config: LargeFileConfig,
+        charset: Option<Charset>,
+    ) -> Result<Self> {
+        use std::io::{BufRead, Read};
+
+        if config.classify_path(path)? == FileSizeClass::Huge {
+            bail!("{} is too large to edit; open it with LargeFileView", path.display());
+        }
+
+        let file = std::fs::File::open(path).with_context(|| format!("failed to open {}", path.display()))?;
+        let mut reader = std::io::BufReader::new(file);
+        let head = reader.fill_buf().with_context(|| format!("failed to read {}", path.display()))?;
+        let bom = Charset::detect(head);
+        let charset = charset.or(bom).unwrap_or_default();
+
+        let rope = match charset {
+            // UTF-8 streams straight into the rope, minus the BOM
+            Charset::Utf8 | Charset::Utf8Bom => {
+                if bom == Some(Charset::Utf8Bom) {
+                    reader.consume(3);
+                }
+                Rope::from_reader(reader).with_context(|| format!("failed to read {}", path.display()))?
+            }
+            _ => {
+                let mut bytes = Vec::new();
+                reader
+                    .read_to_end(&mut bytes)
+                    .with_context(|| format!("failed to read {}", path.display()))?;
+                let text = charset
+                    .decode(&bytes)
+                    .with_context(|| format!("failed to decode {} as {:?}", path.display(), charset))?;
+                Rope::from_str(&text)
+            }
+        };
+
+        let mut editor = Self::new();
+        editor.save_settings.charset = charset;
+        editor.large_file_config = config;
+        editor.size_class = editor.classify_content_of(&rope);
+        editor.rope = rope;
+        editor.set_language(LanguageId::from_path(path))?;
+        Ok(editor)
+    }
+
     /// Gets the entire content as string
     pub fn content(&self) -> String {
         self.rope.to_string()
@@ -149,11 +389,28 @@ impl Editor {
 
     /// Sets the entire content (replaces everything)
     pub fn set_content(&mut self, content: &str) -> Result<()> {
+        if let Some(collab) = &mut self.collab {
+            collab.record_local(0, self.rope.len_chars(), content);
+        }
         self.rope = Rope::from_str(content);
+        self.version += 1;
+        if let Some(journal) = &mut self.edit_journal {
+            journal.invalidate();
+        }
+        self.size_class = self.classify_content_of(&self.rope);
+        self.syntax_tree = None;
+        self.injections.clear();
         self.cursor = Position::new(0, 0);
         self.selection = None;
         self.is_dirty = true;
+        if let Some(diff) = &mut self.diff_state {
+            diff.rediff(&self.rope);
+        }
+        if let Some(spell) = &mut self.spell {
+            spell.invalidate(self.rope.len_lines());
+        }
         self.reparse();
+        self.rebuild_completion_index();
         Ok(())
     }
 
@@ -166,10 +423,15 @@ impl Editor {
             let mut parser = Parser::new();
             parser.set_language(ts_language)?;
             self.parser = Some(parser);
+            self.syntax_tree = None;
+            self.injections.clear();
             self.reparse();
+            self.rebuild_completion_index();
         } else {
             self.parser = None;
             self.syntax_tree = None;
+            self.injections.clear();
+            self.rebuild_completion_index();
         }
 
         Ok(())
@@ -177,51 +439,35 @@ impl Editor {
 
     /// Inserts text at cursor position
     pub fn insert_text(&mut self, text: &str) -> Result<()> {
+        self.ensure_writable()?;
+        let span = telemetry::span(Operation::Insert);
         let byte_offset = self.cursor.to_byte_offset(&self.rope);
 
-        // Record edit for undo
-        let edit = Edit {
-            position: byte_offset,
-            deleted_text: String::new(),
-            inserted_text: text.to_string(),
-        };
-        self.push_undo(edit);
-
         // Insert into rope (O(log n) - fast!)
-        self.rope.insert(byte_offset, text);
+        self.replace_bytes(byte_offset, byte_offset, text);
 
         // Update cursor position
         let new_offset = byte_offset + text.len();
         self.cursor = Position::from_byte_offset(&self.rope, new_offset);
 
-        self.is_dirty = true;
-        self.reparse();
+        self.performance.record_insert(span.elapsed());
+        self.record_command(|| EditorCommand::InsertAtCursor { text: text.to_string() });
         Ok(())
     }
 
     /// Deletes text in selection or at cursor
     pub fn delete(&mut self) -> Result<()> {
+        self.ensure_writable()?;
+        let span = telemetry::span(Operation::Delete);
         if let Some(selection) = self.selection {
             let normalized = selection.normalize();
             let start_offset = normalized.start.to_byte_offset(&self.rope);
             let end_offset = normalized.end.to_byte_offset(&self.rope);
 
             if start_offset < end_offset {
-                let deleted_text = self.rope.slice(start_offset..end_offset).to_string();
-
-                // Record edit for undo
-                let edit = Edit {
-                    position: start_offset,
-                    deleted_text,
-                    inserted_text: String::new(),
-                };
-                self.push_undo(edit);
-
-                self.rope.remove(start_offset..end_offset);
+                self.replace_bytes(start_offset, end_offset, "");
                 self.cursor = normalized.start;
                 self.selection = None;
-                self.is_dirty = true;
-                self.reparse();
             }
         } else {
             // Delete character at cursor (forward delete)
@@ -230,22 +476,86 @@ impl Editor {
                 let next_offset = self.rope.byte_to_char(byte_offset) + 1;
                 let next_byte = self.rope.char_to_byte(next_offset.min(self.rope.len_chars()));
 
-                let deleted_text = self.rope.slice(byte_offset..next_byte).to_string();
+                self.replace_bytes(byte_offset, next_byte, "");
+            }
+        }
+
+        self.performance.record_delete(span.elapsed());
+        self.record_command(|| EditorCommand::DeleteForward);
+        Ok(())
+    }
+
+    /// Replaces the text between two positions.
+    ///
+    /// The edit is recorded for undo. Positions are clamped to the document
+    /// and may be given in any order.
+    pub fn replace_range(&mut self, start: Position, end: Position, text: &str) -> Result<()> {
+        self.ensure_writable()?;
+        let start_offset = Position::clamp(&start, &self.rope).to_byte_offset(&self.rope);
+        let end_offset = Position::clamp(&end, &self.rope).to_byte_offset(&self.rope);
+
+        self.replace_bytes(start_offset.min(end_offset), start_offset.max(end_offset), text);
+        Ok(())
+    }
+
+    /// Starts grouping edits into a single undo step.
+    ///
+    /// Calls can be nested; the group is committed (and the document
+    /// reparsed once) when the outermost `end_transaction` is called.
+    pub fn begin_transaction(&mut self) {
+        if self.transaction_depth == 0 {
+            self.pending_transaction = Some(Transaction::default());
+        }
+        self.transaction_depth += 1;
+    }
+
+    /// Commits the edit group started by `begin_transaction`.
+    pub fn end_transaction(&mut self) {
+        if self.transaction_depth == 0 {
+            return;
+        }
 
-                let edit = Edit {
-                    position: byte_offset,
-                    deleted_text,
-                    inserted_text: String::new(),
-                };
-                self.push_undo(edit);
+        self.transaction_depth -= 1;
+        if self.transaction_depth == 0 {
+            if let Some(transaction) = self.pending_transaction.take() {
+                if !transaction.edits.is_empty() {
+                    self.push_undo_transaction(transaction);
+                }
+            }
+            self.reparse();
+        }
+    }
 
-                self.rope.remove(byte_offset..next_byte);
-                self.is_dirty = true;
-                self.reparse();
+    /// Runs `f` as a single undoable transaction.
+    pub fn transaction<T>(&mut self, f: impl FnOnce(&mut Self) -> Result<T>) -> Result<T> {
+        self.begin_transaction();
+        let result = f(self);
+        self.end_transaction();
+        result
+    }
+
+    /// Repairs state a panic may have left half-updated (FFI recovery)
+    ///
+    /// Closes an open transaction (its edits stay undoable as one step),
+    /// clamps the cursor and selection, and reparses from scratch since
+    /// the syntax tree may not have seen the last edit.
+    pub fn recover_after_panic(&mut self) {
+        self.transaction_depth = 0;
+        if let Some(transaction) = self.pending_transaction.take() {
+            if !transaction.edits.is_empty() {
+                self.push_undo_transaction(transaction);
             }
         }
 
-        Ok(())
+        self.cursor = Position::clamp(&self.cursor, &self.rope);
+        self.selection = self.selection.map(|selection| {
+            Selection::new(Position::clamp(&selection.start, &self.rope), Position::clamp(&selection.end, &self.rope))
+        });
+
+        self.syntax_tree = None;
+        self.injections.clear();
+        self.rebuild_completion_index();
+        self.reparse();
     }
 
     /// Moves cursor to position
@@ -254,41 +564,49 @@ impl Editor {
         let line_len = self.rope.line(line).len_chars();
         let column = position.column.min(line_len);
 
+        let from = self.cursor;
         self.cursor = Position::new(line, column);
+        let delta = Delta::between(from, self.cursor);
+        self.record_command(|| EditorCommand::MoveBy { delta });
     }
 
     /// Sets selection
     pub fn set_selection(&mut self, selection: Selection) {
         self.selection = Some(selection);
+        let cursor = self.cursor;
+        self.record_command(|| EditorCommand::Select {
+            start: Delta::between(cursor, selection.start),
+            end: Delta::between(cursor, selection.end),
+        });
     }
 
     /// Clears selection
     pub fn clear_selection(&mut self) {
         self.selection = None;
+        self.record_command(|| EditorCommand::ClearSelection);
     }
 
     /// Undo last edit
     pub fn undo(&mut self) -> Result<bool> {
-        if let Some(edit) = self.undo_stack.pop() {
-            // Reverse the edit
-            if !edit.inserted_text.is_empty() {
-                // Remove inserted text
+        self.ensure_writable()?;
+        let span = telemetry::span(Operation::Undo);
+        if let Some(transaction) = self.undo_stack.pop() {
+            // Reverse the edits (last edit first)
+            for edit in transaction.edits.iter().rev() {
                 let end = edit.position + edit.inserted_text.len();
-                self.rope.remove(edit.position..end);
-            }
-
-            if !edit.deleted_text.is_empty() {
-                // Re-insert deleted text
-                self.rope.insert(edit.position, &edit.deleted_text);
+                self.apply_raw_edit(edit.position, end, &edit.deleted_text);
             }
 
             // Update cursor
-            self.cursor = Position::from_byte_offset(&self.rope, edit.position);
+            if let Some(first) = transaction.edits.first() {
+                self.cursor = Position::from_byte_offset(&self.rope, first.position);
+            }
 
             // Push to redo stack
-            self.redo_stack.push(edit);
+            self.redo_stack.push(transaction);
 
             self.reparse();
+            self.performance.record_undo(span.elapsed());
             Ok(true)
         } else {
             Ok(false)
@@ -297,23 +615,24 @@ impl Editor {
 
     /// Redo last undone edit
     pub fn redo(&mut self) -> Result<bool> {
-        if let Some(edit) = self.redo_stack.pop() {
-            // Re-apply the edit
-            if !edit.deleted_text.is_empty() {
+        self.ensure_writable()?;
+        let span = telemetry::span(Operation::Redo);
+        if let Some(transaction) = self.redo_stack.pop() {
+            // Re-apply the edits in their original order
+            for edit in &transaction.edits {
                 let end = edit.position + edit.deleted_text.len();
-                self.rope.remove(edit.position..end);
+                self.apply_raw_edit(edit.position, end, &edit.inserted_text);
             }
 
-            if !edit.inserted_text.is_empty() {
-                self.rope.insert(edit.position, &edit.inserted_text);
+            if let Some(last) = transaction.edits.last() {
+                let new_offset = last.position + last.inserted_text.len();
+                self.cursor = Position::from_byte_offset(&self.rope, new_offset);
             }
 
-            let new_offset = edit.position + edit.inserted_text.len();
-            self.cursor = Position::from_byte_offset(&self.rope, new_offset);
-
-            self.undo_stack.push(edit);
+            self.undo_stack.push(transaction);
 
             self.reparse();
+            self.performance.record_redo(span.elapsed());
             Ok(true)
         } else {
             Ok(false)
@@ -349,33 +668,561 @@ impl Editor {
         self.is_dirty
     }
 
+    /// Gets the content version
+    ///
+    /// Increases with every change (edits, undo/redo, `set_content`), so it
+    /// can be used as the LSP document version.
+    pub fn version(&self) -> u64 {
+        self.version
+    }
+
     /// Marks editor as saved
     pub fn mark_saved(&mut self) {
         self.is_dirty = false;
+        if self.diff_state.as_ref().is_some_and(|diff| diff.follows_saved) {
+            self.set_diff_base_to_saved();
+        }
+    }
+
+    /// Gets the indentation settings
+    pub fn indent_config(&self) -> &IndentConfig {
+        &self.indent_config
+    }
+
+    /// Sets the indentation settings
+    pub fn set_indent_config(&mut self, config: IndentConfig) {
+        self.indent_config = config;
+    }
+
+    /// Gets the save-time settings
+    pub fn save_settings(&self) -> &SaveSettings {
+        &self.save_settings
+    }
+
+    /// Sets the save-time settings
+    pub fn set_save_settings(&mut self, settings: SaveSettings) {
+        self.save_settings = settings;
+    }
+
+    /// Gets the preferred maximum line length, if any
+    pub fn max_line_length(&self) -> Option<usize> {
+        self.max_line_length
+    }
+
+    /// Applies resolved EditorConfig properties.
+    ///
+    /// Updates indentation, save behaviour and the line length ruler.
+    /// Unset properties keep their current values.
+    pub fn apply_editorconfig(&mut self, props: &EditorConfigProperties) {
+        self.indent_config = props.to_indent_config(&self.indent_config);
+        self.save_settings = self.save_settings.with_editorconfig(props);
+        self.max_line_length = props.max_line_length.or(self.max_line_length);
+    }
+
+    /// Resolves `.editorconfig` files for a path and applies them.
+    pub fn load_editorconfig(&mut self, file_path: &std::path::Path) -> Result<()> {
+        let props = editorconfig::resolve_for_path(file_path)?;
+        self.apply_editorconfig(&props);
+        Ok(())
+    }
+
+    /// Prepares the buffer for saving and returns the bytes to write.
+    ///
+    /// Enforces the save settings:
+    /// - The save actions enabled for the document language (trailing
+    ///   whitespace, final newlines, indentation, ...) fix the buffer
+    ///   itself, as a single undoable transaction
+    /// - Line endings and charset are applied to the returned bytes
+    pub fn prepare_save(&mut self) -> Result<Vec<u8>> {
+        self.ensure_writable()?;
+        let settings = self.save_settings.clone();
+        self.run_save_actions(&settings.actions(&self.language))?;
+
+        let mut content = self.content();
+        if let Some(line_ending) = settings.end_of_line {
+            content = line_ending.normalize(&content);
+        }
+
+        Ok(settings.charset.encode(&content))
+    }
+
+    /// Replaces a byte range and records the edit for undo
+    fn replace_bytes(&mut self, start: usize, end: usize, text: &str) {
+        if start == end && text.is_empty() {
+            return;
+        }
+
+        let edit = Edit {
+            position: start,
+            deleted_text: self.rope.byte_slice(start..end).to_string(),
+            inserted_text: text.to_string(),
+        };
+        self.push_undo(edit);
+
+        self.apply_raw_edit(start, end, text);
+        self.is_dirty = true;
+        self.reparse();
+    }
+
+    /// Replaces a byte range in the rope without recording undo
+    ///
+    /// The syntax tree is adjusted with the edit so the next reparse is
+    /// incremental.
+    fn apply_raw_edit(&mut self, start: usize, end: usize, text: &str) {
+        let start_char = self.rope.byte_to_char(start);
+        let end_char = self.rope.byte_to_char(end);
+        let start_line = self.rope.byte_to_line(start);
+        let old_end_line = self.rope.byte_to_line(end);
+        let start_point = byte_to_point(&self.rope, start);
+        let old_end_point = byte_to_point(&self.rope, end);
+
+        if let Some(journal) = &mut self.edit_journal {
+            journal.record(Edit {
+                position: start,
+                deleted_text: self.rope.byte_slice(start..end).to_string(),
+                inserted_text: text.to_string(),
+            });
+        }
+        if let Some(collab) = &mut self.collab {
+            collab.record_local(start_char, end_char, text);
+        }
+
+        if end_char > start_char {
+            self.rope.remove(start_char..end_char);
+        }
+        if !text.is_empty() {
+            self.rope.insert(start_char, text);
+        }
+        self.version += 1;
+        if let Some(diff) = &mut self.diff_state {
+            diff.edit(&self.rope, start_line, old_end_line);
+        }
+
+        let new_end = start + text.len();
+        if let Some(spell) = &mut self.spell {
+            spell.edit(start_line, old_end_line, self.rope.byte_to_line(new_end));
+        }
+        let input_edit = InputEdit {
+            start_byte: start,
+            old_end_byte: end,
+            new_end_byte: new_end,
+            start_position: start_point,
+            old_end_position: old_end_point,
+            new_end_position: byte_to_point(&self.rope, new_end),
+        };
+        if let Some(tree) = &mut self.syntax_tree {
+            tree.edit(&input_edit);
+        }
+        self.injections.edit(&input_edit);
+
+        if self.size_class == FileSizeClass::Normal {
+            let new_end_line = self.rope.byte_to_line(new_end);
+            self.completion_index
+                .apply_edit(&self.rope, start_line, old_end_line, new_end_line);
+        }
     }
 
     /// Reparses the syntax tree (incremental)
+    ///
+    /// Deferred while a transaction is open. Edits that move the document
+    /// across the large-file threshold switch whole-document parsing off
+    /// (or back on).
     fn reparse(&mut self) {
-        if let Some(parser) = &mut self.parser {
-            let content = self.rope.to_string();
-            let tree = parser.parse(&content, self.syntax_tree.as_ref());
-            self.syntax_tree = tree;
+        if self.transaction_depth > 0 {
+            return;
         }
+
+        let size_class = self.classify_content_of(&self.rope);
+        if size_class != self.size_class {
+            self.size_class = size_class;
+            self.syntax_tree = None;
+            self.injections.clear();
+            self.rebuild_completion_index();
+        }
+        if size_class != FileSizeClass::Normal {
+            return;
+        }
+
+        self.parse_syntax();
     }
 
-    /// Pushes edit to undo stack
+    /// Pushes edit to undo stack (or to the open transaction)
     fn push_undo(&mut self, edit: Edit) {
+        self.redo_stack.clear(); // Clear redo stack on new edit
+
+        if let Some(transaction) = &mut self.pending_transaction {
+            transaction.edits.push(edit);
+        } else {
+            self.push_undo_transaction(Transaction { edits: vec![edit] });
+        }
+    }
+
+    /// Pushes a committed transaction to the undo stack
+    fn push_undo_transaction(&mut self, transaction: Transaction) {
         if self.undo_stack.len() >= self.max_undo_history {
             self.undo_stack.remove(0);
         }
-        self.undo_stack.push(edit);
-        self.redo_stack.clear(); // Clear redo stack on new edit
+        self.undo_stack.push(transaction);
+    }
+
+    /// Gets the document rope (for layout)
+    pub fn rope(&self) -> &Rope {
+        &self.rope
+    }
+
+    /// Gets the document language
+    pub fn language(&self) -> &LanguageId {
+        &self.language
     }
 
     /// Gets syntax tree (for rendering)
     pub fn syntax_tree(&self) -> Option<&Tree> {
         self.syntax_tree.as_ref()
     }
+
+    /// Gets the document symbol outline
+    ///
+    /// Built from the current syntax tree, so it reflects every edit once
+    /// the (incremental) reparse has run.
+    ///
+    /// Returns: Top-level symbols with nested children (empty without a grammar)
+    pub fn document_symbols(&self) -> Vec<DocumentSymbol> {
+        let Some(tree) = &self.syntax_tree else {
+            return Vec::new();
+        };
+
+        let mut layers = vec![(tree, &self.language)];
+        layers.extend(self.injections.layers().iter().map(|layer| (&layer.tree, &layer.language)));
+        document_symbols_layered(&self.rope, &layers)
+    }
+
+    /// Gets the injected language layers
+    pub fn injection_layers(&self) -> &[InjectionLayer] {
+        self.injections.layers()
+    }
+
+    /// Gets the innermost language at a position
+    ///
+    /// Inside embedded code (e.g. a Markdown code fence) this is the
+    /// embedded language, otherwise the document language.
+    pub fn language_at(&self, position: Position) -> LanguageId {
+        let position = Position::clamp(&position, &self.rope);
+        self.language_at_byte(position.to_byte_offset(&self.rope))
+    }
+
+    /// Gets syntax highlights for a line range
+    ///
+    /// Spans of injected layers follow the spans of their host, so
+    /// painting in order lets embedded code override the host colors.
+    ///
+    /// Parameters:
+    /// - `start_line`: First line
+    /// - `end_line`: Last line (inclusive)
+    ///
+    /// In large-file mode only the requested lines (plus context) are parsed.
+    pub fn highlights(&self, start_line: usize, end_line: usize) -> Vec<HighlightSpan> {
+        let _span = telemetry::span(Operation::Highlight);
+        if self.size_class != FileSizeClass::Normal {
+            return self.viewport_highlights(start_line, end_line);
+        }
+
+        let Some(tree) = &self.syntax_tree else {
+            return Vec::new();
+        };
+
+        let last_line = self.rope.len_lines().saturating_sub(1);
+        let start_byte = self.rope.line_to_byte(start_line.min(last_line));
+        let end_byte = if end_line < last_line {
+            self.rope.line_to_byte(end_line + 1)
+        } else {
+            self.rope.len_bytes()
+        };
+        let source = self.rope.to_string();
+
+        let mut spans = highlight_layer(tree, &self.language, &self.rope, &source, start_byte..end_byte, None, 0);
+        for layer in self.injections.layers() {
+            spans.extend(highlight_layer(
+                &layer.tree,
+                &layer.language,
+                &self.rope,
+                &source,
+                start_byte..end_byte,
+                Some(&layer.ranges),
+                layer.depth,
+            ));
+        }
+
+        spans.sort_by_key(|span| span.depth);
+        spans
+    }
+
+    /// Gets folding ranges (root and injected layers)
+    pub fn folding_ranges(&self) -> Vec<FoldingRange> {
+        let Some(tree) = &self.syntax_tree else {
+            return Vec::new();
+        };
+
+        let mut layers: Vec<(&Tree, Option<&[tree_sitter::Range]>)> = vec![(tree, None)];
+        layers.extend(
+            self.injections
+                .layers()
+                .iter()
+                .map(|layer| (&layer.tree, Some(layer.ranges.as_slice()))),
+        );
+        folding_ranges(&self.rope, &layers)
+    }
+
+    /// Toggles line comments using the language at the first line
+    ///
+    /// The comment syntax follows injections, so lines inside a Markdown
+    /// code fence use the fence's language. Languages without line
+    /// comments (CSS, HTML) and lines between JSX elements get block
+    /// comments. Recorded as one undo step.
+    ///
+    /// Parameters:
+    /// - `start_line`: First line
+    /// - `end_line`: Last line (inclusive)
+    ///
+    /// Returns: Some(true) if commented, Some(false) if uncommented,
+    /// None if the language has no comments
+    pub fn toggle_line_comments(&mut self, start_line: usize, end_line: usize) -> Result<Option<bool>> {
+        self.ensure_writable()?;
+        let last_line = self.rope.len_lines().saturating_sub(1);
+        if start_line > last_line {
+            return Ok(None);
+        }
+        let end_line = end_line.clamp(start_line, last_line);
+
+        // Language at the first non-whitespace character
+        let line = self.rope.line(start_line);
+        let indent = line.chars().take_while(|c| *c == ' ' || *c == '\t').count();
+        let byte = self.rope.line_to_byte(start_line) + line.char_to_byte(indent);
+        let Some(config) = self.comment_config_at(byte) else {
+            return Ok(None);
+        };
+
+        let mut scratch = self.rope.clone();
+        let commented = toggle_line_comments(&mut scratch, start_line, end_line, &config);
+        self.replace_lines_from(&scratch, start_line, end_line);
+        Ok(Some(commented))
+    }
+
+    /// Finds structural matches in the document
+    pub fn structural_search(&self, query: &StructuralQuery) -> Vec<StructuralMatch> {
+        let _span = telemetry::span(Operation::Search);
+        match &self.syntax_tree {
+            Some(tree) if *query.language() == self.language => query.find(tree, &self.rope),
+            _ => Vec::new(),
+        }
+    }
+
+    /// Previews a structural replacement without editing
+    ///
+    /// Returns: Matches with their replacement text
+    pub fn structural_preview(&self, query: &StructuralQuery, replacement: &str) -> Result<Vec<StructuralMatch>> {
+        match &self.syntax_tree {
+            Some(tree) if *query.language() == self.language => {
+                Ok(query.preview(tree, &self.rope, replacement)?)
+            }
+            _ => Ok(Vec::new()),
+        }
+    }
+
+    /// Replaces every structural match as one undoable transaction
+    ///
+    /// Parameters:
+    /// - `query`: Compiled search query (must match the editor language)
+    /// - `replacement`: Replacement template (`$name` for captures)
+    ///
+    /// Returns: The replaced matches
+    pub fn structural_replace(&mut self, query: &StructuralQuery, replacement: &str) -> Result<Vec<StructuralMatch>> {
+        self.ensure_writable()?;
+        let matches = self.structural_preview(query, replacement)?;
+        if matches.is_empty() {
+            return Ok(matches);
+        }
+
+        self.transaction(|editor| {
+            // Back to front so earlier offsets stay valid
+            for m in matches.iter().rev() {
+                let text = m.replacement.as_deref().unwrap_or_default();
+                editor.replace_bytes(m.start_byte, m.end_byte, text);
+            }
+            Ok(())
+        })?;
+        self.cursor = Position::clamp(&self.cursor, &self.rope);
+
+        Ok(matches)
+    }
+
+    /// Gets the large-file thresholds
+    pub fn large_file_config(&self) -> &LargeFileConfig {
+        &self.large_file_config
+    }
+
+    /// Sets the large-file thresholds and reclassifies the document
+    pub fn set_large_file_config(&mut self, config: LargeFileConfig) {
+        self.large_file_config = config;
+        self.reparse();
+    }
+
+    /// Gets the size class of the document
+    ///
+    /// Editors are at most `Large`: huge files are viewed through
+    /// `LargeFileView` instead.
+    pub fn size_class(&self) -> FileSizeClass {
+        self.size_class
+    }
+
+    /// Gets the features degraded by large-file mode or read-only viewing
+    pub fn degraded_features(&self) -> DegradedFeatures {
+        DegradedFeatures::for_class(self.size_class, &self.large_file_config, self.read_only)
+    }
+
+    /// Checks if edits are rejected
+    pub fn is_read_only(&self) -> bool {
+        self.read_only
+    }
+
+    /// Makes the document read-only (or editable again)
+    pub fn set_read_only(&mut self, read_only: bool) {
+        self.read_only = read_only;
+    }
+
+    /// Appends streamed text at the end of the document (tail -f)
+    ///
+    /// Allowed on read-only documents, since the text comes from the
+    /// file rather than the user. Not recorded for undo and does not move
+    /// the cursor or mark the document dirty.
+    pub fn append_streamed(&mut self, text: &str) {
+        if text.is_empty() {
+            return;
+        }
+
+        let end = self.rope.len_bytes();
+        self.apply_raw_edit(end, end, text);
+        self.cursor = Position::clamp(&self.cursor, &self.rope);
+        self.reparse();
+    }
+
+    /// Helper: Rejects edits on read-only documents
+    fn ensure_writable(&self) -> Result<()> {
+        if self.read_only {
+            bail!("document is read-only");
+        }
+        Ok(())
+    }
+
+    /// Helper: Classifies content by size (capped at `Large`)
+    fn classify_content_of(&self, rope: &Rope) -> FileSizeClass {
+        self.large_file_config
+            .classify(rope.len_bytes() as u64)
+            .min(FileSizeClass::Large)
+    }
+
+    /// Helper: Rebuilds the completion index (emptied in large-file mode)
+    fn rebuild_completion_index(&mut self) {
+        if self.size_class == FileSizeClass::Normal {
+            self.completion_index.rebuild(&self.rope, self.syntax_tree.as_ref());
+        } else {
+            self.completion_index = CompletionIndex::new();
+        }
+    }
+
+    /// Helper: Highlights a line range from a throwaway parse of the lines
+    /// around it (large-file mode)
+    fn viewport_highlights(&self, start_line: usize, end_line: usize) -> Vec<HighlightSpan> {
+        if !self.large_file_config.viewport_highlighting {
+            return Vec::new();
+        }
+
+        let last_line = self.rope.len_lines().saturating_sub(1);
+        let start_line = start_line.min(last_line);
+        let end_line = end_line.clamp(start_line, last_line);
+        let context = self.large_file_config.viewport_context_lines;
+        let first_line = start_line.saturating_sub(context);
+
+        let line_end_byte = |line: usize| {
+            if line < last_line {
+                self.rope.line_to_byte(line + 1)
+            } else {
+                self.rope.len_bytes()
+            }
+        };
+        let first_byte = self.rope.line_to_byte(first_line);
+        let excerpt_end = line_end_byte(end_line.saturating_add(context).min(last_line));
+        let excerpt = self.rope.byte_slice(first_byte..excerpt_end).to_string();
+        let visible = self.rope.line_to_byte(start_line) - first_byte..line_end_byte(end_line) - first_byte;
+
+        highlight_excerpt(&self.language, &excerpt, first_line, first_byte, visible)
+    }
+
+    /// Helper: Gets the innermost language at a byte offset
+    fn language_at_byte(&self, byte: usize) -> LanguageId {
+        match self.injections.layer_at(byte) {
+            Some(layer) => layer.language.clone(),
+            None => self.language.clone(),
+        }
+    }
+
+    /// Gets the scopes to pin above the viewport (sticky scroll)
+    ///
+    /// Parameters:
+    /// - `top_line`: First visible line
+    /// - `max_scopes`: Maximum number of pinned scopes
+    ///
+    /// Returns: Enclosing scopes from outermost to innermost
+    pub fn sticky_scopes(&self, top_line: usize, max_scopes: usize) -> Vec<StickyScope> {
+        sticky_scopes(
+            &self.rope,
+            self.syntax_tree.as_ref(),
+            top_line,
+            max_scopes,
+            self.indent_config.tab_size,
+        )
+    }
+
+    /// Gets indentation guides for a viewport
+    ///
+    /// The guide of the scope containing the cursor is marked active.
+    ///
+    /// Parameters:
+    /// - `start_line`: First visible line
+    /// - `end_line`: Last visible line (inclusive)
+    pub fn indent_guides(&self, start_line: usize, end_line: usize) -> Vec<IndentGuide> {
+        indent_guides(
+            &self.rope,
+            self.syntax_tree.as_ref(),
+            start_line,
+            end_line,
+            self.cursor,
+            self.indent_config.tab_size,
+        )
+    }
+
+    /// Gets the local completion index
+    pub fn completion_index(&self) -> &CompletionIndex {
+        &self.completion_index
+    }
+
+    /// Computes local completions at a position
+    ///
+    /// Parameters:
+    /// - `position`: Cursor position
+    /// - `others`: Other open editors whose words are also offered
+    ///
+    /// Returns: LSP-shaped completion list
+    pub fn completions(&self, position: Position, others: &[&Editor]) -> CompletionList {
+        let other_indexes: Vec<&CompletionIndex> =
+            others.iter().map(|editor| &editor.completion_index).collect();
+        complete(&self.rope, &self.completion_index, position, &other_indexes)
+    }
+}
+
+/// Helper: Converts a byte offset to a tree-sitter point (row, byte column)
+fn byte_to_point(rope: &Rope, byte: usize) -> Point {
+    let row = rope.byte_to_line(byte);
+    Point::new(row, byte - rope.line_to_byte(row))
 }
 
 impl Default for Editor {
@@ -522,6 +1369,9 @@ mod tests {
         assert_eq!(LanguageId::parse("java"), LanguageId::Java);
         assert_eq!(LanguageId::parse("go"), LanguageId::Go);
         assert_eq!(LanguageId::parse("dart"), LanguageId::Dart);
+        assert_eq!(LanguageId::parse("html"), LanguageId::Html);
+        assert_eq!(LanguageId::parse("css"), LanguageId::Css);
+        assert_eq!(LanguageId::parse("md"), LanguageId::Markdown);
 
         assert_eq!(LanguageId::parse("unknown"), LanguageId::PlainText);
         assert_eq!(LanguageId::parse(""), LanguageId::PlainText);
@@ -535,6 +1385,9 @@ mod tests {
         assert!(LanguageId::Python.tree_sitter_language().is_some());
         assert!(LanguageId::Java.tree_sitter_language().is_some());
         assert!(LanguageId::Go.tree_sitter_language().is_some());
+        assert!(LanguageId::Html.tree_sitter_language().is_some());
+        assert!(LanguageId::Css.tree_sitter_language().is_some());
+        assert!(LanguageId::Markdown.tree_sitter_language().is_some());
 
         assert!(LanguageId::PlainText.tree_sitter_language().is_none());
         assert!(LanguageId::Dart.tree_sitter_language().is_none());
@@ -1066,4 +1919,398 @@ mod tests {
         editor.delete().unwrap();
         assert_eq!(editor.content(), "");
     }
+
+    // ============================================================
+    // Editor - Transactions
+    // ============================================================
+
+    #[test]
+    fn test_transaction_undo_as_single_step() {
+        let mut editor = Editor::new();
+        editor.set_content("abc").unwrap();
+
+        editor.transaction(|ed| {
+            ed.replace_range(Position::new(0, 0), Position::new(0, 1), "X")?;
+            ed.replace_range(Position::new(0, 2), Position::new(0, 3), "Z")
+        }).unwrap();
+        assert_eq!(editor.content(), "XbZ");
+
+        editor.undo().unwrap();
+        assert_eq!(editor.content(), "abc");
+
+        editor.redo().unwrap();
+        assert_eq!(editor.content(), "XbZ");
+    }
+
+    #[test]
+    fn test_nested_transactions_commit_once() {
+        let mut editor = Editor::new();
+        editor.begin_transaction();
+        editor.insert_text("a").unwrap();
+        editor.begin_transaction();
+        editor.insert_text("b").unwrap();
+        editor.end_transaction();
+        editor.insert_text("c").unwrap();
+        editor.end_transaction();
+
+        assert_eq!(editor.content(), "abc");
+        assert!(editor.undo().unwrap());
+        assert_eq!(editor.content(), "");
+        assert!(!editor.undo().unwrap());
+    }
+
+    #[test]
+    fn test_undo_redo_unicode() {
+        let mut editor = Editor::new();
+        editor.insert_text("世界").unwrap();
+        editor.insert_text("🦀!").unwrap();
+
+        editor.undo().unwrap();
+        assert_eq!(editor.content(), "世界");
+        editor.redo().unwrap();
+        assert_eq!(editor.content(), "世界🦀!");
+    }
+
+    // ============================================================
+    // Editor - EditorConfig / Save
+    // ============================================================
+
+    #[test]
+    fn test_apply_editorconfig_indent() {
+        let mut editor = Editor::new();
+        editor.apply_editorconfig(&EditorConfigProperties {
+            indent_style: Some(IndentStyle::Space),
+            indent_size: Some(IndentSize::Columns(2)),
+            max_line_length: Some(80),
+            ..Default::default()
+        });
+
+        assert_eq!(editor.indent_config().indent_string(), "  ");
+        assert_eq!(editor.max_line_length(), Some(80));
+    }
+
+    #[test]
+    fn test_prepare_save_enforces_settings() {
+        let mut editor = Editor::new();
+        editor.set_content("fn main() {  \n    x; \t\n}").unwrap();
+        editor.apply_editorconfig(&EditorConfigProperties {
+            trim_trailing_whitespace: Some(true),
+            insert_final_newline: Some(true),
+            end_of_line: Some(LineEnding::CrLf),
+            ..Default::default()
+        });
+
+        let bytes = editor.prepare_save().unwrap();
+        assert_eq!(editor.content(), "fn main() {\n    x;\n}\n");
+        assert_eq!(bytes, b"fn main() {\r\n    x;\r\n}\r\n".to_vec());
+
+        // Save fixes are a single undo step
+        editor.undo().unwrap();
+        assert_eq!(editor.content(), "fn main() {  \n    x; \t\n}");
+    }
+
+    #[test]
+    fn test_prepare_save_default_keeps_content() {
+        let mut editor = Editor::new();
+        editor.set_content("a  \r\nb").unwrap();
+
+        let bytes = editor.prepare_save().unwrap();
+        assert_eq!(bytes, b"a  \r\nb".to_vec());
+        assert!(!editor.undo().unwrap());
+    }
+    #[test]
+    fn test_completion_index_follows_edits() {
+        let mut editor = Editor::with_content("fn compute_total() {}
+", LanguageId::Rust).unwrap();
+        assert_eq!(editor.completion_index().frequency("compute_total"), 1);
+
+        editor.move_cursor(Position::new(1, 0));
+        editor.insert_text("let computed = 1;
+com").unwrap();
+        assert_eq!(editor.completion_index().frequency("computed"), 1);
+
+        let list = editor.completions(editor.cursor(), &[]);
+        let labels: Vec<&str> = list.items.iter().map(|i| i.label.as_str()).collect();
+        assert_eq!(labels, vec!["computed", "compute_total"]);
+        assert_eq!(list.items[1].kind, CompletionItemKind::Function);
+
+        // Undo removes the words again
+        editor.undo().unwrap();
+        assert_eq!(editor.completion_index().frequency("computed"), 0);
+    }
+
+    #[test]
+    fn test_incremental_reparse_matches_full_parse() {
+        let mut editor = Editor::with_content("fn a() {}\n\nfn c() {}\n", LanguageId::Rust).unwrap();
+
+        editor.move_cursor(Position::new(1, 0));
+        editor.insert_text("struct B {\n    field: u8,\n}").unwrap();
+        editor.move_cursor(Position::new(0, 4));
+        editor.insert_text("lpha").unwrap();
+
+        let fresh = Editor::with_content(&editor.content(), LanguageId::Rust).unwrap();
+        assert_eq!(
+            editor.syntax_tree().unwrap().root_node().to_sexp(),
+            fresh.syntax_tree().unwrap().root_node().to_sexp()
+        );
+        assert_eq!(editor.document_symbols(), fresh.document_symbols());
+
+        let names: Vec<String> = editor.document_symbols().into_iter().map(|s| s.name).collect();
+        assert_eq!(names, vec!["alpha", "B", "c"]);
+
+        // Undo keeps the outline in sync too
+        editor.undo().unwrap();
+        let names: Vec<String> = editor.document_symbols().into_iter().map(|s| s.name).collect();
+        assert_eq!(names, vec!["a", "B", "c"]);
+    }
+
+    #[test]
+    fn test_document_symbols_plain_text() {
+        let editor = Editor::with_content("fn main() {}", LanguageId::PlainText).unwrap();
+        assert!(editor.document_symbols().is_empty());
+    }
+
+    #[test]
+    fn test_markdown_injection_language_and_comments() {
+        let content = "# Notes\n\n```python\nx = 1\ny = 2\n```\n\n```rust\nlet a = 1;\n```\n";
+        let mut editor = Editor::with_content(content, LanguageId::Markdown).unwrap();
+
+        assert_eq!(editor.language_at(Position::new(0, 2)), LanguageId::Markdown);
+        assert_eq!(editor.language_at(Position::new(3, 0)), LanguageId::Python);
+        assert_eq!(editor.language_at(Position::new(8, 4)), LanguageId::Rust);
+
+        // Comment toggling uses the fence language
+        assert_eq!(editor.toggle_line_comments(3, 4).unwrap(), Some(true));
+        assert_eq!(editor.line(3).unwrap(), "# x = 1\n");
+        assert_eq!(editor.toggle_line_comments(8, 8).unwrap(), Some(true));
+        assert_eq!(editor.line(8).unwrap(), "// let a = 1;\n");
+
+        // Markdown itself has no comment syntax
+        assert_eq!(editor.toggle_line_comments(0, 0).unwrap(), None);
+
+        editor.undo().unwrap();
+        assert_eq!(editor.line(8).unwrap(), "let a = 1;\n");
+    }
+
+    #[test]
+    fn test_injections_follow_incremental_edits() {
+        let content = "<p>hi</p>\n<script>\nlet a = 1;\n</script>\n";
+        let mut editor = Editor::with_content(content, LanguageId::Html).unwrap();
+        assert_eq!(editor.injection_layers().len(), 1);
+
+        // Edit inside the script, then before it
+        editor.move_cursor(Position::new(2, 10));
+        editor.insert_text("\nfunction run() {}").unwrap();
+        editor.move_cursor(Position::new(0, 0));
+        editor.insert_text("<h1>Title</h1>\n").unwrap();
+
+        let layer = &editor.injection_layers()[0];
+        assert_eq!(layer.language, LanguageId::JavaScript);
+        let script = &editor.content()[layer.ranges[0].start_byte..layer.ranges[0].end_byte];
+        assert!(script.contains("let a = 1;\nfunction run() {}\n"));
+
+        let fresh = Editor::with_content(&editor.content(), LanguageId::Html).unwrap();
+        assert_eq!(
+            layer.tree.root_node().to_sexp(),
+            fresh.injection_layers()[0].tree.root_node().to_sexp()
+        );
+
+        let names: Vec<String> = editor.document_symbols().into_iter().map(|s| s.name).collect();
+        assert!(names.contains(&"run".to_string()));
+        assert_eq!(editor.language_at(Position::new(4, 2)), LanguageId::JavaScript);
+    }
+
+    #[test]
+    fn test_layered_highlights_and_folding() {
+        let content = "Intro\n\n```rust\nfn main() {\n    go();\n}\n```\n";
+        let editor = Editor::with_content(content, LanguageId::Markdown).unwrap();
+
+        let spans = editor.highlights(0, 7);
+        let rust_keyword = spans
+            .iter()
+            .find(|s| s.language == "rust" && s.capture == "keyword")
+            .unwrap();
+        assert_eq!(rust_keyword.depth, 1);
+        assert_eq!(rust_keyword.range.start.line, 3);
+        // Host spans come before injected spans
+        assert!(spans.iter().position(|s| s.depth == 1) > spans.iter().position(|s| s.depth == 0));
+
+        // The Rust function body folds inside the code fence
+        let folds = editor.folding_ranges();
+        assert!(folds.iter().any(|f| f.start_line == 3 && f.end_line == 4));
+    }
+
+    #[test]
+    fn test_language_from_path() {
+        use std::path::Path;
+
+        assert_eq!(LanguageId::from_path(Path::new("src/main.rs")), LanguageId::Rust);
+        assert_eq!(LanguageId::from_path(Path::new("App.tsx")), LanguageId::TypeScript);
+        assert_eq!(LanguageId::from_path(Path::new("README.md")), LanguageId::Markdown);
+        assert_eq!(LanguageId::from_path(Path::new("Makefile")), LanguageId::PlainText);
+    }
+
+    #[test]
+    fn test_structural_replace_single_undo() {
+        let content = "fn main() {\n    let a = x.unwrap();\n    let b = y.unwrap();\n}\n";
+        let mut editor = Editor::with_content(content, LanguageId::Rust).unwrap();
+        let query = StructuralQuery::from_template(&LanguageId::Rust, "$e.unwrap()").unwrap();
+
+        let preview = editor.structural_preview(&query, "$e?").unwrap();
+        assert_eq!(preview.len(), 2);
+        assert_eq!(editor.content(), content);
+
+        let replaced = editor.structural_replace(&query, "$e?").unwrap();
+        assert_eq!(replaced.len(), 2);
+        assert_eq!(editor.content(), "fn main() {\n    let a = x?;\n    let b = y?;\n}\n");
+        assert!(editor.structural_search(&query).is_empty());
+
+        assert!(editor.undo().unwrap());
+        assert_eq!(editor.content(), content);
+    }
+
+    #[test]
+    fn test_completions_from_other_editors() {
+        let editor = Editor::with_content("wid", LanguageId::PlainText).unwrap();
+        let other = Editor::with_content("widget_factory", LanguageId::PlainText).unwrap();
+
+        let list = editor.completions(Position::new(0, 3), &[&other]);
+        assert_eq!(list.items[0].label, "widget_factory");
+    }
+
+    fn small_large_file_config() -> LargeFileConfig {
+        LargeFileConfig {
+            large_file_bytes: 64,
+            huge_file_bytes: 4096,
+            viewport_context_lines: 2,
+            ..LargeFileConfig::default()
+        }
+    }
+
+    #[test]
+    fn test_large_file_mode_degrades_features() {
+        let mut editor = Editor::new();
+        editor.set_large_file_config(small_large_file_config());
+        editor.set_language(LanguageId::Rust).unwrap();
+
+        let content = "// filler line\n".repeat(10) + "fn main() {\n    go();\n}\n";
+        editor.set_content(&content).unwrap();
+
+        assert_eq!(editor.size_class(), FileSizeClass::Large);
+        assert!(editor.syntax_tree().is_none());
+        assert!(editor.folding_ranges().is_empty());
+        assert_eq!(editor.completion_index().word_count(), 0);
+        let features = editor.degraded_features();
+        assert!(features.is_disabled(Feature::SyntaxTree));
+        assert_eq!(features.highlighting, HighlightScope::Viewport);
+
+        // Viewport highlighting still works, in document coordinates
+        let spans = editor.highlights(10, 10);
+        let keyword = spans.iter().find(|s| s.capture == "keyword").unwrap();
+        assert_eq!(keyword.range.start.line, 10);
+        assert_eq!(&content[keyword.start_byte..keyword.end_byte], "fn");
+
+        // Shrinking below the threshold re-enables full parsing
+        editor.set_content("fn main() {}\n").unwrap();
+        assert_eq!(editor.size_class(), FileSizeClass::Normal);
+        assert!(editor.syntax_tree().is_some());
+
+        // Typing past the threshold switches it off again
+        editor.move_cursor(Position::new(1, 0));
+        editor.insert_text(&"x".repeat(100)).unwrap();
+        assert_eq!(editor.size_class(), FileSizeClass::Large);
+        assert!(editor.syntax_tree().is_none());
+    }
+
+    #[test]
+    fn test_read_only_and_streamed_append() {
+        let mut editor = Editor::with_content("log 1\n", LanguageId::PlainText).unwrap();
+        editor.mark_saved();
+        editor.set_read_only(true);
+
+        assert!(editor.insert_text("x").is_err());
+        assert!(editor.undo().is_err());
+        assert!(editor.degraded_features().is_disabled(Feature::Editing));
+
+        editor.append_streamed("log 2\n");
+        assert_eq!(editor.content(), "log 1\nlog 2\n");
+        assert!(!editor.is_dirty());
+        assert_eq!(editor.cursor(), Position::new(0, 0));
+
+        editor.set_read_only(false);
+        editor.insert_text("x").unwrap();
+        assert_eq!(editor.line(0).unwrap(), "xlog 1\n");
+    }
+
+    #[test]
+    fn test_open_file_by_size() {
+        let dir = std::env::temp_dir();
+        let small = dir.join(format!("open_small_{}.rs", std::process::id()));
+        let large = dir.join(format!("open_large_{}.rs", std::process::id()));
+        let huge = dir.join(format!("open_huge_{}.rs", std::process::id()));
+        std::fs::write(&small, "fn main() {}\n").unwrap();
+        std::fs::write(&large, "// line\n".repeat(20)).unwrap();
+        std::fs::write(&huge, "// line\n".repeat(1000)).unwrap();
+
+        let editor = Editor::open_file(&small, small_large_file_config()).unwrap();
+        assert_eq!(*editor.language(), LanguageId::Rust);
+        assert_eq!(editor.size_class(), FileSizeClass::Normal);
+        assert!(editor.syntax_tree().is_some());
+        assert!(!editor.is_dirty());
+
+        let editor = Editor::open_file(&large, small_large_file_config()).unwrap();
+        assert_eq!(editor.size_class(), FileSizeClass::Large);
+        assert_eq!(editor.line_count(), 21);
+
+        assert!(Editor::open_file(&huge, small_large_file_config()).is_err());
+
+        for path in [small, large, huge] {
+            std::fs::remove_file(path).ok();
+        }
+    }
+
+    #[test]
+    fn test_open_file_charset_round_trip() {
+        let path = std::env::temp_dir().join(format!("open_charset_{}.txt", std::process::id()));
+        let text = "héllo\nwörld\n";
+        for charset in [Charset::Utf8, Charset::Utf8Bom, Charset::Latin1, Charset::Utf16Be, Charset::Utf16Le] {
+            let bytes = charset.encode(text);
+            std::fs::write(&path, &bytes).unwrap();
+
+            let mut editor = Editor::open_file_with_charset(&path, LargeFileConfig::default(), Some(charset)).unwrap();
+            assert_eq!(editor.content(), text, "{:?}", charset);
+            assert_eq!(editor.prepare_save().unwrap(), bytes, "{:?}", charset);
+
+            // Byte order marks are detected without a charset
+            if charset != Charset::Latin1 && charset != Charset::Utf8 {
+                let editor = Editor::open_file(&path, LargeFileConfig::default()).unwrap();
+                assert_eq!(editor.content(), text, "{:?}", charset);
+                assert_eq!(editor.save_settings().charset, charset);
+            }
+        }
+
+        // A BOM is not written twice, even if the buffer starts with U+FEFF
+        std::fs::write(&path, Charset::Utf8Bom.encode("a\n")).unwrap();
+        let mut editor = Editor::open_file(&path, LargeFileConfig::default()).unwrap();
+        editor.set_content("\u{FEFF}a\n").unwrap();
+        assert_eq!(editor.prepare_save().unwrap(), vec![0xEF, 0xBB, 0xBF, b'a', b'\n']);
+
+        // Latin-1 bytes are not valid UTF-8
+        std::fs::write(&path, [b'a', 0xE9]).unwrap();
+        assert!(Editor::open_file(&path, LargeFileConfig::default()).is_err());
+        std::fs::remove_file(path).ok();
+    }
+
+    #[test]
+    fn test_version_bumps_on_every_change() {
+        let mut editor = Editor::with_content("a", LanguageId::PlainText).unwrap();
+        let initial = editor.version();
+
+        editor.insert_text("b").unwrap();
+        assert_eq!(editor.version(), initial + 1);
+        editor.undo().unwrap();
+        assert_eq!(editor.version(), initial + 2);
+        editor.move_cursor(Position::new(0, 1));
+        assert_eq!(editor.version(), initial + 2);
+    }
 }
@@ -88,7 +88,7 @@ impl MultiEdit {
         let mut sorted_edits = self.edits.clone();
 
         // Sort by position (descending) to apply from bottom to top
-        sorted_edits.sort_by(|a, b| b.position.cmp(&a.position));
+        sorted_edits.sort_by_key(|edit| std::cmp::Reverse(edit.position));
 
         // Check for overlapping edits (on sorted list for efficiency)
         for i in 0..sorted_edits.len() {
@@ -335,7 +335,7 @@ mod tests {
             Position::new(2, 1),
         );
 
-        let mut multi_edit = col_sel.insert_text(&mut rope, "X");
+        let multi_edit = col_sel.insert_text(&mut rope, "X");
         multi_edit.apply(&mut rope);
 
         let result = rope.to_string();
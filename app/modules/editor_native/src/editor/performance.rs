@@ -1,6 +1,8 @@
 use std::time::{Duration, Instant};
 use std::collections::VecDeque;
 
+use serde::Serialize;
+
 /// Performance metrics for editor operations.
 ///
 /// Tracks operation latency to help identify performance bottlenecks.
@@ -11,6 +13,11 @@ pub struct PerformanceMetrics {
     delete_times: VecDeque<Duration>,
     undo_times: VecDeque<Duration>,
     redo_times: VecDeque<Duration>,
+    parse_times: VecDeque<Duration>,
+
+    /// Parses recorded / given up (cancelled, timed out or superseded)
+    parse_count: usize,
+    cancelled_parses: usize,
 
     /// Maximum samples to keep
     max_samples: usize,
@@ -29,6 +36,9 @@ impl PerformanceMetrics {
             delete_times: VecDeque::with_capacity(max_samples),
             undo_times: VecDeque::with_capacity(max_samples),
             redo_times: VecDeque::with_capacity(max_samples),
+            parse_times: VecDeque::with_capacity(max_samples),
+            parse_count: 0,
+            cancelled_parses: 0,
             max_samples,
         }
     }
@@ -53,6 +63,17 @@ impl PerformanceMetrics {
         Self::add_sample(&mut self.redo_times, duration, self.max_samples);
     }
 
+    /// Records a completed syntax parse.
+    pub fn record_parse(&mut self, duration: Duration) {
+        Self::add_sample(&mut self.parse_times, duration, self.max_samples);
+        self.parse_count += 1;
+    }
+
+    /// Records a parse that was cancelled, timed out or superseded.
+    pub fn record_cancelled_parse(&mut self) {
+        self.cancelled_parses += 1;
+    }
+
     /// Gets average insert time.
     pub fn avg_insert_time(&self) -> Option<Duration> {
         Self::calculate_average(&self.insert_times)
@@ -73,6 +94,26 @@ impl PerformanceMetrics {
         Self::calculate_average(&self.redo_times)
     }
 
+    /// Gets average parse time.
+    pub fn avg_parse_time(&self) -> Option<Duration> {
+        Self::calculate_average(&self.parse_times)
+    }
+
+    /// Gets p95 (95th percentile) parse time.
+    pub fn p95_parse_time(&self) -> Option<Duration> {
+        Self::calculate_percentile(&self.parse_times, 95)
+    }
+
+    /// Gets the number of completed parses.
+    pub fn parse_count(&self) -> usize {
+        self.parse_count
+    }
+
+    /// Gets the number of parses that were given up.
+    pub fn cancelled_parses(&self) -> usize {
+        self.cancelled_parses
+    }
+
     /// Gets p95 (95th percentile) insert time.
     pub fn p95_insert_time(&self) -> Option<Duration> {
         Self::calculate_percentile(&self.insert_times, 95)
@@ -89,6 +130,9 @@ impl PerformanceMetrics {
         self.delete_times.clear();
         self.undo_times.clear();
         self.redo_times.clear();
+        self.parse_times.clear();
+        self.parse_count = 0;
+        self.cancelled_parses = 0;
     }
 
     /// Adds a sample to a rolling window.
@@ -147,7 +191,8 @@ impl OperationTimer {
 }
 
 /// Performance statistics summary.
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
 pub struct PerformanceStats {
     pub avg_insert_ms: f64,
     pub avg_delete_ms: f64,
@@ -155,6 +200,10 @@ pub struct PerformanceStats {
     pub avg_redo_ms: f64,
     pub p95_insert_ms: f64,
     pub p99_insert_ms: f64,
+    pub avg_parse_ms: f64,
+    pub p95_parse_ms: f64,
+    pub parse_count: usize,
+    pub cancelled_parses: usize,
 }
 
 impl PerformanceMetrics {
@@ -179,6 +228,14 @@ impl PerformanceMetrics {
             p99_insert_ms: self.p99_insert_time()
                 .map(|d| d.as_secs_f64() * 1000.0)
                 .unwrap_or(0.0),
+            avg_parse_ms: self.avg_parse_time()
+                .map(|d| d.as_secs_f64() * 1000.0)
+                .unwrap_or(0.0),
+            p95_parse_ms: self.p95_parse_time()
+                .map(|d| d.as_secs_f64() * 1000.0)
+                .unwrap_or(0.0),
+            parse_count: self.parse_count,
+            cancelled_parses: self.cancelled_parses,
         }
     }
 }
@@ -249,4 +306,23 @@ mod tests {
         assert_eq!(stats.avg_insert_ms, 5.0);
         assert_eq!(stats.avg_delete_ms, 3.0);
     }
+
+    #[test]
+    fn test_parse_metrics() {
+        let mut metrics = PerformanceMetrics::new(10);
+        assert!(metrics.avg_parse_time().is_none());
+
+        metrics.record_parse(Duration::from_millis(2));
+        metrics.record_parse(Duration::from_millis(4));
+        metrics.record_cancelled_parse();
+
+        let stats = metrics.get_stats();
+        assert_eq!(stats.avg_parse_ms, 3.0);
+        assert_eq!(stats.parse_count, 2);
+        assert_eq!(stats.cancelled_parses, 1);
+
+        metrics.clear();
+        assert_eq!(metrics.parse_count(), 0);
+        assert!(metrics.p95_parse_time().is_none());
+    }
 }
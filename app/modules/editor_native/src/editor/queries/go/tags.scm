@@ -0,0 +1,35 @@
+; Document symbols for Go (see rust/tags.scm for capture names)
+
+(function_declaration
+  name: (identifier) @name
+  parameters: (parameter_list) @detail) @definition.function
+
+(method_declaration
+  receiver: (parameter_list) @detail
+  name: (field_identifier) @name) @definition.method
+
+(type_spec
+  name: (type_identifier) @name
+  type: (struct_type)) @definition.struct
+
+(type_spec
+  name: (type_identifier) @name
+  type: (interface_type)) @definition.interface
+
+(type_spec
+  name: (type_identifier) @name) @definition.type
+
+(field_declaration
+  name: (field_identifier) @name
+  type: (_) @detail) @definition.field
+
+(method_spec
+  name: (field_identifier) @name) @definition.method
+
+(const_spec
+  name: (identifier) @name) @definition.constant
+
+(source_file
+  (var_declaration
+    (var_spec
+      name: (identifier) @name) @definition.variable))
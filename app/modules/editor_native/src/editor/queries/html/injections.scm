@@ -0,0 +1,18 @@
+; Language injections for HTML
+;
+; Captures:
+; - @injection.content: Node whose text is parsed with the injected language
+; - @injection.language: Node whose text names the language (optional)
+;
+; Properties:
+; - injection.language: Fixed language name
+; - injection.combined: Parse all matches of the pattern as one document
+; - injection.include-children: Keep child nodes in the content ranges
+
+((script_element
+  (raw_text) @injection.content)
+ (#set! injection.language "javascript"))
+
+((style_element
+  (raw_text) @injection.content)
+ (#set! injection.language "css"))
@@ -0,0 +1,26 @@
+; Document symbols for Java (see rust/tags.scm for capture names)
+
+(class_declaration
+  name: (identifier) @name) @definition.class
+
+(interface_declaration
+  name: (identifier) @name) @definition.interface
+
+(enum_declaration
+  name: (identifier) @name) @definition.enum
+
+(enum_constant
+  name: (identifier) @name) @definition.enum_member
+
+(constructor_declaration
+  name: (identifier) @name
+  parameters: (formal_parameters) @detail) @definition.constructor
+
+(method_declaration
+  name: (identifier) @name
+  parameters: (formal_parameters) @detail) @definition.method
+
+(field_declaration
+  type: (_) @detail
+  declarator: (variable_declarator
+    name: (identifier) @name)) @definition.field
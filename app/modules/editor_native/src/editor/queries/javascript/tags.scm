@@ -0,0 +1,33 @@
+; Document symbols for JavaScript (see rust/tags.scm for capture names)
+
+(class_declaration
+  name: (identifier) @name) @definition.class
+
+(method_definition
+  name: (property_identifier) @name
+  parameters: (formal_parameters) @detail) @definition.method
+
+(field_definition
+  property: (property_identifier) @name) @definition.field
+
+(function_declaration
+  name: (identifier) @name
+  parameters: (formal_parameters) @detail) @definition.function
+
+(generator_function_declaration
+  name: (identifier) @name
+  parameters: (formal_parameters) @detail) @definition.function
+
+(variable_declarator
+  name: (identifier) @name
+  value: [(arrow_function) (function_expression)]) @definition.function
+
+(program
+  [
+    (lexical_declaration
+      (variable_declarator
+        name: (identifier) @name) @definition.variable)
+    (variable_declaration
+      (variable_declarator
+        name: (identifier) @name) @definition.variable)
+  ])
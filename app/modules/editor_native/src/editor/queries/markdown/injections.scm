@@ -0,0 +1,10 @@
+; Language injections for Markdown (see html/injections.scm for captures)
+
+(fenced_code_block
+  (info_string
+    (language) @injection.language)
+  (code_fence_content) @injection.content)
+
+((html_block) @injection.content
+ (#set! injection.language "html")
+ (#set! injection.combined))
@@ -0,0 +1,7 @@
+; Language injections for Python (see html/injections.scm for captures)
+
+; SQL statements in string literals (only parsed when a SQL grammar is available)
+((string
+  (string_content) @injection.content)
+ (#match? @injection.content "^\\s*(?i:select|insert|update|delete|create|alter|drop|with)\\s")
+ (#set! injection.language "sql"))
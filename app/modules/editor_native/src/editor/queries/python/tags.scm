@@ -0,0 +1,13 @@
+; Document symbols for Python (see rust/tags.scm for capture names)
+
+(class_definition
+  name: (identifier) @name) @definition.class
+
+(function_definition
+  name: (identifier) @name
+  parameters: (parameters) @detail) @definition.function
+
+(module
+  (expression_statement
+    (assignment
+      left: (identifier) @name) @definition.variable))
@@ -0,0 +1,52 @@
+; Document symbols for Rust
+;
+; Captures:
+; - @definition.<kind>: Whole symbol node (kind maps to an LSP SymbolKind)
+; - @name: Symbol name (selection range)
+; - @detail: Optional extra text shown next to the name
+
+(mod_item
+  name: (identifier) @name) @definition.module
+
+(struct_item
+  name: (type_identifier) @name) @definition.struct
+
+(union_item
+  name: (type_identifier) @name) @definition.struct
+
+(enum_item
+  name: (type_identifier) @name) @definition.enum
+
+(enum_variant
+  name: (identifier) @name) @definition.enum_member
+
+(field_declaration
+  name: (field_identifier) @name
+  type: (_) @detail) @definition.field
+
+(trait_item
+  name: (type_identifier) @name) @definition.interface
+
+(impl_item
+  trait: (_)? @detail
+  type: (_) @name) @definition.impl
+
+(function_item
+  name: (identifier) @name
+  parameters: (parameters) @detail) @definition.function
+
+(function_signature_item
+  name: (identifier) @name
+  parameters: (parameters) @detail) @definition.function
+
+(type_item
+  name: (type_identifier) @name) @definition.type
+
+(const_item
+  name: (identifier) @name) @definition.constant
+
+(static_item
+  name: (identifier) @name) @definition.constant
+
+(macro_definition
+  name: (identifier) @name) @definition.macro
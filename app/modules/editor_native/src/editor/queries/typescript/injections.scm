@@ -0,0 +1,13 @@
+; Language injections for TypeScript (see html/injections.scm for captures)
+
+; html`<div>${x}</div>` and css`color: red;` tagged templates
+((call_expression
+  function: (identifier) @injection.language
+  arguments: (template_string) @injection.content)
+ (#match? @injection.language "^(html|css)$")
+ (#set! injection.include-children))
+
+; Regex literals (only parsed when a regex grammar is available)
+((regex
+  pattern: (regex_pattern) @injection.content)
+ (#set! injection.language "regex"))
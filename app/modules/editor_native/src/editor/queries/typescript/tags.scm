@@ -0,0 +1,73 @@
+; Document symbols for TypeScript (see rust/tags.scm for capture names)
+
+(class_declaration
+  name: (type_identifier) @name) @definition.class
+
+(abstract_class_declaration
+  name: (type_identifier) @name) @definition.class
+
+(interface_declaration
+  name: (type_identifier) @name) @definition.interface
+
+(enum_declaration
+  name: (identifier) @name) @definition.enum
+
+(enum_body
+  [
+    (property_identifier) @name @definition.enum_member
+    (enum_assignment
+      name: (property_identifier) @name) @definition.enum_member
+  ])
+
+(type_alias_declaration
+  name: (type_identifier) @name) @definition.type
+
+(module
+  name: (_) @name) @definition.module
+
+(internal_module
+  name: (_) @name) @definition.module
+
+(method_definition
+  name: (property_identifier) @name
+  parameters: (formal_parameters) @detail) @definition.method
+
+(method_signature
+  name: (property_identifier) @name
+  parameters: (formal_parameters) @detail) @definition.method
+
+(abstract_method_signature
+  name: (property_identifier) @name
+  parameters: (formal_parameters) @detail) @definition.method
+
+(public_field_definition
+  name: (property_identifier) @name) @definition.field
+
+(property_signature
+  name: (property_identifier) @name) @definition.field
+
+(function_declaration
+  name: (identifier) @name
+  parameters: (formal_parameters) @detail) @definition.function
+
+(function_signature
+  name: (identifier) @name
+  parameters: (formal_parameters) @detail) @definition.function
+
+(generator_function_declaration
+  name: (identifier) @name
+  parameters: (formal_parameters) @detail) @definition.function
+
+(variable_declarator
+  name: (identifier) @name
+  value: [(arrow_function) (function_expression)]) @definition.function
+
+(program
+  [
+    (lexical_declaration
+      (variable_declarator
+        name: (identifier) @name) @definition.variable)
+    (variable_declaration
+      (variable_declarator
+        name: (identifier) @name) @definition.variable)
+  ])
@@ -0,0 +1,317 @@
+//! Whitespace hygiene run when saving
+//!
+//! `Editor::prepare_save` runs the actions enabled in the save settings
+//! (EditorConfig, per-language overrides) as one undoable transaction;
+//! `Editor::run_save_actions` runs any set of them directly.
+//!
+//! Trailing whitespace that is content is kept: inside multiline string
+//! literals (checked with the syntax tree, injected code included) and
+//! Markdown hard line breaks (two or more spaces ending a paragraph line
+//! that is not the paragraph's last).
+
+use std::ops::Range;
+
+use anyhow::Result;
+use serde::{Deserialize, Serialize};
+use tree_sitter::{Node, Tree};
+
+use crate::editor::script::PARSE_TIMEOUT;
+use crate::editor::{Editor, LanguageId, LineEnding, Position};
+
+/// Action run on save.
+///
+/// Variants are listed (and run) in order: trimming comes first, while
+/// the syntax tree still matches the text.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum SaveAction {
+    /// Remove spaces and tabs at the end of lines
+    TrimTrailingWhitespace,
+    /// Convert indentation to the indent style
+    NormalizeIndentation,
+    /// Convert mixed line breaks (to `end_of_line`, or the most common one)
+    NormalizeLineEndings,
+    /// Remove blank lines after the last line break
+    TrimFinalNewlines,
+    /// Make sure the file ends with a line break
+    InsertFinalNewline,
+}
+
+impl SaveAction {
+    /// All actions, in run order
+    pub const ALL: [SaveAction; 5] = [
+        SaveAction::TrimTrailingWhitespace,
+        SaveAction::NormalizeIndentation,
+        SaveAction::NormalizeLineEndings,
+        SaveAction::TrimFinalNewlines,
+        SaveAction::InsertFinalNewline,
+    ];
+}
+
+impl Editor {
+    /// Runs save actions as one undoable transaction
+    ///
+    /// Actions run in `SaveAction` order, whatever order they are given in.
+    ///
+    /// Parameters:
+    /// - `actions`: Actions to run
+    ///
+    /// Returns: true if the buffer changed
+    pub fn run_save_actions(&mut self, actions: &[SaveAction]) -> Result<bool> {
+        self.ensure_writable()?;
+        if actions.contains(&SaveAction::TrimTrailingWhitespace) {
+            self.wait_for_parse(PARSE_TIMEOUT);
+        }
+
+        self.transaction(|editor| {
+            let mut changed = false;
+            for action in SaveAction::ALL.iter().filter(|action| actions.contains(action)) {
+                changed |= match action {
+                    SaveAction::TrimTrailingWhitespace => editor.trim_trailing_whitespace(),
+                    SaveAction::NormalizeIndentation => editor.normalize_indentation()?,
+                    SaveAction::NormalizeLineEndings => editor.normalize_line_endings(),
+                    SaveAction::TrimFinalNewlines => editor.trim_final_newlines(),
+                    SaveAction::InsertFinalNewline => editor.ensure_final_newline(),
+                };
+            }
+            Ok(changed)
+        })
+    }
+
+    /// Helper: Removes trailing spaces and tabs that are not content
+    ///
+    /// Returns: true if anything was removed
+    fn trim_trailing_whitespace(&mut self) -> bool {
+        // Decide with the current tree first, then edit bottom-up so
+        // earlier byte offsets stay valid
+        let ranges: Vec<Range<usize>> = (0..self.rope.len_lines())
+            .filter_map(|line_idx| {
+                let line_start = self.rope.line_to_byte(line_idx);
+                let line = self.rope.line(line_idx).to_string();
+                let content = line.trim_end_matches(['\n', '\r']);
+                let trimmed = content.trim_end_matches([' ', '\t']);
+                let range = line_start + trimmed.len()..line_start + content.len();
+                (!range.is_empty() && !self.keeps_trailing_whitespace(line_idx, &range)).then_some(range)
+            })
+            .collect();
+
+        for range in ranges.iter().rev() {
+            self.replace_bytes(range.start, range.end, "");
+        }
+        self.cursor = Position::clamp(&self.cursor, &self.rope);
+        !ranges.is_empty()
+    }
+
+    /// Helper: Checks if trailing whitespace is content
+    fn keeps_trailing_whitespace(&self, line_idx: usize, range: &Range<usize>) -> bool {
+        let layer_tree = self.injections.layer_at(range.start).map(|layer| &layer.tree);
+        let in_string = [self.syntax_tree.as_ref(), layer_tree]
+            .into_iter()
+            .flatten()
+            .any(|tree| in_multiline_string(tree, range));
+        if in_string {
+            return true;
+        }
+
+        self.language == LanguageId::Markdown
+            && self.rope.byte_slice(range.clone()).chars().filter(|c| *c == ' ').count() >= 2
+            && self.is_hard_break(line_idx, range.start)
+    }
+
+    /// Helper: Checks if a Markdown line continues its paragraph on the next line
+    fn is_hard_break(&self, line_idx: usize, content_end: usize) -> bool {
+        let Some(tree) = &self.syntax_tree else {
+            return false;
+        };
+        if line_idx + 1 >= self.rope.len_lines() || content_end == self.rope.line_to_byte(line_idx) {
+            return false;
+        }
+        let next = self.rope.line(line_idx + 1);
+        let indent = next.chars().take_while(|c| *c == ' ' || *c == '\t').count();
+        if next.chars().nth(indent).is_none_or(char::is_whitespace) {
+            return false;
+        }
+        let next_byte = self.rope.line_to_byte(line_idx + 1) + next.char_to_byte(indent);
+
+        let paragraph = |byte: usize| {
+            let mut node = tree.root_node().descendant_for_byte_range(byte, byte);
+            while let Some(n) = node {
+                if n.kind() == "paragraph" {
+                    return Some(n.id());
+                }
+                node = n.parent();
+            }
+            None
+        };
+        let current = paragraph(content_end - 1);
+        current.is_some() && current == paragraph(next_byte)
+    }
+
+    /// Helper: Converts all line breaks to `end_of_line` or the most common style
+    ///
+    /// Returns: true if anything changed
+    fn normalize_line_endings(&mut self) -> bool {
+        let content = self.content();
+        let line_ending = self.save_settings.end_of_line.unwrap_or_else(|| dominant_line_ending(&content));
+        let normalized = line_ending.normalize(&content);
+        self.replace_changed(&normalized)
+    }
+
+    /// Helper: Removes blank lines after the last line break
+    ///
+    /// Returns: true if anything was removed
+    fn trim_final_newlines(&mut self) -> bool {
+        let len = self.rope.len_chars();
+        let mut content_end = len;
+        while content_end > 0 && self.rope.char(content_end - 1).is_whitespace() {
+            content_end -= 1;
+        }
+
+        // Keep the first line break (and any whitespace before it)
+        let Some(offset) = self.rope.slice(content_end..).chars().position(|c| c == '\n' || c == '\r') else {
+            return false;
+        };
+        let mut keep_end = content_end + offset + 1;
+        if self.rope.char(keep_end - 1) == '\r' && keep_end < len && self.rope.char(keep_end) == '\n' {
+            keep_end += 1;
+        }
+        if keep_end == len {
+            return false;
+        }
+
+        let start = self.rope.char_to_byte(keep_end);
+        let end = self.rope.len_bytes();
+        self.replace_bytes(start, end, "");
+        self.cursor = Position::clamp(&self.cursor, &self.rope);
+        true
+    }
+
+    /// Helper: Appends a line break if the document does not end with one
+    ///
+    /// Returns: true if a line break was added
+    fn ensure_final_newline(&mut self) -> bool {
+        let len = self.rope.len_chars();
+        if len == 0 {
+            return false;
+        }
+
+        let last = self.rope.char(len - 1);
+        if last == '\n' || last == '\r' {
+            return false;
+        }
+        let end = self.rope.len_bytes();
+        self.replace_bytes(end, end, "\n");
+        true
+    }
+}
+
+/// Helper: Checks if a byte range lies in a string literal spanning lines
+fn in_multiline_string(tree: &Tree, range: &Range<usize>) -> bool {
+    let mut node: Option<Node> = tree.root_node().descendant_for_byte_range(range.start, range.end);
+    while let Some(n) = node {
+        let kind = n.kind();
+        if (kind.contains("string") || kind == "text_block") && n.start_position().row != n.end_position().row {
+            return true;
+        }
+        node = n.parent();
+    }
+    false
+}
+
+/// Helper: Finds the most common line break style (LF on ties)
+fn dominant_line_ending(text: &str) -> LineEnding {
+    let crlf = text.matches("\r\n").count();
+    let lf = text.matches('\n').count() - crlf;
+    let cr = text.matches('\r').count() - crlf;
+    if crlf > lf && crlf >= cr {
+        LineEnding::CrLf
+    } else if cr > lf && cr > crlf {
+        LineEnding::Cr
+    } else {
+        LineEnding::Lf
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::editor::{EditorConfigProperties, IndentConfig, SaveSettings};
+
+    #[test]
+    fn test_trim_keeps_multiline_strings() {
+        let content = "fn main() {   \n    let s = \"a  \n  b\";  \n}\t\n";
+        let mut editor = Editor::with_content(content, LanguageId::Rust).unwrap();
+
+        assert!(editor.run_save_actions(&[SaveAction::TrimTrailingWhitespace]).unwrap());
+        assert_eq!(editor.content(), "fn main() {\n    let s = \"a  \n  b\";\n}\n");
+        assert!(!editor.run_save_actions(&[SaveAction::TrimTrailingWhitespace]).unwrap());
+    }
+
+    #[test]
+    fn test_trim_keeps_markdown_hard_breaks() {
+        let content = "# Title  \n\nfirst line  \nsecond line  \n\n```python\nx = 1  \n```\n";
+        let mut editor = Editor::with_content(content, LanguageId::Markdown).unwrap();
+
+        editor.run_save_actions(&[SaveAction::TrimTrailingWhitespace]).unwrap();
+        assert_eq!(editor.content(), "# Title\n\nfirst line  \nsecond line\n\n```python\nx = 1\n```\n");
+    }
+
+    #[test]
+    fn test_final_newlines_and_line_endings() {
+        let mut editor = Editor::with_content("a\r\nb\nc\r\n  \r\n\n", LanguageId::PlainText).unwrap();
+
+        let actions = [SaveAction::InsertFinalNewline, SaveAction::TrimFinalNewlines, SaveAction::NormalizeLineEndings];
+        assert!(editor.run_save_actions(&actions).unwrap());
+        assert_eq!(editor.content(), "a\r\nb\r\nc\r\n");
+
+        let mut editor = Editor::with_content("a\n\n\n", LanguageId::PlainText).unwrap();
+        editor.run_save_actions(&[SaveAction::TrimFinalNewlines]).unwrap();
+        assert_eq!(editor.content(), "a\n");
+        editor.run_save_actions(&[SaveAction::TrimFinalNewlines]).unwrap();
+        assert_eq!(editor.content(), "a\n");
+    }
+
+    #[test]
+    fn test_prepare_save_uses_language_actions() {
+        let mut editor = Editor::with_content("def f():\n\tx = 1   \n\n\n", LanguageId::Python).unwrap();
+        editor.set_indent_config(IndentConfig { use_spaces: true, tab_size: 4, ..Default::default() });
+        editor.apply_editorconfig(&EditorConfigProperties {
+            trim_trailing_whitespace: Some(true),
+            ..Default::default()
+        });
+        let mut settings = editor.save_settings().clone();
+        settings.set_language_action(LanguageId::Python, SaveAction::NormalizeIndentation, Some(true));
+        settings.set_language_action(LanguageId::Python, SaveAction::TrimFinalNewlines, Some(true));
+        editor.set_save_settings(settings);
+
+        editor.prepare_save().unwrap();
+        assert_eq!(editor.content(), "def f():\n    x = 1\n");
+
+        // All actions are one undo step
+        editor.undo().unwrap();
+        assert_eq!(editor.content(), "def f():\n\tx = 1   \n\n\n");
+    }
+
+    #[test]
+    fn test_language_action_overrides() {
+        let mut settings = SaveSettings {
+            trim_trailing_whitespace: true,
+            insert_final_newline: true,
+            ..Default::default()
+        };
+        settings.set_language_action(LanguageId::Rust, SaveAction::TrimTrailingWhitespace, Some(false));
+        assert_eq!(
+            settings.actions(&LanguageId::Go),
+            [SaveAction::TrimTrailingWhitespace, SaveAction::InsertFinalNewline]
+        );
+        assert_eq!(settings.actions(&LanguageId::Rust), [SaveAction::InsertFinalNewline]);
+
+        let mut editor = Editor::with_content("fn f() {}  ", LanguageId::Rust).unwrap();
+        editor.set_save_settings(settings.clone());
+        editor.prepare_save().unwrap();
+        assert_eq!(editor.content(), "fn f() {}  \n");
+
+        settings.set_language_action(LanguageId::Rust, SaveAction::TrimTrailingWhitespace, None);
+        assert_eq!(settings.actions(&LanguageId::Rust), settings.actions(&LanguageId::Go));
+    }
+}
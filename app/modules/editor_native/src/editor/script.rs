@@ -0,0 +1,606 @@
+//! Editor command scripts
+//!
+//! Scripts drive an `Editor` without a UI (the `editor-cli` binary, tests,
+//! bug reproductions), so batch edits get exactly the same semantics as
+//! interactive ones. One command per line; `#` starts a comment line.
+//! Arguments are separated by spaces and may be quoted: `"..."` with
+//! `\n`, `\t`, `\"` and `\\` escapes, or `'...'` taken literally. Line and
+//! column numbers are 1-based, as shown in editors.
+//!
+//! ```text
+//! language rust                     # override the detected language
+//! indent spaces 4                   # or: indent tabs
+//! replace "old" "new" case word     # text replace (flags optional)
+//! normalize-indent                  # convert indentation to the indent style
+//! reindent [FIRST LAST]             # reindent from bracket nesting
+//! toggle-comment FIRST [LAST]
+//! goto LINE [COLUMN]
+//! select LINE COLUMN LINE COLUMN
+//! exec {"command": "insertText", "text": "x"}   # any macro command
+//! find '$a.unwrap()'                # structural search (template), reported
+//! query '(line_comment) @c'         # structural search (tree-sitter query)
+//! rewrite '$a.unwrap()' '$a?'       # structural replace
+//! undo
+//! redo
+//! ```
+
+use std::time::Duration;
+
+use anyhow::{anyhow, bail, Context, Result};
+use ropey::Rope;
+use tree_sitter::Tree;
+
+use crate::editor::auto_indent::normalize_indentation;
+use crate::editor::{
+    search_rope, Editor, EditorCommand, IndentConfig, LanguageId, LspRange, Position, SearchOptions, Selection,
+    StructuralPattern,
+};
+
+/// Longest wait for a background parse before a syntax-aware command
+pub(super) const PARSE_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Script command (see the module docs for the syntax).
+#[derive(Debug, Clone)]
+pub enum ScriptCommand {
+    /// Changes the document language
+    Language(LanguageId),
+
+    /// Sets the indent style used by the indentation commands
+    Indent(IndentConfig),
+
+    /// Replaces every occurrence of a text
+    Replace {
+        find: String,
+        replace: String,
+        case_sensitive: bool,
+        whole_word: bool,
+    },
+
+    /// Converts all indentation to the indent style
+    NormalizeIndent,
+
+    /// Reindents lines from bracket nesting (0-based, inclusive; None = all)
+    Reindent(Option<(usize, usize)>),
+
+    /// Toggles line comments (0-based, inclusive)
+    ToggleComment(usize, usize),
+
+    /// Moves the cursor (clears the selection)
+    Goto(Position),
+
+    /// Selects a range
+    Select(Selection),
+
+    /// Runs a macro command
+    Exec(EditorCommand),
+
+    /// Reports structural matches
+    Find(StructuralPattern),
+
+    /// Replaces structural matches (`$name` refers to captures)
+    Rewrite {
+        pattern: StructuralPattern,
+        replacement: String,
+    },
+
+    Undo,
+    Redo,
+}
+
+/// Parsed script.
+#[derive(Debug, Clone, Default)]
+pub struct Script {
+    /// Commands with their (1-based) script line
+    commands: Vec<(usize, ScriptCommand)>,
+}
+
+impl Script {
+    /// Parses a script
+    ///
+    /// Returns: Error naming the first invalid line
+    pub fn parse(source: &str) -> Result<Self> {
+        let mut commands = Vec::new();
+        for (index, line) in source.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+
+            let command = parse_command(line).with_context(|| format!("script line {}", index + 1))?;
+            commands.push((index + 1, command));
+        }
+
+        Ok(Self { commands })
+    }
+
+    /// Appends the commands of another script
+    pub fn extend(&mut self, other: Script) {
+        self.commands.extend(other.commands);
+    }
+
+    pub fn commands(&self) -> impl Iterator<Item = &ScriptCommand> {
+        self.commands.iter().map(|(_, command)| command)
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.commands.is_empty()
+    }
+}
+
+/// Match reported by `find`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ScriptMatch {
+    /// Script line of the `find` command
+    pub script_line: usize,
+
+    pub range: LspRange,
+
+    /// Matched text
+    pub text: String,
+}
+
+/// Helper: Parses one script line
+fn parse_command(line: &str) -> Result<ScriptCommand> {
+    let (name, rest) = line.split_once(char::is_whitespace).unwrap_or((line, ""));
+    if name == "exec" {
+        let command = serde_json::from_str(rest.trim()).context("invalid command JSON")?;
+        return Ok(ScriptCommand::Exec(command));
+    }
+
+    let args = split_arguments(rest)?;
+    let args: Vec<&str> = args.iter().map(String::as_str).collect();
+    let command = match (name, args.as_slice()) {
+        ("language", [language]) => {
+            let id = LanguageId::parse(language);
+            if id == LanguageId::PlainText && !matches!(*language, "plaintext" | "text") {
+                bail!("unknown language: {}", language);
+            }
+            ScriptCommand::Language(id)
+        }
+        ("indent", ["tabs"]) => ScriptCommand::Indent(IndentConfig::tabs()),
+        ("indent", ["spaces", size]) => ScriptCommand::Indent(IndentConfig::spaces(parse_number(size)?)),
+        ("replace", [find, replace, flags @ ..]) => {
+            if find.is_empty() {
+                bail!("replace needs a non-empty search text");
+            }
+            if let Some(flag) = flags.iter().find(|flag| !matches!(**flag, "case" | "word")) {
+                bail!("unknown replace flag: {}", flag);
+            }
+            ScriptCommand::Replace {
+                find: find.to_string(),
+                replace: replace.to_string(),
+                case_sensitive: flags.contains(&"case"),
+                whole_word: flags.contains(&"word"),
+            }
+        }
+        ("normalize-indent", []) => ScriptCommand::NormalizeIndent,
+        ("reindent", []) => ScriptCommand::Reindent(None),
+        ("reindent", [first, last]) => ScriptCommand::Reindent(Some((parse_line(first)?, parse_line(last)?))),
+        ("toggle-comment", [first]) => {
+            let line = parse_line(first)?;
+            ScriptCommand::ToggleComment(line, line)
+        }
+        ("toggle-comment", [first, last]) => ScriptCommand::ToggleComment(parse_line(first)?, parse_line(last)?),
+        ("goto", [line]) => ScriptCommand::Goto(Position::new(parse_line(line)?, 0)),
+        ("goto", [line, column]) => ScriptCommand::Goto(Position::new(parse_line(line)?, parse_line(column)?)),
+        ("select", [start_line, start_column, end_line, end_column]) => ScriptCommand::Select(Selection::new(
+            Position::new(parse_line(start_line)?, parse_line(start_column)?),
+            Position::new(parse_line(end_line)?, parse_line(end_column)?),
+        )),
+        ("find", [template]) => ScriptCommand::Find(StructuralPattern::Template(template.to_string())),
+        ("query", [source]) => ScriptCommand::Find(StructuralPattern::Query(source.to_string())),
+        ("rewrite", [template, replacement]) => ScriptCommand::Rewrite {
+            pattern: StructuralPattern::Template(template.to_string()),
+            replacement: replacement.to_string(),
+        },
+        ("undo", []) => ScriptCommand::Undo,
+        ("redo", []) => ScriptCommand::Redo,
+        (
+            "language" | "indent" | "replace" | "normalize-indent" | "reindent" | "toggle-comment" | "goto"
+            | "select" | "find" | "query" | "rewrite" | "undo" | "redo",
+            _,
+        ) => bail!("wrong arguments for {}", name),
+        _ => bail!("unknown command: {}", name),
+    };
+    Ok(command)
+}
+
+/// Helper: Splits arguments, honouring quotes
+fn split_arguments(text: &str) -> Result<Vec<String>> {
+    let mut args = Vec::new();
+    let mut chars = text.chars().peekable();
+    loop {
+        while chars.next_if(|c| c.is_whitespace()).is_some() {}
+        let Some(&first) = chars.peek() else {
+            return Ok(args);
+        };
+
+        let mut arg = String::new();
+        match first {
+            '\'' => {
+                chars.next();
+                arg.extend(chars.by_ref().take_while(|&c| c != '\''));
+            }
+            '"' => {
+                chars.next();
+                loop {
+                    match chars.next() {
+                        Some('"') => break,
+                        Some('\\') => arg.push(match chars.next() {
+                            Some('n') => '\n',
+                            Some('t') => '\t',
+                            Some(c @ ('"' | '\\')) => c,
+                            Some(c) => bail!("unknown escape: \\{}", c),
+                            None => bail!("unterminated string"),
+                        }),
+                        Some(c) => arg.push(c),
+                        None => bail!("unterminated string"),
+                    }
+                }
+            }
+            _ => {
+                while let Some(c) = chars.next_if(|c| !c.is_whitespace()) {
+                    arg.push(c);
+                }
+            }
+        }
+        args.push(arg);
+    }
+}
+
+/// Helper: Parses a number argument
+fn parse_number(text: &str) -> Result<usize> {
+    text.parse().map_err(|_| anyhow!("expected a number, got {:?}", text))
+}
+
+/// Helper: Parses a 1-based line or column to 0-based
+fn parse_line(text: &str) -> Result<usize> {
+    match parse_number(text)? {
+        0 => bail!("line and column numbers start at 1"),
+        number => Ok(number - 1),
+    }
+}
+
+/// Helper: Checks if the bracket at `byte` is code (not in a string or
+/// comment); without a tree every bracket counts
+fn is_bracket_token(tree: Option<&Tree>, byte: usize) -> bool {
+    let Some(tree) = tree else {
+        return true;
+    };
+    tree.root_node()
+        .descendant_for_byte_range(byte, byte + 1)
+        .is_some_and(|node| !node.is_named() && node.start_byte() == byte && node.end_byte() == byte + 1)
+}
+
+/// Helper: Checks if a line starts inside a multi-line string or comment
+fn starts_in_literal(tree: Option<&Tree>, line_start: usize, text_start: usize) -> bool {
+    let Some(tree) = tree else {
+        return false;
+    };
+    let mut node = tree.root_node().descendant_for_byte_range(text_start, text_start + 1);
+    while let Some(current) = node {
+        let kind = current.kind();
+        if current.start_byte() < line_start && (kind.contains("string") || kind.contains("comment")) {
+            return true;
+        }
+        node = current.parent();
+    }
+    false
+}
+
+/// Helper: Computes reindented text
+///
+/// Lines inside an unclosed bracket get one level more than the line of
+/// that bracket; lines starting with closing brackets go back to the
+/// level of the line that opened them.
+fn reindented(rope: &Rope, tree: Option<&Tree>, first: usize, last: usize, config: &IndentConfig) -> String {
+    let mut result = String::with_capacity(rope.len_bytes());
+    // Level of the line of each unclosed opening bracket
+    let mut open: Vec<usize> = Vec::new();
+
+    for (index, line) in rope.lines().enumerate() {
+        let text = line.to_string();
+        let line_start = rope.line_to_byte(index);
+        let body = text.trim_start_matches([' ', '\t']);
+        let body_start = line_start + text.len() - body.len();
+
+        let brackets: Vec<(usize, bool)> = body
+            .char_indices()
+            // Angle brackets are comparison operators too often to count
+            .filter(|(_, c)| matches!(c, '(' | ')' | '[' | ']' | '{' | '}'))
+            .filter(|(offset, _)| is_bracket_token(tree, body_start + offset))
+            .map(|(offset, c)| (offset, matches!(c, '(' | '[' | '{')))
+            .collect();
+        let leading_closers = body
+            .char_indices()
+            .take_while(|(offset, c)| c.is_whitespace() || brackets.contains(&(*offset, false)))
+            .filter(|(_, c)| !c.is_whitespace())
+            .count();
+
+        let level = if leading_closers > 0 {
+            open.len().checked_sub(leading_closers).map_or(0, |index| open[index])
+        } else {
+            open.last().map_or(0, |level| level + 1)
+        };
+
+        if !(first..=last).contains(&index) || starts_in_literal(tree, line_start, body_start) {
+            result.push_str(&text);
+        } else {
+            // Blank lines lose their whitespace
+            if !body.trim().is_empty() {
+                result.push_str(&config.indent_string().repeat(level));
+            }
+            result.push_str(body);
+        }
+
+        for (_, opening) in brackets {
+            if opening {
+                open.push(level);
+            } else {
+                open.pop();
+            }
+        }
+    }
+    result
+}
+
+impl Editor {
+    /// Runs a script
+    ///
+    /// Each command is its own undo step; the first failing command stops
+    /// the script (edits before it are kept).
+    ///
+    /// Returns: Matches reported by `find` / `query` commands
+    pub fn run_script(&mut self, script: &Script) -> Result<Vec<ScriptMatch>> {
+        let mut matches = Vec::new();
+        for (script_line, command) in &script.commands {
+            let found = self
+                .run_script_command(command)
+                .with_context(|| format!("script line {}", script_line))?;
+            matches.extend(found.into_iter().map(|(range, text)| ScriptMatch {
+                script_line: *script_line,
+                range,
+                text,
+            }));
+        }
+        Ok(matches)
+    }
+
+    /// Replaces every occurrence of a text (one undo step)
+    ///
+    /// Returns: Number of replacements
+    pub fn replace_all_text(&mut self, find: &str, replace: &str, options: &SearchOptions) -> Result<usize> {
+        self.ensure_writable()?;
+        let found = search_rope(&self.rope, find, options, None);
+        if found.is_empty() {
+            return Ok(0);
+        }
+
+        self.transaction(|editor| {
+            // Back to front so earlier offsets stay valid
+            for m in found.iter().rev() {
+                let start = m.start.to_byte_offset(&editor.rope);
+                let end = m.end.to_byte_offset(&editor.rope);
+                editor.replace_bytes(start, end, replace);
+            }
+            Ok(())
+        })?;
+        self.cursor = Position::clamp(&self.cursor, &self.rope);
+        self.selection = None;
+        Ok(found.len())
+    }
+
+    /// Converts all indentation to the editor's indent style (one undo step)
+    ///
+    /// Returns: true if anything changed
+    pub fn normalize_indentation(&mut self) -> Result<bool> {
+        self.ensure_writable()?;
+        let mut scratch = self.rope.clone();
+        normalize_indentation(&mut scratch, &self.indent_config);
+        Ok(self.replace_changed(&scratch.to_string()))
+    }
+
+    /// Reindents lines from bracket nesting (one undo step)
+    ///
+    /// Brackets in strings and comments are ignored when the language has
+    /// a grammar. Indentation-sensitive and prose languages are rejected.
+    ///
+    /// Parameters:
+    /// - `first_line`: First line to reindent
+    /// - `last_line`: Last line to reindent (inclusive)
+    ///
+    /// Returns: true if anything changed
+    pub fn reindent_lines(&mut self, first_line: usize, last_line: usize) -> Result<bool> {
+        self.ensure_writable()?;
+        if matches!(self.language, LanguageId::Python | LanguageId::Markdown | LanguageId::PlainText) {
+            bail!("reindent is not supported for {}", self.language.name());
+        }
+
+        self.wait_for_parse(PARSE_TIMEOUT);
+        let text = reindented(&self.rope, self.syntax_tree.as_ref(), first_line, last_line, &self.indent_config);
+        Ok(self.replace_changed(&text))
+    }
+
+    /// Helper: Runs one script command
+    ///
+    /// Returns: Reported matches (range and text)
+    fn run_script_command(&mut self, command: &ScriptCommand) -> Result<Vec<(LspRange, String)>> {
+        match command {
+            ScriptCommand::Language(language) => self.set_language(language.clone())?,
+            ScriptCommand::Indent(config) => self.set_indent_config(config.clone()),
+            ScriptCommand::Replace { find, replace, case_sensitive, whole_word } => {
+                let options = SearchOptions {
+                    case_sensitive: *case_sensitive,
+                    whole_word: *whole_word,
+                    ..SearchOptions::default()
+                };
+                self.replace_all_text(find, replace, &options)?;
+            }
+            ScriptCommand::NormalizeIndent => {
+                self.normalize_indentation()?;
+            }
+            ScriptCommand::Reindent(lines) => {
+                let (first, last) = lines.unwrap_or((0, usize::MAX));
+                self.reindent_lines(first, last)?;
+            }
+            ScriptCommand::ToggleComment(first, last) => {
+                if self.toggle_line_comments(*first, *last)?.is_none() {
+                    bail!("{} has no comments", self.language.name());
+                }
+            }
+            ScriptCommand::Goto(position) => {
+                self.move_cursor(*position);
+                self.clear_selection();
+            }
+            ScriptCommand::Select(selection) => {
+                let start = Position::clamp(&selection.start, &self.rope);
+                let end = Position::clamp(&selection.end, &self.rope);
+                self.set_selection(Selection::new(start, end));
+                self.move_cursor(end);
+            }
+            ScriptCommand::Exec(command) => {
+                self.execute_command(command)?;
+            }
+            ScriptCommand::Find(pattern) => {
+                let query = pattern.compile(&self.language)?;
+                self.wait_for_parse(PARSE_TIMEOUT);
+                return Ok(self
+                    .structural_search(&query)
+                    .into_iter()
+                    .map(|m| (m.range, m.text))
+                    .collect());
+            }
+            ScriptCommand::Rewrite { pattern, replacement } => {
+                let query = pattern.compile(&self.language)?;
+                self.wait_for_parse(PARSE_TIMEOUT);
+                self.structural_replace(&query, replacement)?;
+            }
+            ScriptCommand::Undo => {
+                self.undo()?;
+            }
+            ScriptCommand::Redo => {
+                self.redo()?;
+            }
+        }
+        Ok(Vec::new())
+    }
+
+    /// Helper: Replaces the document with `text` as one edit covering
+    /// only the changed middle (keeps the cursor where the text is equal)
+    ///
+    /// Returns: true if the text differed
+    pub(super) fn replace_changed(&mut self, text: &str) -> bool {
+        let old = self.content();
+        let mut prefix = old.bytes().zip(text.bytes()).take_while(|(a, b)| a == b).count();
+        while !old.is_char_boundary(prefix) || !text.is_char_boundary(prefix) {
+            prefix -= 1;
+        }
+        let max_suffix = old.len().min(text.len()) - prefix;
+        let mut suffix = old
+            .bytes()
+            .rev()
+            .zip(text.bytes().rev())
+            .take(max_suffix)
+            .take_while(|(a, b)| a == b)
+            .count();
+        while !old.is_char_boundary(old.len() - suffix) || !text.is_char_boundary(text.len() - suffix) {
+            suffix -= 1;
+        }
+
+        if prefix == old.len() && prefix == text.len() {
+            return false;
+        }
+        self.replace_bytes(prefix, old.len() - suffix, &text[prefix..text.len() - suffix]);
+        self.cursor = Position::clamp(&self.cursor, &self.rope);
+        self.selection = None;
+        true
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn run(content: &str, language: LanguageId, script: &str) -> (Editor, Vec<ScriptMatch>) {
+        let mut editor = Editor::with_content(content, language).unwrap();
+        let matches = editor.run_script(&Script::parse(script).unwrap()).unwrap();
+        (editor, matches)
+    }
+
+    #[test]
+    fn test_parse_arguments() {
+        assert_eq!(
+            split_arguments(r#"plain "a \"b\"\n" 'c\d' """#).unwrap(),
+            ["plain", "a \"b\"\n", "c\\d", ""]
+        );
+        assert!(split_arguments("\"open").is_err());
+
+        let error = Script::parse("# comment\n\ngoto 1\nfrobnicate\n").unwrap_err();
+        assert_eq!(format!("{:#}", error), "script line 4: unknown command: frobnicate");
+        assert!(Script::parse("goto 0").is_err());
+        assert!(Script::parse("replace a b sometimes").is_err());
+        assert!(Script::parse("language klingon").is_err());
+        assert!(Script::parse("exec {\"command\": \"indent\"}").is_ok());
+    }
+
+    #[test]
+    fn test_replace_and_undo() {
+        let (editor, _) = run("foo Foo food\n", LanguageId::PlainText, "replace foo bar case word");
+        assert_eq!(editor.content(), "bar Foo food\n");
+
+        let (editor, _) = run("foo Foo food\n", LanguageId::PlainText, "replace foo bar\nundo\nredo");
+        assert_eq!(editor.content(), "bar bar bard\n");
+    }
+
+    #[test]
+    fn test_reindent_ignores_strings_and_comments() {
+        let source = "fn main() {\nlet s = \"{\";\n        // {\n    v.iter().map(|x| {\n  x + 1\n        });\n\n}\n";
+        let (editor, _) = run(source, LanguageId::Rust, "reindent");
+        assert_eq!(
+            editor.content(),
+            "fn main() {\n    let s = \"{\";\n    // {\n    v.iter().map(|x| {\n        x + 1\n    });\n\n}\n"
+        );
+
+        let (editor, _) = run(source, LanguageId::Rust, "indent spaces 2\nreindent 2 2");
+        assert_eq!(editor.line(1).unwrap(), "  let s = \"{\";\n");
+        assert_eq!(editor.line(4).unwrap(), "  x + 1\n");
+
+        let mut editor = Editor::with_content("def f():\n  pass\n", LanguageId::Python).unwrap();
+        assert!(editor.run_script(&Script::parse("reindent").unwrap()).is_err());
+    }
+
+    #[test]
+    fn test_indentation_and_comments() {
+        let (editor, _) = run("a\n    b\n        c\n", LanguageId::Rust, "indent tabs\nnormalize-indent");
+        assert_eq!(editor.content(), "a\n\tb\n\t\tc\n");
+
+        let (editor, _) = run("let a = 1;\nlet b = 2;\n", LanguageId::Rust, "toggle-comment 1 2");
+        assert_eq!(editor.content(), "// let a = 1;\n// let b = 2;\n");
+    }
+
+    #[test]
+    fn test_exec_and_selection() {
+        let script = "goto 1 5\nexec {\"command\": \"insertText\", \"text\": \"big \"}\n\
+                      select 2 1 2 4\nexec {\"command\": \"deleteForward\"}";
+        let (editor, _) = run("the cat\nsat\n", LanguageId::PlainText, script);
+        assert_eq!(editor.content(), "the big cat\n\n");
+    }
+
+    #[test]
+    fn test_structural_find_and_rewrite() {
+        let source = "fn f() {\n    let a = x.unwrap();\n    y.unwrap()\n}\n";
+        let (editor, matches) = run(source, LanguageId::Rust, "find '$a.unwrap()'\nrewrite '$a.unwrap()' '$a?'");
+        assert_eq!(matches.len(), 2);
+        assert_eq!(matches[0].script_line, 1);
+        assert_eq!(matches[0].range.start.line, 1);
+        assert_eq!(matches[1].text, "y.unwrap()");
+        assert_eq!(editor.content(), "fn f() {\n    let a = x?;\n    y?\n}\n");
+
+        let (_, matches) = run(source, LanguageId::Rust, "query '(let_declaration) @match'");
+        assert_eq!(matches.len(), 1);
+
+        let mut editor = Editor::with_content(source, LanguageId::Rust).unwrap();
+        let error = editor.run_script(&Script::parse("goto 1\nquery '(oops'").unwrap()).unwrap_err();
+        assert!(format!("{:#}", error).starts_with("script line 2: "));
+    }
+}
@@ -1,5 +1,6 @@
 use ropey::Rope;
 use crate::editor::Position;
+use crate::editor::telemetry::{self, Operation};
 
 /// Search options for find/replace operations.
 #[derive(Debug, Clone, Default)]
@@ -38,6 +39,9 @@ pub struct SearchMatch {
 ///
 /// # Examples
 /// ```rust
+/// use editor_native::editor::{search_rope, SearchOptions};
+/// use ropey::Rope;
+///
 /// let rope = Rope::from_str("Hello World\nHello Rust");
 /// let options = SearchOptions::default();
 /// let matches = search_rope(&rope, "Hello", &options, None);
@@ -49,6 +53,7 @@ pub fn search_rope(
     options: &SearchOptions,
     start_pos: Option<Position>,
 ) -> Vec<SearchMatch> {
+    let _span = telemetry::span(Operation::Search);
     if query.is_empty() {
         return Vec::new();
     }
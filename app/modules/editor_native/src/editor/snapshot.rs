@@ -0,0 +1,254 @@
+//! Editor snapshots and edit journal (crash recovery)
+//!
+//! A snapshot captures everything needed to bring a buffer back after a
+//! crash: content, language, version, cursor/selection and undo history.
+//! Between snapshots, the edit journal records raw edits so only the
+//! changes need to be written.
+
+use anyhow::{bail, Result};
+use serde::{Deserialize, Serialize};
+
+use crate::editor::{Edit, Editor, LanguageId, Position, Selection, Transaction};
+
+/// Journaled edit text after which the journal gives up (a full snapshot
+/// is cheaper to write than the log)
+const MAX_JOURNAL_BYTES: usize = 4 * 1024 * 1024;
+
+/// Raw edits recorded since the journal was last taken.
+#[derive(Debug, Default)]
+pub(crate) struct EditJournal {
+    edits: Vec<Edit>,
+    bytes: usize,
+
+    /// The edits no longer describe the changes (content replaced, too big)
+    invalid: bool,
+}
+
+impl EditJournal {
+    /// Records an applied edit
+    pub(crate) fn record(&mut self, edit: Edit) {
+        if self.invalid {
+            return;
+        }
+
+        self.bytes += edit.deleted_text.len() + edit.inserted_text.len();
+        if self.bytes > MAX_JOURNAL_BYTES {
+            self.invalidate();
+        } else {
+            self.edits.push(edit);
+        }
+    }
+
+    /// Marks the journal unusable until it is restarted
+    pub(crate) fn invalidate(&mut self) {
+        self.invalid = true;
+        self.edits.clear();
+        self.bytes = 0;
+    }
+}
+
+/// Full state of an editor for recovery.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct EditorSnapshot {
+    pub content: String,
+
+    /// Language name (see `LanguageId::name`)
+    pub language: String,
+
+    pub version: u64,
+    pub cursor: Position,
+    pub selection: Option<Selection>,
+    pub undo_stack: Vec<Transaction>,
+    pub redo_stack: Vec<Transaction>,
+}
+
+/// Edits made since the previous snapshot or journal entry.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct JournalEntry {
+    /// Version after the edits
+    pub version: u64,
+    pub cursor: Position,
+    pub selection: Option<Selection>,
+
+    /// Raw edits in the order they were applied (undo/redo included)
+    pub edits: Vec<Edit>,
+}
+
+impl Editor {
+    /// Captures the editor state for recovery
+    pub fn snapshot(&self) -> EditorSnapshot {
+        EditorSnapshot {
+            content: self.content(),
+            language: self.language.name().to_string(),
+            version: self.version,
+            cursor: self.cursor,
+            selection: self.selection,
+            undo_stack: self.undo_stack.clone(),
+            redo_stack: self.redo_stack.clone(),
+        }
+    }
+
+    /// Recreates an editor from a snapshot
+    ///
+    /// The restored editor is dirty (its content was never saved).
+    pub fn from_snapshot(snapshot: EditorSnapshot) -> Result<Self> {
+        let mut editor = Self::with_content(&snapshot.content, LanguageId::parse(&snapshot.language))?;
+
+        editor.version = snapshot.version;
+        editor.cursor = Position::clamp(&snapshot.cursor, &editor.rope);
+        editor.selection = snapshot.selection.map(|s| {
+            Selection::new(Position::clamp(&s.start, &editor.rope), Position::clamp(&s.end, &editor.rope))
+        });
+        editor.undo_stack = snapshot.undo_stack;
+        editor.redo_stack = snapshot.redo_stack;
+        Ok(editor)
+    }
+
+    /// Starts (or restarts) recording raw edits for `take_edit_journal`
+    pub fn start_edit_journal(&mut self) {
+        self.edit_journal = Some(EditJournal::default());
+    }
+
+    /// Stops recording raw edits
+    pub fn stop_edit_journal(&mut self) {
+        self.edit_journal = None;
+    }
+
+    /// Takes the edits recorded since the last call and restarts the journal
+    ///
+    /// Returns: Journal entry, or None if the journal cannot describe the
+    /// changes (not started, content replaced, or too large), in which
+    /// case a full snapshot is needed
+    pub fn take_edit_journal(&mut self) -> Option<JournalEntry> {
+        let journal = self.edit_journal.replace(EditJournal::default())?;
+        if journal.invalid {
+            return None;
+        }
+
+        Some(JournalEntry {
+            version: self.version,
+            cursor: self.cursor,
+            selection: self.selection,
+            edits: journal.edits,
+        })
+    }
+
+    /// Re-applies a journal entry on top of a restored snapshot
+    ///
+    /// The entry's edits become one undo step. Each edit is checked against
+    /// the current content, so a log that does not belong to this snapshot
+    /// is rejected before anything changes.
+    pub fn replay_journal(&mut self, entry: &JournalEntry) -> Result<()> {
+        let mut scratch = self.rope.clone();
+        for edit in &entry.edits {
+            let end = edit.position + edit.deleted_text.len();
+            let is_boundary = |byte: usize| scratch.char_to_byte(scratch.byte_to_char(byte)) == byte;
+            let matches = end <= scratch.len_bytes()
+                && is_boundary(edit.position)
+                && is_boundary(end)
+                && scratch.byte_slice(edit.position..end) == edit.deleted_text.as_str();
+            if !matches {
+                bail!("journal edit at byte {} does not match the snapshot", edit.position);
+            }
+
+            let start_char = scratch.byte_to_char(edit.position);
+            scratch.remove(start_char..scratch.byte_to_char(end));
+            scratch.insert(start_char, &edit.inserted_text);
+        }
+
+        self.transaction(|editor| {
+            for edit in &entry.edits {
+                let end = edit.position + edit.deleted_text.len();
+                editor.replace_bytes(edit.position, end, &edit.inserted_text);
+            }
+            Ok(())
+        })?;
+
+        self.version = entry.version;
+        self.cursor = Position::clamp(&entry.cursor, &self.rope);
+        self.selection = entry.selection.map(|s| {
+            Selection::new(Position::clamp(&s.start, &self.rope), Position::clamp(&s.end, &self.rope))
+        });
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_snapshot_roundtrip() {
+        let mut editor = Editor::with_content("fn main() {}\n", LanguageId::Rust).unwrap();
+        editor.move_cursor(Position::new(0, 3));
+        editor.insert_text("x").unwrap();
+        editor.insert_text("y").unwrap();
+        editor.undo().unwrap();
+        editor.set_selection(Selection::new(Position::new(0, 0), Position::new(0, 2)));
+
+        let json = serde_json::to_string(&editor.snapshot()).unwrap();
+        let snapshot: EditorSnapshot = serde_json::from_str(&json).unwrap();
+        let mut restored = Editor::from_snapshot(snapshot).unwrap();
+
+        assert_eq!(restored.content(), editor.content());
+        assert_eq!(restored.version(), editor.version());
+        assert_eq!(*restored.language(), LanguageId::Rust);
+        assert_eq!(restored.cursor(), editor.cursor());
+        assert_eq!(restored.selection(), editor.selection());
+        assert!(restored.is_dirty());
+        assert!(restored.syntax_tree().is_some());
+
+        // Undo history survives
+        assert!(restored.redo().unwrap());
+        assert_eq!(restored.content(), "fn xymain() {}\n");
+        assert!(restored.undo().unwrap());
+        assert!(restored.undo().unwrap());
+        assert_eq!(restored.content(), "fn main() {}\n");
+    }
+
+    #[test]
+    fn test_edit_journal_replay() {
+        let mut editor = Editor::with_content("hello\n", LanguageId::PlainText).unwrap();
+        assert!(editor.take_edit_journal().is_none());
+
+        editor.start_edit_journal();
+        let snapshot = editor.snapshot();
+        editor.move_cursor(Position::new(0, 5));
+        editor.insert_text(" world").unwrap();
+        editor.insert_text("!").unwrap();
+        editor.undo().unwrap();
+
+        let entry = editor.take_edit_journal().unwrap();
+        assert_eq!(entry.edits.len(), 3);
+        assert_eq!(entry.version, editor.version());
+
+        let mut restored = Editor::from_snapshot(snapshot.clone()).unwrap();
+        restored.replay_journal(&entry).unwrap();
+        assert_eq!(restored.content(), "hello world\n");
+        assert_eq!(restored.cursor(), editor.cursor());
+        assert_eq!(restored.version(), editor.version());
+
+        // The replayed entry is one undo step
+        assert!(restored.undo().unwrap());
+        assert_eq!(restored.content(), "hello\n");
+
+        // A log that does not match the snapshot is rejected untouched
+        let mut other = Editor::with_content("bye\n", LanguageId::PlainText).unwrap();
+        assert!(other.replay_journal(&entry).is_err());
+        assert_eq!(other.content(), "bye\n");
+    }
+
+    #[test]
+    fn test_edit_journal_invalidated_by_set_content() {
+        let mut editor = Editor::with_content("a", LanguageId::PlainText).unwrap();
+        editor.start_edit_journal();
+        editor.set_content("b").unwrap();
+        assert!(editor.take_edit_journal().is_none());
+
+        // Restarted by the take
+        editor.insert_text("c").unwrap();
+        assert_eq!(editor.take_edit_journal().unwrap().edits.len(), 1);
+    }
+}
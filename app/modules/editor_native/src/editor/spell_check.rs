@@ -0,0 +1,1140 @@
+//! Spell checking of comments, strings and prose
+//!
+//! Only text meant for people is checked: comment and string nodes of the
+//! syntax tree in code, everything but code blocks (and inline code) in
+//! Markdown, and the whole document in plain text. Identifiers written in
+//! camelCase or snake_case are split into their words first.
+//!
+//! Words are looked up in Hunspell dictionaries (`.aff` + `.dic`, affixes
+//! expanded at load time) and in user and project word lists. Results are
+//! cached per line: an edit only rechecks the lines it touched, plus the
+//! lines whose syntax changed once the new tree is installed (a `/*`
+//! opening a comment, for example).
+
+use std::collections::{HashMap, HashSet};
+use std::fs::OpenOptions;
+use std::io::Write;
+use std::ops::Range;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, PoisonError, RwLock};
+
+use anyhow::{bail, Context, Result};
+use serde::Serialize;
+use tree_sitter::{Node, Tree};
+
+use crate::editor::{Editor, LanguageId, LspPosition, LspRange, Position};
+
+/// Suggestions returned per misspelled word
+const MAX_SUGGESTIONS: usize = 5;
+
+/// All-capital words up to this length are taken as acronyms (HTTP, JSON)
+const MAX_ACRONYM_LEN: usize = 5;
+
+/// Markdown nodes holding code (not prose)
+const MARKDOWN_CODE_NODES: &[&str] = &["fenced_code_block", "indented_code_block", "html_block"];
+
+/// How a dictionary writes affix flags (`FLAG` in the `.aff` file).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum FlagMode {
+    /// One character per flag (default)
+    Char,
+
+    /// Two characters per flag
+    Long,
+
+    /// Comma-separated numbers
+    Numeric,
+}
+
+impl FlagMode {
+    fn split(&self, flags: &str) -> Vec<String> {
+        match self {
+            FlagMode::Char => flags.chars().map(String::from).collect(),
+            FlagMode::Long => {
+                let chars: Vec<char> = flags.chars().collect();
+                chars.chunks(2).map(|pair| pair.iter().collect()).collect()
+            }
+            FlagMode::Numeric => flags.split(',').map(|flag| flag.trim().to_string()).collect(),
+        }
+    }
+}
+
+/// Character test of an affix condition.
+#[derive(Debug, Clone)]
+enum ConditionChar {
+    Any,
+    Literal(char),
+    OneOf(Vec<char>),
+    NoneOf(Vec<char>),
+}
+
+impl ConditionChar {
+    fn matches(&self, ch: char) -> bool {
+        match self {
+            ConditionChar::Any => true,
+            ConditionChar::Literal(c) => *c == ch,
+            ConditionChar::OneOf(set) => set.contains(&ch),
+            ConditionChar::NoneOf(set) => !set.contains(&ch),
+        }
+    }
+}
+
+/// Prefix or suffix rule.
+#[derive(Debug, Clone)]
+struct AffixRule {
+    flag: String,
+    is_prefix: bool,
+
+    /// Can combine with an affix of the other kind
+    cross_product: bool,
+
+    strip: String,
+    add: String,
+
+    /// Test of the stem's first (prefix) or last (suffix) characters
+    condition: Vec<ConditionChar>,
+}
+
+impl AffixRule {
+    /// Applies the rule to a stem (None if its condition does not match)
+    fn apply(&self, stem: &str) -> Option<String> {
+        let chars: Vec<char> = stem.chars().collect();
+        if chars.len() < self.condition.len() {
+            return None;
+        }
+
+        if self.is_prefix {
+            let matches = self.condition.iter().zip(&chars).all(|(c, &ch)| c.matches(ch));
+            let rest = stem.strip_prefix(self.strip.as_str())?;
+            (matches && !rest.is_empty()).then(|| format!("{}{}", self.add, rest))
+        } else {
+            let tail = &chars[chars.len() - self.condition.len()..];
+            let matches = self.condition.iter().zip(tail).all(|(c, &ch)| c.matches(ch));
+            let rest = stem.strip_suffix(self.strip.as_str())?;
+            (matches && !rest.is_empty()).then(|| format!("{}{}", rest, self.add))
+        }
+    }
+}
+
+/// Helper: Parses an affix condition (`.`, `[abc]`, `[^abc]`, literals)
+fn parse_condition(condition: &str) -> Vec<ConditionChar> {
+    let mut result = Vec::new();
+    let mut chars = condition.chars();
+    while let Some(ch) = chars.next() {
+        result.push(match ch {
+            '.' => ConditionChar::Any,
+            '[' => {
+                let mut set: Vec<char> = chars.by_ref().take_while(|&c| c != ']').collect();
+                if set.first() == Some(&'^') {
+                    set.remove(0);
+                    ConditionChar::NoneOf(set)
+                } else {
+                    ConditionChar::OneOf(set)
+                }
+            }
+            ch => ConditionChar::Literal(ch),
+        });
+    }
+    result
+}
+
+/// Helper: Decodes a dictionary file in its declared encoding
+fn decode(bytes: &[u8], encoding: &str) -> Result<String> {
+    match encoding.to_ascii_uppercase().as_str() {
+        "UTF-8" | "UTF8" => Ok(String::from_utf8_lossy(bytes).into_owned()),
+        "ISO8859-1" | "ISO-8859-1" | "LATIN1" => Ok(bytes.iter().map(|&b| b as char).collect()),
+        other => bail!("unsupported dictionary encoding: {}", other),
+    }
+}
+
+/// Case pattern of a word.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum WordCase {
+    Lower,
+    Title,
+    Upper,
+    Mixed,
+}
+
+impl WordCase {
+    fn of(word: &str) -> Self {
+        let mut chars = word.chars().filter(|c| c.is_alphabetic());
+        let Some(first) = chars.next() else {
+            return WordCase::Lower;
+        };
+        let rest: Vec<char> = chars.collect();
+        let rest_lower = rest.iter().all(|c| !c.is_uppercase());
+        let rest_upper = rest.iter().all(|c| !c.is_lowercase());
+
+        match (first.is_uppercase(), rest_lower, rest_upper) {
+            (false, true, _) => WordCase::Lower,
+            (true, _, true) if !rest.is_empty() => WordCase::Upper,
+            (true, true, _) => WordCase::Title,
+            _ => WordCase::Mixed,
+        }
+    }
+
+    /// Writes a lowercase word in this case
+    fn apply(&self, word: &str) -> String {
+        match self {
+            WordCase::Upper => word.to_uppercase(),
+            WordCase::Title => title_case(word),
+            _ => word.to_string(),
+        }
+    }
+}
+
+/// Helper: Uppercases the first character
+fn title_case(word: &str) -> String {
+    let mut chars = word.chars();
+    match chars.next() {
+        Some(first) => first.to_uppercase().chain(chars).collect(),
+        None => String::new(),
+    }
+}
+
+/// Helper: Looks a word up with the usual case rules
+///
+/// A capitalized or all-caps word matches its lowercase entry (sentence
+/// starts, headings), but a lowercase word does not match a capitalized
+/// entry (proper nouns).
+fn contains_word(words: &HashSet<String>, word: &str) -> bool {
+    if words.contains(word) {
+        return true;
+    }
+    match WordCase::of(word) {
+        WordCase::Title => words.contains(&word.to_lowercase()),
+        WordCase::Upper => {
+            let lower = word.to_lowercase();
+            words.contains(&lower) || words.contains(&title_case(&lower))
+        }
+        _ => false,
+    }
+}
+
+/// Hunspell dictionary with its affixes expanded.
+#[derive(Debug, Clone, Default)]
+pub struct Dictionary {
+    words: HashSet<String>,
+
+    /// Words marked FORBIDDENWORD (reported even if another form matches)
+    forbidden: HashSet<String>,
+
+    /// Words marked NOSUGGEST (accepted, never suggested)
+    no_suggest: HashSet<String>,
+
+    /// Characters tried when building suggestions (`TRY`)
+    try_chars: Vec<char>,
+
+    /// Common misspellings (`REP`), tried first for suggestions
+    replacements: Vec<(String, String)>,
+}
+
+impl Dictionary {
+    /// Loads a dictionary from its `.aff` and `.dic` files
+    pub fn load(aff_path: &Path, dic_path: &Path) -> Result<Self> {
+        let aff = std::fs::read(aff_path).with_context(|| format!("failed to read {}", aff_path.display()))?;
+        let dic = std::fs::read(dic_path).with_context(|| format!("failed to read {}", dic_path.display()))?;
+
+        // The encoding is declared by `SET` in the (ASCII) header of the .aff
+        let encoding = String::from_utf8_lossy(&aff)
+            .lines()
+            .find_map(|line| line.trim().strip_prefix("SET ").map(|e| e.trim().to_string()))
+            .unwrap_or_else(|| "ISO8859-1".to_string());
+
+        Self::parse(&decode(&aff, &encoding)?, &decode(&dic, &encoding)?)
+    }
+
+    /// Parses dictionary sources (see `load`)
+    ///
+    /// Supported `.aff` directives: SET, FLAG, TRY, REP, PFX, SFX,
+    /// NEEDAFFIX, NOSUGGEST, FORBIDDENWORD. Others (compounding, ...) are
+    /// ignored.
+    pub fn parse(aff: &str, dic: &str) -> Result<Self> {
+        let mut dictionary = Self::default();
+        let mut flag_mode = FlagMode::Char;
+        let mut rules: Vec<AffixRule> = Vec::new();
+        let mut cross_products: Vec<(String, bool, bool)> = Vec::new();
+        let (mut need_affix, mut no_suggest, mut forbidden) = (None, None, None);
+
+        for line in aff.lines() {
+            let fields: Vec<&str> = line.split_whitespace().collect();
+            match fields.as_slice() {
+                ["FLAG", mode, ..] => {
+                    flag_mode = match *mode {
+                        "long" => FlagMode::Long,
+                        "num" => FlagMode::Numeric,
+                        _ => FlagMode::Char,
+                    }
+                }
+                ["TRY", chars, ..] => dictionary.try_chars = chars.chars().collect(),
+                ["REP", from, to, ..] => {
+                    dictionary.replacements.push((from.replace('_', " "), to.replace('_', " ")));
+                }
+                ["NEEDAFFIX", flag, ..] => need_affix = Some(flag.to_string()),
+                ["NOSUGGEST", flag, ..] => no_suggest = Some(flag.to_string()),
+                ["FORBIDDENWORD", flag, ..] => forbidden = Some(flag.to_string()),
+                [kind @ ("PFX" | "SFX"), flag, cross, count] if count.parse::<usize>().is_ok() => {
+                    cross_products.push((flag.to_string(), *kind == "PFX", *cross == "Y"));
+                }
+                [kind @ ("PFX" | "SFX"), flag, strip, add, rest @ ..] => {
+                    let is_prefix = *kind == "PFX";
+                    let cross_product = cross_products
+                        .iter()
+                        .any(|(f, prefix, cross)| f == flag && *prefix == is_prefix && *cross);
+                    let zero = |s: &str| if s == "0" { String::new() } else { s.to_string() };
+                    // Continuation flags (`add/flags`) are not supported
+                    let add = add.split('/').next().unwrap_or_default();
+                    rules.push(AffixRule {
+                        flag: flag.to_string(),
+                        is_prefix,
+                        cross_product,
+                        strip: zero(strip),
+                        add: zero(add),
+                        condition: parse_condition(rest.first().copied().unwrap_or(".")),
+                    });
+                }
+                _ => {}
+            }
+        }
+
+        let mut lines = dic.lines();
+        if lines.next().and_then(|count| count.trim().parse::<usize>().ok()).is_none() {
+            bail!("invalid .dic file: the first line must be the word count");
+        }
+
+        for line in lines {
+            // Morphological fields follow a tab or a space
+            let Some(entry) = line.split(['\t', ' ']).next().filter(|e| !e.is_empty()) else {
+                continue;
+            };
+            let (stem, flags) = match entry.split_once('/') {
+                Some((stem, flags)) => (stem, flag_mode.split(flags)),
+                None => (entry, Vec::new()),
+            };
+            let has = |flag: &Option<String>| flag.as_ref().is_some_and(|f| flags.contains(f));
+
+            if has(&forbidden) {
+                dictionary.forbidden.insert(stem.to_string());
+                continue;
+            }
+            let mut forms = Vec::new();
+            if !has(&need_affix) {
+                forms.push(stem.to_string());
+            }
+
+            let applicable: Vec<&AffixRule> = rules.iter().filter(|r| flags.contains(&r.flag)).collect();
+            for suffix in applicable.iter().filter(|r| !r.is_prefix) {
+                let Some(suffixed) = suffix.apply(stem) else {
+                    continue;
+                };
+                if suffix.cross_product {
+                    for prefix in applicable.iter().filter(|r| r.is_prefix && r.cross_product) {
+                        forms.extend(prefix.apply(&suffixed));
+                    }
+                }
+                forms.push(suffixed);
+            }
+            for prefix in applicable.iter().filter(|r| r.is_prefix) {
+                forms.extend(prefix.apply(stem));
+            }
+
+            if has(&no_suggest) {
+                dictionary.no_suggest.extend(forms.iter().cloned());
+            }
+            dictionary.words.extend(forms);
+        }
+
+        Ok(dictionary)
+    }
+
+    /// Checks if the dictionary knows a word (case rules included)
+    pub fn contains(&self, word: &str) -> bool {
+        !self.forbidden.contains(word) && contains_word(&self.words, word)
+    }
+
+    /// Gets the number of word forms (affixes expanded)
+    pub fn len(&self) -> usize {
+        self.words.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.words.is_empty()
+    }
+}
+
+/// Extra accepted words.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum WordListKind {
+    /// Personal words (all projects)
+    User,
+
+    /// Words of the current project (usually committed with it)
+    Project,
+}
+
+/// Word list kept in a file, one word per line.
+#[derive(Debug, Clone, Default)]
+struct WordList {
+    path: Option<PathBuf>,
+    words: HashSet<String>,
+}
+
+impl WordList {
+    /// Loads a list (a missing file is an empty list, created on the first add)
+    fn load(path: &Path) -> Result<Self> {
+        let words = match std::fs::read_to_string(path) {
+            Ok(text) => text
+                .lines()
+                .map(str::trim)
+                .filter(|line| !line.is_empty() && !line.starts_with('#'))
+                .map(String::from)
+                .collect(),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => HashSet::new(),
+            Err(e) => return Err(e).with_context(|| format!("failed to read {}", path.display())),
+        };
+
+        Ok(Self {
+            path: Some(path.to_path_buf()),
+            words,
+        })
+    }
+
+    /// Adds a word (appended to the file, if the list has one)
+    fn add(&mut self, word: &str) -> Result<()> {
+        if !self.words.insert(word.to_string()) {
+            return Ok(());
+        }
+
+        if let Some(path) = &self.path {
+            let mut file = OpenOptions::new()
+                .create(true)
+                .append(true)
+                .open(path)
+                .with_context(|| format!("failed to open {}", path.display()))?;
+            writeln!(file, "{}", word).with_context(|| format!("failed to write {}", path.display()))?;
+        }
+        Ok(())
+    }
+}
+
+/// Dictionaries and word lists shared by editors.
+#[derive(Debug, Default)]
+pub struct SpellChecker {
+    dictionaries: Vec<Dictionary>,
+    user_words: WordList,
+    project_words: WordList,
+
+    /// Bumped on every change, so editors know their cached results are stale
+    generation: u64,
+}
+
+/// Spell checker shared between editors
+pub type SharedSpellChecker = Arc<RwLock<SpellChecker>>;
+
+impl SpellChecker {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Wraps the checker for `Editor::set_spell_checker`
+    pub fn shared(self) -> SharedSpellChecker {
+        Arc::new(RwLock::new(self))
+    }
+
+    /// Adds a dictionary (words of every dictionary are accepted)
+    pub fn add_dictionary(&mut self, dictionary: Dictionary) {
+        self.dictionaries.push(dictionary);
+        self.generation += 1;
+    }
+
+    /// Loads a Hunspell dictionary from disk
+    ///
+    /// Parameters:
+    /// - `aff_path`: Affix file (e.g. `en_US.aff`)
+    /// - `dic_path`: Word file (e.g. `en_US.dic`)
+    pub fn load_dictionary(&mut self, aff_path: &Path, dic_path: &Path) -> Result<()> {
+        self.add_dictionary(Dictionary::load(aff_path, dic_path)?);
+        Ok(())
+    }
+
+    /// Loads a word list (replacing the current one of that kind)
+    ///
+    /// Words added later are appended to the file.
+    pub fn load_word_list(&mut self, kind: WordListKind, path: &Path) -> Result<()> {
+        *self.word_list_mut(kind) = WordList::load(path)?;
+        self.generation += 1;
+        Ok(())
+    }
+
+    /// Accepts a word from now on (saved to the list's file, if any)
+    pub fn add_word(&mut self, kind: WordListKind, word: &str) -> Result<()> {
+        let word = word.trim();
+        if word.is_empty() || word.contains(char::is_whitespace) {
+            bail!("invalid word: {:?}", word);
+        }
+
+        self.word_list_mut(kind).add(word)?;
+        self.generation += 1;
+        Ok(())
+    }
+
+    /// Checks if any dictionary is loaded (nothing is reported without one)
+    pub fn has_dictionary(&self) -> bool {
+        !self.dictionaries.is_empty()
+    }
+
+    /// Gets the change counter
+    pub fn generation(&self) -> u64 {
+        self.generation
+    }
+
+    /// Checks a single word
+    pub fn check(&self, word: &str) -> bool {
+        if !self.has_dictionary() {
+            return true;
+        }
+        if self.dictionaries.iter().any(|d| d.forbidden.contains(word)) {
+            return false;
+        }
+
+        contains_word(&self.user_words.words, word)
+            || contains_word(&self.project_words.words, word)
+            || self.dictionaries.iter().any(|d| d.contains(word))
+    }
+
+    /// Suggests corrections for a misspelled word
+    ///
+    /// Candidates are the dictionary's common misspellings (`REP`), then
+    /// words one edit away (using the `TRY` characters), then two known
+    /// words run together. The word's case is kept.
+    ///
+    /// Returns: Up to 5 suggestions, best first
+    pub fn suggest(&self, word: &str) -> Vec<String> {
+        let case = WordCase::of(word);
+        let lower = if case == WordCase::Mixed { word.to_string() } else { word.to_lowercase() };
+
+        let mut candidates: Vec<String> = Vec::new();
+        for dictionary in &self.dictionaries {
+            for (from, to) in &dictionary.replacements {
+                for (index, _) in lower.match_indices(from.as_str()) {
+                    candidates.push(format!("{}{}{}", &lower[..index], to, &lower[index + from.len()..]));
+                }
+            }
+        }
+        candidates.extend(self.edits(&lower));
+
+        let chars: Vec<char> = lower.chars().collect();
+        for split in 1..chars.len() {
+            let (left, right): (String, String) = (chars[..split].iter().collect(), chars[split..].iter().collect());
+            if self.check(&left) && self.check(&right) {
+                candidates.push(format!("{} {}", left, right));
+            }
+        }
+
+        let mut seen = HashSet::new();
+        candidates
+            .into_iter()
+            .filter(|candidate| *candidate != lower)
+            .filter(|candidate| candidate.split(' ').all(|part| self.check(part)))
+            .filter(|candidate| !self.dictionaries.iter().any(|d| d.no_suggest.contains(candidate)))
+            .filter(|candidate| seen.insert(candidate.clone()))
+            .map(|candidate| case.apply(&candidate))
+            .take(MAX_SUGGESTIONS)
+            .collect()
+    }
+
+    /// Helper: Words one edit away (delete, transpose, replace, insert)
+    fn edits(&self, word: &str) -> Vec<String> {
+        let mut alphabet: Vec<char> = self.dictionaries.iter().flat_map(|d| d.try_chars.iter().copied()).collect();
+        if alphabet.is_empty() {
+            alphabet = ('a'..='z').collect();
+        }
+
+        let chars: Vec<char> = word.chars().collect();
+        let join = |chars: &[char]| chars.iter().collect::<String>();
+        let mut edits = Vec::new();
+        for i in 0..chars.len() {
+            let mut swapped = chars.clone();
+            if i + 1 < chars.len() {
+                swapped.swap(i, i + 1);
+                edits.push(join(&swapped));
+            }
+
+            let mut deleted = chars.clone();
+            deleted.remove(i);
+            edits.push(join(&deleted));
+        }
+        for i in 0..=chars.len() {
+            for &ch in &alphabet {
+                if i < chars.len() {
+                    let mut replaced = chars.clone();
+                    replaced[i] = ch;
+                    edits.push(join(&replaced));
+                }
+
+                let mut inserted = chars.clone();
+                inserted.insert(i, ch);
+                edits.push(join(&inserted));
+            }
+        }
+        edits
+    }
+
+    fn word_list_mut(&mut self, kind: WordListKind) -> &mut WordList {
+        match kind {
+            WordListKind::User => &mut self.user_words,
+            WordListKind::Project => &mut self.project_words,
+        }
+    }
+}
+
+/// Misspelled word, reported like an LSP diagnostic.
+///
+/// Suggestions are not included (they are slow to build); get them for
+/// the word under the cursor with `Editor::spell_suggestions_at`.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SpellDiagnostic {
+    pub range: LspRange,
+
+    /// LSP severity (3 = information)
+    pub severity: u8,
+
+    /// Always "spell"
+    pub source: &'static str,
+
+    pub message: String,
+    pub word: String,
+}
+
+/// Misspelled word of a line (char columns).
+#[derive(Debug, Clone)]
+struct SpellIssue {
+    columns: Range<usize>,
+    word: String,
+}
+
+/// Per-editor spell check results.
+#[derive(Debug)]
+pub(crate) struct SpellState {
+    checker: SharedSpellChecker,
+
+    /// Checker generation and language the cached lines were checked with
+    generation: u64,
+    language: LanguageId,
+
+    /// Issues per line (None = not checked since it changed)
+    lines: Vec<Option<Vec<SpellIssue>>>,
+
+    /// Suggestions built so far, by word
+    suggestions: HashMap<String, Vec<String>>,
+}
+
+impl SpellState {
+    /// Marks the lines of an edit for rechecking
+    pub(crate) fn edit(&mut self, start_line: usize, old_end_line: usize, new_end_line: usize) {
+        let end = (old_end_line + 1).min(self.lines.len());
+        let start = start_line.min(end);
+        self.lines
+            .splice(start..end, std::iter::repeat_n(None, new_end_line + 1 - start_line));
+    }
+
+    /// Marks every line for rechecking
+    pub(crate) fn invalidate(&mut self, line_count: usize) {
+        self.lines = vec![None; line_count];
+    }
+
+    /// Marks the lines whose syntax changed between two trees
+    pub(crate) fn syntax_changed(&mut self, old: Option<&Tree>, new: &Tree) {
+        let Some(old) = old else {
+            self.lines.fill(None);
+            return;
+        };
+
+        for range in old.changed_ranges(new) {
+            let end = (range.end_point.row + 1).min(self.lines.len());
+            for line in range.start_point.row.min(end)..end {
+                self.lines[line] = None;
+            }
+        }
+    }
+}
+
+/// Helper: Checks if a node holds comment or string text
+fn is_checked_node(node: &Node, language: &LanguageId) -> bool {
+    let kind = node.kind();
+    node.is_named()
+        && (kind.contains("comment")
+            || (kind.contains("string") && !kind.contains("escape"))
+            || (*language == LanguageId::Html && kind == "text"))
+}
+
+/// Helper: Collects the byte ranges of `range` that are checked
+fn checked_ranges(node: Node, language: &LanguageId, range: &Range<usize>, out: &mut Vec<Range<usize>>) {
+    if node.end_byte() <= range.start || node.start_byte() >= range.end {
+        return;
+    }
+
+    if is_checked_node(&node, language) {
+        // Code interpolated into a string is not text
+        let mut start = node.start_byte().max(range.start);
+        let mut cursor = node.walk();
+        for child in node.named_children(&mut cursor) {
+            let kind = child.kind();
+            if kind.contains("substitution") || kind.contains("interpolation") {
+                out.push(start..child.start_byte().clamp(start, range.end));
+                start = child.end_byte().max(start);
+            }
+        }
+        out.push(start.min(range.end)..node.end_byte().min(range.end));
+        return;
+    }
+
+    let mut cursor = node.walk();
+    for child in node.children(&mut cursor) {
+        checked_ranges(child, language, range, out);
+    }
+}
+
+/// Helper: Collects Markdown code block ranges overlapping `range`
+fn markdown_code_ranges(node: Node, range: &Range<usize>, out: &mut Vec<Range<usize>>) {
+    if node.end_byte() <= range.start || node.start_byte() >= range.end {
+        return;
+    }
+    if MARKDOWN_CODE_NODES.contains(&node.kind()) {
+        out.push(node.start_byte()..node.end_byte());
+        return;
+    }
+
+    let mut cursor = node.walk();
+    for child in node.children(&mut cursor) {
+        markdown_code_ranges(child, range, out);
+    }
+}
+
+/// Splits text into the words to check
+///
+/// URLs, e-mail addresses, escape sequences and words containing digits
+/// are skipped; identifiers are split at `_` and camelCase humps, and
+/// short all-capital words (acronyms) are left out.
+///
+/// Returns: Byte ranges of the words in `text`
+pub fn split_words(text: &str) -> Vec<Range<usize>> {
+    let mut words = Vec::new();
+    let mut offset = 0;
+    for chunk in text.split_inclusive(char::is_whitespace) {
+        let chunk_start = offset;
+        offset += chunk.len();
+        if chunk.contains("://") || chunk.starts_with("www.") || chunk.contains('@') {
+            continue;
+        }
+
+        // Tokens: letters, digits, underscores and inner apostrophes
+        let mut token_start: Option<usize> = None;
+        let mut escaped = false;
+        for (i, ch) in chunk.char_indices().chain(std::iter::once((chunk.len(), ' '))) {
+            let in_token = !escaped && (ch.is_alphanumeric() || ch == '_' || ch == '\'' || ch == '\u{2019}');
+            escaped = !escaped && ch == '\\';
+            match (in_token, token_start) {
+                (true, None) => token_start = Some(i),
+                (false, Some(start)) => {
+                    token_start = None;
+                    split_token(&chunk[start..i], chunk_start + start, &mut words);
+                }
+                _ => {}
+            }
+        }
+    }
+    words
+}
+
+/// Helper: Splits an identifier-like token into words
+fn split_token(token: &str, offset: usize, words: &mut Vec<Range<usize>>) {
+    if token.chars().any(|c| c.is_ascii_digit()) {
+        return;
+    }
+
+    let mut part_start = 0;
+    for part in token.split('_') {
+        let base = offset + part_start;
+        part_start += part.len() + 1;
+
+        // camelCase and HTTPServer humps
+        let chars: Vec<(usize, char)> = part.char_indices().collect();
+        let mut start = 0;
+        for i in 1..chars.len() {
+            let (index, ch) = chars[i];
+            let prev = chars[i - 1].1;
+            let next_lower = chars.get(i + 1).is_some_and(|(_, c)| c.is_lowercase());
+            if ch.is_uppercase() && (prev.is_lowercase() || (prev.is_uppercase() && next_lower)) {
+                push_word(&part[start..index], base + start, words);
+                start = index;
+            }
+        }
+        push_word(&part[start..], base + start, words);
+    }
+}
+
+/// Helper: Keeps a word worth checking (trimmed of apostrophes)
+fn push_word(word: &str, offset: usize, words: &mut Vec<Range<usize>>) {
+    let quotes: &[char] = &['\'', '\u{2019}'];
+    let trimmed = word.trim_start_matches(quotes);
+    let start = offset + word.len() - trimmed.len();
+    let trimmed = trimmed.trim_end_matches(quotes);
+
+    let len = trimmed.chars().count();
+    let acronym = WordCase::of(trimmed) == WordCase::Upper && len <= MAX_ACRONYM_LEN;
+    if len >= 2 && !acronym {
+        words.push(start..start + trimmed.len());
+    }
+}
+
+/// Helper: Removes inline code spans (`code`) from a Markdown line
+fn without_inline_code(ranges: Vec<Range<usize>>, line: &str, line_start: usize) -> Vec<Range<usize>> {
+    let ticks: Vec<usize> = line.match_indices('`').map(|(i, _)| line_start + i).collect();
+    let spans: Vec<Range<usize>> = ticks.chunks_exact(2).map(|pair| pair[0]..pair[1] + 1).collect();
+    subtract(ranges, &spans)
+}
+
+/// Helper: Removes `holes` from sorted `ranges`
+fn subtract(ranges: Vec<Range<usize>>, holes: &[Range<usize>]) -> Vec<Range<usize>> {
+    let mut result = Vec::new();
+    for range in ranges {
+        let mut start = range.start;
+        for hole in holes.iter().filter(|h| h.end > range.start && h.start < range.end) {
+            if hole.start > start {
+                result.push(start..hole.start);
+            }
+            start = start.max(hole.end);
+        }
+        if start < range.end {
+            result.push(start..range.end);
+        }
+    }
+    result
+}
+
+impl Editor {
+    /// Turns spell checking on (with a shared checker) or off
+    pub fn set_spell_checker(&mut self, checker: Option<SharedSpellChecker>) {
+        self.spell = checker.map(|checker| SpellState {
+            checker,
+            generation: 0,
+            language: self.language.clone(),
+            lines: vec![None; self.rope.len_lines()],
+            suggestions: HashMap::new(),
+        });
+    }
+
+    /// Gets the misspelled words of the document
+    ///
+    /// Lines unchanged since the last call are served from the cache.
+    ///
+    /// Returns: Diagnostics in document order (empty when spell checking
+    /// is off or no dictionary is loaded)
+    pub fn spell_diagnostics(&mut self) -> Vec<SpellDiagnostic> {
+        let line_count = self.rope.len_lines();
+        self.spell_diagnostics_in(0, line_count.saturating_sub(1))
+    }
+
+    /// Gets the misspelled words of a range of lines (e.g. the viewport)
+    ///
+    /// Parameters:
+    /// - `first_line`: First line to check
+    /// - `last_line`: Last line to check (inclusive)
+    pub fn spell_diagnostics_in(&mut self, first_line: usize, last_line: usize) -> Vec<SpellDiagnostic> {
+        let Some(mut spell) = self.spell.take() else {
+            return Vec::new();
+        };
+
+        let checker = spell.checker.clone();
+        let checker = checker.read().unwrap_or_else(PoisonError::into_inner);
+        self.sync_spell_state(&mut spell, &checker);
+
+        let last_line = last_line.min(spell.lines.len().saturating_sub(1));
+        let mut diagnostics = Vec::new();
+        for line in first_line..=last_line {
+            if spell.lines[line].is_none() {
+                spell.lines[line] = Some(self.check_line(&checker, line));
+            }
+
+            for issue in spell.lines[line].iter().flatten() {
+                diagnostics.push(SpellDiagnostic {
+                    range: LspRange {
+                        start: LspPosition { line, character: issue.columns.start },
+                        end: LspPosition { line, character: issue.columns.end },
+                    },
+                    severity: 3,
+                    source: "spell",
+                    message: format!("Unknown word: {}", issue.word),
+                    word: issue.word.clone(),
+                });
+            }
+        }
+
+        drop(checker);
+        self.spell = Some(spell);
+        diagnostics
+    }
+
+    /// Gets corrections for the misspelled word at a position (for hovers
+    /// and quick fixes)
+    ///
+    /// Suggestions are built on first request and kept per word until the
+    /// word lists change.
+    ///
+    /// Returns: Up to 5 suggestions, best first (empty if the word at the
+    /// position is spelled correctly or spell checking is off)
+    pub fn spell_suggestions_at(&mut self, position: Position) -> Vec<String> {
+        let Some(mut spell) = self.spell.take() else {
+            return Vec::new();
+        };
+
+        let checker = spell.checker.clone();
+        let checker = checker.read().unwrap_or_else(PoisonError::into_inner);
+        self.sync_spell_state(&mut spell, &checker);
+
+        let mut suggestions = Vec::new();
+        if let Some(slot) = spell.lines.get_mut(position.line) {
+            let issues = slot.get_or_insert_with(|| self.check_line(&checker, position.line));
+            let issue = issues
+                .iter()
+                .find(|issue| issue.columns.start <= position.column && position.column <= issue.columns.end);
+            if let Some(issue) = issue {
+                suggestions = spell
+                    .suggestions
+                    .entry(issue.word.clone())
+                    .or_insert_with(|| checker.suggest(&issue.word))
+                    .clone();
+            }
+        }
+
+        drop(checker);
+        self.spell = Some(spell);
+        suggestions
+    }
+
+    /// Helper: Drops cached results checked with other word lists or
+    /// another language
+    fn sync_spell_state(&self, spell: &mut SpellState, checker: &SpellChecker) {
+        if spell.generation != checker.generation() || spell.language != self.language {
+            spell.generation = checker.generation();
+            spell.language = self.language.clone();
+            spell.invalidate(self.rope.len_lines());
+            spell.suggestions.clear();
+        }
+    }
+
+    /// Helper: Checks the text of one line
+    fn check_line(&self, checker: &SpellChecker, line: usize) -> Vec<SpellIssue> {
+        if !checker.has_dictionary() {
+            return Vec::new();
+        }
+
+        let line_start = self.rope.line_to_byte(line);
+        let text = self.rope.line(line).to_string();
+        let line_range = line_start..line_start + text.len();
+
+        let mut ranges = Vec::new();
+        match (&self.language, &self.syntax_tree) {
+            (LanguageId::PlainText, _) | (LanguageId::Markdown, None) => ranges.push(line_range.clone()),
+            (LanguageId::Markdown, Some(tree)) => {
+                let mut code = Vec::new();
+                markdown_code_ranges(tree.root_node(), &line_range, &mut code);
+                ranges = without_inline_code(subtract(vec![line_range.clone()], &code), &text, line_start);
+            }
+            (language, Some(tree)) => checked_ranges(tree.root_node(), language, &line_range, &mut ranges),
+            // Code without a grammar: comments and strings cannot be told apart
+            (_, None) => {}
+        }
+
+        let mut issues = Vec::new();
+        for range in ranges.into_iter().filter(|r| r.start < r.end) {
+            let local = range.start - line_start..range.end - line_start;
+            let Some(segment) = text.get(local.clone()) else {
+                continue;
+            };
+
+            for word_range in split_words(segment) {
+                let word = &segment[word_range.clone()];
+                if checker.check(word) {
+                    continue;
+                }
+
+                let byte_start = local.start + word_range.start;
+                let start = text[..byte_start].chars().count();
+                issues.push(SpellIssue {
+                    columns: start..start + word.chars().count(),
+                    word: word.to_string(),
+                });
+            }
+        }
+        issues
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::{Duration, Instant};
+
+    use super::*;
+
+    const AFF: &str = "SET UTF-8\nTRY esiantrolcdugmphbyfvkwzESIANTROLCDUGMPHBYFVKWZ'\nREP 1\nREP teh the\n\
+        SFX S Y 2\nSFX S 0 s [^sxz]\nSFX S 0 es [sxz]\n\
+        PFX U Y 1\nPFX U 0 un .\n\
+        SFX D Y 2\nSFX D 0 ed [^ey]\nSFX D y ied y\n";
+    const DIC: &str = "12\nthe\nword/S\nbox/S\ncheck/SUD\ncopy/D\nspell\nparse\nconfig\nline\nhello\nLondon\nworld\n";
+
+    fn checker() -> SharedSpellChecker {
+        let mut checker = SpellChecker::new();
+        checker.add_dictionary(Dictionary::parse(AFF, DIC).unwrap());
+        checker.shared()
+    }
+
+    fn words(text: &str) -> Vec<&str> {
+        split_words(text).into_iter().map(|r| &text[r]).collect()
+    }
+
+    #[test]
+    fn test_dictionary_affixes_and_case() {
+        let dictionary = Dictionary::parse(AFF, DIC).unwrap();
+        for word in ["word", "words", "boxes", "checked", "unchecked", "unchecks", "copied", "Hello", "HELLO", "London"] {
+            assert!(dictionary.contains(word), "{word}");
+        }
+        for word in ["boxs", "copyed", "london", "unword", "wrod"] {
+            assert!(!dictionary.contains(word), "{word}");
+        }
+        assert!(Dictionary::parse(AFF, "word\n").is_err());
+    }
+
+    #[test]
+    fn test_split_words() {
+        assert_eq!(words("parseConfig HTTPServer snake_case_word"), ["parse", "Config", "Server", "snake", "case", "word"]);
+        assert_eq!(words("see https://example.com or me@example.com, x86 utf8"), ["see", "or"]);
+        assert_eq!(words("line\\nwrap don't 'quoted'"), ["line", "wrap", "don't", "quoted"]);
+        assert_eq!(words("a JSON I"), Vec::<&str>::new());
+    }
+
+    #[test]
+    fn test_suggestions() {
+        let checker = checker();
+        let checker = checker.read().unwrap();
+        assert_eq!(checker.suggest("teh")[0], "the");
+        assert!(checker.suggest("wrod").contains(&"word".to_string()));
+        assert_eq!(checker.suggest("Wrold")[0], "World");
+        assert!(checker.suggest("helloworld").contains(&"hello world".to_string()));
+    }
+
+    #[test]
+    fn test_checks_comments_and_strings_only() {
+        let source = "// teh wrod check\nfn chekc() {\n    let s = \"hello wrold\";\n}\n";
+        let mut editor = Editor::with_content(source, LanguageId::Rust).unwrap();
+        editor.set_spell_checker(Some(checker()));
+
+        let diagnostics = editor.spell_diagnostics();
+        let found: Vec<(&str, usize, usize)> = diagnostics
+            .iter()
+            .map(|d| (d.word.as_str(), d.range.start.line, d.range.start.character))
+            .collect();
+        assert_eq!(found, vec![("teh", 0, 3), ("wrod", 0, 7), ("wrold", 2, 19)]);
+        assert_eq!(diagnostics[0].source, "spell");
+    }
+
+    #[test]
+    fn test_suggestions_at_position() {
+        let mut editor = Editor::with_content("// teh wrod check\nfn chekc() {}\n", LanguageId::Rust).unwrap();
+        let checker = checker();
+        editor.set_spell_checker(Some(checker.clone()));
+
+        // Lines are checked on demand, without listing diagnostics first
+        assert_eq!(editor.spell_suggestions_at(Position::new(0, 4))[0], "the");
+        assert_eq!(editor.spell_suggestions_at(Position::new(0, 11)), ["word"]);
+        assert!(editor.spell_suggestions_at(Position::new(0, 14)).is_empty());
+        assert!(editor.spell_suggestions_at(Position::new(1, 4)).is_empty());
+        assert!(editor.spell_suggestions_at(Position::new(9, 0)).is_empty());
+        assert_eq!(editor.spell.as_ref().unwrap().suggestions.len(), 2);
+
+        checker.write().unwrap().add_word(WordListKind::User, "teh").unwrap();
+        assert!(editor.spell_suggestions_at(Position::new(0, 4)).is_empty());
+        assert!(editor.spell.as_ref().unwrap().suggestions.is_empty());
+    }
+
+    #[test]
+    fn test_diagnostics_large_buffer() {
+        let source = "teh wrold chekc hellos\n".repeat(40_000);
+        let mut editor = Editor::with_content(&source, LanguageId::PlainText).unwrap();
+        editor.set_spell_checker(Some(checker()));
+
+        // Listing diagnostics builds no suggestions
+        let started = Instant::now();
+        assert_eq!(editor.spell_diagnostics().len(), 160_000);
+        assert!(started.elapsed() < Duration::from_secs(2), "{:?}", started.elapsed());
+        assert!(editor.spell.as_ref().unwrap().suggestions.is_empty());
+    }
+
+    #[test]
+    fn test_markdown_prose() {
+        let source = "# Hello wrold\n\nSome `chekc` text\n\n```rust\nfn chekc() {}\n```\n";
+        let mut editor = Editor::with_content(source, LanguageId::Markdown).unwrap();
+        let checker = checker();
+        checker.write().unwrap().add_word(WordListKind::Project, "Some").unwrap();
+        checker.write().unwrap().add_word(WordListKind::Project, "text").unwrap();
+        editor.set_spell_checker(Some(checker));
+
+        let words: Vec<String> = editor.spell_diagnostics().into_iter().map(|d| d.word).collect();
+        assert_eq!(words, ["wrold"]);
+    }
+
+    #[test]
+    fn test_incremental_recheck() {
+        let mut editor = Editor::with_content("hello\nwrold\nhello\n", LanguageId::PlainText).unwrap();
+        let checker = checker();
+        editor.set_spell_checker(Some(checker.clone()));
+        assert_eq!(editor.spell_diagnostics().len(), 1);
+
+        // Only the edited line is rechecked
+        editor.move_cursor(Position::new(2, 5));
+        editor.insert_text(" teh").unwrap();
+        let unchecked = editor.spell.as_ref().unwrap().lines.iter().filter(|l| l.is_none()).count();
+        assert_eq!(unchecked, 1);
+
+        let diagnostics = editor.spell_diagnostics();
+        assert_eq!(diagnostics.len(), 2);
+        assert_eq!(diagnostics[1].range.start, LspPosition { line: 2, character: 6 });
+
+        // Lines shift with inserted lines
+        editor.move_cursor(Position::new(0, 0));
+        editor.insert_text("new\nlines\n").unwrap();
+        let lines: Vec<usize> = editor.spell_diagnostics().iter().map(|d| d.range.start.line).collect();
+        assert_eq!(lines, [0, 1, 3, 4]);
+
+        // Word list changes recheck everything
+        checker.write().unwrap().add_word(WordListKind::User, "wrold").unwrap();
+        let words: Vec<String> = editor.spell_diagnostics().into_iter().map(|d| d.word).collect();
+        assert_eq!(words, ["new", "lines", "teh"]);
+    }
+
+    #[test]
+    fn test_word_lists_on_disk() {
+        let dir = std::env::temp_dir().join(format!("spell_check_{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("words.txt");
+        std::fs::write(&path, "# project words\nrustacean\n").unwrap();
+
+        let mut checker = SpellChecker::new();
+        checker.add_dictionary(Dictionary::parse(AFF, DIC).unwrap());
+        checker.load_word_list(WordListKind::Project, &path).unwrap();
+        assert!(checker.check("rustacean"));
+        assert!(!checker.check("ferris"));
+
+        checker.add_word(WordListKind::Project, "ferris").unwrap();
+        assert!(checker.add_word(WordListKind::Project, "two words").is_err());
+        let mut reloaded = SpellChecker::new();
+        reloaded.add_dictionary(Dictionary::parse(AFF, DIC).unwrap());
+        reloaded.load_word_list(WordListKind::Project, &path).unwrap();
+        assert!(reloaded.check("ferris"));
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,288 @@
+use ropey::Rope;
+use serde::Serialize;
+use tree_sitter::{Node, Point, Tree};
+
+use crate::editor::indent_guides::{line_indent_width, is_blank_line};
+
+/// Node kinds that open a scope worth pinning, besides `*_item`,
+/// `*_definition` and `*_declaration` nodes.
+const CONTROL_FLOW_SCOPES: &[&str] = &[
+    "if_statement",
+    "if_expression",
+    "for_statement",
+    "for_expression",
+    "for_in_statement",
+    "while_statement",
+    "while_expression",
+    "loop_expression",
+    "match_expression",
+    "switch_statement",
+    "try_statement",
+    "with_statement",
+];
+
+/// Declarations that never contain nested code.
+const NON_SCOPE_DECLARATIONS: &[&str] = &[
+    "use_declaration",
+    "field_declaration",
+    "const_item",
+    "static_item",
+    "type_item",
+    "let_declaration",
+    "lexical_declaration",
+    "variable_declaration",
+    "import_declaration",
+    "package_declaration",
+    "local_variable_declaration",
+];
+
+/// Scope pinned at the top of the viewport.
+///
+/// Example (first visible line is inside `area`):
+/// ```text
+/// impl Shape for Circle {      <- sticky (line 10)
+///     fn area(&self) -> f64 {  <- sticky (line 14)
+///         let r = self.radius; <- first visible line
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct StickyScope {
+    /// Header line of the scope
+    pub line: usize,
+
+    /// Last line of the scope
+    pub end_line: usize,
+
+    /// Header line text (without trailing whitespace)
+    pub text: String,
+
+    /// Syntax node kind, or "indent" for the indentation fallback
+    pub kind: String,
+}
+
+/// Computes the scopes enclosing the first visible line.
+///
+/// Scopes come from the syntax tree when available, otherwise from
+/// indentation (each less-indented line above is a header).
+///
+/// Parameters:
+/// - `rope`: The rope
+/// - `tree`: Current syntax tree (if any)
+/// - `top_line`: First visible line
+/// - `max_scopes`: Maximum number of scopes to return (outermost kept)
+/// - `tab_size`: Tab width used to measure indentation
+///
+/// Returns: Scopes ordered from outermost to innermost
+pub fn sticky_scopes(
+    rope: &Rope,
+    tree: Option<&Tree>,
+    top_line: usize,
+    max_scopes: usize,
+    tab_size: usize,
+) -> Vec<StickyScope> {
+    if top_line == 0 || top_line >= rope.len_lines() || max_scopes == 0 {
+        return Vec::new();
+    }
+
+    let mut scopes = match tree {
+        Some(tree) => syntax_scopes(rope, tree, top_line),
+        None => indentation_scopes(rope, top_line, tab_size),
+    };
+
+    scopes.truncate(max_scopes);
+    scopes
+}
+
+/// Helper: Collects enclosing scope nodes from the syntax tree.
+fn syntax_scopes(rope: &Rope, tree: &Tree, top_line: usize) -> Vec<StickyScope> {
+    // Anchor on the first non-whitespace char so leading indentation
+    // does not resolve to the parent node
+    let line = rope.line(top_line);
+    let indent_chars = line.chars().take_while(|c| *c == ' ' || *c == '\t').count();
+    let column = line.char_to_byte(indent_chars);
+    let point = Point::new(top_line, column);
+
+    let mut scopes = Vec::new();
+    let mut current = tree.root_node().descendant_for_point_range(point, point);
+
+    while let Some(node) = current {
+        if is_scope_node(node)
+            && node.start_position().row < top_line
+            && node.end_position().row >= top_line
+            && scopes
+                .last()
+                .is_none_or(|inner: &StickyScope| inner.line != node.start_position().row)
+        {
+            let header = node.start_position().row;
+            scopes.push(StickyScope {
+                line: header,
+                end_line: node.end_position().row,
+                text: line_text(rope, header),
+                kind: node.kind().to_string(),
+            });
+        }
+        current = node.parent();
+    }
+
+    scopes.reverse();
+    scopes
+}
+
+/// Helper: Collects enclosing scopes from indentation.
+fn indentation_scopes(rope: &Rope, top_line: usize, tab_size: usize) -> Vec<StickyScope> {
+    // Blank lines take the indentation of the next non-blank line
+    let mut anchor = top_line;
+    while anchor < rope.len_lines() && is_blank_line(rope, anchor) {
+        anchor += 1;
+    }
+    if anchor >= rope.len_lines() {
+        return Vec::new();
+    }
+
+    let mut current_indent = line_indent_width(rope, anchor, tab_size);
+    let mut scopes = Vec::new();
+
+    for line in (0..top_line).rev() {
+        if current_indent == 0 {
+            break;
+        }
+        if is_blank_line(rope, line) {
+            continue;
+        }
+
+        let indent = line_indent_width(rope, line, tab_size);
+        if indent < current_indent {
+            scopes.push(StickyScope {
+                line,
+                end_line: indentation_scope_end(rope, line, indent, tab_size),
+                text: line_text(rope, line),
+                kind: "indent".to_string(),
+            });
+            current_indent = indent;
+        }
+    }
+
+    scopes.reverse();
+    scopes
+}
+
+/// Helper: Finds the last line indented deeper than a header.
+fn indentation_scope_end(rope: &Rope, header: usize, header_indent: usize, tab_size: usize) -> usize {
+    let mut end = header;
+    for line in header + 1..rope.len_lines() {
+        if is_blank_line(rope, line) {
+            continue;
+        }
+        if line_indent_width(rope, line, tab_size) <= header_indent {
+            break;
+        }
+        end = line;
+    }
+    end
+}
+
+/// Helper: Checks if a node opens a pinnable scope.
+fn is_scope_node(node: Node) -> bool {
+    let kind = node.kind();
+    if node.start_position().row == node.end_position().row
+        || NON_SCOPE_DECLARATIONS.contains(&kind)
+    {
+        return false;
+    }
+
+    kind.ends_with("_item")
+        || kind.ends_with("_definition")
+        || kind.ends_with("_declaration")
+        || CONTROL_FLOW_SCOPES.contains(&kind)
+}
+
+/// Helper: Gets line text without trailing whitespace.
+fn line_text(rope: &Rope, line: usize) -> String {
+    rope.line(line).to_string().trim_end().to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tree_sitter::Parser;
+
+    const RUST_SOURCE: &str = "\
+impl Shape for Circle {
+    fn area(&self) -> f64 {
+        let r = self.radius;
+        if r > 0.0 {
+            r * r
+        } else {
+            0.0
+        }
+    }
+}
+";
+
+    fn parse(source: &str, language: tree_sitter::Language) -> Tree {
+        let mut parser = Parser::new();
+        parser.set_language(language).unwrap();
+        parser.parse(source, None).unwrap()
+    }
+
+    #[test]
+    fn test_sticky_scopes_syntax() {
+        let rope = Rope::from_str(RUST_SOURCE);
+        let tree = parse(RUST_SOURCE, tree_sitter_rust::language());
+
+        let scopes = sticky_scopes(&rope, Some(&tree), 4, 10, 4);
+        let lines: Vec<usize> = scopes.iter().map(|s| s.line).collect();
+        assert_eq!(lines, vec![0, 1, 3]);
+        assert_eq!(scopes[0].kind, "impl_item");
+        assert_eq!(scopes[0].end_line, 9);
+        assert_eq!(scopes[1].text, "    fn area(&self) -> f64 {");
+        assert_eq!(scopes[2].kind, "if_expression");
+    }
+
+    #[test]
+    fn test_sticky_scopes_max_keeps_outermost() {
+        let rope = Rope::from_str(RUST_SOURCE);
+        let tree = parse(RUST_SOURCE, tree_sitter_rust::language());
+
+        let scopes = sticky_scopes(&rope, Some(&tree), 4, 2, 4);
+        let lines: Vec<usize> = scopes.iter().map(|s| s.line).collect();
+        assert_eq!(lines, vec![0, 1]);
+    }
+
+    #[test]
+    fn test_sticky_scopes_header_visible() {
+        let rope = Rope::from_str(RUST_SOURCE);
+        let tree = parse(RUST_SOURCE, tree_sitter_rust::language());
+
+        // The `fn` header itself is the first visible line
+        let scopes = sticky_scopes(&rope, Some(&tree), 1, 10, 4);
+        let lines: Vec<usize> = scopes.iter().map(|s| s.line).collect();
+        assert_eq!(lines, vec![0]);
+
+        assert!(sticky_scopes(&rope, Some(&tree), 0, 10, 4).is_empty());
+    }
+
+    #[test]
+    fn test_sticky_scopes_python() {
+        let source = "class Greeter:\n    def greet(self):\n        x = 1\n\n        return x\n";
+        let rope = Rope::from_str(source);
+        let tree = parse(source, tree_sitter_python::language());
+
+        let scopes = sticky_scopes(&rope, Some(&tree), 4, 10, 4);
+        let kinds: Vec<&str> = scopes.iter().map(|s| s.kind.as_str()).collect();
+        assert_eq!(kinds, vec!["class_definition", "function_definition"]);
+    }
+
+    #[test]
+    fn test_sticky_scopes_indentation_fallback() {
+        let source = "section:\n  item:\n    value\n\n    other\nnext\n";
+        let rope = Rope::from_str(source);
+
+        let scopes = sticky_scopes(&rope, None, 3, 10, 4);
+        let lines: Vec<usize> = scopes.iter().map(|s| s.line).collect();
+        assert_eq!(lines, vec![0, 1]);
+        assert_eq!(scopes[0].kind, "indent");
+        assert_eq!(scopes[0].end_line, 4);
+        assert_eq!(scopes[1].text, "  item:");
+    }
+}
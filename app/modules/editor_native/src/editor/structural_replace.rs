@@ -0,0 +1,874 @@
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use ropey::Rope;
+use serde::Serialize;
+use tree_sitter::{Node, Parser, Query, QueryCursor, Tree};
+
+use crate::editor::editorconfig::{self, Charset};
+use crate::editor::lsp_types::{LspPosition, LspRange};
+use crate::editor::syntax_query::QueryError;
+use crate::editor::{Editor, LanguageId, Position};
+use crate::workspace::write_atomic;
+
+/// Capture marking the node to replace (the whole template in template mode).
+pub const MATCH_CAPTURE: &str = "match";
+
+/// Prefix of placeholder identifiers substituted for `$metavariables`.
+const PLACEHOLDER_PREFIX: &str = "__sr_";
+
+/// Prefix of internal captures (hidden from match previews).
+const INTERNAL_CAPTURE_PREFIX: &str = "__";
+
+/// Anonymous tokens skipped when compiling templates (structure is
+/// already fixed by the named children).
+const PUNCTUATION: &[&str] = &["(", ")", "[", "]", "{", "}", ",", ";", ":", "."];
+
+/// Structural search pattern, compiled per language.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum StructuralPattern {
+    /// Raw tree-sitter query; `@match` (if captured) is the replaced node
+    Query(String),
+
+    /// Code with `$name` metavariables, e.g. `$a.unwrap_or($b)`
+    Template(String),
+}
+
+impl StructuralPattern {
+    /// Compiles the pattern for a language.
+    pub fn compile(&self, language: &LanguageId) -> Result<StructuralQuery, QueryError> {
+        match self {
+            StructuralPattern::Query(source) => StructuralQuery::from_query(language, source),
+            StructuralPattern::Template(template) => {
+                StructuralQuery::from_template(language, template)
+            }
+        }
+    }
+}
+
+/// Match found by a structural search.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct StructuralMatch {
+    pub start_byte: usize,
+    pub end_byte: usize,
+    pub range: LspRange,
+
+    /// Matched source text
+    pub text: String,
+
+    /// Captured text by metavariable / capture name
+    pub captures: BTreeMap<String, String>,
+
+    /// Replacement text (previews only)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub replacement: Option<String>,
+}
+
+/// Compiled structural search query.
+///
+/// Example (Rust template):
+/// ```text
+/// search:  $opt.unwrap_or($default)
+/// replace: $opt.unwrap_or_else(|| $default)
+/// ```
+#[derive(Debug)]
+pub struct StructuralQuery {
+    language: LanguageId,
+    query: Query,
+}
+
+impl StructuralQuery {
+    /// Compiles a raw tree-sitter query.
+    ///
+    /// Parameters:
+    /// - `language`: Language of the searched documents
+    /// - `source`: Query source; capture `@match` to choose the replaced
+    ///   node, otherwise the span of all captures is replaced
+    pub fn from_query(language: &LanguageId, source: &str) -> Result<Self, QueryError> {
+        let ts_language = language
+            .tree_sitter_language()
+            .ok_or_else(|| QueryError::UnsupportedLanguage(language.name().to_string()))?;
+
+        // tree-sitter panics while formatting errors for unclosed patterns
+        if !balanced_parentheses(source) {
+            return Err(QueryError::InvalidPattern("unbalanced parentheses".to_string()));
+        }
+        let query = Query::new(ts_language, source)
+            .map_err(|e| QueryError::InvalidPattern(e.to_string()))?;
+
+        Ok(Self { language: language.clone(), query })
+    }
+
+    /// Compiles a code template with `$name` metavariables.
+    ///
+    /// Each metavariable matches any single syntax node; a metavariable
+    /// used twice must match identical text. `$_` matches without
+    /// capturing. All other nodes must match the template exactly
+    /// (ignoring whitespace and comments).
+    ///
+    /// Parameters:
+    /// - `language`: Language of the template and searched documents
+    /// - `template`: Code template, e.g. `foo($a, $b)`
+    pub fn from_template(language: &LanguageId, template: &str) -> Result<Self, QueryError> {
+        let ts_language = language
+            .tree_sitter_language()
+            .ok_or_else(|| QueryError::UnsupportedLanguage(language.name().to_string()))?;
+
+        let (code, placeholders) = substitute_metavariables(template)?;
+        let code = code.trim();
+        if code.is_empty() {
+            return Err(QueryError::InvalidTemplate("empty template".to_string()));
+        }
+
+        let mut parser = Parser::new();
+        parser
+            .set_language(ts_language)
+            .map_err(|e| QueryError::InvalidPattern(e.to_string()))?;
+
+        for wrapper in template_wrappers(language) {
+            let source = wrapper.replace("{}", code);
+            let offset = wrapper.find("{}").unwrap_or(0);
+            let Some(tree) = parser.parse(&source, None) else {
+                continue;
+            };
+            if tree.root_node().has_error() {
+                continue;
+            }
+            let Some(node) = template_node(&tree, offset, offset + code.len()) else {
+                continue;
+            };
+
+            let mut compiler = TemplateCompiler {
+                source: &source,
+                placeholders: &placeholders,
+                seen: BTreeMap::new(),
+                predicates: Vec::new(),
+                leaf_count: 0,
+            };
+            let pattern = compiler.compile(node);
+            let query_source = format!(
+                "({} @{} {})",
+                pattern,
+                MATCH_CAPTURE,
+                compiler.predicates.join(" ")
+            );
+
+            let query = Query::new(ts_language, &query_source)
+                .map_err(|e| QueryError::InvalidTemplate(e.to_string()))?;
+            return Ok(Self { language: language.clone(), query });
+        }
+
+        Err(QueryError::InvalidTemplate(format!(
+            "template does not parse as {}: {}",
+            language.name(),
+            template
+        )))
+    }
+
+    /// Gets the language the query was compiled for.
+    pub fn language(&self) -> &LanguageId {
+        &self.language
+    }
+
+    /// Gets the user-visible capture names (metavariables).
+    pub fn capture_names(&self) -> Vec<&str> {
+        self.query
+            .capture_names()
+            .iter()
+            .map(String::as_str)
+            .filter(|name| !name.starts_with(INTERNAL_CAPTURE_PREFIX))
+            .collect()
+    }
+
+    /// Finds all matches in a document.
+    ///
+    /// Matches nested in an earlier match are skipped, so the results
+    /// never overlap and can be replaced together.
+    ///
+    /// Parameters:
+    /// - `tree`: Syntax tree of the document
+    /// - `rope`: Document
+    ///
+    /// Returns: Matches in document order
+    pub fn find(&self, tree: &Tree, rope: &Rope) -> Vec<StructuralMatch> {
+        let source = rope.to_string();
+        let capture_names = self.query.capture_names();
+        let match_index = self.query.capture_index_for_name(MATCH_CAPTURE);
+
+        let mut cursor = QueryCursor::new();
+        let mut results: Vec<StructuralMatch> = Vec::new();
+
+        for m in cursor.matches(&self.query, tree.root_node(), source.as_bytes()) {
+            if m.captures.is_empty() {
+                continue;
+            }
+
+            let (start_byte, end_byte) = match m.captures.iter().find(|c| Some(c.index) == match_index) {
+                Some(capture) => (capture.node.start_byte(), capture.node.end_byte()),
+                None => (
+                    m.captures.iter().map(|c| c.node.start_byte()).min().unwrap_or(0),
+                    m.captures.iter().map(|c| c.node.end_byte()).max().unwrap_or(0),
+                ),
+            };
+
+            let overlaps = results
+                .iter()
+                .any(|r| start_byte < r.end_byte && r.start_byte < end_byte);
+            if overlaps {
+                continue;
+            }
+
+            let mut captures = BTreeMap::new();
+            for capture in m.captures {
+                let name = &capture_names[capture.index as usize];
+                if !name.starts_with(INTERNAL_CAPTURE_PREFIX) {
+                    captures
+                        .entry(name.clone())
+                        .or_insert_with(|| source[capture.node.byte_range()].to_string());
+                }
+            }
+
+            results.push(StructuralMatch {
+                start_byte,
+                end_byte,
+                range: LspRange {
+                    start: byte_to_lsp(rope, start_byte),
+                    end: byte_to_lsp(rope, end_byte),
+                },
+                text: source[start_byte..end_byte].to_string(),
+                captures,
+                replacement: None,
+            });
+        }
+
+        results.sort_by_key(|r| r.start_byte);
+        results
+    }
+
+    /// Finds all matches and computes their replacement text.
+    ///
+    /// Parameters:
+    /// - `tree`: Syntax tree of the document
+    /// - `rope`: Document
+    /// - `replacement`: Template using `$name` / `${name}` for captures
+    ///   (`$$` for a literal `$`)
+    ///
+    /// Returns: Matches with `replacement` set, or an error if the
+    /// replacement uses an unknown metavariable
+    pub fn preview(
+        &self,
+        tree: &Tree,
+        rope: &Rope,
+        replacement: &str,
+    ) -> Result<Vec<StructuralMatch>, QueryError> {
+        let names = self.capture_names();
+        for name in replacement_variables(replacement)? {
+            if !names.contains(&name.as_str()) {
+                return Err(QueryError::InvalidTemplate(format!(
+                    "unknown metavariable in replacement: ${}",
+                    name
+                )));
+            }
+        }
+
+        let mut matches = self.find(tree, rope);
+        for m in &mut matches {
+            m.replacement = Some(expand_replacement(replacement, &m.captures)?);
+        }
+        Ok(matches)
+    }
+}
+
+/// Structural replacement planned for one file.
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct FileReplacement {
+    pub path: PathBuf,
+
+    /// Matches with their replacement text
+    pub matches: Vec<StructuralMatch>,
+
+    /// File content after all replacements
+    #[serde(skip)]
+    pub new_content: String,
+
+    /// File bytes the preview was made from
+    #[serde(skip)]
+    pub original: Vec<u8>,
+}
+
+/// Previews a structural replacement over a set of files.
+///
+/// Each file is parsed with the language of its extension and the
+/// pattern is compiled once per language. Files without a grammar, or
+/// whose language cannot compile the pattern, are skipped.
+///
+/// Parameters:
+/// - `paths`: Files to search
+/// - `pattern`: Search pattern
+/// - `replacement`: Replacement template
+///
+/// Returns: One entry per file with at least one match, or an error if
+/// a file cannot be read or the pattern compiles for none of the languages
+pub fn preview_files<P: AsRef<Path>>(
+    paths: &[P],
+    pattern: &StructuralPattern,
+    replacement: &str,
+) -> anyhow::Result<Vec<FileReplacement>> {
+    let mut compiled: BTreeMap<&'static str, Result<StructuralQuery, QueryError>> = BTreeMap::new();
+    let mut results = Vec::new();
+
+    for path in paths {
+        let path = path.as_ref();
+        let language = LanguageId::from_path(path);
+        if language.tree_sitter_language().is_none() {
+            continue;
+        }
+
+        let query = compiled
+            .entry(language.name())
+            .or_insert_with(|| pattern.compile(&language));
+        let Ok(query) = query else {
+            continue;
+        };
+
+        let original = fs::read(path)?;
+        let mut editor = file_editor(path, &original, None)?;
+        let matches = editor.structural_replace(query, replacement)?;
+        if matches.is_empty() {
+            continue;
+        }
+
+        results.push(FileReplacement {
+            path: path.to_path_buf(),
+            matches,
+            new_content: editor.content(),
+            original,
+        });
+    }
+
+    // A pattern that compiles nowhere is an error, not "no matches"
+    if let Some(Err(err)) = compiled.values().next() {
+        if compiled.values().all(Result::is_err) {
+            return Err(err.clone().into());
+        }
+    }
+
+    Ok(results)
+}
+
+/// Writes previewed replacements to disk.
+///
+/// Files are saved like the editor saves them (save actions, line
+/// endings and charset from `.editorconfig`), each replaced atomically.
+/// Nothing is written if any file changed on disk since the preview.
+///
+/// Returns: Total number of replaced matches
+pub fn apply_files(replacements: &[FileReplacement]) -> anyhow::Result<usize> {
+    for file in replacements {
+        if fs::read(&file.path)? != file.original {
+            anyhow::bail!("{} changed on disk since the preview", file.path.display());
+        }
+    }
+
+    let mut count = 0;
+    for file in replacements {
+        let mut editor = file_editor(&file.path, &file.original, Some(&file.new_content))?;
+        write_atomic(&file.path, &editor.prepare_save()?)?;
+        count += file.matches.len();
+    }
+    Ok(count)
+}
+
+/// Helper: Loads a file into an editor set up to save it back
+///
+/// The bytes are decoded with the `.editorconfig` charset (or the one of
+/// their byte order mark), and the file's `.editorconfig` settings apply.
+///
+/// Parameters:
+/// - `path`: File path (for the language and `.editorconfig` lookup)
+/// - `bytes`: File content on disk
+/// - `content`: Buffer text (None: the decoded bytes)
+fn file_editor(path: &Path, bytes: &[u8], content: Option<&str>) -> anyhow::Result<Editor> {
+    // Missing or unreadable .editorconfig files just keep the defaults
+    let props = editorconfig::resolve_for_path(path).ok();
+    let charset = props
+        .as_ref()
+        .and_then(|props| props.charset)
+        .or(Charset::detect(bytes))
+        .unwrap_or_default();
+
+    let decoded;
+    let content = match content {
+        Some(content) => content,
+        None => {
+            decoded = charset.decode(bytes)?;
+            &decoded
+        }
+    };
+
+    let mut editor = Editor::with_content(content, LanguageId::from_path(path))?;
+    let mut settings = editor.save_settings().clone();
+    settings.charset = charset;
+    editor.set_save_settings(settings);
+    if let Some(props) = &props {
+        editor.apply_editorconfig(props);
+    }
+    Ok(editor)
+}
+
+/// Helper: Compiles template syntax nodes to query patterns.
+struct TemplateCompiler<'a> {
+    source: &'a str,
+    placeholders: &'a BTreeMap<String, String>,
+
+    /// Occurrences of each metavariable so far
+    seen: BTreeMap<String, usize>,
+
+    predicates: Vec<String>,
+    leaf_count: usize,
+}
+
+impl TemplateCompiler<'_> {
+    fn compile(&mut self, node: Node) -> String {
+        let text = &self.source[node.byte_range()];
+
+        if let Some(name) = self.placeholders.get(text) {
+            if name == "_" {
+                return "(_)".to_string();
+            }
+
+            let count = self.seen.entry(name.clone()).or_insert(0);
+            *count += 1;
+            if *count == 1 {
+                return format!("(_) @{}", name);
+            }
+
+            // Repeated metavariable: must capture the same text
+            let alias = format!("{}{}_{}", INTERNAL_CAPTURE_PREFIX, name, count);
+            self.predicates.push(format!("(#eq? @{} @{})", name, alias));
+            return format!("(_) @{}", alias);
+        }
+
+        if node.named_child_count() == 0 {
+            // Leaf: match its text exactly
+            let capture = format!("{}leaf{}", INTERNAL_CAPTURE_PREFIX, self.leaf_count);
+            self.leaf_count += 1;
+            self.predicates.push(format!("(#eq? @{} {})", capture, quote(text)));
+            return format!("({}) @{}", node.kind(), capture);
+        }
+
+        let mut children = Vec::new();
+        let mut cursor = node.walk();
+        if cursor.goto_first_child() {
+            loop {
+                let child = cursor.node();
+                if !child.is_extra() && !child.is_missing() {
+                    let field = cursor.field_name().map(|f| format!("{}: ", f)).unwrap_or_default();
+                    if child.is_named() {
+                        children.push(format!(". {}{}", field, self.compile(child)));
+                    } else if !PUNCTUATION.contains(&child.kind()) {
+                        children.push(format!("{}{}", field, quote(child.kind())));
+                    }
+                }
+                if !cursor.goto_next_sibling() {
+                    break;
+                }
+            }
+        }
+
+        format!("({} {} .)", node.kind(), children.join(" "))
+    }
+}
+
+/// Helper: Replaces `$name` with placeholder identifiers.
+///
+/// Returns: Substituted code and a map from placeholder to metavariable
+fn substitute_metavariables(template: &str) -> Result<(String, BTreeMap<String, String>), QueryError> {
+    let mut code = String::with_capacity(template.len());
+    let mut placeholders = BTreeMap::new();
+    let mut wildcards = 0;
+    let mut chars = template.char_indices().peekable();
+
+    while let Some((_, c)) = chars.next() {
+        if c != '$' {
+            code.push(c);
+            continue;
+        }
+
+        let mut name = String::new();
+        while let Some(&(_, next)) = chars.peek() {
+            if next.is_ascii_alphanumeric() || next == '_' {
+                name.push(next);
+                chars.next();
+            } else {
+                break;
+            }
+        }
+
+        if name.is_empty() || name.starts_with(|c: char| c.is_ascii_digit()) {
+            return Err(QueryError::InvalidTemplate(format!(
+                "invalid metavariable name: ${}",
+                name
+            )));
+        }
+
+        // Every `$_` gets its own placeholder
+        let placeholder = if name == "_" {
+            wildcards += 1;
+            format!("{}w{}", PLACEHOLDER_PREFIX, wildcards)
+        } else {
+            format!("{}{}", PLACEHOLDER_PREFIX, name)
+        };
+        code.push_str(&placeholder);
+        placeholders.insert(placeholder, name);
+    }
+
+    Ok((code, placeholders))
+}
+
+/// Helper: Source wrappers tried in order so templates can be
+/// expressions or statements (`{}` marks the template).
+fn template_wrappers(language: &LanguageId) -> &'static [&'static str] {
+    match language {
+        LanguageId::Rust => &["{}", "fn __sr() { {} }", "fn __sr() { {}; }"],
+        LanguageId::Java => &[
+            "{}",
+            "class __Sr { {} }",
+            "class __Sr { void __sr() { {} } }",
+            "class __Sr { void __sr() { {}; } }",
+            "class __Sr { Object __sr = {}; }",
+        ],
+        LanguageId::Go => &["package __sr\n{}", "package __sr\nfunc __sr() { {} }"],
+        LanguageId::JavaScript | LanguageId::TypeScript => &["{}", "({})"],
+        _ => &["{}"],
+    }
+}
+
+/// Helper: Finds the innermost node spanning exactly the template.
+fn template_node(tree: &Tree, start: usize, end: usize) -> Option<Node<'_>> {
+    let mut node = tree.root_node().descendant_for_byte_range(start, end)?;
+    if node.start_byte() != start || node.end_byte() != end {
+        return None;
+    }
+
+    while node.named_child_count() == 1 {
+        let child = node.named_child(0)?;
+        if child.start_byte() != start || child.end_byte() != end {
+            break;
+        }
+        node = child;
+    }
+
+    Some(node)
+}
+
+/// Helper: Gets the metavariable names used in a replacement.
+fn replacement_variables(replacement: &str) -> Result<Vec<String>, QueryError> {
+    let mut names = Vec::new();
+    expand(replacement, &mut |name| {
+        names.push(name.to_string());
+        Some(String::new())
+    })?;
+    Ok(names)
+}
+
+/// Helper: Expands `$name` / `${name}` in a replacement template.
+fn expand_replacement(
+    replacement: &str,
+    captures: &BTreeMap<String, String>,
+) -> Result<String, QueryError> {
+    expand(replacement, &mut |name| captures.get(name).cloned())
+}
+
+/// Helper: Walks a replacement template, resolving metavariables.
+fn expand(
+    replacement: &str,
+    resolve: &mut dyn FnMut(&str) -> Option<String>,
+) -> Result<String, QueryError> {
+    let mut result = String::with_capacity(replacement.len());
+    let mut chars = replacement.chars().peekable();
+
+    while let Some(c) = chars.next() {
+        if c != '$' {
+            result.push(c);
+            continue;
+        }
+
+        let name: String = match chars.peek() {
+            Some('$') => {
+                chars.next();
+                result.push('$');
+                continue;
+            }
+            Some('{') => {
+                chars.next();
+                let name: String = chars.by_ref().take_while(|c| *c != '}').collect();
+                name
+            }
+            _ => {
+                let mut name = String::new();
+                while let Some(&next) = chars.peek() {
+                    if next.is_ascii_alphanumeric() || next == '_' {
+                        name.push(next);
+                        chars.next();
+                    } else {
+                        break;
+                    }
+                }
+                name
+            }
+        };
+
+        if name.is_empty() {
+            return Err(QueryError::InvalidTemplate(
+                "empty metavariable in replacement".to_string(),
+            ));
+        }
+        match resolve(&name) {
+            Some(text) => result.push_str(&text),
+            None => {
+                return Err(QueryError::InvalidTemplate(format!(
+                    "unknown metavariable in replacement: ${}",
+                    name
+                )))
+            }
+        }
+    }
+
+    Ok(result)
+}
+
+/// Helper: Checks that parentheses outside string literals and
+/// comments are balanced.
+fn balanced_parentheses(source: &str) -> bool {
+    let mut depth: i64 = 0;
+    let mut in_string = false;
+    let mut chars = source.chars();
+
+    while let Some(c) = chars.next() {
+        match c {
+            '\\' if in_string => {
+                chars.next();
+            }
+            '"' => in_string = !in_string,
+            ';' if !in_string => {
+                // Comment until end of line
+                for next in chars.by_ref() {
+                    if next == '\n' {
+                        break;
+                    }
+                }
+            }
+            '(' if !in_string => depth += 1,
+            ')' if !in_string => {
+                depth -= 1;
+                if depth < 0 {
+                    return false;
+                }
+            }
+            _ => {}
+        }
+    }
+
+    depth == 0 && !in_string
+}
+
+/// Helper: Quotes text as a query string literal.
+fn quote(text: &str) -> String {
+    let mut quoted = String::with_capacity(text.len() + 2);
+    quoted.push('"');
+    for c in text.chars() {
+        match c {
+            '"' => quoted.push_str("\\\""),
+            '\\' => quoted.push_str("\\\\"),
+            '\n' => quoted.push_str("\\n"),
+            '\r' => quoted.push_str("\\r"),
+            '\t' => quoted.push_str("\\t"),
+            c => quoted.push(c),
+        }
+    }
+    quoted.push('"');
+    quoted
+}
+
+/// Helper: Converts a byte offset to an LSP position.
+fn byte_to_lsp(rope: &Rope, byte: usize) -> LspPosition {
+    let line = rope.byte_to_line(byte);
+    let column = rope.byte_to_char(byte) - rope.line_to_char(line);
+    Position::new(line, column).into()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(source: &str, language: &LanguageId) -> (Tree, Rope) {
+        let mut parser = Parser::new();
+        parser.set_language(language.tree_sitter_language().unwrap()).unwrap();
+        (parser.parse(source, None).unwrap(), Rope::from_str(source))
+    }
+
+    #[test]
+    fn test_template_matches_structure() {
+        let source = "fn main() {\n    let a = x.unwrap_or(1);\n    let b = y.unwrap_or(f(2));\n    let c = z.unwrap_or(1, 2);\n    let d = z.unwrap_or_default();\n}\n";
+        let (tree, rope) = parse(source, &LanguageId::Rust);
+
+        let query = StructuralQuery::from_template(&LanguageId::Rust, "$opt.unwrap_or($default)").unwrap();
+        let matches = query.find(&tree, &rope);
+
+        assert_eq!(matches.len(), 2);
+        assert_eq!(matches[0].text, "x.unwrap_or(1)");
+        assert_eq!(matches[0].captures["opt"], "x");
+        assert_eq!(matches[1].captures["default"], "f(2)");
+        assert_eq!(matches[1].range.start.line, 2);
+        assert_eq!(matches[1].range.start.character, 12);
+    }
+
+    #[test]
+    fn test_template_repeated_metavariable() {
+        let source = "a = x + x\nb = x + y\n";
+        let (tree, rope) = parse(source, &LanguageId::Python);
+
+        let query = StructuralQuery::from_template(&LanguageId::Python, "$v + $v").unwrap();
+        let matches = query.find(&tree, &rope);
+
+        assert_eq!(matches.len(), 1);
+        assert_eq!(matches[0].text, "x + x");
+    }
+
+    #[test]
+    fn test_template_operators_and_literals_must_match() {
+        let source = "a = x + 1\nb = x - 1\nc = x + 2\n";
+        let (tree, rope) = parse(source, &LanguageId::Python);
+
+        let query = StructuralQuery::from_template(&LanguageId::Python, "$v + 1").unwrap();
+        let texts: Vec<String> = query.find(&tree, &rope).into_iter().map(|m| m.text).collect();
+        assert_eq!(texts, vec!["x + 1"]);
+    }
+
+    #[test]
+    fn test_preview_expands_replacement() {
+        let source = "console.log(a);\nconsole.log(\"$\", b);\n";
+        let (tree, rope) = parse(source, &LanguageId::JavaScript);
+
+        let query = StructuralQuery::from_template(&LanguageId::JavaScript, "console.log($msg)").unwrap();
+        let matches = query.preview(&tree, &rope, "logger.debug(${msg}, \"$$\")").unwrap();
+
+        assert_eq!(matches.len(), 1);
+        assert_eq!(matches[0].replacement.as_deref(), Some("logger.debug(a, \"$\")"));
+
+        let err = query.preview(&tree, &rope, "logger.debug($missing)").unwrap_err();
+        assert!(matches!(err, QueryError::InvalidTemplate(_)));
+    }
+
+    #[test]
+    fn test_raw_query_with_match_capture() {
+        let source = "def f():\n    pass\n\ndef g():\n    return 1\n";
+        let (tree, rope) = parse(source, &LanguageId::Python);
+
+        let query = StructuralQuery::from_query(
+            &LanguageId::Python,
+            "(function_definition name: (identifier) @name body: (block (pass_statement) @match))",
+        )
+        .unwrap();
+        let matches = query.find(&tree, &rope);
+
+        assert_eq!(matches.len(), 1);
+        assert_eq!(matches[0].text, "pass");
+        assert_eq!(matches[0].captures["name"], "f");
+    }
+
+    #[test]
+    fn test_nested_matches_do_not_overlap() {
+        let source = "fn main() { foo(foo(1)); }\n";
+        let (tree, rope) = parse(source, &LanguageId::Rust);
+
+        let query = StructuralQuery::from_template(&LanguageId::Rust, "foo($x)").unwrap();
+        let matches = query.find(&tree, &rope);
+
+        assert_eq!(matches.len(), 1);
+        assert_eq!(matches[0].captures["x"], "foo(1)");
+    }
+
+    #[test]
+    fn test_invalid_raw_query() {
+        for source in ["(identifier", "(identifier))", "(identifier) @x (#eq? @x \"(\""] {
+            assert!(matches!(
+                StructuralQuery::from_query(&LanguageId::Rust, source),
+                Err(QueryError::InvalidPattern(_))
+            ));
+        }
+        assert!(StructuralQuery::from_query(&LanguageId::Rust, "((identifier) @x (#eq? @x \"(\"))").is_ok());
+    }
+
+    #[test]
+    fn test_template_errors() {
+        assert!(matches!(
+            StructuralQuery::from_template(&LanguageId::Rust, "foo($1)"),
+            Err(QueryError::InvalidTemplate(_))
+        ));
+        assert!(matches!(
+            StructuralQuery::from_template(&LanguageId::Rust, "fn ("),
+            Err(QueryError::InvalidTemplate(_))
+        ));
+        assert!(matches!(
+            StructuralQuery::from_template(&LanguageId::PlainText, "x"),
+            Err(QueryError::UnsupportedLanguage(_))
+        ));
+    }
+
+    #[test]
+    fn test_preview_and_apply_files() {
+        let dir = std::env::temp_dir().join(format!("structural_replace_{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let rust = dir.join("lib.rs");
+        let python = dir.join("main.py");
+        let text = dir.join("notes.txt");
+        fs::write(&rust, "fn f() { old(1); old(2); }\n").unwrap();
+        fs::write(&python, "old(3)\nnew(4)\n").unwrap();
+        fs::write(&text, "old(5)\n").unwrap();
+
+        let pattern = StructuralPattern::Template("old($x)".to_string());
+        let previews = preview_files(&[&rust, &python, &text], &pattern, "new($x)").unwrap();
+
+        assert_eq!(previews.len(), 2);
+        assert_eq!(previews[0].matches.len(), 2);
+        assert_eq!(previews[1].new_content, "new(3)\nnew(4)\n");
+
+        assert_eq!(apply_files(&previews).unwrap(), 3);
+        assert_eq!(fs::read_to_string(&rust).unwrap(), "fn f() { new(1); new(2); }\n");
+        assert_eq!(fs::read_to_string(&text).unwrap(), "old(5)\n");
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_apply_files_saves_like_the_editor() {
+        let dir = std::env::temp_dir().join(format!("structural_replace_save_{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        fs::write(dir.join(".editorconfig"), "root = true\n[*]\nend_of_line = crlf\ncharset = latin1\n").unwrap();
+        let first = dir.join("a.py");
+        let second = dir.join("b.py");
+        fs::write(&first, b"old('caf\xE9')\r\n").unwrap();
+        fs::write(&second, "old(1)\r\n").unwrap();
+
+        let pattern = StructuralPattern::Template("old($x)".to_string());
+        let previews = preview_files(&[&first, &second], &pattern, "new($x)").unwrap();
+        assert_eq!(previews[0].new_content, "new('café')\r\n");
+
+        // Nothing is written once a file changed since the preview
+        fs::write(&second, "old(2)\r\n").unwrap();
+        assert!(apply_files(&previews).is_err());
+        assert_eq!(fs::read(&first).unwrap(), b"old('caf\xE9')\r\n");
+
+        let previews = preview_files(&[&first, &second], &pattern, "new($x)").unwrap();
+        assert_eq!(apply_files(&previews).unwrap(), 2);
+        assert_eq!(fs::read(&first).unwrap(), b"new('caf\xE9')\r\n");
+        assert_eq!(fs::read(&second).unwrap(), b"new(2)\r\n");
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
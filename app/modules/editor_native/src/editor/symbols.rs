@@ -0,0 +1,455 @@
+use std::collections::HashMap;
+use std::sync::OnceLock;
+
+use ropey::Rope;
+use serde::Serialize;
+use tree_sitter::{Query, Tree};
+
+use crate::editor::lsp_types::LspRange;
+use crate::editor::syntax_query::SyntaxQuery;
+use crate::editor::LanguageId;
+
+const RUST_TAGS: &str = include_str!("queries/rust/tags.scm");
+const JAVASCRIPT_TAGS: &str = include_str!("queries/javascript/tags.scm");
+const TYPESCRIPT_TAGS: &str = include_str!("queries/typescript/tags.scm");
+const PYTHON_TAGS: &str = include_str!("queries/python/tags.scm");
+const JAVA_TAGS: &str = include_str!("queries/java/tags.scm");
+const GO_TAGS: &str = include_str!("queries/go/tags.scm");
+
+/// Prefix of the capture holding the whole symbol node.
+const DEFINITION_CAPTURE_PREFIX: &str = "definition.";
+
+/// Symbol kind (numeric values match LSP `SymbolKind`).
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum SymbolKind {
+    Module = 2,
+    Class = 5,
+    Method = 6,
+    Field = 8,
+    Constructor = 9,
+    Enum = 10,
+    Interface = 11,
+    Function = 12,
+    Variable = 13,
+    Constant = 14,
+    Object = 19,
+    EnumMember = 22,
+    Struct = 23,
+    TypeParameter = 26,
+}
+
+impl Serialize for SymbolKind {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_u8(*self as u8)
+    }
+}
+
+impl SymbolKind {
+    /// Maps a `@definition.<tag>` capture suffix to a symbol kind.
+    pub fn from_tag(tag: &str) -> Option<Self> {
+        Some(match tag {
+            "module" => Self::Module,
+            "class" => Self::Class,
+            "method" => Self::Method,
+            "field" => Self::Field,
+            "constructor" => Self::Constructor,
+            "enum" => Self::Enum,
+            "enum_member" => Self::EnumMember,
+            "interface" => Self::Interface,
+            "function" | "macro" => Self::Function,
+            "variable" => Self::Variable,
+            "constant" => Self::Constant,
+            "impl" => Self::Object,
+            "struct" => Self::Struct,
+            "type" => Self::TypeParameter,
+            _ => return None,
+        })
+    }
+
+    /// Checks if functions declared inside this kind are methods.
+    fn is_container_type(&self) -> bool {
+        matches!(
+            self,
+            Self::Class | Self::Struct | Self::Interface | Self::Object | Self::Enum
+        )
+    }
+}
+
+/// Hierarchical document symbol (LSP `DocumentSymbol` shape).
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DocumentSymbol {
+    pub name: String,
+
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub detail: Option<String>,
+
+    pub kind: SymbolKind,
+
+    /// Range of the whole definition
+    pub range: LspRange,
+
+    /// Range of the symbol name
+    pub selection_range: LspRange,
+
+    pub children: Vec<DocumentSymbol>,
+}
+
+/// Flat symbol collected from a query match.
+struct RawSymbol {
+    start_byte: usize,
+    end_byte: usize,
+    pattern_index: usize,
+    symbol: DocumentSymbol,
+}
+
+/// Gets the compiled tags query for a language.
+///
+/// Queries are compiled once and shared between editors.
+///
+/// Returns: Query, or None if the language has no grammar
+pub fn tags_query(language: &LanguageId) -> Option<&'static Query> {
+    static QUERIES: OnceLock<HashMap<LanguageId, Query>> = OnceLock::new();
+
+    let queries = QUERIES.get_or_init(|| {
+        let sources = [
+            (LanguageId::Rust, RUST_TAGS),
+            (LanguageId::JavaScript, JAVASCRIPT_TAGS),
+            (LanguageId::TypeScript, TYPESCRIPT_TAGS),
+            (LanguageId::Python, PYTHON_TAGS),
+            (LanguageId::Java, JAVA_TAGS),
+            (LanguageId::Go, GO_TAGS),
+        ];
+
+        sources
+            .into_iter()
+            .filter_map(|(language, source)| {
+                let ts_language = language.tree_sitter_language()?;
+                let query = Query::new(ts_language, source)
+                    .unwrap_or_else(|e| panic!("invalid bundled tags query for {:?}: {}", language, e));
+                Some((language, query))
+            })
+            .collect()
+    });
+
+    queries.get(language)
+}
+
+/// Builds the document symbol outline for a syntax tree.
+///
+/// Symbols are nested by containment: a definition inside another one
+/// becomes its child, and functions inside types are reported as methods.
+///
+/// Parameters:
+/// - `tree`: Current syntax tree
+/// - `rope`: Document the tree was parsed from
+/// - `language`: Document language
+///
+/// Returns: Top-level symbols in document order
+pub fn document_symbols(tree: &Tree, rope: &Rope, language: &LanguageId) -> Vec<DocumentSymbol> {
+    document_symbols_layered(rope, &[(tree, language)])
+}
+
+/// Builds one outline from several syntax layers.
+///
+/// Used with language injections: symbols of embedded code (e.g. a
+/// `<script>` in HTML) are nested under the enclosing host symbols.
+///
+/// Parameters:
+/// - `rope`: Document
+/// - `layers`: Trees with their languages (root first)
+///
+/// Returns: Top-level symbols in document order
+pub fn document_symbols_layered(rope: &Rope, layers: &[(&Tree, &LanguageId)]) -> Vec<DocumentSymbol> {
+    let source = rope.to_string();
+
+    // Collect one symbol per definition node (first matching pattern wins)
+    let mut by_node: HashMap<(usize, usize), RawSymbol> = HashMap::new();
+    for (layer, (tree, language)) in layers.iter().enumerate() {
+        collect_symbols(tree, language, rope, &source, layer, &mut by_node);
+    }
+
+    let mut symbols: Vec<RawSymbol> = by_node.into_values().collect();
+    symbols.sort_by(|a, b| a.start_byte.cmp(&b.start_byte).then(b.end_byte.cmp(&a.end_byte)));
+
+    build_hierarchy(symbols)
+}
+
+/// Helper: Collects the definitions of one layer, keyed by (layer, node id).
+fn collect_symbols(
+    tree: &Tree,
+    language: &LanguageId,
+    rope: &Rope,
+    source: &str,
+    layer: usize,
+    by_node: &mut HashMap<(usize, usize), RawSymbol>,
+) {
+    let Some(query) = tags_query(language) else {
+        return;
+    };
+    let syntax = SyntaxQuery::new(tree, source);
+
+    for m in syntax.matches(query) {
+        let Some((node, kind)) = m.captures.iter().find_map(|(node, name)| {
+            let tag = name.strip_prefix(DEFINITION_CAPTURE_PREFIX)?;
+            Some((*node, SymbolKind::from_tag(tag)?))
+        }) else {
+            continue;
+        };
+        let Some(name_node) = m.capture("name") else {
+            continue;
+        };
+
+        if by_node
+            .get(&(layer, node.id()))
+            .is_some_and(|existing| existing.pattern_index <= m.pattern_index)
+        {
+            continue;
+        }
+
+        let name = collapse_whitespace(syntax.node_text(name_node));
+        let detail = m
+            .capture("detail")
+            .map(|detail| collapse_whitespace(syntax.node_text(detail)));
+
+        // Impl blocks are named after the trait and type
+        let (name, detail) = match (kind, detail) {
+            (SymbolKind::Object, Some(trait_name)) => (format!("impl {} for {}", trait_name, name), None),
+            (SymbolKind::Object, None) => (format!("impl {}", name), None),
+            (_, detail) => (name, detail),
+        };
+
+        by_node.insert(
+            (layer, node.id()),
+            RawSymbol {
+                start_byte: node.start_byte(),
+                end_byte: node.end_byte(),
+                pattern_index: m.pattern_index,
+                symbol: DocumentSymbol {
+                    name,
+                    detail,
+                    kind,
+                    range: LspRange::from_node(rope, node),
+                    selection_range: LspRange::from_node(rope, name_node),
+                    children: Vec::new(),
+                },
+            },
+        );
+    }
+}
+
+/// Helper: Nests symbols (sorted by start, outermost first) by containment.
+fn build_hierarchy(symbols: Vec<RawSymbol>) -> Vec<DocumentSymbol> {
+    let mut roots = Vec::new();
+    let mut stack: Vec<RawSymbol> = Vec::new();
+
+    for mut raw in symbols {
+        while stack.last().is_some_and(|top| raw.end_byte > top.end_byte) {
+            close_symbol(&mut stack, &mut roots);
+        }
+
+        if let Some(parent) = stack.last() {
+            if raw.symbol.kind == SymbolKind::Function && parent.symbol.kind.is_container_type() {
+                raw.symbol.kind = SymbolKind::Method;
+            }
+        }
+        stack.push(raw);
+    }
+
+    while !stack.is_empty() {
+        close_symbol(&mut stack, &mut roots);
+    }
+
+    roots
+}
+
+/// Helper: Pops the innermost open symbol into its parent (or the roots).
+fn close_symbol(stack: &mut Vec<RawSymbol>, roots: &mut Vec<DocumentSymbol>) {
+    if let Some(done) = stack.pop() {
+        match stack.last_mut() {
+            Some(parent) => parent.symbol.children.push(done.symbol),
+            None => roots.push(done.symbol),
+        }
+    }
+}
+
+/// Helper: Joins multi-line text into a single line.
+fn collapse_whitespace(text: &str) -> String {
+    text.split_whitespace().collect::<Vec<_>>().join(" ")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tree_sitter::Parser;
+
+    fn symbols_for(source: &str, language: LanguageId) -> Vec<DocumentSymbol> {
+        let mut parser = Parser::new();
+        parser.set_language(language.tree_sitter_language().unwrap()).unwrap();
+        let tree = parser.parse(source, None).unwrap();
+        document_symbols(&tree, &Rope::from_str(source), &language)
+    }
+
+    fn outline(symbols: &[DocumentSymbol]) -> Vec<(String, SymbolKind, usize)> {
+        let mut out = Vec::new();
+        fn walk(symbols: &[DocumentSymbol], depth: usize, out: &mut Vec<(String, SymbolKind, usize)>) {
+            for symbol in symbols {
+                out.push((symbol.name.clone(), symbol.kind, depth));
+                walk(&symbol.children, depth + 1, out);
+            }
+        }
+        walk(symbols, 0, &mut out);
+        out
+    }
+
+    fn entry(name: &str, kind: SymbolKind, depth: usize) -> (String, SymbolKind, usize) {
+        (name.to_string(), kind, depth)
+    }
+
+    #[test]
+    fn test_all_tags_queries_compile() {
+        for language in [
+            LanguageId::Rust,
+            LanguageId::JavaScript,
+            LanguageId::TypeScript,
+            LanguageId::Python,
+            LanguageId::Java,
+            LanguageId::Go,
+        ] {
+            assert!(tags_query(&language).is_some(), "{:?}", language);
+        }
+        assert!(tags_query(&LanguageId::PlainText).is_none());
+    }
+
+    #[test]
+    fn test_rust_symbols() {
+        let source = "\
+struct Point {
+    x: i32,
+}
+
+enum Shape { Circle, Square }
+
+impl Display for Point {
+    fn fmt(&self) {}
+}
+
+mod geometry {
+    pub fn area() -> f64 { 0.0 }
+}
+
+const ORIGIN: i32 = 0;
+";
+        let symbols = symbols_for(source, LanguageId::Rust);
+        assert_eq!(
+            outline(&symbols),
+            vec![
+                entry("Point", SymbolKind::Struct, 0),
+                entry("x", SymbolKind::Field, 1),
+                entry("Shape", SymbolKind::Enum, 0),
+                entry("Circle", SymbolKind::EnumMember, 1),
+                entry("Square", SymbolKind::EnumMember, 1),
+                entry("impl Display for Point", SymbolKind::Object, 0),
+                entry("fmt", SymbolKind::Method, 1),
+                entry("geometry", SymbolKind::Module, 0),
+                entry("area", SymbolKind::Function, 1),
+                entry("ORIGIN", SymbolKind::Constant, 0),
+            ]
+        );
+
+        let fmt = &symbols[2].children[0];
+        assert_eq!(fmt.detail.as_deref(), Some("(&self)"));
+        assert_eq!(fmt.selection_range.start.line, 7);
+        assert_eq!(fmt.selection_range.start.character, 7);
+        assert_eq!(fmt.range.start.character, 4);
+    }
+
+    #[test]
+    fn test_python_symbols() {
+        let source = "VERSION = 1\n\nclass Greeter:\n    def greet(self, name):\n        pass\n\ndef main():\n    pass\n";
+        assert_eq!(
+            outline(&symbols_for(source, LanguageId::Python)),
+            vec![
+                entry("VERSION", SymbolKind::Variable, 0),
+                entry("Greeter", SymbolKind::Class, 0),
+                entry("greet", SymbolKind::Method, 1),
+                entry("main", SymbolKind::Function, 0),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_javascript_symbols() {
+        let source = "class Counter {\n  increment() {}\n}\nconst handler = () => {};\nconst limit = 3;\nfunction run() {}\n";
+        assert_eq!(
+            outline(&symbols_for(source, LanguageId::JavaScript)),
+            vec![
+                entry("Counter", SymbolKind::Class, 0),
+                entry("increment", SymbolKind::Method, 1),
+                entry("handler", SymbolKind::Function, 0),
+                entry("limit", SymbolKind::Variable, 0),
+                entry("run", SymbolKind::Function, 0),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_typescript_symbols() {
+        let source = "interface User {\n  name: string;\n}\nenum Color { Red, Green = 2 }\nclass Store {\n  items: number;\n  load(): void {}\n}\n";
+        assert_eq!(
+            outline(&symbols_for(source, LanguageId::TypeScript)),
+            vec![
+                entry("User", SymbolKind::Interface, 0),
+                entry("name", SymbolKind::Field, 1),
+                entry("Color", SymbolKind::Enum, 0),
+                entry("Red", SymbolKind::EnumMember, 1),
+                entry("Green", SymbolKind::EnumMember, 1),
+                entry("Store", SymbolKind::Class, 0),
+                entry("items", SymbolKind::Field, 1),
+                entry("load", SymbolKind::Method, 1),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_java_symbols() {
+        let source = "class App {\n  int count;\n  App() {}\n  void run() {}\n}\n";
+        assert_eq!(
+            outline(&symbols_for(source, LanguageId::Java)),
+            vec![
+                entry("App", SymbolKind::Class, 0),
+                entry("count", SymbolKind::Field, 1),
+                entry("App", SymbolKind::Constructor, 1),
+                entry("run", SymbolKind::Method, 1),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_go_symbols() {
+        let source = "package main\n\ntype Server struct {\n\tport int\n}\n\nfunc (s *Server) Start() {}\n\nfunc main() {}\n";
+        let symbols = symbols_for(source, LanguageId::Go);
+        assert_eq!(
+            outline(&symbols),
+            vec![
+                entry("Server", SymbolKind::Struct, 0),
+                entry("port", SymbolKind::Field, 1),
+                entry("Start", SymbolKind::Method, 0),
+                entry("main", SymbolKind::Function, 0),
+            ]
+        );
+        assert_eq!(symbols[1].detail.as_deref(), Some("(s *Server)"));
+    }
+
+    #[test]
+    fn test_symbols_serialize_lsp_shape() {
+        let symbols = symbols_for("fn main() {}\n", LanguageId::Rust);
+        let json = serde_json::to_value(&symbols).unwrap();
+
+        assert_eq!(json[0]["name"], "main");
+        assert_eq!(json[0]["kind"], 12);
+        assert_eq!(json[0]["selectionRange"]["start"]["character"], 3);
+        assert_eq!(json[0]["range"]["end"]["character"], 12);
+        assert!(json[0]["children"].as_array().unwrap().is_empty());
+    }
+}
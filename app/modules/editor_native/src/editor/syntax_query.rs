@@ -98,6 +98,32 @@ impl<'a> SyntaxQuery<'a> {
         Ok(results)
     }
 
+    /// Runs a compiled query and groups captures by match.
+    ///
+    /// Unlike `find_by_pattern`, captures belonging to the same match stay
+    /// together and text predicates (`#eq?`, `#match?`) are applied.
+    ///
+    /// Parameters:
+    /// - `query`: Compiled tree-sitter query
+    ///
+    /// Returns: One `QueryMatchResult` per match, in document order
+    pub fn matches(&self, query: &Query) -> Vec<QueryMatchResult<'a>> {
+        let mut cursor = QueryCursor::new();
+        cursor
+            .matches(query, self.root(), self.source.as_bytes())
+            .map(|m| QueryMatchResult {
+                pattern_index: m.pattern_index,
+                captures: m
+                    .captures
+                    .iter()
+                    .map(|capture| {
+                        (capture.node, query.capture_names()[capture.index as usize].to_string())
+                    })
+                    .collect(),
+            })
+            .collect()
+    }
+
     /// Gets text content of a node.
     ///
     /// Parameters:
@@ -256,16 +282,40 @@ impl<'a> SyntaxQuery<'a> {
     }
 }
 
+/// Captures of a single query match.
+#[derive(Debug, Clone)]
+pub struct QueryMatchResult<'a> {
+    /// Index of the pattern that matched
+    pub pattern_index: usize,
+
+    /// Captured nodes with their capture names
+    pub captures: Vec<(Node<'a>, String)>,
+}
+
+impl<'a> QueryMatchResult<'a> {
+    /// Gets the first node captured under a name.
+    pub fn capture(&self, name: &str) -> Option<Node<'a>> {
+        self.captures
+            .iter()
+            .find(|(_, capture_name)| capture_name == name)
+            .map(|(node, _)| *node)
+    }
+}
+
 /// Query error types.
 #[derive(Debug, Clone)]
 pub enum QueryError {
     InvalidPattern(String),
+    InvalidTemplate(String),
+    UnsupportedLanguage(String),
 }
 
 impl std::fmt::Display for QueryError {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         match self {
             QueryError::InvalidPattern(msg) => write!(f, "Invalid query pattern: {}", msg),
+            QueryError::InvalidTemplate(msg) => write!(f, "Invalid template: {}", msg),
+            QueryError::UnsupportedLanguage(lang) => write!(f, "No grammar for language: {}", lang),
         }
     }
 }
@@ -292,6 +342,24 @@ mod tests {
         assert_eq!(functions.len(), 2);
     }
 
+    #[test]
+    fn test_matches_groups_captures_and_predicates() {
+        let source = "def foo():\n    pass\n\ndef bar():\n    pass";
+        let tree = parse_python(source);
+        let query = SyntaxQuery::new(&tree, source);
+
+        let compiled = Query::new(
+            tree_sitter_python::language(),
+            "((function_definition name: (identifier) @name) @def (#eq? @name \"bar\"))",
+        )
+        .unwrap();
+
+        let matches = query.matches(&compiled);
+        assert_eq!(matches.len(), 1);
+        assert_eq!(query.node_text(matches[0].capture("name").unwrap()), "bar");
+        assert_eq!(matches[0].capture("def").unwrap().kind(), "function_definition");
+    }
+
     #[test]
     fn test_node_at_position() {
         let source = "def foo():\n    pass";
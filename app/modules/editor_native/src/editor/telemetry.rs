@@ -0,0 +1,361 @@
+//! Performance telemetry
+//!
+//! Process-wide latency histograms for every instrumented operation
+//! (editing, parsing, highlighting, search, layout and the FFI boundary).
+//! Each measurement also runs inside a `tracing` span named
+//! `editor_operation`, so a subscriber can see the same timings.
+//!
+//! Histograms are HDR (microsecond resolution, 3 significant digits), so
+//! tail latencies stay exact no matter how many samples are recorded.
+
+use std::collections::BTreeMap;
+use std::sync::{Mutex, OnceLock, PoisonError};
+use std::time::{Duration, Instant};
+
+use hdrhistogram::Histogram;
+use serde::Serialize;
+use tree_sitter::Tree;
+
+use crate::editor::Editor;
+
+/// Longest latency a histogram tracks exactly (longer ones saturate)
+const MAX_TRACKED_MICROS: u64 = 60 * 1_000_000;
+
+/// Estimated heap size of one syntax tree node (tree-sitter subtree plus
+/// its slot in the parent's child array)
+const TREE_NODE_BYTES: usize = 40;
+
+/// Instrumented operation.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub enum Operation {
+    Insert,
+    Delete,
+    Undo,
+    Redo,
+    Parse,
+    Highlight,
+    Search,
+    Layout,
+    Render,
+
+    /// Whole FFI call (work included)
+    FfiCall,
+
+    /// Time an FFI call waited for its handle's lock (another isolate)
+    FfiLockWait,
+}
+
+impl Operation {
+    /// Gets the operation name (span field and snapshot key)
+    pub fn name(&self) -> &'static str {
+        match self {
+            Operation::Insert => "insert",
+            Operation::Delete => "delete",
+            Operation::Undo => "undo",
+            Operation::Redo => "redo",
+            Operation::Parse => "parse",
+            Operation::Highlight => "highlight",
+            Operation::Search => "search",
+            Operation::Layout => "layout",
+            Operation::Render => "render",
+            Operation::FfiCall => "ffiCall",
+            Operation::FfiLockWait => "ffiLockWait",
+        }
+    }
+}
+
+/// HDR latency histogram (microseconds).
+#[derive(Debug, Clone)]
+pub struct LatencyHistogram {
+    histogram: Histogram<u64>,
+}
+
+impl LatencyHistogram {
+    pub fn new() -> Self {
+        Self {
+            histogram: Histogram::new_with_bounds(1, MAX_TRACKED_MICROS, 3)
+                .expect("valid histogram bounds"),
+        }
+    }
+
+    /// Records a latency (saturating at one minute)
+    pub fn record(&mut self, duration: Duration) {
+        let micros = duration.as_micros().clamp(1, MAX_TRACKED_MICROS as u128) as u64;
+        self.histogram.saturating_record(micros);
+    }
+
+    /// Number of recorded samples
+    pub fn count(&self) -> u64 {
+        self.histogram.len()
+    }
+
+    /// Latency at a quantile (0.0 - 1.0)
+    pub fn quantile(&self, quantile: f64) -> Duration {
+        Duration::from_micros(self.histogram.value_at_quantile(quantile))
+    }
+
+    pub fn max(&self) -> Duration {
+        Duration::from_micros(self.histogram.max())
+    }
+
+    pub fn mean(&self) -> Duration {
+        Duration::from_secs_f64(self.histogram.mean() / 1_000_000.0)
+    }
+
+    pub fn reset(&mut self) {
+        self.histogram.reset();
+    }
+
+    /// Gets the summary exported in snapshots
+    pub fn summary(&self) -> LatencySummary {
+        let micros = |d: Duration| d.as_micros() as u64;
+        LatencySummary {
+            count: self.count(),
+            mean_us: micros(self.mean()),
+            p50_us: micros(self.quantile(0.5)),
+            p95_us: micros(self.quantile(0.95)),
+            p99_us: micros(self.quantile(0.99)),
+            p999_us: micros(self.quantile(0.999)),
+            max_us: micros(self.max()),
+        }
+    }
+}
+
+impl Default for LatencyHistogram {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Latency percentiles of one operation.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct LatencySummary {
+    pub count: u64,
+    pub mean_us: u64,
+    pub p50_us: u64,
+    pub p95_us: u64,
+    pub p99_us: u64,
+    pub p999_us: u64,
+    pub max_us: u64,
+}
+
+/// Memory held by an editor (estimates for syntax trees).
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct MemoryUsage {
+    /// Document size
+    pub text_bytes: usize,
+
+    /// Rope buffer capacity (text plus slack in its chunks)
+    pub rope_capacity_bytes: usize,
+
+    pub syntax_tree_nodes: usize,
+    pub injection_tree_nodes: usize,
+
+    /// Estimated heap size of the root and injection trees
+    pub tree_bytes: usize,
+
+    /// Text held by the undo and redo stacks
+    pub undo_bytes: usize,
+}
+
+/// Exported telemetry.
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TelemetrySnapshot {
+    /// Operations with at least one sample, by name
+    pub operations: BTreeMap<&'static str, LatencySummary>,
+
+    /// Memory of the editor the snapshot was taken for, if any
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub memory: Option<MemoryUsage>,
+}
+
+/// Process-wide histograms
+fn histograms() -> &'static Mutex<BTreeMap<Operation, LatencyHistogram>> {
+    static HISTOGRAMS: OnceLock<Mutex<BTreeMap<Operation, LatencyHistogram>>> = OnceLock::new();
+    HISTOGRAMS.get_or_init(|| Mutex::new(BTreeMap::new()))
+}
+
+/// Records a latency for an operation
+pub fn record(operation: Operation, duration: Duration) {
+    histograms()
+        .lock()
+        .unwrap_or_else(PoisonError::into_inner)
+        .entry(operation)
+        .or_default()
+        .record(duration);
+}
+
+/// Measures an operation until the span is dropped
+///
+/// Returns: Guard that records the latency (and closes the `tracing`
+/// span) on drop
+pub fn span(operation: Operation) -> OperationSpan {
+    OperationSpan {
+        operation,
+        start: Instant::now(),
+        _span: tracing::trace_span!("editor_operation", operation = operation.name()).entered(),
+    }
+}
+
+/// Measures `f` as one operation
+pub fn measure<T>(operation: Operation, f: impl FnOnce() -> T) -> T {
+    let _span = span(operation);
+    f()
+}
+
+/// Gets the histogram summaries (plus an editor's memory usage)
+pub fn snapshot(editor: Option<&Editor>) -> TelemetrySnapshot {
+    let operations = histograms()
+        .lock()
+        .unwrap_or_else(PoisonError::into_inner)
+        .iter()
+        .filter(|(_, histogram)| histogram.count() > 0)
+        .map(|(operation, histogram)| (operation.name(), histogram.summary()))
+        .collect();
+
+    TelemetrySnapshot {
+        operations,
+        memory: editor.map(Editor::memory_usage),
+    }
+}
+
+/// Clears every histogram
+pub fn reset() {
+    histograms().lock().unwrap_or_else(PoisonError::into_inner).clear();
+}
+
+/// In-flight measurement (see `span`).
+pub struct OperationSpan {
+    operation: Operation,
+    start: Instant,
+    _span: tracing::span::EnteredSpan,
+}
+
+impl OperationSpan {
+    /// Time since the span started
+    pub fn elapsed(&self) -> Duration {
+        self.start.elapsed()
+    }
+}
+
+impl Drop for OperationSpan {
+    fn drop(&mut self) {
+        record(self.operation, self.start.elapsed());
+    }
+}
+
+/// Helper: Counts the nodes of a tree
+fn tree_node_count(tree: &Tree) -> usize {
+    let mut cursor = tree.walk();
+    let mut count = 1;
+    loop {
+        if cursor.goto_first_child() || cursor.goto_next_sibling() {
+            count += 1;
+            continue;
+        }
+        loop {
+            if !cursor.goto_parent() {
+                return count;
+            }
+            if cursor.goto_next_sibling() {
+                count += 1;
+                break;
+            }
+        }
+    }
+}
+
+impl Editor {
+    /// Measures the memory held by the document, its trees and history
+    ///
+    /// Walks every syntax tree, so it is O(nodes): meant for telemetry
+    /// snapshots, not for every frame.
+    pub fn memory_usage(&self) -> MemoryUsage {
+        let syntax_tree_nodes = self.syntax_tree.as_ref().map_or(0, tree_node_count);
+        let injection_tree_nodes = self
+            .injection_layers()
+            .iter()
+            .map(|layer| tree_node_count(&layer.tree))
+            .sum();
+        let undo_bytes = self
+            .undo_stack
+            .iter()
+            .chain(&self.redo_stack)
+            .flat_map(|transaction| &transaction.edits)
+            .map(|edit| edit.deleted_text.len() + edit.inserted_text.len())
+            .sum();
+
+        MemoryUsage {
+            text_bytes: self.rope.len_bytes(),
+            rope_capacity_bytes: self.rope.capacity(),
+            syntax_tree_nodes,
+            injection_tree_nodes,
+            tree_bytes: (syntax_tree_nodes + injection_tree_nodes) * TREE_NODE_BYTES,
+            undo_bytes,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::editor::LanguageId;
+
+    #[test]
+    fn test_histogram_percentiles() {
+        let mut histogram = LatencyHistogram::new();
+        for micros in 1..=1000 {
+            histogram.record(Duration::from_micros(micros));
+        }
+
+        let summary = histogram.summary();
+        assert_eq!(summary.count, 1000);
+        assert_eq!(summary.p50_us, 500);
+        assert_eq!(summary.p99_us, 990);
+        assert_eq!(summary.max_us, 1000);
+
+        // Beyond the tracked range saturates instead of failing
+        histogram.record(Duration::from_secs(3600));
+        assert_eq!(histogram.max().as_secs(), 60);
+
+        histogram.reset();
+        assert_eq!(histogram.count(), 0);
+    }
+
+    #[test]
+    fn test_editor_operations_are_recorded() {
+        let mut editor = Editor::with_content("fn main() {}\n", LanguageId::Rust).unwrap();
+        editor.insert_text("// x\n").unwrap();
+        editor.undo().unwrap();
+        editor.highlights(0, 1);
+
+        // Histograms are process-wide (other tests record too)
+        let snapshot = snapshot(Some(&editor));
+        for name in ["insert", "undo", "parse", "highlight"] {
+            assert!(snapshot.operations[name].count >= 1, "{name} not recorded");
+        }
+
+        let memory = snapshot.memory.as_ref().unwrap();
+        assert_eq!(memory.text_bytes, 13);
+        assert!(memory.rope_capacity_bytes >= 13);
+        assert!(memory.syntax_tree_nodes > 5);
+        assert_eq!(memory.undo_bytes, 5);
+
+        let json = serde_json::to_value(&snapshot).unwrap();
+        assert!(json["operations"]["insert"]["p99Us"].is_u64());
+        assert!(json["memory"]["treeBytes"].is_u64());
+    }
+
+    #[test]
+    fn test_tree_node_count() {
+        let editor = Editor::with_content("fn a() {}", LanguageId::Rust).unwrap();
+        let tree = editor.syntax_tree().unwrap();
+
+        // source_file > function_item > (fn, identifier, parameters > ( ), block > { })
+        assert_eq!(tree_node_count(tree), 10);
+    }
+}
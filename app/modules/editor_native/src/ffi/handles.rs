@@ -0,0 +1,607 @@
+//! Handle registry for the FFI boundary
+//!
+//! Objects handed to the host are never exposed as real pointers. Each one
+//! lives in a registry slot and the host gets an opaque, generation-checked
+//! id: freeing a handle bumps the slot's generation, so a double free or a
+//! call with a stale handle is detected instead of being undefined
+//! behaviour. Every object sits behind a mutex, so handles can be shared
+//! between Dart isolates (calls on the same object are serialized).
+//!
+//! Every `extern "C"` function runs inside `ffi_guard`, which turns a panic
+//! into an error value and records the message for `editor_last_error()`.
+
+use std::cell::{Cell, RefCell};
+use std::collections::BTreeMap;
+use std::ffi::CString;
+use std::os::raw::c_char;
+use std::panic::{self, AssertUnwindSafe};
+use std::ptr;
+use std::sync::{Arc, Mutex, MutexGuard, PoisonError, RwLock};
+use std::time::Instant;
+
+use serde::Serialize;
+
+use crate::editor::{Editor, SharedSpellChecker, SpellChecker};
+use crate::editor::telemetry::{self, Operation};
+use crate::renderer::TextRenderer;
+use crate::editor::LargeFileView;
+use crate::keymap::Keymap;
+use crate::workspace::recovery::RecoveryJournal;
+use crate::workspace::{BufferId, Workspace};
+
+use super::ResultCode;
+
+/// Bits of a handle holding the slot index (the rest is the generation).
+/// Half of a pointer, so handles fit pointer-sized values on every target.
+const INDEX_BITS: u32 = usize::BITS / 2;
+const INDEX_MASK: usize = (1 << INDEX_BITS) - 1;
+
+/// Opaque types behind the handle pointers (never dereferenced)
+pub enum OpaqueEditor {}
+pub enum OpaqueRenderer {}
+pub enum OpaqueLargeFile {}
+pub enum OpaqueWorkspace {}
+pub enum OpaqueRecoveryJournal {}
+pub enum OpaqueSpellChecker {}
+pub enum OpaqueKeymap {}
+
+thread_local! {
+    /// Message of the last failure on this thread (see `editor_last_error()`)
+    static LAST_ERROR: RefCell<Option<String>> = const { RefCell::new(None) };
+}
+
+/// Records the last error message of the calling thread
+pub(crate) fn set_last_error(message: impl Into<String>) {
+    LAST_ERROR.with(|last| *last.borrow_mut() = Some(message.into()));
+}
+
+/// Takes the last error message of the calling thread
+pub(crate) fn take_last_error() -> Option<String> {
+    LAST_ERROR.with(|last| last.borrow_mut().take())
+}
+
+/// Value an FFI function returns when it cannot run.
+pub(crate) trait FfiReturn: Copy {
+    /// Value returned after a panic
+    fn panicked() -> Self;
+
+    /// Value returned for a freed or unknown handle (defaults to the
+    /// function's null-handle value)
+    fn stale_handle(on_invalid: Self) -> Self {
+        on_invalid
+    }
+}
+
+impl FfiReturn for ResultCode {
+    fn panicked() -> Self {
+        ResultCode::ErrorPanic
+    }
+
+    fn stale_handle(_on_invalid: Self) -> Self {
+        ResultCode::ErrorInvalidHandle
+    }
+}
+
+impl<T> FfiReturn for *mut T {
+    fn panicked() -> Self {
+        ptr::null_mut()
+    }
+}
+
+impl FfiReturn for i32 {
+    fn panicked() -> Self {
+        -1
+    }
+}
+
+impl FfiReturn for i64 {
+    fn panicked() -> Self {
+        -1
+    }
+}
+
+impl FfiReturn for usize {
+    fn panicked() -> Self {
+        0
+    }
+}
+
+impl FfiReturn for () {
+    fn panicked() -> Self {}
+}
+
+thread_local! {
+    /// Nesting depth of `ffi_guard` on this thread
+    static FFI_DEPTH: Cell<usize> = const { Cell::new(0) };
+}
+
+/// Runs the body of an FFI function, turning a panic into an error value
+///
+/// The outermost call on a thread is recorded as `Operation::FfiCall`.
+pub(crate) fn ffi_guard<R: FfiReturn>(f: impl FnOnce() -> R) -> R {
+    let depth = FFI_DEPTH.with(|depth| depth.replace(depth.get() + 1));
+    let _span = (depth == 0).then(|| telemetry::span(Operation::FfiCall));
+    let result = panic::catch_unwind(AssertUnwindSafe(f));
+    FFI_DEPTH.with(|depth| depth.set(depth.get() - 1));
+
+    match result {
+        Ok(result) => result,
+        Err(payload) => {
+            let message = payload
+                .downcast_ref::<&str>()
+                .map(|s| s.to_string())
+                .or_else(|| payload.downcast_ref::<String>().cloned())
+                .unwrap_or_else(|| "unknown panic".to_string());
+            set_last_error(format!("panic: {}", message));
+            R::panicked()
+        }
+    }
+}
+
+/// Hands a string to the host (freed with `editor_free_string()`)
+///
+/// Returns: The C string, or null if the text contains a NUL byte
+pub(crate) fn c_string(text: impl Into<Vec<u8>>) -> *mut c_char {
+    CString::new(text).map_or(ptr::null_mut(), CString::into_raw)
+}
+
+/// Serializes a value as a JSON C string for the host (see `c_string`)
+///
+/// Returns: The C string, or null if serialization fails
+pub(crate) fn json_c_string<T: Serialize + ?Sized>(value: &T) -> *mut c_char {
+    serde_json::to_string(value).map_or(ptr::null_mut(), c_string)
+}
+
+/// Locks a mutex, recovering it if a caught panic poisoned it
+pub(crate) fn lock<T>(mutex: &Mutex<T>) -> MutexGuard<'_, T> {
+    mutex.lock().unwrap_or_else(PoisonError::into_inner)
+}
+
+/// Locks a handle's value, recording the wait as `Operation::FfiLockWait`
+fn lock_value<T>(mutex: &Mutex<T>) -> MutexGuard<'_, T> {
+    let start = Instant::now();
+    let guard = lock(mutex);
+    telemetry::record(Operation::FfiLockWait, start.elapsed());
+    guard
+}
+
+/// Locks a handle's value like `lock_value`, repairing it first if a
+/// caught panic poisoned the mutex (the poison is then cleared)
+fn lock_repaired<T>(mutex: &Mutex<T>, repair: impl FnOnce(&mut T)) -> MutexGuard<'_, T> {
+    let start = Instant::now();
+    let guard = match mutex.lock() {
+        Ok(guard) => guard,
+        Err(poisoned) => {
+            let mut guard = poisoned.into_inner();
+            repair(&mut guard);
+            mutex.clear_poison();
+            guard
+        }
+    };
+    telemetry::record(Operation::FfiLockWait, start.elapsed());
+    guard
+}
+
+/// Helper: Locks an editor, repairing it after a panic
+fn lock_editor(editor: &Mutex<Editor>) -> MutexGuard<'_, Editor> {
+    lock_repaired(editor, Editor::recover_after_panic)
+}
+
+/// Helper: Locks a workspace, repairing its editors after a panic
+fn lock_workspace(workspace: &Mutex<Workspace>) -> MutexGuard<'_, Workspace> {
+    lock_repaired(workspace, Workspace::recover_after_panic)
+}
+
+/// Slot of a handle table.
+struct Slot<V> {
+    generation: usize,
+    value: Option<Arc<V>>,
+}
+
+/// Generation-checked table of live objects.
+pub(crate) struct HandleTable<V> {
+    slots: Vec<Slot<V>>,
+    free: Vec<usize>,
+}
+
+impl<V> HandleTable<V> {
+    pub(crate) const fn new() -> Self {
+        Self {
+            slots: Vec::new(),
+            free: Vec::new(),
+        }
+    }
+
+    /// Stores a value
+    ///
+    /// Returns: Handle (never 0), or None if the table is full
+    pub(crate) fn insert(&mut self, value: V) -> Option<usize> {
+        let index = match self.free.pop() {
+            Some(index) => index,
+            None => {
+                // The all-ones index is reserved so `index + 1` fits the mask
+                if self.slots.len() >= INDEX_MASK {
+                    return None;
+                }
+                self.slots.push(Slot { generation: 0, value: None });
+                self.slots.len() - 1
+            }
+        };
+
+        let slot = &mut self.slots[index];
+        slot.value = Some(Arc::new(value));
+        Some((slot.generation << INDEX_BITS) | (index + 1))
+    }
+
+    /// Gets the value of a live handle
+    pub(crate) fn get(&self, handle: usize) -> Option<Arc<V>> {
+        let index = self.index_of(handle)?;
+        self.slots[index].value.clone()
+    }
+
+    /// Removes a live handle (the slot's old handles become stale)
+    ///
+    /// Returns: The value, or None if the handle was not live
+    pub(crate) fn remove(&mut self, handle: usize) -> Option<Arc<V>> {
+        let index = self.index_of(handle)?;
+        let slot = &mut self.slots[index];
+        let value = slot.value.take()?;
+        slot.generation = (slot.generation + 1) & (usize::MAX >> INDEX_BITS);
+        self.free.push(index);
+        Some(value)
+    }
+
+    /// Helper: Slot index of a handle whose generation is current
+    fn index_of(&self, handle: usize) -> Option<usize> {
+        let index = (handle & INDEX_MASK).checked_sub(1)?;
+        let slot = self.slots.get(index)?;
+        (slot.value.is_some() && slot.generation == handle >> INDEX_BITS).then_some(index)
+    }
+}
+
+/// Named, thread-safe handle table.
+pub(crate) struct Registry<V> {
+    name: &'static str,
+    table: Mutex<HandleTable<V>>,
+}
+
+impl<V> Registry<V> {
+    const fn new(name: &'static str) -> Self {
+        Self {
+            name,
+            table: Mutex::new(HandleTable::new()),
+        }
+    }
+
+    /// Registers a value and returns its handle as an opaque pointer
+    pub(crate) fn insert<P>(&self, value: V) -> *mut P {
+        match lock(&self.table).insert(value) {
+            Some(handle) => handle as *mut P,
+            None => {
+                set_last_error(format!("too many live {} handles", self.name));
+                ptr::null_mut()
+            }
+        }
+    }
+
+    /// Gets the value of a handle (recording an error if it is stale)
+    pub(crate) fn get<P>(&self, handle: *mut P) -> Option<Arc<V>> {
+        let value = lock(&self.table).get(handle as usize);
+        if value.is_none() {
+            set_last_error(format!("invalid or freed {} handle", self.name));
+        }
+        value
+    }
+
+    /// Unregisters a handle
+    pub(crate) fn remove<P>(&self, handle: *mut P) -> Option<Arc<V>> {
+        lock(&self.table).remove(handle as usize)
+    }
+}
+
+/// Object behind an editor handle.
+// Entries are only ever stored behind an `Arc`, so the size gap is moot
+#[allow(clippy::large_enum_variant)]
+pub(crate) enum EditorEntry {
+    /// Standalone editor (`editor_new()`)
+    Owned(Mutex<Editor>),
+
+    /// Editor of a workspace buffer; valid while the buffer is open
+    Buffer {
+        workspace: Arc<Mutex<Workspace>>,
+        buffer: BufferId,
+    },
+}
+
+pub(crate) static EDITORS: Registry<EditorEntry> = Registry::new("editor");
+pub(crate) static RENDERERS: Registry<Mutex<TextRenderer>> = Registry::new("renderer");
+pub(crate) static LARGE_FILES: Registry<Mutex<LargeFileView>> = Registry::new("large file");
+pub(crate) static WORKSPACES: Registry<Mutex<Workspace>> = Registry::new("workspace");
+pub(crate) static RECOVERY_JOURNALS: Registry<Mutex<RecoveryJournal>> = Registry::new("recovery journal");
+/// Spell checkers are read-locked by every editor they are set on
+pub(crate) static SPELL_CHECKERS: Registry<RwLock<SpellChecker>> = Registry::new("spell checker");
+pub(crate) static KEYMAPS: Registry<Mutex<Keymap>> = Registry::new("keymap");
+
+/// Editor handles of workspace buffers, by (workspace handle, buffer),
+/// so asking twice for the same buffer returns the same handle
+static BUFFER_EDITORS: Mutex<BTreeMap<(usize, BufferId), usize>> = Mutex::new(BTreeMap::new());
+
+/// Helper: Runs `f` on the value of a handle inside `ffi_guard`
+fn with_entry<V, P, R: FfiReturn>(
+    registry: &Registry<V>,
+    handle: *mut P,
+    on_invalid: R,
+    f: impl FnOnce(&Arc<V>) -> R,
+) -> R {
+    ffi_guard(|| {
+        if handle.is_null() {
+            return on_invalid;
+        }
+        match registry.get(handle) {
+            Some(value) => f(&value),
+            None => R::stale_handle(on_invalid),
+        }
+    })
+}
+
+/// Runs `f` on a locked editor
+///
+/// An editor left half-updated by a panic in an earlier call is repaired
+/// first (see `Editor::recover_after_panic`).
+///
+/// Parameters:
+/// - `on_invalid`: Returned for a null handle (or a stale one, except for
+///   `ResultCode`, which reports `ErrorInvalidHandle`)
+pub(crate) fn with_editor<R: FfiReturn>(
+    handle: *mut OpaqueEditor,
+    on_invalid: R,
+    f: impl FnOnce(&mut Editor) -> R,
+) -> R {
+    with_entry(&EDITORS, handle, on_invalid, |entry| match &**entry {
+        EditorEntry::Owned(editor) => f(&mut lock_editor(editor)),
+        EditorEntry::Buffer { workspace, buffer } => {
+            let mut workspace = lock_workspace(workspace);
+            match workspace.buffer_mut(*buffer) {
+                Some(buffer) => f(buffer.editor_mut()),
+                None => {
+                    set_last_error("the editor's buffer was closed");
+                    R::stale_handle(on_invalid)
+                }
+            }
+        }
+    })
+}
+
+/// Runs `f` on a locked renderer
+pub(crate) fn with_renderer<R: FfiReturn>(
+    handle: *mut OpaqueRenderer,
+    on_invalid: R,
+    f: impl FnOnce(&mut TextRenderer) -> R,
+) -> R {
+    with_entry(&RENDERERS, handle, on_invalid, |renderer| f(&mut lock_value(renderer)))
+}
+
+/// Runs `f` on a locked large-file view
+pub(crate) fn with_large_file<R: FfiReturn>(
+    handle: *mut OpaqueLargeFile,
+    on_invalid: R,
+    f: impl FnOnce(&mut LargeFileView) -> R,
+) -> R {
+    with_entry(&LARGE_FILES, handle, on_invalid, |view| f(&mut lock_value(view)))
+}
+
+/// Runs `f` on a locked workspace
+pub(crate) fn with_workspace<R: FfiReturn>(
+    handle: *mut OpaqueWorkspace,
+    on_invalid: R,
+    f: impl FnOnce(&mut Workspace) -> R,
+) -> R {
+    with_entry(&WORKSPACES, handle, on_invalid, |workspace| f(&mut lock_workspace(workspace)))
+}
+
+/// Runs `f` on a locked recovery journal
+pub(crate) fn with_recovery_journal<R: FfiReturn>(
+    handle: *mut OpaqueRecoveryJournal,
+    on_invalid: R,
+    f: impl FnOnce(&mut RecoveryJournal) -> R,
+) -> R {
+    with_entry(&RECOVERY_JOURNALS, handle, on_invalid, |journal| f(&mut lock_value(journal)))
+}
+
+/// Runs `f` on a spell checker (shared, so `f` takes the lock it needs)
+pub(crate) fn with_spell_checker<R: FfiReturn>(
+    handle: *mut OpaqueSpellChecker,
+    on_invalid: R,
+    f: impl FnOnce(&SharedSpellChecker) -> R,
+) -> R {
+    with_entry(&SPELL_CHECKERS, handle, on_invalid, f)
+}
+
+/// Runs `f` on a locked keymap
+pub(crate) fn with_keymap<R: FfiReturn>(
+    handle: *mut OpaqueKeymap,
+    on_invalid: R,
+    f: impl FnOnce(&mut Keymap) -> R,
+) -> R {
+    with_entry(&KEYMAPS, handle, on_invalid, |keymap| f(&mut lock_value(keymap)))
+}
+
+/// Lock held on another editor while it is read.
+enum OtherEditor<'a> {
+    Owned(MutexGuard<'a, Editor>),
+    Buffer(MutexGuard<'a, Workspace>, BufferId),
+}
+
+impl OtherEditor<'_> {
+    fn editor(&self) -> Option<&Editor> {
+        match self {
+            OtherEditor::Owned(editor) => Some(editor),
+            OtherEditor::Buffer(workspace, buffer) => workspace.buffer(*buffer).map(|b| b.editor()),
+        }
+    }
+}
+
+/// Runs `f` on other editors while `current` is locked by the caller
+///
+/// Other editors are only try-locked: one that is busy (another isolate,
+/// or sharing the caller's workspace lock) is skipped instead of risking
+/// a deadlock. Null, stale and `current` handles are skipped too.
+pub(crate) fn with_other_editors<R>(
+    current: *mut OpaqueEditor,
+    handles: &[*mut OpaqueEditor],
+    f: impl FnOnce(&[&Editor]) -> R,
+) -> R {
+    let entries: Vec<Arc<EditorEntry>> = handles
+        .iter()
+        .filter(|&&handle| !handle.is_null() && handle != current)
+        .filter_map(|&handle| lock(&EDITORS.table).get(handle as usize))
+        .collect();
+
+    fn try_lock<T>(mutex: &Mutex<T>) -> Option<MutexGuard<'_, T>> {
+        match mutex.try_lock() {
+            Ok(guard) => Some(guard),
+            Err(std::sync::TryLockError::Poisoned(poisoned)) => Some(poisoned.into_inner()),
+            Err(std::sync::TryLockError::WouldBlock) => None,
+        }
+    }
+
+    let guards: Vec<OtherEditor> = entries
+        .iter()
+        .filter_map(|entry| match &**entry {
+            EditorEntry::Owned(editor) => try_lock(editor).map(OtherEditor::Owned),
+            EditorEntry::Buffer { workspace, buffer } => {
+                try_lock(workspace).map(|workspace| OtherEditor::Buffer(workspace, *buffer))
+            }
+        })
+        .collect();
+
+    let others: Vec<&Editor> = guards.iter().filter_map(OtherEditor::editor).collect();
+    f(&others)
+}
+
+/// Gets the editor handle of a workspace buffer
+///
+/// `select` runs on the locked workspace and picks the buffer (or fails).
+/// Returns the same handle for the same buffer until the workspace is freed.
+pub(crate) fn buffer_editor(
+    handle: *mut OpaqueWorkspace,
+    select: impl FnOnce(&mut Workspace) -> Option<BufferId>,
+) -> *mut OpaqueEditor {
+    with_entry(&WORKSPACES, handle, ptr::null_mut(), |workspace| {
+        let Some(buffer) = select(&mut lock_workspace(workspace)) else {
+            return ptr::null_mut();
+        };
+
+        let mut handles = lock(&BUFFER_EDITORS);
+        let key = (handle as usize, buffer);
+        if let Some(&existing) = handles.get(&key) {
+            if lock(&EDITORS.table).get(existing).is_some() {
+                return existing as *mut OpaqueEditor;
+            }
+        }
+
+        let editor: *mut OpaqueEditor = EDITORS.insert(EditorEntry::Buffer {
+            workspace: Arc::clone(workspace),
+            buffer,
+        });
+        if !editor.is_null() {
+            handles.insert(key, editor as usize);
+        }
+        editor
+    })
+}
+
+/// Frees a workspace and the editor handles of its buffers
+pub(crate) fn free_workspace(handle: *mut OpaqueWorkspace) {
+    if WORKSPACES.remove(handle).is_none() {
+        return;
+    }
+
+    lock(&BUFFER_EDITORS).retain(|&(workspace, _), editor| {
+        if workspace == handle as usize {
+            lock(&EDITORS.table).remove(*editor);
+            false
+        } else {
+            true
+        }
+    });
+}
+
+/// Unregisters an editor handle
+///
+/// Standalone editors are dropped; buffer editors stay with their
+/// workspace (only the handle goes away).
+pub(crate) fn free_editor(handle: *mut OpaqueEditor) {
+    if EDITORS.remove(handle).is_some() {
+        lock(&BUFFER_EDITORS).retain(|_, editor| *editor != handle as usize);
+    }
+}
+
+/// Removes a recovery journal handle and takes the journal
+///
+/// Returns: The journal, or None if the handle is stale or still in use
+/// by another call
+pub(crate) fn take_recovery_journal(handle: *mut OpaqueRecoveryJournal) -> Option<RecoveryJournal> {
+    let journal = RECOVERY_JOURNALS.remove(handle)?;
+    match Arc::try_unwrap(journal) {
+        Ok(journal) => Some(journal.into_inner().unwrap_or_else(PoisonError::into_inner)),
+        Err(_) => {
+            set_last_error("recovery journal is in use by another call");
+            None
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_generation_check() {
+        let mut table = HandleTable::new();
+        let first = table.insert("a").unwrap();
+        assert_ne!(first, 0);
+        assert_eq!(*table.get(first).unwrap(), "a");
+
+        assert!(table.remove(first).is_some());
+        assert!(table.remove(first).is_none());
+        assert!(table.get(first).is_none());
+
+        // The slot is reused under a new generation
+        let second = table.insert("b").unwrap();
+        assert_eq!(second & INDEX_MASK, first & INDEX_MASK);
+        assert_ne!(second, first);
+        assert!(table.get(first).is_none());
+        assert_eq!(*table.get(second).unwrap(), "b");
+
+        assert!(table.get(0).is_none());
+        assert!(table.get(usize::MAX).is_none());
+    }
+
+    #[test]
+    fn test_ffi_guard_catches_panics() {
+        take_last_error();
+        assert_eq!(ffi_guard(|| 7), 7);
+        assert!(take_last_error().is_none());
+
+        let code = ffi_guard(|| -> ResultCode { panic!("boom {}", 1) });
+        assert!(matches!(code, ResultCode::ErrorPanic));
+        assert_eq!(take_last_error().as_deref(), Some("panic: boom 1"));
+
+        let pointer: *mut OpaqueEditor = ffi_guard(|| panic!("static message"));
+        assert!(pointer.is_null());
+        assert_eq!(take_last_error().as_deref(), Some("panic: static message"));
+    }
+
+    #[test]
+    fn test_poisoned_lock_recovers() {
+        let mutex = Mutex::new(1);
+        ffi_guard::<i32>(|| {
+            let _guard = lock(&mutex);
+            panic!("while locked");
+        });
+        assert!(mutex.is_poisoned());
+        assert_eq!(*lock(&mutex), 1);
+        take_last_error();
+    }
+}
@@ -1,19 +1,62 @@
 use std::ffi::{CStr, CString};
 use std::os::raw::c_char;
 use std::ptr;
-use crate::editor::{Editor, Position, Selection, LanguageId};
+use std::sync::{Mutex, PoisonError, RwLock, RwLockWriteGuard};
+use crate::editor::{Editor, Position, Selection, LanguageId, StructuralPattern, StructuralQuery, LargeFileConfig, LargeFileView, FileSizeClass, RefreshStatus, EditorCommand, Macro, ParseConfig, CollabSnapshot, CrdtOp, CursorUpdate, VersionVector, SharedSpellChecker, SpellChecker, WordListKind, SaveAction};
+use crate::renderer::{LayoutConfig, RasterOptions, TextRenderer, WrapMode};
+use crate::workspace::{CloseChoice, CloseOutcome, Workspace};
+use crate::workspace::recovery::{self, RecoveryFormat, RecoveryJournal};
+use crate::editor::telemetry;
+use crate::keymap::{parse_key, KeyContext, Keymap, Platform};
 
-/// Opaque pointer to Editor (for FFI safety)
-type EditorHandle = *mut Editor;
+mod handles;
+
+use handles::{
+    c_string, ffi_guard, json_c_string, with_editor, with_keymap, with_large_file, with_recovery_journal, with_renderer,
+    with_spell_checker, with_workspace, EditorEntry, OpaqueEditor, OpaqueKeymap, OpaqueLargeFile, OpaqueRecoveryJournal,
+    OpaqueRenderer, OpaqueSpellChecker, OpaqueWorkspace, EDITORS, KEYMAPS, LARGE_FILES, RECOVERY_JOURNALS, RENDERERS,
+    SPELL_CHECKERS, WORKSPACES,
+};
+
+// Handles are generation-checked ids from the handle registry (see
+// `handles`), typed as opaque pointers for the host. They are never
+// dereferenced: a freed or foreign handle is rejected, not undefined
+// behaviour.
+
+/// Opaque handle to an Editor
+type EditorHandle = *mut OpaqueEditor;
+
+/// Opaque handle to a TextRenderer
+type RendererHandle = *mut OpaqueRenderer;
+
+/// Opaque handle to a read-only view of a huge file
+type LargeFileHandle = *mut OpaqueLargeFile;
+
+/// Opaque handle to a Workspace
+type WorkspaceHandle = *mut OpaqueWorkspace;
+
+/// Opaque handle to the crash recovery journal of the running session
+type RecoveryHandle = *mut OpaqueRecoveryJournal;
+
+/// Opaque handle to a spell checker (shared by the editors it is set on)
+type SpellCheckerHandle = *mut OpaqueSpellChecker;
+
+/// Opaque handle to a keymap (bindings plus chord state)
+type KeymapHandle = *mut OpaqueKeymap;
 
 /// FFI Result codes
 #[repr(C)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum ResultCode {
     Success = 0,
     ErrorNull = -1,
     ErrorInvalidUtf8 = -2,
     ErrorOutOfBounds = -3,
     ErrorUnknown = -4,
+    /// The handle was freed (or never issued)
+    ErrorInvalidHandle = -5,
+    /// The call panicked (see `editor_last_error()`)
+    ErrorPanic = -6,
 }
 
 // ==================================================================
@@ -26,7 +69,9 @@ pub enum ResultCode {
 /// Returns an opaque pointer that must be freed with `editor_free()`
 #[no_mangle]
 pub unsafe extern "C" fn editor_new() -> EditorHandle {
-    Box::into_raw(Box::new(Editor::new()))
+    ffi_guard(|| {
+        EDITORS.insert(EditorEntry::Owned(Mutex::new(Editor::new())))
+    })
 }
 
 /// Creates an editor with initial content
@@ -40,38 +85,40 @@ pub unsafe extern "C" fn editor_with_content(
     content: *const c_char,
     language_id: *const c_char,
 ) -> EditorHandle {
-    if content.is_null() || language_id.is_null() {
-        return ptr::null_mut();
-    }
+    ffi_guard(|| {
+        if content.is_null() || language_id.is_null() {
+            return ptr::null_mut();
+        }
 
-    let content_str = match CStr::from_ptr(content).to_str() {
-        Ok(s) => s,
-        Err(_) => return ptr::null_mut(),
-    };
+        let content_str = match CStr::from_ptr(content).to_str() {
+            Ok(s) => s,
+            Err(_) => return ptr::null_mut(),
+        };
 
-    let language_str = match CStr::from_ptr(language_id).to_str() {
-        Ok(s) => s,
-        Err(_) => return ptr::null_mut(),
-    };
+        let language_str = match CStr::from_ptr(language_id).to_str() {
+            Ok(s) => s,
+            Err(_) => return ptr::null_mut(),
+        };
 
-    let language = LanguageId::parse(language_str);
+        let language = LanguageId::parse(language_str);
 
-    match Editor::with_content(content_str, language) {
-        Ok(editor) => Box::into_raw(Box::new(editor)),
-        Err(_) => ptr::null_mut(),
-    }
+        match Editor::with_content(content_str, language) {
+            Ok(editor) => EDITORS.insert(EditorEntry::Owned(Mutex::new(editor))),
+            Err(_) => ptr::null_mut(),
+        }
+    })
 }
 
 /// Frees an editor instance
 ///
 /// # Safety
-/// - `handle` must be a valid pointer returned by `editor_new()`
-/// - Must not be used after calling this function
+/// - `handle` must be a handle returned by `editor_new()` (or a buffer
+///   editor handle, which is only unregistered) or null
+/// - Must not be used after calling this function (a second free is
+///   ignored)
 #[no_mangle]
 pub unsafe extern "C" fn editor_free(handle: EditorHandle) {
-    if !handle.is_null() {
-        drop(Box::from_raw(handle));
-    }
+    ffi_guard(|| handles::free_editor(handle))
 }
 
 // ==================================================================
@@ -85,17 +132,9 @@ pub unsafe extern "C" fn editor_free(handle: EditorHandle) {
 /// - Caller must free the returned string with `editor_free_string()`
 #[no_mangle]
 pub unsafe extern "C" fn editor_get_content(handle: EditorHandle) -> *mut c_char {
-    if handle.is_null() {
-        return ptr::null_mut();
-    }
-
-    let editor = &*handle;
-    let content = editor.content();
-
-    match CString::new(content) {
-        Ok(c_str) => c_str.into_raw(),
-        Err(_) => ptr::null_mut(),
-    }
+    with_editor(handle, ptr::null_mut(), |editor| {
+        c_string(editor.content())
+    })
 }
 
 /// Sets the editor content
@@ -108,21 +147,21 @@ pub unsafe extern "C" fn editor_set_content(
     handle: EditorHandle,
     content: *const c_char,
 ) -> ResultCode {
-    if handle.is_null() || content.is_null() {
-        return ResultCode::ErrorNull;
-    }
-
-    let editor = &mut *handle;
-
-    let content_str = match CStr::from_ptr(content).to_str() {
-        Ok(s) => s,
-        Err(_) => return ResultCode::ErrorInvalidUtf8,
-    };
-
-    match editor.set_content(content_str) {
-        Ok(_) => ResultCode::Success,
-        Err(_) => ResultCode::ErrorUnknown,
-    }
+    with_editor(handle, ResultCode::ErrorNull, |editor| {
+        if content.is_null() {
+            return ResultCode::ErrorNull;
+        }
+
+        let content_str = match CStr::from_ptr(content).to_str() {
+            Ok(s) => s,
+            Err(_) => return ResultCode::ErrorInvalidUtf8,
+        };
+
+        match editor.set_content(content_str) {
+            Ok(_) => ResultCode::Success,
+            Err(_) => ResultCode::ErrorUnknown,
+        }
+    })
 }
 
 /// Inserts text at the current cursor position
@@ -135,21 +174,21 @@ pub unsafe extern "C" fn editor_insert_text(
     handle: EditorHandle,
     text: *const c_char,
 ) -> ResultCode {
-    if handle.is_null() || text.is_null() {
-        return ResultCode::ErrorNull;
-    }
-
-    let editor = &mut *handle;
-
-    let text_str = match CStr::from_ptr(text).to_str() {
-        Ok(s) => s,
-        Err(_) => return ResultCode::ErrorInvalidUtf8,
-    };
-
-    match editor.insert_text(text_str) {
-        Ok(_) => ResultCode::Success,
-        Err(_) => ResultCode::ErrorUnknown,
-    }
+    with_editor(handle, ResultCode::ErrorNull, |editor| {
+        if text.is_null() {
+            return ResultCode::ErrorNull;
+        }
+
+        let text_str = match CStr::from_ptr(text).to_str() {
+            Ok(s) => s,
+            Err(_) => return ResultCode::ErrorInvalidUtf8,
+        };
+
+        match editor.insert_text(text_str) {
+            Ok(_) => ResultCode::Success,
+            Err(_) => ResultCode::ErrorUnknown,
+        }
+    })
 }
 
 /// Deletes the current selection or character at cursor
@@ -158,16 +197,12 @@ pub unsafe extern "C" fn editor_insert_text(
 /// - `handle` must be a valid editor pointer
 #[no_mangle]
 pub unsafe extern "C" fn editor_delete(handle: EditorHandle) -> ResultCode {
-    if handle.is_null() {
-        return ResultCode::ErrorNull;
-    }
-
-    let editor = &mut *handle;
-
-    match editor.delete() {
-        Ok(_) => ResultCode::Success,
-        Err(_) => ResultCode::ErrorUnknown,
-    }
+    with_editor(handle, ResultCode::ErrorNull, |editor| {
+        match editor.delete() {
+            Ok(_) => ResultCode::Success,
+            Err(_) => ResultCode::ErrorUnknown,
+        }
+    })
 }
 
 // ==================================================================
@@ -185,17 +220,18 @@ pub unsafe extern "C" fn editor_get_cursor(
     out_line: *mut usize,
     out_column: *mut usize,
 ) -> ResultCode {
-    if handle.is_null() || out_line.is_null() || out_column.is_null() {
-        return ResultCode::ErrorNull;
-    }
+    with_editor(handle, ResultCode::ErrorNull, |editor| {
+        if out_line.is_null() || out_column.is_null() {
+            return ResultCode::ErrorNull;
+        }
 
-    let editor = &*handle;
-    let cursor = editor.cursor();
+        let cursor = editor.cursor();
 
-    *out_line = cursor.line;
-    *out_column = cursor.column;
+        *out_line = cursor.line;
+        *out_column = cursor.column;
 
-    ResultCode::Success
+        ResultCode::Success
+    })
 }
 
 /// Moves the cursor to a position
@@ -208,14 +244,11 @@ pub unsafe extern "C" fn editor_move_cursor(
     line: usize,
     column: usize,
 ) -> ResultCode {
-    if handle.is_null() {
-        return ResultCode::ErrorNull;
-    }
-
-    let editor = &mut *handle;
-    editor.move_cursor(Position::new(line, column));
+    with_editor(handle, ResultCode::ErrorNull, |editor| {
+        editor.move_cursor(Position::new(line, column));
 
-    ResultCode::Success
+        ResultCode::Success
+    })
 }
 
 /// Sets the selection range
@@ -230,18 +263,15 @@ pub unsafe extern "C" fn editor_set_selection(
     end_line: usize,
     end_column: usize,
 ) -> ResultCode {
-    if handle.is_null() {
-        return ResultCode::ErrorNull;
-    }
-
-    let editor = &mut *handle;
-    let selection = Selection::new(
-        Position::new(start_line, start_column),
-        Position::new(end_line, end_column),
-    );
-
-    editor.set_selection(selection);
-    ResultCode::Success
+    with_editor(handle, ResultCode::ErrorNull, |editor| {
+        let selection = Selection::new(
+            Position::new(start_line, start_column),
+            Position::new(end_line, end_column),
+        );
+
+        editor.set_selection(selection);
+        ResultCode::Success
+    })
 }
 
 /// Clears the current selection
@@ -250,14 +280,11 @@ pub unsafe extern "C" fn editor_set_selection(
 /// - `handle` must be a valid editor pointer
 #[no_mangle]
 pub unsafe extern "C" fn editor_clear_selection(handle: EditorHandle) -> ResultCode {
-    if handle.is_null() {
-        return ResultCode::ErrorNull;
-    }
+    with_editor(handle, ResultCode::ErrorNull, |editor| {
+        editor.clear_selection();
 
-    let editor = &mut *handle;
-    editor.clear_selection();
-
-    ResultCode::Success
+        ResultCode::Success
+    })
 }
 
 // ==================================================================
@@ -268,40 +295,34 @@ pub unsafe extern "C" fn editor_clear_selection(handle: EditorHandle) -> ResultC
 ///
 /// # Safety
 /// - `handle` must be a valid editor pointer
+///
 /// Returns 1 if undo was performed, 0 if undo stack is empty
 #[no_mangle]
 pub unsafe extern "C" fn editor_undo(handle: EditorHandle) -> i32 {
-    if handle.is_null() {
-        return -1;
-    }
-
-    let editor = &mut *handle;
-
-    match editor.undo() {
-        Ok(true) => 1,
-        Ok(false) => 0,
-        Err(_) => -1,
-    }
+    with_editor(handle, -1, |editor| {
+        match editor.undo() {
+            Ok(true) => 1,
+            Ok(false) => 0,
+            Err(_) => -1,
+        }
+    })
 }
 
 /// Redoes the last undone edit
 ///
 /// # Safety
 /// - `handle` must be a valid editor pointer
+///
 /// Returns 1 if redo was performed, 0 if redo stack is empty
 #[no_mangle]
 pub unsafe extern "C" fn editor_redo(handle: EditorHandle) -> i32 {
-    if handle.is_null() {
-        return -1;
-    }
-
-    let editor = &mut *handle;
-
-    match editor.redo() {
-        Ok(true) => 1,
-        Ok(false) => 0,
-        Err(_) => -1,
-    }
+    with_editor(handle, -1, |editor| {
+        match editor.redo() {
+            Ok(true) => 1,
+            Ok(false) => 0,
+            Err(_) => -1,
+        }
+    })
 }
 
 // ==================================================================
@@ -318,23 +339,23 @@ pub unsafe extern "C" fn editor_set_language(
     handle: EditorHandle,
     language_id: *const c_char,
 ) -> ResultCode {
-    if handle.is_null() || language_id.is_null() {
-        return ResultCode::ErrorNull;
-    }
-
-    let editor = &mut *handle;
-
-    let language_str = match CStr::from_ptr(language_id).to_str() {
-        Ok(s) => s,
-        Err(_) => return ResultCode::ErrorInvalidUtf8,
-    };
-
-    let language = LanguageId::parse(language_str);
-
-    match editor.set_language(language) {
-        Ok(_) => ResultCode::Success,
-        Err(_) => ResultCode::ErrorUnknown,
-    }
+    with_editor(handle, ResultCode::ErrorNull, |editor| {
+        if language_id.is_null() {
+            return ResultCode::ErrorNull;
+        }
+
+        let language_str = match CStr::from_ptr(language_id).to_str() {
+            Ok(s) => s,
+            Err(_) => return ResultCode::ErrorInvalidUtf8,
+        };
+
+        let language = LanguageId::parse(language_str);
+
+        match editor.set_language(language) {
+            Ok(_) => ResultCode::Success,
+            Err(_) => ResultCode::ErrorUnknown,
+        }
+    })
 }
 
 // ==================================================================
@@ -347,12 +368,9 @@ pub unsafe extern "C" fn editor_set_language(
 /// - `handle` must be a valid editor pointer
 #[no_mangle]
 pub unsafe extern "C" fn editor_line_count(handle: EditorHandle) -> usize {
-    if handle.is_null() {
-        return 0;
-    }
-
-    let editor = &*handle;
-    editor.line_count()
+    with_editor(handle, 0, |editor| {
+        editor.line_count()
+    })
 }
 
 /// Gets a specific line content
@@ -365,38 +383,29 @@ pub unsafe extern "C" fn editor_get_line(
     handle: EditorHandle,
     line_index: usize,
 ) -> *mut c_char {
-    if handle.is_null() {
-        return ptr::null_mut();
-    }
-
-    let editor = &*handle;
-
-    match editor.line(line_index) {
-        Some(line) => match CString::new(line) {
-            Ok(c_str) => c_str.into_raw(),
-            Err(_) => ptr::null_mut(),
-        },
-        None => ptr::null_mut(),
-    }
+    with_editor(handle, ptr::null_mut(), |editor| {
+        match editor.line(line_index) {
+            Some(line) => c_string(line),
+            None => ptr::null_mut(),
+        }
+    })
 }
 
 /// Checks if the editor has unsaved changes
 ///
 /// # Safety
 /// - `handle` must be a valid editor pointer
+///
 /// Returns 1 if dirty, 0 if not
 #[no_mangle]
 pub unsafe extern "C" fn editor_is_dirty(handle: EditorHandle) -> i32 {
-    if handle.is_null() {
-        return 0;
-    }
-
-    let editor = &*handle;
-    if editor.is_dirty() {
-        1
-    } else {
-        0
-    }
+    with_editor(handle, 0, |editor| {
+        if editor.is_dirty() {
+            1
+        } else {
+            0
+        }
+    })
 }
 
 /// Marks the editor as saved
@@ -405,30 +414,2502 @@ pub unsafe extern "C" fn editor_is_dirty(handle: EditorHandle) -> i32 {
 /// - `handle` must be a valid editor pointer
 #[no_mangle]
 pub unsafe extern "C" fn editor_mark_saved(handle: EditorHandle) -> ResultCode {
-    if handle.is_null() {
-        return ResultCode::ErrorNull;
-    }
+    with_editor(handle, ResultCode::ErrorNull, |editor| {
+        editor.mark_saved();
+
+        ResultCode::Success
+    })
+}
+
+// ==================================================================
+// Settings & Save
+// ==================================================================
+
+/// Resolves `.editorconfig` files for a path and applies them to the editor
+///
+/// # Safety
+/// - `handle` must be a valid editor pointer
+/// - `file_path` must be a valid C string
+#[no_mangle]
+pub unsafe extern "C" fn editor_load_editorconfig(
+    handle: EditorHandle,
+    file_path: *const c_char,
+) -> ResultCode {
+    with_editor(handle, ResultCode::ErrorNull, |editor| {
+        if file_path.is_null() {
+            return ResultCode::ErrorNull;
+        }
+
+        let path_str = match CStr::from_ptr(file_path).to_str() {
+            Ok(s) => s,
+            Err(_) => return ResultCode::ErrorInvalidUtf8,
+        };
+
+        match editor.load_editorconfig(std::path::Path::new(path_str)) {
+            Ok(_) => ResultCode::Success,
+            Err(_) => ResultCode::ErrorUnknown,
+        }
+    })
+}
+
+/// Gets the indentation settings
+///
+/// # Safety
+/// - `handle` must be a valid editor pointer
+/// - `out_use_spaces` and `out_tab_size` must be valid pointers
+#[no_mangle]
+pub unsafe extern "C" fn editor_get_indent_config(
+    handle: EditorHandle,
+    out_use_spaces: *mut i32,
+    out_tab_size: *mut usize,
+) -> ResultCode {
+    with_editor(handle, ResultCode::ErrorNull, |editor| {
+        if out_use_spaces.is_null() || out_tab_size.is_null() {
+            return ResultCode::ErrorNull;
+        }
 
-    let editor = &mut *handle;
-    editor.mark_saved();
+        let config = editor.indent_config();
 
-    ResultCode::Success
+        *out_use_spaces = if config.use_spaces { 1 } else { 0 };
+        *out_tab_size = config.tab_size;
+
+        ResultCode::Success
+    })
+}
+
+/// Gets the preferred maximum line length
+///
+/// # Safety
+/// - `handle` must be a valid editor pointer
+///
+/// Returns 0 if no limit is configured
+#[no_mangle]
+pub unsafe extern "C" fn editor_max_line_length(handle: EditorHandle) -> usize {
+    with_editor(handle, 0, |editor| {
+        editor.max_line_length().unwrap_or(0)
+    })
+}
+
+/// Applies save settings to the buffer and returns the bytes to write
+///
+/// # Safety
+/// - `handle` must be a valid editor pointer
+/// - `out_len` must be a valid pointer (receives the byte count)
+/// - Caller must free the returned buffer with `editor_free_bytes()`
+#[no_mangle]
+pub unsafe extern "C" fn editor_prepare_save(
+    handle: EditorHandle,
+    out_len: *mut usize,
+) -> *mut u8 {
+    with_editor(handle, ptr::null_mut(), |editor| {
+        if out_len.is_null() {
+            return ptr::null_mut();
+        }
+
+        match editor.prepare_save() {
+            Ok(bytes) => {
+                let boxed = bytes.into_boxed_slice();
+                *out_len = boxed.len();
+                Box::into_raw(boxed) as *mut u8
+            }
+            Err(_) => ptr::null_mut(),
+        }
+    })
+}
+
+/// Enables or disables a save action, for all languages or one language
+///
+/// Actions: 0 = trim trailing whitespace, 1 = normalize indentation,
+/// 2 = normalize line endings, 3 = trim final newlines, 4 = insert final
+/// newline.
+///
+/// # Safety
+/// - `handle` must be a valid editor pointer
+/// - `language` must be null (all languages) or a valid C string
+///
+/// `enabled` is 1 or 0; with a language, -1 removes its override.
+#[no_mangle]
+pub unsafe extern "C" fn editor_set_save_action(
+    handle: EditorHandle,
+    language: *const c_char,
+    action: i32,
+    enabled: i32,
+) -> ResultCode {
+    with_editor(handle, ResultCode::ErrorNull, |editor| {
+        let Some(action) = usize::try_from(action).ok().and_then(|i| SaveAction::ALL.get(i).copied()) else {
+            return ResultCode::ErrorOutOfBounds;
+        };
+        let enabled = match enabled {
+            1 => Some(true),
+            0 => Some(false),
+            -1 if !language.is_null() => None,
+            _ => return ResultCode::ErrorOutOfBounds,
+        };
+
+        let mut settings = editor.save_settings().clone();
+        if language.is_null() {
+            settings.set_enabled(action, enabled.unwrap_or_default());
+        } else {
+            let language = match CStr::from_ptr(language).to_str() {
+                Ok(s) => LanguageId::parse(s),
+                Err(_) => return ResultCode::ErrorInvalidUtf8,
+            };
+            settings.set_language_action(language, action, enabled);
+        }
+        editor.set_save_settings(settings);
+        ResultCode::Success
+    })
 }
 
 // ==================================================================
-// Memory Management
+// Completion
 // ==================================================================
 
-/// Frees a C string returned by the editor
+/// Computes local completions (syntax identifiers and buffer words)
 ///
 /// # Safety
-/// - `ptr` must be a string returned by an editor function
-/// - Must not be used after calling this function
+/// - `handle` must be a valid editor pointer
+/// - `other_handles` must point to `other_count` editor handles (may be
+///   null if `other_count` is 0); null, stale and busy handles are skipped
+/// - Caller must free the returned string with `editor_free_string()`
+///
+/// Returns an LSP-shaped `CompletionList` as JSON, or null on error
 #[no_mangle]
-pub unsafe extern "C" fn editor_free_string(ptr: *mut c_char) {
-    if !ptr.is_null() {
-        drop(CString::from_raw(ptr));
+pub unsafe extern "C" fn editor_complete(
+    handle: EditorHandle,
+    line: usize,
+    column: usize,
+    other_handles: *const EditorHandle,
+    other_count: usize,
+) -> *mut c_char {
+    if other_handles.is_null() && other_count > 0 {
+        return ptr::null_mut();
     }
+
+    let other_handles = if other_count > 0 {
+        std::slice::from_raw_parts(other_handles, other_count)
+    } else {
+        &[]
+    };
+
+    with_editor(handle, ptr::null_mut(), |editor| {
+        let list = handles::with_other_editors(handle, other_handles, |others| {
+            editor.completions(Position::new(line, column), others)
+        });
+
+        json_c_string(&list)
+    })
+}
+
+// ==================================================================
+// Symbols
+// ==================================================================
+
+/// Gets the document symbol outline
+///
+/// # Safety
+/// - `handle` must be a valid editor pointer
+/// - Caller must free the returned string with `editor_free_string()`
+///
+/// Returns a JSON array of LSP-shaped `DocumentSymbol`s, or null on error
+#[no_mangle]
+pub unsafe extern "C" fn editor_document_symbols(handle: EditorHandle) -> *mut c_char {
+    with_editor(handle, ptr::null_mut(), |editor| {
+        json_c_string(&editor.document_symbols())
+    })
+}
+
+// ==================================================================
+// Viewport Decorations
+// ==================================================================
+
+/// Gets the scopes to pin at the top of the viewport (sticky scroll)
+///
+/// # Safety
+/// - `handle` must be a valid editor pointer
+/// - Caller must free the returned string with `editor_free_string()`
+///
+/// Returns a JSON array of `{line, endLine, text, kind}`, or null on error
+#[no_mangle]
+pub unsafe extern "C" fn editor_sticky_scopes(
+    handle: EditorHandle,
+    top_line: usize,
+    max_scopes: usize,
+) -> *mut c_char {
+    with_editor(handle, ptr::null_mut(), |editor| {
+        json_c_string(&editor.sticky_scopes(top_line, max_scopes))
+    })
+}
+
+/// Gets indentation guides for a viewport
+///
+/// # Safety
+/// - `handle` must be a valid editor pointer
+/// - Caller must free the returned string with `editor_free_string()`
+///
+/// Returns a JSON array of `{column, level, startLine, endLine, active}`,
+/// or null on error
+#[no_mangle]
+pub unsafe extern "C" fn editor_indent_guides(
+    handle: EditorHandle,
+    start_line: usize,
+    end_line: usize,
+) -> *mut c_char {
+    with_editor(handle, ptr::null_mut(), |editor| {
+        json_c_string(&editor.indent_guides(start_line, end_line))
+    })
+}
+
+// ==================================================================
+// Embedded Languages
+// ==================================================================
+
+/// Gets syntax highlights for a line range, including injected languages
+///
+/// # Safety
+/// - `handle` must be a valid editor pointer
+/// - Caller must free the returned string with `editor_free_string()`
+///
+/// Returns a JSON array of `{startByte, endByte, range, capture, language, depth}`,
+/// or null on error
+#[no_mangle]
+pub unsafe extern "C" fn editor_highlights(
+    handle: EditorHandle,
+    start_line: usize,
+    end_line: usize,
+) -> *mut c_char {
+    with_editor(handle, ptr::null_mut(), |editor| {
+        json_c_string(&editor.highlights(start_line, end_line))
+    })
+}
+
+/// Gets folding ranges, including injected languages
+///
+/// # Safety
+/// - `handle` must be a valid editor pointer
+/// - Caller must free the returned string with `editor_free_string()`
+///
+/// Returns a JSON array of LSP-shaped `FoldingRange`s, or null on error
+#[no_mangle]
+pub unsafe extern "C" fn editor_folding_ranges(handle: EditorHandle) -> *mut c_char {
+    with_editor(handle, ptr::null_mut(), |editor| {
+        json_c_string(&editor.folding_ranges())
+    })
+}
+
+/// Gets the innermost language at a position
+///
+/// # Safety
+/// - `handle` must be a valid editor pointer
+/// - Caller must free the returned string with `editor_free_string()`
+///
+/// Returns the language name (e.g. "python" inside a Markdown code
+/// fence), or null on error
+#[no_mangle]
+pub unsafe extern "C" fn editor_language_at(
+    handle: EditorHandle,
+    line: usize,
+    column: usize,
+) -> *mut c_char {
+    with_editor(handle, ptr::null_mut(), |editor| {
+        c_string(editor.language_at(Position::new(line, column)).name())
+    })
+}
+
+/// Toggles line comments using the innermost language at `start_line`
+///
+/// # Safety
+/// - `handle` must be a valid editor pointer
+///
+/// Returns 1 if commented, 0 if uncommented, -1 on error or if the
+/// language has no comments
+#[no_mangle]
+pub unsafe extern "C" fn editor_toggle_line_comments(
+    handle: EditorHandle,
+    start_line: usize,
+    end_line: usize,
+) -> i32 {
+    with_editor(handle, -1, |editor| {
+        match editor.toggle_line_comments(start_line, end_line) {
+            Ok(Some(true)) => 1,
+            Ok(Some(false)) => 0,
+            Ok(None) | Err(_) => -1,
+        }
+    })
+}
+
+/// Toggles a block comment around a selection (its line if empty)
+///
+/// # Safety
+/// - `handle` must be a valid editor pointer
+///
+/// Returns 1 if commented, 0 if uncommented, -1 on error, if the
+/// language has no block comments, or if the selection holds a comment
+/// that would end the new one early
+#[no_mangle]
+pub unsafe extern "C" fn editor_toggle_block_comment(
+    handle: EditorHandle,
+    start_line: usize,
+    start_column: usize,
+    end_line: usize,
+    end_column: usize,
+) -> i32 {
+    with_editor(handle, -1, |editor| {
+        let start = Position::new(start_line, start_column);
+        let end = Position::new(end_line, end_column);
+        match editor.toggle_block_comment(start, end) {
+            Ok(Some(true)) => 1,
+            Ok(Some(false)) => 0,
+            Ok(None) | Err(_) => -1,
+        }
+    })
+}
+
+/// Inserts a doc-comment stub for the function declared on a line
+///
+/// The cursor moves to the stub's summary.
+///
+/// # Safety
+/// - `handle` must be a valid editor pointer
+///
+/// Returns 1 if inserted, 0 if no undocumented function starts on the
+/// line, -1 on error
+#[no_mangle]
+pub unsafe extern "C" fn editor_insert_doc_comment(handle: EditorHandle, line: usize) -> i32 {
+    with_editor(handle, -1, |editor| match editor.insert_doc_comment(line) {
+        Ok(Some(_)) => 1,
+        Ok(None) => 0,
+        Err(_) => -1,
+    })
+}
+
+// ==================================================================
+// Macros
+// ==================================================================
+
+/// Runs an editor command (recorded while a macro is being recorded)
+///
+/// # Safety
+/// - `handle` must be a valid editor pointer
+/// - `command_json` must be a valid null-terminated UTF-8 string, e.g.
+///   `{"command":"move","motion":"lineEnd","extend":false}`
+///
+/// Returns 1 if the command ran, 0 if it had nothing to act on (search
+/// without matches), -1 on error
+#[no_mangle]
+pub unsafe extern "C" fn editor_execute_command(handle: EditorHandle, command_json: *const c_char) -> i32 {
+    with_editor(handle, -1, |editor| {
+        if command_json.is_null() {
+            return -1;
+        }
+
+        let Ok(command_json) = CStr::from_ptr(command_json).to_str() else {
+            return -1;
+        };
+        let Ok(command) = serde_json::from_str::<EditorCommand>(command_json) else {
+            return -1;
+        };
+
+        match editor.execute_command(&command) {
+            Ok(true) => 1,
+            Ok(false) => 0,
+            Err(_) => -1,
+        }
+    })
+}
+
+/// Starts recording a macro (discards an unfinished recording)
+///
+/// # Safety
+/// - `handle` must be a valid editor pointer
+#[no_mangle]
+pub unsafe extern "C" fn editor_start_macro_recording(handle: EditorHandle) -> ResultCode {
+    with_editor(handle, ResultCode::ErrorNull, |editor| {
+        editor.start_macro_recording();
+        ResultCode::Success
+    })
+}
+
+/// Stops recording and returns the macro as JSON
+///
+/// # Safety
+/// - `handle` must be a valid editor pointer
+/// - Caller must free the returned string with `editor_free_string()`
+///
+/// Returns `{"commands": [...]}`, or null if not recording
+#[no_mangle]
+pub unsafe extern "C" fn editor_stop_macro_recording(handle: EditorHandle) -> *mut c_char {
+    with_editor(handle, ptr::null_mut(), |editor| {
+        match editor.stop_macro_recording().map(|m| m.to_json()) {
+            Some(Ok(json)) => c_string(json),
+            _ => ptr::null_mut(),
+        }
+    })
+}
+
+/// Plays a macro `times` times from the cursor (one undo step)
+///
+/// # Safety
+/// - `handle` must be a valid editor pointer
+/// - `macro_json` must be a valid null-terminated UTF-8 string
+///
+/// Returns the number of complete iterations, or -1 on error
+#[no_mangle]
+pub unsafe extern "C" fn editor_play_macro(handle: EditorHandle, macro_json: *const c_char, times: usize) -> i32 {
+    with_editor(handle, -1, |editor| {
+        if macro_json.is_null() {
+            return -1;
+        }
+
+        let Ok(macro_json) = CStr::from_ptr(macro_json).to_str() else {
+            return -1;
+        };
+        let Ok(macro_) = Macro::from_json(macro_json) else {
+            return -1;
+        };
+
+        match editor.play_macro(&macro_, times) {
+            Ok(completed) => completed as i32,
+            Err(_) => -1,
+        }
+    })
+}
+
+/// Plays a macro at the start of every line in a range (one undo step)
+///
+/// # Safety
+/// - `handle` must be a valid editor pointer
+/// - `macro_json` must be a valid null-terminated UTF-8 string
+///
+/// Returns the number of lines where the macro completed, or -1 on error
+#[no_mangle]
+pub unsafe extern "C" fn editor_play_macro_on_lines(
+    handle: EditorHandle,
+    macro_json: *const c_char,
+    start_line: usize,
+    end_line: usize,
+) -> i32 {
+    with_editor(handle, -1, |editor| {
+        if macro_json.is_null() {
+            return -1;
+        }
+
+        let Ok(macro_json) = CStr::from_ptr(macro_json).to_str() else {
+            return -1;
+        };
+        let Ok(macro_) = Macro::from_json(macro_json) else {
+            return -1;
+        };
+
+        match editor.play_macro_on_lines(&macro_, start_line, end_line) {
+            Ok(completed) => completed as i32,
+            Err(_) => -1,
+        }
+    })
+}
+
+// ==================================================================
+// Structural Search & Replace
+// ==================================================================
+
+/// Helper: Compiles a structural pattern for the editor language
+unsafe fn compile_structural_pattern(
+    editor: &Editor,
+    pattern: *const c_char,
+    is_template: i32,
+) -> Option<StructuralQuery> {
+    let pattern = CStr::from_ptr(pattern).to_str().ok()?.to_string();
+    let pattern = if is_template != 0 {
+        StructuralPattern::Template(pattern)
+    } else {
+        StructuralPattern::Query(pattern)
+    };
+    pattern.compile(editor.language()).ok()
+}
+
+/// Previews a structural replacement without editing
+///
+/// # Safety
+/// - `handle` must be a valid editor pointer
+/// - `pattern` and `replacement` must be valid null-terminated UTF-8 strings
+/// - Caller must free the returned string with `editor_free_string()`
+///
+/// `is_template` selects a `$metavariable` code template (non-zero) or a
+/// raw tree-sitter query (0).
+///
+/// Returns a JSON array of `{startByte, endByte, range, text, captures,
+/// replacement}`, or null if the pattern or replacement is invalid
+#[no_mangle]
+pub unsafe extern "C" fn editor_structural_preview(
+    handle: EditorHandle,
+    pattern: *const c_char,
+    is_template: i32,
+    replacement: *const c_char,
+) -> *mut c_char {
+    with_editor(handle, ptr::null_mut(), |editor| {
+        if pattern.is_null() || replacement.is_null() {
+            return ptr::null_mut();
+        }
+
+        let Some(query) = compile_structural_pattern(editor, pattern, is_template) else {
+            return ptr::null_mut();
+        };
+        let Ok(replacement) = CStr::from_ptr(replacement).to_str() else {
+            return ptr::null_mut();
+        };
+
+        match editor.structural_preview(&query, replacement) {
+            Ok(matches) => json_c_string(&matches),
+            Err(_) => ptr::null_mut(),
+        }
+    })
+}
+
+/// Replaces every structural match as one undoable edit
+///
+/// # Safety
+/// - `handle` must be a valid editor pointer
+/// - `pattern` and `replacement` must be valid null-terminated UTF-8 strings
+///
+/// Returns the number of replaced matches, or -1 on error
+#[no_mangle]
+pub unsafe extern "C" fn editor_structural_replace(
+    handle: EditorHandle,
+    pattern: *const c_char,
+    is_template: i32,
+    replacement: *const c_char,
+) -> i32 {
+    with_editor(handle, -1, |editor| {
+        if pattern.is_null() || replacement.is_null() {
+            return -1;
+        }
+
+        let Some(query) = compile_structural_pattern(editor, pattern, is_template) else {
+            return -1;
+        };
+        let Ok(replacement) = CStr::from_ptr(replacement).to_str() else {
+            return -1;
+        };
+
+        match editor.structural_replace(&query, replacement) {
+            Ok(matches) => matches.len() as i32,
+            Err(_) => -1,
+        }
+    })
+}
+
+// ==================================================================
+// Text Layout
+// ==================================================================
+
+/// Creates a text layout renderer using system fonts
+///
+/// # Safety
+/// - Caller must free the renderer with `renderer_free()`
+///
+/// Returns an opaque pointer to the renderer
+#[no_mangle]
+pub unsafe extern "C" fn renderer_new(font_size: f32, line_height: f32) -> RendererHandle {
+    ffi_guard(|| {
+        let config = LayoutConfig {
+            font_size,
+            line_height,
+            ..LayoutConfig::default()
+        };
+        RENDERERS.insert(Mutex::new(TextRenderer::with_config(config)))
+    })
+}
+
+/// Frees a renderer instance
+///
+/// # Safety
+/// - `handle` must be a valid renderer pointer or null
+/// - `handle` must not be used after calling this function
+#[no_mangle]
+pub unsafe extern "C" fn renderer_free(handle: RendererHandle) {
+    ffi_guard(|| {
+        RENDERERS.remove(handle);
+    })
+}
+
+/// Loads a font (TTF/OTF bytes) into the renderer
+///
+/// # Safety
+/// - `handle` must be a valid renderer pointer
+/// - `data` must point to `len` readable bytes
+#[no_mangle]
+pub unsafe extern "C" fn renderer_load_font(
+    handle: RendererHandle,
+    data: *const u8,
+    len: usize,
+) -> ResultCode {
+    with_renderer(handle, ResultCode::ErrorNull, |renderer| {
+        if data.is_null() {
+            return ResultCode::ErrorNull;
+        }
+
+        renderer.load_font_data(std::slice::from_raw_parts(data, len).to_vec());
+
+        ResultCode::Success
+    })
+}
+
+/// Configures soft wrapping and tabs
+///
+/// # Safety
+/// - `handle` must be a valid renderer pointer
+///
+/// `wrap_width` <= 0 disables wrapping; `wrap_mode` is 0 (word) or
+/// 1 (grapheme).
+#[no_mangle]
+pub unsafe extern "C" fn renderer_set_wrap(
+    handle: RendererHandle,
+    wrap_width: f32,
+    wrap_mode: i32,
+    tab_size: usize,
+    hanging_indent: usize,
+) -> ResultCode {
+    with_renderer(handle, ResultCode::ErrorNull, |renderer| {
+        let mut config = renderer.config().clone();
+        config.wrap_width = (wrap_width > 0.0).then_some(wrap_width);
+        config.wrap_mode = if wrap_mode == 1 { WrapMode::Grapheme } else { WrapMode::Word };
+        config.tab_size = tab_size.max(1);
+        config.hanging_indent = hanging_indent;
+        renderer.set_config(config);
+
+        ResultCode::Success
+    })
+}
+
+/// Updates cached line layouts after an edit
+///
+/// # Safety
+/// - `handle` must be a valid renderer pointer
+#[no_mangle]
+pub unsafe extern "C" fn renderer_invalidate_lines(
+    handle: RendererHandle,
+    start_line: usize,
+    old_end_line: usize,
+    new_end_line: usize,
+) -> ResultCode {
+    with_renderer(handle, ResultCode::ErrorNull, |renderer| {
+        renderer.invalidate_lines(start_line, old_end_line, new_end_line);
+
+        ResultCode::Success
+    })
+}
+
+/// Lays out a range of lines
+///
+/// # Safety
+/// - `handle` must be a valid renderer pointer
+/// - `editor` must be a valid editor pointer
+/// - Caller must free the returned string with `editor_free_string()`
+///
+/// Returns a JSON `ViewportLayout` (rows with glyph positions relative to
+/// the top of `first_line`), or null on error
+#[no_mangle]
+pub unsafe extern "C" fn renderer_layout_viewport(
+    handle: RendererHandle,
+    editor: EditorHandle,
+    first_line: usize,
+    last_line: usize,
+) -> *mut c_char {
+    with_renderer(handle, ptr::null_mut(), |renderer| {
+        with_editor(editor, ptr::null_mut(), |editor| {
+            let layout = renderer.layout_viewport(editor.rope(), first_line, last_line);
+
+            json_c_string(&layout)
+        })
+    })
+}
+
+/// Maps a viewport point to a document position
+///
+/// # Safety
+/// - `handle` must be a valid renderer pointer
+/// - `editor` must be a valid editor pointer
+/// - `out_line` and `out_column` must be valid pointers
+#[no_mangle]
+pub unsafe extern "C" fn renderer_hit_test(
+    handle: RendererHandle,
+    editor: EditorHandle,
+    first_line: usize,
+    x: f32,
+    y: f32,
+    out_line: *mut usize,
+    out_column: *mut usize,
+) -> ResultCode {
+    with_renderer(handle, ResultCode::ErrorNull, |renderer| {
+        with_editor(editor, ResultCode::ErrorNull, |editor| {
+            if out_line.is_null() || out_column.is_null() {
+                return ResultCode::ErrorNull;
+            }
+
+            let position = renderer.hit_test(editor.rope(), first_line, x, y);
+
+            *out_line = position.line;
+            *out_column = position.column;
+
+            ResultCode::Success
+        })
+    })
+}
+
+/// Gets the caret position for a document position
+///
+/// # Safety
+/// - `handle` must be a valid renderer pointer
+/// - `editor` must be a valid editor pointer
+/// - `out_x` and `out_y` must be valid pointers
+///
+/// Returns `ErrorOutOfBounds` if the position is above `first_line`
+#[no_mangle]
+pub unsafe extern "C" fn renderer_caret_position(
+    handle: RendererHandle,
+    editor: EditorHandle,
+    first_line: usize,
+    line: usize,
+    column: usize,
+    out_x: *mut f32,
+    out_y: *mut f32,
+) -> ResultCode {
+    with_renderer(handle, ResultCode::ErrorNull, |renderer| {
+        with_editor(editor, ResultCode::ErrorNull, |editor| {
+            if out_x.is_null() || out_y.is_null() {
+                return ResultCode::ErrorNull;
+            }
+
+            match renderer.caret_position(editor.rope(), first_line, Position::new(line, column)) {
+                Some(caret) => {
+                    *out_x = caret.x;
+                    *out_y = caret.y;
+                    ResultCode::Success
+                }
+                None => ResultCode::ErrorOutOfBounds,
+            }
+        })
+    })
+}
+
+/// Renders the viewport to a PNG image (headless, CPU only)
+///
+/// # Safety
+/// - `handle` must be a valid renderer pointer
+/// - `editor` must be a valid editor pointer
+/// - `out_len` must be a valid pointer (receives the byte count)
+/// - Caller must free the returned buffer with `editor_free_bytes()`
+///
+/// A `height` of 0 fits the image to the remaining lines.
+/// Returns null on error
+#[no_mangle]
+pub unsafe extern "C" fn renderer_render_png(
+    handle: RendererHandle,
+    editor: EditorHandle,
+    first_line: usize,
+    width: u32,
+    height: u32,
+    scale: f32,
+    out_len: *mut usize,
+) -> *mut u8 {
+    with_renderer(handle, ptr::null_mut(), |renderer| {
+        with_editor(editor, ptr::null_mut(), |editor| {
+            if out_len.is_null() || width == 0 {
+                return ptr::null_mut();
+            }
+
+            let options = RasterOptions {
+                width,
+                height: (height > 0).then_some(height),
+                scale: if scale > 0.0 { scale } else { 1.0 },
+                first_line,
+                ..RasterOptions::default()
+            };
+
+            match renderer.render_png(editor, &options) {
+                Ok(bytes) => {
+                    let boxed = bytes.into_boxed_slice();
+                    *out_len = boxed.len();
+                    Box::into_raw(boxed) as *mut u8
+                }
+                Err(_) => ptr::null_mut(),
+            }
+        })
+    })
+}
+
+// ==================================================================
+// Large Files
+// ==================================================================
+
+/// Opens a file for editing (large-file mode above the size threshold)
+///
+/// # Safety
+/// - `path` must be a valid null-terminated UTF-8 string
+/// - Returns an opaque pointer that must be freed with `editor_free()`
+///
+/// Returns null on error or if the file is huge (use `large_file_open()`)
+#[no_mangle]
+pub unsafe extern "C" fn editor_open_file(path: *const c_char) -> EditorHandle {
+    ffi_guard(|| {
+        if path.is_null() {
+            return ptr::null_mut();
+        }
+
+        let path = match CStr::from_ptr(path).to_str() {
+            Ok(s) => s,
+            Err(_) => return ptr::null_mut(),
+        };
+
+        match Editor::open_file(std::path::Path::new(path), LargeFileConfig::default()) {
+            Ok(editor) => EDITORS.insert(EditorEntry::Owned(Mutex::new(editor))),
+            Err(_) => ptr::null_mut(),
+        }
+    })
+}
+
+/// Gets the features degraded by large-file mode or read-only viewing
+///
+/// # Safety
+/// - `handle` must be a valid editor pointer
+/// - Caller must free the returned string with `editor_free_string()`
+///
+/// Returns a JSON `{sizeClass, readOnly, highlighting, disabled}`, or null on error
+#[no_mangle]
+pub unsafe extern "C" fn editor_degraded_features(handle: EditorHandle) -> *mut c_char {
+    with_editor(handle, ptr::null_mut(), |editor| {
+        json_c_string(&editor.degraded_features())
+    })
+}
+
+/// Makes the document read-only (non-zero) or editable (0)
+///
+/// # Safety
+/// - `handle` must be a valid editor pointer
+#[no_mangle]
+pub unsafe extern "C" fn editor_set_read_only(handle: EditorHandle, read_only: i32) -> ResultCode {
+    with_editor(handle, ResultCode::ErrorNull, |editor| {
+        editor.set_read_only(read_only != 0);
+        ResultCode::Success
+    })
+}
+
+/// Appends streamed text at the end of the document (tail -f)
+///
+/// # Safety
+/// - `handle` must be a valid editor pointer
+/// - `text` must be a valid null-terminated UTF-8 string
+#[no_mangle]
+pub unsafe extern "C" fn editor_append_streamed(handle: EditorHandle, text: *const c_char) -> ResultCode {
+    with_editor(handle, ResultCode::ErrorNull, |editor| {
+        if text.is_null() {
+            return ResultCode::ErrorNull;
+        }
+
+        match CStr::from_ptr(text).to_str() {
+            Ok(text) => {
+                editor.append_streamed(text);
+                ResultCode::Success
+            }
+            Err(_) => ResultCode::ErrorInvalidUtf8,
+        }
+    })
+}
+
+/// Classifies a file by size
+///
+/// # Safety
+/// - `path` must be a valid null-terminated UTF-8 string
+///
+/// Returns 0 (normal), 1 (large), 2 (huge) or -1 on error
+#[no_mangle]
+pub unsafe extern "C" fn large_file_classify(path: *const c_char) -> i32 {
+    ffi_guard(|| {
+        if path.is_null() {
+            return -1;
+        }
+
+        let Ok(path) = CStr::from_ptr(path).to_str() else {
+            return -1;
+        };
+
+        match LargeFileConfig::default().classify_path(std::path::Path::new(path)) {
+            Ok(FileSizeClass::Normal) => 0,
+            Ok(FileSizeClass::Large) => 1,
+            Ok(FileSizeClass::Huge) => 2,
+            Err(_) => -1,
+        }
+    })
+}
+
+/// Opens a memory-mapped, read-only view of a file
+///
+/// # Safety
+/// - `path` must be a valid null-terminated UTF-8 string
+/// - Caller must free the view with `large_file_free()`
+///
+/// Returns an opaque pointer, or null on error
+#[no_mangle]
+pub unsafe extern "C" fn large_file_open(path: *const c_char) -> LargeFileHandle {
+    ffi_guard(|| {
+        if path.is_null() {
+            return ptr::null_mut();
+        }
+
+        let path = match CStr::from_ptr(path).to_str() {
+            Ok(s) => s,
+            Err(_) => return ptr::null_mut(),
+        };
+
+        match LargeFileView::open(std::path::Path::new(path), LargeFileConfig::default()) {
+            Ok(view) => LARGE_FILES.insert(Mutex::new(view)),
+            Err(_) => ptr::null_mut(),
+        }
+    })
+}
+
+/// Frees a large file view
+///
+/// # Safety
+/// - `handle` must be a valid view pointer or null
+/// - `handle` must not be used after calling this function
+#[no_mangle]
+pub unsafe extern "C" fn large_file_free(handle: LargeFileHandle) {
+    ffi_guard(|| {
+        LARGE_FILES.remove(handle);
+    })
+}
+
+/// Gets the line count (estimated until the file is fully indexed)
+///
+/// # Safety
+/// - `handle` must be a valid view pointer
+/// - `out_exact` may be null; otherwise receives 1 if the count is exact
+#[no_mangle]
+pub unsafe extern "C" fn large_file_line_count(handle: LargeFileHandle, out_exact: *mut i32) -> usize {
+    with_large_file(handle, 0, |view| {
+        if !out_exact.is_null() {
+            *out_exact = view.is_fully_indexed() as i32;
+        }
+        view.estimated_line_count()
+    })
+}
+
+/// Indexes the next chunk of the file (call repeatedly in the background)
+///
+/// # Safety
+/// - `handle` must be a valid view pointer
+///
+/// Returns 1 when the whole file is indexed, 0 if more remains, -1 on error
+#[no_mangle]
+pub unsafe extern "C" fn large_file_index_step(handle: LargeFileHandle) -> i32 {
+    with_large_file(handle, -1, |view| {
+        view.index_step() as i32
+    })
+}
+
+/// Gets a range of lines
+///
+/// # Safety
+/// - `handle` must be a valid view pointer
+/// - Caller must free the returned string with `editor_free_string()`
+///
+/// Returns a JSON array of line strings (without line endings), or null on error
+#[no_mangle]
+pub unsafe extern "C" fn large_file_get_lines(
+    handle: LargeFileHandle,
+    start_line: usize,
+    count: usize,
+) -> *mut c_char {
+    with_large_file(handle, ptr::null_mut(), |view| {
+        json_c_string(&view.lines(start_line, count))
+    })
+}
+
+/// Gets syntax highlights for a line range, parsing only around it
+///
+/// # Safety
+/// - `handle` must be a valid view pointer
+/// - `language_id` must be a valid null-terminated UTF-8 string
+/// - Caller must free the returned string with `editor_free_string()`
+///
+/// Returns a JSON array of `HighlightSpan`s, or null on error
+#[no_mangle]
+pub unsafe extern "C" fn large_file_highlights(
+    handle: LargeFileHandle,
+    language_id: *const c_char,
+    start_line: usize,
+    end_line: usize,
+) -> *mut c_char {
+    with_large_file(handle, ptr::null_mut(), |view| {
+        if language_id.is_null() {
+            return ptr::null_mut();
+        }
+
+        let language = match CStr::from_ptr(language_id).to_str() {
+            Ok(s) => LanguageId::parse(s),
+            Err(_) => return ptr::null_mut(),
+        };
+
+        json_c_string(&view.highlights(&language, start_line, end_line))
+    })
+}
+
+/// Picks up bytes appended to the file since the last refresh (tail -f)
+///
+/// # Safety
+/// - `handle` must be a valid view pointer
+///
+/// Returns the number of appended bytes (0 if unchanged), -2 if the file
+/// shrank (the view was reset), or -1 on error
+#[no_mangle]
+pub unsafe extern "C" fn large_file_refresh(handle: LargeFileHandle) -> i64 {
+    with_large_file(handle, -1, |view| {
+        match view.refresh() {
+            Ok(RefreshStatus::Unchanged) => 0,
+            Ok(RefreshStatus::Appended(bytes)) => bytes as i64,
+            Ok(RefreshStatus::Truncated) => -2,
+            Err(_) => -1,
+        }
+    })
+}
+
+/// Gets the features degraded for a large file view
+///
+/// # Safety
+/// - `handle` must be a valid view pointer
+/// - Caller must free the returned string with `editor_free_string()`
+///
+/// Returns a JSON `{sizeClass, readOnly, highlighting, disabled}`, or null on error
+#[no_mangle]
+pub unsafe extern "C" fn large_file_degraded_features(handle: LargeFileHandle) -> *mut c_char {
+    with_large_file(handle, ptr::null_mut(), |view| {
+        json_c_string(&view.degraded_features())
+    })
+}
+
+// ==================================================================
+// Workspace
+// ==================================================================
+
+/// Creates an empty workspace
+///
+/// # Safety
+/// Returns an opaque pointer that must be freed with `workspace_free()`
+#[no_mangle]
+pub unsafe extern "C" fn workspace_new() -> WorkspaceHandle {
+    ffi_guard(|| {
+        WORKSPACES.insert(Mutex::new(Workspace::new()))
+    })
+}
+
+/// Frees a workspace and all its buffers
+///
+/// # Safety
+/// - `handle` must be a valid workspace pointer or null
+/// - `handle` and editors borrowed from it must not be used afterwards
+#[no_mangle]
+pub unsafe extern "C" fn workspace_free(handle: WorkspaceHandle) {
+    ffi_guard(|| handles::free_workspace(handle))
+}
+
+/// Opens a file, or finds the buffer already showing it
+///
+/// # Safety
+/// - `handle` must be a valid workspace pointer
+/// - `path` must be a valid null-terminated UTF-8 string
+///
+/// Returns the buffer id, or -1 on error
+#[no_mangle]
+pub unsafe extern "C" fn workspace_open_file(handle: WorkspaceHandle, path: *const c_char) -> i64 {
+    with_workspace(handle, -1, |workspace| {
+        if path.is_null() {
+            return -1;
+        }
+
+        let Ok(path) = CStr::from_ptr(path).to_str() else {
+            return -1;
+        };
+
+        match workspace.open_file(std::path::Path::new(path)) {
+            Ok(id) => id as i64,
+            Err(_) => -1,
+        }
+    })
+}
+
+/// Opens an unsaved document
+///
+/// # Safety
+/// - `handle` must be a valid workspace pointer
+/// - `content` and `language_id` must be valid null-terminated UTF-8 strings
+///
+/// Returns the buffer id, or -1 on error
+#[no_mangle]
+pub unsafe extern "C" fn workspace_open_untitled(
+    handle: WorkspaceHandle,
+    content: *const c_char,
+    language_id: *const c_char,
+) -> i64 {
+    with_workspace(handle, -1, |workspace| {
+        if content.is_null() || language_id.is_null() {
+            return -1;
+        }
+
+        let (Ok(content), Ok(language)) = (CStr::from_ptr(content).to_str(), CStr::from_ptr(language_id).to_str()) else {
+            return -1;
+        };
+
+        match workspace.open_untitled(content, LanguageId::parse(language)) {
+            Ok(id) => id as i64,
+            Err(_) => -1,
+        }
+    })
+}
+
+/// Finds the buffer of a file
+///
+/// # Safety
+/// - `handle` must be a valid workspace pointer
+/// - `path` must be a valid null-terminated UTF-8 string
+///
+/// Returns the buffer id, or -1 if the file is not open
+#[no_mangle]
+pub unsafe extern "C" fn workspace_find_by_path(handle: WorkspaceHandle, path: *const c_char) -> i64 {
+    with_workspace(handle, -1, |workspace| {
+        if path.is_null() {
+            return -1;
+        }
+
+        let Ok(path) = CStr::from_ptr(path).to_str() else {
+            return -1;
+        };
+
+        workspace
+            .find_by_path(std::path::Path::new(path))
+            .map_or(-1, |id| id as i64)
+    })
+}
+
+/// Gets the editor of a buffer
+///
+/// # Safety
+/// - `handle` must be a valid workspace pointer
+/// - The editor is owned by the workspace: the handle is valid until its
+///   buffer is closed or the workspace is freed (`editor_free()` only
+///   unregisters it)
+///
+/// Returns an editor handle usable with the `editor_*` functions (the same
+/// one for every call on a buffer), or null
+#[no_mangle]
+pub unsafe extern "C" fn workspace_buffer_editor(handle: WorkspaceHandle, buffer: u64) -> EditorHandle {
+    handles::buffer_editor(handle, |workspace| workspace.buffer(buffer).map(|b| b.id()))
+}
+
+/// Opens a new view (split pane) on a buffer
+///
+/// # Safety
+/// - `handle` must be a valid workspace pointer
+///
+/// Returns the view id, or -1 on error
+#[no_mangle]
+pub unsafe extern "C" fn workspace_create_view(handle: WorkspaceHandle, buffer: u64) -> i64 {
+    with_workspace(handle, -1, |workspace| {
+        match workspace.create_view(buffer) {
+            Ok(id) => id as i64,
+            Err(_) => -1,
+        }
+    })
+}
+
+/// Activates a view, restoring its cursor and selection into the editor
+///
+/// # Safety
+/// - `handle` must be a valid workspace pointer
+/// - The returned editor is owned by the workspace (see
+///   `workspace_buffer_editor()`)
+///
+/// Returns the buffer's editor handle, or null on error
+#[no_mangle]
+pub unsafe extern "C" fn workspace_activate_view(handle: WorkspaceHandle, view: u64) -> EditorHandle {
+    handles::buffer_editor(handle, |workspace| {
+        workspace.activate_view(view).ok()?;
+        workspace.view(view).map(|v| v.buffer)
+    })
+}
+
+/// Lists the open buffers
+///
+/// # Safety
+/// - `handle` must be a valid workspace pointer
+/// - Caller must free the returned string with `editor_free_string()`
+///
+/// Returns a JSON array of `{id, uri, path, language, version, dirty,
+/// views, closePending}`, or null on error
+#[no_mangle]
+pub unsafe extern "C" fn workspace_list_buffers(handle: WorkspaceHandle) -> *mut c_char {
+    with_workspace(handle, ptr::null_mut(), |workspace| {
+        let buffers: Vec<_> = workspace.buffers().map(|buffer| buffer.info()).collect();
+
+        json_c_string(&buffers)
+    })
+}
+
+/// Gets the ids of buffers with unsaved changes
+///
+/// # Safety
+/// - `handle` must be a valid workspace pointer
+/// - Caller must free the returned string with `editor_free_string()`
+///
+/// Returns a JSON array of buffer ids, or null on error
+#[no_mangle]
+pub unsafe extern "C" fn workspace_dirty_buffers(handle: WorkspaceHandle) -> *mut c_char {
+    with_workspace(handle, ptr::null_mut(), |workspace| {
+        json_c_string(&workspace.dirty_buffers())
+    })
+}
+
+/// Saves every dirty buffer
+///
+/// # Safety
+/// - `handle` must be a valid workspace pointer
+/// - Caller must free the returned string with `editor_free_string()`
+///
+/// Returns a JSON `{saved: [id], failed: [{buffer, error}]}`, or null on error
+#[no_mangle]
+pub unsafe extern "C" fn workspace_save_all(handle: WorkspaceHandle) -> *mut c_char {
+    with_workspace(handle, ptr::null_mut(), |workspace| {
+        json_c_string(&workspace.save_all())
+    })
+}
+
+/// Helper: Maps a close outcome to an FFI code
+fn close_outcome_code(outcome: anyhow::Result<CloseOutcome>) -> i32 {
+    match outcome {
+        Ok(CloseOutcome::Closed) => 0,
+        Ok(CloseOutcome::NeedsConfirmation) => 1,
+        Ok(CloseOutcome::Cancelled) => 2,
+        Err(_) => -1,
+    }
+}
+
+/// Closes a view (closing the buffer with its last view)
+///
+/// # Safety
+/// - `handle` must be a valid workspace pointer
+///
+/// Returns 0 if closed, 1 if the buffer is dirty and needs confirmation
+/// (answer with `workspace_resolve_close()`), -1 on error
+#[no_mangle]
+pub unsafe extern "C" fn workspace_close_view(handle: WorkspaceHandle, view: u64) -> i32 {
+    with_workspace(handle, -1, |workspace| {
+        close_outcome_code(workspace.close_view(view))
+    })
+}
+
+/// Closes a buffer and all its views
+///
+/// # Safety
+/// - `handle` must be a valid workspace pointer
+///
+/// Returns 0 if closed, 1 if it needs confirmation, -1 on error
+#[no_mangle]
+pub unsafe extern "C" fn workspace_close_buffer(handle: WorkspaceHandle, buffer: u64) -> i32 {
+    with_workspace(handle, -1, |workspace| {
+        close_outcome_code(workspace.close_buffer(buffer))
+    })
+}
+
+/// Answers a close confirmation prompt
+///
+/// # Safety
+/// - `handle` must be a valid workspace pointer
+///
+/// `choice`: 0 = save, 1 = discard, 2 = cancel.
+/// Returns 0 if closed, 2 if cancelled, -1 on error (e.g. the save failed)
+#[no_mangle]
+pub unsafe extern "C" fn workspace_resolve_close(handle: WorkspaceHandle, buffer: u64, choice: i32) -> i32 {
+    with_workspace(handle, -1, |workspace| {
+        let choice = match choice {
+            0 => CloseChoice::Save,
+            1 => CloseChoice::Discard,
+            2 => CloseChoice::Cancel,
+            _ => return -1,
+        };
+        close_outcome_code(workspace.resolve_close(buffer, choice))
+    })
+}
+
+// ==================================================================
+// Crash Recovery
+// ==================================================================
+
+/// Starts a recovery session under a journal directory
+///
+/// # Safety
+/// - `root` must be a valid null-terminated UTF-8 string
+/// - Caller must end the session with `recovery_journal_finish()` (or
+///   `recovery_journal_free()`, which leaves it recoverable)
+///
+/// `edit_log` selects the edit log format (non-zero) or full content (0).
+/// Returns an opaque pointer, or null on error
+#[no_mangle]
+pub unsafe extern "C" fn recovery_journal_create(root: *const c_char, edit_log: i32) -> RecoveryHandle {
+    ffi_guard(|| {
+        if root.is_null() {
+            return ptr::null_mut();
+        }
+
+        let Ok(root) = CStr::from_ptr(root).to_str() else {
+            return ptr::null_mut();
+        };
+        let format = if edit_log != 0 {
+            RecoveryFormat::EditLog
+        } else {
+            RecoveryFormat::FullContent
+        };
+
+        match RecoveryJournal::create(std::path::Path::new(root), format) {
+            Ok(journal) => RECOVERY_JOURNALS.insert(Mutex::new(journal)),
+            Err(_) => ptr::null_mut(),
+        }
+    })
+}
+
+/// Gets the id of the running session
+///
+/// # Safety
+/// - `handle` must be a valid journal pointer
+/// - Caller must free the returned string with `editor_free_string()`
+#[no_mangle]
+pub unsafe extern "C" fn recovery_journal_session_id(handle: RecoveryHandle) -> *mut c_char {
+    with_recovery_journal(handle, ptr::null_mut(), |journal| {
+        c_string(journal.session_id())
+    })
+}
+
+/// Writes the dirty buffers of a workspace (call periodically)
+///
+/// # Safety
+/// - `handle` must be a valid journal pointer
+/// - `workspace` must be a valid workspace pointer
+///
+/// Returns the number of buffers written, or -1 on error
+#[no_mangle]
+pub unsafe extern "C" fn recovery_journal_write(handle: RecoveryHandle, workspace: WorkspaceHandle) -> i32 {
+    with_recovery_journal(handle, -1, |journal| {
+        with_workspace(workspace, -1, |workspace| {
+            match journal.write(workspace) {
+                Ok(written) => written as i32,
+                Err(_) => -1,
+            }
+        })
+    })
+}
+
+/// Ends the session and frees the journal
+///
+/// # Safety
+/// - `handle` must be a valid journal pointer; it is freed by this call
+/// - `workspace` must be a valid workspace pointer
+///
+/// `hot_exit` (non-zero) keeps the dirty buffers for the next start;
+/// otherwise the session is deleted.
+#[no_mangle]
+pub unsafe extern "C" fn recovery_journal_finish(
+    handle: RecoveryHandle,
+    workspace: WorkspaceHandle,
+    hot_exit: i32,
+) -> ResultCode {
+    if handle.is_null() {
+        return ResultCode::ErrorNull;
+    }
+
+    with_workspace(workspace, ResultCode::ErrorNull, |workspace| {
+        let Some(journal) = handles::take_recovery_journal(handle) else {
+            return ResultCode::ErrorInvalidHandle;
+        };
+
+        match journal.finish(workspace, hot_exit != 0) {
+            Ok(()) => ResultCode::Success,
+            Err(_) => ResultCode::ErrorUnknown,
+        }
+    })
+}
+
+/// Frees the journal without ending the session (it stays recoverable)
+///
+/// # Safety
+/// - `handle` must be a valid journal pointer or null
+/// - `handle` must not be used after calling this function
+#[no_mangle]
+pub unsafe extern "C" fn recovery_journal_free(handle: RecoveryHandle) {
+    ffi_guard(|| {
+        RECOVERY_JOURNALS.remove(handle);
+    })
+}
+
+/// Lists recoverable sessions, newest first
+///
+/// # Safety
+/// - `root` must be a valid null-terminated UTF-8 string
+/// - `exclude_session` may be null; otherwise a session id to leave out
+/// - Caller must free the returned string with `editor_free_string()`
+///
+/// Returns a JSON array of `{id, startedAtMs, updatedAtMs, cleanExit,
+/// buffers: [{key, uri, path, language, version, writtenAtMs}]}`, or null
+#[no_mangle]
+pub unsafe extern "C" fn recovery_list_sessions(root: *const c_char, exclude_session: *const c_char) -> *mut c_char {
+    ffi_guard(|| {
+        if root.is_null() {
+            return ptr::null_mut();
+        }
+
+        let Ok(root) = CStr::from_ptr(root).to_str() else {
+            return ptr::null_mut();
+        };
+        let exclude = if exclude_session.is_null() {
+            None
+        } else {
+            match CStr::from_ptr(exclude_session).to_str() {
+                Ok(s) => Some(s),
+                Err(_) => return ptr::null_mut(),
+            }
+        };
+
+        match recovery::list_sessions(std::path::Path::new(root), exclude) {
+            Ok(sessions) => json_c_string(&sessions),
+            Err(_) => ptr::null_mut(),
+        }
+    })
+}
+
+/// Diffs a recovered buffer against its file on disk
+///
+/// # Safety
+/// - `root`, `session_id` and `key` must be valid null-terminated UTF-8 strings
+/// - Caller must free the returned string with `editor_free_string()`
+///
+/// Returns a JSON array of hunks `{oldStart, oldCount, newStart, newCount,
+/// lines: [{op, text, oldLine, newLine}]}`, or null on error
+#[no_mangle]
+pub unsafe extern "C" fn recovery_diff(
+    root: *const c_char,
+    session_id: *const c_char,
+    key: *const c_char,
+    context: usize,
+) -> *mut c_char {
+    ffi_guard(|| {
+        if root.is_null() || session_id.is_null() || key.is_null() {
+            return ptr::null_mut();
+        }
+
+        let (Ok(root), Ok(session_id), Ok(key)) = (
+            CStr::from_ptr(root).to_str(),
+            CStr::from_ptr(session_id).to_str(),
+            CStr::from_ptr(key).to_str(),
+        ) else {
+            return ptr::null_mut();
+        };
+
+        match recovery::diff_with_disk(std::path::Path::new(root), session_id, key, context) {
+            Ok(hunks) => json_c_string(&hunks),
+            Err(_) => ptr::null_mut(),
+        }
+    })
+}
+
+/// Restores every buffer of a session into a workspace
+///
+/// # Safety
+/// - `root` and `session_id` must be valid null-terminated UTF-8 strings
+/// - `workspace` must be a valid workspace pointer
+/// - Caller must free the returned string with `editor_free_string()`
+///
+/// Returns a JSON array of restored buffer ids, or null on error
+#[no_mangle]
+pub unsafe extern "C" fn recovery_restore_session(
+    root: *const c_char,
+    session_id: *const c_char,
+    workspace: WorkspaceHandle,
+) -> *mut c_char {
+    with_workspace(workspace, ptr::null_mut(), |workspace| {
+        if root.is_null() || session_id.is_null() {
+            return ptr::null_mut();
+        }
+
+        let (Ok(root), Ok(session_id)) = (CStr::from_ptr(root).to_str(), CStr::from_ptr(session_id).to_str()) else {
+            return ptr::null_mut();
+        };
+
+        match recovery::restore_session(std::path::Path::new(root), session_id, workspace) {
+            Ok(ids) => json_c_string(&ids),
+            Err(_) => ptr::null_mut(),
+        }
+    })
+}
+
+/// Deletes a recovery session
+///
+/// # Safety
+/// - `root` and `session_id` must be valid null-terminated UTF-8 strings
+#[no_mangle]
+pub unsafe extern "C" fn recovery_delete_session(root: *const c_char, session_id: *const c_char) -> ResultCode {
+    ffi_guard(|| {
+        if root.is_null() || session_id.is_null() {
+            return ResultCode::ErrorNull;
+        }
+
+        let (Ok(root), Ok(session_id)) = (CStr::from_ptr(root).to_str(), CStr::from_ptr(session_id).to_str()) else {
+            return ResultCode::ErrorInvalidUtf8;
+        };
+
+        match recovery::delete_session(std::path::Path::new(root), session_id) {
+            Ok(()) => ResultCode::Success,
+            Err(_) => ResultCode::ErrorUnknown,
+        }
+    })
+}
+
+// ==================================================================
+// Background Parsing
+// ==================================================================
+
+/// Sets the parsing budgets
+///
+/// # Safety
+/// - `handle` must be a valid editor pointer
+///
+/// Documents up to `inline_bytes` parse inline after every edit; bigger
+/// ones parse inline for at most `inline_budget_us` microseconds (0 =
+/// always in the background) before moving to the background worker,
+/// which gives up after `background_timeout_ms`. `background` = 0 makes
+/// every parse inline.
+#[no_mangle]
+pub unsafe extern "C" fn editor_set_parse_config(
+    handle: EditorHandle,
+    inline_bytes: usize,
+    inline_budget_us: u64,
+    background_timeout_ms: u64,
+    background: i32,
+) -> ResultCode {
+    with_editor(handle, ResultCode::ErrorNull, |editor| {
+        editor.set_parse_config(ParseConfig {
+            inline_bytes,
+            inline_budget: std::time::Duration::from_micros(inline_budget_us),
+            background_timeout: std::time::Duration::from_millis(background_timeout_ms),
+            background: background != 0,
+        });
+        ResultCode::Success
+    })
+}
+
+/// Installs finished background parses (call periodically, e.g. per frame)
+///
+/// # Safety
+/// - `handle` must be a valid editor pointer
+///
+/// Returns 1 if a new syntax tree was installed (refresh highlights),
+/// 0 if not, -1 on error
+#[no_mangle]
+pub unsafe extern "C" fn editor_poll_parse(handle: EditorHandle) -> i32 {
+    with_editor(handle, -1, |editor| editor.poll_parse() as i32)
+}
+
+/// Waits for the background parse of the current text
+///
+/// # Safety
+/// - `handle` must be a valid editor pointer
+///
+/// Returns 1 if no parse is pending anymore, 0 on timeout, -1 on error
+#[no_mangle]
+pub unsafe extern "C" fn editor_wait_for_parse(handle: EditorHandle, timeout_ms: u64) -> i32 {
+    with_editor(handle, -1, |editor| {
+        editor.wait_for_parse(std::time::Duration::from_millis(timeout_ms)) as i32
+    })
+}
+
+/// Gets operation timings (parse durations and cancellations)
+///
+/// # Safety
+/// - `handle` must be a valid editor pointer
+/// - Caller must free the returned string with `editor_free_string()`
+///
+/// Returns `{avgParseMs, p95ParseMs, parseCount, cancelledParses, ...}`
+/// as JSON, or null on error
+#[no_mangle]
+pub unsafe extern "C" fn editor_performance_stats(handle: EditorHandle) -> *mut c_char {
+    with_editor(handle, ptr::null_mut(), |editor| {
+        json_c_string(&editor.performance_metrics().get_stats())
+    })
+}
+
+// ==================================================================
+// Telemetry
+// ==================================================================
+
+/// Exports the latency histograms as JSON
+///
+/// Histograms are process-wide (every editor, renderer and FFI call).
+///
+/// # Safety
+/// - `handle` must be a valid editor pointer, or null to skip memory usage
+/// - Caller must free the returned string with `editor_free_string()`
+///
+/// Returns `{operations: {name: {count, meanUs, p50Us, p95Us, p99Us,
+/// p999Us, maxUs}}, memory?: {textBytes, ropeCapacityBytes, ...}}`,
+/// or null on error
+#[no_mangle]
+pub unsafe extern "C" fn editor_telemetry_snapshot(handle: EditorHandle) -> *mut c_char {
+    if handle.is_null() {
+        return ffi_guard(|| json_c_string(&telemetry::snapshot(None)));
+    }
+    with_editor(handle, ptr::null_mut(), |editor| json_c_string(&telemetry::snapshot(Some(editor))))
+}
+
+/// Clears the latency histograms
+#[no_mangle]
+pub extern "C" fn editor_telemetry_reset() -> ResultCode {
+    ffi_guard(|| {
+        telemetry::reset();
+        ResultCode::Success
+    })
+}
+
+// ==================================================================
+// Collaboration
+// ==================================================================
+
+/// Helper: Hands a byte buffer to the host
+unsafe fn bytes_to_host(bytes: Vec<u8>, out_len: *mut usize) -> *mut u8 {
+    let boxed = bytes.into_boxed_slice();
+    *out_len = boxed.len();
+    Box::into_raw(boxed) as *mut u8
+}
+
+/// Starts collaborating on the current content
+///
+/// # Safety
+/// - `handle` must be a valid editor pointer
+///
+/// Every replica must start from the same content. `replica` must be
+/// unique in the session and not 0.
+#[no_mangle]
+pub unsafe extern "C" fn editor_collab_enable(handle: EditorHandle, replica: u64) -> ResultCode {
+    with_editor(handle, ResultCode::ErrorNull, |editor| {
+        match editor.enable_collaboration(replica) {
+            Ok(()) => ResultCode::Success,
+            Err(_) => ResultCode::ErrorUnknown,
+        }
+    })
+}
+
+/// Stops collaborating
+///
+/// # Safety
+/// - `handle` must be a valid editor pointer
+#[no_mangle]
+pub unsafe extern "C" fn editor_collab_disable(handle: EditorHandle) -> ResultCode {
+    with_editor(handle, ResultCode::ErrorNull, |editor| {
+        editor.disable_collaboration();
+        ResultCode::Success
+    })
+}
+
+/// Takes the operations made locally since the last call, encoded
+///
+/// # Safety
+/// - `handle` must be a valid editor pointer
+/// - `out_len` must be a valid pointer (receives the byte count)
+/// - Caller must free the returned buffer with `editor_free_bytes()`
+///
+/// Returns the encoded operations (send them to every other replica),
+/// or null on error
+#[no_mangle]
+pub unsafe extern "C" fn editor_collab_take_ops(handle: EditorHandle, out_len: *mut usize) -> *mut u8 {
+    with_editor(handle, ptr::null_mut(), |editor| {
+        if out_len.is_null() {
+            return ptr::null_mut();
+        }
+
+        bytes_to_host(CrdtOp::encode(&editor.take_collab_ops()), out_len)
+    })
+}
+
+/// Applies operations received from another replica
+///
+/// # Safety
+/// - `handle` must be a valid editor pointer
+/// - `data` must point to `len` readable bytes
+///
+/// Returns the number of operations waiting for missing ones, or -1 on
+/// error
+#[no_mangle]
+pub unsafe extern "C" fn editor_collab_apply_ops(handle: EditorHandle, data: *const u8, len: usize) -> i32 {
+    with_editor(handle, -1, |editor| {
+        if data.is_null() {
+            return -1;
+        }
+
+        let Ok(ops) = CrdtOp::decode(std::slice::from_raw_parts(data, len)) else {
+            return -1;
+        };
+        match editor.apply_collab_ops(ops) {
+            Ok(pending) => pending as i32,
+            Err(_) => -1,
+        }
+    })
+}
+
+/// Gets the number of operations integrated from each replica
+///
+/// # Safety
+/// - `handle` must be a valid editor pointer
+/// - Caller must free the returned string with `editor_free_string()`
+///
+/// Returns `{"<replica>": count, ...}` as JSON, or null if not
+/// collaborating
+#[no_mangle]
+pub unsafe extern "C" fn editor_collab_versions(handle: EditorHandle) -> *mut c_char {
+    with_editor(handle, ptr::null_mut(), |editor| {
+        let Some(versions) = editor.collab_versions() else {
+            return ptr::null_mut();
+        };
+        json_c_string(&versions)
+    })
+}
+
+/// Gets the operations another replica is missing (after reconnecting)
+///
+/// # Safety
+/// - `handle` must be a valid editor pointer
+/// - `versions_json` must be a valid null-terminated UTF-8 string (the
+///   other replica's `editor_collab_versions()`)
+/// - `out_len` must be a valid pointer (receives the byte count)
+/// - Caller must free the returned buffer with `editor_free_bytes()`
+///
+/// Returns the encoded operations, or null on error (including when some
+/// were trimmed from the log: send `editor_collab_snapshot()` instead)
+#[no_mangle]
+pub unsafe extern "C" fn editor_collab_ops_since(
+    handle: EditorHandle,
+    versions_json: *const c_char,
+    out_len: *mut usize,
+) -> *mut u8 {
+    with_editor(handle, ptr::null_mut(), |editor| {
+        if versions_json.is_null() || out_len.is_null() {
+            return ptr::null_mut();
+        }
+
+        let Ok(versions_json) = CStr::from_ptr(versions_json).to_str() else {
+            return ptr::null_mut();
+        };
+        let Ok(versions) = serde_json::from_str::<VersionVector>(versions_json) else {
+            return ptr::null_mut();
+        };
+
+        match editor.collab_ops_since(&versions) {
+            Ok(ops) => bytes_to_host(CrdtOp::encode(&ops), out_len),
+            Err(_) => ptr::null_mut(),
+        }
+    })
+}
+
+/// Records the operations another replica reported integrating, so the
+/// log can drop what every peer has
+///
+/// # Safety
+/// - `handle` must be a valid editor pointer
+/// - `versions_json` must be a valid null-terminated UTF-8 string (the
+///   other replica's `editor_collab_versions()`)
+#[no_mangle]
+pub unsafe extern "C" fn editor_collab_acknowledge(
+    handle: EditorHandle,
+    replica: u64,
+    versions_json: *const c_char,
+) -> ResultCode {
+    with_editor(handle, ResultCode::ErrorNull, |editor| {
+        if versions_json.is_null() {
+            return ResultCode::ErrorNull;
+        }
+
+        let Ok(versions_json) = CStr::from_ptr(versions_json).to_str() else {
+            return ResultCode::ErrorInvalidUtf8;
+        };
+        let Ok(versions) = serde_json::from_str::<VersionVector>(versions_json) else {
+            return ResultCode::ErrorUnknown;
+        };
+        match editor.acknowledge_collab_versions(replica, &versions) {
+            Ok(()) => ResultCode::Success,
+            Err(_) => ResultCode::ErrorUnknown,
+        }
+    })
+}
+
+/// Forgets a replica that left the session
+///
+/// # Safety
+/// - `handle` must be a valid editor pointer
+#[no_mangle]
+pub unsafe extern "C" fn editor_collab_remove_peer(handle: EditorHandle, replica: u64) -> ResultCode {
+    with_editor(handle, ResultCode::ErrorNull, |editor| {
+        editor.remove_collab_peer(replica);
+        ResultCode::Success
+    })
+}
+
+/// Gets the collaboration state, encoded for a replica too far behind for
+/// `editor_collab_ops_since()`
+///
+/// # Safety
+/// - `handle` must be a valid editor pointer
+/// - `out_len` must be a valid pointer (receives the byte count)
+/// - Caller must free the returned buffer with `editor_free_bytes()`
+///
+/// Returns null if not collaborating
+#[no_mangle]
+pub unsafe extern "C" fn editor_collab_snapshot(handle: EditorHandle, out_len: *mut usize) -> *mut u8 {
+    with_editor(handle, ptr::null_mut(), |editor| {
+        if out_len.is_null() {
+            return ptr::null_mut();
+        }
+
+        match editor.collab_snapshot() {
+            Some(snapshot) => bytes_to_host(snapshot.encode(), out_len),
+            None => ptr::null_mut(),
+        }
+    })
+}
+
+/// Starts collaborating from another replica's snapshot (replaces the
+/// content and clears undo history)
+///
+/// # Safety
+/// - `handle` must be a valid editor pointer
+/// - `data` must point to `len` readable bytes
+#[no_mangle]
+pub unsafe extern "C" fn editor_collab_enable_from_snapshot(
+    handle: EditorHandle,
+    replica: u64,
+    data: *const u8,
+    len: usize,
+) -> ResultCode {
+    with_editor(handle, ResultCode::ErrorNull, |editor| {
+        if data.is_null() {
+            return ResultCode::ErrorNull;
+        }
+
+        let Ok(snapshot) = CollabSnapshot::decode(std::slice::from_raw_parts(data, len)) else {
+            return ResultCode::ErrorUnknown;
+        };
+        match editor.enable_collaboration_from_snapshot(replica, snapshot) {
+            Ok(()) => ResultCode::Success,
+            Err(_) => ResultCode::ErrorUnknown,
+        }
+    })
+}
+
+/// Gets the local cursor and selection, encoded for other replicas
+///
+/// # Safety
+/// - `handle` must be a valid editor pointer
+/// - `out_len` must be a valid pointer (receives the byte count)
+/// - Caller must free the returned buffer with `editor_free_bytes()`
+///
+/// Returns null if not collaborating
+#[no_mangle]
+pub unsafe extern "C" fn editor_collab_cursor_update(handle: EditorHandle, out_len: *mut usize) -> *mut u8 {
+    with_editor(handle, ptr::null_mut(), |editor| {
+        if out_len.is_null() {
+            return ptr::null_mut();
+        }
+
+        match editor.collab_cursor_update() {
+            Some(update) => bytes_to_host(update.encode(), out_len),
+            None => ptr::null_mut(),
+        }
+    })
+}
+
+/// Applies a cursor update received from another replica
+///
+/// # Safety
+/// - `handle` must be a valid editor pointer
+/// - `data` must point to `len` readable bytes
+#[no_mangle]
+pub unsafe extern "C" fn editor_collab_apply_cursor(handle: EditorHandle, data: *const u8, len: usize) -> ResultCode {
+    with_editor(handle, ResultCode::ErrorNull, |editor| {
+        if data.is_null() {
+            return ResultCode::ErrorNull;
+        }
+
+        let Ok(update) = CursorUpdate::decode(std::slice::from_raw_parts(data, len)) else {
+            return ResultCode::ErrorUnknown;
+        };
+        match editor.apply_cursor_update(update) {
+            Ok(()) => ResultCode::Success,
+            Err(_) => ResultCode::ErrorUnknown,
+        }
+    })
+}
+
+/// Gets the other replicas' cursors in the current text
+///
+/// # Safety
+/// - `handle` must be a valid editor pointer
+/// - Caller must free the returned string with `editor_free_string()`
+///
+/// Returns `[{replica, cursor: {line, column}, selection}]` as JSON, or
+/// null on error
+#[no_mangle]
+pub unsafe extern "C" fn editor_collab_remote_cursors(handle: EditorHandle) -> *mut c_char {
+    with_editor(handle, ptr::null_mut(), |editor| {
+        json_c_string(&editor.remote_cursors())
+    })
+}
+
+// ==================================================================
+// Dirty Diff
+// ==================================================================
+
+/// Sets the text the gutter markers compare against
+///
+/// # Safety
+/// - `handle` must be a valid editor pointer
+/// - `base` must be a valid null-terminated UTF-8 string, or null to
+///   compare against the saved content (updated by `editor_mark_saved()`)
+#[no_mangle]
+pub unsafe extern "C" fn editor_set_diff_base(handle: EditorHandle, base: *const c_char) -> ResultCode {
+    with_editor(handle, ResultCode::ErrorNull, |editor| {
+        if base.is_null() {
+            editor.set_diff_base_to_saved();
+            return ResultCode::Success;
+        }
+
+        match CStr::from_ptr(base).to_str() {
+            Ok(base) => {
+                editor.set_diff_base(base);
+                ResultCode::Success
+            }
+            Err(_) => ResultCode::ErrorInvalidUtf8,
+        }
+    })
+}
+
+/// Removes the diff base
+///
+/// # Safety
+/// - `handle` must be a valid editor pointer
+#[no_mangle]
+pub unsafe extern "C" fn editor_clear_diff_base(handle: EditorHandle) -> ResultCode {
+    with_editor(handle, ResultCode::ErrorNull, |editor| {
+        editor.clear_diff_base();
+        ResultCode::Success
+    })
+}
+
+/// Gets gutter markers for the visible lines
+///
+/// # Safety
+/// - `handle` must be a valid editor pointer
+/// - Caller must free the returned string with `editor_free_string()`
+///
+/// Returns `[{line, kind: "added"|"modified"|"deleted"}]` as JSON, or
+/// null on error
+#[no_mangle]
+pub unsafe extern "C" fn editor_gutter_markers(
+    handle: EditorHandle,
+    first_line: usize,
+    last_line: usize,
+) -> *mut c_char {
+    with_editor(handle, ptr::null_mut(), |editor| {
+        json_c_string(&editor.gutter_markers(first_line, last_line))
+    })
+}
+
+/// Gets the changed blocks against the diff base
+///
+/// # Safety
+/// - `handle` must be a valid editor pointer
+/// - Caller must free the returned string with `editor_free_string()`
+///
+/// Returns `[{kind, old: {start, end}, new: {start, end}}]` as JSON, or
+/// null on error
+#[no_mangle]
+pub unsafe extern "C" fn editor_diff_changes(handle: EditorHandle) -> *mut c_char {
+    with_editor(handle, ptr::null_mut(), |editor| {
+        json_c_string(editor.diff_changes())
+    })
+}
+
+/// Gets the first line of the next change after `line` (wraps around)
+///
+/// # Safety
+/// - `handle` must be a valid editor pointer
+///
+/// Returns the line, or -1 if there are no changes
+#[no_mangle]
+pub unsafe extern "C" fn editor_next_change(handle: EditorHandle, line: usize) -> i64 {
+    with_editor(handle, -1, |editor| editor.next_change(line).map_or(-1, |line| line as i64))
+}
+
+/// Gets the first line of the previous change before `line` (wraps around)
+///
+/// # Safety
+/// - `handle` must be a valid editor pointer
+///
+/// Returns the line, or -1 if there are no changes
+#[no_mangle]
+pub unsafe extern "C" fn editor_previous_change(handle: EditorHandle, line: usize) -> i64 {
+    with_editor(handle, -1, |editor| editor.previous_change(line).map_or(-1, |line| line as i64))
+}
+
+/// Restores the base text of the change marked on a line (undoable)
+///
+/// # Safety
+/// - `handle` must be a valid editor pointer
+///
+/// Returns 1 if a change was reverted, 0 if the line is unchanged, -1 on
+/// error (no diff base, read-only)
+#[no_mangle]
+pub unsafe extern "C" fn editor_revert_change(handle: EditorHandle, line: usize) -> i32 {
+    with_editor(handle, -1, |editor| match editor.revert_change(line) {
+        Ok(true) => 1,
+        Ok(false) => 0,
+        Err(_) => -1,
+    })
+}
+
+// ==================================================================
+// Spell Checking
+// ==================================================================
+
+/// Creates an empty spell checker (accepts every word until a dictionary
+/// is loaded)
+///
+/// # Safety
+/// - Caller must free the returned pointer with `spell_checker_free()`
+///
+/// Returns an opaque pointer, or null on error
+#[no_mangle]
+pub unsafe extern "C" fn spell_checker_new() -> SpellCheckerHandle {
+    ffi_guard(|| SPELL_CHECKERS.insert(RwLock::new(SpellChecker::new())))
+}
+
+/// Frees a spell checker (editors using it keep their reference)
+///
+/// # Safety
+/// - `handle` must be a pointer from `spell_checker_new()`
+#[no_mangle]
+pub unsafe extern "C" fn spell_checker_free(handle: SpellCheckerHandle) {
+    ffi_guard(|| {
+        SPELL_CHECKERS.remove(handle);
+    })
+}
+
+/// Loads a Hunspell dictionary
+///
+/// # Safety
+/// - `handle` must be a valid spell checker pointer
+/// - `aff_path` and `dic_path` must be valid null-terminated UTF-8 strings
+///
+/// Returns `ErrorUnknown` if the files cannot be read or parsed
+#[no_mangle]
+pub unsafe extern "C" fn spell_checker_load_dictionary(
+    handle: SpellCheckerHandle,
+    aff_path: *const c_char,
+    dic_path: *const c_char,
+) -> ResultCode {
+    with_spell_checker(handle, ResultCode::ErrorNull, |checker| {
+        if aff_path.is_null() || dic_path.is_null() {
+            return ResultCode::ErrorNull;
+        }
+
+        let (Ok(aff_path), Ok(dic_path)) = (CStr::from_ptr(aff_path).to_str(), CStr::from_ptr(dic_path).to_str())
+        else {
+            return ResultCode::ErrorInvalidUtf8;
+        };
+        let result = write_lock(checker).load_dictionary(std::path::Path::new(aff_path), std::path::Path::new(dic_path));
+        spell_result(result)
+    })
+}
+
+/// Loads the user (0) or project (1) word list
+///
+/// # Safety
+/// - `handle` must be a valid spell checker pointer
+/// - `path` must be a valid null-terminated UTF-8 string
+///
+/// A missing file is an empty list; words added later are appended to it.
+#[no_mangle]
+pub unsafe extern "C" fn spell_checker_load_word_list(
+    handle: SpellCheckerHandle,
+    kind: i32,
+    path: *const c_char,
+) -> ResultCode {
+    with_spell_checker(handle, ResultCode::ErrorNull, |checker| {
+        if path.is_null() {
+            return ResultCode::ErrorNull;
+        }
+
+        let Some(kind) = word_list_kind(kind) else {
+            return ResultCode::ErrorOutOfBounds;
+        };
+        let Ok(path) = CStr::from_ptr(path).to_str() else {
+            return ResultCode::ErrorInvalidUtf8;
+        };
+        spell_result(write_lock(checker).load_word_list(kind, std::path::Path::new(path)))
+    })
+}
+
+/// Adds a word to the user (0) or project (1) word list
+///
+/// # Safety
+/// - `handle` must be a valid spell checker pointer
+/// - `word` must be a valid null-terminated UTF-8 string
+#[no_mangle]
+pub unsafe extern "C" fn spell_checker_add_word(
+    handle: SpellCheckerHandle,
+    kind: i32,
+    word: *const c_char,
+) -> ResultCode {
+    with_spell_checker(handle, ResultCode::ErrorNull, |checker| {
+        if word.is_null() {
+            return ResultCode::ErrorNull;
+        }
+
+        let Some(kind) = word_list_kind(kind) else {
+            return ResultCode::ErrorOutOfBounds;
+        };
+        let Ok(word) = CStr::from_ptr(word).to_str() else {
+            return ResultCode::ErrorInvalidUtf8;
+        };
+        spell_result(write_lock(checker).add_word(kind, word))
+    })
+}
+
+/// Gets corrections for a word
+///
+/// # Safety
+/// - `handle` must be a valid spell checker pointer
+/// - `word` must be a valid null-terminated UTF-8 string
+/// - Caller must free the returned string with `editor_free_string()`
+///
+/// Returns `["suggestion", ...]` as JSON, or null on error
+#[no_mangle]
+pub unsafe extern "C" fn spell_checker_suggest(handle: SpellCheckerHandle, word: *const c_char) -> *mut c_char {
+    with_spell_checker(handle, ptr::null_mut(), |checker| {
+        if word.is_null() {
+            return ptr::null_mut();
+        }
+
+        let Ok(word) = CStr::from_ptr(word).to_str() else {
+            return ptr::null_mut();
+        };
+        let suggestions = checker.read().unwrap_or_else(PoisonError::into_inner).suggest(word);
+        json_c_string(&suggestions)
+    })
+}
+
+/// Sets the spell checker of an editor (null turns spell checking off)
+///
+/// # Safety
+/// - `handle` must be a valid editor pointer
+/// - `checker` must be a valid spell checker pointer or null
+///
+/// One checker can be set on many editors; word list changes apply to all.
+#[no_mangle]
+pub unsafe extern "C" fn editor_set_spell_checker(handle: EditorHandle, checker: SpellCheckerHandle) -> ResultCode {
+    with_editor(handle, ResultCode::ErrorNull, |editor| {
+        if checker.is_null() {
+            editor.set_spell_checker(None);
+            return ResultCode::Success;
+        }
+
+        match SPELL_CHECKERS.get(checker) {
+            Some(checker) => {
+                editor.set_spell_checker(Some(checker));
+                ResultCode::Success
+            }
+            None => ResultCode::ErrorInvalidHandle,
+        }
+    })
+}
+
+/// Gets the misspelled words in comments, strings and prose
+///
+/// # Safety
+/// - `handle` must be a valid editor pointer
+/// - Caller must free the returned string with `editor_free_string()`
+///
+/// Only lines edited since the last call are checked again. Suggestions
+/// are not included; get them with `editor_spell_suggestions()`.
+/// Returns `[{range, severity, source: "spell", message, word}]` as JSON,
+/// or null on error
+#[no_mangle]
+pub unsafe extern "C" fn editor_spell_check(handle: EditorHandle) -> *mut c_char {
+    with_editor(handle, ptr::null_mut(), |editor| {
+        json_c_string(&editor.spell_diagnostics())
+    })
+}
+
+/// Gets corrections for the misspelled word at a position (for hovers and
+/// quick fixes)
+///
+/// # Safety
+/// - `handle` must be a valid editor pointer
+/// - Caller must free the returned string with `editor_free_string()`
+///
+/// Returns `["suggestion", ...]` as JSON (empty if the word is spelled
+/// correctly), or null on error
+#[no_mangle]
+pub unsafe extern "C" fn editor_spell_suggestions(handle: EditorHandle, line: usize, column: usize) -> *mut c_char {
+    with_editor(handle, ptr::null_mut(), |editor| {
+        json_c_string(&editor.spell_suggestions_at(Position::new(line, column)))
+    })
+}
+
+/// Helper: Maps a word list kind code (0 = user, 1 = project)
+fn word_list_kind(kind: i32) -> Option<WordListKind> {
+    match kind {
+        0 => Some(WordListKind::User),
+        1 => Some(WordListKind::Project),
+        _ => None,
+    }
+}
+
+/// Helper: Write-locks a shared spell checker
+fn write_lock(checker: &SharedSpellChecker) -> RwLockWriteGuard<'_, SpellChecker> {
+    checker.write().unwrap_or_else(PoisonError::into_inner)
+}
+
+/// Helper: Maps a spell checker result (I/O and parse errors are unknown)
+fn spell_result(result: anyhow::Result<()>) -> ResultCode {
+    match result {
+        Ok(()) => ResultCode::Success,
+        Err(_) => ResultCode::ErrorUnknown,
+    }
+}
+
+// ==================================================================
+// Keymap
+// ==================================================================
+
+/// Creates an empty keymap
+///
+/// # Safety
+/// - Caller must free the returned pointer with `keymap_free()`
+///
+/// `platform` picks the `mac` / `linux` / `win` key variants: 0 = Linux,
+/// 1 = macOS, 2 = Windows, anything else = the platform built for.
+/// Returns an opaque pointer, or null on error
+#[no_mangle]
+pub unsafe extern "C" fn keymap_new(platform: i32) -> KeymapHandle {
+    ffi_guard(|| {
+        let platform = match platform {
+            0 => Platform::Linux,
+            1 => Platform::Mac,
+            2 => Platform::Windows,
+            _ => Platform::current(),
+        };
+        KEYMAPS.insert(Mutex::new(Keymap::new(platform)))
+    })
+}
+
+/// Frees a keymap
+///
+/// # Safety
+/// - `handle` must be a pointer from `keymap_new()`
+#[no_mangle]
+pub unsafe extern "C" fn keymap_free(handle: KeymapHandle) {
+    ffi_guard(|| {
+        KEYMAPS.remove(handle);
+    })
+}
+
+/// Replaces the default keybindings
+///
+/// # Safety
+/// - `handle` must be a valid keymap pointer
+/// - `json` must be a valid null-terminated UTF-8 string (VS Code
+///   keybindings format)
+///
+/// Returns `ErrorUnknown` for invalid JSON, keys or `when` clauses (the
+/// previous bindings are kept)
+#[no_mangle]
+pub unsafe extern "C" fn keymap_load_defaults(handle: KeymapHandle, json: *const c_char) -> ResultCode {
+    with_keymap(handle, ResultCode::ErrorNull, |keymap| {
+        if json.is_null() {
+            return ResultCode::ErrorNull;
+        }
+
+        match CStr::from_ptr(json).to_str() {
+            Ok(json) => match keymap.load_defaults(json) {
+                Ok(()) => ResultCode::Success,
+                Err(_) => ResultCode::ErrorUnknown,
+            },
+            Err(_) => ResultCode::ErrorInvalidUtf8,
+        }
+    })
+}
+
+/// Replaces the user keybindings (layered over the defaults)
+///
+/// # Safety
+/// - `handle` must be a valid keymap pointer
+/// - `json` must be a valid null-terminated UTF-8 string
+///
+/// Returns `ErrorUnknown` for invalid bindings (the previous ones are kept)
+#[no_mangle]
+pub unsafe extern "C" fn keymap_load_user(handle: KeymapHandle, json: *const c_char) -> ResultCode {
+    with_keymap(handle, ResultCode::ErrorNull, |keymap| {
+        if json.is_null() {
+            return ResultCode::ErrorNull;
+        }
+
+        match CStr::from_ptr(json).to_str() {
+            Ok(json) => match keymap.load_user(json) {
+                Ok(()) => ResultCode::Success,
+                Err(_) => ResultCode::ErrorUnknown,
+            },
+            Err(_) => ResultCode::ErrorInvalidUtf8,
+        }
+    })
+}
+
+/// Resolves a key press to a command
+///
+/// # Safety
+/// - `handle` must be a valid keymap pointer
+/// - `editor` must be a valid editor pointer, or null (no editor keys)
+/// - `key` must be a valid null-terminated UTF-8 string (`ctrl+shift+k`)
+/// - `context_json` must be a valid null-terminated UTF-8 string or null:
+///   extra context keys from the host (`{"editorTextFocus": true,
+///   "mode": "insert"}`), overriding the editor's
+/// - Caller must free the returned string with `editor_free_string()`
+///
+/// Returns `{"kind": "command", "command", "args"?}`, `{"kind":
+/// "pending", "keys"}`, `{"kind": "cancelled", "keys"}` or `{"kind":
+/// "noMatch"}` as JSON, or null on error
+#[no_mangle]
+pub unsafe extern "C" fn keymap_resolve(
+    handle: KeymapHandle,
+    editor: EditorHandle,
+    key: *const c_char,
+    context_json: *const c_char,
+) -> *mut c_char {
+    with_keymap(handle, ptr::null_mut(), |keymap| {
+        if key.is_null() {
+            return ptr::null_mut();
+        }
+
+        let Ok(Ok(key)) = CStr::from_ptr(key).to_str().map(parse_key) else {
+            return ptr::null_mut();
+        };
+
+        let mut context = KeyContext::new();
+        if !editor.is_null() {
+            let result = with_editor(editor, ResultCode::ErrorNull, |editor| {
+                context = KeyContext::for_editor(editor);
+                ResultCode::Success
+            });
+            if result != ResultCode::Success {
+                return ptr::null_mut();
+            }
+        }
+        if !context_json.is_null() {
+            let Ok(json) = CStr::from_ptr(context_json).to_str() else {
+                return ptr::null_mut();
+            };
+            match serde_json::from_str(json) {
+                Ok(extra) => context.merge(extra),
+                Err(_) => return ptr::null_mut(),
+            }
+        }
+
+        json_c_string(&keymap.resolve(key, &context))
+    })
+}
+
+/// Abandons the chord in progress (e.g. after a timeout or focus loss)
+///
+/// # Safety
+/// - `handle` must be a valid keymap pointer
+#[no_mangle]
+pub unsafe extern "C" fn keymap_cancel_chord(handle: KeymapHandle) -> ResultCode {
+    with_keymap(handle, ResultCode::ErrorNull, |keymap| {
+        keymap.cancel_chord();
+        ResultCode::Success
+    })
+}
+
+/// Lists bindings hidden by other bindings
+///
+/// # Safety
+/// - `handle` must be a valid keymap pointer
+/// - Caller must free the returned string with `editor_free_string()`
+///
+/// Returns `[{kind: "duplicate"|"chordPrefix", keys, when, winner,
+/// shadowed}]` as JSON, or null on error
+#[no_mangle]
+pub unsafe extern "C" fn keymap_conflicts(handle: KeymapHandle) -> *mut c_char {
+    with_keymap(handle, ptr::null_mut(), |keymap| {
+        json_c_string(&keymap.conflicts())
+    })
+}
+
+// ==================================================================
+// Error Reporting
+// ==================================================================
+
+/// Takes the message of the last failure on the calling thread
+///
+/// Set when a call panics (the call returns an error value instead of
+/// aborting) or gets a freed or unknown handle. Read it right after the
+/// failing call; reading clears it.
+///
+/// # Safety
+/// - Caller must free the returned string with `editor_free_string()`
+///
+/// Returns the message, or null if there is none
+#[no_mangle]
+pub unsafe extern "C" fn editor_last_error() -> *mut c_char {
+    ffi_guard(|| match handles::take_last_error() {
+        Some(message) => c_string(message),
+        None => ptr::null_mut(),
+    })
+}
+
+// ==================================================================
+// Memory Management
+// ==================================================================
+
+/// Frees a C string returned by the editor
+///
+/// # Safety
+/// - `ptr` must be a string returned by an editor function
+/// - Must not be used after calling this function
+#[no_mangle]
+pub unsafe extern "C" fn editor_free_string(ptr: *mut c_char) {
+    ffi_guard(|| {
+        if !ptr.is_null() {
+            drop(CString::from_raw(ptr));
+        }
+    })
+}
+
+/// Frees a byte buffer returned by the editor
+///
+/// # Safety
+/// - `ptr` and `len` must come from an editor function returning bytes
+/// - Must not be used after calling this function
+#[no_mangle]
+pub unsafe extern "C" fn editor_free_bytes(ptr: *mut u8, len: usize) {
+    ffi_guard(|| {
+        if !ptr.is_null() {
+            drop(Box::from_raw(ptr::slice_from_raw_parts_mut(ptr, len)));
+        }
+    })
 }
 
 #[cfg(test)]
@@ -562,6 +562,1384 @@ fn test_ffi_mark_saved_null_handle() {
     }
 }
 
+// ============================================================
+// Settings & Save Tests
+// ============================================================
+
+#[test]
+fn test_ffi_prepare_save() {
+    unsafe {
+        let handle = editor_new();
+        let content = create_c_string("line  \nlast");
+        editor_set_content(handle, content);
+
+        with_editor(handle, (), |editor| {
+            editor.apply_editorconfig(&crate::editor::EditorConfigProperties {
+                trim_trailing_whitespace: Some(true),
+                insert_final_newline: Some(true),
+                ..Default::default()
+            })
+        });
+
+        let mut len = 0usize;
+        let bytes = editor_prepare_save(handle, &mut len);
+        assert!(!bytes.is_null());
+        assert_eq!(std::slice::from_raw_parts(bytes, len), b"line\nlast\n");
+
+        editor_free_bytes(bytes, len);
+        free_c_string(content);
+        editor_free(handle);
+    }
+}
+
+#[test]
+fn test_ffi_save_actions() {
+    unsafe {
+        let content = create_c_string("fn f() {}  \n\n\n");
+        let language = create_c_string("rust");
+        let handle = editor_with_content(content, language);
+
+        assert_eq!(editor_set_save_action(handle, ptr::null(), 0, 1), ResultCode::Success);
+        assert_eq!(editor_set_save_action(handle, ptr::null(), 3, 1), ResultCode::Success);
+        assert_eq!(editor_set_save_action(handle, language, 0, 0), ResultCode::Success);
+        assert_eq!(editor_set_save_action(handle, ptr::null(), 9, 1), ResultCode::ErrorOutOfBounds);
+        assert_eq!(editor_set_save_action(handle, ptr::null(), 0, -1), ResultCode::ErrorOutOfBounds);
+
+        let mut len = 0usize;
+        let bytes = editor_prepare_save(handle, &mut len);
+        assert_eq!(std::slice::from_raw_parts(bytes, len), b"fn f() {}  \n");
+        editor_free_bytes(bytes, len);
+
+        free_c_string(content);
+        free_c_string(language);
+        editor_free(handle);
+        assert_eq!(editor_set_save_action(handle, ptr::null(), 0, 1), ResultCode::ErrorInvalidHandle);
+    }
+}
+
+#[test]
+fn test_ffi_prepare_save_null_handle() {
+    unsafe {
+        let mut len = 0usize;
+        assert!(editor_prepare_save(ptr::null_mut(), &mut len).is_null());
+    }
+}
+
+#[test]
+fn test_ffi_get_indent_config() {
+    unsafe {
+        let handle = editor_new();
+        let mut use_spaces = 0;
+        let mut tab_size = 0;
+
+        let result = editor_get_indent_config(handle, &mut use_spaces, &mut tab_size);
+        assert_eq!(result as i32, ResultCode::Success as i32);
+        assert_eq!(use_spaces, 1);
+        assert_eq!(tab_size, 4);
+        assert_eq!(editor_max_line_length(handle), 0);
+
+        editor_free(handle);
+    }
+}
+
+#[test]
+fn test_ffi_load_editorconfig_null() {
+    unsafe {
+        let path = create_c_string("/tmp/file.rs");
+        let result = editor_load_editorconfig(ptr::null_mut(), path);
+        assert_eq!(result as i32, ResultCode::ErrorNull as i32);
+        free_c_string(path);
+    }
+}
+
+// ============================================================
+// Completion Tests
+// ============================================================
+
+#[test]
+fn test_ffi_complete_with_other_editors() {
+    unsafe {
+        let handle = editor_new();
+        let content = create_c_string("let total = 1;
+to");
+        editor_set_content(handle, content);
+
+        let other = editor_new();
+        let other_content = create_c_string("fn topological_sort() {}");
+        editor_set_content(other, other_content);
+
+        let others = [other];
+        let json_ptr = editor_complete(handle, 1, 2, others.as_ptr(), others.len());
+        assert!(!json_ptr.is_null());
+
+        let json: serde_json::Value =
+            serde_json::from_str(CStr::from_ptr(json_ptr).to_str().unwrap()).unwrap();
+        let labels: Vec<&str> = json["items"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|item| item["label"].as_str().unwrap())
+            .collect();
+        assert!(labels.contains(&"total"));
+        assert!(labels.contains(&"topological_sort"));
+
+        editor_free_string(json_ptr);
+        free_c_string(content);
+        free_c_string(other_content);
+        editor_free(other);
+        editor_free(handle);
+    }
+}
+
+#[test]
+fn test_ffi_complete_null_handle() {
+    unsafe {
+        assert!(editor_complete(ptr::null_mut(), 0, 0, ptr::null(), 0).is_null());
+
+        let handle = editor_new();
+        assert!(editor_complete(handle, 0, 0, ptr::null(), 1).is_null());
+        editor_free(handle);
+    }
+}
+
+// ============================================================
+// Symbols Tests
+// ============================================================
+
+#[test]
+fn test_ffi_document_symbols() {
+    unsafe {
+        let content = create_c_string("struct Config {\n    debug: bool,\n}\n");
+        let language = create_c_string("rust");
+        let handle = editor_with_content(content, language);
+
+        let json_ptr = editor_document_symbols(handle);
+        assert!(!json_ptr.is_null());
+
+        let json: serde_json::Value =
+            serde_json::from_str(CStr::from_ptr(json_ptr).to_str().unwrap()).unwrap();
+        assert_eq!(json[0]["name"], "Config");
+        assert_eq!(json[0]["kind"], 23);
+        assert_eq!(json[0]["children"][0]["name"], "debug");
+
+        editor_free_string(json_ptr);
+        free_c_string(content);
+        free_c_string(language);
+        editor_free(handle);
+    }
+}
+
+#[test]
+fn test_ffi_document_symbols_null_handle() {
+    unsafe {
+        assert!(editor_document_symbols(ptr::null_mut()).is_null());
+    }
+}
+
+// ============================================================
+// Viewport Decorations Tests
+// ============================================================
+
+#[test]
+fn test_ffi_sticky_scopes_and_indent_guides() {
+    unsafe {
+        let content = create_c_string("fn main() {\n    let a = 1;\n    let b = 2;\n}\n");
+        let language = create_c_string("rust");
+        let handle = editor_with_content(content, language);
+        editor_move_cursor(handle, 2, 4);
+
+        let sticky_ptr = editor_sticky_scopes(handle, 2, 5);
+        assert!(!sticky_ptr.is_null());
+        let sticky: serde_json::Value =
+            serde_json::from_str(CStr::from_ptr(sticky_ptr).to_str().unwrap()).unwrap();
+        assert_eq!(sticky[0]["line"], 0);
+        assert_eq!(sticky[0]["kind"], "function_item");
+
+        let guides_ptr = editor_indent_guides(handle, 0, 3);
+        assert!(!guides_ptr.is_null());
+        let guides: serde_json::Value =
+            serde_json::from_str(CStr::from_ptr(guides_ptr).to_str().unwrap()).unwrap();
+        assert_eq!(guides[0]["startLine"], 1);
+        assert_eq!(guides[0]["endLine"], 2);
+        assert_eq!(guides[0]["active"], true);
+
+        editor_free_string(sticky_ptr);
+        editor_free_string(guides_ptr);
+        free_c_string(content);
+        free_c_string(language);
+        editor_free(handle);
+    }
+}
+
+#[test]
+fn test_ffi_viewport_decorations_null_handle() {
+    unsafe {
+        assert!(editor_sticky_scopes(ptr::null_mut(), 0, 1).is_null());
+        assert!(editor_indent_guides(ptr::null_mut(), 0, 1).is_null());
+    }
+}
+
+// ============================================================
+// Embedded Languages Tests
+// ============================================================
+
+#[test]
+fn test_ffi_embedded_languages() {
+    unsafe {
+        let content = create_c_string("Intro\n\n```python\ndef run():\n    pass\n```\n");
+        let language = create_c_string("markdown");
+        let handle = editor_with_content(content, language);
+
+        let lang_ptr = editor_language_at(handle, 3, 0);
+        assert_eq!(CStr::from_ptr(lang_ptr).to_str().unwrap(), "python");
+
+        let highlights_ptr = editor_highlights(handle, 0, 6);
+        assert!(!highlights_ptr.is_null());
+        let highlights: serde_json::Value =
+            serde_json::from_str(CStr::from_ptr(highlights_ptr).to_str().unwrap()).unwrap();
+        assert!(highlights
+            .as_array()
+            .unwrap()
+            .iter()
+            .any(|span| span["language"] == "python" && span["depth"] == 1));
+
+        let folds_ptr = editor_folding_ranges(handle);
+        assert!(!folds_ptr.is_null());
+        let folds: serde_json::Value =
+            serde_json::from_str(CStr::from_ptr(folds_ptr).to_str().unwrap()).unwrap();
+        assert!(folds.as_array().unwrap().iter().any(|f| f["startLine"] == 3));
+
+        assert_eq!(editor_toggle_line_comments(handle, 3, 4), 1);
+        assert_eq!(editor_toggle_line_comments(handle, 3, 4), 0);
+        assert_eq!(editor_toggle_line_comments(handle, 0, 0), -1);
+
+        editor_free_string(lang_ptr);
+        editor_free_string(highlights_ptr);
+        editor_free_string(folds_ptr);
+        free_c_string(content);
+        free_c_string(language);
+        editor_free(handle);
+    }
+}
+
+#[test]
+fn test_ffi_embedded_languages_null_handle() {
+    unsafe {
+        assert!(editor_highlights(ptr::null_mut(), 0, 1).is_null());
+        assert!(editor_folding_ranges(ptr::null_mut()).is_null());
+        assert!(editor_language_at(ptr::null_mut(), 0, 0).is_null());
+        assert_eq!(editor_toggle_line_comments(ptr::null_mut(), 0, 0), -1);
+        assert_eq!(editor_toggle_block_comment(ptr::null_mut(), 0, 0, 0, 0), -1);
+        assert_eq!(editor_insert_doc_comment(ptr::null_mut(), 0), -1);
+    }
+}
+
+#[test]
+fn test_ffi_block_and_doc_comments() {
+    unsafe {
+        let content = create_c_string("fn add(a: i32) -> i32 {\n    a + 1\n}\n");
+        let language = create_c_string("rust");
+        let handle = editor_with_content(content, language);
+
+        assert_eq!(editor_toggle_block_comment(handle, 1, 4, 1, 9), 1);
+        let line_ptr = editor_get_line(handle, 1);
+        assert_eq!(c_string_to_rust(line_ptr), "    /*a + 1*/\n");
+        editor_free_string(line_ptr);
+        assert_eq!(editor_toggle_block_comment(handle, 1, 0, 1, 0), 0);
+
+        assert_eq!(editor_insert_doc_comment(handle, 0), 1);
+        assert_eq!(editor_insert_doc_comment(handle, 1), 0);
+        let (mut line, mut column) = (0, 0);
+        assert_eq!(editor_get_cursor(handle, &mut line, &mut column), ResultCode::Success);
+        assert_eq!((line, column), (0, 4));
+
+        free_c_string(content);
+        free_c_string(language);
+        editor_free(handle);
+    }
+}
+
+// ============================================================
+// Structural Search & Replace Tests
+// ============================================================
+
+#[test]
+fn test_ffi_structural_preview_and_replace() {
+    unsafe {
+        let content = create_c_string("print(a)\nprint(b, c)\n");
+        let language = create_c_string("python");
+        let handle = editor_with_content(content, language);
+        let pattern = create_c_string("print($x)");
+        let replacement = create_c_string("log($x)");
+
+        let preview_ptr = editor_structural_preview(handle, pattern, 1, replacement);
+        assert!(!preview_ptr.is_null());
+        let preview: serde_json::Value =
+            serde_json::from_str(CStr::from_ptr(preview_ptr).to_str().unwrap()).unwrap();
+        assert_eq!(preview.as_array().unwrap().len(), 1);
+        assert_eq!(preview[0]["captures"]["x"], "a");
+        assert_eq!(preview[0]["replacement"], "log(a)");
+
+        assert_eq!(editor_structural_replace(handle, pattern, 1, replacement), 1);
+        let content_ptr = editor_get_content(handle);
+        assert_eq!(CStr::from_ptr(content_ptr).to_str().unwrap(), "log(a)\nprint(b, c)\n");
+
+        // Raw query mode, invalid query
+        let bad_query = create_c_string("(not_a_node");
+        assert_eq!(editor_structural_replace(handle, bad_query, 0, replacement), -1);
+        assert!(editor_structural_preview(handle, bad_query, 0, replacement).is_null());
+
+        editor_free_string(preview_ptr);
+        editor_free_string(content_ptr);
+        free_c_string(bad_query);
+        free_c_string(pattern);
+        free_c_string(replacement);
+        free_c_string(content);
+        free_c_string(language);
+        editor_free(handle);
+    }
+}
+
+#[test]
+fn test_ffi_structural_null_handle() {
+    unsafe {
+        let pattern = create_c_string("print($x)");
+        assert!(editor_structural_preview(ptr::null_mut(), pattern, 1, pattern).is_null());
+        assert_eq!(editor_structural_replace(ptr::null_mut(), pattern, 1, pattern), -1);
+        free_c_string(pattern);
+    }
+}
+
+// ============================================================
+// Text Layout Tests
+// ============================================================
+
+#[test]
+fn test_ffi_renderer_layout_and_hit_test() {
+    unsafe {
+        let content = create_c_string("fn main() {\n\tgo();\n}\n");
+        let language = create_c_string("rust");
+        let editor = editor_with_content(content, language);
+        let renderer = renderer_new(14.0, 20.0);
+        assert!(!renderer.is_null());
+
+        let layout_ptr = renderer_layout_viewport(renderer, editor, 0, 10);
+        assert!(!layout_ptr.is_null());
+        let layout: serde_json::Value =
+            serde_json::from_str(CStr::from_ptr(layout_ptr).to_str().unwrap()).unwrap();
+        assert_eq!(layout["lineHeight"], 20.0);
+        assert_eq!(layout["lines"].as_array().unwrap().len(), 4);
+        assert_eq!(layout["lines"][0]["glyphs"].as_array().unwrap().len(), 11);
+        assert_eq!(layout["lines"][1]["top"], 20.0);
+
+        // Caret after the tab, then hit test it back
+        let mut x = 0.0f32;
+        let mut y = 0.0f32;
+        assert!(matches!(
+            renderer_caret_position(renderer, editor, 0, 1, 1, &mut x, &mut y),
+            ResultCode::Success
+        ));
+        assert_eq!(y, 20.0);
+        let mut line = 0;
+        let mut column = 0;
+        assert!(matches!(
+            renderer_hit_test(renderer, editor, 0, x + 0.5, y + 5.0, &mut line, &mut column),
+            ResultCode::Success
+        ));
+        assert_eq!((line, column), (1, 1));
+
+        // Above the viewport
+        assert!(matches!(
+            renderer_caret_position(renderer, editor, 1, 0, 0, &mut x, &mut y),
+            ResultCode::ErrorOutOfBounds
+        ));
+
+        assert!(matches!(renderer_set_wrap(renderer, 30.0, 0, 4, 0), ResultCode::Success));
+        assert!(matches!(renderer_invalidate_lines(renderer, 0, 0, 0), ResultCode::Success));
+
+        editor_free_string(layout_ptr);
+        renderer_free(renderer);
+        free_c_string(content);
+        free_c_string(language);
+        editor_free(editor);
+    }
+}
+
+#[test]
+fn test_ffi_renderer_render_png() {
+    unsafe {
+        let content = create_c_string("fn main() {}\n");
+        let language = create_c_string("rust");
+        let editor = editor_with_content(content, language);
+        let renderer = renderer_new(14.0, 20.0);
+
+        let mut len = 0usize;
+        let png = renderer_render_png(renderer, editor, 0, 120, 0, 2.0, &mut len);
+        assert!(!png.is_null());
+        let bytes = std::slice::from_raw_parts(png, len);
+        assert_eq!(&bytes[..8], b"\x89PNG\r\n\x1a\n");
+
+        editor_free_bytes(png, len);
+        renderer_free(renderer);
+        free_c_string(content);
+        free_c_string(language);
+        editor_free(editor);
+    }
+}
+
+#[test]
+fn test_ffi_renderer_null_handles() {
+    unsafe {
+        let mut line = 0;
+        let mut column = 0;
+        let mut x = 0.0f32;
+        let mut y = 0.0f32;
+
+        assert!(renderer_layout_viewport(ptr::null_mut(), ptr::null_mut(), 0, 1).is_null());
+        assert!(matches!(
+            renderer_hit_test(ptr::null_mut(), ptr::null_mut(), 0, 0.0, 0.0, &mut line, &mut column),
+            ResultCode::ErrorNull
+        ));
+        assert!(matches!(
+            renderer_caret_position(ptr::null_mut(), ptr::null_mut(), 0, 0, 0, &mut x, &mut y),
+            ResultCode::ErrorNull
+        ));
+        assert!(matches!(renderer_set_wrap(ptr::null_mut(), 0.0, 0, 4, 0), ResultCode::ErrorNull));
+        assert!(matches!(renderer_load_font(ptr::null_mut(), ptr::null(), 0), ResultCode::ErrorNull));
+        let mut len = 0usize;
+        assert!(renderer_render_png(ptr::null_mut(), ptr::null_mut(), 0, 100, 0, 1.0, &mut len).is_null());
+        renderer_free(ptr::null_mut());
+    }
+}
+
+// ============================================================
+// Large File Tests
+// ============================================================
+
+#[test]
+fn test_ffi_large_file_view() {
+    unsafe {
+        let file = std::env::temp_dir().join(format!("ffi_large_file_{}.rs", std::process::id()));
+        std::fs::write(&file, "fn main() {}\nlet x = 1;\n").unwrap();
+        let path = create_c_string(file.to_str().unwrap());
+
+        assert_eq!(large_file_classify(path), 0);
+        let view = large_file_open(path);
+        assert!(!view.is_null());
+
+        let lines_ptr = large_file_get_lines(view, 1, 5);
+        assert_eq!(c_string_to_rust(lines_ptr), r#"["let x = 1;",""]"#);
+
+        let mut exact = 0;
+        assert_eq!(large_file_index_step(view), 1);
+        assert_eq!(large_file_line_count(view, &mut exact), 3);
+        assert_eq!(exact, 1);
+
+        let language = create_c_string("rust");
+        let spans_ptr = large_file_highlights(view, language, 0, 0);
+        let spans: serde_json::Value = serde_json::from_str(&c_string_to_rust(spans_ptr)).unwrap();
+        assert!(spans.as_array().unwrap().iter().any(|s| s["capture"] == "keyword"));
+
+        let features_ptr = large_file_degraded_features(view);
+        let features: serde_json::Value = serde_json::from_str(&c_string_to_rust(features_ptr)).unwrap();
+        assert_eq!(features["sizeClass"], "huge");
+        assert_eq!(features["readOnly"], true);
+
+        std::fs::write(&file, "fn main() {}\nlet x = 1;\nlet y = 2;\n").unwrap();
+        assert_eq!(large_file_refresh(view), 11);
+        assert_eq!(large_file_refresh(view), 0);
+
+        editor_free_string(lines_ptr);
+        editor_free_string(spans_ptr);
+        editor_free_string(features_ptr);
+        large_file_free(view);
+        free_c_string(language);
+        free_c_string(path);
+        std::fs::remove_file(file).ok();
+    }
+}
+
+#[test]
+fn test_ffi_editor_open_file_read_only() {
+    unsafe {
+        let file = std::env::temp_dir().join(format!("ffi_open_file_{}.log", std::process::id()));
+        std::fs::write(&file, "line 1\n").unwrap();
+        let path = create_c_string(file.to_str().unwrap());
+
+        let editor = editor_open_file(path);
+        assert!(!editor.is_null());
+        assert!(matches!(editor_set_read_only(editor, 1), ResultCode::Success));
+        let text = create_c_string("x");
+        assert!(matches!(editor_insert_text(editor, text), ResultCode::ErrorUnknown));
+
+        let appended = create_c_string("line 2\n");
+        assert!(matches!(editor_append_streamed(editor, appended), ResultCode::Success));
+        let content_ptr = editor_get_content(editor);
+        assert_eq!(c_string_to_rust(content_ptr), "line 1\nline 2\n");
+
+        let features_ptr = editor_degraded_features(editor);
+        let features: serde_json::Value = serde_json::from_str(&c_string_to_rust(features_ptr)).unwrap();
+        assert_eq!(features["sizeClass"], "normal");
+        assert_eq!(features["disabled"], serde_json::json!(["editing"]));
+
+        editor_free_string(content_ptr);
+        editor_free_string(features_ptr);
+        free_c_string(text);
+        free_c_string(appended);
+        free_c_string(path);
+        editor_free(editor);
+        std::fs::remove_file(file).ok();
+    }
+}
+
+#[test]
+fn test_ffi_large_file_null_handles() {
+    unsafe {
+        assert!(editor_open_file(ptr::null()).is_null());
+        assert!(editor_degraded_features(ptr::null_mut()).is_null());
+        assert!(matches!(editor_set_read_only(ptr::null_mut(), 1), ResultCode::ErrorNull));
+        assert_eq!(large_file_classify(ptr::null()), -1);
+        assert!(large_file_open(ptr::null()).is_null());
+        assert!(large_file_get_lines(ptr::null_mut(), 0, 1).is_null());
+        assert_eq!(large_file_index_step(ptr::null_mut()), -1);
+        assert_eq!(large_file_refresh(ptr::null_mut()), -1);
+        large_file_free(ptr::null_mut());
+    }
+}
+
+// ============================================================
+// Workspace Tests
+// ============================================================
+
+#[test]
+fn test_ffi_workspace_buffers_and_views() {
+    unsafe {
+        let workspace = workspace_new();
+        let content = create_c_string("let a = 1;");
+        let language = create_c_string("rust");
+
+        let buffer = workspace_open_untitled(workspace, content, language);
+        assert_eq!(buffer, 1);
+        let left = workspace_create_view(workspace, buffer as u64);
+        let right = workspace_create_view(workspace, buffer as u64);
+        assert!(left > 0 && right > left);
+
+        // The borrowed editor works with the editor_* functions
+        let editor = workspace_activate_view(workspace, left as u64);
+        assert!(!editor.is_null());
+        let text = create_c_string("x");
+        assert!(matches!(editor_insert_text(editor, text), ResultCode::Success));
+        assert_eq!(workspace_buffer_editor(workspace, buffer as u64), editor);
+
+        let list_ptr = workspace_list_buffers(workspace);
+        let list: serde_json::Value = serde_json::from_str(&c_string_to_rust(list_ptr)).unwrap();
+        assert_eq!(list[0]["uri"], "untitled:Untitled-1");
+        assert_eq!(list[0]["language"], "rust");
+        assert_eq!(list[0]["dirty"], true);
+        assert_eq!(list[0]["views"], serde_json::json!([left, right]));
+
+        let dirty_ptr = workspace_dirty_buffers(workspace);
+        assert_eq!(c_string_to_rust(dirty_ptr), "[1]");
+
+        let report_ptr = workspace_save_all(workspace);
+        let report: serde_json::Value = serde_json::from_str(&c_string_to_rust(report_ptr)).unwrap();
+        assert_eq!(report["failed"][0]["buffer"], 1);
+
+        assert_eq!(workspace_close_view(workspace, left as u64), 0);
+        assert_eq!(workspace_close_view(workspace, right as u64), 1);
+        assert_eq!(workspace_resolve_close(workspace, buffer as u64, 2), 2);
+        assert_eq!(workspace_close_buffer(workspace, buffer as u64), 1);
+        assert_eq!(workspace_resolve_close(workspace, buffer as u64, 1), 0);
+        assert!(workspace_buffer_editor(workspace, buffer as u64).is_null());
+
+        editor_free_string(list_ptr);
+        editor_free_string(dirty_ptr);
+        editor_free_string(report_ptr);
+        free_c_string(content);
+        free_c_string(language);
+        free_c_string(text);
+        workspace_free(workspace);
+    }
+}
+
+#[test]
+fn test_ffi_workspace_open_file() {
+    unsafe {
+        let file = std::env::temp_dir().join(format!("ffi_workspace_{}.py", std::process::id()));
+        std::fs::write(&file, "print(1)\n").unwrap();
+        let path = create_c_string(file.to_str().unwrap());
+
+        let workspace = workspace_new();
+        let buffer = workspace_open_file(workspace, path);
+        assert!(buffer > 0);
+        assert_eq!(workspace_open_file(workspace, path), buffer);
+        assert_eq!(workspace_find_by_path(workspace, path), buffer);
+
+        workspace_free(workspace);
+        free_c_string(path);
+        std::fs::remove_file(file).ok();
+    }
+}
+
+#[test]
+fn test_ffi_workspace_null_handles() {
+    unsafe {
+        assert_eq!(workspace_open_file(ptr::null_mut(), ptr::null()), -1);
+        assert_eq!(workspace_create_view(ptr::null_mut(), 1), -1);
+        assert!(workspace_activate_view(ptr::null_mut(), 1).is_null());
+        assert!(workspace_list_buffers(ptr::null_mut()).is_null());
+        assert!(workspace_save_all(ptr::null_mut()).is_null());
+        assert_eq!(workspace_close_view(ptr::null_mut(), 1), -1);
+        assert_eq!(workspace_resolve_close(ptr::null_mut(), 1, 0), -1);
+
+        let workspace = workspace_new();
+        assert_eq!(workspace_create_view(workspace, 42), -1);
+        assert!(workspace_activate_view(workspace, 42).is_null());
+        assert_eq!(workspace_resolve_close(workspace, 42, 5), -1);
+        workspace_free(workspace);
+        workspace_free(ptr::null_mut());
+    }
+}
+
+// ============================================================
+// Macro Tests
+// ============================================================
+
+#[test]
+fn test_ffi_macro_record_and_play() {
+    unsafe {
+        let handle = editor_new();
+        let content = create_c_string("a\nb\nc\n");
+        editor_set_content(handle, content);
+
+        assert!(matches!(editor_start_macro_recording(handle), ResultCode::Success));
+        let end = create_c_string(r#"{"command":"move","motion":"lineEnd"}"#);
+        let semicolon = create_c_string(r#"{"command":"insertText","text":";"}"#);
+        let down = create_c_string(r#"{"command":"move","motion":"down"}"#);
+        for command in [end, semicolon, down] {
+            assert_eq!(editor_execute_command(handle, command), 1);
+        }
+        let macro_ptr = editor_stop_macro_recording(handle);
+        assert!(!macro_ptr.is_null());
+        assert!(editor_stop_macro_recording(handle).is_null());
+
+        assert_eq!(editor_play_macro(handle, macro_ptr, 1), 1);
+        let content_ptr = editor_get_content(handle);
+        assert_eq!(c_string_to_rust(content_ptr), "a;\nb;\nc\n");
+        assert_eq!(editor_undo(handle), 1);
+
+        assert_eq!(editor_play_macro_on_lines(handle, macro_ptr, 1, 2), 2);
+        let lines_ptr = editor_get_content(handle);
+        assert_eq!(c_string_to_rust(lines_ptr), "a;\nb;\nc;\n");
+
+        let search = create_c_string(r#"{"command":"searchNext","query":"zzz"}"#);
+        assert_eq!(editor_execute_command(handle, search), 0);
+        let bad = create_c_string(r#"{"command":"fly"}"#);
+        assert_eq!(editor_execute_command(handle, bad), -1);
+        assert_eq!(editor_play_macro(handle, bad, 1), -1);
+
+        for ptr in [macro_ptr, content_ptr, lines_ptr] {
+            editor_free_string(ptr);
+        }
+        for ptr in [content, end, semicolon, down, search, bad] {
+            free_c_string(ptr);
+        }
+        editor_free(handle);
+    }
+}
+
+#[test]
+fn test_ffi_macro_records_edit_calls() {
+    unsafe {
+        let handle = editor_new();
+        let content = create_c_string("a\nb\nc\n");
+        editor_set_content(handle, content);
+
+        editor_start_macro_recording(handle);
+        let semicolon = create_c_string(";");
+        assert!(matches!(editor_move_cursor(handle, 0, 1), ResultCode::Success));
+        assert!(matches!(editor_insert_text(handle, semicolon), ResultCode::Success));
+        assert!(matches!(editor_move_cursor(handle, 1, 0), ResultCode::Success));
+        let macro_ptr = editor_stop_macro_recording(handle);
+        assert!(!macro_ptr.is_null());
+
+        assert_eq!(editor_play_macro(handle, macro_ptr, 2), 2);
+        let content_ptr = editor_get_content(handle);
+        assert_eq!(c_string_to_rust(content_ptr), "a;\nb;\nc;\n");
+
+        editor_free_string(macro_ptr);
+        editor_free_string(content_ptr);
+        free_c_string(content);
+        free_c_string(semicolon);
+        editor_free(handle);
+    }
+}
+
+#[test]
+fn test_ffi_macro_null_handles() {
+    unsafe {
+        assert_eq!(editor_execute_command(ptr::null_mut(), ptr::null()), -1);
+        assert!(matches!(editor_start_macro_recording(ptr::null_mut()), ResultCode::ErrorNull));
+        assert!(editor_stop_macro_recording(ptr::null_mut()).is_null());
+        assert_eq!(editor_play_macro(ptr::null_mut(), ptr::null(), 1), -1);
+        assert_eq!(editor_play_macro_on_lines(ptr::null_mut(), ptr::null(), 0, 0), -1);
+    }
+}
+
+// ============================================================
+// Crash Recovery Tests
+// ============================================================
+
+#[test]
+fn test_ffi_recovery_crash_and_restore() {
+    unsafe {
+        let dir = std::env::temp_dir().join(format!("ffi_recovery_{}", std::process::id()));
+        std::fs::remove_dir_all(&dir).ok();
+        let root = create_c_string(dir.to_str().unwrap());
+
+        let workspace = workspace_new();
+        let content = create_c_string("draft");
+        let language = create_c_string("markdown");
+        let buffer = workspace_open_untitled(workspace, content, language);
+        let editor = workspace_buffer_editor(workspace, buffer as u64);
+        let text = create_c_string("# ");
+        editor_insert_text(editor, text);
+
+        let journal = recovery_journal_create(root, 1);
+        assert!(!journal.is_null());
+        assert_eq!(recovery_journal_write(journal, workspace), 1);
+        let session_ptr = recovery_journal_session_id(journal);
+
+        // Simulated crash: no finish
+        recovery_journal_free(journal);
+        workspace_free(workspace);
+
+        let sessions_ptr = recovery_list_sessions(root, ptr::null());
+        let sessions: serde_json::Value = serde_json::from_str(&c_string_to_rust(sessions_ptr)).unwrap();
+        assert_eq!(sessions[0]["id"], c_string_to_rust(session_ptr));
+        assert_eq!(sessions[0]["cleanExit"], false);
+        assert_eq!(sessions[0]["buffers"][0]["uri"], "untitled:Untitled-1");
+
+        let key = create_c_string("buffer-1");
+        let diff_ptr = recovery_diff(root, session_ptr, key, 3);
+        let diff: serde_json::Value = serde_json::from_str(&c_string_to_rust(diff_ptr)).unwrap();
+        assert_eq!(diff[0]["lines"][0]["op"], "insert");
+        assert_eq!(diff[0]["lines"][0]["text"], "# draft");
+
+        let workspace = workspace_new();
+        let restored_ptr = recovery_restore_session(root, session_ptr, workspace);
+        assert_eq!(c_string_to_rust(restored_ptr), "[1]");
+        let content_ptr = editor_get_content(workspace_buffer_editor(workspace, 1));
+        assert_eq!(c_string_to_rust(content_ptr), "# draft");
+
+        assert!(matches!(recovery_delete_session(root, session_ptr), ResultCode::Success));
+
+        // A finished session without hot exit leaves nothing behind
+        let journal = recovery_journal_create(root, 0);
+        assert!(matches!(recovery_journal_finish(journal, workspace, 0), ResultCode::Success));
+        let empty_ptr = recovery_list_sessions(root, ptr::null());
+        assert_eq!(c_string_to_rust(empty_ptr), "[]");
+
+        for ptr in [session_ptr, sessions_ptr, diff_ptr, restored_ptr, content_ptr, empty_ptr] {
+            editor_free_string(ptr);
+        }
+        for ptr in [root, content, language, text, key] {
+            free_c_string(ptr);
+        }
+        workspace_free(workspace);
+        std::fs::remove_dir_all(dir).ok();
+    }
+}
+
+#[test]
+fn test_ffi_recovery_null_handles() {
+    unsafe {
+        assert!(recovery_journal_create(ptr::null(), 0).is_null());
+        assert!(recovery_journal_session_id(ptr::null_mut()).is_null());
+        assert_eq!(recovery_journal_write(ptr::null_mut(), ptr::null_mut()), -1);
+        assert!(matches!(
+            recovery_journal_finish(ptr::null_mut(), ptr::null_mut(), 0),
+            ResultCode::ErrorNull
+        ));
+        assert!(recovery_list_sessions(ptr::null(), ptr::null()).is_null());
+        assert!(recovery_diff(ptr::null(), ptr::null(), ptr::null(), 3).is_null());
+        assert!(recovery_restore_session(ptr::null(), ptr::null(), ptr::null_mut()).is_null());
+        assert!(matches!(recovery_delete_session(ptr::null(), ptr::null()), ResultCode::ErrorNull));
+        recovery_journal_free(ptr::null_mut());
+    }
+}
+
+// ============================================================
+// Background Parsing Tests
+// ============================================================
+
+#[test]
+fn test_ffi_background_parse() {
+    unsafe {
+        let content = create_c_string("fn a() {}\n");
+        let language = create_c_string("rust");
+        let handle = editor_with_content(content, language);
+
+        assert_eq!(editor_set_parse_config(handle, 0, 0, 5000, 1), ResultCode::Success);
+        editor_move_cursor(handle, 1, 0);
+        let text = create_c_string("fn b() {}\n");
+        assert_eq!(editor_insert_text(handle, text), ResultCode::Success);
+
+        assert_eq!(editor_wait_for_parse(handle, 10_000), 1);
+        assert_eq!(editor_poll_parse(handle), 0);
+
+        let stats_ptr = editor_performance_stats(handle);
+        let stats: serde_json::Value = serde_json::from_str(&c_string_to_rust(stats_ptr)).unwrap();
+        assert_eq!(stats["parseCount"], 2);
+        assert_eq!(stats["cancelledParses"], 0);
+
+        editor_free_string(stats_ptr);
+        for ptr in [content, language, text] {
+            free_c_string(ptr);
+        }
+        editor_free(handle);
+    }
+}
+
+#[test]
+fn test_ffi_background_parse_null_handle() {
+    unsafe {
+        assert_eq!(editor_set_parse_config(ptr::null_mut(), 0, 0, 0, 1), ResultCode::ErrorNull);
+        assert_eq!(editor_poll_parse(ptr::null_mut()), -1);
+        assert_eq!(editor_wait_for_parse(ptr::null_mut(), 0), -1);
+        assert!(editor_performance_stats(ptr::null_mut()).is_null());
+    }
+}
+
+// ============================================================
+// Telemetry Tests
+// ============================================================
+
+#[test]
+fn test_ffi_telemetry_snapshot() {
+    unsafe {
+        let handle = editor_new();
+        let text = create_c_string("hello");
+        assert_eq!(editor_insert_text(handle, text), ResultCode::Success);
+
+        let snapshot_ptr = editor_telemetry_snapshot(handle);
+        let snapshot: serde_json::Value = serde_json::from_str(&c_string_to_rust(snapshot_ptr)).unwrap();
+        assert!(snapshot["operations"]["insert"]["count"].as_u64().unwrap() >= 1);
+        assert!(snapshot["operations"]["ffiCall"]["count"].as_u64().unwrap() >= 1);
+        assert!(snapshot["operations"]["ffiLockWait"]["p99Us"].is_u64());
+        assert_eq!(snapshot["memory"]["textBytes"], 5);
+
+        // Without an editor there is no memory section
+        let global_ptr = editor_telemetry_snapshot(ptr::null_mut());
+        let global: serde_json::Value = serde_json::from_str(&c_string_to_rust(global_ptr)).unwrap();
+        assert!(global["operations"].is_object());
+        assert!(global.get("memory").is_none());
+
+        editor_free_string(snapshot_ptr);
+        editor_free_string(global_ptr);
+        free_c_string(text);
+        editor_free(handle);
+
+        // A freed handle is rejected
+        assert!(editor_telemetry_snapshot(handle).is_null());
+    }
+}
+
+// ============================================================
+// Collaboration Tests
+// ============================================================
+
+#[test]
+fn test_ffi_collab_replicas_converge() {
+    unsafe {
+        let a = editor_new();
+        let b = editor_new();
+        let content = create_c_string("shared\n");
+        for handle in [a, b] {
+            assert_eq!(editor_set_content(handle, content), ResultCode::Success);
+        }
+        assert_eq!(editor_collab_enable(a, 1), ResultCode::Success);
+        assert_eq!(editor_collab_enable(b, 2), ResultCode::Success);
+
+        let text_a = create_c_string(">");
+        let text_b = create_c_string("!");
+        assert_eq!(editor_insert_text(a, text_a), ResultCode::Success);
+        assert_eq!(editor_move_cursor(b, 0, 6), ResultCode::Success);
+        assert_eq!(editor_insert_text(b, text_b), ResultCode::Success);
+
+        // Exchange operations through the byte encoding
+        let mut len_a = 0;
+        let ops_a = editor_collab_take_ops(a, &mut len_a);
+        let mut len_b = 0;
+        let ops_b = editor_collab_take_ops(b, &mut len_b);
+        assert_eq!(editor_collab_apply_ops(b, ops_a, len_a), 0);
+        assert_eq!(editor_collab_apply_ops(a, ops_b, len_b), 0);
+
+        let content_a = editor_get_content(a);
+        let content_b = editor_get_content(b);
+        assert_eq!(c_string_to_rust(content_a), ">shared!\n");
+        assert_eq!(c_string_to_rust(content_b), ">shared!\n");
+
+        // Nothing is missing once both applied each other's operations
+        let versions_ptr = editor_collab_versions(b);
+        let versions: serde_json::Value = serde_json::from_str(&c_string_to_rust(versions_ptr)).unwrap();
+        assert_eq!(versions["1"], 1);
+        let mut missing_len = 0;
+        let missing = editor_collab_ops_since(a, versions_ptr, &mut missing_len);
+        let missing_ops = CrdtOp::decode(std::slice::from_raw_parts(missing, missing_len)).unwrap();
+        assert!(missing_ops.is_empty());
+
+        // Remote cursors
+        let mut cursor_len = 0;
+        let cursor = editor_collab_cursor_update(a, &mut cursor_len);
+        assert_eq!(editor_collab_apply_cursor(b, cursor, cursor_len), ResultCode::Success);
+        let cursors_ptr = editor_collab_remote_cursors(b);
+        let cursors: serde_json::Value = serde_json::from_str(&c_string_to_rust(cursors_ptr)).unwrap();
+        assert_eq!(cursors[0]["replica"], 1);
+        assert_eq!(cursors[0]["cursor"]["column"], 1);
+
+        // Invalid input
+        assert_eq!(editor_collab_apply_ops(a, cursor, cursor_len), -1);
+        assert_eq!(editor_collab_enable(a, 0), ResultCode::ErrorUnknown);
+
+        for (ptr, len) in [(ops_a, len_a), (ops_b, len_b), (missing, missing_len), (cursor, cursor_len)] {
+            editor_free_bytes(ptr, len);
+        }
+        for ptr in [content_a, content_b, versions_ptr, cursors_ptr] {
+            editor_free_string(ptr);
+        }
+        for ptr in [content, text_a, text_b] {
+            free_c_string(ptr);
+        }
+        editor_free(a);
+        editor_free(b);
+    }
+}
+
+#[test]
+fn test_ffi_collab_trim_and_snapshot() {
+    unsafe {
+        let a = editor_new();
+        let b = editor_new();
+        assert_eq!(editor_collab_enable(a, 1), ResultCode::Success);
+        assert_eq!(editor_collab_enable(b, 2), ResultCode::Success);
+
+        let text = create_c_string("hi");
+        assert_eq!(editor_insert_text(a, text), ResultCode::Success);
+        let mut ops_len = 0;
+        let ops = editor_collab_take_ops(a, &mut ops_len);
+        assert_eq!(editor_collab_apply_ops(b, ops, ops_len), 0);
+
+        // Once b acknowledges, a replica starting from scratch needs a snapshot
+        let versions_ptr = editor_collab_versions(b);
+        assert_eq!(editor_collab_acknowledge(a, 2, versions_ptr), ResultCode::Success);
+        let empty = create_c_string("{}");
+        let mut missing_len = 0;
+        assert!(editor_collab_ops_since(a, empty, &mut missing_len).is_null());
+
+        let mut snapshot_len = 0;
+        let snapshot = editor_collab_snapshot(a, &mut snapshot_len);
+        assert!(!snapshot.is_null());
+        let c = editor_new();
+        assert_eq!(editor_collab_enable_from_snapshot(c, 3, snapshot, snapshot_len), ResultCode::Success);
+        let content_ptr = editor_get_content(c);
+        assert_eq!(c_string_to_rust(content_ptr), "hi");
+
+        assert_eq!(editor_collab_remove_peer(a, 2), ResultCode::Success);
+        assert_eq!(editor_collab_acknowledge(a, 1, versions_ptr), ResultCode::ErrorUnknown);
+        assert_eq!(editor_collab_enable_from_snapshot(c, 3, ops, ops_len), ResultCode::ErrorUnknown);
+
+        editor_free_bytes(ops, ops_len);
+        editor_free_bytes(snapshot, snapshot_len);
+        editor_free_string(versions_ptr);
+        editor_free_string(content_ptr);
+        free_c_string(text);
+        free_c_string(empty);
+        for handle in [a, b, c] {
+            editor_free(handle);
+        }
+    }
+}
+
+#[test]
+fn test_ffi_collab_null_handle() {
+    unsafe {
+        let mut len = 0;
+        assert_eq!(editor_collab_enable(ptr::null_mut(), 1), ResultCode::ErrorNull);
+        assert!(editor_collab_take_ops(ptr::null_mut(), &mut len).is_null());
+        assert_eq!(editor_collab_apply_ops(ptr::null_mut(), ptr::null(), 0), -1);
+        assert!(editor_collab_remote_cursors(ptr::null_mut()).is_null());
+
+        // Not collaborating
+        let handle = editor_new();
+        assert!(editor_collab_versions(handle).is_null());
+        assert!(editor_collab_cursor_update(handle, &mut len).is_null());
+        editor_free(handle);
+    }
+}
+
+// ============================================================
+// Dirty Diff Tests
+// ============================================================
+
+#[test]
+fn test_ffi_dirty_diff() {
+    unsafe {
+        let handle = editor_new();
+        let content = create_c_string("a\nb\nc\n");
+        assert_eq!(editor_set_content(handle, content), ResultCode::Success);
+        assert_eq!(editor_set_diff_base(handle, content), ResultCode::Success);
+        assert_eq!(editor_next_change(handle, 0), -1);
+
+        let text = create_c_string("x");
+        assert_eq!(editor_move_cursor(handle, 2, 0), ResultCode::Success);
+        assert_eq!(editor_insert_text(handle, text), ResultCode::Success);
+
+        let markers_ptr = editor_gutter_markers(handle, 0, 10);
+        let markers: serde_json::Value = serde_json::from_str(&c_string_to_rust(markers_ptr)).unwrap();
+        assert_eq!(markers, serde_json::json!([{"line": 2, "kind": "modified"}]));
+
+        let changes_ptr = editor_diff_changes(handle);
+        let changes: serde_json::Value = serde_json::from_str(&c_string_to_rust(changes_ptr)).unwrap();
+        assert_eq!(changes[0]["old"]["start"], 2);
+
+        assert_eq!(editor_next_change(handle, 0), 2);
+        assert_eq!(editor_previous_change(handle, 0), 2);
+        assert_eq!(editor_revert_change(handle, 0), 0);
+        assert_eq!(editor_revert_change(handle, 2), 1);
+
+        let reverted = editor_get_content(handle);
+        assert_eq!(c_string_to_rust(reverted), "a\nb\nc\n");
+
+        assert_eq!(editor_clear_diff_base(handle), ResultCode::Success);
+        assert_eq!(editor_revert_change(handle, 2), -1);
+
+        for ptr in [markers_ptr, changes_ptr, reverted] {
+            editor_free_string(ptr);
+        }
+        free_c_string(content);
+        free_c_string(text);
+        editor_free(handle);
+    }
+}
+
+#[test]
+fn test_ffi_diff_base_saved() {
+    unsafe {
+        let handle = editor_new();
+        assert_eq!(editor_set_diff_base(handle, ptr::null()), ResultCode::Success);
+
+        let text = create_c_string("new\n");
+        assert_eq!(editor_insert_text(handle, text), ResultCode::Success);
+        assert_eq!(editor_next_change(handle, 0), 0);
+
+        assert_eq!(editor_mark_saved(handle), ResultCode::Success);
+        assert_eq!(editor_next_change(handle, 0), -1);
+
+        assert_eq!(editor_set_diff_base(ptr::null_mut(), ptr::null()), ResultCode::ErrorNull);
+        assert!(editor_gutter_markers(ptr::null_mut(), 0, 0).is_null());
+
+        free_c_string(text);
+        editor_free(handle);
+    }
+}
+
+// ============================================================
+// Spell Checking Tests
+// ============================================================
+
+#[test]
+fn test_ffi_spell_check() {
+    unsafe {
+        let dir = std::env::temp_dir().join(format!("ffi_spell_check_{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        std::fs::write(dir.join("en.aff"), "SET UTF-8\nSFX S Y 1\nSFX S 0 s .\n").unwrap();
+        std::fs::write(dir.join("en.dic"), "3\nhello/S\nworld/S\nthe\n").unwrap();
+
+        let checker = spell_checker_new();
+        let aff = create_c_string(dir.join("en.aff").to_str().unwrap());
+        let dic = create_c_string(dir.join("en.dic").to_str().unwrap());
+        let missing = create_c_string(dir.join("missing.dic").to_str().unwrap());
+        assert_eq!(spell_checker_load_dictionary(checker, aff, missing), ResultCode::ErrorUnknown);
+        assert_eq!(spell_checker_load_dictionary(checker, aff, dic), ResultCode::Success);
+
+        let handle = editor_new();
+        let content = create_c_string("hellos wrold\n");
+        assert_eq!(editor_set_content(handle, content), ResultCode::Success);
+        assert_eq!(editor_set_spell_checker(handle, checker), ResultCode::Success);
+
+        let diagnostics_ptr = editor_spell_check(handle);
+        let diagnostics: serde_json::Value = serde_json::from_str(&c_string_to_rust(diagnostics_ptr)).unwrap();
+        assert_eq!(diagnostics[0]["word"], "wrold");
+        assert_eq!(diagnostics[0]["range"]["start"]["character"], 7);
+        let hover_ptr = editor_spell_suggestions(handle, 0, 8);
+        assert_eq!(c_string_to_rust(hover_ptr), r#"["world"]"#);
+
+        let word = create_c_string("wrold");
+        let suggestions_ptr = spell_checker_suggest(checker, word);
+        assert_eq!(c_string_to_rust(suggestions_ptr), r#"["world"]"#);
+
+        let list = create_c_string(dir.join("words.txt").to_str().unwrap());
+        assert_eq!(spell_checker_load_word_list(checker, 1, list), ResultCode::Success);
+        assert_eq!(spell_checker_add_word(checker, 1, word), ResultCode::Success);
+        assert_eq!(spell_checker_add_word(checker, 2, word), ResultCode::ErrorOutOfBounds);
+        let rechecked = editor_spell_check(handle);
+        assert_eq!(c_string_to_rust(rechecked), "[]");
+        assert_eq!(std::fs::read_to_string(dir.join("words.txt")).unwrap(), "wrold\n");
+
+        // Editors keep the checker after its handle is freed
+        spell_checker_free(checker);
+        assert_eq!(spell_checker_add_word(checker, 0, word), ResultCode::ErrorInvalidHandle);
+        assert_eq!(editor_set_spell_checker(handle, checker), ResultCode::ErrorInvalidHandle);
+        assert_eq!(editor_set_spell_checker(handle, ptr::null_mut()), ResultCode::Success);
+
+        for ptr in [diagnostics_ptr, hover_ptr, suggestions_ptr, rechecked] {
+            editor_free_string(ptr);
+        }
+        for ptr in [aff, dic, missing, content, word, list] {
+            free_c_string(ptr);
+        }
+        editor_free(handle);
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
+
+// ============================================================
+// Keymap Tests
+// ============================================================
+
+#[test]
+fn test_ffi_keymap_resolve() {
+    unsafe {
+        let keymap = keymap_new(0);
+        let defaults = create_c_string(
+            r#"[
+                { "key": "ctrl+k ctrl+c", "command": "comment", "when": "!editorReadonly" },
+                { "key": "tab", "command": "indent", "when": "editorHasSelection && mode == insert" },
+            ]"#,
+        );
+        assert_eq!(keymap_load_defaults(keymap, defaults), ResultCode::Success);
+
+        let handle = editor_new();
+        let content = create_c_string("hello\n");
+        assert_eq!(editor_set_content(handle, content), ResultCode::Success);
+
+        let chord = create_c_string("ctrl+k");
+        let pending = keymap_resolve(keymap, handle, chord, ptr::null());
+        assert_eq!(c_string_to_rust(pending), r#"{"kind":"pending","keys":"ctrl+k"}"#);
+        let second = create_c_string("Ctrl+C");
+        let resolved = keymap_resolve(keymap, handle, second, ptr::null());
+        assert_eq!(c_string_to_rust(resolved), r#"{"kind":"command","command":"comment"}"#);
+
+        // Editor state and host context both feed the when clause
+        let tab = create_c_string("tab");
+        let insert_mode = create_c_string(r#"{"mode": "insert"}"#);
+        let no_selection = keymap_resolve(keymap, handle, tab, insert_mode);
+        assert_eq!(c_string_to_rust(no_selection), r#"{"kind":"noMatch"}"#);
+        assert_eq!(editor_set_selection(handle, 0, 0, 0, 3), ResultCode::Success);
+        let indent = keymap_resolve(keymap, handle, tab, insert_mode);
+        assert_eq!(c_string_to_rust(indent), r#"{"kind":"command","command":"indent"}"#);
+
+        let invalid = create_c_string("hyper+k");
+        assert!(keymap_resolve(keymap, handle, invalid, ptr::null()).is_null());
+        assert_eq!(keymap_load_user(keymap, invalid), ResultCode::ErrorUnknown);
+
+        for ptr in [pending, resolved, no_selection, indent] {
+            editor_free_string(ptr);
+        }
+        for ptr in [defaults, content, chord, second, tab, insert_mode, invalid] {
+            free_c_string(ptr);
+        }
+        editor_free(handle);
+        keymap_free(keymap);
+    }
+}
+
+#[test]
+fn test_ffi_keymap_conflicts() {
+    unsafe {
+        let keymap = keymap_new(1);
+        let defaults = create_c_string(r#"[{ "key": "ctrl+d", "mac": "cmd+d", "command": "selectNext" }]"#);
+        let user = create_c_string(r#"[{ "key": "cmd+d", "command": "duplicateLine" }]"#);
+        assert_eq!(keymap_load_defaults(keymap, defaults), ResultCode::Success);
+        assert_eq!(keymap_load_user(keymap, user), ResultCode::Success);
+
+        let conflicts_ptr = keymap_conflicts(keymap);
+        let conflicts: serde_json::Value = serde_json::from_str(&c_string_to_rust(conflicts_ptr)).unwrap();
+        assert_eq!(conflicts[0]["kind"], "duplicate");
+        assert_eq!(conflicts[0]["keys"], "meta+d");
+        assert_eq!(conflicts[0]["shadowed"], "selectNext");
+
+        assert_eq!(keymap_cancel_chord(keymap), ResultCode::Success);
+        keymap_free(keymap);
+        assert_eq!(keymap_cancel_chord(keymap), ResultCode::ErrorInvalidHandle);
+        assert!(keymap_conflicts(ptr::null_mut()).is_null());
+
+        editor_free_string(conflicts_ptr);
+        free_c_string(defaults);
+        free_c_string(user);
+    }
+}
+
+// ============================================================
+// Handle Safety Tests
+// ============================================================
+
+#[test]
+fn test_ffi_use_after_free_is_rejected() {
+    unsafe {
+        let handle = editor_new();
+        editor_free(handle);
+
+        // Double free is ignored
+        editor_free(handle);
+
+        let text = create_c_string("x");
+        assert_eq!(editor_insert_text(handle, text), ResultCode::ErrorInvalidHandle);
+        assert!(editor_get_content(handle).is_null());
+        assert_eq!(editor_undo(handle), -1);
+
+        let error_ptr = editor_last_error();
+        assert_eq!(c_string_to_rust(error_ptr), "invalid or freed editor handle");
+        assert!(editor_last_error().is_null());
+
+        // A new editor reusing the slot does not answer to the old handle
+        let fresh = editor_new();
+        assert_ne!(fresh, handle);
+        assert_eq!(editor_insert_text(handle, text), ResultCode::ErrorInvalidHandle);
+        assert_eq!(editor_insert_text(fresh, text), ResultCode::Success);
+
+        // Foreign values are not handles
+        let renderer = renderer_new(14.0, 20.0);
+        assert_eq!(editor_insert_text(renderer as EditorHandle, text), ResultCode::ErrorInvalidHandle);
+        renderer_free(renderer);
+        renderer_free(renderer);
+        assert_eq!(renderer_set_wrap(renderer, 0.0, 0, 4, 0), ResultCode::ErrorInvalidHandle);
+
+        editor_free_string(error_ptr);
+        free_c_string(text);
+        editor_free(fresh);
+    }
+}
+
+#[test]
+fn test_ffi_concurrent_calls_on_one_handle() {
+    unsafe {
+        let handle = editor_new();
+        let shared = handle as usize;
+
+        let threads: Vec<_> = (0..8)
+            .map(|_| {
+                std::thread::spawn(move || {
+                    let handle = shared as EditorHandle;
+                    let text = create_c_string("ab");
+                    for _ in 0..100 {
+                        assert_eq!(editor_insert_text(handle, text), ResultCode::Success);
+                        let content = editor_get_content(handle);
+                        assert!(!content.is_null());
+                        editor_free_string(content);
+                    }
+                    free_c_string(text);
+                })
+            })
+            .collect();
+        for thread in threads {
+            thread.join().unwrap();
+        }
+
+        let content_ptr = editor_get_content(handle);
+        assert_eq!(c_string_to_rust(content_ptr), "ab".repeat(800));
+
+        editor_free_string(content_ptr);
+        editor_free(handle);
+    }
+}
+
+#[test]
+fn test_ffi_buffer_editor_handles_follow_workspace() {
+    unsafe {
+        let workspace = workspace_new();
+        let content = create_c_string("text");
+        let language = create_c_string("plaintext");
+        let buffer = workspace_open_untitled(workspace, content, language) as u64;
+
+        let editor = workspace_buffer_editor(workspace, buffer);
+        assert!(!editor.is_null());
+        assert_eq!(workspace_buffer_editor(workspace, buffer), editor);
+
+        // Closing the buffer invalidates its editor handle
+        assert_eq!(workspace_close_buffer(workspace, buffer), 1);
+        assert_eq!(workspace_resolve_close(workspace, buffer, 1), 0);
+        assert_eq!(editor_move_cursor(editor, 0, 0), ResultCode::ErrorInvalidHandle);
+        let error_ptr = editor_last_error();
+        assert_eq!(c_string_to_rust(error_ptr), "the editor's buffer was closed");
+
+        // Freeing the workspace invalidates the editor handles of its buffers
+        let other = workspace_open_untitled(workspace, content, language) as u64;
+        let other_editor = workspace_buffer_editor(workspace, other);
+        assert_eq!(editor_move_cursor(other_editor, 0, 1), ResultCode::Success);
+        workspace_free(workspace);
+        assert_eq!(editor_move_cursor(other_editor, 0, 0), ResultCode::ErrorInvalidHandle);
+        assert!(workspace_buffer_editor(workspace, other).is_null());
+        workspace_free(workspace);
+
+        editor_free_string(error_ptr);
+        free_c_string(content);
+        free_c_string(language);
+    }
+}
+
+#[test]
+fn test_ffi_panic_becomes_error_code() {
+    unsafe {
+        let code = ffi_guard(|| -> ResultCode { panic!("layout exploded") });
+        assert_eq!(code, ResultCode::ErrorPanic);
+
+        let error_ptr = editor_last_error();
+        assert_eq!(c_string_to_rust(error_ptr), "panic: layout exploded");
+
+        // The editor behind a handle stays usable after a panic while locked
+        let handle = editor_new();
+        let result: i32 = with_editor(handle, -1, |_| panic!("inside the lock"));
+        assert_eq!(result, -1);
+        let text = create_c_string("ok");
+        assert_eq!(editor_insert_text(handle, text), ResultCode::Success);
+
+        editor_free_string(error_ptr);
+        free_c_string(text);
+        editor_free(handle);
+    }
+}
+
+#[test]
+fn test_ffi_panic_inside_transaction_is_repaired() {
+    unsafe {
+        let handle = editor_new();
+        let result: i32 = with_editor(handle, -1, |editor| {
+            editor.begin_transaction();
+            editor.insert_text("a").unwrap();
+            panic!("inside a transaction")
+        });
+        assert_eq!(result, -1);
+
+        // The next call closes the stale transaction: edits are separate undo steps
+        let text = create_c_string("b");
+        assert_eq!(editor_insert_text(handle, text), ResultCode::Success);
+        assert_eq!(editor_undo(handle), 1);
+        let content_ptr = editor_get_content(handle);
+        assert_eq!(c_string_to_rust(content_ptr), "a");
+        editor_free_string(content_ptr);
+        assert_eq!(editor_undo(handle), 1);
+        let content_ptr = editor_get_content(handle);
+        assert_eq!(c_string_to_rust(content_ptr), "");
+        editor_free_string(content_ptr);
+
+        editor_free_string(editor_last_error());
+        free_c_string(text);
+        editor_free(handle);
+    }
+}
+
 // ============================================================
 // Memory Management Tests
 // ============================================================
@@ -0,0 +1,683 @@
+//! Keymap: resolving key presses to commands
+//!
+//! Keybindings load from VS Code-style JSON (comments and trailing commas
+//! allowed):
+//!
+//! ```text
+//! [
+//!   { "key": "ctrl+k ctrl+c", "command": "editor.action.addCommentLine",
+//!     "when": "editorTextFocus && !editorReadonly" },
+//!   { "key": "ctrl+d", "mac": "cmd+d", "command": "selectNext", "args": { "wrap": true } },
+//!   { "key": "ctrl+shift+k", "command": "-editor.action.deleteLines" }
+//! ]
+//! ```
+//!
+//! User bindings are layered over the defaults: later bindings win, and a
+//! `-command` entry removes earlier bindings of that command. A key press
+//! resolves to the highest-priority binding whose keys start with the
+//! pressed sequence and whose `when` clause holds; if that binding has
+//! more keys, the press starts (or continues) a chord.
+
+pub mod when;
+
+use std::collections::BTreeMap;
+use std::fmt;
+use std::str::FromStr;
+
+use anyhow::{anyhow, bail, Context, Result};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+pub use when::{Comparison, ContextValue, KeyContext, WhenClause};
+
+/// Modifier keys held with a key.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Modifiers {
+    pub ctrl: bool,
+    pub shift: bool,
+    pub alt: bool,
+
+    /// Cmd on macOS, Windows/Super key elsewhere
+    pub meta: bool,
+}
+
+/// Single key press, e.g. `ctrl+shift+k`.
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct KeyPress {
+    pub modifiers: Modifiers,
+
+    /// Lowercase key name (`k`, `enter`, `f5`, `[`)
+    pub key: String,
+}
+
+impl KeyPress {
+    pub fn new(modifiers: Modifiers, key: &str) -> Self {
+        Self {
+            modifiers,
+            key: normalize_key(key),
+        }
+    }
+}
+
+/// Helper: Canonical key name
+///
+/// Uses VS Code's names (`up`, `escape`, `enter`); host names such as
+/// Flutter/DOM `ArrowUp` are accepted as aliases.
+fn normalize_key(key: &str) -> String {
+    let key = key.to_lowercase();
+    match key.as_str() {
+        "esc" => "escape".to_string(),
+        "return" => "enter".to_string(),
+        "del" => "delete".to_string(),
+        "ins" => "insert".to_string(),
+        "spacebar" => "space".to_string(),
+        "arrowup" | "arrowdown" | "arrowleft" | "arrowright" => key["arrow".len()..].to_string(),
+        _ => key,
+    }
+}
+
+impl FromStr for KeyPress {
+    type Err = anyhow::Error;
+
+    /// Parses `ctrl+shift+k` (modifier names as in VS Code: `ctrl`,
+    /// `shift`, `alt`/`option`, `cmd`/`meta`/`win`)
+    fn from_str(text: &str) -> Result<Self> {
+        // `ctrl++` binds the plus key
+        let (modifiers_text, key) = match text.strip_suffix("++") {
+            Some(rest) => (rest, "+"),
+            None => match text.rsplit_once('+') {
+                Some((modifiers, key)) => (modifiers, key),
+                None => ("", text),
+            },
+        };
+        if key.is_empty() {
+            bail!("missing key in {:?}", text);
+        }
+
+        let mut modifiers = Modifiers::default();
+        for modifier in modifiers_text.split('+').filter(|m| !m.is_empty()) {
+            match modifier.to_lowercase().as_str() {
+                "ctrl" | "control" => modifiers.ctrl = true,
+                "shift" => modifiers.shift = true,
+                "alt" | "option" | "opt" => modifiers.alt = true,
+                "cmd" | "meta" | "win" | "super" => modifiers.meta = true,
+                other => bail!("unknown modifier {:?} in {:?}", other, text),
+            }
+        }
+
+        Ok(Self::new(modifiers, key))
+    }
+}
+
+impl fmt::Display for KeyPress {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let modifiers = [
+            (self.modifiers.ctrl, "ctrl"),
+            (self.modifiers.shift, "shift"),
+            (self.modifiers.alt, "alt"),
+            (self.modifiers.meta, "meta"),
+        ];
+        for (_, name) in modifiers.iter().filter(|(held, _)| *held) {
+            write!(f, "{}+", name)?;
+        }
+        write!(f, "{}", self.key)
+    }
+}
+
+/// Helper: Parses a space-separated chord (`ctrl+k ctrl+c`)
+fn parse_sequence(text: &str) -> Result<Vec<KeyPress>> {
+    let keys = text.split_whitespace().map(KeyPress::from_str).collect::<Result<Vec<_>>>()?;
+    if keys.is_empty() {
+        bail!("empty key");
+    }
+    Ok(keys)
+}
+
+/// Helper: Formats a key sequence as in keybinding files
+fn format_sequence(keys: &[KeyPress]) -> String {
+    keys.iter().map(KeyPress::to_string).collect::<Vec<_>>().join(" ")
+}
+
+/// Platform whose key variants (`mac`, `linux`, `win`) apply.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Platform {
+    Linux,
+    Mac,
+    Windows,
+}
+
+impl Platform {
+    /// Gets the platform the library was built for
+    pub fn current() -> Self {
+        if cfg!(target_os = "macos") || cfg!(target_os = "ios") {
+            Platform::Mac
+        } else if cfg!(target_os = "windows") {
+            Platform::Windows
+        } else {
+            Platform::Linux
+        }
+    }
+}
+
+/// Where a binding comes from.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub enum BindingSource {
+    Default,
+    User,
+}
+
+/// Key sequence bound to a command.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Keybinding {
+    pub keys: Vec<KeyPress>,
+    pub command: String,
+    pub args: Option<Value>,
+    pub when: Option<WhenClause>,
+    pub source: BindingSource,
+}
+
+impl Keybinding {
+    /// Helper: Checks if the binding applies in a context
+    fn is_active(&self, context: &KeyContext) -> bool {
+        self.when.as_ref().is_none_or(|when| when.evaluate(context))
+    }
+
+    /// Helper: Canonical `when` text (None = always)
+    fn when_text(&self) -> Option<String> {
+        self.when.as_ref().map(WhenClause::to_string)
+    }
+}
+
+/// Entry of a keybindings file as written.
+#[derive(Debug, Deserialize)]
+struct RawKeybinding {
+    key: Option<String>,
+    mac: Option<String>,
+    linux: Option<String>,
+    win: Option<String>,
+    command: String,
+    args: Option<Value>,
+    when: Option<String>,
+}
+
+/// Parsed keybindings file entry.
+#[derive(Debug, Clone)]
+enum KeybindingRule {
+    Add(Keybinding),
+
+    /// `-command`: removes earlier bindings of the command (only those
+    /// with these keys / `when`, if given)
+    Remove {
+        command: String,
+        keys: Option<Vec<KeyPress>>,
+        when: Option<String>,
+    },
+}
+
+/// Helper: Parses a keybindings file
+fn parse_rules(json: &str, source: BindingSource, platform: Platform) -> Result<Vec<KeybindingRule>> {
+    let raw: Vec<RawKeybinding> =
+        serde_json::from_str(&strip_jsonc(json)).context("invalid keybindings JSON")?;
+
+    let mut rules = Vec::with_capacity(raw.len());
+    for (index, entry) in raw.into_iter().enumerate() {
+        let rule = parse_rule(entry, source, platform).with_context(|| format!("keybinding {}", index + 1))?;
+        rules.extend(rule);
+    }
+    Ok(rules)
+}
+
+/// Helper: Parses one entry (None if it has no key on this platform)
+fn parse_rule(entry: RawKeybinding, source: BindingSource, platform: Platform) -> Result<Option<KeybindingRule>> {
+    let key = match platform {
+        Platform::Mac => entry.mac.or(entry.key),
+        Platform::Linux => entry.linux.or(entry.key),
+        Platform::Windows => entry.win.or(entry.key),
+    };
+    let keys = key.as_deref().map(parse_sequence).transpose()?;
+    let when = entry.when.as_deref().map(WhenClause::parse).transpose()?;
+
+    if let Some(command) = entry.command.strip_prefix('-') {
+        return Ok(Some(KeybindingRule::Remove {
+            command: command.to_string(),
+            keys,
+            when: when.map(|when| when.to_string()),
+        }));
+    }
+    if entry.command.is_empty() {
+        bail!("missing command");
+    }
+
+    let Some(keys) = keys else {
+        return Ok(None);
+    };
+    Ok(Some(KeybindingRule::Add(Keybinding {
+        keys,
+        command: entry.command,
+        args: entry.args,
+        when,
+        source,
+    })))
+}
+
+/// Helper: Removes `//` and `/* */` comments and trailing commas (JSONC)
+fn strip_jsonc(json: &str) -> String {
+    let mut result = String::with_capacity(json.len());
+    let mut chars = json.chars().peekable();
+    while let Some(ch) = chars.next() {
+        match ch {
+            '"' => {
+                result.push(ch);
+                while let Some(c) = chars.next() {
+                    result.push(c);
+                    match c {
+                        '\\' => result.extend(chars.next()),
+                        '"' => break,
+                        _ => {}
+                    }
+                }
+            }
+            '/' if chars.next_if_eq(&'/').is_some() => {
+                while chars.next_if(|c| *c != '\n').is_some() {}
+            }
+            '/' if chars.next_if_eq(&'*').is_some() => {
+                let mut previous = ' ';
+                for c in chars.by_ref() {
+                    if previous == '*' && c == '/' {
+                        break;
+                    }
+                    previous = c;
+                }
+            }
+            ']' | '}' => {
+                // Drop a comma before the closing bracket (only whitespace between)
+                let trimmed = result.trim_end().len();
+                if result[..trimmed].ends_with(',') {
+                    result.truncate(trimmed - 1);
+                }
+                result.push(ch);
+            }
+            _ => result.push(ch),
+        }
+    }
+    result
+}
+
+/// Outcome of a key press.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(tag = "kind", rename_all = "camelCase")]
+pub enum KeyResolution {
+    /// Run a command
+    Command {
+        command: String,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        args: Option<Value>,
+    },
+
+    /// Part of a chord; waiting for the next key
+    Pending { keys: String },
+
+    /// The key ended a chord without a match (swallowed, not typed)
+    Cancelled { keys: String },
+
+    /// No binding (the key goes on to normal handling, e.g. typing)
+    NoMatch,
+}
+
+/// Bindings whose keys collide.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct KeyConflict {
+    pub kind: ConflictKind,
+
+    /// Keys of the shadowed binding
+    pub keys: String,
+
+    /// Shared `when` clause (canonical form; None = always)
+    pub when: Option<String>,
+
+    /// Command that runs
+    pub winner: String,
+
+    /// Command that never runs under this `when` clause
+    pub shadowed: String,
+}
+
+/// How two bindings collide.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub enum ConflictKind {
+    /// Same keys, different commands
+    Duplicate,
+
+    /// One binding's keys start the other's chord
+    ChordPrefix,
+}
+
+/// Default and user keybindings plus chord state.
+#[derive(Debug, Clone)]
+pub struct Keymap {
+    platform: Platform,
+    defaults: Vec<Keybinding>,
+    user: Vec<KeybindingRule>,
+
+    /// Effective bindings, lowest priority first
+    bindings: Vec<Keybinding>,
+
+    /// Keys of the chord in progress
+    pending: Vec<KeyPress>,
+}
+
+impl Default for Keymap {
+    fn default() -> Self {
+        Self::new(Platform::current())
+    }
+}
+
+impl Keymap {
+    /// Creates an empty keymap
+    pub fn new(platform: Platform) -> Self {
+        Self {
+            platform,
+            defaults: Vec::new(),
+            user: Vec::new(),
+            bindings: Vec::new(),
+            pending: Vec::new(),
+        }
+    }
+
+    /// Replaces the default bindings
+    ///
+    /// Removal entries (`-command`) are not allowed in defaults.
+    pub fn load_defaults(&mut self, json: &str) -> Result<()> {
+        let mut defaults = Vec::new();
+        for rule in parse_rules(json, BindingSource::Default, self.platform)? {
+            match rule {
+                KeybindingRule::Add(binding) => defaults.push(binding),
+                KeybindingRule::Remove { command, .. } => bail!("default keybindings cannot remove -{}", command),
+            }
+        }
+
+        self.defaults = defaults;
+        self.rebuild();
+        Ok(())
+    }
+
+    /// Replaces the user bindings (layered over the defaults)
+    pub fn load_user(&mut self, json: &str) -> Result<()> {
+        self.user = parse_rules(json, BindingSource::User, self.platform)?;
+        self.rebuild();
+        Ok(())
+    }
+
+    /// Gets the effective bindings, lowest priority first
+    pub fn bindings(&self) -> &[Keybinding] {
+        &self.bindings
+    }
+
+    /// Gets the keys of the chord in progress
+    pub fn pending_keys(&self) -> &[KeyPress] {
+        &self.pending
+    }
+
+    /// Abandons the chord in progress (e.g. after a timeout or focus loss)
+    pub fn cancel_chord(&mut self) {
+        self.pending.clear();
+    }
+
+    /// Resolves a key press
+    ///
+    /// Parameters:
+    /// - `key`: Pressed key
+    /// - `context`: Current context keys (see `KeyContext::for_editor`)
+    pub fn resolve(&mut self, key: KeyPress, context: &KeyContext) -> KeyResolution {
+        let was_pending = !self.pending.is_empty();
+        let mut sequence = std::mem::take(&mut self.pending);
+        sequence.push(key);
+
+        let found = self
+            .bindings
+            .iter()
+            .rev()
+            .find(|binding| binding.keys.starts_with(&sequence) && binding.is_active(context));
+        match found {
+            Some(binding) if binding.keys.len() == sequence.len() => KeyResolution::Command {
+                command: binding.command.clone(),
+                args: binding.args.clone(),
+            },
+            Some(_) => {
+                let keys = format_sequence(&sequence);
+                self.pending = sequence;
+                KeyResolution::Pending { keys }
+            }
+            None if was_pending => KeyResolution::Cancelled {
+                keys: format_sequence(&sequence),
+            },
+            None => KeyResolution::NoMatch,
+        }
+    }
+
+    /// Finds bindings that can never run
+    ///
+    /// Only bindings with the same `when` clause are compared (different
+    /// clauses may never hold together).
+    pub fn conflicts(&self) -> Vec<KeyConflict> {
+        // Bindings by `when` clause and first key, lowest priority first
+        let mut groups: BTreeMap<(Option<String>, &KeyPress), Vec<&Keybinding>> = BTreeMap::new();
+        for binding in &self.bindings {
+            groups.entry((binding.when_text(), &binding.keys[0])).or_default().push(binding);
+        }
+
+        let mut conflicts = Vec::new();
+        for ((when, _), group) in groups {
+            for (index, lower) in group.iter().enumerate() {
+                // The highest-priority binding that hides `lower`
+                let winner = group[index + 1..].iter().rev().find(|higher| {
+                    let same = higher.keys == lower.keys;
+                    (same && (higher.command != lower.command || higher.args != lower.args))
+                        || (!same && (lower.keys.starts_with(&higher.keys) || higher.keys.starts_with(&lower.keys)))
+                });
+                let Some(winner) = winner else {
+                    continue;
+                };
+
+                conflicts.push(KeyConflict {
+                    kind: if winner.keys == lower.keys {
+                        ConflictKind::Duplicate
+                    } else {
+                        ConflictKind::ChordPrefix
+                    },
+                    keys: format_sequence(&lower.keys),
+                    when: when.clone(),
+                    winner: winner.command.clone(),
+                    shadowed: lower.command.clone(),
+                });
+            }
+        }
+        conflicts
+    }
+
+    /// Helper: Layers the user rules over the defaults
+    fn rebuild(&mut self) {
+        let mut bindings = self.defaults.clone();
+        for rule in &self.user {
+            match rule {
+                KeybindingRule::Add(binding) => bindings.push(binding.clone()),
+                KeybindingRule::Remove { command, keys, when } => bindings.retain(|binding| {
+                    binding.command != *command
+                        || keys.as_ref().is_some_and(|keys| *keys != binding.keys)
+                        || when.as_ref().is_some_and(|when| Some(when) != binding.when_text().as_ref())
+                }),
+            }
+        }
+
+        self.bindings = bindings;
+        self.pending.clear();
+    }
+}
+
+/// Parses a key press from its text form (for hosts)
+pub fn parse_key(text: &str) -> Result<KeyPress> {
+    text.trim().parse().map_err(|e: anyhow::Error| anyhow!("invalid key {:?}: {}", text, e))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::editor::{Editor, LanguageId, Position, Selection};
+
+    const DEFAULTS: &str = r#"[
+        // Comments and trailing commas are allowed
+        { "key": "ctrl+k ctrl+c", "command": "comment", "when": "editorTextFocus && !editorReadonly" },
+        { "key": "ctrl+k ctrl+u", "command": "uncomment" },
+        { "key": "ctrl+d", "mac": "cmd+d", "command": "selectNext", "args": { "wrap": true } },
+        { "key": "tab", "command": "indent", "when": "editorHasSelection" },
+        { "key": "tab", "command": "snippetNext", "when": "inSnippet" },
+        { "key": "ctrl+/", "command": "toggleComment", "when": "editorLangId == rust" },
+    ]"#;
+
+    fn key(text: &str) -> KeyPress {
+        parse_key(text).unwrap()
+    }
+
+    fn keymap() -> Keymap {
+        let mut keymap = Keymap::new(Platform::Linux);
+        keymap.load_defaults(DEFAULTS).unwrap();
+        keymap
+    }
+
+    fn command(resolution: KeyResolution) -> Option<String> {
+        match resolution {
+            KeyResolution::Command { command, .. } => Some(command),
+            _ => None,
+        }
+    }
+
+    #[test]
+    fn test_parse_keys() {
+        assert_eq!(key("Shift+Ctrl+K").to_string(), "ctrl+shift+k");
+        assert_eq!(key("cmd+alt+Up").to_string(), "alt+meta+up");
+        assert_eq!(key("alt+ArrowUp"), key("alt+up"));
+        assert_eq!(key("ctrl++").to_string(), "ctrl++");
+        assert_eq!(key("esc"), key("Escape"));
+        assert!(parse_key("hyper+k").is_err());
+        assert!(parse_key("ctrl+").is_err());
+
+        assert_eq!(strip_jsonc("[1, /* x */ \"a//b\", // c\n 2,\n]"), "[1,  \"a//b\", \n 2]");
+    }
+
+    #[test]
+    fn test_resolve_chords_and_when() {
+        let mut keymap = keymap();
+        let mut context = KeyContext::new();
+        context.set("editorTextFocus", true);
+
+        assert_eq!(keymap.resolve(key("ctrl+k"), &context), KeyResolution::Pending { keys: "ctrl+k".into() });
+        assert_eq!(command(keymap.resolve(key("ctrl+c"), &context)).as_deref(), Some("comment"));
+        assert!(keymap.pending_keys().is_empty());
+
+        keymap.resolve(key("ctrl+k"), &context);
+        assert_eq!(keymap.resolve(key("x"), &context), KeyResolution::Cancelled { keys: "ctrl+k x".into() });
+        assert_eq!(keymap.resolve(key("x"), &context), KeyResolution::NoMatch);
+
+        // The when clause fails: the other chord still applies
+        context.set("editorReadonly", true);
+        keymap.resolve(key("ctrl+k"), &context);
+        assert_eq!(keymap.resolve(key("ctrl+c"), &context), KeyResolution::Cancelled { keys: "ctrl+k ctrl+c".into() });
+
+        let resolution = keymap.resolve(key("ctrl+d"), &context);
+        assert_eq!(
+            resolution,
+            KeyResolution::Command {
+                command: "selectNext".into(),
+                args: Some(serde_json::json!({ "wrap": true })),
+            }
+        );
+        assert_eq!(keymap.resolve(key("tab"), &context), KeyResolution::NoMatch);
+    }
+
+    #[test]
+    fn test_resolve_host_key_names() {
+        let mut keymap = Keymap::new(Platform::Linux);
+        keymap.load_defaults(r#"[{ "key": "alt+up", "command": "moveLinesUp" }]"#).unwrap();
+
+        // A host reports the key as Flutter/DOM `ArrowUp`
+        let modifiers = Modifiers { alt: true, ..Default::default() };
+        let resolution = keymap.resolve(KeyPress::new(modifiers, "ArrowUp"), &KeyContext::new());
+        assert_eq!(command(resolution).as_deref(), Some("moveLinesUp"));
+    }
+
+    #[test]
+    fn test_editor_context() {
+        let mut keymap = keymap();
+        let mut editor = Editor::with_content("fn main() {}\n", LanguageId::Rust).unwrap();
+        editor.set_selection(Selection::new(Position::new(0, 0), Position::new(0, 2)));
+
+        let context = KeyContext::for_editor(&editor);
+        assert_eq!(command(keymap.resolve(key("tab"), &context)).as_deref(), Some("indent"));
+        assert_eq!(command(keymap.resolve(key("ctrl+/"), &context)).as_deref(), Some("toggleComment"));
+
+        editor.clear_selection();
+        editor.set_language(LanguageId::Python).unwrap();
+        let mut context = KeyContext::for_editor(&editor);
+        context.set("inSnippet", true);
+        assert_eq!(command(keymap.resolve(key("tab"), &context)).as_deref(), Some("snippetNext"));
+        assert_eq!(keymap.resolve(key("ctrl+/"), &context), KeyResolution::NoMatch);
+    }
+
+    #[test]
+    fn test_user_overrides() {
+        let mut keymap = Keymap::new(Platform::Mac);
+        keymap.load_defaults(DEFAULTS).unwrap();
+        let context = KeyContext::new();
+        assert_eq!(command(keymap.resolve(key("cmd+d"), &context)).as_deref(), Some("selectNext"));
+
+        keymap
+            .load_user(
+                r#"[
+                    { "key": "cmd+d", "command": "duplicateLine" },
+                    { "command": "-uncomment" },
+                    { "key": "ctrl+k", "command": "killLine" },
+                ]"#,
+            )
+            .unwrap();
+        assert_eq!(command(keymap.resolve(key("cmd+d"), &context)).as_deref(), Some("duplicateLine"));
+
+        // A user binding on the chord's first key wins over the chord
+        assert_eq!(command(keymap.resolve(key("ctrl+k"), &context)).as_deref(), Some("killLine"));
+        assert!(!keymap.bindings().iter().any(|binding| binding.command == "uncomment"));
+        assert_eq!(keymap.bindings().last().unwrap().source, BindingSource::User);
+
+        assert!(keymap.load_user(r#"[{ "key": "ctrl+k", "command": "x", "when": "a &&" }]"#).is_err());
+        assert!(keymap.load_defaults(r#"[{ "command": "-x" }]"#).is_err());
+    }
+
+    #[test]
+    fn test_conflicts() {
+        let mut keymap = keymap();
+        keymap
+            .load_user(
+                r#"[
+                    { "key": "ctrl+d", "command": "duplicateLine" },
+                    { "key": "ctrl+k", "command": "killLine" },
+                    { "key": "tab", "command": "indentMore", "when": "editorHasSelection" },
+                ]"#,
+            )
+            .unwrap();
+
+        let conflicts: Vec<(ConflictKind, String, String, String)> = keymap
+            .conflicts()
+            .into_iter()
+            .map(|c| (c.kind, c.keys, c.winner, c.shadowed))
+            .collect();
+        assert_eq!(
+            conflicts,
+            vec![
+                (ConflictKind::Duplicate, "ctrl+d".into(), "duplicateLine".into(), "selectNext".into()),
+                (ConflictKind::ChordPrefix, "ctrl+k ctrl+u".into(), "killLine".into(), "uncomment".into()),
+                (ConflictKind::Duplicate, "tab".into(), "indentMore".into(), "indent".into()),
+            ]
+        );
+    }
+}
@@ -0,0 +1,461 @@
+//! `when` clauses of keybindings
+//!
+//! VS Code-compatible context expressions such as
+//! `editorTextFocus && !editorReadonly || editorLangId == rust`. Keys are
+//! looked up in a `KeyContext`; a missing key is false (and equal to
+//! nothing). Operators, loosest first: `||`, `&&`, `!`, then comparisons
+//! (`==`, `!=`, `<`, `<=`, `>`, `>=`, `in`, `not in`). Regular
+//! expression matching (`=~`) is not supported.
+
+use std::collections::BTreeMap;
+use std::fmt;
+
+use anyhow::{anyhow, bail, Result};
+use serde::{Deserialize, Serialize};
+
+use crate::editor::Editor;
+
+/// Value of a context key.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(untagged)]
+pub enum ContextValue {
+    Bool(bool),
+    Number(f64),
+    String(String),
+    List(Vec<String>),
+}
+
+impl ContextValue {
+    /// Truthiness (`when: "key"`)
+    fn is_truthy(&self) -> bool {
+        match self {
+            ContextValue::Bool(value) => *value,
+            ContextValue::Number(value) => *value != 0.0,
+            ContextValue::String(value) => !value.is_empty(),
+            ContextValue::List(_) => true,
+        }
+    }
+
+    /// Text compared by `==` and `!=` (numbers without a trailing `.0`)
+    fn as_text(&self) -> Option<String> {
+        match self {
+            ContextValue::Bool(value) => Some(value.to_string()),
+            ContextValue::Number(value) => Some(value.to_string()),
+            ContextValue::String(value) => Some(value.clone()),
+            ContextValue::List(_) => None,
+        }
+    }
+}
+
+impl From<bool> for ContextValue {
+    fn from(value: bool) -> Self {
+        ContextValue::Bool(value)
+    }
+}
+
+impl From<f64> for ContextValue {
+    fn from(value: f64) -> Self {
+        ContextValue::Number(value)
+    }
+}
+
+impl From<&str> for ContextValue {
+    fn from(value: &str) -> Self {
+        ContextValue::String(value.to_string())
+    }
+}
+
+impl From<String> for ContextValue {
+    fn from(value: String) -> Self {
+        ContextValue::String(value)
+    }
+}
+
+impl From<Vec<String>> for ContextValue {
+    fn from(value: Vec<String>) -> Self {
+        ContextValue::List(value)
+    }
+}
+
+/// Context keys `when` clauses are evaluated against.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(transparent)]
+pub struct KeyContext {
+    values: BTreeMap<String, ContextValue>,
+}
+
+impl KeyContext {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Gets the context of an editor
+    ///
+    /// Sets `editorHasSelection`, `editorLangId`, `editorReadonly`,
+    /// `activeEditorIsDirty` and `editorIsRecordingMacro`. UI state (focus,
+    /// modes) is added by the host with `set` or `merge`.
+    pub fn for_editor(editor: &Editor) -> Self {
+        let mut context = Self::new();
+        let has_selection = editor.selection().is_some_and(|selection| !selection.is_empty());
+        context.set("editorHasSelection", has_selection);
+        context.set("editorLangId", editor.language().name());
+        context.set("editorReadonly", editor.is_read_only());
+        context.set("activeEditorIsDirty", editor.is_dirty());
+        context.set("editorIsRecordingMacro", editor.is_recording_macro());
+        context
+    }
+
+    pub fn set(&mut self, key: &str, value: impl Into<ContextValue>) {
+        self.values.insert(key.to_string(), value.into());
+    }
+
+    pub fn remove(&mut self, key: &str) {
+        self.values.remove(key);
+    }
+
+    pub fn get(&self, key: &str) -> Option<&ContextValue> {
+        self.values.get(key)
+    }
+
+    /// Sets every key of another context (its values win)
+    pub fn merge(&mut self, other: KeyContext) {
+        self.values.extend(other.values);
+    }
+}
+
+/// Comparison operator.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Comparison {
+    Equal,
+    NotEqual,
+    Less,
+    LessOrEqual,
+    Greater,
+    GreaterOrEqual,
+}
+
+impl Comparison {
+    pub fn symbol(&self) -> &'static str {
+        match self {
+            Comparison::Equal => "==",
+            Comparison::NotEqual => "!=",
+            Comparison::Less => "<",
+            Comparison::LessOrEqual => "<=",
+            Comparison::Greater => ">",
+            Comparison::GreaterOrEqual => ">=",
+        }
+    }
+}
+
+/// Parsed `when` clause.
+#[derive(Debug, Clone, PartialEq)]
+pub enum WhenClause {
+    Bool(bool),
+
+    /// Key is truthy
+    Key(String),
+
+    Not(Box<WhenClause>),
+    And(Vec<WhenClause>),
+    Or(Vec<WhenClause>),
+
+    /// Key compared with a literal
+    Compare(String, Comparison, String),
+
+    /// Key's value is an element of the list under another key (`in`;
+    /// negated for `not in`)
+    In {
+        key: String,
+        collection: String,
+        negated: bool,
+    },
+}
+
+impl WhenClause {
+    /// Parses a clause
+    pub fn parse(source: &str) -> Result<Self> {
+        let tokens = tokenize(source)?;
+        let mut parser = Parser { tokens, position: 0 };
+        let clause = parser.or()?;
+        match parser.tokens.get(parser.position) {
+            None => Ok(clause),
+            Some(token) => bail!("unexpected {:?} in when clause {:?}", token, source),
+        }
+    }
+
+    /// Evaluates the clause
+    pub fn evaluate(&self, context: &KeyContext) -> bool {
+        match self {
+            WhenClause::Bool(value) => *value,
+            WhenClause::Key(key) => context.get(key).is_some_and(ContextValue::is_truthy),
+            WhenClause::Not(clause) => !clause.evaluate(context),
+            WhenClause::And(clauses) => clauses.iter().all(|clause| clause.evaluate(context)),
+            WhenClause::Or(clauses) => clauses.iter().any(|clause| clause.evaluate(context)),
+            WhenClause::Compare(key, comparison, literal) => {
+                let value = context.get(key);
+                match comparison {
+                    Comparison::Equal => value.and_then(ContextValue::as_text).as_deref() == Some(literal),
+                    Comparison::NotEqual => value.and_then(ContextValue::as_text).as_deref() != Some(literal),
+                    _ => {
+                        let number = match value {
+                            Some(ContextValue::Number(number)) => *number,
+                            Some(ContextValue::String(text)) => match text.parse() {
+                                Ok(number) => number,
+                                Err(_) => return false,
+                            },
+                            _ => return false,
+                        };
+                        let Ok(literal) = literal.parse::<f64>() else {
+                            return false;
+                        };
+                        match comparison {
+                            Comparison::Less => number < literal,
+                            Comparison::LessOrEqual => number <= literal,
+                            Comparison::Greater => number > literal,
+                            _ => number >= literal,
+                        }
+                    }
+                }
+            }
+            WhenClause::In { key, collection, negated } => {
+                let value = context.get(key).and_then(ContextValue::as_text);
+                let found = match (value, context.get(collection)) {
+                    (Some(value), Some(ContextValue::List(items))) => items.contains(&value),
+                    _ => false,
+                };
+                found != *negated
+            }
+        }
+    }
+}
+
+impl fmt::Display for WhenClause {
+    /// Canonical form (equal for clauses that differ only in spacing)
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let join = |f: &mut fmt::Formatter<'_>, clauses: &[WhenClause], separator: &str| {
+            for (index, clause) in clauses.iter().enumerate() {
+                if index > 0 {
+                    write!(f, " {} ", separator)?;
+                }
+                match clause {
+                    WhenClause::Or(_) | WhenClause::And(_) => write!(f, "({})", clause)?,
+                    _ => write!(f, "{}", clause)?,
+                }
+            }
+            Ok(())
+        };
+
+        match self {
+            WhenClause::Bool(value) => write!(f, "{}", value),
+            WhenClause::Key(key) => write!(f, "{}", key),
+            WhenClause::Not(clause) => match **clause {
+                WhenClause::Bool(_) | WhenClause::Key(_) => write!(f, "!{}", clause),
+                _ => write!(f, "!({})", clause),
+            },
+            WhenClause::And(clauses) => join(f, clauses, "&&"),
+            WhenClause::Or(clauses) => join(f, clauses, "||"),
+            WhenClause::Compare(key, comparison, literal) => {
+                write!(f, "{} {} '{}'", key, comparison.symbol(), literal)
+            }
+            WhenClause::In { key, collection, negated } => {
+                write!(f, "{} {}in {}", key, if *negated { "not " } else { "" }, collection)
+            }
+        }
+    }
+}
+
+/// Token of a `when` clause.
+#[derive(Debug, Clone, PartialEq)]
+enum Token {
+    Word(String),
+
+    /// Quoted string (never a keyword)
+    Quoted(String),
+
+    Not,
+    And,
+    Or,
+    Open,
+    Close,
+    Compare(Comparison),
+}
+
+/// Helper: Splits a clause into tokens
+fn tokenize(source: &str) -> Result<Vec<Token>> {
+    let mut tokens = Vec::new();
+    let mut chars = source.chars().peekable();
+    while let Some(ch) = chars.next() {
+        let token = match ch {
+            ' ' | '\t' | '\n' | '\r' => continue,
+            '(' => Token::Open,
+            ')' => Token::Close,
+            '&' if chars.next_if_eq(&'&').is_some() => Token::And,
+            '|' if chars.next_if_eq(&'|').is_some() => Token::Or,
+            '=' if chars.next_if_eq(&'=').is_some() => {
+                // `===` is accepted like in VS Code
+                chars.next_if_eq(&'=');
+                Token::Compare(Comparison::Equal)
+            }
+            '=' if chars.peek() == Some(&'~') => bail!("regular expressions (=~) are not supported"),
+            '!' if chars.next_if_eq(&'=').is_some() => {
+                chars.next_if_eq(&'=');
+                Token::Compare(Comparison::NotEqual)
+            }
+            '!' => Token::Not,
+            '<' if chars.next_if_eq(&'=').is_some() => Token::Compare(Comparison::LessOrEqual),
+            '<' => Token::Compare(Comparison::Less),
+            '>' if chars.next_if_eq(&'=').is_some() => Token::Compare(Comparison::GreaterOrEqual),
+            '>' => Token::Compare(Comparison::Greater),
+            '\'' | '"' => {
+                let mut text = String::new();
+                loop {
+                    match chars.next() {
+                        Some(c) if c == ch => break,
+                        Some(c) => text.push(c),
+                        None => bail!("unterminated string in when clause {:?}", source),
+                    }
+                }
+                Token::Quoted(text)
+            }
+            c if is_word_char(c) => {
+                let mut word = c.to_string();
+                while let Some(c) = chars.next_if(|c| is_word_char(*c)) {
+                    word.push(c);
+                }
+                Token::Word(word)
+            }
+            c => bail!("unexpected {:?} in when clause {:?}", c, source),
+        };
+        tokens.push(token);
+    }
+    Ok(tokens)
+}
+
+fn is_word_char(c: char) -> bool {
+    c.is_alphanumeric() || matches!(c, '_' | '.' | ':' | '-' | '/' | '#' | '@' | '$')
+}
+
+/// Recursive descent parser over tokens.
+struct Parser {
+    tokens: Vec<Token>,
+    position: usize,
+}
+
+impl Parser {
+    fn peek(&self) -> Option<&Token> {
+        self.tokens.get(self.position)
+    }
+
+    fn next(&mut self) -> Option<Token> {
+        let token = self.tokens.get(self.position).cloned();
+        self.position += 1;
+        token
+    }
+
+    fn or(&mut self) -> Result<WhenClause> {
+        let mut clauses = vec![self.and()?];
+        while self.peek() == Some(&Token::Or) {
+            self.position += 1;
+            clauses.push(self.and()?);
+        }
+        Ok(if clauses.len() == 1 { clauses.remove(0) } else { WhenClause::Or(clauses) })
+    }
+
+    fn and(&mut self) -> Result<WhenClause> {
+        let mut clauses = vec![self.unary()?];
+        while self.peek() == Some(&Token::And) {
+            self.position += 1;
+            clauses.push(self.unary()?);
+        }
+        Ok(if clauses.len() == 1 { clauses.remove(0) } else { WhenClause::And(clauses) })
+    }
+
+    fn unary(&mut self) -> Result<WhenClause> {
+        match self.next() {
+            Some(Token::Not) => Ok(WhenClause::Not(Box::new(self.unary()?))),
+            Some(Token::Open) => {
+                let clause = self.or()?;
+                match self.next() {
+                    Some(Token::Close) => Ok(clause),
+                    _ => bail!("missing ) in when clause"),
+                }
+            }
+            Some(Token::Word(word)) => self.comparison(word),
+            token => Err(anyhow!("expected a context key, got {:?}", token)),
+        }
+    }
+
+    /// Parses what follows a key (a comparison, `in`, or nothing)
+    fn comparison(&mut self, key: String) -> Result<WhenClause> {
+        match self.peek() {
+            Some(Token::Compare(comparison)) => {
+                let comparison = *comparison;
+                self.position += 1;
+                let literal = match self.next() {
+                    Some(Token::Word(word) | Token::Quoted(word)) => word,
+                    token => bail!("expected a value after {}, got {:?}", comparison.symbol(), token),
+                };
+                Ok(WhenClause::Compare(key, comparison, literal))
+            }
+            Some(Token::Word(word)) if word == "in" || word == "not" => {
+                let negated = word == "not";
+                self.position += 1;
+                if negated && self.next() != Some(Token::Word("in".to_string())) {
+                    bail!("expected `in` after `not`");
+                }
+                match self.next() {
+                    Some(Token::Word(collection)) => Ok(WhenClause::In { key, collection, negated }),
+                    token => bail!("expected a context key after `in`, got {:?}", token),
+                }
+            }
+            _ => Ok(match key.as_str() {
+                "true" => WhenClause::Bool(true),
+                "false" => WhenClause::Bool(false),
+                _ => WhenClause::Key(key),
+            }),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn context() -> KeyContext {
+        let mut context = KeyContext::new();
+        context.set("editorTextFocus", true);
+        context.set("editorLangId", "rust");
+        context.set("mode", "normal");
+        context.set("count", 3.0);
+        context.set("supportedLangs", vec!["rust".to_string(), "go".to_string()]);
+        context
+    }
+
+    fn eval(source: &str) -> bool {
+        WhenClause::parse(source).unwrap().evaluate(&context())
+    }
+
+    #[test]
+    fn test_evaluate() {
+        assert!(eval("editorTextFocus && !editorReadonly"));
+        assert!(eval("editorLangId == rust && mode == 'normal'"));
+        assert!(eval("editorLangId != python"));
+        assert!(!eval("missing == ''"));
+        assert!(eval("editorReadonly || editorLangId == rust && editorTextFocus"));
+        assert!(!eval("!(editorTextFocus && mode == normal)"));
+        assert!(eval("count >= 3 && count < 4"));
+        assert!(eval("editorLangId in supportedLangs && mode not in supportedLangs"));
+        assert!(eval("editorTextFocus == true && true"));
+    }
+
+    #[test]
+    fn test_parse_errors_and_display() {
+        for source in ["", "a &&", "(a", "a == ", "a =~ /x/", "a & b", "a not b"] {
+            assert!(WhenClause::parse(source).is_err(), "{source}");
+        }
+
+        let clause = WhenClause::parse("a||b  &&  !c||x=='y z'").unwrap();
+        assert_eq!(clause.to_string(), "a || (b && !c) || x == 'y z'");
+        assert_eq!(WhenClause::parse(&clause.to_string()).unwrap(), clause);
+    }
+}
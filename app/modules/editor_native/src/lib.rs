@@ -10,7 +10,10 @@
 
 pub mod editor;
 pub mod renderer;
+pub mod workspace;
+pub mod keymap;
 pub mod ffi;
 
 pub use editor::Editor;
 pub use renderer::TextRenderer;
+pub use workspace::Workspace;
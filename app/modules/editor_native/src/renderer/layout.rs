@@ -0,0 +1,520 @@
+use cosmic_text::{fontdb, Attrs, AttrsList, Family, FontSystem, LayoutLine, ShapeLine, Shaping, Wrap};
+
+/// Soft wrap mode.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum WrapMode {
+    /// Lines never wrap
+    None,
+
+    /// Wrap at word boundaries (words wider than the line break at
+    /// grapheme boundaries)
+    Word,
+
+    /// Wrap at any grapheme boundary
+    Grapheme,
+}
+
+/// Text layout settings.
+#[derive(Debug, Clone, PartialEq)]
+pub struct LayoutConfig {
+    /// Font family name (None = system monospace)
+    pub font_family: Option<String>,
+
+    /// Font size in logical pixels
+    pub font_size: f32,
+
+    /// Height of each visual line in logical pixels
+    pub line_height: f32,
+
+    /// Wrap width in logical pixels (None = no wrapping)
+    pub wrap_width: Option<f32>,
+
+    pub wrap_mode: WrapMode,
+
+    /// Columns per tab stop
+    pub tab_size: usize,
+
+    /// Extra indentation of continuation lines, in columns, on top of
+    /// the line's own leading whitespace
+    pub hanging_indent: usize,
+}
+
+impl Default for LayoutConfig {
+    fn default() -> Self {
+        Self {
+            font_family: None,
+            font_size: 14.0,
+            line_height: 20.0,
+            wrap_width: None,
+            wrap_mode: WrapMode::Word,
+            tab_size: 4,
+            hanging_indent: 0,
+        }
+    }
+}
+
+/// Laid out logical line (one or more visual rows).
+#[derive(Debug, Clone)]
+pub struct LineLayout {
+    /// Line text without the line break
+    pub text: String,
+
+    /// Visual rows in order (at least one)
+    pub rows: Vec<VisualRow>,
+}
+
+/// One visual row of a wrapped line.
+#[derive(Debug, Clone)]
+pub struct VisualRow {
+    /// First byte of the row in the line text
+    pub start_byte: usize,
+
+    /// End byte of the row (start of the next row, or the line length)
+    pub end_byte: usize,
+
+    /// Left offset of the row (hanging indent of continuation rows)
+    pub indent: f32,
+
+    /// Right edge of the row content
+    pub width: f32,
+
+    pub ascent: f32,
+    pub descent: f32,
+
+    /// Glyphs in visual (left to right) order
+    pub glyphs: Vec<LaidOutGlyph>,
+}
+
+/// Positioned glyph (or glyph cluster).
+///
+/// Ligatures cover several characters; tabs are one glyph as wide as
+/// their expansion.
+#[derive(Debug, Clone, PartialEq)]
+pub struct LaidOutGlyph {
+    /// Byte range of the cluster in the line text
+    pub start_byte: usize,
+    pub end_byte: usize,
+
+    /// Left edge and advance in logical pixels
+    pub x: f32,
+    pub width: f32,
+
+    pub glyph_id: u16,
+
+    /// Font the glyph was shaped with (fallback fonts included)
+    pub font_id: fontdb::ID,
+    pub font_size: f32,
+
+    /// Drawing offsets from the pen position (pixels)
+    pub x_offset: f32,
+    pub y_offset: f32,
+
+    /// Right-to-left run
+    pub rtl: bool,
+}
+
+impl LineLayout {
+    /// Gets the row containing a byte offset.
+    ///
+    /// An offset at a wrap point belongs to the following row.
+    pub fn row_for_byte(&self, byte: usize) -> usize {
+        self.rows
+            .iter()
+            .rposition(|row| row.start_byte <= byte)
+            .unwrap_or(0)
+    }
+
+    /// Gets the x coordinate of the caret before a byte offset.
+    ///
+    /// Returns: (row index, x)
+    pub fn caret_x(&self, byte: usize) -> (usize, f32) {
+        let byte = floor_char_boundary(&self.text, byte.min(self.text.len()));
+        let row_index = self.row_for_byte(byte);
+        let row = &self.rows[row_index];
+
+        for glyph in &row.glyphs {
+            if glyph.start_byte <= byte && byte < glyph.end_byte {
+                let cluster = &self.text[glyph.start_byte..glyph.end_byte];
+                let total = cluster.chars().count().max(1) as f32;
+                let before = self.text[glyph.start_byte..byte].chars().count() as f32;
+                let fraction = before / total;
+                let x = if glyph.rtl {
+                    glyph.x + glyph.width * (1.0 - fraction)
+                } else {
+                    glyph.x + glyph.width * fraction
+                };
+                return (row_index, x);
+            }
+        }
+
+        // End of the row: after the logically last glyph
+        let x = match row.glyphs.iter().max_by_key(|g| g.end_byte) {
+            Some(last) if last.rtl => last.x,
+            Some(last) => last.x + last.width,
+            None => row.indent,
+        };
+        (row_index, x)
+    }
+
+    /// Gets the byte offset closest to an x coordinate within a row.
+    pub fn byte_at_x(&self, row_index: usize, x: f32) -> usize {
+        let Some(row) = self.rows.get(row_index) else {
+            return self.text.len();
+        };
+        let is_last_row = row_index + 1 == self.rows.len();
+
+        for glyph in &row.glyphs {
+            if x < glyph.x + glyph.width {
+                let cluster = &self.text[glyph.start_byte..glyph.end_byte];
+                let total = cluster.chars().count();
+                let fraction = ((x - glyph.x) / glyph.width.max(f32::EPSILON)).clamp(0.0, 1.0);
+                let mut chars = (fraction * total as f32).round() as usize;
+                if glyph.rtl {
+                    chars = total - chars;
+                }
+                let offset = cluster.char_indices().nth(chars).map_or(cluster.len(), |(i, _)| i);
+                let byte = glyph.start_byte + offset;
+
+                // The wrap point itself is shown on the next row
+                if !is_last_row && byte >= row.end_byte {
+                    return glyph.start_byte;
+                }
+                return byte;
+            }
+        }
+
+        // Past the content; whitespace at a wrap point has no glyph
+        match row.glyphs.iter().max_by_key(|g| g.end_byte) {
+            Some(last) if !is_last_row && last.end_byte < row.end_byte => last.end_byte,
+            Some(last) if !is_last_row => last.start_byte,
+            Some(last) if last.rtl => last.start_byte,
+            _ => row.end_byte,
+        }
+    }
+}
+
+/// Lays out one logical line.
+///
+/// Tabs are expanded to the next tab stop before shaping, so wrapping
+/// and hit testing see their real width. Shaping uses the full
+/// HarfBuzz-compatible pipeline (bidi, ligatures, font fallback).
+///
+/// Parameters:
+/// - `font_system`: Font database
+/// - `config`: Layout settings
+/// - `space_width`: Advance of a space (used for the hanging indent)
+/// - `text`: Line text without the line break
+///
+/// Returns: Layout with one or more rows
+pub fn layout_line(
+    font_system: &mut FontSystem,
+    config: &LayoutConfig,
+    space_width: f32,
+    text: &str,
+) -> LineLayout {
+    let (expanded, byte_map) = expand_tabs(text, config.tab_size);
+
+    let empty_row = |start: usize| VisualRow {
+        start_byte: start,
+        end_byte: text.len(),
+        indent: 0.0,
+        width: 0.0,
+        ascent: config.font_size * 0.8,
+        descent: config.font_size * 0.2,
+        glyphs: Vec::new(),
+    };
+    if expanded.is_empty() {
+        return LineLayout { text: text.to_string(), rows: vec![empty_row(0)] };
+    }
+
+    let (width, wrap) = match (config.wrap_width, config.wrap_mode) {
+        (Some(width), WrapMode::Word) if width > 0.0 => (width, Wrap::Word),
+        (Some(width), WrapMode::Grapheme) if width > 0.0 => (width, Wrap::Glyph),
+        _ => (f32::MAX, Wrap::None),
+    };
+
+    let lines = shape_and_wrap(font_system, config, &expanded, width, wrap);
+
+    // Continuation rows are laid out again, narrower, after the indent
+    let leading = expanded.len() - expanded.trim_start_matches(' ').len();
+    let indent = (leading + config.hanging_indent) as f32 * space_width;
+    let mut segments: Vec<(usize, f32, LayoutLine)> = Vec::new();
+
+    let split = lines.get(1).and_then(|line| line.glyphs.iter().map(|g| g.start).min());
+    match split {
+        Some(split) if indent > 0.0 && indent < width / 2.0 => {
+            let mut lines = lines.into_iter();
+            if let Some(first) = lines.next() {
+                segments.push((0, 0.0, first));
+            }
+            let rest = shape_and_wrap(font_system, config, &expanded[split..], width - indent, wrap);
+            segments.extend(rest.into_iter().map(|line| (split, indent, line)));
+        }
+        _ => segments.extend(lines.into_iter().map(|line| (0, 0.0, line))),
+    }
+
+    let mut rows: Vec<VisualRow> = segments
+        .into_iter()
+        .map(|(offset, indent, line)| {
+            let mut glyphs: Vec<LaidOutGlyph> = Vec::with_capacity(line.glyphs.len());
+            for glyph in &line.glyphs {
+                let start = byte_map[offset + glyph.start];
+                let end = byte_map[offset + glyph.end];
+
+                // Merge the spaces of an expanded tab into one glyph
+                if let Some(previous) = glyphs.last_mut() {
+                    if previous.start_byte == start {
+                        previous.end_byte = previous.end_byte.max(end);
+                        previous.width += glyph.w;
+                        continue;
+                    }
+                }
+
+                glyphs.push(LaidOutGlyph {
+                    start_byte: start,
+                    end_byte: end,
+                    x: indent + glyph.x,
+                    width: glyph.w,
+                    glyph_id: glyph.glyph_id,
+                    font_id: glyph.font_id,
+                    font_size: glyph.font_size,
+                    x_offset: glyph.font_size * glyph.x_offset,
+                    y_offset: glyph.font_size * glyph.y_offset,
+                    rtl: glyph.level.is_rtl(),
+                });
+            }
+
+            VisualRow {
+                start_byte: glyphs.iter().map(|g| g.start_byte).min().unwrap_or(text.len()),
+                end_byte: text.len(),
+                indent,
+                width: indent + line.w,
+                ascent: line.max_ascent,
+                descent: line.max_descent,
+                glyphs,
+            }
+        })
+        .collect();
+
+    if rows.is_empty() {
+        rows.push(empty_row(0));
+    }
+
+    // Rows tile the line: each ends where the next starts
+    rows[0].start_byte = 0;
+    for i in 1..rows.len() {
+        rows[i - 1].end_byte = rows[i].start_byte;
+    }
+
+    LineLayout { text: text.to_string(), rows }
+}
+
+/// Helper: Shapes text and wraps it at a width.
+///
+/// Word wrapping falls back to grapheme wrapping when a single word is
+/// wider than the line.
+fn shape_and_wrap(
+    font_system: &mut FontSystem,
+    config: &LayoutConfig,
+    text: &str,
+    width: f32,
+    wrap: Wrap,
+) -> Vec<LayoutLine> {
+    let attrs = match &config.font_family {
+        Some(name) => Attrs::new().family(Family::Name(name)),
+        None => Attrs::new().family(Family::Monospace),
+    };
+    let shape = ShapeLine::new(font_system, text, &AttrsList::new(attrs), Shaping::Advanced);
+
+    let lines = shape.layout(config.font_size, width, wrap, None);
+    let overflows = lines.iter().any(|line| line.w > width + 0.5 && line.glyphs.len() > 1);
+    if wrap == Wrap::Word && overflows {
+        return shape.layout(config.font_size, width, Wrap::Glyph, None);
+    }
+    lines
+}
+
+/// Helper: Expands tabs to spaces up to the next tab stop.
+///
+/// Returns: Expanded text and, for each expanded byte (plus the end),
+/// the byte offset in the original text
+fn expand_tabs(text: &str, tab_size: usize) -> (String, Vec<usize>) {
+    let tab_size = tab_size.max(1);
+    let mut expanded = String::with_capacity(text.len());
+    let mut byte_map = Vec::with_capacity(text.len() + 1);
+    let mut column = 0;
+
+    for (offset, c) in text.char_indices() {
+        if c == '\t' {
+            let spaces = tab_size - column % tab_size;
+            expanded.extend(std::iter::repeat_n(' ', spaces));
+            byte_map.extend(std::iter::repeat_n(offset, spaces));
+            column += spaces;
+        } else {
+            expanded.push(c);
+            byte_map.extend(std::iter::repeat_n(offset, c.len_utf8()));
+            column += 1;
+        }
+    }
+    byte_map.push(text.len());
+
+    (expanded, byte_map)
+}
+
+/// Helper: Rounds a byte offset down to a char boundary.
+fn floor_char_boundary(text: &str, mut byte: usize) -> usize {
+    while byte > 0 && !text.is_char_boundary(byte) {
+        byte -= 1;
+    }
+    byte
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn font_system() -> FontSystem {
+        FontSystem::new()
+    }
+
+    fn space_width(font_system: &mut FontSystem, config: &LayoutConfig) -> f32 {
+        layout_line(font_system, config, 0.0, " ").rows[0].width
+    }
+
+    #[test]
+    fn test_expand_tabs() {
+        let (expanded, map) = expand_tabs("a\tbc\td", 4);
+        assert_eq!(expanded, "a   bc  d");
+        assert_eq!(map, vec![0, 1, 1, 1, 2, 3, 4, 4, 5, 6]);
+    }
+
+    #[test]
+    fn test_layout_single_row() {
+        let mut fs = font_system();
+        let config = LayoutConfig::default();
+        let layout = layout_line(&mut fs, &config, 0.0, "let x = 1;");
+
+        assert_eq!(layout.rows.len(), 1);
+        assert_eq!(layout.rows[0].glyphs.len(), 10);
+        assert_eq!((layout.rows[0].start_byte, layout.rows[0].end_byte), (0, 10));
+
+        // Monospace: every advance is equal
+        let advance = layout.rows[0].glyphs[0].width;
+        assert!(advance > 0.0);
+        assert!(layout.rows[0].glyphs.iter().all(|g| (g.width - advance).abs() < 0.01));
+    }
+
+    #[test]
+    fn test_tab_expands_to_tab_stop() {
+        let mut fs = font_system();
+        let config = LayoutConfig::default();
+        let space = space_width(&mut fs, &config);
+        let layout = layout_line(&mut fs, &config, space, "a\tb");
+
+        let glyphs = &layout.rows[0].glyphs;
+        assert_eq!(glyphs.len(), 3);
+        assert_eq!((glyphs[1].start_byte, glyphs[1].end_byte), (1, 2));
+        assert!((glyphs[1].width - 3.0 * space).abs() < 0.01);
+        assert!((layout.caret_x(2).1 - 4.0 * space).abs() < 0.01);
+    }
+
+    #[test]
+    fn test_word_wrap_and_hanging_indent() {
+        let mut fs = font_system();
+        let mut config = LayoutConfig::default();
+        let space = space_width(&mut fs, &config);
+        config.wrap_width = Some(space * 12.0);
+        config.hanging_indent = 2;
+
+        let text = "  alpha beta gamma delta";
+        let layout = layout_line(&mut fs, &config, space, text);
+
+        assert!(layout.rows.len() >= 2);
+        assert_eq!(layout.rows[0].indent, 0.0);
+        // Continuation: 2 leading spaces + 2 hanging columns
+        assert!((layout.rows[1].indent - 4.0 * space).abs() < 0.01);
+        assert!(layout.rows.iter().all(|row| row.width <= space * 12.0 + 0.5));
+
+        // Rows tile the line and break at word starts
+        assert_eq!(layout.rows.last().unwrap().end_byte, text.len());
+        for pair in layout.rows.windows(2) {
+            assert_eq!(pair[0].end_byte, pair[1].start_byte);
+            assert_eq!(&text[pair[1].start_byte - 1..pair[1].start_byte], " ");
+        }
+    }
+
+    #[test]
+    fn test_long_word_breaks_at_graphemes() {
+        let mut fs = font_system();
+        let mut config = LayoutConfig::default();
+        let space = space_width(&mut fs, &config);
+        config.wrap_width = Some(space * 5.0);
+
+        let layout = layout_line(&mut fs, &config, space, "abcdefghijkl");
+        assert_eq!(layout.rows.len(), 3);
+        assert_eq!(layout.rows[1].start_byte, 5);
+    }
+
+    #[test]
+    fn test_caret_and_hit_test_round_trip() {
+        let mut fs = font_system();
+        let mut config = LayoutConfig::default();
+        let space = space_width(&mut fs, &config);
+        config.wrap_width = Some(space * 8.0);
+
+        let text = "one two three four";
+        let layout = layout_line(&mut fs, &config, space, text);
+
+        for byte in 0..text.len() {
+            let (row, x) = layout.caret_x(byte);
+            assert_eq!(layout.byte_at_x(row, x + 0.1), byte, "byte {}", byte);
+        }
+
+        // Past the end of the last row
+        let last = layout.rows.len() - 1;
+        assert_eq!(layout.byte_at_x(last, 10_000.0), text.len());
+    }
+
+    #[test]
+    fn test_rtl_run() {
+        let mut fs = font_system();
+        let config = LayoutConfig::default();
+        let text = "ab שלום";
+        let layout = layout_line(&mut fs, &config, 0.0, text);
+
+        let glyphs = &layout.rows[0].glyphs;
+        assert!(glyphs.iter().any(|g| g.rtl));
+        assert!(glyphs.iter().any(|g| !g.rtl));
+
+        // Logical order reversed within the Hebrew run
+        let hebrew_start = text.find('ש').unwrap();
+        let (_, first_x) = layout.caret_x(hebrew_start);
+        let (_, second_x) = layout.caret_x(hebrew_start + 'ש'.len_utf8());
+        assert!(second_x < first_x);
+    }
+
+    #[test]
+    fn test_ligature_cluster_interpolation() {
+        let layout = LineLayout {
+            text: "a->b".to_string(),
+            rows: vec![VisualRow {
+                start_byte: 0,
+                end_byte: 4,
+                indent: 0.0,
+                width: 40.0,
+                ascent: 8.0,
+                descent: 2.0,
+                glyphs: vec![
+                    LaidOutGlyph { start_byte: 0, end_byte: 1, x: 0.0, width: 10.0, glyph_id: 1, font_id: fontdb::ID::dummy(), font_size: 14.0, x_offset: 0.0, y_offset: 0.0, rtl: false },
+                    LaidOutGlyph { start_byte: 1, end_byte: 3, x: 10.0, width: 20.0, glyph_id: 2, font_id: fontdb::ID::dummy(), font_size: 14.0, x_offset: 0.0, y_offset: 0.0, rtl: false },
+                    LaidOutGlyph { start_byte: 3, end_byte: 4, x: 30.0, width: 10.0, glyph_id: 3, font_id: fontdb::ID::dummy(), font_size: 14.0, x_offset: 0.0, y_offset: 0.0, rtl: false },
+                ],
+            }],
+        };
+
+        // Caret between `-` and `>` lands mid-ligature
+        assert_eq!(layout.caret_x(2), (0, 20.0));
+        assert_eq!(layout.byte_at_x(0, 19.0), 2);
+        assert_eq!(layout.byte_at_x(0, 12.0), 1);
+    }
+}
@@ -1,16 +1,366 @@
 // Text renderer module (using cosmic-text)
-// This will be implemented when integrating with Flutter's rendering pipeline
+//
+// CPU-side shaping and layout. Only glyph positions are handed to
+// Flutter, which does the actual drawing; `raster` draws the same
+// layout into an image for headless rendering and golden tests.
 
+pub mod layout;
+pub mod raster;
+
+pub use layout::{LayoutConfig, LineLayout, VisualRow, LaidOutGlyph, WrapMode, layout_line};
+pub use raster::{RasterOptions, RgbaImage, Rgba, Theme};
+
+use std::collections::HashMap;
+
+use cosmic_text::{FontSystem, SwashCache};
+use ropey::Rope;
+use serde::Serialize;
+
+use crate::editor::Position;
+use crate::editor::telemetry::{self, Operation};
+
+/// Cached lines kept beyond the requested viewport before eviction.
+const MAX_CACHED_LINES: usize = 10_000;
+
+/// Glyph position for drawing (relative to the viewport top-left).
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct GlyphPosition {
+    /// Pen x position
+    pub x: f32,
+
+    /// Baseline y position
+    pub y: f32,
+
+    /// Advance width
+    pub width: f32,
+
+    pub glyph_id: u16,
+
+    /// Character range of the cluster in its line
+    pub start_column: usize,
+    pub end_column: usize,
+
+    pub rtl: bool,
+}
+
+/// Visual (wrapped) line of the viewport.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct VisualLineLayout {
+    /// Logical line
+    pub line: usize,
+
+    /// Row within the logical line (0 = first row)
+    pub wrap_index: usize,
+
+    /// Top of the row
+    pub top: f32,
+
+    pub baseline: f32,
+
+    /// Right edge of the row content
+    pub width: f32,
+
+    /// Character range of the row in its line
+    pub start_column: usize,
+    pub end_column: usize,
+
+    pub glyphs: Vec<GlyphPosition>,
+}
+
+/// Layout of a range of lines.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ViewportLayout {
+    pub line_height: f32,
+
+    /// Total height of the laid out rows
+    pub height: f32,
+
+    pub lines: Vec<VisualLineLayout>,
+}
+
+/// Caret rectangle (relative to the viewport top-left).
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+pub struct CaretPosition {
+    pub x: f32,
+    pub y: f32,
+    pub height: f32,
+}
+
+/// Text layout engine.
+///
+/// Shapes lines with cosmic-text and caches one `LineLayout` per logical
+/// line. Coordinates are relative to the top of the first line of the
+/// viewport (`first_line`), so layouts above it are never computed.
+///
+/// Call `invalidate_lines` after each edit so cached lines below it are
+/// shifted instead of recomputed.
 pub struct TextRenderer {
-    // TODO: cosmic-text integration
-    // font_system: FontSystem,
-    // buffer: Buffer,
+    font_system: FontSystem,
+    config: LayoutConfig,
+
+    /// Advance of a space in the current font (lazily measured)
+    space_width: Option<f32>,
+
+    /// Layout per logical line
+    cache: HashMap<usize, LineLayout>,
+
+    /// Rasterized glyphs (headless rendering)
+    swash_cache: SwashCache,
 }
 
 impl TextRenderer {
+    /// Creates a renderer using system fonts and default settings.
     pub fn new() -> Self {
+        Self::with_config(LayoutConfig::default())
+    }
+
+    /// Creates a renderer using system fonts.
+    pub fn with_config(config: LayoutConfig) -> Self {
+        Self {
+            font_system: FontSystem::new(),
+            config,
+            space_width: None,
+            cache: HashMap::new(),
+            swash_cache: SwashCache::new(),
+        }
+    }
+
+    /// Creates a renderer using only the given font files (no system fonts).
+    ///
+    /// Output then does not depend on the installed fonts (golden images,
+    /// headless rendering on servers).
+    pub fn with_font_data(config: LayoutConfig, fonts: Vec<Vec<u8>>) -> Self {
+        let mut db = cosmic_text::fontdb::Database::new();
+        for font in fonts {
+            db.load_font_data(font);
+        }
+
         Self {
-            // Will be implemented
+            font_system: FontSystem::new_with_locale_and_db("en-US".to_string(), db),
+            config,
+            space_width: None,
+            cache: HashMap::new(),
+            swash_cache: SwashCache::new(),
+        }
+    }
+
+    /// Gets the layout settings.
+    pub fn config(&self) -> &LayoutConfig {
+        &self.config
+    }
+
+    /// Replaces the layout settings (drops all cached layouts).
+    pub fn set_config(&mut self, config: LayoutConfig) {
+        if config != self.config {
+            self.config = config;
+            self.space_width = None;
+            self.cache.clear();
+        }
+    }
+
+    /// Sets the soft wrap width (None disables wrapping).
+    pub fn set_wrap_width(&mut self, wrap_width: Option<f32>) {
+        let mut config = self.config.clone();
+        config.wrap_width = wrap_width;
+        self.set_config(config);
+    }
+
+    /// Loads a font (TTF/OTF data), e.g. one bundled with the app.
+    pub fn load_font_data(&mut self, data: Vec<u8>) {
+        self.font_system.db_mut().load_font_data(data);
+        self.space_width = None;
+        self.cache.clear();
+    }
+
+    /// Adjusts the cache after an edit.
+    ///
+    /// Parameters:
+    /// - `start_line`: First edited line
+    /// - `old_end_line`: Last edited line before the edit
+    /// - `new_end_line`: Last edited line after the edit
+    pub fn invalidate_lines(&mut self, start_line: usize, old_end_line: usize, new_end_line: usize) {
+        let cache = std::mem::take(&mut self.cache);
+        self.cache = cache
+            .into_iter()
+            .filter(|(line, _)| *line < start_line || *line > old_end_line)
+            .map(|(line, layout)| {
+                if line > old_end_line {
+                    (line - old_end_line + new_end_line, layout)
+                } else {
+                    (line, layout)
+                }
+            })
+            .collect();
+    }
+
+    /// Drops all cached layouts.
+    pub fn clear_cache(&mut self) {
+        self.cache.clear();
+    }
+
+    /// Gets the number of cached line layouts.
+    pub fn cached_line_count(&self) -> usize {
+        self.cache.len()
+    }
+
+    /// Gets the layout of a logical line (cached).
+    ///
+    /// A cached layout whose text no longer matches the line is
+    /// recomputed, so a missed `invalidate_lines` only costs time.
+    pub fn line_layout(&mut self, rope: &Rope, line: usize) -> &LineLayout {
+        let text = line_text(rope, line);
+        let space_width = self.space_width();
+
+        let stale = self.cache.get(&line).is_none_or(|layout| layout.text != text);
+        if stale {
+            let layout = layout_line(&mut self.font_system, &self.config, space_width, &text);
+            self.cache.insert(line, layout);
+        }
+        &self.cache[&line]
+    }
+
+    /// Gets the number of visual rows of a logical line.
+    pub fn visual_line_count(&mut self, rope: &Rope, line: usize) -> usize {
+        self.line_layout(rope, line).rows.len()
+    }
+
+    /// Lays out a range of lines for drawing.
+    ///
+    /// Parameters:
+    /// - `rope`: Document
+    /// - `first_line`: Line at the top of the viewport (y = 0)
+    /// - `last_line`: Last line to lay out (inclusive)
+    ///
+    /// Returns: Visual rows with glyph positions
+    pub fn layout_viewport(&mut self, rope: &Rope, first_line: usize, last_line: usize) -> ViewportLayout {
+        let _span = telemetry::span(Operation::Layout);
+        let line_height = self.config.line_height;
+        let last_line = last_line.min(rope.len_lines().saturating_sub(1));
+        let mut lines = Vec::new();
+        let mut top = 0.0;
+
+        for line in first_line..=last_line {
+            let layout = self.line_layout(rope, line);
+            for (wrap_index, row) in layout.rows.iter().enumerate() {
+                let baseline = top + (line_height + row.ascent - row.descent) / 2.0;
+                lines.push(VisualLineLayout {
+                    line,
+                    wrap_index,
+                    top,
+                    baseline,
+                    width: row.width,
+                    start_column: char_column(&layout.text, row.start_byte),
+                    end_column: char_column(&layout.text, row.end_byte),
+                    glyphs: row
+                        .glyphs
+                        .iter()
+                        .map(|glyph| GlyphPosition {
+                            x: glyph.x + glyph.x_offset,
+                            y: baseline - glyph.y_offset,
+                            width: glyph.width,
+                            glyph_id: glyph.glyph_id,
+                            start_column: char_column(&layout.text, glyph.start_byte),
+                            end_column: char_column(&layout.text, glyph.end_byte),
+                            rtl: glyph.rtl,
+                        })
+                        .collect(),
+                });
+                top += line_height;
+            }
+        }
+
+        self.evict_outside(first_line, last_line);
+
+        ViewportLayout { line_height, height: top, lines }
+    }
+
+    /// Maps a point to the closest document position (hit testing).
+    ///
+    /// Parameters:
+    /// - `rope`: Document
+    /// - `first_line`: Line at the top of the viewport (y = 0)
+    /// - `x`, `y`: Point relative to the viewport
+    ///
+    /// Returns: Position (clamped to the document)
+    pub fn hit_test(&mut self, rope: &Rope, first_line: usize, x: f32, y: f32) -> Position {
+        let last_line = rope.len_lines().saturating_sub(1);
+        let line_height = self.config.line_height;
+        let mut line = first_line.min(last_line);
+        let mut row = 0;
+
+        if y > 0.0 {
+            let mut remaining = (y / line_height) as usize;
+            loop {
+                let rows = self.visual_line_count(rope, line);
+                if remaining < rows {
+                    row = remaining;
+                    break;
+                }
+                if line == last_line {
+                    row = rows - 1;
+                    break;
+                }
+                remaining -= rows;
+                line += 1;
+            }
+        }
+
+        let layout = self.line_layout(rope, line);
+        let byte = layout.byte_at_x(row, x);
+        Position::new(line, char_column(&layout.text, byte))
+    }
+
+    /// Gets the caret rectangle for a position.
+    ///
+    /// Parameters:
+    /// - `rope`: Document
+    /// - `first_line`: Line at the top of the viewport (y = 0)
+    /// - `position`: Caret position (at or below `first_line`)
+    ///
+    /// Returns: Caret rectangle, or None if the position is above the viewport
+    pub fn caret_position(&mut self, rope: &Rope, first_line: usize, position: Position) -> Option<CaretPosition> {
+        let position = Position::clamp(&position, rope);
+        if position.line < first_line {
+            return None;
+        }
+
+        let line_height = self.config.line_height;
+        let mut y = 0.0;
+        for line in first_line..position.line {
+            y += self.visual_line_count(rope, line) as f32 * line_height;
+        }
+
+        let layout = self.line_layout(rope, position.line);
+        let byte = layout
+            .text
+            .char_indices()
+            .nth(position.column)
+            .map_or(layout.text.len(), |(i, _)| i);
+        let (row, x) = layout.caret_x(byte);
+
+        Some(CaretPosition { x, y: y + row as f32 * line_height, height: line_height })
+    }
+
+    /// Helper: Gets (and caches) the advance of a space.
+    fn space_width(&mut self) -> f32 {
+        if let Some(width) = self.space_width {
+            return width;
+        }
+        let width = layout_line(&mut self.font_system, &self.config, 0.0, " ").rows[0].width;
+        self.space_width = Some(width);
+        width
+    }
+
+    /// Helper: Bounds the cache around the last viewport.
+    fn evict_outside(&mut self, first_line: usize, last_line: usize) {
+        if self.cache.len() > MAX_CACHED_LINES {
+            let margin = MAX_CACHED_LINES / 4;
+            let low = first_line.saturating_sub(margin);
+            let high = last_line + margin;
+            self.cache.retain(|line, _| (low..=high).contains(line));
         }
     }
 }
@@ -20,3 +370,103 @@ impl Default for TextRenderer {
         Self::new()
     }
 }
+
+/// Helper: Gets line text without the line break.
+fn line_text(rope: &Rope, line: usize) -> String {
+    if line >= rope.len_lines() {
+        return String::new();
+    }
+    let mut text = rope.line(line).to_string();
+    while text.ends_with(['\n', '\r']) {
+        text.pop();
+    }
+    text
+}
+
+/// Helper: Converts a byte offset in a line to a char column.
+fn char_column(text: &str, byte: usize) -> usize {
+    text.char_indices().take_while(|(i, _)| *i < byte).count()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn renderer(wrap_columns: Option<f32>) -> (TextRenderer, f32) {
+        let mut renderer = TextRenderer::new();
+        let space = renderer.space_width();
+        renderer.set_wrap_width(wrap_columns.map(|columns| columns * space));
+        (renderer, space)
+    }
+
+    #[test]
+    fn test_layout_viewport_rows() {
+        let (mut renderer, _) = renderer(Some(10.0));
+        let rope = Rope::from_str("short\nthis line wraps around\n\nend");
+
+        let layout = renderer.layout_viewport(&rope, 0, 3);
+        let rows: Vec<(usize, usize)> = layout.lines.iter().map(|l| (l.line, l.wrap_index)).collect();
+
+        assert_eq!(rows[0], (0, 0));
+        assert!(rows.iter().filter(|(line, _)| *line == 1).count() >= 3);
+        assert_eq!(*rows.last().unwrap(), (3, 0));
+        assert_eq!(layout.height, rows.len() as f32 * 20.0);
+        assert_eq!(layout.lines[1].top, 20.0);
+        assert!(layout.lines[0].baseline > 0.0 && layout.lines[0].baseline < 20.0);
+        assert_eq!(layout.lines[0].glyphs.len(), 5);
+        assert_eq!(layout.lines[0].glyphs[4].end_column, 5);
+    }
+
+    #[test]
+    fn test_hit_test_and_caret_position() {
+        let (mut renderer, space) = renderer(Some(8.0));
+        let rope = Rope::from_str("first\nalpha beta gamma\nlast\n");
+
+        // Second row of line 1
+        let caret = renderer.caret_position(&rope, 0, Position::new(1, 7)).unwrap();
+        assert_eq!(caret.y, 40.0);
+        assert!((caret.x - space).abs() < 0.01);
+
+        let position = renderer.hit_test(&rope, 0, caret.x + 0.1, caret.y + 5.0);
+        assert_eq!(position, Position::new(1, 7));
+
+        // Relative to a scrolled viewport
+        let caret = renderer.caret_position(&rope, 1, Position::new(2, 0)).unwrap();
+        assert!(caret.y >= 40.0);
+        assert!(renderer.caret_position(&rope, 2, Position::new(0, 0)).is_none());
+
+        // Below the document
+        assert_eq!(renderer.hit_test(&rope, 0, 0.0, 10_000.0).line, 3);
+    }
+
+    #[test]
+    fn test_cache_invalidation_shifts_lines() {
+        let (mut renderer, _) = renderer(None);
+        let rope = Rope::from_str("a\nb\nc\nd\n");
+        renderer.layout_viewport(&rope, 0, 4);
+        assert_eq!(renderer.cached_line_count(), 5);
+
+        // Line 1 split into two lines
+        let edited = Rope::from_str("a\nb1\nb2\nc\nd\n");
+        renderer.invalidate_lines(1, 1, 2);
+        assert_eq!(renderer.cached_line_count(), 4);
+        assert_eq!(renderer.cache[&3].text, "c");
+        assert_eq!(renderer.cache[&4].text, "d");
+
+        renderer.layout_viewport(&edited, 0, 5);
+        assert_eq!(renderer.cache[&2].text, "b2");
+
+        // Lines joined
+        renderer.invalidate_lines(1, 2, 1);
+        assert_eq!(renderer.cache[&2].text, "c");
+    }
+
+    #[test]
+    fn test_stale_cache_entry_is_recomputed() {
+        let (mut renderer, _) = renderer(None);
+        renderer.line_layout(&Rope::from_str("old"), 0);
+        let layout = renderer.line_layout(&Rope::from_str("newer"), 0);
+        assert_eq!(layout.text, "newer");
+        assert_eq!(layout.rows[0].glyphs.len(), 5);
+    }
+}
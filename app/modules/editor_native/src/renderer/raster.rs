@@ -0,0 +1,600 @@
+use std::collections::HashMap;
+
+use anyhow::{bail, Result};
+use cosmic_text::{CacheKey, Color};
+
+use crate::editor::{Editor, HighlightSpan, Position};
+use crate::editor::telemetry::{self, Operation};
+use crate::renderer::{LineLayout, TextRenderer};
+
+/// RGBA color (straight alpha).
+pub type Rgba = [u8; 4];
+
+/// Colors used by the software rasterizer.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Theme {
+    pub background: Rgba,
+    pub foreground: Rgba,
+    pub gutter_background: Rgba,
+    pub gutter_foreground: Rgba,
+
+    /// Line number of the cursor line
+    pub gutter_active_foreground: Rgba,
+
+    /// Selection fill (may be translucent)
+    pub selection: Rgba,
+
+    pub cursor: Rgba,
+
+    /// Colors by highlight capture; `function.method` falls back to
+    /// `function`
+    pub syntax: HashMap<String, Rgba>,
+}
+
+impl Theme {
+    /// Gets the color of a highlight capture, if themed.
+    pub fn syntax_color(&self, capture: &str) -> Option<Rgba> {
+        let mut name = capture;
+        loop {
+            if let Some(color) = self.syntax.get(name) {
+                return Some(*color);
+            }
+            name = &name[..name.rfind('.')?];
+        }
+    }
+}
+
+impl Default for Theme {
+    /// Dark theme (VS Code "Dark+" palette).
+    fn default() -> Self {
+        let syntax = [
+            ("keyword", [0x56, 0x9C, 0xD6, 0xFF]),
+            ("string", [0xCE, 0x91, 0x78, 0xFF]),
+            ("comment", [0x6A, 0x99, 0x55, 0xFF]),
+            ("function", [0xDC, 0xDC, 0xAA, 0xFF]),
+            ("type", [0x4E, 0xC9, 0xB0, 0xFF]),
+            ("constructor", [0x4E, 0xC9, 0xB0, 0xFF]),
+            ("number", [0xB5, 0xCE, 0xA8, 0xFF]),
+            ("constant", [0x4F, 0xC1, 0xFF, 0xFF]),
+            ("variable", [0x9C, 0xDC, 0xFE, 0xFF]),
+            ("property", [0x9C, 0xDC, 0xFE, 0xFF]),
+            ("attribute", [0x9C, 0xDC, 0xFE, 0xFF]),
+            ("tag", [0x56, 0x9C, 0xD6, 0xFF]),
+            ("escape", [0xD7, 0xBA, 0x7D, 0xFF]),
+        ];
+
+        Self {
+            background: [0x1E, 0x1E, 0x1E, 0xFF],
+            foreground: [0xD4, 0xD4, 0xD4, 0xFF],
+            gutter_background: [0x1E, 0x1E, 0x1E, 0xFF],
+            gutter_foreground: [0x85, 0x85, 0x85, 0xFF],
+            gutter_active_foreground: [0xC6, 0xC6, 0xC6, 0xFF],
+            selection: [0x26, 0x4F, 0x78, 0xFF],
+            cursor: [0xAE, 0xAF, 0xAD, 0xFF],
+            syntax: syntax
+                .into_iter()
+                .map(|(name, color)| (name.to_string(), color))
+                .collect(),
+        }
+    }
+}
+
+/// What to rasterize.
+#[derive(Debug, Clone, PartialEq)]
+pub struct RasterOptions {
+    /// Image width in logical pixels
+    pub width: u32,
+
+    /// Image height in logical pixels (None = fit the remaining lines)
+    pub height: Option<u32>,
+
+    /// Device pixel ratio
+    pub scale: f32,
+
+    /// Line at the top of the image
+    pub first_line: usize,
+
+    /// Draw line numbers
+    pub gutter: bool,
+
+    /// Draw the cursor and selection
+    pub show_cursor: bool,
+
+    pub theme: Theme,
+}
+
+impl Default for RasterOptions {
+    fn default() -> Self {
+        Self {
+            width: 800,
+            height: Some(600),
+            scale: 1.0,
+            first_line: 0,
+            gutter: true,
+            show_cursor: true,
+            theme: Theme::default(),
+        }
+    }
+}
+
+/// RGBA8 image (straight alpha, row-major).
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RgbaImage {
+    pub width: u32,
+    pub height: u32,
+    pub data: Vec<u8>,
+}
+
+impl RgbaImage {
+    /// Creates an image filled with a color.
+    pub fn new(width: u32, height: u32, fill: Rgba) -> Self {
+        let data = fill.repeat(width as usize * height as usize);
+        Self { width, height, data }
+    }
+
+    /// Gets a pixel.
+    pub fn pixel(&self, x: u32, y: u32) -> Rgba {
+        let i = (y as usize * self.width as usize + x as usize) * 4;
+        [self.data[i], self.data[i + 1], self.data[i + 2], self.data[i + 3]]
+    }
+
+    /// Blends a color over a pixel (ignored outside the image).
+    pub fn blend(&mut self, x: i32, y: i32, color: Rgba) {
+        if x < 0 || y < 0 || x >= self.width as i32 || y >= self.height as i32 || color[3] == 0 {
+            return;
+        }
+
+        let i = (y as usize * self.width as usize + x as usize) * 4;
+        let alpha = color[3] as u32;
+        for (dst, src) in self.data[i..i + 3].iter_mut().zip(color) {
+            *dst = ((src as u32 * alpha + *dst as u32 * (255 - alpha)) / 255) as u8;
+        }
+        let dst_alpha = self.data[i + 3] as u32;
+        self.data[i + 3] = (alpha + dst_alpha * (255 - alpha) / 255) as u8;
+    }
+
+    /// Fills a rectangle (physical pixels, clipped).
+    pub fn fill_rect(&mut self, x: i32, y: i32, width: i32, height: i32, color: Rgba) {
+        for py in y.max(0)..(y + height).min(self.height as i32) {
+            for px in x.max(0)..(x + width).min(self.width as i32) {
+                self.blend(px, py, color);
+            }
+        }
+    }
+
+    /// Encodes the image as PNG.
+    pub fn to_png(&self) -> Result<Vec<u8>> {
+        let mut bytes = Vec::new();
+        {
+            let mut encoder = png::Encoder::new(&mut bytes, self.width, self.height);
+            encoder.set_color(png::ColorType::Rgba);
+            encoder.set_depth(png::BitDepth::Eight);
+            let mut writer = encoder.write_header()?;
+            writer.write_image_data(&self.data)?;
+        }
+        Ok(bytes)
+    }
+
+    /// Decodes an 8-bit RGBA PNG (as written by `to_png`).
+    pub fn from_png(bytes: &[u8]) -> Result<Self> {
+        let decoder = png::Decoder::new(bytes);
+        let mut reader = decoder.read_info()?;
+        let mut data = vec![0; reader.output_buffer_size()];
+        let info = reader.next_frame(&mut data)?;
+
+        if info.color_type != png::ColorType::Rgba || info.bit_depth != png::BitDepth::Eight {
+            bail!("unsupported PNG format: {:?} {:?}", info.color_type, info.bit_depth);
+        }
+        data.truncate(info.buffer_size());
+
+        Ok(Self { width: info.width, height: info.height, data })
+    }
+
+    /// Counts pixels differing by more than `tolerance` in any channel.
+    ///
+    /// Returns: Differing pixels, or None if the sizes differ
+    pub fn diff(&self, other: &RgbaImage, tolerance: u8) -> Option<usize> {
+        if self.width != other.width || self.height != other.height {
+            return None;
+        }
+
+        let differing = self
+            .data
+            .chunks_exact(4)
+            .zip(other.data.chunks_exact(4))
+            .filter(|(a, b)| a.iter().zip(b.iter()).any(|(x, y)| x.abs_diff(*y) > tolerance))
+            .count();
+        Some(differing)
+    }
+}
+
+impl TextRenderer {
+    /// Rasterizes the editor viewport on the CPU.
+    ///
+    /// Draws syntax-colored text (glyphs rendered with swash), the
+    /// selection, the cursor and gutter line numbers. Uses the current
+    /// layout settings, so soft wrap applies.
+    ///
+    /// Parameters:
+    /// - `editor`: Editor to draw
+    /// - `options`: Image size, scale, first line and theme
+    ///
+    /// Returns: RGBA image in physical pixels
+    pub fn rasterize(&mut self, editor: &Editor, options: &RasterOptions) -> RgbaImage {
+        let _span = telemetry::span(Operation::Render);
+        let rope = editor.rope();
+        let theme = &options.theme;
+        let scale = options.scale.max(0.1);
+        let line_height = self.config.line_height;
+        let space = self.space_width();
+        let last_line = rope.len_lines().saturating_sub(1);
+        let first_line = options.first_line.min(last_line);
+
+        // Lines that can be visible (each takes at least one row)
+        let end_line = match options.height {
+            Some(height) => (first_line + (height as f32 / line_height).ceil() as usize).min(last_line),
+            None => last_line,
+        };
+        let layouts: Vec<LineLayout> = (first_line..=end_line)
+            .map(|line| self.line_layout(rope, line).clone())
+            .collect();
+
+        let logical_height = match options.height {
+            Some(height) => height as f32,
+            None => layouts.iter().map(|l| l.rows.len()).sum::<usize>() as f32 * line_height,
+        };
+        let mut image = RgbaImage::new(
+            (options.width as f32 * scale).ceil() as u32,
+            (logical_height * scale).ceil() as u32,
+            theme.background,
+        );
+
+        // Gutter: line numbers right-aligned with one column of padding
+        let digits = (last_line + 1).to_string().len().max(2);
+        let gutter_width = if options.gutter { (digits + 2) as f32 * space } else { 0.0 };
+        let text_x = gutter_width + if options.gutter { space } else { 0.0 };
+        if options.gutter {
+            image.fill_rect(0, 0, (gutter_width * scale).ceil() as i32, image.height as i32, theme.gutter_background);
+        }
+
+        let highlights = editor.highlights(first_line, end_line);
+        let cursor = Position::clamp(&editor.cursor(), rope);
+        let selection = editor.selection().filter(|s| !s.is_empty() && options.show_cursor);
+
+        let mut top = 0.0;
+        for (offset, layout) in layouts.iter().enumerate() {
+            let line = first_line + offset;
+            let line_start = rope.line_to_byte(line);
+
+            for (row_index, row) in layout.rows.iter().enumerate() {
+                if top >= logical_height {
+                    break;
+                }
+                let baseline = top + (line_height + row.ascent - row.descent) / 2.0;
+                let is_last_row = row_index + 1 == layout.rows.len();
+
+                // Selection
+                if let Some(selection) = &selection {
+                    let (start, end) = if selection.start <= selection.end {
+                        (selection.start, selection.end)
+                    } else {
+                        (selection.end, selection.start)
+                    };
+                    if start.line <= line && line <= end.line {
+                        let from = if start.line == line { column_byte(&layout.text, start.column) } else { 0 };
+                        let past_eol = end.line > line;
+                        let to = if past_eol { layout.text.len() } else { column_byte(&layout.text, end.column) };
+
+                        let from = from.max(row.start_byte);
+                        let to = to.min(row.end_byte);
+                        if from < to || (past_eol && is_last_row) {
+                            let x0 = layout.caret_x(from).1;
+                            let mut x1 = if to >= row.end_byte && !is_last_row {
+                                row.width
+                            } else {
+                                layout.caret_x(to).1
+                            };
+                            if past_eol && is_last_row {
+                                x1 += space;
+                            }
+                            image.fill_rect(
+                                ((text_x + x0) * scale) as i32,
+                                (top * scale) as i32,
+                                ((x1 - x0) * scale).ceil() as i32,
+                                (line_height * scale).ceil() as i32,
+                                theme.selection,
+                            );
+                        }
+                    }
+                }
+
+                // Text
+                for glyph in &row.glyphs {
+                    let byte = line_start + glyph.start_byte;
+                    let color = glyph_color(&highlights, byte, theme);
+                    self.draw_glyph(
+                        &mut image,
+                        glyph.font_id,
+                        glyph.glyph_id,
+                        glyph.font_size,
+                        (text_x + glyph.x + glyph.x_offset) * scale,
+                        (baseline - glyph.y_offset) * scale,
+                        scale,
+                        color,
+                    );
+                }
+
+                // Line number
+                if options.gutter && row_index == 0 {
+                    let color = if line == cursor.line && options.show_cursor {
+                        theme.gutter_active_foreground
+                    } else {
+                        theme.gutter_foreground
+                    };
+                    let number = self.line_number_layout(line + 1);
+                    let number_x = gutter_width - space - number.rows[0].width;
+                    for glyph in &number.rows[0].glyphs {
+                        self.draw_glyph(
+                            &mut image,
+                            glyph.font_id,
+                            glyph.glyph_id,
+                            glyph.font_size,
+                            (number_x + glyph.x) * scale,
+                            baseline * scale,
+                            scale,
+                            color,
+                        );
+                    }
+                }
+
+                // Cursor
+                if options.show_cursor && line == cursor.line {
+                    let byte = column_byte(&layout.text, cursor.column);
+                    let (cursor_row, x) = layout.caret_x(byte);
+                    if cursor_row == row_index {
+                        image.fill_rect(
+                            ((text_x + x) * scale) as i32,
+                            (top * scale) as i32,
+                            (2.0 * scale).round().max(1.0) as i32,
+                            (line_height * scale).ceil() as i32,
+                            theme.cursor,
+                        );
+                    }
+                }
+
+                top += line_height;
+            }
+        }
+
+        image
+    }
+
+    /// Rasterizes the editor viewport and encodes it as PNG.
+    pub fn render_png(&mut self, editor: &Editor, options: &RasterOptions) -> Result<Vec<u8>> {
+        self.rasterize(editor, options).to_png()
+    }
+
+    /// Checks if a font family is available.
+    pub fn has_font_family(&self, family: &str) -> bool {
+        self.font_system
+            .db()
+            .faces()
+            .any(|face| face.families.iter().any(|(name, _)| name == family))
+    }
+
+    /// Helper: Lays out a line number (uncached, never wrapped).
+    fn line_number_layout(&mut self, number: usize) -> LineLayout {
+        let mut config = self.config.clone();
+        config.wrap_width = None;
+        crate::renderer::layout_line(&mut self.font_system, &config, 0.0, &number.to_string())
+    }
+
+    /// Helper: Draws one glyph with its baseline at (x, y) physical pixels.
+    #[allow(clippy::too_many_arguments)]
+    fn draw_glyph(
+        &mut self,
+        image: &mut RgbaImage,
+        font_id: cosmic_text::fontdb::ID,
+        glyph_id: u16,
+        font_size: f32,
+        x: f32,
+        y: f32,
+        scale: f32,
+        color: Rgba,
+    ) {
+        let (cache_key, glyph_x, glyph_y) = CacheKey::new(font_id, glyph_id, font_size * scale, (x, y.trunc()));
+        let base = Color::rgba(color[0], color[1], color[2], color[3]);
+
+        self.swash_cache
+            .with_pixels(&mut self.font_system, cache_key, base, |dx, dy, pixel| {
+                let alpha = (pixel.a() as u32 * color[3] as u32 / 255) as u8;
+                image.blend(glyph_x + dx, glyph_y + dy, [pixel.r(), pixel.g(), pixel.b(), alpha]);
+            });
+    }
+}
+
+/// Helper: Gets the color of the glyph at a document byte.
+///
+/// The deepest injection layer wins, then the innermost span; for the
+/// same node the first query pattern wins (tree-sitter-highlight rules).
+fn glyph_color(highlights: &[HighlightSpan], byte: usize, theme: &Theme) -> Rgba {
+    let mut best: Option<(&HighlightSpan, Rgba)> = None;
+
+    for span in highlights {
+        if byte < span.start_byte || span.end_byte <= byte {
+            continue;
+        }
+        let Some(color) = theme.syntax_color(&span.capture) else {
+            continue;
+        };
+
+        let better = best.is_none_or(|(current, _)| {
+            span.depth > current.depth
+                || (span.depth == current.depth
+                    && span.end_byte - span.start_byte < current.end_byte - current.start_byte)
+        });
+        if better {
+            best = Some((span, color));
+        }
+    }
+
+    best.map_or(theme.foreground, |(_, color)| color)
+}
+
+/// Helper: Converts a char column to a byte offset in a line.
+fn column_byte(text: &str, column: usize) -> usize {
+    text.char_indices().nth(column).map_or(text.len(), |(i, _)| i)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::editor::{LanguageId, Selection};
+    use crate::renderer::LayoutConfig;
+
+    const GOLDEN_FONT: &str = "DejaVu Sans Mono";
+    const SAMPLE: &str = "fn main() {\n    // greet\n    let name = \"world\";\n    println!(\"hi {}\", name);\n}\n";
+
+    /// Renderer with only the vendored `tests/fixtures/fonts` font, so
+    /// goldens do not depend on the fonts installed
+    fn golden_renderer() -> TextRenderer {
+        let path = std::path::Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/fixtures/fonts/DejaVuSansMono.ttf");
+        let font = std::fs::read(&path).unwrap_or_else(|_| panic!("missing font fixture {:?}", path));
+        let config = LayoutConfig { font_family: Some(GOLDEN_FONT.to_string()), ..LayoutConfig::default() };
+
+        let renderer = TextRenderer::with_font_data(config, vec![font]);
+        assert!(renderer.has_font_family(GOLDEN_FONT));
+        renderer
+    }
+
+    /// Compares with `tests/golden/<name>.png`; `UPDATE_GOLDENS=1`
+    /// rewrites the file.
+    fn assert_golden(name: &str, image: &RgbaImage) {
+        let path = std::path::Path::new(env!("CARGO_MANIFEST_DIR"))
+            .join("tests/golden")
+            .join(format!("{}.png", name));
+
+        if std::env::var_os("UPDATE_GOLDENS").is_some() {
+            std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+            std::fs::write(&path, image.to_png().unwrap()).unwrap();
+            return;
+        }
+
+        let bytes = std::fs::read(&path)
+            .unwrap_or_else(|_| panic!("missing golden {:?} (run with UPDATE_GOLDENS=1)", path));
+        let expected = RgbaImage::from_png(&bytes).unwrap();
+        let differing = image.diff(&expected, 8).expect("golden size changed");
+
+        // Allow anti-aliasing noise from rasterizer versions
+        let allowed = (image.width * image.height) as usize / 500;
+        assert!(differing <= allowed, "{} differs in {} pixels", name, differing);
+    }
+
+    #[test]
+    fn test_theme_syntax_color_fallback() {
+        let theme = Theme::default();
+        assert_eq!(theme.syntax_color("function.method"), theme.syntax_color("function"));
+        assert!(theme.syntax_color("function").is_some());
+        assert_eq!(theme.syntax_color("punctuation.bracket"), None);
+    }
+
+    #[test]
+    fn test_png_round_trip_and_diff() {
+        let mut image = RgbaImage::new(4, 3, [10, 20, 30, 255]);
+        image.fill_rect(1, 1, 2, 1, [200, 0, 0, 255]);
+
+        let decoded = RgbaImage::from_png(&image.to_png().unwrap()).unwrap();
+        assert_eq!(decoded, image);
+        assert_eq!(decoded.pixel(2, 1), [200, 0, 0, 255]);
+
+        let plain = RgbaImage::new(4, 3, [10, 20, 30, 255]);
+        assert_eq!(image.diff(&plain, 0), Some(2));
+        assert_eq!(image.diff(&RgbaImage::new(1, 1, [0; 4]), 0), None);
+    }
+
+    #[test]
+    fn test_blend_translucent() {
+        let mut image = RgbaImage::new(1, 1, [0, 0, 0, 255]);
+        image.blend(0, 0, [255, 255, 255, 128]);
+        assert_eq!(image.pixel(0, 0), [128, 128, 128, 255]);
+    }
+
+    #[test]
+    fn test_rasterize_selection_cursor_and_text() {
+        let mut editor = Editor::with_content(SAMPLE, LanguageId::Rust).unwrap();
+        editor.set_selection(Selection::new(Position::new(2, 8), Position::new(2, 12)));
+        editor.move_cursor(Position::new(4, 1));
+
+        let mut renderer = TextRenderer::new();
+        let space = renderer.space_width();
+        let options = RasterOptions { width: 300, height: Some(120), ..RasterOptions::default() };
+        let image = renderer.rasterize(&editor, &options);
+        let theme = &options.theme;
+
+        assert_eq!((image.width, image.height), (300, 120));
+
+        // Gutter is 4 columns wide, text starts one column later
+        let text_x = 5.0 * space;
+
+        // Selection behind `name` on line 2 (row top 40)
+        let selection_x = (text_x + 8.5 * space) as u32;
+        let selection_pixels = (40..60).filter(|y| image.pixel(selection_x, *y) != theme.background).count();
+        assert!(selection_pixels > 10);
+        assert!(image.pixel(selection_x, 41)[2] >= theme.selection[2] / 2);
+
+        // Cursor bar after `}` on line 4 (row top 80)
+        let cursor_x = (text_x + space) as u32;
+        assert_eq!(image.pixel(cursor_x, 90), theme.cursor);
+
+        // Keyword `fn` is drawn in the keyword color
+        let keyword = theme.syntax["keyword"];
+        let fn_pixels: Vec<Rgba> = (0..20)
+            .flat_map(|y| (0..(2.0 * space) as u32).map(move |x| (x, y)))
+            .map(|(x, y)| image.pixel(text_x as u32 + x, y))
+            .collect();
+        assert!(fn_pixels.iter().any(|p| p[2] > p[0] && p[2] as i32 - keyword[2] as i32 > -40));
+    }
+
+    #[test]
+    fn test_rasterize_fit_content_and_scale() {
+        let editor = Editor::with_content("a\nb\nc", LanguageId::PlainText).unwrap();
+        let mut renderer = TextRenderer::new();
+        let options = RasterOptions {
+            width: 100,
+            height: None,
+            scale: 2.0,
+            ..RasterOptions::default()
+        };
+
+        let image = renderer.rasterize(&editor, &options);
+        assert_eq!((image.width, image.height), (200, 120));
+        assert!(renderer.render_png(&editor, &options).unwrap().starts_with(b"\x89PNG"));
+    }
+
+    #[test]
+    fn test_golden_rust_viewport() {
+        let mut renderer = golden_renderer();
+        let mut editor = Editor::with_content(SAMPLE, LanguageId::Rust).unwrap();
+        editor.set_selection(Selection::new(Position::new(2, 8), Position::new(3, 4)));
+        editor.move_cursor(Position::new(3, 4));
+
+        let options = RasterOptions { width: 320, height: Some(110), ..RasterOptions::default() };
+        assert_golden("rust_viewport", &renderer.rasterize(&editor, &options));
+    }
+
+    #[test]
+    fn test_golden_soft_wrap_hidpi() {
+        let mut renderer = golden_renderer();
+        renderer.set_wrap_width(Some(120.0));
+        let editor = Editor::with_content("# heading\nsome prose that wraps across rows\n", LanguageId::Markdown).unwrap();
+
+        let options = RasterOptions {
+            width: 160,
+            height: None,
+            scale: 2.0,
+            show_cursor: false,
+            ..RasterOptions::default()
+        };
+        assert_golden("soft_wrap_hidpi", &renderer.rasterize(&editor, &options));
+    }
+}
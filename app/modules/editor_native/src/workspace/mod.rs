@@ -0,0 +1,880 @@
+//! Workspace: the set of open documents
+//!
+//! A `Workspace` owns every open buffer (one `Editor` per document) by id
+//! and URI, and the views showing them. Several views can share a buffer
+//! (split panes of the same file); each keeps its own cursor, selection
+//! and scroll position.
+//!
+//! Cross-buffer features (save all, local completion across files, global
+//! replace, LSP document sync) go through the workspace so they all see
+//! the same documents.
+
+pub mod recovery;
+
+use std::collections::BTreeMap;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+
+use anyhow::{anyhow, bail, Context, Result};
+use serde::Serialize;
+
+use crate::editor::{
+    editorconfig, search_rope, CompletionList, Editor, LanguageId, LargeFileConfig, Position, SearchOptions,
+    Selection,
+};
+
+/// Buffer identifier (unique for the lifetime of the workspace)
+pub type BufferId = u64;
+
+/// View identifier (unique for the lifetime of the workspace)
+pub type ViewId = u64;
+
+/// Open document.
+pub struct Buffer {
+    id: BufferId,
+
+    /// `file://` URI, or `untitled:Untitled-N` for unsaved documents
+    uri: String,
+
+    /// Normalized file path (None for untitled buffers)
+    path: Option<PathBuf>,
+
+    editor: Editor,
+
+    /// Views showing this buffer, in creation order
+    views: Vec<ViewId>,
+
+    /// View whose cursor the editor currently holds
+    active_view: Option<ViewId>,
+
+    /// Close requested while dirty; waiting for `resolve_close`
+    close_pending: bool,
+}
+
+impl Buffer {
+    /// Gets the buffer id
+    pub fn id(&self) -> BufferId {
+        self.id
+    }
+
+    /// Gets the document URI
+    pub fn uri(&self) -> &str {
+        &self.uri
+    }
+
+    /// Gets the file path (None for untitled buffers)
+    pub fn path(&self) -> Option<&Path> {
+        self.path.as_deref()
+    }
+
+    /// Gets the editor
+    pub fn editor(&self) -> &Editor {
+        &self.editor
+    }
+
+    /// Gets the editor for editing
+    pub fn editor_mut(&mut self) -> &mut Editor {
+        &mut self.editor
+    }
+
+    /// Gets the views showing this buffer
+    pub fn views(&self) -> &[ViewId] {
+        &self.views
+    }
+
+    /// Checks if the buffer has unsaved changes
+    pub fn is_dirty(&self) -> bool {
+        self.editor.is_dirty()
+    }
+
+    /// Checks if a close is waiting for confirmation
+    pub fn is_close_pending(&self) -> bool {
+        self.close_pending
+    }
+
+    /// Gets a summary for the UI
+    pub fn info(&self) -> BufferInfo {
+        BufferInfo {
+            id: self.id,
+            uri: self.uri.clone(),
+            path: self.path.as_ref().map(|path| path.display().to_string()),
+            language: self.editor.language().name(),
+            version: self.editor.version(),
+            dirty: self.is_dirty(),
+            views: self.views.clone(),
+            close_pending: self.close_pending,
+        }
+    }
+}
+
+/// Pane showing a buffer.
+///
+/// The cursor and selection of the buffer's active view live in the
+/// editor itself; the fields here hold them while the view is inactive.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct View {
+    pub id: ViewId,
+    pub buffer: BufferId,
+    pub cursor: Position,
+    pub selection: Option<Selection>,
+
+    /// First visible line
+    pub scroll_line: usize,
+}
+
+/// Buffer summary (serialized for the UI).
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct BufferInfo {
+    pub id: BufferId,
+    pub uri: String,
+    pub path: Option<String>,
+    pub language: &'static str,
+    pub version: u64,
+    pub dirty: bool,
+    pub views: Vec<ViewId>,
+    pub close_pending: bool,
+}
+
+/// Result of a close request.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CloseOutcome {
+    /// The view or buffer was closed
+    Closed,
+
+    /// The buffer has unsaved changes; answer with `resolve_close`
+    NeedsConfirmation,
+
+    /// The pending close was cancelled
+    Cancelled,
+}
+
+/// Answer to a close confirmation prompt.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CloseChoice {
+    /// Save, then close
+    Save,
+
+    /// Close without saving
+    Discard,
+
+    /// Keep the buffer open
+    Cancel,
+}
+
+/// Failed save of one buffer.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SaveFailure {
+    pub buffer: BufferId,
+    pub error: String,
+}
+
+/// Result of `save_all`.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SaveReport {
+    pub saved: Vec<BufferId>,
+    pub failed: Vec<SaveFailure>,
+}
+
+/// Set of open documents and their views.
+pub struct Workspace {
+    buffers: BTreeMap<BufferId, Buffer>,
+    views: BTreeMap<ViewId, View>,
+    next_buffer_id: BufferId,
+    next_view_id: ViewId,
+    next_untitled: usize,
+    large_file_config: LargeFileConfig,
+}
+
+impl Workspace {
+    /// Creates an empty workspace
+    pub fn new() -> Self {
+        Self {
+            buffers: BTreeMap::new(),
+            views: BTreeMap::new(),
+            next_buffer_id: 1,
+            next_view_id: 1,
+            next_untitled: 1,
+            large_file_config: LargeFileConfig::default(),
+        }
+    }
+
+    /// Sets the large-file thresholds used for files opened from now on
+    pub fn set_large_file_config(&mut self, config: LargeFileConfig) {
+        self.large_file_config = config;
+    }
+
+    /// Opens a file, or returns the buffer already showing it
+    ///
+    /// `.editorconfig` settings (including the charset the file is decoded
+    /// from) are applied to newly opened files.
+    ///
+    /// Returns: Buffer id
+    pub fn open_file(&mut self, path: &Path) -> Result<BufferId> {
+        let path = normalize_path(path);
+        if let Some(id) = self.find_by_path(&path) {
+            return Ok(id);
+        }
+
+        // Missing or unreadable .editorconfig files just keep the defaults
+        let props = editorconfig::resolve_for_path(&path).ok();
+        let charset = props.as_ref().and_then(|props| props.charset);
+        let mut editor = Editor::open_file_with_charset(&path, self.large_file_config.clone(), charset)?;
+        if let Some(props) = &props {
+            editor.apply_editorconfig(props);
+        }
+
+        let uri = path_to_uri(&path);
+        Ok(self.insert_buffer(uri, Some(path), editor))
+    }
+
+    /// Opens an unsaved document
+    ///
+    /// Returns: Buffer id (URI `untitled:Untitled-N`)
+    pub fn open_untitled(&mut self, content: &str, language: LanguageId) -> Result<BufferId> {
+        let editor = Editor::with_content(content, language)?;
+        let uri = format!("untitled:Untitled-{}", self.next_untitled);
+        self.next_untitled += 1;
+        Ok(self.insert_buffer(uri, None, editor))
+    }
+
+    /// Adds a buffer recovered after a crash or hot exit
+    ///
+    /// If the document is already open, its buffer takes the recovered
+    /// editor, unless it has unsaved changes of its own.
+    ///
+    /// Returns: Buffer id
+    pub fn open_recovered(&mut self, uri: String, path: Option<PathBuf>, editor: Editor) -> Result<BufferId> {
+        let path = path.map(|path| normalize_path(&path));
+        let existing = match &path {
+            Some(path) => self.find_by_path(path),
+            None => self.find_by_uri(&uri),
+        };
+
+        if let Some(id) = existing {
+            let buffer = self.buffer_entry(id)?;
+            if buffer.is_dirty() {
+                bail!("{} has unsaved changes", buffer.uri);
+            }
+            buffer.editor = editor;
+            return Ok(id);
+        }
+
+        // Keep new untitled names distinct from recovered ones
+        if let Some(n) = uri.strip_prefix("untitled:Untitled-").and_then(|n| n.parse::<usize>().ok()) {
+            self.next_untitled = self.next_untitled.max(n + 1);
+        }
+        let uri = path.as_deref().map(path_to_uri).unwrap_or(uri);
+        Ok(self.insert_buffer(uri, path, editor))
+    }
+
+    /// Opens a new view (pane) on a buffer
+    ///
+    /// The first view of a buffer becomes its active view.
+    pub fn create_view(&mut self, buffer: BufferId) -> Result<ViewId> {
+        let buffer = self
+            .buffers
+            .get_mut(&buffer)
+            .ok_or_else(|| anyhow!("unknown buffer {}", buffer))?;
+        let id = self.next_view_id;
+        self.next_view_id += 1;
+
+        buffer.views.push(id);
+        if buffer.active_view.is_none() {
+            buffer.active_view = Some(id);
+        }
+        self.views.insert(
+            id,
+            View {
+                id,
+                buffer: buffer.id,
+                cursor: Position::start(),
+                selection: None,
+                scroll_line: 0,
+            },
+        );
+        Ok(id)
+    }
+
+    /// Makes a view the active view of its buffer and returns its editor
+    ///
+    /// The cursor and selection of the previously active view are stored,
+    /// and the view's own are restored (clamped, since other views may
+    /// have edited the buffer meanwhile).
+    pub fn activate_view(&mut self, view: ViewId) -> Result<&mut Editor> {
+        let buffer_id = self.view(view).ok_or_else(|| anyhow!("unknown view {}", view))?.buffer;
+        let buffer = self
+            .buffers
+            .get_mut(&buffer_id)
+            .ok_or_else(|| anyhow!("unknown buffer {}", buffer_id))?;
+
+        if buffer.active_view != Some(view) {
+            if let Some(previous) = buffer.active_view.and_then(|id| self.views.get_mut(&id)) {
+                previous.cursor = buffer.editor.cursor();
+                previous.selection = buffer.editor.selection();
+            }
+
+            let state = &self.views[&view];
+            let rope = buffer.editor.rope();
+            let cursor = Position::clamp(&state.cursor, rope);
+            let selection = state
+                .selection
+                .map(|s| Selection::new(Position::clamp(&s.start, rope), Position::clamp(&s.end, rope)));
+
+            buffer.editor.without_macro_recording(|editor| {
+                editor.move_cursor(cursor);
+                match selection {
+                    Some(selection) => editor.set_selection(selection),
+                    None => editor.clear_selection(),
+                }
+            });
+            buffer.active_view = Some(view);
+        }
+
+        Ok(&mut buffer.editor)
+    }
+
+    /// Gets a view's current state
+    ///
+    /// For the active view of a buffer the cursor and selection are read
+    /// from the editor.
+    pub fn view_state(&self, view: ViewId) -> Option<View> {
+        let mut state = self.views.get(&view)?.clone();
+        let buffer = self.buffers.get(&state.buffer)?;
+        if buffer.active_view == Some(view) {
+            state.cursor = buffer.editor.cursor();
+            state.selection = buffer.editor.selection();
+        }
+        Some(state)
+    }
+
+    /// Sets the first visible line of a view
+    pub fn set_view_scroll(&mut self, view: ViewId, line: usize) -> Result<()> {
+        let view = self.views.get_mut(&view).ok_or_else(|| anyhow!("unknown view {}", view))?;
+        view.scroll_line = line;
+        Ok(())
+    }
+
+    /// Gets a view (inactive state, see `view_state`)
+    pub fn view(&self, view: ViewId) -> Option<&View> {
+        self.views.get(&view)
+    }
+
+    /// Gets a buffer
+    pub fn buffer(&self, id: BufferId) -> Option<&Buffer> {
+        self.buffers.get(&id)
+    }
+
+    /// Gets a buffer for editing
+    pub fn buffer_mut(&mut self, id: BufferId) -> Option<&mut Buffer> {
+        self.buffers.get_mut(&id)
+    }
+
+    /// Gets all buffers in opening order
+    pub fn buffers(&self) -> impl Iterator<Item = &Buffer> {
+        self.buffers.values()
+    }
+
+    /// Gets the number of open buffers
+    pub fn buffer_count(&self) -> usize {
+        self.buffers.len()
+    }
+
+    /// Finds the buffer of a file
+    pub fn find_by_path(&self, path: &Path) -> Option<BufferId> {
+        let path = normalize_path(path);
+        self.buffers
+            .values()
+            .find(|buffer| buffer.path.as_deref() == Some(path.as_path()))
+            .map(|buffer| buffer.id)
+    }
+
+    /// Finds a buffer by URI
+    pub fn find_by_uri(&self, uri: &str) -> Option<BufferId> {
+        self.buffers.values().find(|buffer| buffer.uri == uri).map(|buffer| buffer.id)
+    }
+
+    /// Gets the buffers with unsaved changes
+    pub fn dirty_buffers(&self) -> Vec<BufferId> {
+        self.buffers.values().filter(|b| b.is_dirty()).map(|b| b.id).collect()
+    }
+
+    /// Gets the buffers waiting for a close confirmation
+    pub fn pending_closes(&self) -> Vec<BufferId> {
+        self.buffers.values().filter(|b| b.close_pending).map(|b| b.id).collect()
+    }
+
+    /// Repairs every buffer after a panic (see `Editor::recover_after_panic`)
+    pub fn recover_after_panic(&mut self) {
+        for buffer in self.buffers.values_mut() {
+            buffer.editor.recover_after_panic();
+        }
+    }
+
+    /// Saves a buffer to its file
+    ///
+    /// Save settings (trailing whitespace, final newline, line endings,
+    /// charset) are applied first. Untitled buffers need `save_as`.
+    pub fn save(&mut self, id: BufferId) -> Result<()> {
+        let buffer = self.buffer_entry(id)?;
+        let path = buffer
+            .path
+            .clone()
+            .ok_or_else(|| anyhow!("{} has no file path", buffer.uri))?;
+
+        let bytes = buffer.editor.prepare_save()?;
+        write_atomic(&path, &bytes)?;
+        buffer.editor.mark_saved();
+        Ok(())
+    }
+
+    /// Saves a buffer to a new path (the buffer takes the new URI)
+    pub fn save_as(&mut self, id: BufferId, path: &Path) -> Result<()> {
+        let path = normalize_path(path);
+        if self.find_by_path(&path).is_some_and(|other| other != id) {
+            return Err(anyhow!("{} is already open in another buffer", path.display()));
+        }
+
+        let buffer = self.buffer_entry(id)?;
+        let previous = (buffer.uri.clone(), buffer.path.clone());
+        buffer.uri = path_to_uri(&path);
+        buffer.path = Some(path);
+
+        let result = self.save(id);
+        if result.is_err() {
+            let buffer = self.buffer_entry(id)?;
+            (buffer.uri, buffer.path) = previous;
+        }
+        result
+    }
+
+    /// Saves every dirty buffer
+    ///
+    /// Returns: Saved buffers and the ones that failed (e.g. untitled)
+    pub fn save_all(&mut self) -> SaveReport {
+        let mut report = SaveReport::default();
+        for id in self.dirty_buffers() {
+            match self.save(id) {
+                Ok(()) => report.saved.push(id),
+                Err(e) => report.failed.push(SaveFailure {
+                    buffer: id,
+                    error: format!("{:#}", e),
+                }),
+            }
+        }
+        report
+    }
+
+    /// Closes a view
+    ///
+    /// Closing the last view of a buffer closes the buffer, which needs
+    /// confirmation if it has unsaved changes (the view stays open until
+    /// `resolve_close`).
+    pub fn close_view(&mut self, view: ViewId) -> Result<CloseOutcome> {
+        let buffer_id = self.view(view).ok_or_else(|| anyhow!("unknown view {}", view))?.buffer;
+        let buffer = self.buffer_entry(buffer_id)?;
+
+        if buffer.views.len() > 1 {
+            if buffer.active_view == Some(view) {
+                // Hand the editor state to another view
+                let next = buffer.views.iter().copied().find(|&id| id != view);
+                self.activate_view(next.unwrap_or(view))?;
+            }
+            let buffer = self.buffer_entry(buffer_id)?;
+            buffer.views.retain(|&id| id != view);
+            self.views.remove(&view);
+            return Ok(CloseOutcome::Closed);
+        }
+
+        self.close_buffer(buffer_id)
+    }
+
+    /// Closes a buffer and all its views
+    ///
+    /// Returns: `NeedsConfirmation` if the buffer is dirty
+    pub fn close_buffer(&mut self, id: BufferId) -> Result<CloseOutcome> {
+        let buffer = self.buffer_entry(id)?;
+        if buffer.is_dirty() {
+            buffer.close_pending = true;
+            return Ok(CloseOutcome::NeedsConfirmation);
+        }
+
+        self.remove_buffer(id);
+        Ok(CloseOutcome::Closed)
+    }
+
+    /// Answers a close confirmation prompt
+    ///
+    /// A failed save keeps the buffer open (and the prompt pending).
+    pub fn resolve_close(&mut self, id: BufferId, choice: CloseChoice) -> Result<CloseOutcome> {
+        let buffer = self.buffer_entry(id)?;
+        if !buffer.close_pending {
+            return Err(anyhow!("no close pending for buffer {}", id));
+        }
+
+        match choice {
+            CloseChoice::Save => {
+                self.save(id)?;
+                self.remove_buffer(id);
+                Ok(CloseOutcome::Closed)
+            }
+            CloseChoice::Discard => {
+                self.remove_buffer(id);
+                Ok(CloseOutcome::Closed)
+            }
+            CloseChoice::Cancel => {
+                buffer.close_pending = false;
+                Ok(CloseOutcome::Cancelled)
+            }
+        }
+    }
+
+    /// Computes local completions using the words of every open buffer
+    ///
+    /// Parameters:
+    /// - `id`: Buffer being edited
+    /// - `position`: Cursor position
+    pub fn completions(&self, id: BufferId, position: Position) -> Option<CompletionList> {
+        let buffer = self.buffers.get(&id)?;
+        let others: Vec<&Editor> = self
+            .buffers
+            .values()
+            .filter(|other| other.id != id)
+            .map(|other| &other.editor)
+            .collect();
+        Some(buffer.editor.completions(position, &others))
+    }
+
+    /// Replaces a search query in every open buffer
+    ///
+    /// Each buffer is changed as one undoable transaction. Read-only
+    /// buffers are skipped.
+    ///
+    /// Returns: Buffers that changed, with their replacement counts
+    pub fn replace_all(&mut self, query: &str, replacement: &str, options: &SearchOptions) -> Vec<(BufferId, usize)> {
+        let mut results = Vec::new();
+
+        for buffer in self.buffers.values_mut() {
+            let editor = &mut buffer.editor;
+            if editor.is_read_only() {
+                continue;
+            }
+
+            let matches = search_rope(editor.rope(), query, options, None);
+            if matches.is_empty() {
+                continue;
+            }
+
+            let replaced = editor.transaction(|editor| {
+                // Back to front so earlier positions stay valid
+                for m in matches.iter().rev() {
+                    editor.replace_range(m.start, m.end, replacement)?;
+                }
+                Ok(matches.len())
+            });
+            if let Ok(count) = replaced {
+                let cursor = Position::clamp(&editor.cursor(), editor.rope());
+                editor.without_macro_recording(|editor| editor.move_cursor(cursor));
+                results.push((buffer.id, count));
+            }
+        }
+
+        results
+    }
+
+    /// Helper: Registers a new buffer
+    fn insert_buffer(&mut self, uri: String, path: Option<PathBuf>, editor: Editor) -> BufferId {
+        let id = self.next_buffer_id;
+        self.next_buffer_id += 1;
+
+        self.buffers.insert(
+            id,
+            Buffer {
+                id,
+                uri,
+                path,
+                editor,
+                views: Vec::new(),
+                active_view: None,
+                close_pending: false,
+            },
+        );
+        id
+    }
+
+    /// Helper: Removes a buffer and its views
+    fn remove_buffer(&mut self, id: BufferId) {
+        if let Some(buffer) = self.buffers.remove(&id) {
+            for view in buffer.views {
+                self.views.remove(&view);
+            }
+        }
+    }
+
+    /// Helper: Gets a buffer or an "unknown buffer" error
+    fn buffer_entry(&mut self, id: BufferId) -> Result<&mut Buffer> {
+        self.buffers.get_mut(&id).ok_or_else(|| anyhow!("unknown buffer {}", id))
+    }
+}
+
+impl Default for Workspace {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Normalizes a path for buffer lookup
+///
+/// Existing files are canonicalized (symlinks resolved); other paths are
+/// made absolute against the current directory.
+pub fn normalize_path(path: &Path) -> PathBuf {
+    std::fs::canonicalize(path).unwrap_or_else(|_| {
+        if path.is_absolute() {
+            path.to_path_buf()
+        } else {
+            std::env::current_dir().map(|dir| dir.join(path)).unwrap_or_else(|_| path.to_path_buf())
+        }
+    })
+}
+
+/// Writes a file via a temporary file, fsync and a rename
+///
+/// A crash mid-write leaves either the old or the new content, never a
+/// truncated file. Symlinks are written through to their target, and an
+/// existing file keeps its permissions.
+pub fn write_atomic(path: &Path, bytes: &[u8]) -> Result<()> {
+    let path = std::fs::canonicalize(path).unwrap_or_else(|_| path.to_path_buf());
+    let file_name = path
+        .file_name()
+        .ok_or_else(|| anyhow!("{} is not a file path", path.display()))?
+        .to_string_lossy();
+    // Same directory, so the rename stays on one filesystem
+    let tmp = path.with_file_name(format!(".{}.{}.tmp", file_name, std::process::id()));
+
+    let result = (|| {
+        let mut file = std::fs::File::create(&tmp).with_context(|| format!("failed to create {}", tmp.display()))?;
+        file.write_all(bytes)?;
+        if let Ok(metadata) = std::fs::metadata(&path) {
+            file.set_permissions(metadata.permissions())?;
+        }
+        file.sync_all()?;
+        std::fs::rename(&tmp, &path).with_context(|| format!("failed to write {}", path.display()))
+    })();
+    if result.is_err() {
+        std::fs::remove_file(&tmp).ok();
+        return result;
+    }
+
+    // Persist the rename itself (directories cannot be synced on Windows)
+    #[cfg(unix)]
+    if let Some(dir) = path.parent().filter(|dir| !dir.as_os_str().is_empty()) {
+        std::fs::File::open(dir).and_then(|dir| dir.sync_all()).ok();
+    }
+    Ok(())
+}
+
+/// Converts an absolute path to a `file://` URI (percent-encoded)
+pub fn path_to_uri(path: &Path) -> String {
+    let path = path.to_string_lossy().replace('\\', "/");
+    let mut uri = String::from("file://");
+    if !path.starts_with('/') {
+        // Windows drive paths (C:/...)
+        uri.push('/');
+    }
+
+    for byte in path.bytes() {
+        match byte {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'.' | b'_' | b'~' | b'/' | b':' => {
+                uri.push(byte as char)
+            }
+            _ => uri.push_str(&format!("%{:02X}", byte)),
+        }
+    }
+    uri
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn temp_dir(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("workspace_{}_{}", name, std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    #[test]
+    fn test_open_file_reuses_buffer() {
+        let dir = temp_dir("open");
+        let file = dir.join("main.rs");
+        std::fs::write(&file, "fn main() {}\n").unwrap();
+
+        let mut workspace = Workspace::new();
+        let id = workspace.open_file(&file).unwrap();
+        assert_eq!(workspace.open_file(&dir.join(".").join("main.rs")).unwrap(), id);
+        assert_eq!(workspace.buffer_count(), 1);
+
+        let buffer = workspace.buffer(id).unwrap();
+        assert_eq!(*buffer.editor().language(), LanguageId::Rust);
+        assert!(buffer.uri().starts_with("file:///") && buffer.uri().ends_with("/main.rs"));
+        assert_eq!(workspace.find_by_uri(buffer.uri()), Some(id));
+        assert_eq!(workspace.find_by_path(&file), Some(id));
+        assert!(!buffer.is_dirty());
+
+        std::fs::remove_dir_all(dir).ok();
+    }
+
+    #[test]
+    fn test_open_file_decodes_editorconfig_charset() {
+        let dir = temp_dir("charset");
+        std::fs::write(dir.join(".editorconfig"), "root = true\n[*.txt]\ncharset = latin1\n").unwrap();
+        let file = dir.join("notes.txt");
+        std::fs::write(&file, [b'c', b'a', b'f', 0xE9, b'\n']).unwrap();
+
+        let mut workspace = Workspace::new();
+        let id = workspace.open_file(&file).unwrap();
+        let editor = workspace.buffer_mut(id).unwrap().editor_mut();
+        assert_eq!(editor.content(), "café\n");
+        assert_eq!(editor.prepare_save().unwrap(), std::fs::read(&file).unwrap());
+
+        std::fs::remove_dir_all(dir).ok();
+    }
+
+    #[test]
+    fn test_split_views_keep_own_cursors() {
+        let mut workspace = Workspace::new();
+        let id = workspace.open_untitled("one\ntwo\nthree\n", LanguageId::PlainText).unwrap();
+        let left = workspace.create_view(id).unwrap();
+        let right = workspace.create_view(id).unwrap();
+
+        workspace.activate_view(left).unwrap().move_cursor(Position::new(2, 3));
+        workspace.activate_view(right).unwrap().move_cursor(Position::new(1, 1));
+
+        assert_eq!(workspace.view_state(left).unwrap().cursor, Position::new(2, 3));
+        assert_eq!(workspace.view_state(right).unwrap().cursor, Position::new(1, 1));
+
+        // Edits through one view are seen by the other; its cursor is clamped
+        let editor = workspace.activate_view(right).unwrap();
+        editor.replace_range(Position::new(1, 0), Position::new(3, 0), "").unwrap();
+        let editor = workspace.activate_view(left).unwrap();
+        assert_eq!(editor.content(), "one\n");
+        assert_eq!(editor.cursor(), Position::new(1, 0));
+
+        assert_eq!(workspace.buffer(id).unwrap().views(), &[left, right]);
+    }
+
+    #[test]
+    fn test_write_atomic() {
+        let dir = temp_dir("atomic");
+        let file = dir.join("script.sh");
+        std::fs::write(&file, "old").unwrap();
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::{symlink, PermissionsExt};
+            std::fs::set_permissions(&file, std::fs::Permissions::from_mode(0o755)).unwrap();
+            symlink(&file, dir.join("link.sh")).unwrap();
+
+            // Written through the link; the file keeps its mode
+            write_atomic(&dir.join("link.sh"), b"new").unwrap();
+            assert!(std::fs::symlink_metadata(dir.join("link.sh")).unwrap().file_type().is_symlink());
+            assert_eq!(std::fs::metadata(&file).unwrap().permissions().mode() & 0o777, 0o755);
+        }
+        write_atomic(&file, b"new").unwrap();
+        assert_eq!(std::fs::read_to_string(&file).unwrap(), "new");
+
+        // No temporary files are left behind
+        let names: Vec<_> = std::fs::read_dir(&dir).unwrap().map(|e| e.unwrap().file_name()).collect();
+        assert!(names.iter().all(|name| !name.to_string_lossy().ends_with(".tmp")));
+        assert!(write_atomic(&dir.join("missing").join("file"), b"x").is_err());
+
+        std::fs::remove_dir_all(&dir).ok();
+    }
+
+    #[test]
+    fn test_close_with_prompt() {
+        let mut workspace = Workspace::new();
+        let id = workspace.open_untitled("", LanguageId::PlainText).unwrap();
+        let first = workspace.create_view(id).unwrap();
+        let second = workspace.create_view(id).unwrap();
+        workspace.activate_view(first).unwrap().insert_text("draft").unwrap();
+
+        // Other views remain: no prompt
+        assert_eq!(workspace.close_view(first).unwrap(), CloseOutcome::Closed);
+        // The remaining view keeps its own cursor
+        assert_eq!(workspace.view_state(second).unwrap().cursor, Position::new(0, 0));
+
+        // Last view of a dirty buffer
+        assert_eq!(workspace.close_view(second).unwrap(), CloseOutcome::NeedsConfirmation);
+        assert_eq!(workspace.pending_closes(), vec![id]);
+        assert_eq!(workspace.resolve_close(id, CloseChoice::Cancel).unwrap(), CloseOutcome::Cancelled);
+        assert!(workspace.pending_closes().is_empty());
+        assert!(workspace.view(second).is_some());
+
+        // Untitled buffers cannot be saved without a path
+        workspace.close_buffer(id).unwrap();
+        assert!(workspace.resolve_close(id, CloseChoice::Save).is_err());
+        assert_eq!(workspace.resolve_close(id, CloseChoice::Discard).unwrap(), CloseOutcome::Closed);
+        assert_eq!(workspace.buffer_count(), 0);
+        assert!(workspace.view(second).is_none());
+    }
+
+    #[test]
+    fn test_save_all_and_dirty_buffers() {
+        let dir = temp_dir("save");
+        let file = dir.join("notes.txt");
+        std::fs::write(&file, "old").unwrap();
+
+        let mut workspace = Workspace::new();
+        let saved = workspace.open_file(&file).unwrap();
+        let untitled = workspace.open_untitled("scratch", LanguageId::PlainText).unwrap();
+        workspace.buffer_mut(saved).unwrap().editor_mut().set_content("new\n").unwrap();
+
+        assert_eq!(workspace.dirty_buffers(), vec![saved, untitled]);
+        let report = workspace.save_all();
+        assert_eq!(report.saved, vec![saved]);
+        assert_eq!(report.failed.len(), 1);
+        assert_eq!(report.failed[0].buffer, untitled);
+        assert_eq!(std::fs::read_to_string(&file).unwrap(), "new\n");
+        assert_eq!(workspace.dirty_buffers(), vec![untitled]);
+
+        let target = dir.join("scratch.txt");
+        workspace.save_as(untitled, &target).unwrap();
+        assert_eq!(workspace.find_by_path(&target), Some(untitled));
+        assert!(workspace.dirty_buffers().is_empty());
+
+        std::fs::remove_dir_all(dir).ok();
+    }
+
+    #[test]
+    fn test_replace_all_and_completions_across_buffers() {
+        let mut workspace = Workspace::new();
+        let a = workspace.open_untitled("let widget = 1;\nwidget + 1", LanguageId::PlainText).unwrap();
+        let b = workspace.open_untitled("widget_factory()", LanguageId::PlainText).unwrap();
+        let c = workspace.open_untitled("nothing here", LanguageId::PlainText).unwrap();
+        workspace.buffer_mut(c).unwrap().editor_mut().set_read_only(true);
+
+        workspace.buffer_mut(a).unwrap().editor_mut().set_content("wid").unwrap();
+        let list = workspace.completions(a, Position::new(0, 3)).unwrap();
+        assert_eq!(list.items[0].label, "widget_factory");
+
+        let options = SearchOptions {
+            case_sensitive: true,
+            ..SearchOptions::default()
+        };
+        let results = workspace.replace_all("widget", "gadget", &options);
+        assert_eq!(results, vec![(b, 1)]);
+        assert_eq!(workspace.buffer(b).unwrap().editor().content(), "gadget_factory()");
+
+        // One undo step per buffer
+        assert!(workspace.buffer_mut(b).unwrap().editor_mut().undo().unwrap());
+        assert_eq!(workspace.buffer(b).unwrap().editor().content(), "widget_factory()");
+    }
+
+    #[test]
+    fn test_path_to_uri() {
+        assert_eq!(path_to_uri(Path::new("/home/me/my file.rs")), "file:///home/me/my%20file.rs");
+        assert_eq!(path_to_uri(Path::new("C:\\src\\main.rs")), "file:///C:/src/main.rs");
+    }
+}
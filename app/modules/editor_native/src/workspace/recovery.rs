@@ -0,0 +1,550 @@
+//! Hot exit and crash recovery
+//!
+//! While the app runs, `RecoveryJournal::write` is called periodically and
+//! stores the dirty buffers of a workspace in a session directory:
+//!
+//! ```text
+//! <root>/<session id>/session.json     manifest (buffers, clean exit flag)
+//! <root>/<session id>/buffer-<id>.json full snapshot (content, undo history)
+//! <root>/<session id>/buffer-<id>.log  edits since the snapshot (JSON lines)
+//! ```
+//!
+//! On a normal exit the session is either deleted or kept as a hot exit;
+//! after a crash it is simply left behind. On the next start the app lists
+//! the sessions, shows each buffer's diff against its file on disk, and
+//! restores or deletes them.
+
+use std::collections::HashMap;
+use std::fs::OpenOptions;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use anyhow::{anyhow, Context, Result};
+use serde::{Deserialize, Serialize};
+
+use crate::editor::{diff_hunks, DiffHunk, Editor, EditorSnapshot, JournalEntry};
+use crate::workspace::{write_atomic, BufferId, Workspace};
+
+/// Manifest file name inside a session directory
+const MANIFEST_FILE: &str = "session.json";
+
+/// How buffers are written to the journal.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RecoveryFormat {
+    /// Every write stores the whole buffer
+    FullContent,
+
+    /// A full snapshot, then only the edits made since (compacted into a
+    /// new snapshot every `max_log_entries` writes)
+    EditLog,
+}
+
+/// Recoverable buffer of a session.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RecoveredBufferInfo {
+    /// File name stem inside the session directory
+    pub key: String,
+    pub uri: String,
+    pub path: Option<String>,
+    pub language: String,
+
+    /// Buffer version at the last write
+    pub version: u64,
+    pub written_at_ms: u64,
+}
+
+/// Session manifest.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SessionManifest {
+    pub id: String,
+    pub started_at_ms: u64,
+    pub updated_at_ms: u64,
+
+    /// True for a hot exit, false if the app crashed (or is still running)
+    pub clean_exit: bool,
+    pub buffers: Vec<RecoveredBufferInfo>,
+}
+
+/// Journal state of one buffer
+struct JournaledBuffer {
+    key: String,
+    version: u64,
+    log_entries: usize,
+}
+
+/// Writer for the current session.
+pub struct RecoveryJournal {
+    session_dir: PathBuf,
+    manifest: SessionManifest,
+    format: RecoveryFormat,
+    max_log_entries: usize,
+    buffers: HashMap<BufferId, JournaledBuffer>,
+}
+
+impl RecoveryJournal {
+    /// Starts a new session under a journal directory
+    ///
+    /// Parameters:
+    /// - `root`: Journal directory (created if missing)
+    /// - `format`: Full content or edit log
+    pub fn create(root: &Path, format: RecoveryFormat) -> Result<Self> {
+        let started_at_ms = now_ms();
+        let id = format!("{}-{}", started_at_ms, std::process::id());
+        let session_dir = root.join(&id);
+        std::fs::create_dir_all(&session_dir)
+            .with_context(|| format!("failed to create {}", session_dir.display()))?;
+
+        let journal = Self {
+            session_dir,
+            manifest: SessionManifest {
+                id,
+                started_at_ms,
+                updated_at_ms: started_at_ms,
+                clean_exit: false,
+                buffers: Vec::new(),
+            },
+            format,
+            max_log_entries: 100,
+            buffers: HashMap::new(),
+        };
+        journal.write_manifest()?;
+        Ok(journal)
+    }
+
+    /// Gets the session id
+    pub fn session_id(&self) -> &str {
+        &self.manifest.id
+    }
+
+    /// Sets how many log writes are appended before compacting into a new
+    /// snapshot (edit log format)
+    pub fn set_max_log_entries(&mut self, max_log_entries: usize) {
+        self.max_log_entries = max_log_entries.max(1);
+    }
+
+    /// Writes the dirty buffers that changed since the last write
+    ///
+    /// Buffers that were saved or closed are removed from the journal.
+    ///
+    /// Returns: Number of buffers written
+    pub fn write(&mut self, workspace: &mut Workspace) -> Result<usize> {
+        let dirty: Vec<BufferId> = workspace.dirty_buffers();
+
+        // Forget saved and closed buffers
+        let stale: Vec<BufferId> = self.buffers.keys().copied().filter(|id| !dirty.contains(id)).collect();
+        for id in &stale {
+            if let Some(buffer) = self.buffers.remove(id) {
+                self.remove_buffer_files(&buffer.key);
+                self.manifest.buffers.retain(|info| info.key != buffer.key);
+            }
+        }
+
+        let mut written = 0;
+        for id in dirty {
+            let Some(buffer) = workspace.buffer_mut(id) else {
+                continue;
+            };
+            let version = buffer.editor().version();
+            if self.buffers.get(&id).is_some_and(|b| b.version == version) {
+                continue;
+            }
+
+            let info = RecoveredBufferInfo {
+                key: format!("buffer-{}", id),
+                uri: buffer.uri().to_string(),
+                path: buffer.path().map(|path| path.display().to_string()),
+                language: buffer.editor().language().name().to_string(),
+                version,
+                written_at_ms: now_ms(),
+            };
+            let editor = buffer.editor_mut();
+
+            let log_entry = match (self.format, self.buffers.get(&id)) {
+                (RecoveryFormat::EditLog, Some(journaled)) if journaled.log_entries < self.max_log_entries => {
+                    editor.take_edit_journal()
+                }
+                _ => None,
+            };
+
+            match log_entry {
+                Some(entry) => {
+                    self.append_log(&info.key, &entry)?;
+                    let journaled = self.buffers.get_mut(&id).expect("checked above");
+                    journaled.version = version;
+                    journaled.log_entries += 1;
+                }
+                None => {
+                    if self.format == RecoveryFormat::EditLog {
+                        editor.start_edit_journal();
+                    }
+                    self.write_snapshot(&info.key, &editor.snapshot())?;
+                    self.buffers.insert(
+                        id,
+                        JournaledBuffer {
+                            key: info.key.clone(),
+                            version,
+                            log_entries: 0,
+                        },
+                    );
+                }
+            }
+
+            self.manifest.buffers.retain(|existing| existing.key != info.key);
+            self.manifest.buffers.push(info);
+            written += 1;
+        }
+
+        if written > 0 || !stale.is_empty() {
+            self.manifest.updated_at_ms = now_ms();
+            self.write_manifest()?;
+        }
+        Ok(written)
+    }
+
+    /// Ends the session
+    ///
+    /// With `hot_exit`, the dirty buffers are written and the session is
+    /// kept for the next start; otherwise it is deleted.
+    pub fn finish(mut self, workspace: &mut Workspace, hot_exit: bool) -> Result<()> {
+        if !hot_exit || workspace.dirty_buffers().is_empty() {
+            return std::fs::remove_dir_all(&self.session_dir)
+                .with_context(|| format!("failed to remove {}", self.session_dir.display()));
+        }
+
+        self.write(workspace)?;
+        self.manifest.clean_exit = true;
+        self.manifest.updated_at_ms = now_ms();
+        self.write_manifest()
+    }
+
+    /// Helper: Writes the manifest atomically
+    fn write_manifest(&self) -> Result<()> {
+        write_atomic(&self.session_dir.join(MANIFEST_FILE), &serde_json::to_vec(&self.manifest)?)
+    }
+
+    /// Helper: Writes a full snapshot and drops the edit log
+    fn write_snapshot(&self, key: &str, snapshot: &EditorSnapshot) -> Result<()> {
+        write_atomic(&buffer_path(&self.session_dir, key, "json")?, &serde_json::to_vec(snapshot)?)?;
+
+        let log = buffer_path(&self.session_dir, key, "log")?;
+        if log.exists() {
+            std::fs::remove_file(&log).with_context(|| format!("failed to remove {}", log.display()))?;
+        }
+        Ok(())
+    }
+
+    /// Helper: Appends an entry to a buffer's edit log
+    fn append_log(&self, key: &str, entry: &JournalEntry) -> Result<()> {
+        let path = buffer_path(&self.session_dir, key, "log")?;
+        let mut line = serde_json::to_vec(entry)?;
+        line.push(b'\n');
+
+        let mut file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&path)
+            .with_context(|| format!("failed to open {}", path.display()))?;
+        file.write_all(&line)?;
+        file.sync_data()?;
+        Ok(())
+    }
+
+    /// Helper: Removes a buffer's snapshot and log
+    fn remove_buffer_files(&self, key: &str) {
+        for extension in ["json", "log"] {
+            if let Ok(path) = buffer_path(&self.session_dir, key, extension) {
+                std::fs::remove_file(path).ok();
+            }
+        }
+    }
+}
+
+/// Lists the recoverable sessions, newest first
+///
+/// Sessions without buffers and unreadable manifests are skipped.
+///
+/// Parameters:
+/// - `root`: Journal directory
+/// - `exclude`: Session to leave out (usually the running one)
+pub fn list_sessions(root: &Path, exclude: Option<&str>) -> Result<Vec<SessionManifest>> {
+    if !root.exists() {
+        return Ok(Vec::new());
+    }
+
+    let mut sessions = Vec::new();
+    for entry in std::fs::read_dir(root).with_context(|| format!("failed to read {}", root.display()))? {
+        let path = entry?.path().join(MANIFEST_FILE);
+        let Ok(bytes) = std::fs::read(&path) else {
+            continue;
+        };
+        let Ok(manifest) = serde_json::from_slice::<SessionManifest>(&bytes) else {
+            continue;
+        };
+        if !manifest.buffers.is_empty() && Some(manifest.id.as_str()) != exclude {
+            sessions.push(manifest);
+        }
+    }
+
+    sessions.sort_by_key(|s| std::cmp::Reverse(s.started_at_ms));
+    Ok(sessions)
+}
+
+/// Loads a recovered buffer (snapshot plus its edit log)
+///
+/// A truncated last log line (crash during a write) is ignored.
+pub fn load_buffer(root: &Path, session_id: &str, key: &str) -> Result<Editor> {
+    let session_dir = session_path(root, session_id)?;
+    let snapshot_path = buffer_path(&session_dir, key, "json")?;
+    let bytes = std::fs::read(&snapshot_path).with_context(|| format!("failed to read {}", snapshot_path.display()))?;
+    let mut editor = Editor::from_snapshot(serde_json::from_slice(&bytes)?)?;
+
+    if let Ok(log) = std::fs::read_to_string(buffer_path(&session_dir, key, "log")?) {
+        for line in log.lines().filter(|line| !line.trim().is_empty()) {
+            let Ok(entry) = serde_json::from_str::<JournalEntry>(line) else {
+                break;
+            };
+            editor.replay_journal(&entry)?;
+        }
+    }
+
+    Ok(editor)
+}
+
+/// Diffs a recovered buffer against its file on disk
+///
+/// A missing file (or an untitled buffer) compares against empty text.
+///
+/// Parameters:
+/// - `root`: Journal directory
+/// - `session_id`: Session
+/// - `key`: Buffer key (see `RecoveredBufferInfo`)
+/// - `context`: Unchanged lines kept around each change
+///
+/// Returns: Hunks from the disk content to the recovered content
+pub fn diff_with_disk(root: &Path, session_id: &str, key: &str, context: usize) -> Result<Vec<DiffHunk>> {
+    let manifest = read_manifest(root, session_id)?;
+    let info = manifest
+        .buffers
+        .iter()
+        .find(|info| info.key == key)
+        .ok_or_else(|| anyhow!("no buffer {} in session {}", key, session_id))?;
+
+    let disk = match &info.path {
+        Some(path) => std::fs::read(path).map(|bytes| String::from_utf8_lossy(&bytes).into_owned()).unwrap_or_default(),
+        None => String::new(),
+    };
+    let editor = load_buffer(root, session_id, key)?;
+    Ok(diff_hunks(&disk, &editor.content(), context))
+}
+
+/// Restores every buffer of a session into a workspace
+///
+/// Files that are already open (and unchanged) take the recovered content;
+/// the session itself is left on disk until `delete_session`.
+///
+/// Returns: Restored buffer ids
+pub fn restore_session(root: &Path, session_id: &str, workspace: &mut Workspace) -> Result<Vec<BufferId>> {
+    let manifest = read_manifest(root, session_id)?;
+
+    let mut restored = Vec::new();
+    for info in &manifest.buffers {
+        let editor = load_buffer(root, session_id, &info.key)?;
+        let path = info.path.as_ref().map(PathBuf::from);
+        restored.push(workspace.open_recovered(info.uri.clone(), path, editor)?);
+    }
+    Ok(restored)
+}
+
+/// Deletes a session directory
+pub fn delete_session(root: &Path, session_id: &str) -> Result<()> {
+    let dir = session_path(root, session_id)?;
+    std::fs::remove_dir_all(&dir).with_context(|| format!("failed to remove {}", dir.display()))
+}
+
+/// Helper: Reads a session manifest
+fn read_manifest(root: &Path, session_id: &str) -> Result<SessionManifest> {
+    let path = session_path(root, session_id)?.join(MANIFEST_FILE);
+    let bytes = std::fs::read(&path).with_context(|| format!("failed to read {}", path.display()))?;
+    Ok(serde_json::from_slice(&bytes)?)
+}
+
+/// Helper: Resolves a session directory (ids are plain names, not paths)
+fn session_path(root: &Path, session_id: &str) -> Result<PathBuf> {
+    if !is_plain_name(session_id) {
+        return Err(anyhow!("invalid session id {:?}", session_id));
+    }
+    Ok(root.join(session_id))
+}
+
+/// Helper: Resolves a buffer file in a session (keys are plain names too)
+fn buffer_path(session_dir: &Path, key: &str, extension: &str) -> Result<PathBuf> {
+    if !is_plain_name(key) {
+        return Err(anyhow!("invalid buffer key {:?}", key));
+    }
+    Ok(session_dir.join(format!("{}.{}", key, extension)))
+}
+
+/// Helper: Checks that a name cannot leave its directory
+fn is_plain_name(name: &str) -> bool {
+    !name.is_empty() && name.chars().all(|c| c.is_ascii_alphanumeric() || c == '-')
+}
+
+/// Helper: Milliseconds since the Unix epoch
+fn now_ms() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_millis() as u64)
+        .unwrap_or_default()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::editor::{LanguageId, Position};
+
+    fn temp_root(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("recovery_{}_{}", name, std::process::id()));
+        std::fs::remove_dir_all(&dir).ok();
+        dir
+    }
+
+    #[test]
+    fn test_crash_and_restore_full_content() {
+        let root = temp_root("full");
+        let file = root.with_extension("txt");
+        std::fs::write(&file, "one\ntwo\n").unwrap();
+
+        let mut workspace = Workspace::new();
+        let id = workspace.open_file(&file).unwrap();
+        let mut journal = RecoveryJournal::create(&root, RecoveryFormat::FullContent).unwrap();
+        assert_eq!(journal.write(&mut workspace).unwrap(), 0);
+
+        let editor = workspace.buffer_mut(id).unwrap().editor_mut();
+        editor.move_cursor(Position::new(1, 3));
+        editor.insert_text("!").unwrap();
+        assert_eq!(journal.write(&mut workspace).unwrap(), 1);
+        assert_eq!(journal.write(&mut workspace).unwrap(), 0);
+
+        // Crash: the journal is dropped without finishing
+        let session = journal.session_id().to_string();
+        drop(journal);
+        drop(workspace);
+
+        let sessions = list_sessions(&root, None).unwrap();
+        assert_eq!(sessions.len(), 1);
+        assert!(!sessions[0].clean_exit);
+        let key = sessions[0].buffers[0].key.clone();
+
+        let hunks = diff_with_disk(&root, &session, &key, 0).unwrap();
+        assert_eq!(hunks.len(), 1);
+        assert_eq!(hunks[0].lines[1].text, "two!");
+
+        let mut workspace = Workspace::new();
+        let restored = restore_session(&root, &session, &mut workspace).unwrap();
+        let buffer = workspace.buffer(restored[0]).unwrap();
+        assert_eq!(buffer.editor().content(), "one\ntwo!\n");
+        assert_eq!(buffer.editor().cursor(), Position::new(1, 4));
+        assert!(buffer.is_dirty());
+        assert_eq!(workspace.find_by_path(&file), Some(restored[0]));
+
+        delete_session(&root, &session).unwrap();
+        assert!(list_sessions(&root, None).unwrap().is_empty());
+        std::fs::remove_file(file).ok();
+        std::fs::remove_dir_all(root).ok();
+    }
+
+    #[test]
+    fn test_keys_cannot_leave_the_session() {
+        let root = temp_root("keys");
+        let session_dir = root.join("session");
+        for key in ["../outside", "a/b", "", "..\\outside"] {
+            assert!(buffer_path(&session_dir, key, "json").is_err());
+            let error = load_buffer(&root, "session", key).err().unwrap();
+            assert!(error.to_string().contains("invalid buffer key"));
+        }
+        assert_eq!(buffer_path(&session_dir, "buffer-1", "log").unwrap(), session_dir.join("buffer-1.log"));
+    }
+
+    #[test]
+    fn test_edit_log_compaction_and_undo() {
+        let root = temp_root("log");
+        let mut workspace = Workspace::new();
+        let id = workspace.open_untitled("", LanguageId::PlainText).unwrap();
+        let mut journal = RecoveryJournal::create(&root, RecoveryFormat::EditLog).unwrap();
+        journal.set_max_log_entries(2);
+
+        for word in ["a", "b", "c", "d"] {
+            workspace.buffer_mut(id).unwrap().editor_mut().insert_text(word).unwrap();
+            journal.write(&mut workspace).unwrap();
+        }
+        workspace.buffer_mut(id).unwrap().editor_mut().undo().unwrap();
+        journal.write(&mut workspace).unwrap();
+
+        // Snapshot after "a", log "b" "c", new snapshot after "d", log the undo
+        let session_dir = root.join(journal.session_id());
+        let log = std::fs::read_to_string(session_dir.join("buffer-1.log")).unwrap();
+        assert_eq!(log.lines().count(), 1);
+
+        let mut editor = load_buffer(&root, journal.session_id(), "buffer-1").unwrap();
+        assert_eq!(editor.content(), "abc");
+        assert_eq!(editor.version(), workspace.buffer(id).unwrap().editor().version());
+        assert!(editor.undo().unwrap());
+        assert_eq!(editor.content(), "abcd");
+
+        std::fs::remove_dir_all(root).ok();
+    }
+
+    #[test]
+    fn test_saved_buffers_leave_the_journal() {
+        let root = temp_root("saved");
+        let file = root.with_extension("md");
+        std::fs::write(&file, "# Title\n").unwrap();
+
+        let mut workspace = Workspace::new();
+        let id = workspace.open_file(&file).unwrap();
+        let mut journal = RecoveryJournal::create(&root, RecoveryFormat::EditLog).unwrap();
+
+        workspace.buffer_mut(id).unwrap().editor_mut().insert_text("x").unwrap();
+        journal.write(&mut workspace).unwrap();
+        assert_eq!(list_sessions(&root, None).unwrap().len(), 1);
+        assert!(list_sessions(&root, Some(journal.session_id())).unwrap().is_empty());
+
+        workspace.save(id).unwrap();
+        journal.write(&mut workspace).unwrap();
+        assert!(list_sessions(&root, None).unwrap().is_empty());
+        assert!(!root.join(journal.session_id()).join("buffer-1.json").exists());
+
+        // A normal exit deletes the session
+        journal.finish(&mut workspace, false).unwrap();
+        assert_eq!(std::fs::read_dir(&root).unwrap().count(), 0);
+
+        std::fs::remove_file(file).ok();
+        std::fs::remove_dir_all(root).ok();
+    }
+
+    #[test]
+    fn test_hot_exit_keeps_session() {
+        let root = temp_root("hot");
+        let mut workspace = Workspace::new();
+        let id = workspace.open_untitled("draft", LanguageId::Markdown).unwrap();
+        workspace.buffer_mut(id).unwrap().editor_mut().insert_text("# ").unwrap();
+
+        let journal = RecoveryJournal::create(&root, RecoveryFormat::FullContent).unwrap();
+        journal.finish(&mut workspace, true).unwrap();
+
+        let sessions = list_sessions(&root, None).unwrap();
+        assert!(sessions[0].clean_exit);
+        assert_eq!(sessions[0].buffers[0].uri, "untitled:Untitled-1");
+        assert_eq!(sessions[0].buffers[0].language, "markdown");
+
+        let mut workspace = Workspace::new();
+        let restored = restore_session(&root, &sessions[0].id, &mut workspace).unwrap();
+        assert_eq!(workspace.buffer(restored[0]).unwrap().editor().content(), "# draft");
+        assert!(restore_session(&root, "../escape", &mut workspace).is_err());
+
+        std::fs::remove_dir_all(root).ok();
+    }
+}
@@ -0,0 +1,138 @@
+use anyhow::{Context, Result};
+use serde::Deserialize;
+use serde_json::Value;
+use std::path::Path;
+use std::time::Duration;
+
+/// Environment variable naming the bridge settings file
+pub const SETTINGS_ENV: &str = "LSP_BRIDGE_SETTINGS";
+
+/// Bridge configuration, read from a JSON settings file.
+///
+/// ```json
+/// {
+///   "clientRequestTimeoutMs": 30000,
+///   "settings": { "dart": { "lineLength": 100 } }
+/// }
+/// ```
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct BridgeConfig {
+    /// How long to wait for a client to answer a server request
+    #[serde(default = "default_client_request_timeout_ms")]
+    pub client_request_timeout_ms: u64,
+    /// Answers to `workspace/configuration`, by section
+    ///
+    /// When unset, servers' configuration requests go to the client.
+    #[serde(default)]
+    pub settings: Option<Value>,
+}
+
+fn default_client_request_timeout_ms() -> u64 {
+    30_000
+}
+
+impl Default for BridgeConfig {
+    fn default() -> Self {
+        Self {
+            client_request_timeout_ms: default_client_request_timeout_ms(),
+            settings: None,
+        }
+    }
+}
+
+impl BridgeConfig {
+    /// Loads the configuration from a settings file
+    pub fn load(path: &Path) -> Result<Self> {
+        let content = std::fs::read_to_string(path)
+            .with_context(|| format!("Failed to read settings file {}", path.display()))?;
+        serde_json::from_str(&content)
+            .with_context(|| format!("Invalid settings file {}", path.display()))
+    }
+
+    /// Loads the settings file named by `LSP_BRIDGE_SETTINGS`
+    ///
+    /// Returns: Default configuration if the variable is not set
+    pub fn from_env() -> Result<Self> {
+        match std::env::var_os(SETTINGS_ENV) {
+            Some(path) => Self::load(Path::new(&path)),
+            None => Ok(Self::default()),
+        }
+    }
+
+    /// Gets the client request timeout
+    pub fn client_request_timeout(&self) -> Duration {
+        Duration::from_millis(self.client_request_timeout_ms)
+    }
+
+    /// Looks up a configuration section
+    ///
+    /// Dotted sections (`python.analysis`) walk nested objects; a key
+    /// containing the dots itself is tried first. No section returns all
+    /// settings.
+    ///
+    /// Returns: Section value, or None if no settings are configured
+    pub fn section(&self, section: Option<&str>) -> Option<Value> {
+        let settings = self.settings.as_ref()?;
+        let Some(section) = section.filter(|s| !s.is_empty()) else {
+            return Some(settings.clone());
+        };
+
+        if let Some(value) = settings.get(section) {
+            return Some(value.clone());
+        }
+        let value = section
+            .split('.')
+            .try_fold(settings, |value, key| value.get(key))
+            .cloned()
+            .unwrap_or(Value::Null);
+        Some(value)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_default_config() {
+        let config = BridgeConfig::default();
+        assert_eq!(config.client_request_timeout(), Duration::from_secs(30));
+        assert!(config.section(Some("dart")).is_none());
+    }
+
+    #[test]
+    fn test_parse_config() {
+        let config: BridgeConfig = serde_json::from_value(json!({
+            "clientRequestTimeoutMs": 500,
+            "settings": {"dart": {"lineLength": 100}}
+        }))
+        .unwrap();
+        assert_eq!(config.client_request_timeout(), Duration::from_millis(500));
+
+        let config: BridgeConfig = serde_json::from_value(json!({})).unwrap();
+        assert_eq!(config.client_request_timeout_ms, 30_000);
+    }
+
+    #[test]
+    fn test_section_lookup() {
+        let config = BridgeConfig {
+            settings: Some(json!({
+                "python": {"analysis": {"typeCheckingMode": "strict"}},
+                "rust-analyzer.cargo": {"features": "all"}
+            })),
+            ..Default::default()
+        };
+
+        assert_eq!(config.section(Some("python.analysis")).unwrap(), json!({"typeCheckingMode": "strict"}));
+        assert_eq!(config.section(Some("rust-analyzer.cargo")).unwrap(), json!({"features": "all"}));
+        assert_eq!(config.section(Some("dart")).unwrap(), Value::Null);
+        assert_eq!(config.section(None).unwrap(), config.settings.clone().unwrap());
+    }
+
+    #[test]
+    fn test_load_missing_file() {
+        assert!(BridgeConfig::load(Path::new("/nonexistent/lsp_bridge.json")).is_err());
+    }
+}
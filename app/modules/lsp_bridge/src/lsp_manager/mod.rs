@@ -1,30 +1,74 @@
 use std::collections::HashMap;
-use std::sync::Arc;
-use tokio::sync::RwLock;
+use std::sync::{Arc, Weak};
+use std::sync::atomic::{AtomicU64, Ordering};
+use serde_json::Value;
+use tokio::sync::{Mutex, RwLock, mpsc};
 use uuid::Uuid;
 use anyhow::{Result, anyhow};
-use tracing::{info, warn};
+use tracing::{debug, info, warn};
 
+use crate::config::BridgeConfig;
 use crate::servers::LspServerInstance;
 
+mod server_requests;
+
+use server_requests::PendingClientRequest;
+
+/// Identifies a connected client
+pub type ClientId = u64;
+
+/// Channel of JSON messages pushed to one client
+pub type ClientSender = mpsc::UnboundedSender<String>;
+
+/// Clients subscribed to each session
+type Subscriptions = Arc<RwLock<HashMap<String, HashMap<ClientId, ClientSender>>>>;
+
+/// Server requests relayed to clients, by bridge request id
+type ClientRequests = Arc<Mutex<HashMap<String, PendingClientRequest>>>;
+
 /// Manages LSP server instances for different languages.
 ///
 /// This is the core component that:
 /// - Starts/stops LSP server processes
 /// - Routes requests to appropriate servers
 /// - Manages server lifecycle
+/// - Fans server notifications out to the clients subscribed to a session
+/// - Answers server requests, itself or through the session's owning client
 #[derive(Clone)]
 pub struct LspManager {
-    servers: Arc<RwLock<HashMap<String, LspServerInstance>>>,
+    servers: Arc<RwLock<HashMap<String, Arc<LspServerInstance>>>>,
+    subscriptions: Subscriptions,
+    /// First client subscribed to each session, which server requests go to
+    owners: Arc<RwLock<HashMap<String, ClientId>>>,
+    client_requests: ClientRequests,
+    next_client_id: Arc<AtomicU64>,
+    next_request_id: Arc<AtomicU64>,
+    config: Arc<BridgeConfig>,
 }
 
 impl LspManager {
     pub fn new() -> Self {
+        Self::with_config(BridgeConfig::default())
+    }
+
+    /// Creates a manager using a bridge configuration
+    pub fn with_config(config: BridgeConfig) -> Self {
         Self {
             servers: Arc::new(RwLock::new(HashMap::new())),
+            subscriptions: Arc::new(RwLock::new(HashMap::new())),
+            owners: Arc::new(RwLock::new(HashMap::new())),
+            client_requests: Arc::new(Mutex::new(HashMap::new())),
+            next_client_id: Arc::new(AtomicU64::new(1)),
+            next_request_id: Arc::new(AtomicU64::new(1)),
+            config: Arc::new(config),
         }
     }
 
+    /// Allocates an id for a new client connection
+    pub fn new_client_id(&self) -> ClientId {
+        self.next_client_id.fetch_add(1, Ordering::Relaxed)
+    }
+
     /// Initializes an LSP server for a language
     pub async fn initialize_server(
         &self,
@@ -36,23 +80,43 @@ impl LspManager {
         info!("Initializing LSP server for language: {}, session: {}", language_id, session_id);
 
         // Create LSP server instance based on language
-        let server = LspServerInstance::create(language_id, root_uri).await?;
-
-        // Store server instance
-        let mut servers = self.servers.write().await;
-        servers.insert(session_id.clone(), server);
+        let (messages, messages_rx) = mpsc::unbounded_channel();
+        let server = LspServerInstance::create(language_id, root_uri, messages).await?;
+        self.add_session(session_id.clone(), server, messages_rx).await;
 
         info!("LSP server initialized successfully: {}", session_id);
         Ok(session_id)
     }
 
+    /// Stores a server and starts handling the messages it sends
+    async fn add_session(
+        &self,
+        session_id: String,
+        server: LspServerInstance,
+        messages: mpsc::UnboundedReceiver<Value>,
+    ) {
+        let server = Arc::new(server);
+        tokio::spawn(forward_server_messages(
+            self.clone(),
+            session_id.clone(),
+            Arc::downgrade(&server),
+            messages,
+        ));
+
+        let mut servers = self.servers.write().await;
+        servers.insert(session_id, server);
+    }
+
     /// Shuts down an LSP server
     pub async fn shutdown_server(&self, session_id: &str) -> Result<()> {
         info!("Shutting down LSP server: {}", session_id);
 
-        let mut servers = self.servers.write().await;
+        let removed = self.servers.write().await.remove(session_id);
+        self.subscriptions.write().await.remove(session_id);
+        self.owners.write().await.remove(session_id);
+        self.client_requests.lock().await.retain(|_, request| !request.is_from(session_id));
 
-        if let Some(mut server) = servers.remove(session_id) {
+        if let Some(server) = removed {
             server.shutdown().await?;
             info!("LSP server shut down successfully: {}", session_id);
             Ok(())
@@ -63,19 +127,15 @@ impl LspManager {
     }
 
     /// Sends a request to an LSP server
+    ///
+    /// Requests to the same server may run concurrently.
     pub async fn send_request(
         &self,
         session_id: &str,
         method: &str,
         params: serde_json::Value,
     ) -> Result<serde_json::Value> {
-        let servers = self.servers.read().await;
-
-        if let Some(server) = servers.get(session_id) {
-            server.send_request(method, params).await
-        } else {
-            Err(anyhow!("Session not found: {}", session_id))
-        }
+        self.server(session_id).await?.send_request(method, params).await
     }
 
     /// Sends a notification to an LSP server
@@ -85,13 +145,93 @@ impl LspManager {
         method: &str,
         params: serde_json::Value,
     ) -> Result<()> {
-        let servers = self.servers.read().await;
+        self.server(session_id).await?.send_notification(method, params).await
+    }
 
-        if let Some(server) = servers.get(session_id) {
-            server.send_notification(method, params).await
-        } else {
-            Err(anyhow!("Session not found: {}", session_id))
+    /// Subscribes a client to the notifications of a session
+    ///
+    /// The first subscriber owns the session: server requests go to it.
+    pub async fn subscribe(&self, session_id: &str, client_id: ClientId, sender: ClientSender) -> Result<()> {
+        if !self.servers.read().await.contains_key(session_id) {
+            return Err(anyhow!("Session not found: {}", session_id));
         }
+
+        self.subscriptions
+            .write()
+            .await
+            .entry(session_id.to_string())
+            .or_default()
+            .insert(client_id, sender);
+        self.owners.write().await.entry(session_id.to_string()).or_insert(client_id);
+        Ok(())
+    }
+
+    /// Unsubscribes a client from a session
+    ///
+    /// Server requests of the session waiting for the client's answer fall
+    /// back at once.
+    ///
+    /// Returns: true if the client was subscribed
+    pub async fn unsubscribe(&self, session_id: &str, client_id: ClientId) -> bool {
+        self.client_requests
+            .lock()
+            .await
+            .retain(|_, request| !(request.is_for(client_id) && request.is_from(session_id)));
+
+        {
+            let mut owners = self.owners.write().await;
+            if owners.get(session_id) == Some(&client_id) {
+                owners.remove(session_id);
+            }
+        }
+
+        let mut subscriptions = self.subscriptions.write().await;
+        subscriptions
+            .get_mut(session_id)
+            .is_some_and(|clients| clients.remove(&client_id).is_some())
+    }
+
+    /// Unsubscribes a client from all sessions (on disconnect)
+    ///
+    /// Server requests waiting for the client's answer fall back at once.
+    pub async fn unsubscribe_all(&self, client_id: ClientId) {
+        self.owners.write().await.retain(|_, owner| *owner != client_id);
+        self.client_requests.lock().await.retain(|_, request| !request.is_for(client_id));
+
+        let mut subscriptions = self.subscriptions.write().await;
+        for clients in subscriptions.values_mut() {
+            clients.remove(&client_id);
+        }
+    }
+
+    /// Picks the client that server requests of a session go to
+    ///
+    /// Returns: The owner, or else the longest-connected subscriber
+    async fn request_target(&self, session_id: &str) -> Option<(ClientId, ClientSender)> {
+        let owner = self.owners.read().await.get(session_id).copied();
+        let subscriptions = self.subscriptions.read().await;
+        let clients = subscriptions.get(session_id)?;
+
+        owner
+            .and_then(|owner| clients.get(&owner).map(|sender| (owner, sender.clone())))
+            .or_else(|| {
+                clients
+                    .iter()
+                    .min_by_key(|(client_id, _)| **client_id)
+                    .map(|(client_id, sender)| (*client_id, sender.clone()))
+            })
+    }
+
+    /// Gets the server of a session
+    ///
+    /// The map lock is released before the caller talks to the server, so
+    /// slow requests do not block other sessions or shutdowns.
+    async fn server(&self, session_id: &str) -> Result<Arc<LspServerInstance>> {
+        let servers = self.servers.read().await;
+        servers
+            .get(session_id)
+            .cloned()
+            .ok_or_else(|| anyhow!("Session not found: {}", session_id))
     }
 
     /// Gets active session count
@@ -102,6 +242,60 @@ impl LspManager {
     }
 }
 
+/// Handles the notifications and requests a session's server sends
+///
+/// Notifications are pushed to the subscribed clients, tagged with the
+/// session id (see `tag_with_session`) like the requests clients send. Each
+/// request is answered in its own task. Runs until the server's reader
+/// stops.
+async fn forward_server_messages(
+    manager: LspManager,
+    session_id: String,
+    server: Weak<LspServerInstance>,
+    mut messages: mpsc::UnboundedReceiver<Value>,
+) {
+    while let Some(message) = messages.recv().await {
+        if message.get("id").is_some() {
+            tokio::spawn(server_requests::handle_server_request(
+                manager.clone(),
+                session_id.clone(),
+                server.clone(),
+                message,
+            ));
+            continue;
+        }
+
+        let message = tag_with_session(message, &session_id).to_string();
+
+        let subscriptions = manager.subscriptions.read().await;
+        let Some(clients) = subscriptions.get(&session_id).filter(|clients| !clients.is_empty()) else {
+            debug!("No client subscribed to session {}, dropping notification", session_id);
+            continue;
+        };
+        for sender in clients.values() {
+            // Disconnected clients are removed by their connection handler
+            let _ = sender.send(message.clone());
+        }
+    }
+}
+
+/// Adds the session id to a message
+///
+/// Object params get `params.sessionId`; other params (arrays, none) are
+/// left intact and the id goes on the message itself (`sessionId`).
+fn tag_with_session(mut notification: Value, session_id: &str) -> Value {
+    let session_id = Value::String(session_id.to_string());
+    match notification.get_mut("params") {
+        Some(Value::Object(params)) => {
+            params.insert("sessionId".to_string(), session_id);
+        }
+        _ => {
+            notification["sessionId"] = session_id;
+        }
+    }
+    notification
+}
+
 impl Default for LspManager {
     fn default() -> Self {
         Self::new()
@@ -111,17 +305,21 @@ impl Default for LspManager {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::servers::{read_lsp_message, write_lsp_message};
+    use serde_json::json;
+    use std::time::Duration;
+    use tokio::io::{BufReader, DuplexStream};
 
     #[test]
     fn test_lsp_manager_new() {
-        let manager = LspManager::new();
+        let _manager = LspManager::new();
         // Manager should be created successfully
         // Can't directly check servers map, but creation should not panic
     }
 
     #[test]
     fn test_lsp_manager_default() {
-        let manager = LspManager::default();
+        let _manager = LspManager::default();
         // Default should work same as new()
     }
 
@@ -156,6 +354,245 @@ mod tests {
         assert!(result.is_err());
     }
 
+    #[tokio::test]
+    async fn test_subscribe_nonexistent_session() {
+        let manager = LspManager::new();
+        let (sender, _receiver) = mpsc::unbounded_channel();
+        let client_id = manager.new_client_id();
+
+        assert!(manager.subscribe("nonexistent", client_id, sender).await.is_err());
+        assert!(!manager.unsubscribe("nonexistent", client_id).await);
+    }
+
+    #[test]
+    fn test_tag_with_session() {
+        let notification = json!({"jsonrpc": "2.0", "method": "$/progress", "params": {"token": 1}});
+        let tagged = tag_with_session(notification, "s1");
+        assert_eq!(tagged["params"], json!({"token": 1, "sessionId": "s1"}));
+
+        let notification = json!({"jsonrpc": "2.0", "method": "custom/ping"});
+        let tagged = tag_with_session(notification, "s1");
+        assert_eq!(tagged, json!({"jsonrpc": "2.0", "method": "custom/ping", "sessionId": "s1"}));
+
+        let notification = json!({"jsonrpc": "2.0", "method": "custom/list", "params": [1, 2]});
+        let tagged = tag_with_session(notification, "s1");
+        assert_eq!(tagged["params"], json!([1, 2]));
+        assert_eq!(tagged["sessionId"], "s1");
+    }
+
+    /// Helper: Adds a session whose fake server is driven by the test
+    ///
+    /// Returns: What the server reads, and where it writes
+    async fn fake_session(manager: &LspManager, session_id: &str) -> (BufReader<DuplexStream>, DuplexStream) {
+        let (to_server, from_instance) = tokio::io::duplex(4096);
+        let (to_instance, from_server) = tokio::io::duplex(4096);
+        let (messages, messages_rx) = mpsc::unbounded_channel();
+        let server = LspServerInstance::from_io("test", from_server, to_server, None, messages);
+        manager.add_session(session_id.to_string(), server, messages_rx).await;
+        (BufReader::new(from_instance), to_instance)
+    }
+
+    /// Helper: Subscribes a new client to a session
+    async fn subscribed_client(
+        manager: &LspManager,
+        session_id: &str,
+    ) -> (ClientId, mpsc::UnboundedReceiver<String>) {
+        let (sender, receiver) = mpsc::unbounded_channel();
+        let client_id = manager.new_client_id();
+        manager.subscribe(session_id, client_id, sender).await.unwrap();
+        (client_id, receiver)
+    }
+
+    #[tokio::test]
+    async fn test_notifications_fan_out_to_subscribers() {
+        let manager = LspManager::new();
+        let (_server_in, mut to_instance) = fake_session(&manager, "s1").await;
+
+        let mut clients = Vec::new();
+        for _ in 0..3 {
+            let (sender, receiver) = mpsc::unbounded_channel();
+            clients.push((manager.new_client_id(), sender, receiver));
+        }
+        for (client_id, sender, _) in &clients[..2] {
+            manager.subscribe("s1", *client_id, sender.clone()).await.unwrap();
+        }
+        assert!(manager.unsubscribe("s1", clients[1].0).await);
+        manager.subscribe("s1", clients[1].0, clients[1].1.clone()).await.unwrap();
+
+        let notification = json!({
+            "jsonrpc": "2.0",
+            "method": "textDocument/publishDiagnostics",
+            "params": {"uri": "file:///a.dart", "diagnostics": []}
+        });
+        write_lsp_message(&mut to_instance, &notification).await.unwrap();
+
+        for (_, _, receiver) in &mut clients[..2] {
+            let pushed: Value = serde_json::from_str(&receiver.recv().await.unwrap()).unwrap();
+            assert_eq!(pushed["method"], "textDocument/publishDiagnostics");
+            assert_eq!(pushed["params"]["sessionId"], "s1");
+            assert_eq!(pushed["params"]["uri"], "file:///a.dart");
+        }
+        assert!(clients[2].2.try_recv().is_err());
+
+        // Sessions that are shut down drop their subscribers
+        drop(to_instance);
+        manager.shutdown_server("s1").await.unwrap();
+        assert!(!manager.unsubscribe("s1", clients[0].0).await);
+    }
+
+    #[tokio::test]
+    async fn test_server_requests_are_relayed_to_owner() {
+        let manager = LspManager::new();
+        let (mut server_in, mut server_out) = fake_session(&manager, "s1").await;
+        let (owner, mut owner_rx) = subscribed_client(&manager, "s1").await;
+        let (other, mut other_rx) = subscribed_client(&manager, "s1").await;
+
+        let request = json!({
+            "jsonrpc": "2.0",
+            "id": 41,
+            "method": "workspace/applyEdit",
+            "params": {"edit": {"changes": {}}}
+        });
+        write_lsp_message(&mut server_out, &request).await.unwrap();
+
+        let relayed: Value = serde_json::from_str(&owner_rx.recv().await.unwrap()).unwrap();
+        assert_eq!(relayed["method"], "workspace/applyEdit");
+        assert_eq!(relayed["params"]["sessionId"], "s1");
+        assert_eq!(relayed["params"]["edit"], json!({"changes": {}}));
+        assert!(relayed["id"].as_str().unwrap().starts_with("bridge-"));
+        assert!(other_rx.try_recv().is_err());
+
+        // Only the client the request went to can answer it
+        let reply = json!({"jsonrpc": "2.0", "id": relayed["id"], "result": {"applied": true}});
+        assert!(!manager.complete_client_request(other, &reply).await);
+        assert!(manager.complete_client_request(owner, &reply).await);
+        assert!(!manager.complete_client_request(owner, &reply).await);
+
+        let response = read_lsp_message(&mut server_in).await.unwrap().unwrap();
+        assert_eq!(response, json!({"jsonrpc": "2.0", "id": 41, "result": {"applied": true}}));
+    }
+
+    #[tokio::test]
+    async fn test_client_errors_are_passed_to_server() {
+        let manager = LspManager::new();
+        let (mut server_in, mut server_out) = fake_session(&manager, "s1").await;
+        let (client, mut client_rx) = subscribed_client(&manager, "s1").await;
+
+        let request = json!({"jsonrpc": "2.0", "id": "r1", "method": "window/showMessageRequest", "params": {}});
+        write_lsp_message(&mut server_out, &request).await.unwrap();
+
+        let relayed: Value = serde_json::from_str(&client_rx.recv().await.unwrap()).unwrap();
+        let reply = json!({"id": relayed["id"], "error": {"code": -32800, "message": "Cancelled"}});
+        assert!(manager.complete_client_request(client, &reply).await);
+
+        let response = read_lsp_message(&mut server_in).await.unwrap().unwrap();
+        assert_eq!(response["id"], "r1");
+        assert_eq!(response["error"], json!({"code": -32800, "message": "Cancelled"}));
+    }
+
+    #[tokio::test]
+    async fn test_server_requests_answered_from_settings() {
+        let manager = LspManager::with_config(BridgeConfig {
+            settings: Some(json!({"dart": {"lineLength": 100}})),
+            ..Default::default()
+        });
+        let (mut server_in, mut server_out) = fake_session(&manager, "s1").await;
+        let (_client, mut client_rx) = subscribed_client(&manager, "s1").await;
+
+        let requests = [
+            json!({
+                "jsonrpc": "2.0",
+                "id": 1,
+                "method": "workspace/configuration",
+                "params": {"items": [{"section": "dart"}, {"section": "dart.enableSdkFormatter"}]}
+            }),
+            json!({"jsonrpc": "2.0", "id": 2, "method": "window/workDoneProgress/create", "params": {"token": "t"}}),
+        ];
+        for request in &requests {
+            write_lsp_message(&mut server_out, request).await.unwrap();
+        }
+
+        let mut responses = Vec::new();
+        for _ in &requests {
+            responses.push(read_lsp_message(&mut server_in).await.unwrap().unwrap());
+        }
+        responses.sort_by_key(|response| response["id"].as_i64());
+        assert_eq!(responses[0]["result"], json!([{"lineLength": 100}, null]));
+        assert_eq!(responses[1]["result"], Value::Null);
+        assert!(client_rx.try_recv().is_err());
+    }
+
+    #[tokio::test]
+    async fn test_unanswered_server_requests_fall_back() {
+        let manager = LspManager::with_config(BridgeConfig {
+            client_request_timeout_ms: 50,
+            ..Default::default()
+        });
+        let (mut server_in, mut server_out) = fake_session(&manager, "s1").await;
+
+        // No client subscribed
+        let request = json!({"jsonrpc": "2.0", "id": 1, "method": "workspace/configuration", "params": {"items": [{}]}});
+        write_lsp_message(&mut server_out, &request).await.unwrap();
+        let response = read_lsp_message(&mut server_in).await.unwrap().unwrap();
+        assert_eq!(response["result"], json!([null]));
+
+        // The client never answers
+        let (client, mut client_rx) = subscribed_client(&manager, "s1").await;
+        let request = json!({"jsonrpc": "2.0", "id": 2, "method": "workspace/applyEdit", "params": {}});
+        write_lsp_message(&mut server_out, &request).await.unwrap();
+        let relayed: Value = serde_json::from_str(&client_rx.recv().await.unwrap()).unwrap();
+        let response = read_lsp_message(&mut server_in).await.unwrap().unwrap();
+        assert_eq!(response["id"], 2);
+        assert_eq!(response["result"]["applied"], false);
+
+        let late = json!({"id": relayed["id"], "result": {"applied": true}});
+        assert!(!manager.complete_client_request(client, &late).await);
+    }
+
+    #[tokio::test]
+    async fn test_disconnect_fails_relayed_requests() {
+        let manager = LspManager::with_config(BridgeConfig {
+            client_request_timeout_ms: 60_000,
+            ..Default::default()
+        });
+        let (mut server_in, mut server_out) = fake_session(&manager, "s1").await;
+        let (client, mut client_rx) = subscribed_client(&manager, "s1").await;
+
+        let request = json!({"jsonrpc": "2.0", "id": 3, "method": "custom/request", "params": {}});
+        write_lsp_message(&mut server_out, &request).await.unwrap();
+        client_rx.recv().await.unwrap();
+        manager.unsubscribe_all(client).await;
+
+        let response = tokio::time::timeout(Duration::from_secs(5), read_lsp_message(&mut server_in))
+            .await
+            .unwrap()
+            .unwrap()
+            .unwrap();
+        assert_eq!(response["id"], 3);
+        assert_eq!(response["error"]["code"], -32601);
+    }
+
+    #[tokio::test]
+    async fn test_unsubscribe_fails_relayed_requests() {
+        let manager = LspManager::new();
+        let (mut server_in, mut server_out) = fake_session(&manager, "s1").await;
+        let (client, mut client_rx) = subscribed_client(&manager, "s1").await;
+
+        let request = json!({"jsonrpc": "2.0", "id": 4, "method": "workspace/applyEdit", "params": {}});
+        write_lsp_message(&mut server_out, &request).await.unwrap();
+        client_rx.recv().await.unwrap();
+        assert!(manager.unsubscribe("s1", client).await);
+
+        // Well before the default 30 s timeout
+        let response = tokio::time::timeout(Duration::from_secs(5), read_lsp_message(&mut server_in))
+            .await
+            .unwrap()
+            .unwrap()
+            .unwrap();
+        assert_eq!(response["id"], 4);
+        assert_eq!(response["result"]["applied"], false);
+    }
+
     #[tokio::test]
     async fn test_manager_clone() {
         let manager = LspManager::new();
@@ -0,0 +1,199 @@
+use anyhow::{Result, anyhow};
+use serde_json::{Value, json};
+use std::sync::Weak;
+use std::sync::atomic::Ordering;
+use tokio::sync::oneshot;
+use tracing::{debug, warn};
+
+use super::{ClientId, LspManager, tag_with_session};
+use crate::servers::{LspServerInstance, ResponseError};
+
+/// A server request relayed to a client, waiting for its reply
+pub(super) struct PendingClientRequest {
+    /// The client the request went to (only it may answer)
+    client: ClientId,
+    session: String,
+    sender: oneshot::Sender<Result<Value, ResponseError>>,
+}
+
+impl PendingClientRequest {
+    /// Checks if the request went to a client
+    pub(super) fn is_for(&self, client_id: ClientId) -> bool {
+        self.client == client_id
+    }
+
+    /// Checks if the request came from a session's server
+    pub(super) fn is_from(&self, session_id: &str) -> bool {
+        self.session == session_id
+    }
+}
+
+/// Answers a request a language server sent
+///
+/// `window/workDoneProgress/create` is accepted right away (progress is
+/// forwarded as notifications) and `workspace/configuration` is answered
+/// from the bridge settings when there are any. Everything else goes to
+/// the session's owning client; if no client answers in time the server
+/// gets a fallback answer, so it never waits forever.
+pub(super) async fn handle_server_request(
+    manager: LspManager,
+    session_id: String,
+    server: Weak<LspServerInstance>,
+    request: Value,
+) {
+    let id = request.get("id").cloned().unwrap_or(Value::Null);
+    let method = request.get("method").and_then(|m| m.as_str()).unwrap_or_default().to_string();
+    let params = request.get("params").cloned().unwrap_or(Value::Null);
+    debug!("Request {} from language server of session {}", method, session_id);
+
+    let result = match answer_from_bridge(&manager, &method, &params) {
+        Some(result) => result,
+        None => match manager.request_client(&session_id, &method, params.clone()).await {
+            Ok(result) => result,
+            Err(e) => {
+                warn!("No client answer to {} for session {}: {}", method, session_id, e);
+                fallback_result(&method, &params)
+            }
+        },
+    };
+
+    // The session may have been shut down meanwhile
+    let Some(server) = server.upgrade() else {
+        return;
+    };
+    if let Err(e) = server.send_response(id, result).await {
+        warn!("Failed to answer {} for session {}: {:?}", method, session_id, e);
+    }
+}
+
+/// Helper: Answers requests the bridge handles itself
+///
+/// Returns: Result, or None if the request goes to a client
+fn answer_from_bridge(manager: &LspManager, method: &str, params: &Value) -> Option<Result<Value, ResponseError>> {
+    match method {
+        "window/workDoneProgress/create" => Some(Ok(Value::Null)),
+        "workspace/configuration" => {
+            let items = params.get("items").and_then(|i| i.as_array())?;
+            let sections = items
+                .iter()
+                .map(|item| manager.config.section(item.get("section").and_then(|s| s.as_str())))
+                .collect::<Option<Vec<_>>>()?;
+            Some(Ok(Value::Array(sections)))
+        }
+        _ => None,
+    }
+}
+
+/// Helper: Answers a request no client answered
+///
+/// Uses the neutral answer of each method (nothing configured, edit not
+/// applied, no action chosen); unknown methods get "method not found".
+fn fallback_result(method: &str, params: &Value) -> Result<Value, ResponseError> {
+    match method {
+        "workspace/configuration" => {
+            let count = params.get("items").and_then(|i| i.as_array()).map_or(0, |items| items.len());
+            Ok(Value::Array(vec![Value::Null; count]))
+        }
+        "workspace/applyEdit" => Ok(json!({ "applied": false, "failureReason": "No client applied the edit" })),
+        "window/showDocument" => Ok(json!({ "success": false })),
+        "client/registerCapability"
+        | "client/unregisterCapability"
+        | "window/showMessageRequest"
+        | "window/workDoneProgress/create"
+        | "workspace/workspaceFolders" => Ok(Value::Null),
+        method if method.starts_with("workspace/") && method.ends_with("/refresh") => Ok(Value::Null),
+        method => Err(ResponseError {
+            code: -32601,
+            message: format!("Method not found: {}", method),
+            data: None,
+        }),
+    }
+}
+
+impl LspManager {
+    /// Relays a server request to the client owning a session
+    ///
+    /// The request goes out with a bridge id (`bridge-<n>`) and the session
+    /// id; the client answers with a JSON-RPC response carrying that id.
+    ///
+    /// Returns: The client's result or error; Err if no client is
+    /// subscribed, it unsubscribed or disconnected, or the timeout expired
+    pub(super) async fn request_client(
+        &self,
+        session_id: &str,
+        method: &str,
+        params: Value,
+    ) -> Result<Result<Value, ResponseError>> {
+        let (client_id, client) = self
+            .request_target(session_id)
+            .await
+            .ok_or_else(|| anyhow!("No client subscribed"))?;
+
+        let request_id = format!("bridge-{}", self.next_request_id.fetch_add(1, Ordering::Relaxed));
+        let (sender, receiver) = oneshot::channel();
+        self.client_requests
+            .lock()
+            .await
+            .insert(
+                request_id.clone(),
+                PendingClientRequest { client: client_id, session: session_id.to_string(), sender },
+            );
+
+        let request = json!({ "jsonrpc": "2.0", "id": request_id, "method": method, "params": params });
+        let request = tag_with_session(request, session_id).to_string();
+        if client.send(request).is_err() {
+            self.client_requests.lock().await.remove(&request_id);
+            return Err(anyhow!("Client disconnected"));
+        }
+
+        let timeout = self.config.client_request_timeout();
+        match tokio::time::timeout(timeout, receiver).await {
+            Ok(Ok(result)) => Ok(result),
+            Ok(Err(_)) => Err(anyhow!("Client unsubscribed or disconnected")),
+            Err(_) => {
+                self.client_requests.lock().await.remove(&request_id);
+                Err(anyhow!("Timed out after {:?}", timeout))
+            }
+        }
+    }
+
+    /// Completes a relayed server request with a client's response
+    ///
+    /// Returns: true if the response answered a request sent to the client
+    pub async fn complete_client_request(&self, client_id: ClientId, response: &Value) -> bool {
+        let Some(request_id) = response.get("id").and_then(|id| id.as_str()) else {
+            return false;
+        };
+
+        let mut requests = self.client_requests.lock().await;
+        if !requests.get(request_id).is_some_and(|request| request.is_for(client_id)) {
+            return false;
+        }
+        let Some(request) = requests.remove(request_id) else {
+            return false;
+        };
+
+        let result = match response.get("error") {
+            Some(error) => Err(ResponseError::from_value(error)),
+            None => Ok(response.get("result").cloned().unwrap_or(Value::Null)),
+        };
+        // The relay may just have timed out
+        let _ = request.sender.send(result);
+        true
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_fallback_results() {
+        let params = json!({"items": [{"section": "a"}, {"section": "b"}]});
+        assert_eq!(fallback_result("workspace/configuration", &params).unwrap(), json!([null, null]));
+        assert_eq!(fallback_result("workspace/applyEdit", &Value::Null).unwrap()["applied"], false);
+        assert_eq!(fallback_result("window/showMessageRequest", &Value::Null).unwrap(), Value::Null);
+        assert_eq!(fallback_result("workspace/inlayHint/refresh", &Value::Null).unwrap(), Value::Null);
+        assert_eq!(fallback_result("custom/request", &Value::Null).unwrap_err().code, -32601);
+    }
+}
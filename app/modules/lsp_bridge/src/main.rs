@@ -2,12 +2,13 @@ use anyhow::Result;
 use tokio::net::TcpListener;
 use tokio_tungstenite::accept_async;
 use tracing::{info, error};
-use tracing_subscriber;
 
+mod config;
 mod lsp_manager;
 mod protocol;
 mod servers;
 
+use config::BridgeConfig;
 use lsp_manager::LspManager;
 use protocol::handle_client_connection;
 
@@ -20,8 +21,14 @@ async fn main() -> Result<()> {
 
     info!("Starting LSP Bridge Server v{}", env!("CARGO_PKG_VERSION"));
 
+    // Load settings (LSP_BRIDGE_SETTINGS), falling back to defaults
+    let config = BridgeConfig::from_env().unwrap_or_else(|e| {
+        error!("Failed to load bridge settings, using defaults: {:?}", e);
+        BridgeConfig::default()
+    });
+
     // Create LSP manager
-    let lsp_manager = LspManager::new();
+    let lsp_manager = LspManager::with_config(config);
 
     // Bind WebSocket server
     let addr = "127.0.0.1:9999";
@@ -2,10 +2,12 @@ use anyhow::Result;
 use futures_util::{SinkExt, StreamExt};
 use serde::{Deserialize, Serialize};
 use serde_json::Value;
+use tokio::sync::mpsc;
 use tokio_tungstenite::{WebSocketStream, tungstenite::Message};
 use tracing::{info, error, warn};
 
-use crate::lsp_manager::LspManager;
+use crate::lsp_manager::{ClientId, ClientSender, LspManager};
+use crate::servers::ResponseError;
 
 /// JSON-RPC request from Flutter client
 #[derive(Debug, Deserialize)]
@@ -34,29 +36,86 @@ struct JsonRpcError {
     message: String,
 }
 
+/// The connected client a request came from
+#[derive(Clone)]
+struct Client {
+    id: ClientId,
+    /// Pushes notifications to the client
+    sender: ClientSender,
+}
+
 /// Handles a client WebSocket connection
+///
+/// Requests are handled concurrently: each runs in its own task and its
+/// response goes out as soon as the language server answers, with the
+/// client's id. Document sync methods (`textDocument/did*`) and other
+/// messages without an id are forwarded before the next message is read,
+/// so servers see edits in the order the client sent them.
+///
+/// Server notifications of the sessions the client has open (initialized
+/// or subscribed to with `session/subscribe`) are pushed as they arrive.
+/// Server requests the bridge relays arrive the same way, with a bridge
+/// id; the client's responses to them are passed back to the server.
 pub async fn handle_client_connection<S>(
     ws_stream: WebSocketStream<S>,
     lsp_manager: LspManager,
 ) -> Result<()>
 where
-    S: tokio::io::AsyncRead + tokio::io::AsyncWrite + Unpin,
+    S: tokio::io::AsyncRead + tokio::io::AsyncWrite + Unpin + Send + 'static,
 {
     let (mut write, mut read) = ws_stream.split();
 
+    // Single writer, fed by all request tasks and session notifications
+    let (outgoing, mut outgoing_rx) = mpsc::unbounded_channel::<Message>();
+    let (notifications, mut notifications_rx) = mpsc::unbounded_channel::<String>();
+    let writer = tokio::spawn(async move {
+        loop {
+            let message = tokio::select! {
+                Some(message) = outgoing_rx.recv() => message,
+                Some(notification) = notifications_rx.recv() => Message::Text(notification),
+                else => break,
+            };
+            if let Err(e) = write.send(message).await {
+                error!("Failed to send WebSocket message: {:?}", e);
+                break;
+            }
+        }
+    });
+    let client = Client {
+        id: lsp_manager.new_client_id(),
+        sender: notifications,
+    };
+
     while let Some(message) = read.next().await {
         match message {
             Ok(Message::Text(text)) => {
                 info!("Received message: {}", text);
 
+                // Responses to relayed server requests
+                let message = serde_json::from_str::<Value>(&text);
+                if let Some(response) = message.as_ref().ok().filter(|m| is_client_response(m)) {
+                    if !lsp_manager.complete_client_request(client.id, response).await {
+                        warn!("Dropping response to unknown request: {}", response["id"]);
+                    }
+                    continue;
+                }
+
                 // Parse JSON-RPC request
-                match serde_json::from_str::<JsonRpcRequest>(&text) {
+                match message.and_then(serde_json::from_value::<JsonRpcRequest>) {
+                    Ok(request) if is_ordered(&request) => {
+                        let response = handle_request(request, &lsp_manager, &client).await;
+                        send_response(&outgoing, &response)?;
+                    }
                     Ok(request) => {
-                        let response = handle_request(request, &lsp_manager).await;
-
-                        // Send response
-                        let response_json = serde_json::to_string(&response)?;
-                        write.send(Message::Text(response_json)).await?;
+                        let manager = lsp_manager.clone();
+                        let outgoing = outgoing.clone();
+                        let client = client.clone();
+                        tokio::spawn(async move {
+                            let response = handle_request(request, &manager, &client).await;
+                            if let Err(e) = send_response(&outgoing, &response) {
+                                warn!("Dropping response for closed connection: {:?}", e);
+                            }
+                        });
                     }
                     Err(e) => {
                         error!("Failed to parse JSON-RPC request: {:?}", e);
@@ -71,8 +130,7 @@ where
                             }),
                         };
 
-                        let response_json = serde_json::to_string(&error_response)?;
-                        write.send(Message::Text(response_json)).await?;
+                        send_response(&outgoing, &error_response)?;
                     }
                 }
             }
@@ -81,7 +139,7 @@ where
                 break;
             }
             Ok(Message::Ping(data)) => {
-                write.send(Message::Pong(data)).await?;
+                outgoing.send(Message::Pong(data))?;
             }
             Err(e) => {
                 error!("WebSocket error: {:?}", e);
@@ -91,6 +149,31 @@ where
         }
     }
 
+    // Responses still in flight are dropped with the connection
+    lsp_manager.unsubscribe_all(client.id).await;
+    drop(outgoing);
+    writer.abort();
+
+    Ok(())
+}
+
+/// Checks if a message must be handled before the next one is read
+fn is_ordered(request: &JsonRpcRequest) -> bool {
+    request.id.is_none() || request.method.starts_with("textDocument/did")
+}
+
+/// Checks if a client message answers a request (no method, an id and a
+/// result or error)
+fn is_client_response(message: &Value) -> bool {
+    message.get("method").is_none()
+        && message.get("id").is_some()
+        && (message.get("result").is_some() || message.get("error").is_some())
+}
+
+/// Queues a response for the connection's writer
+fn send_response(outgoing: &mpsc::UnboundedSender<Message>, response: &JsonRpcResponse) -> Result<()> {
+    let response_json = serde_json::to_string(response)?;
+    outgoing.send(Message::Text(response_json))?;
     Ok(())
 }
 
@@ -98,11 +181,26 @@ where
 async fn handle_request(
     request: JsonRpcRequest,
     lsp_manager: &LspManager,
+    client: &Client,
 ) -> JsonRpcResponse {
     let id = request.id.unwrap_or(Value::Null);
 
     match request.method.as_str() {
-        "initialize" => handle_initialize(id, request.params, lsp_manager).await,
+        "initialize" => {
+            let response = handle_initialize(id, request.params, lsp_manager).await;
+
+            // The client that opens a session gets its notifications
+            let session_id = response.result.as_ref().and_then(|r| r.get("sessionId")).and_then(|v| v.as_str());
+            if let Some(session_id) = session_id {
+                if let Err(e) = lsp_manager.subscribe(session_id, client.id, client.sender.clone()).await {
+                    error!("Failed to subscribe client to {}: {:?}", session_id, e);
+                }
+            }
+            response
+        }
+        "session/subscribe" | "session/unsubscribe" => {
+            handle_subscription(id, &request.method, request.params, lsp_manager, client).await
+        }
         "shutdown" => handle_shutdown(id, request.params, lsp_manager).await,
         "textDocument/completion" => {
             handle_completion(id, request.params, lsp_manager).await
@@ -229,6 +327,47 @@ async fn handle_shutdown(
     }
 }
 
+/// Subscribes a client to (or unsubscribes it from) a session's notifications
+async fn handle_subscription(
+    id: Value,
+    method: &str,
+    params: Option<Value>,
+    lsp_manager: &LspManager,
+    client: &Client,
+) -> JsonRpcResponse {
+    let session_id = params
+        .as_ref()
+        .and_then(|params| params.get("sessionId"))
+        .and_then(|v| v.as_str());
+
+    let result = match session_id {
+        Some(session_id) if method == "session/subscribe" => lsp_manager
+            .subscribe(session_id, client.id, client.sender.clone())
+            .await
+            .map(|_| Value::Null),
+        Some(session_id) => Ok(Value::Bool(lsp_manager.unsubscribe(session_id, client.id).await)),
+        None => Err(anyhow::anyhow!("missing sessionId")),
+    };
+
+    match result {
+        Ok(result) => JsonRpcResponse {
+            jsonrpc: "2.0".to_string(),
+            id,
+            result: Some(result),
+            error: None,
+        },
+        Err(e) => JsonRpcResponse {
+            jsonrpc: "2.0".to_string(),
+            id,
+            result: None,
+            error: Some(JsonRpcError {
+                code: -32602,
+                message: format!("Invalid params: {}", e),
+            }),
+        },
+    }
+}
+
 async fn handle_completion(
     id: Value,
     params: Option<Value>,
@@ -401,14 +540,23 @@ async fn handle_generic_lsp_request(
                 }
                 Err(e) => {
                     error!("LSP request failed for {}: {:?}", method, e);
+
+                    // Pass the server's own errors through
+                    let error = match e.downcast_ref::<ResponseError>() {
+                        Some(server_error) => JsonRpcError {
+                            code: i32::try_from(server_error.code).unwrap_or(-32603),
+                            message: server_error.message.clone(),
+                        },
+                        None => JsonRpcError {
+                            code: -32603,
+                            message: format!("LSP request failed: {}", e),
+                        },
+                    };
                     return JsonRpcResponse {
                         jsonrpc: "2.0".to_string(),
                         id,
                         result: None,
-                        error: Some(JsonRpcError {
-                            code: -32603,
-                            message: format!("LSP request failed: {}", e),
-                        }),
+                        error: Some(error),
                     };
                 }
             }
@@ -560,7 +708,7 @@ mod tests {
             params: None,
         };
 
-        let response = handle_request(request, &lsp_manager).await;
+        let response = handle_request(request, &lsp_manager, &test_client(&lsp_manager)).await;
 
         assert!(response.error.is_some());
         let error = response.error.unwrap();
@@ -568,6 +716,31 @@ mod tests {
         assert!(error.message.contains("Method not found"));
     }
 
+    /// Helper: Creates a client whose notifications are dropped
+    fn test_client(lsp_manager: &LspManager) -> Client {
+        Client {
+            id: lsp_manager.new_client_id(),
+            sender: mpsc::unbounded_channel().0,
+        }
+    }
+
+    #[tokio::test]
+    async fn test_handle_subscription_invalid_params() {
+        let lsp_manager = LspManager::new();
+        let client = test_client(&lsp_manager);
+        let id = Value::Number(1.into());
+
+        let response = handle_subscription(id.clone(), "session/subscribe", None, &lsp_manager, &client).await;
+        assert_eq!(response.error.unwrap().code, -32602);
+
+        let params = Some(serde_json::json!({"sessionId": "nonexistent"}));
+        let response = handle_subscription(id.clone(), "session/subscribe", params.clone(), &lsp_manager, &client).await;
+        assert_eq!(response.error.unwrap().code, -32602);
+
+        let response = handle_subscription(id, "session/unsubscribe", params, &lsp_manager, &client).await;
+        assert_eq!(response.result, Some(Value::Bool(false)));
+    }
+
     #[tokio::test]
     async fn test_handle_initialize_invalid_params() {
         let lsp_manager = LspManager::new();
@@ -633,16 +806,39 @@ mod tests {
     }
 
     #[tokio::test]
-    async fn test_handle_hover_returns_success() {
+    async fn test_handle_hover_no_session_id() {
         let lsp_manager = LspManager::new();
         let id = Value::Number(1.into());
         let params = Some(serde_json::json!({}));
 
         let response = handle_hover(id, params, &lsp_manager).await;
 
-        // Current implementation returns null result
-        assert!(response.result.is_some());
-        assert!(response.error.is_none());
+        // Without a session there is no server to ask
+        assert!(response.result.is_none());
+        let error = response.error.unwrap();
+        assert_eq!(error.code, -32603);
+    }
+
+    #[test]
+    fn test_is_client_response() {
+        assert!(is_client_response(&serde_json::json!({"jsonrpc": "2.0", "id": "bridge-1", "result": null})));
+        assert!(is_client_response(&serde_json::json!({"id": "bridge-1", "error": {"code": 1, "message": "x"}})));
+        assert!(!is_client_response(&serde_json::json!({"id": 1, "method": "initialize"})));
+        assert!(!is_client_response(&serde_json::json!({"id": 1})));
+    }
+
+    #[test]
+    fn test_document_sync_is_ordered() {
+        let request = |id: Option<Value>, method: &str| JsonRpcRequest {
+            jsonrpc: "2.0".to_string(),
+            id,
+            method: method.to_string(),
+            params: None,
+        };
+
+        assert!(is_ordered(&request(Some(Value::from(1)), "textDocument/didChange")));
+        assert!(is_ordered(&request(None, "textDocument/hover")));
+        assert!(!is_ordered(&request(Some(Value::from(2)), "textDocument/hover")));
     }
 
     #[tokio::test]
@@ -1,10 +1,86 @@
 use anyhow::{Result, anyhow};
 use serde_json::Value;
+use std::collections::HashMap;
 use std::process::Stdio;
-use tokio::io::{AsyncBufReadExt, AsyncWriteExt, BufReader};
-use tokio::process::{Child, ChildStdin, ChildStdout, Command};
-use tokio::sync::Mutex;
-use tracing::{info, error};
+use std::sync::{Arc, MutexGuard, PoisonError};
+use std::sync::atomic::{AtomicI64, Ordering};
+use std::time::Duration;
+use tokio::io::{AsyncBufRead, AsyncBufReadExt, AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt, BufReader};
+use tokio::process::{Child, Command};
+use tokio::sync::{Mutex, mpsc, oneshot};
+use tokio::task::JoinHandle;
+use tracing::{debug, info, error, warn};
+
+/// How long to wait for the server to answer `shutdown`
+const SHUTDOWN_TIMEOUT: Duration = Duration::from_secs(2);
+
+/// Error response from a language server.
+#[derive(Debug, Clone, thiserror::Error)]
+#[error("{message} (code {code})")]
+pub struct ResponseError {
+    pub code: i64,
+    pub message: String,
+    pub data: Option<Value>,
+}
+
+impl ResponseError {
+    /// Reads the `error` member of a JSON-RPC response
+    pub fn from_value(error: &Value) -> Self {
+        Self {
+            code: error.get("code").and_then(|c| c.as_i64()).unwrap_or(-32603),
+            message: error.get("message").and_then(|m| m.as_str()).unwrap_or_default().to_string(),
+            data: error.get("data").cloned(),
+        }
+    }
+
+    /// Builds the `error` member of a JSON-RPC response
+    pub fn to_value(&self) -> Value {
+        let mut error = serde_json::json!({ "code": self.code, "message": self.message });
+        if let Some(data) = &self.data {
+            error["data"] = data.clone();
+        }
+        error
+    }
+}
+
+/// Requests waiting for their response, by JSON-RPC id
+///
+/// Behind a std mutex: it is never held across an await, and the guard of
+/// a cancelled request has to clean up without awaiting.
+#[derive(Default)]
+struct PendingRequests {
+    /// Set once the reader task stops (no response can arrive anymore)
+    closed: bool,
+    senders: HashMap<i64, oneshot::Sender<Result<Value>>>,
+}
+
+type SharedPending = Arc<std::sync::Mutex<PendingRequests>>;
+
+/// Helper: Locks the pending requests (a panicked holder left them consistent)
+fn lock_pending(pending: &std::sync::Mutex<PendingRequests>) -> MutexGuard<'_, PendingRequests> {
+    pending.lock().unwrap_or_else(PoisonError::into_inner)
+}
+
+/// Removes a request from the pending map when its caller stops waiting
+///
+/// Runs on completion as well as when the caller's future is dropped
+/// (client disconnect, a timeout around the request), so entries never
+/// leak and late responses are dropped instead of routed to a dead sender.
+struct PendingGuard<'a> {
+    pending: &'a std::sync::Mutex<PendingRequests>,
+    id: i64,
+}
+
+impl Drop for PendingGuard<'_> {
+    fn drop(&mut self) {
+        lock_pending(self.pending).senders.remove(&self.id);
+    }
+}
+
+type MessageWriter = Box<dyn AsyncWrite + Send + Unpin>;
+
+/// Channel receiving the notifications and requests a server sends
+pub type ServerMessageSender = mpsc::UnboundedSender<Value>;
 
 /// Represents an LSP server instance for a specific language.
 ///
@@ -12,17 +88,25 @@ use tracing::{info, error};
 /// - The LSP server process (child process)
 /// - Communication via stdin/stdout
 /// - JSON-RPC protocol handling
+///
+/// A reader task owns stdout and routes each response to the request
+/// with the same id, so any number of requests can be in flight at once.
+/// Notifications (diagnostics, progress, logs) and requests from the
+/// server go to the message channel; requests are answered with
+/// `send_response`.
 pub struct LspServerInstance {
     #[allow(dead_code)]
     language: String,
-    process: Mutex<Child>,
-    stdin: Mutex<ChildStdin>,
-    stdout: Mutex<BufReader<ChildStdout>>,
+    process: Mutex<Option<Child>>,
+    stdin: Arc<Mutex<MessageWriter>>,
+    next_id: AtomicI64,
+    pending: SharedPending,
+    reader: JoinHandle<()>,
 }
 
 impl LspServerInstance {
     /// Creates a new LSP server instance for a language
-    pub async fn create(language_id: &str, root_uri: &str) -> Result<Self> {
+    pub async fn create(language_id: &str, root_uri: &str, messages: ServerMessageSender) -> Result<Self> {
         info!("Creating LSP server for language: {}", language_id);
 
         // Get LSP server command based on language
@@ -39,12 +123,7 @@ impl LspServerInstance {
         let stdin = process.stdin.take().ok_or_else(|| anyhow!("Failed to get stdin"))?;
         let stdout = process.stdout.take().ok_or_else(|| anyhow!("Failed to get stdout"))?;
 
-        let instance = Self {
-            language: language_id.to_string(),
-            process: Mutex::new(process),
-            stdin: Mutex::new(stdin),
-            stdout: Mutex::new(BufReader::new(stdout)),
-        };
+        let instance = Self::from_io(language_id, stdout, stdin, Some(process), messages);
 
         // Send initialize request
         instance.initialize_lsp(root_uri).await?;
@@ -52,49 +131,83 @@ impl LspServerInstance {
         Ok(instance)
     }
 
+    /// Creates an instance talking to a server over any byte streams
+    ///
+    /// Starts the reader task; `process` is killed on shutdown.
+    pub fn from_io<R, W>(
+        language_id: &str,
+        stdout: R,
+        stdin: W,
+        process: Option<Child>,
+        messages: ServerMessageSender,
+    ) -> Self
+    where
+        R: AsyncRead + Send + Unpin + 'static,
+        W: AsyncWrite + Send + Unpin + 'static,
+    {
+        let pending = SharedPending::default();
+        let reader = tokio::spawn(read_loop(
+            BufReader::new(stdout),
+            ServerOutput {
+                language: language_id.to_string(),
+                pending: pending.clone(),
+                messages,
+            },
+        ));
+
+        Self {
+            language: language_id.to_string(),
+            process: Mutex::new(process),
+            stdin: Arc::new(Mutex::new(Box::new(stdin))),
+            next_id: AtomicI64::new(1),
+            pending,
+            reader,
+        }
+    }
+
     /// Sends LSP initialize request
     async fn initialize_lsp(&self, root_uri: &str) -> Result<()> {
-        let init_request = serde_json::json!({
-            "jsonrpc": "2.0",
-            "id": 1,
-            "method": "initialize",
-            "params": {
-                "processId": std::process::id(),
-                "rootUri": root_uri,
-                "capabilities": {}
-            }
+        let params = serde_json::json!({
+            "processId": std::process::id(),
+            "rootUri": root_uri,
+            "capabilities": client_capabilities()
         });
 
-        self.send_lsp_message(&init_request).await?;
-
         // Wait for initialize response
-        let _response = self.read_lsp_message().await?;
+        let _response = self.send_request("initialize", params).await?;
 
         // Send initialized notification
-        let initialized = serde_json::json!({
-            "jsonrpc": "2.0",
-            "method": "initialized",
-            "params": {}
-        });
-
-        self.send_lsp_message(&initialized).await?;
-
-        Ok(())
+        self.send_notification("initialized", serde_json::json!({})).await
     }
 
     /// Sends a request to the LSP server
+    ///
+    /// Each request gets its own id; the reader task hands back the
+    /// response with that id.
     pub async fn send_request(&self, method: &str, params: Value) -> Result<Value> {
+        let id = self.next_id.fetch_add(1, Ordering::Relaxed);
+        let (sender, receiver) = oneshot::channel();
+        {
+            let mut pending = lock_pending(&self.pending);
+            if pending.closed {
+                return Err(anyhow!("Language server connection is closed"));
+            }
+            pending.senders.insert(id, sender);
+        }
+        let _guard = PendingGuard { pending: &self.pending, id };
+
         let request = serde_json::json!({
             "jsonrpc": "2.0",
-            "id": 1,
+            "id": id,
             "method": method,
             "params": params
         });
 
         self.send_lsp_message(&request).await?;
-        let response = self.read_lsp_message().await?;
 
-        Ok(response.get("result").cloned().unwrap_or(Value::Null))
+        receiver
+            .await
+            .map_err(|_| anyhow!("Language server connection is closed"))?
     }
 
     /// Sends a notification to the LSP server
@@ -108,71 +221,204 @@ impl LspServerInstance {
         self.send_lsp_message(&notification).await
     }
 
+    /// Answers a request the server sent, with the server's id
+    pub async fn send_response(&self, id: Value, result: Result<Value, ResponseError>) -> Result<()> {
+        let response = match result {
+            Ok(result) => serde_json::json!({ "jsonrpc": "2.0", "id": id, "result": result }),
+            Err(error) => serde_json::json!({ "jsonrpc": "2.0", "id": id, "error": error.to_value() }),
+        };
+
+        self.send_lsp_message(&response).await
+    }
+
     /// Sends an LSP message (JSON-RPC via Content-Length header)
     async fn send_lsp_message(&self, message: &Value) -> Result<()> {
-        let content = serde_json::to_string(message)?;
-        let msg = format!("Content-Length: {}\r\n\r\n{}", content.len(), content);
-
         let mut stdin = self.stdin.lock().await;
-        stdin.write_all(msg.as_bytes()).await?;
-        stdin.flush().await?;
+        write_lsp_message(&mut *stdin, message).await
+    }
+
+    /// Gets the number of requests waiting for a response
+    #[allow(dead_code)]
+    pub async fn pending_count(&self) -> usize {
+        lock_pending(&self.pending).senders.len()
+    }
+
+    /// Shuts down the LSP server
+    ///
+    /// Sends `shutdown` (waiting briefly for the answer) and `exit`, then
+    /// kills the process.
+    pub async fn shutdown(&self) -> Result<()> {
+        match tokio::time::timeout(SHUTDOWN_TIMEOUT, self.send_request("shutdown", Value::Null)).await {
+            Ok(Ok(_)) => {
+                if let Err(e) = self.send_notification("exit", Value::Null).await {
+                    error!("Failed to send exit notification: {:?}", e);
+                }
+            }
+            Ok(Err(e)) => error!("Shutdown request failed: {:?}", e),
+            Err(_) => warn!("Language server did not answer shutdown in time"),
+        }
+
+        // Kill process
+        if let Some(process) = self.process.lock().await.as_mut() {
+            if let Err(e) = process.kill().await {
+                error!("Failed to kill LSP process: {:?}", e);
+            }
+        }
+        self.reader.abort();
 
         Ok(())
     }
+}
 
-    /// Reads an LSP message
-    async fn read_lsp_message(&self) -> Result<Value> {
-        let mut stdout = self.stdout.lock().await;
+/// Gets the client capabilities sent with `initialize`
+///
+/// Advertises the server requests the bridge answers (itself or through
+/// the session's client), so servers actually send them.
+fn client_capabilities() -> Value {
+    let dynamic = serde_json::json!({ "dynamicRegistration": true });
+    serde_json::json!({
+        "workspace": {
+            "configuration": true,
+            "applyEdit": true,
+            "didChangeConfiguration": dynamic,
+            "symbol": dynamic
+        },
+        "window": {
+            "workDoneProgress": true,
+            "showMessage": { "messageActionItem": { "additionalPropertiesSupport": false } },
+            "showDocument": { "support": true }
+        },
+        "textDocument": {
+            "synchronization": dynamic,
+            "completion": dynamic,
+            "hover": dynamic,
+            "signatureHelp": dynamic,
+            "definition": dynamic,
+            "references": dynamic,
+            "documentSymbol": dynamic,
+            "codeAction": dynamic,
+            "formatting": dynamic,
+            "rename": dynamic
+        }
+    })
+}
 
-        // Read Content-Length header
-        let mut header = String::new();
-        stdout.read_line(&mut header).await?;
+/// Where the reader task delivers server messages
+struct ServerOutput {
+    language: String,
+    pending: SharedPending,
+    messages: ServerMessageSender,
+}
 
-        if !header.starts_with("Content-Length:") {
-            return Err(anyhow!("Invalid LSP message header"));
+/// Reads messages from the server until its stdout closes
+///
+/// Responses complete their pending request; notifications and requests
+/// go to the message channel. Requests still pending when the stream ends fail.
+async fn read_loop<R>(mut stdout: BufReader<R>, output: ServerOutput)
+where
+    R: AsyncRead + Unpin,
+{
+    let language = &output.language;
+    loop {
+        match read_lsp_message(&mut stdout).await {
+            Ok(Some(message)) => dispatch_message(message, &output).await,
+            Ok(None) => {
+                info!("Language server for {} closed its output", language);
+                break;
+            }
+            Err(e) => {
+                error!("Failed to read message from {} language server: {:?}", language, e);
+                break;
+            }
         }
+    }
 
-        let content_length: usize = header
-            .trim_start_matches("Content-Length:")
-            .trim()
-            .parse()?;
+    let mut pending = lock_pending(&output.pending);
+    pending.closed = true;
+    for (_, sender) in pending.senders.drain() {
+        let _ = sender.send(Err(anyhow!("Language server connection is closed")));
+    }
+}
 
-        // Read empty line
-        let mut empty = String::new();
-        stdout.read_line(&mut empty).await?;
+/// Routes one message read from the server
+async fn dispatch_message(message: Value, output: &ServerOutput) {
+    let language = &output.language;
+    if let Some(method) = message.get("method").and_then(|m| m.as_str()) {
+        if output.messages.send(message.clone()).is_err() {
+            debug!("Dropping {} message from {} language server", method, language);
+        }
+        return;
+    }
 
-        // Read content
-        let mut buffer = vec![0u8; content_length];
-        tokio::io::AsyncReadExt::read_exact(&mut *stdout, &mut buffer).await?;
+    let Some(id) = message.get("id").and_then(|id| id.as_i64()) else {
+        warn!("Dropping response without a valid id from {} language server", language);
+        return;
+    };
+    let Some(sender) = lock_pending(&output.pending).senders.remove(&id) else {
+        warn!("Dropping response to unknown request {} from {} language server", id, language);
+        return;
+    };
+
+    let result = match message.get("error") {
+        Some(error) => Err(ResponseError::from_value(error).into()),
+        None => Ok(message.get("result").cloned().unwrap_or(Value::Null)),
+    };
+
+    // The requester may have given up (e.g. shutdown timeout)
+    let _ = sender.send(result);
+}
 
-        let message = String::from_utf8(buffer)?;
-        let value: Value = serde_json::from_str(&message)?;
+/// Writes an LSP message (JSON-RPC with a Content-Length header)
+pub(crate) async fn write_lsp_message<W>(writer: &mut W, message: &Value) -> Result<()>
+where
+    W: AsyncWrite + Unpin + ?Sized,
+{
+    let content = serde_json::to_string(message)?;
+    let msg = format!("Content-Length: {}\r\n\r\n{}", content.len(), content);
 
-        Ok(value)
-    }
+    writer.write_all(msg.as_bytes()).await?;
+    writer.flush().await?;
 
-    /// Shuts down the LSP server
-    pub async fn shutdown(&mut self) -> Result<()> {
-        // Send shutdown request
-        let shutdown_request = serde_json::json!({
-            "jsonrpc": "2.0",
-            "id": 999,
-            "method": "shutdown",
-            "params": null
-        });
+    Ok(())
+}
 
-        if let Err(e) = self.send_lsp_message(&shutdown_request).await {
-            error!("Failed to send shutdown request: {:?}", e);
+/// Reads an LSP message
+///
+/// Returns: Message, or None at the end of the stream
+pub(crate) async fn read_lsp_message<R>(reader: &mut R) -> Result<Option<Value>>
+where
+    R: AsyncBufRead + Unpin,
+{
+    // Read headers up to the empty line (Content-Type is ignored)
+    let mut content_length = None;
+    loop {
+        let mut header = String::new();
+        if reader.read_line(&mut header).await? == 0 {
+            return Ok(None);
         }
 
-        // Kill process
-        let mut process = self.process.lock().await;
-        if let Err(e) = process.kill().await {
-            error!("Failed to kill LSP process: {:?}", e);
+        let header = header.trim_end();
+        if header.is_empty() {
+            if content_length.is_some() {
+                break;
+            }
+            continue;
+        }
+        if let Some(length) = header.strip_prefix("Content-Length:") {
+            content_length = Some(length.trim().parse::<usize>()?);
+        } else if !header.starts_with("Content-Type:") {
+            return Err(anyhow!("Invalid LSP message header: {}", header));
         }
-
-        Ok(())
     }
+
+    // Read content
+    let mut buffer = vec![0u8; content_length.unwrap_or_default()];
+    reader.read_exact(&mut buffer).await?;
+
+    let message = String::from_utf8(buffer)?;
+    let value: Value = serde_json::from_str(&message)?;
+
+    Ok(Some(value))
 }
 
 /// Gets the LSP server command for a language
@@ -201,6 +447,8 @@ fn get_lsp_command(language_id: &str) -> Result<(String, Vec<String>)> {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use serde_json::json;
+    use tokio::io::DuplexStream;
 
     // ============================================================
     // Language Command Tests
@@ -264,6 +512,191 @@ mod tests {
         assert!(result.is_err());
     }
 
-    // Note: LspServerInstance tests would require mocking LSP servers
-    // which is complex. These tests verify the command selection logic.
+    // ============================================================
+    // Message Framing Tests
+    // ============================================================
+
+    #[tokio::test]
+    async fn test_read_lsp_message_headers() {
+        let body = r#"{"jsonrpc":"2.0","method":"initialized"}"#;
+        let input = format!(
+            "Content-Length: {}\r\nContent-Type: application/vscode-jsonrpc; charset=utf-8\r\n\r\n{}",
+            body.len(),
+            body
+        );
+        let mut reader = BufReader::new(input.as_bytes());
+
+        let message = read_lsp_message(&mut reader).await.unwrap().unwrap();
+        assert_eq!(message["method"], "initialized");
+        assert!(read_lsp_message(&mut reader).await.unwrap().is_none());
+    }
+
+    #[tokio::test]
+    async fn test_read_lsp_message_invalid_header() {
+        let mut reader = BufReader::new("Content-Size: 2\r\n\r\n{}".as_bytes());
+        assert!(read_lsp_message(&mut reader).await.is_err());
+    }
+
+    // ============================================================
+    // Request Multiplexing Tests
+    // ============================================================
+
+    /// Helper: Connects an instance to a fake server driven by the test
+    fn fake_server() -> (LspServerInstance, BufReader<DuplexStream>, DuplexStream) {
+        let (instance, server_in, server_out, _) = fake_server_with_messages();
+        (instance, server_in, server_out)
+    }
+
+    /// Helper: Like `fake_server`, also returning the message channel
+    fn fake_server_with_messages(
+    ) -> (LspServerInstance, BufReader<DuplexStream>, DuplexStream, mpsc::UnboundedReceiver<Value>) {
+        let (to_server, from_instance) = tokio::io::duplex(4096);
+        let (to_instance, from_server) = tokio::io::duplex(4096);
+        let (notifications, notifications_rx) = mpsc::unbounded_channel();
+        let instance = LspServerInstance::from_io("test", from_server, to_server, None, notifications);
+        (instance, BufReader::new(from_instance), to_instance, notifications_rx)
+    }
+
+    #[tokio::test]
+    async fn test_concurrent_requests_are_routed_by_id() {
+        let (instance, mut server_in, mut server_out, mut notifications) = fake_server_with_messages();
+
+        let server = async {
+            let first = read_lsp_message(&mut server_in).await.unwrap().unwrap();
+            let second = read_lsp_message(&mut server_in).await.unwrap().unwrap();
+            assert_ne!(first["id"], second["id"]);
+
+            // A notification, then the responses in reverse order
+            let notification = json!({"jsonrpc": "2.0", "method": "window/logMessage", "params": {}});
+            write_lsp_message(&mut server_out, &notification).await.unwrap();
+            for request in [&second, &first] {
+                let response = json!({"jsonrpc": "2.0", "id": request["id"], "result": request["method"]});
+                write_lsp_message(&mut server_out, &response).await.unwrap();
+            }
+        };
+
+        let (hover, completion, ()) = tokio::join!(
+            instance.send_request("textDocument/hover", Value::Null),
+            instance.send_request("textDocument/completion", Value::Null),
+            server
+        );
+        assert_eq!(hover.unwrap(), "textDocument/hover");
+        assert_eq!(completion.unwrap(), "textDocument/completion");
+        assert_eq!(instance.pending_count().await, 0);
+
+        let notification = notifications.recv().await.unwrap();
+        assert_eq!(notification["method"], "window/logMessage");
+    }
+
+    #[tokio::test]
+    async fn test_error_response() {
+        let (instance, mut server_in, mut server_out) = fake_server();
+
+        let server = async {
+            let request = read_lsp_message(&mut server_in).await.unwrap().unwrap();
+            let response = json!({
+                "jsonrpc": "2.0",
+                "id": request["id"],
+                "error": {"code": -32601, "message": "Unhandled method"}
+            });
+            write_lsp_message(&mut server_out, &response).await.unwrap();
+        };
+
+        let (result, ()) = tokio::join!(instance.send_request("custom/method", Value::Null), server);
+        let error = result.unwrap_err();
+        let error = error.downcast_ref::<ResponseError>().unwrap();
+        assert_eq!(error.code, -32601);
+        assert_eq!(error.message, "Unhandled method");
+    }
+
+    #[tokio::test]
+    async fn test_pending_requests_fail_when_server_exits() {
+        let (instance, mut server_in, server_out) = fake_server();
+
+        let server = async {
+            read_lsp_message(&mut server_in).await.unwrap().unwrap();
+            drop(server_out);
+        };
+
+        let (result, ()) = tokio::join!(instance.send_request("textDocument/hover", Value::Null), server);
+        assert!(result.is_err());
+        assert!(instance.send_request("textDocument/hover", Value::Null).await.is_err());
+        assert_eq!(instance.pending_count().await, 0);
+    }
+
+    #[tokio::test]
+    async fn test_server_requests_are_answered_with_their_id() {
+        let (instance, mut server_in, mut server_out, mut messages) = fake_server_with_messages();
+
+        let request = json!({"jsonrpc": "2.0", "id": "cfg-1", "method": "workspace/configuration", "params": {}});
+        write_lsp_message(&mut server_out, &request).await.unwrap();
+        let received = messages.recv().await.unwrap();
+        assert_eq!(received["method"], "workspace/configuration");
+
+        instance.send_response(received["id"].clone(), Ok(json!([null]))).await.unwrap();
+        let response = read_lsp_message(&mut server_in).await.unwrap().unwrap();
+        assert_eq!(response, json!({"jsonrpc": "2.0", "id": "cfg-1", "result": [null]}));
+
+        let error = ResponseError { code: -32601, message: "Method not found".to_string(), data: None };
+        instance.send_response(json!(2), Err(error)).await.unwrap();
+        let response = read_lsp_message(&mut server_in).await.unwrap().unwrap();
+        assert_eq!(response["id"], 2);
+        assert_eq!(response["error"], json!({"code": -32601, "message": "Method not found"}));
+    }
+
+    #[tokio::test]
+    async fn test_initialize_advertises_client_capabilities() {
+        let (instance, mut server_in, mut server_out) = fake_server();
+
+        let server = async {
+            let request = read_lsp_message(&mut server_in).await.unwrap().unwrap();
+            let response = json!({"jsonrpc": "2.0", "id": request["id"], "result": {"capabilities": {}}});
+            write_lsp_message(&mut server_out, &response).await.unwrap();
+            let initialized = read_lsp_message(&mut server_in).await.unwrap().unwrap();
+            (request, initialized)
+        };
+
+        let (result, (request, initialized)) = tokio::join!(instance.initialize_lsp("file:///project"), server);
+        result.unwrap();
+        assert_eq!(request["method"], "initialize");
+        assert_eq!(initialized["method"], "initialized");
+
+        let params = &request["params"];
+        assert_eq!(params["rootUri"], "file:///project");
+        let capabilities = &params["capabilities"];
+        assert_eq!(capabilities["workspace"]["configuration"], true);
+        assert_eq!(capabilities["workspace"]["applyEdit"], true);
+        assert_eq!(capabilities["workspace"]["didChangeConfiguration"]["dynamicRegistration"], true);
+        assert_eq!(capabilities["window"]["workDoneProgress"], true);
+        assert!(capabilities["window"]["showMessage"].is_object());
+        assert_eq!(capabilities["window"]["showDocument"]["support"], true);
+        assert_eq!(capabilities["textDocument"]["completion"]["dynamicRegistration"], true);
+    }
+
+    #[tokio::test]
+    async fn test_cancelled_requests_leave_no_pending_entry() {
+        let (instance, mut server_in, mut server_out) = fake_server();
+
+        let cancelled = tokio::time::timeout(
+            Duration::from_millis(20),
+            instance.send_request("textDocument/hover", Value::Null),
+        )
+        .await;
+        assert!(cancelled.is_err());
+        assert_eq!(instance.pending_count().await, 0);
+
+        // The late response is dropped; later requests still get theirs
+        let request = read_lsp_message(&mut server_in).await.unwrap().unwrap();
+        let late = json!({"jsonrpc": "2.0", "id": request["id"], "result": "late"});
+        write_lsp_message(&mut server_out, &late).await.unwrap();
+
+        let server = async {
+            let request = read_lsp_message(&mut server_in).await.unwrap().unwrap();
+            let response = json!({"jsonrpc": "2.0", "id": request["id"], "result": "fresh"});
+            write_lsp_message(&mut server_out, &response).await.unwrap();
+        };
+        let (result, ()) = tokio::join!(instance.send_request("textDocument/hover", Value::Null), server);
+        assert_eq!(result.unwrap(), "fresh");
+        assert_eq!(instance.pending_count().await, 0);
+    }
 }